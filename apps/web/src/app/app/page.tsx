@@ -63,6 +63,7 @@ function convertApiLedger(apiRows: ApiLedgerRow[]): LedgerRow[] {
     decimals: row.decimals,
     direction: row.direction,
     counterparty: row.counterparty ?? undefined,
+    counterpartyEns: row.counterparty_ens ?? undefined,
     category: row.category,
     confidence: row.confidence,
     userOverride: row.user_override,
@@ -70,11 +71,12 @@ function convertApiLedger(apiRows: ApiLedgerRow[]): LedgerRow[] {
 }
 
 function SetupComplete() {
-  const { session, resetSession, setLedger, setUse44ada } = useSession();
+  const { session, resetSession, setLedger, setPrices, setUse44ada } = useSession();
   const [showResetConfirm, setShowResetConfirm] = useState(false);
   const [syncing, setSyncing] = useState(false);
   const [syncError, setSyncError] = useState<string | null>(null);
   const [lastSyncCount, setLastSyncCount] = useState<number | null>(null);
+  const [failedWallets, setFailedWallets] = useState<string[]>([]);
 
   const userTypeLabels = {
     individual: "Individual",
@@ -82,17 +84,38 @@ function SetupComplete() {
     corporate: "Corporate",
   };
 
-  const handleSync = async () => {
+  // Retrying only fetches the failed wallets and keeps the rest of the ledger
+  const handleSync = async (retry = false) => {
     setSyncing(true);
     setSyncError(null);
     setLastSyncCount(null);
 
     try {
-      const walletAddresses = session.wallets.map((w) => w.address);
-      const response = await fetchTransfers(walletAddresses);
+      const walletAddresses = retry ? failedWallets : session.wallets.map((w) => w.address);
+      const response = await fetchTransfers(walletAddresses, retry);
       const ledgerRows = convertApiLedger(response.ledger);
       setLedger(ledgerRows);
+      // Daily prices fetched for the ledger and saved overrides replace earlier ones for
+      // the same asset and day
+      const dailyPrices = (response.prices ?? [])
+        .filter((p) => p.date || p.user_override)
+        .map((p) => ({
+          asset: p.asset,
+          usdPrice: p.usd_price,
+          date: p.date ?? undefined,
+          userOverride: p.user_override,
+        }));
+      const kept = session.prices.filter(
+        (p) => !dailyPrices.some((d) => d.asset === p.asset && d.date === p.date)
+      );
+      setPrices([...kept, ...dailyPrices]);
       setLastSyncCount(ledgerRows.length);
+      setFailedWallets(response.errors.map((e) => e.wallet));
+      if (response.errors.length > 0) {
+        setSyncError(
+          `Failed to sync ${response.errors.map((e) => `${e.wallet.slice(0, 10)}… (${e.error})`).join(", ")}`
+        );
+      }
     } catch (error) {
       setSyncError(error instanceof Error ? error.message : "Failed to sync");
     } finally {
@@ -166,7 +189,7 @@ function SetupComplete() {
           )}
 
           <button
-            onClick={handleSync}
+            onClick={() => handleSync()}
             disabled={syncing}
             className="px-4 py-2 rounded-lg font-medium bg-blue-600 hover:bg-blue-500 disabled:bg-blue-600/50 text-white transition-colors flex items-center gap-2"
           >
@@ -189,6 +212,14 @@ function SetupComplete() {
           <div className="mt-4 p-3 rounded-lg bg-red-950/50 border border-red-800/50 flex items-center gap-2 text-red-400 text-sm">
             <IconAlertCircle className="w-4 h-4 flex-shrink-0" />
             {syncError}
+            {failedWallets.length > 0 && !syncing && (
+              <button
+                onClick={() => handleSync(true)}
+                className="ml-auto px-2 py-1 text-xs font-medium text-red-300 hover:text-white border border-red-800 rounded transition-colors"
+              >
+                Retry failed
+              </button>
+            )}
           </div>
         )}
         {lastSyncCount !== null && !syncError && (
@@ -43,18 +43,36 @@ const categoryColors: Record<string, string> = {
   income: "text-green-400 bg-green-950/50 border-green-800/50",
   gains: "text-emerald-400 bg-emerald-950/50 border-emerald-800/50",
   losses: "text-red-400 bg-red-950/50 border-red-800/50",
+  capital_gains: "text-teal-400 bg-teal-950/50 border-teal-800/50",
+  gift: "text-pink-400 bg-pink-950/50 border-pink-800/50",
+  airdrop: "text-purple-400 bg-purple-950/50 border-purple-800/50",
+  staking_reward: "text-lime-400 bg-lime-950/50 border-lime-800/50",
+  nft_purchase: "text-fuchsia-400 bg-fuchsia-950/50 border-fuchsia-800/50",
+  nft_sale: "text-pink-400 bg-pink-950/50 border-pink-800/50",
   fees: "text-orange-400 bg-orange-950/50 border-orange-800/50",
   internal: "text-blue-400 bg-blue-950/50 border-blue-800/50",
+  exchange_transfer: "text-sky-400 bg-sky-950/50 border-sky-800/50",
+  non_taxable: "text-neutral-500 bg-neutral-900/50 border-neutral-800/50",
   unknown: "text-neutral-400 bg-neutral-800/50 border-neutral-700/50",
+  spam: "text-neutral-500 bg-neutral-900/50 border-neutral-800/50 line-through",
 };
 
 const categoryLabels: Record<Category, string> = {
   income: "Income",
   gains: "Gains",
   losses: "Losses",
+  capital_gains: "Capital Gains",
+  gift: "Gift",
+  airdrop: "Airdrop",
+  staking_reward: "Staking Reward",
+  nft_purchase: "NFT Purchase",
+  nft_sale: "NFT Sale",
   fees: "Fees",
   internal: "Internal",
+  exchange_transfer: "Exchange Transfer",
+  non_taxable: "Non-taxable",
   unknown: "Unknown",
+  spam: "Spam",
 };
 
 type TabFilter = "all" | "review" | Category;
@@ -111,7 +129,23 @@ function CategorySelect({ row, effectiveCategory, hasOverride, onOverride, onCle
   const [isOpen, setIsOpen] = useState(false);
   const [menuPos, setMenuPos] = useState({ top: 0, left: 0 });
   const buttonRef = useRef<HTMLButtonElement>(null);
-  const categories: Category[] = ["income", "gains", "losses", "fees", "internal", "unknown"];
+  const categories: Category[] = [
+    "income",
+    "gains",
+    "losses",
+    "capital_gains",
+    "gift",
+    "airdrop",
+    "staking_reward",
+    "nft_purchase",
+    "nft_sale",
+    "fees",
+    "internal",
+    "exchange_transfer",
+    "non_taxable",
+    "unknown",
+    "spam",
+  ];
 
   const handleOpen = () => {
     if (buttonRef.current) {
@@ -450,8 +484,12 @@ export function LedgerTable() {
                         onClearOverride={() => removeCategoryOverride(row.id)}
                       />
                     </td>
-                    <td className="p-3 font-mono text-xs text-neutral-400">
-                      {row.counterparty ? shortenAddress(row.counterparty) : "—"}
+                    <td
+                      className="p-3 font-mono text-xs text-neutral-400"
+                      title={row.counterparty}
+                    >
+                      {row.counterpartyEns ??
+                        (row.counterparty ? shortenAddress(row.counterparty) : "—")}
                     </td>
                     <td className="p-3">
                       <a
@@ -22,9 +22,10 @@ export function PricingPanel() {
     () => [...new Set(session.ledger.map((row) => row.asset))],
     [session.ledger]
   );
+  // Fetched daily prices are kept as they are; the panel edits the undated ones
   const priceMap = useMemo(() => {
     const map: Record<string, string> = {};
-    for (const entry of session.prices) {
+    for (const entry of session.prices.filter((p) => !p.date)) {
       map[entry.asset] = entry.usdPrice;
     }
     for (const asset of uniqueAssets) {
@@ -43,7 +44,7 @@ export function PricingPanel() {
         asset: a,
         usdPrice,
       }));
-      setPrices(priceEntries);
+      setPrices([...priceEntries, ...session.prices.filter((p) => p.date)]);
     }
   };
 
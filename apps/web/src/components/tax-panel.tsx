@@ -123,6 +123,7 @@ export function TaxPanel() {
       const apiPrices: PriceEntry[] = session.prices.map((p) => ({
         asset: p.asset,
         usd_price: p.usdPrice,
+        date: p.date,
       }));
 
       const response = await calculateTax({
@@ -165,6 +166,7 @@ export function TaxPanel() {
     const apiPrices: PriceEntry[] = session.prices.map((p) => ({
       asset: p.asset,
       usd_price: p.usdPrice,
+      date: p.date,
     }));
 
     return {
@@ -18,10 +18,19 @@ interface WalletsStepProps {
   onBack: () => void;
 }
 
-function isValidAddress(address: string): boolean {
+function isEvmAddress(address: string): boolean {
   return /^0x[a-fA-F0-9]{40}$/.test(address);
 }
 
+// Solana public keys and Bitcoin addresses; the API tells legacy Bitcoin and Solana apart
+function isValidAddress(address: string): boolean {
+  return (
+    isEvmAddress(address) ||
+    /^(bc1|tb1)[a-zA-HJ-NP-Z0-9]{11,71}$/i.test(address) ||
+    /^[1-9A-HJ-NP-Za-km-z]{26,44}$/.test(address)
+  );
+}
+
 function shortenAddress(address: string): string {
   return `${address.slice(0, 6)}...${address.slice(-4)}`;
 }
@@ -52,10 +61,12 @@ export function WalletsStep({ onNext, onBack }: WalletsStepProps) {
       return;
     }
 
-    const address = newAddress.trim().toLowerCase();
+    // Base58 addresses are case-sensitive
+    const trimmed = newAddress.trim();
+    const address = isEvmAddress(trimmed) ? trimmed.toLowerCase() : trimmed;
 
     if (!isValidAddress(address)) {
-      setError("Invalid Ethereum address format");
+      setError("Invalid Ethereum, Solana or Bitcoin address format");
       return;
     }
 
@@ -202,7 +213,7 @@ export function WalletsStep({ onNext, onBack }: WalletsStepProps) {
               value={newAddress}
               onChange={(e) => setNewAddress(e.target.value)}
               onKeyDown={handleKeyPress}
-              placeholder="0x..."
+              placeholder="0x..., Solana or Bitcoin address"
               className="flex-1 px-4 py-2.5 bg-neutral-800/50 border border-neutral-700 rounded-lg text-white placeholder-neutral-500 focus:outline-none focus:border-neutral-600 font-mono text-sm"
             />
             <input
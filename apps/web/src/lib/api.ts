@@ -2,6 +2,87 @@
 
 const API_BASE = process.env.NEXT_PUBLIC_API_URL || "http://localhost:3001";
 
+// Session token from signIn, sent with every request; without one the API turns requests away,
+// unless it runs with REQUIRE_AUTH=false and uses its anonymous account
+let sessionToken: string | null = null;
+// Integrator's API key, which the API uses in place of a session
+let apiKey: string | null = null;
+
+export function setSessionToken(token: string | null) {
+  sessionToken = token;
+}
+
+export function setApiKey(key: string | null) {
+  apiKey = key;
+}
+
+function apiFetch(path: string, init: RequestInit = {}): Promise<Response> {
+  const headers = new Headers(init.headers);
+  if (apiKey) {
+    headers.set("X-API-Key", apiKey);
+  } else if (sessionToken) {
+    headers.set("Authorization", `Bearer ${sessionToken}`);
+  }
+  return fetch(`${API_BASE}${path}`, { ...init, headers });
+}
+
+export interface SignInMessage {
+  nonce: string;
+  /** EIP-4361 message to sign with personal_sign */
+  message: string;
+}
+
+export interface SignInSession {
+  token: string;
+  /** Lowercase address the account is named after */
+  account: string;
+  /** Seconds until the session expires */
+  expires_in: number;
+}
+
+// Sign-in message for an EVM wallet to sign
+export async function getSignInMessage(address: string, chainId?: number): Promise<SignInMessage> {
+  const response = await apiFetch("/auth/nonce", {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ address, chain_id: chainId }),
+  });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to get sign-in message");
+  }
+
+  return response.json();
+}
+
+// Exchange the signed message for a session; later requests are made as its account
+export async function signIn(nonce: string, signature: string): Promise<SignInSession> {
+  const response = await apiFetch("/auth/verify", {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ nonce, signature }),
+  });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to sign in");
+  }
+
+  const session: SignInSession = await response.json();
+  setSessionToken(session.token);
+  return session;
+}
+
+export async function signOut(): Promise<void> {
+  await apiFetch("/auth/logout", { method: "POST" });
+  setSessionToken(null);
+}
+
 export interface ApiLedgerRow {
   chain_id: number;
   owner_wallet: string;
@@ -12,9 +93,36 @@ export interface ApiLedgerRow {
   decimals: number;
   direction: "in" | "out";
   counterparty: string | null;
-  category: "income" | "gains" | "losses" | "fees" | "internal" | "unknown";
+  category:
+    | "income"
+    | "gains"
+    | "losses"
+    | "capital_gains"
+    | "gift"
+    | "airdrop"
+    | "fees"
+    | "internal"
+    | "unknown"
+    | "spam"
+    | "staking_reward"
+    | "nft_purchase"
+    | "nft_sale"
+    | "exchange_transfer"
+    | "non_taxable";
   confidence: number;
   user_override: boolean;
+  /** NFT token ID (decimal), for NFT transfers */
+  token_id?: string | null;
+  /** Number of items of the token ID moved, for NFT transfers */
+  quantity?: string | null;
+  /** NFT collection contract, for NFT transfers */
+  contract_address?: string | null;
+  /** Primary ENS name of the counterparty, if it has one */
+  counterparty_ens?: string | null;
+  /** Confidence of the ML model that categorized the row, if the rules couldn't */
+  model_confidence?: number | null;
+  /** INR value in paisa, set by the API when the row is valued for a tax calculation or proof */
+  inr_value?: number | null;
 }
 
 export interface WalletCount {
@@ -22,35 +130,189 @@ export interface WalletCount {
   count: number;
 }
 
+export interface WalletError {
+  wallet: string;
+  error: string;
+}
+
 export interface TransfersResponse {
   ledger: ApiLedgerRow[];
   wallet_counts: WalletCount[];
+  /** Wallets that couldn't be fetched; retry them with `merge` */
+  errors: WalletError[];
+  /** Prices the ledger was categorized with, including daily prices fetched for it */
+  prices?: PriceEntry[];
 }
 
+/** A problem with one field of a /tax or /proofs request, e.g. `ledger[3].amount` */
+export interface ValidationIssue {
+  field: string;
+  code:
+    | "LEDGER_TOO_LARGE"
+    | "INVALID_AMOUNT"
+    | "INVALID_RATE"
+    | "INVALID_PRICE"
+    | "INVALID_ADDRESS"
+    | "INVALID_DATE"
+    | "MISSING_ASSET"
+    | "PRICE_MISSING";
+  message: string;
+}
+
+/** What went wrong; branch on this rather than the message */
+export type ErrorCode =
+  | "INVALID_REQUEST"
+  | "VALIDATION_FAILED"
+  | "PRICE_MISSING"
+  | "UNAUTHORIZED"
+  | "FORBIDDEN"
+  | "RATE_LIMITED"
+  | "NOT_FOUND"
+  | "NOT_CONFIGURED"
+  | "CONFLICT"
+  | "TOO_MANY_JOBS"
+  | "PROOF_PENDING"
+  | "PROOF_FAILED"
+  | "PROOF_INVALID"
+  | "ALCHEMY_RATE_LIMITED"
+  | "UPSTREAM_FAILED"
+  | "INTERNAL";
+
 export interface ApiError {
-  error: string;
+  code: ErrorCode;
+  message: string;
+  details?: {
+    /** Each problem with the request (VALIDATION_FAILED, PRICE_MISSING) */
+    issues?: ValidationIssue[];
+    /** Seconds until an API key can be used again (RATE_LIMITED) */
+    retry_after_secs?: number;
+    /** Why the job failed (PROOF_FAILED) */
+    error?: string;
+  };
+  /** Whether the same request may succeed later */
+  retryable: boolean;
 }
 
-export async function fetchTransfers(wallets: string[]): Promise<TransfersResponse> {
-  const response = await fetch(`${API_BASE}/transfers`, {
+/** A request the API turned away, with its error code */
+export class ApiRequestError extends Error {
+  readonly code: ErrorCode;
+  readonly retryable: boolean;
+  readonly details: ApiError["details"];
+
+  constructor(error: ApiError, fallback: string) {
+    const issues = error.details?.issues ?? [];
+    const message = [error.message || fallback, ...issues.map((issue) => `${issue.field}: ${issue.message}`)];
+    super(message.join("\n"));
+    this.code = error.code;
+    this.retryable = error.retryable;
+    this.details = error.details;
+  }
+}
+
+export async function fetchTransfers(
+  wallets: string[],
+  merge = false,
+  historicalPrices = true
+): Promise<TransfersResponse> {
+  const response = await apiFetch(`/transfers`, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
     },
-    body: JSON.stringify({ wallets }),
+    body: JSON.stringify({ wallets, merge, historical_prices: historicalPrices }),
   });
 
   if (!response.ok) {
     const error: ApiError = await response.json();
-    throw new Error(error.error || "Failed to fetch transfers");
+    throw new ApiRequestError(error, "Failed to fetch transfers");
   }
 
   return response.json();
 }
 
+export type WalletSource = "manual" | "ens_text_record" | "ens_subdomain";
+
+/** A stored wallet of the user */
+export interface Wallet {
+  id: string;
+  address: string;
+  label: string | null;
+  group_id: string | null;
+  source: WalletSource;
+  /** Signature of the wallet's ownership message, so proofs commit it as owned */
+  ownership_signature?: string;
+}
+
+export interface WalletGroup {
+  id: string;
+  name: string;
+  description: string | null;
+}
+
+async function walletRequest<T>(path: string, method: string, body: unknown, failure: string): Promise<T> {
+  const response = await apiFetch(path, {
+    method,
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: body === undefined ? undefined : JSON.stringify(body),
+  });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, failure);
+  }
+
+  return response.status === 204 ? (undefined as T) : response.json();
+}
+
+// The user's stored wallets (those fetched, added or saved from ENS)
+export async function listWallets(): Promise<Wallet[]> {
+  return walletRequest("/wallets", "GET", undefined, "Failed to list wallets");
+}
+
+// Add a wallet, or replace the stored wallet with the same address
+export async function addWallet(
+  wallet: Pick<Wallet, "address"> & Partial<Omit<Wallet, "id" | "address">>
+): Promise<Wallet> {
+  return walletRequest("/wallets", "POST", wallet, "Failed to add wallet");
+}
+
+// Change a wallet's label, group or ownership signature (null clears it); `wallet` is its ID or address
+export async function updateWallet(
+  wallet: string,
+  changes: Partial<Pick<Wallet, "label" | "group_id" | "ownership_signature">>
+): Promise<Wallet> {
+  return walletRequest(`/wallets/${encodeURIComponent(wallet)}`, "PATCH", changes, "Failed to update wallet");
+}
+
+export async function deleteWallet(wallet: string): Promise<void> {
+  return walletRequest(`/wallets/${encodeURIComponent(wallet)}`, "DELETE", undefined, "Failed to delete wallet");
+}
+
+export async function listWalletGroups(): Promise<WalletGroup[]> {
+  return walletRequest("/wallet-groups", "GET", undefined, "Failed to list wallet groups");
+}
+
+export async function addWalletGroup(name: string, description?: string): Promise<WalletGroup> {
+  return walletRequest("/wallet-groups", "POST", { name, description }, "Failed to add wallet group");
+}
+
+export async function updateWalletGroup(
+  groupId: string,
+  changes: Partial<Pick<WalletGroup, "name" | "description">>
+): Promise<WalletGroup> {
+  return walletRequest(`/wallet-groups/${groupId}`, "PATCH", changes, "Failed to update wallet group");
+}
+
+// Delete a wallet group; its wallets are left in no group
+export async function deleteWalletGroup(groupId: string): Promise<void> {
+  return walletRequest(`/wallet-groups/${groupId}`, "DELETE", undefined, "Failed to delete wallet group");
+}
+
 export async function checkHealth(): Promise<boolean> {
   try {
-    const response = await fetch(`${API_BASE}/health`);
+    const response = await apiFetch(`/health`);
     return response.ok;
   } catch {
     return false;
@@ -61,9 +323,32 @@ export async function checkHealth(): Promise<boolean> {
 export interface PriceEntry {
   asset: string;
   usd_price: string;
+  /** Day the price applies to ("YYYY-MM-DD", UTC); undated prices apply to any day */
+  date?: string | null;
+  /** Set by the user through `PUT /prices` rather than fetched */
+  user_override?: boolean;
+}
+
+// Set or correct USD prices per (asset, date); returns all overrides
+export async function setPriceOverrides(prices: PriceEntry[]): Promise<PriceEntry[]> {
+  const response = await apiFetch(`/prices`, {
+    method: "PUT",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ prices }),
+  });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to save prices");
+  }
+
+  return response.json();
 }
 
 export interface TaxBreakdown {
+  total_income_inr: string;
   professional_income_inr: string;
   taxable_professional_income_inr: string;
   vda_gains_inr: string;
@@ -73,10 +358,37 @@ export interface TaxBreakdown {
   vda_tax_inr: string;
   cess_inr: string;
   total_tax_inr: string;
+  effective_tax_rate_bps: number;
+  marginal_tax_rate_bps: number;
+  tax_by_category: CategoryTax[];
+  monthly_income: MonthlyIncome[];
+}
+
+export interface CategoryTax {
+  category:
+    | "professional_income"
+    | "other_income"
+    | "short_term_capital_gains"
+    | "long_term_capital_gains"
+    | "vda";
+  income_inr: string;
+  tax_inr: string;
+}
+
+export interface MonthlyIncome {
+  month: string;
+  professional_income_inr: string;
+  vda_gains_inr: string;
+  gifts_received_inr: string;
+  staking_rewards_inr: string;
 }
 
 export interface TaxResponse {
   breakdown: TaxBreakdown;
+  /** Hex commitment to the ledger that a proof of this calculation will carry */
+  ledger_commitment: string;
+  /** Hex SHA256 of the USD/INR rates it converts at, as the proof's rate table commitment */
+  rate_table_commitment: string;
 }
 
 export interface TaxRequest {
@@ -88,7 +400,7 @@ export interface TaxRequest {
 }
 
 export async function calculateTax(request: TaxRequest): Promise<TaxResponse> {
-  const response = await fetch(`${API_BASE}/tax`, {
+  const response = await apiFetch(`/tax`, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
@@ -98,50 +410,247 @@ export async function calculateTax(request: TaxRequest): Promise<TaxResponse> {
 
   if (!response.ok) {
     const error: ApiError = await response.json();
-    throw new Error(error.error || "Failed to calculate tax");
+    throw new ApiRequestError(error, "Failed to calculate tax");
   }
 
   return response.json();
 }
 
+// Saved tax sessions
+export interface CategoryChange {
+  /** Index of the row in this version's ledger */
+  row_id: number;
+  tx_hash: string;
+  from: ApiLedgerRow["category"];
+  to: ApiLedgerRow["category"];
+  confidence: number;
+}
+
+export interface ReportProof extends ProofResult {
+  job_id: string;
+}
+
+export interface ReportVersion {
+  version: number;
+  created_at: number;
+  /** Wallets, valued ledger, prices and settings the breakdown was calculated from */
+  input: TaxRequest & { wallets: Wallet[] };
+  breakdown: TaxBreakdown;
+  ledger_commitment: string;
+  /** Rows recategorized since the previous version */
+  category_changes?: CategoryChange[];
+  proof?: ReportProof;
+}
+
+export interface VersionSummary {
+  version: number;
+  created_at: number;
+  total_tax_paisa: number;
+  category_changes: number;
+  proved: boolean;
+}
+
+export interface ReportSummary {
+  id: string;
+  name: string;
+  created_at: number;
+  updated_at: number;
+  version: number;
+  assessment_year: number;
+  total_tax_paisa: number;
+  ledger_commitment: string;
+  proved: boolean;
+}
+
+export interface TaxReport {
+  id: string;
+  name: string;
+  created_at: number;
+  versions: VersionSummary[];
+  /** The version asked for, in full */
+  version: ReportVersion;
+}
+
+export interface SaveReportRequest extends TaxRequest {
+  /** Report to add a version to; a new report when omitted */
+  report_id?: string;
+  name?: string;
+  /** Finished proof of this session to attach */
+  job_id?: string;
+}
+
+// Save a tax session as a new report, or as a new version of `report_id`
+export async function saveReport(request: SaveReportRequest): Promise<TaxReport> {
+  return walletRequest("/reports", "POST", request, "Failed to save report");
+}
+
+export async function listReports(): Promise<ReportSummary[]> {
+  return walletRequest("/reports", "GET", undefined, "Failed to list reports");
+}
+
+// A report with one of its versions in full (the latest by default)
+export async function getReport(reportId: string, version?: number): Promise<TaxReport> {
+  const query = version === undefined ? "" : `?version=${version}`;
+  return walletRequest(`/reports/${encodeURIComponent(reportId)}${query}`, "GET", undefined, "Failed to get report");
+}
+
+// Attach a finished proof to a version of a report (the latest by default)
+export async function attachReportProof(reportId: string, jobId: string, version?: number): Promise<TaxReport> {
+  const path = `/reports/${encodeURIComponent(reportId)}/proof`;
+  return walletRequest(path, "POST", { job_id: jobId, version }, "Failed to attach proof to report");
+}
+
 // Proof generation types
+/** "core" and "compressed" are quicker but only verifiable off-chain */
+export type ProofMode = "core" | "compressed" | "groth16" | "plonk";
+
+/** Where a proof is generated; network proofs fall back to local ones if the network fails */
+export type ProvingBackend = "local" | "network";
+
+/** A statement a proof commits in place of the exact figures; it must hold to be proved */
+export type Disclosure =
+  | { kind: "tax_at_least"; paisa: number }
+  /** Ascending bounds; the band total income falls in is disclosed */
+  | { kind: "income_band"; bounds_paisa: number[] };
+
+/** What a selective-disclosure proof states: the figure lies in [lower, upper) */
+export interface DisclosedRange {
+  figure: "total_tax" | "total_income";
+  lower_bound_paisa: number;
+  /** null when unbounded */
+  upper_bound_paisa: number | null;
+}
+
 export interface ProofRequest {
   user_type: string;
+  /**
+   * The user's wallets with their groups (for proofs split by wallet group) and their
+   * signatures of `getOwnershipMessage`
+   */
+  wallets?: { address: string; group_id?: string | null; ownership_signature?: string }[];
   ledger: ApiLedgerRow[];
   prices: PriceEntry[];
   usd_inr_rate: string;
   use_44ada: boolean;
+  /** Defaults to "groth16" */
+  mode?: ProofMode;
+  /** Defaults to the prover network when the API has it configured */
+  backend?: ProvingBackend;
+  /** Queued jobs run highest priority first; defaults to "normal" */
+  priority?: "low" | "normal" | "high";
+  /** Prove only this statement instead of the exact tax and income */
+  disclosure?: Disclosure;
+  /** 32 bytes (hex) the proof commits, so it can only be filed once; random by default */
+  nonce?: string;
+  /** URL the job's status is POSTed to, signed with the API's callback secret, once it's done or has failed */
+  callback_url?: string;
 }
 
 export interface ProofResult {
   ledger_commitment: string;
+  /** Hash of the USD/INR reference rates the ledger was converted at */
+  rate_table_commitment: string;
+  /** Hash of the canonical USD price table the ledger was valued at */
+  prices_commitment: string;
+  mode: ProofMode;
+  backend: ProvingBackend;
   total_tax_paisa: number;
+  professional_income_paisa: number;
+  vda_gains_paisa: number;
+  vda_tax_paisa: number;
+  cess_paisa: number;
   user_type_code: number;
   used_44ada: boolean;
   proof: string;
+  /** For groth16/plonk, the whole SP1 proof (base64), to verify off-chain with `verifyProof` */
+  sp1_proof?: string;
   public_values: string;
   vk_hash: string;
+  /** Public values of each part an aggregate proof verified (base64); absent otherwise */
+  parts?: string[];
+  /** What a selective-disclosure proof states; its figures are then all zero */
+  disclosed?: DisclosedRange;
+  /** The public values, decoded; absent for proofs from before they were kept */
+  decoded?: DecodedPublicValues;
+  /** Hash of the wallets whose ownership signatures the proof verified; empty for aggregates */
+  owned_wallets_commitment?: string;
+  /** Whether those wallets cover every wallet the ledger has rows for */
+  all_wallets_owned?: boolean;
+  /** Nonce the proof commits; empty for aggregates */
+  nonce?: string;
+  /** Names the version of the program the proof came from; empty for aggregates */
+  domain_separator?: string;
+}
+
+/** Proves each wallet group (or financial year) separately, then one proof of them all */
+export interface AggregateProofRequest extends ProofRequest {
+  split: "group" | "year";
+  /** Deductions, manual income and filing date for each assessment year, when split by year */
+  years?: Record<string, unknown>[];
 }
 
 export interface ProofSubmitResponse {
   job_id: string;
+  /** The job is an earlier one proving the same (done or still pending), not a new one */
+  reused: boolean;
+}
+
+/** "wrapping" is estimated: it starts once proving has taken its estimated time */
+export type ProofStage = "queued" | "executing" | "proving" | "wrapping";
+
+export interface ProofProgress {
+  stage: ProofStage;
+  /** Cycles the program runs for, once executed */
+  cycles?: number;
+  /** Rough time from leaving the queue to the proof, once executed */
+  estimated_seconds?: number;
+  /** Time since the job left the queue */
+  elapsed_seconds: number;
 }
 
 export type ProofJobStatus =
-  | { status: "pending" }
+  | ({ status: "pending" } & ProofProgress)
   | { status: "done"; result: ProofResult }
-  | { status: "error"; error: string };
+  | { status: "error"; error: string }
+  | { status: "cancelled" };
 
 export interface ProofStatusResponse {
   job_id: string;
-  status: "pending" | "done" | "error";
+  status: "pending" | "done" | "error" | "cancelled";
   result?: ProofResult;
   error?: string;
+  /** Place in the queue (1 = next) while the job waits for a prover */
+  queue_position?: number;
+  /** Progress while pending */
+  stage?: ProofStage;
+  cycles?: number;
+  estimated_seconds?: number;
+  elapsed_seconds?: number;
 }
 
 // Submit a proof job (returns immediately with job_id)
 export async function submitProofJob(request: ProofRequest): Promise<string> {
-  const response = await fetch(`${API_BASE}/proofs`, {
+  const response = await apiFetch(`/proofs`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify(request),
+  });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to submit proof job");
+  }
+
+  const data: ProofSubmitResponse = await response.json();
+  return data.job_id;
+}
+
+// Submit an aggregate proof job (returns immediately with job_id); its ledger_commitment
+// is the digest of the parts' public values
+export async function submitAggregateProofJob(request: AggregateProofRequest): Promise<string> {
+  const response = await apiFetch(`/proofs/aggregate`, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
@@ -151,20 +660,461 @@ export async function submitProofJob(request: ProofRequest): Promise<string> {
 
   if (!response.ok) {
     const error: ApiError = await response.json();
-    throw new Error(error.error || "Failed to submit proof job");
+    throw new ApiRequestError(error, "Failed to submit aggregate proof job");
   }
 
   const data: ProofSubmitResponse = await response.json();
   return data.job_id;
 }
 
+// Message a wallet signs (personal_sign) to show it's the user's, for its `ownership_signature`
+export async function getOwnershipMessage(wallet: string): Promise<string> {
+  const response = await apiFetch(`/proofs/ownership-message?wallet=${encodeURIComponent(wallet)}`);
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to get ownership message");
+  }
+
+  const data: { wallet: string; message: string } = await response.json();
+  return data.message;
+}
+
+/** What the program computes for a proof request, without proving it */
+export interface DryRunResponse {
+  ledger_commitment: string;
+  rate_table_commitment: string;
+  prices_commitment: string;
+  total_tax_paisa: number;
+  professional_income_paisa: number;
+  vda_gains_paisa: number;
+  vda_tax_paisa: number;
+  cess_paisa: number;
+  user_type_code: number;
+  used_44ada: boolean;
+  assessment_year: number;
+  fy_start: number;
+  fy_end: number;
+  owned_wallets_commitment: string;
+  /** Whether the wallets' ownership signatures cover every wallet with rows */
+  all_wallets_owned: boolean;
+  nonce: string;
+  /** Names the version of the program the proof would come from */
+  domain_separator: string;
+  /** ABI-encoded public values, hex */
+  public_values: string;
+  /** Instructions executed; proving time grows with it */
+  cycles: number;
+  /** Total tax of the `/tax` preview for the same input, in paisa */
+  preview_total_tax_paisa: number;
+  /** Whether a proof would carry the previewed total */
+  matches_preview: boolean;
+}
+
+// Execute the program without proving, to check what a proof would commit
+export async function dryRunProof(request: ProofRequest): Promise<DryRunResponse> {
+  const response = await apiFetch(`/proofs/dry-run`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify(request),
+  });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to execute proof dry run");
+  }
+
+  return response.json();
+}
+
 // Check proof job status
 export async function getProofStatus(jobId: string): Promise<ProofStatusResponse> {
-  const response = await fetch(`${API_BASE}/proofs/${jobId}`);
+  const response = await apiFetch(`/proofs/${jobId}`);
 
   if (!response.ok) {
     const error: ApiError = await response.json();
-    throw new Error(error.error || "Failed to get proof status");
+    throw new ApiRequestError(error, "Failed to get proof status");
+  }
+
+  return response.json();
+}
+
+// Follow a job's status as the API streams it (server-sent events), until it finishes;
+// resolves with the final status. Read through fetch, as EventSource can't send the session.
+export async function watchProofStatus(
+  jobId: string,
+  onStatus: (status: ProofStatusResponse) => void,
+  signal?: AbortSignal
+): Promise<ProofStatusResponse> {
+  const response = await apiFetch(`/proofs/${jobId}/events`, { signal });
+
+  if (!response.ok || !response.body) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to follow proof status");
+  }
+
+  const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+  let buffered = "";
+  let last: ProofStatusResponse | undefined;
+  while (true) {
+    const { done, value } = await reader.read();
+    if (done) break;
+    buffered += value;
+    const events = buffered.split("\n\n");
+    buffered = events.pop() ?? "";
+    for (const event of events) {
+      const data = event
+        .split("\n")
+        .filter((line) => line.startsWith("data:"))
+        .map((line) => line.slice(5).trimStart())
+        .join("\n");
+      if (data) {
+        last = JSON.parse(data);
+        onStatus(last!);
+      }
+    }
+  }
+
+  if (!last) {
+    throw new Error("Proof status stream ended without a status");
+  }
+  return last;
+}
+
+export interface ProofJobSummary {
+  job_id: string;
+  status: "pending" | "done" | "error" | "cancelled";
+  mode: ProofMode;
+  backend: ProvingBackend;
+  assessment_year: number;
+  /** Unix time the job was submitted */
+  submitted_at: number;
+  /** Set once done */
+  ledger_commitment?: string;
+  total_tax_paisa?: number;
+  /** Set if it failed */
+  error?: string;
+}
+
+export interface ProofJobFilter {
+  status?: "pending" | "done" | "error" | "cancelled";
+  /** One of the wallets the job's ledger belongs to */
+  wallet?: string;
+  assessment_year?: number;
+  /** Batch the job was submitted in */
+  batch?: string;
+  /** Page to list, from 1, of `per_page` jobs (50 by default, 200 at most) */
+  page?: number;
+  per_page?: number;
+}
+
+export interface ProofJobPage {
+  jobs: ProofJobSummary[];
+  page: number;
+  per_page: number;
+  /** Jobs matching the filter across every page */
+  total: number;
+}
+
+// List proof jobs, newest first, a page at a time
+export async function listProofJobs(filter: ProofJobFilter = {}): Promise<ProofJobPage> {
+  const params = new URLSearchParams();
+  for (const [key, value] of Object.entries(filter)) {
+    if (value !== undefined) {
+      params.set(key, String(value));
+    }
+  }
+  const response = await apiFetch(`/proofs?${params}`);
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to list proof jobs");
+  }
+
+  return response.json();
+}
+
+// Cancel a proof job; a running proof is discarded when it finishes
+export async function cancelProofJob(jobId: string): Promise<ProofStatusResponse> {
+  const response = await apiFetch(`/proofs/${jobId}`, { method: "DELETE" });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to cancel proof job");
+  }
+
+  return response.json();
+}
+
+// Delete a finished proof job and its proof now, rather than when it expires
+export async function deleteProofJob(jobId: string): Promise<void> {
+  const response = await apiFetch(`/proofs/${jobId}`, { method: "DELETE" });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to delete proof job");
+  }
+}
+
+export interface BatchProofItem extends ProofRequest {
+  /** What the proof is called in the batch's status and bundle, e.g. the client's name */
+  label?: string;
+}
+
+export interface BatchSubmitResponse {
+  batch_id: string;
+  /** A job per proof, in the order they were submitted */
+  job_ids: string[];
+}
+
+export interface BatchStatus {
+  batch_id: string;
+  /** "pending" while any job is, then "done" (though some may have failed or been cancelled) */
+  status: "pending" | "done";
+  total: number;
+  pending: number;
+  done: number;
+  error: number;
+  cancelled: number;
+  jobs: (ProofJobSummary & { label?: string })[];
+}
+
+export interface BatchBundle {
+  batch_id: string;
+  proofs: ({ job_id: string; label?: string } & ProofJobStatus)[];
+}
+
+// Submit a batch of proofs (e.g. every client of an accountant); all are queued or none
+export async function submitProofBatch(proofs: BatchProofItem[]): Promise<BatchSubmitResponse> {
+  const response = await apiFetch(`/proofs/batch`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ proofs }),
+  });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to submit proof batch");
+  }
+
+  return response.json();
+}
+
+// Status of every job in a batch
+export async function getProofBatch(batchId: string): Promise<BatchStatus> {
+  const response = await apiFetch(`/proofs/batch/${batchId}`);
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to get proof batch");
+  }
+
+  return response.json();
+}
+
+// Every proof of a finished batch in one bundle; fails while any job is pending
+export async function getProofBatchBundle(batchId: string): Promise<BatchBundle> {
+  const response = await apiFetch(`/proofs/batch/${batchId}/bundle`);
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to get proof batch bundle");
+  }
+
+  return response.json();
+}
+
+/** An on-chain proof as the verifier contracts take it, hex encoded */
+export interface OnchainCalldata {
+  /** `programVKey` of ISP1Verifier.verifyProof */
+  vk_hash: `0x${string}`;
+  public_values: `0x${string}`;
+  proof_bytes: `0x${string}`;
+  /** Calldata of ISP1Verifier.verifyProof(programVKey, publicValues, proofBytes) */
+  verify_proof_calldata: `0x${string}`;
+  /** Calldata of TaxVerifier.verifyTaxProof(proofBytes, publicValues) */
+  verify_tax_proof_calldata: `0x${string}`;
+}
+
+// Get a finished Groth16/PLONK proof in the form the verifier contracts take
+export async function getProofCalldata(jobId: string): Promise<OnchainCalldata> {
+  const response = await apiFetch(`/proofs/${jobId}/calldata`);
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to get proof calldata");
+  }
+
+  return response.json();
+}
+
+/** Public values as the kind of proof they're from commits them */
+export type DecodedPublicValues =
+  | {
+      kind: "tax";
+      ledger_commitment: string;
+      user_type: "individual" | "huf" | "corporate" | null;
+      assessment_year: number;
+      fy_start: number;
+      fy_end: number;
+      total_tax_paisa: number;
+      professional_income_paisa: number;
+      vda_gains_paisa: number;
+      vda_tax_paisa: number;
+      cess_paisa: number;
+      used_44ada: boolean;
+      rate_table_commitment: string;
+      prices_commitment: string;
+      owned_wallets_commitment: string;
+      all_wallets_owned: boolean;
+      nonce: string;
+      domain_separator: string;
+    }
+  | {
+      kind: "disclosure";
+      ledger_commitment: string;
+      user_type: "individual" | "huf" | "corporate" | null;
+      assessment_year: number;
+      fy_start: number;
+      fy_end: number;
+      rate_table_commitment: string;
+      prices_commitment: string;
+      disclosed: DisclosedRange;
+      owned_wallets_commitment: string;
+      all_wallets_owned: boolean;
+      nonce: string;
+      domain_separator: string;
+    }
+  | {
+      kind: "aggregate";
+      tax_program_vkey: string;
+      parts_digest: string;
+      part_count: number;
+      total_tax_paisa: number;
+      professional_income_paisa: number;
+      vda_gains_paisa: number;
+      vda_tax_paisa: number;
+      cess_paisa: number;
+      all_wallets_owned: boolean;
+    };
+
+export interface VerifyResponse {
+  vk_hash: string;
+  /** Version of the tax program the proof is from; absent for one the API doesn't know */
+  program_version?: number;
+  claim: DecodedPublicValues;
+  /** Whether the given ledger is the one the proof commits; absent without one */
+  ledger_matches?: boolean;
+}
+
+// Verify proof artifacts received from anyone, optionally against the ledger (rows valued
+// as proved) they should commit; throws if the proof doesn't verify
+export async function verifyProof(artifacts: ProofResult, ledger?: ApiLedgerRow[]): Promise<VerifyResponse> {
+  const response = await apiFetch(`/verify`, {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ artifacts, ledger }),
+  });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to verify proof");
+  }
+
+  return response.json();
+}
+
+export interface ProverConfig {
+  cuda: boolean;
+  shard_size: number | null;
+  shard_batch_size: number | null;
+  threads: number | null;
+  memory_limit_gb: number | null;
+}
+
+export interface ProverInfo {
+  /** "cpu", "cuda", "mock" or "network" */
+  sp1_prover: string;
+  default_backend: ProvingBackend;
+  /** Whether the prover network is configured */
+  network: boolean;
+  /** Version of the tax program loaded */
+  program_version: number;
+  vk_hash: string;
+  vk_digest: string;
+  config: ProverConfig;
+  /** Shard sizes in use; null where the SDK picks for the machine */
+  shard_size: number | null;
+  shard_batch_size: number | null;
+}
+
+// Get the prover's backends, keys and resources
+export async function getProverInfo(): Promise<ProverInfo> {
+  const response = await apiFetch(`/prover/info`);
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to get prover info");
+  }
+
+  return response.json();
+}
+
+/** A released version of the tax program */
+export interface ProgramVersion {
+  version: number;
+  vk_hash: string;
+  /** As aggregates commit it (`taxProgramVkey`) */
+  vk_digest: string;
+  elf_digest: string;
+  /** `domainSeparator` its proofs commit */
+  domain_separator: string;
+  /** Whether its proofs are still accepted */
+  accepted: boolean;
+}
+
+export interface ProgramVersions {
+  /** Version proofs are generated with */
+  current: number;
+  /** Oldest first */
+  versions: ProgramVersion[];
+}
+
+// Get every version of the tax program, to tell which one a proof is from
+export async function getProgramVersions(): Promise<ProgramVersions> {
+  const response = await apiFetch(`/prover/versions`);
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to get program versions");
+  }
+
+  return response.json();
+}
+
+export interface OnchainSubmission {
+  tx_hash: string;
+  /** "pending" if it wasn't mined while the API waited */
+  status: "pending" | "confirmed" | "reverted";
+  block_number?: number;
+  chain_id: number;
+  /** TaxVerifier the proof was sent to */
+  contract: string;
+}
+
+// Have the API's relayer account submit a finished Groth16/PLONK proof to TaxVerifier
+export async function submitProofOnchain(jobId: string): Promise<OnchainSubmission> {
+  const response = await apiFetch(`/proofs/${jobId}/submit-onchain`, { method: "POST" });
+
+  if (!response.ok) {
+    const error: ApiError = await response.json();
+    throw new ApiRequestError(error, "Failed to submit proof on-chain");
   }
 
   return response.json();
@@ -173,7 +1123,11 @@ export async function getProofStatus(jobId: string): Promise<ProofStatusResponse
 // Poll for proof completion (with callback for status updates)
 export async function generateProofWithPolling(
   request: ProofRequest,
-  onStatusUpdate?: (status: "pending" | "done" | "error", elapsedSeconds: number) => void,
+  onStatusUpdate?: (
+    status: "pending" | "done" | "error",
+    elapsedSeconds: number,
+    progress?: ProofStatusResponse
+  ) => void,
   pollIntervalMs: number = 5000
 ): Promise<ProofResult> {
   // Submit the job
@@ -196,8 +1150,13 @@ export async function generateProofWithPolling(
       throw new Error(statusResponse.error || "Proof generation failed");
     }
 
+    if (statusResponse.status === "cancelled") {
+      onStatusUpdate?.("error", elapsedSeconds);
+      throw new Error("Proof job was cancelled");
+    }
+
     // Still pending
-    onStatusUpdate?.("pending", elapsedSeconds);
+    onStatusUpdate?.("pending", elapsedSeconds, statusResponse);
 
     // Wait before polling again
     await new Promise((resolve) => setTimeout(resolve, pollIntervalMs));
@@ -215,18 +1174,19 @@ export interface EnsResolveResponse {
   subdomains: EnsSubdomain[];
 }
 
-export async function resolveEnsSubdomains(rootName: string): Promise<EnsResolveResponse> {
-  const response = await fetch(`${API_BASE}/ens/resolve`, {
+// With `save`, the resolved wallets are stored, labeled with their subdomains
+export async function resolveEnsSubdomains(rootName: string, save = false): Promise<EnsResolveResponse> {
+  const response = await apiFetch(`/ens/resolve`, {
     method: "POST",
     headers: {
       "Content-Type": "application/json",
     },
-    body: JSON.stringify({ root_name: rootName }),
+    body: JSON.stringify({ root_name: rootName, save }),
   });
 
   if (!response.ok) {
     const error: ApiError = await response.json();
-    throw new Error(error.error || "Failed to resolve ENS subdomains");
+    throw new ApiRequestError(error, "Failed to resolve ENS subdomains");
   }
 
   return response.json();
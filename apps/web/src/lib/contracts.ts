@@ -157,6 +157,47 @@ export const taxVerifierAbi = [
     outputs: [],
     stateMutability: "nonpayable",
   },
+  {
+    type: "function",
+    name: "verifyAggregateTaxProof",
+    inputs: [
+      { name: "proofBytes", type: "bytes" },
+      { name: "publicValues", type: "bytes" },
+    ],
+    outputs: [],
+    stateMutability: "nonpayable",
+  },
+  {
+    type: "function",
+    name: "verifyDisclosureProof",
+    inputs: [
+      { name: "proofBytes", type: "bytes" },
+      { name: "publicValues", type: "bytes" },
+    ],
+    outputs: [
+      {
+        name: "",
+        type: "tuple",
+        components: [
+          { name: "ledgerCommitment", type: "bytes32" },
+          { name: "userType", type: "uint8" },
+          { name: "assessmentYear", type: "uint16" },
+          { name: "fyStart", type: "uint64" },
+          { name: "fyEnd", type: "uint64" },
+          { name: "rateTableCommitment", type: "bytes32" },
+          { name: "pricesCommitment", type: "bytes32" },
+          { name: "figure", type: "uint8" },
+          { name: "lowerBoundPaisa", type: "uint256" },
+          { name: "upperBoundPaisa", type: "uint256" },
+          { name: "ownedWalletsCommitment", type: "bytes32" },
+          { name: "allWalletsOwned", type: "bool" },
+          { name: "nonce", type: "bytes32" },
+          { name: "domainSeparator", type: "bytes32" },
+        ],
+      },
+    ],
+    stateMutability: "view",
+  },
   {
     type: "function",
     name: "isVerified",
@@ -176,6 +217,41 @@ export const taxVerifierAbi = [
           { name: "totalTaxPaisa", type: "uint256" },
           { name: "userType", type: "uint8" },
           { name: "used44ada", type: "bool" },
+          { name: "assessmentYear", type: "uint16" },
+          { name: "fyStart", type: "uint64" },
+          { name: "fyEnd", type: "uint64" },
+          { name: "rateTableCommitment", type: "bytes32" },
+          { name: "pricesCommitment", type: "bytes32" },
+          { name: "professionalIncomePaisa", type: "uint256" },
+          { name: "vdaGainsPaisa", type: "uint256" },
+          { name: "vdaTaxPaisa", type: "uint256" },
+          { name: "cessPaisa", type: "uint256" },
+          { name: "ownedWalletsCommitment", type: "bytes32" },
+          { name: "allWalletsOwned", type: "bool" },
+          { name: "nonce", type: "bytes32" },
+          { name: "verifiedAt", type: "uint256" },
+          { name: "verifiedBy", type: "address" },
+        ],
+      },
+    ],
+    stateMutability: "view",
+  },
+  {
+    type: "function",
+    name: "getAggregateRecord",
+    inputs: [{ name: "partsDigest", type: "bytes32" }],
+    outputs: [
+      {
+        name: "",
+        type: "tuple",
+        components: [
+          { name: "partCount", type: "uint32" },
+          { name: "totalTaxPaisa", type: "uint256" },
+          { name: "professionalIncomePaisa", type: "uint256" },
+          { name: "vdaGainsPaisa", type: "uint256" },
+          { name: "vdaTaxPaisa", type: "uint256" },
+          { name: "cessPaisa", type: "uint256" },
+          { name: "allWalletsOwned", type: "bool" },
           { name: "verifiedAt", type: "uint256" },
           { name: "verifiedBy", type: "address" },
         ],
@@ -190,14 +266,73 @@ export const taxVerifierAbi = [
     outputs: [{ name: "", type: "bytes32" }],
     stateMutability: "view",
   },
+  {
+    type: "function",
+    name: "DOMAIN_SEPARATOR",
+    inputs: [],
+    outputs: [{ name: "", type: "bytes32" }],
+    stateMutability: "view",
+  },
+  {
+    type: "function",
+    name: "usedProofs",
+    inputs: [{ name: "", type: "bytes32" }],
+    outputs: [{ name: "", type: "bool" }],
+    stateMutability: "view",
+  },
   {
     type: "event",
     name: "TaxProofVerified",
     inputs: [
       { name: "ledgerCommitment", type: "bytes32", indexed: true },
-      { name: "totalTaxPaisa", type: "uint256", indexed: false },
-      { name: "userType", type: "uint8", indexed: false },
-      { name: "used44ada", type: "bool", indexed: false },
+      {
+        name: "record",
+        type: "tuple",
+        indexed: false,
+        components: [
+          { name: "totalTaxPaisa", type: "uint256" },
+          { name: "userType", type: "uint8" },
+          { name: "used44ada", type: "bool" },
+          { name: "assessmentYear", type: "uint16" },
+          { name: "fyStart", type: "uint64" },
+          { name: "fyEnd", type: "uint64" },
+          { name: "rateTableCommitment", type: "bytes32" },
+          { name: "pricesCommitment", type: "bytes32" },
+          { name: "professionalIncomePaisa", type: "uint256" },
+          { name: "vdaGainsPaisa", type: "uint256" },
+          { name: "vdaTaxPaisa", type: "uint256" },
+          { name: "cessPaisa", type: "uint256" },
+          { name: "ownedWalletsCommitment", type: "bytes32" },
+          { name: "allWalletsOwned", type: "bool" },
+          { name: "nonce", type: "bytes32" },
+          { name: "verifiedAt", type: "uint256" },
+          { name: "verifiedBy", type: "address" },
+        ],
+      },
+      { name: "verifiedBy", type: "address", indexed: true },
+    ],
+  },
+  {
+    type: "event",
+    name: "AggregateTaxProofVerified",
+    inputs: [
+      { name: "partsDigest", type: "bytes32", indexed: true },
+      {
+        name: "record",
+        type: "tuple",
+        indexed: false,
+        components: [
+          { name: "partCount", type: "uint32" },
+          { name: "totalTaxPaisa", type: "uint256" },
+          { name: "professionalIncomePaisa", type: "uint256" },
+          { name: "vdaGainsPaisa", type: "uint256" },
+          { name: "vdaTaxPaisa", type: "uint256" },
+          { name: "cessPaisa", type: "uint256" },
+          { name: "allWalletsOwned", type: "bool" },
+          { name: "verifiedAt", type: "uint256" },
+          { name: "verifiedBy", type: "address" },
+        ],
+      },
       { name: "verifiedBy", type: "address", indexed: true },
     ],
   },
@@ -19,9 +19,18 @@ export type Category =
   | "income"
   | "gains"
   | "losses"
+  | "capital_gains"
+  | "gift"
+  | "airdrop"
   | "fees"
   | "internal"
-  | "unknown";
+  | "unknown"
+  | "spam"
+  | "staking_reward"
+  | "nft_purchase"
+  | "nft_sale"
+  | "exchange_transfer"
+  | "non_taxable";
 
 export type Direction = "in" | "out";
 
@@ -36,6 +45,8 @@ export interface LedgerRow {
   decimals: number;
   direction: Direction;
   counterparty?: string;
+  /** Primary ENS name of the counterparty */
+  counterpartyEns?: string;
   category: Category;
   confidence: number;
   userOverride: boolean;
@@ -49,6 +60,10 @@ export interface CategoryOverride {
 export interface PriceEntry {
   asset: string;
   usdPrice: string;
+  /** Day the price applies to ("YYYY-MM-DD", UTC); undated prices apply to any day */
+  date?: string;
+  /** Saved on the server as a price override */
+  userOverride?: boolean;
 }
 
 export interface TaxSummary {
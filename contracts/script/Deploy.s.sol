@@ -17,6 +17,11 @@ contract DeployScript is Script {
     // Updated after Section 87A rebate fix
     bytes32 constant TAX_ZK_VKEY = 0x00e443478c063561810f469214d4e6d80639bd6da21eab50470b7ca5a52c726c;
 
+    // Aggregation: the tax-aggregate program's VK hash, and the tax-zk VK digest it commits
+    // for its parts (`TaxProver::get_vk_digest`); set after building tax-aggregate
+    bytes32 constant TAX_AGGREGATE_VKEY = bytes32(0);
+    bytes32 constant TAX_ZK_VKEY_DIGEST = bytes32(0);
+
     function run() external {
         uint256 deployerPrivateKey = vm.envUint("PRIVATE_KEY");
         vm.startBroadcast(deployerPrivateKey);
@@ -38,7 +43,9 @@ contract DeployScript is Script {
         console.log("YieldFarm deployed at:", address(farm));
 
         // Deploy TaxVerifier with real SP1 verifier and real VK
-        TaxVerifier taxVerifier = new TaxVerifier(SP1_VERIFIER_SEPOLIA, TAX_ZK_VKEY);
+        TaxVerifier taxVerifier = new TaxVerifier(
+            SP1_VERIFIER_SEPOLIA, TAX_ZK_VKEY, TAX_AGGREGATE_VKEY, TAX_ZK_VKEY_DIGEST
+        );
         console.log("TaxVerifier deployed at:", address(taxVerifier));
 
         vm.stopBroadcast();
@@ -11,12 +11,19 @@ contract DeployTaxVerifierScript is Script {
     // Real VK hash for tax-zk program (SP1 4.2 - matches VM build)
     bytes32 constant TAX_ZK_VKEY = 0x00b4a3eaf10debf992cf924c5dc4eac96d8190e60ef534fc91610cca8576b43e;
 
+    // Aggregation: the tax-aggregate program's VK hash, and the tax-zk VK digest it commits
+    // for its parts (`TaxProver::get_vk_digest`); set after building tax-aggregate
+    bytes32 constant TAX_AGGREGATE_VKEY = bytes32(0);
+    bytes32 constant TAX_ZK_VKEY_DIGEST = bytes32(0);
+
     function run() external {
         uint256 deployerPrivateKey = vm.envUint("PRIVATE_KEY");
         vm.startBroadcast(deployerPrivateKey);
 
         // Deploy TaxVerifier with real SP1 verifier and updated VK
-        TaxVerifier taxVerifier = new TaxVerifier(SP1_VERIFIER_SEPOLIA, TAX_ZK_VKEY);
+        TaxVerifier taxVerifier = new TaxVerifier(
+            SP1_VERIFIER_SEPOLIA, TAX_ZK_VKEY, TAX_AGGREGATE_VKEY, TAX_ZK_VKEY_DIGEST
+        );
         console.log("TaxVerifier deployed at:", address(taxVerifier));
         console.log("VK hash:");
         console.logBytes32(TAX_ZK_VKEY);
@@ -12,30 +12,126 @@ contract TaxVerifier {
     /// @notice The verification key for the tax-zk program
     bytes32 public immutable taxZkVkey;
 
-    /// @notice Emitted when a tax proof is verified
-    event TaxProofVerified(
-        bytes32 indexed ledgerCommitment,
-        uint256 totalTaxPaisa,
-        uint8 userType,
-        bool used44ada,
-        address indexed verifiedBy
-    );
+    /// @notice The verification key for the tax-aggregate program
+    bytes32 public immutable taxAggregateVkey;
+
+    /// @notice The tax-zk verification key digest the aggregate program commits for its parts
+    bytes32 public immutable taxZkVkeyDigest;
+
+    /// @notice Domain separator the tax-zk program version this contract accepts commits
+    /// (`domain_separator_bytes`); proofs from other versions are rejected
+    bytes32 public constant DOMAIN_SEPARATOR = keccak256("financoor.tax_zk.v1");
+
+    /// @notice Public values committed by the tax-zk program (`TaxProofPublicValues`)
+    struct PublicValues {
+        bytes32 ledgerCommitment;
+        uint256 totalTaxPaisa;
+        uint8 userType;
+        bool used44ada;
+        uint16 assessmentYear;
+        uint64 fyStart;
+        uint64 fyEnd;
+        bytes32 rateTableCommitment;
+        bytes32 pricesCommitment;
+        uint256 professionalIncomePaisa;
+        uint256 vdaGainsPaisa;
+        uint256 vdaTaxPaisa;
+        uint256 cessPaisa;
+        bytes32 ownedWalletsCommitment;
+        bool allWalletsOwned;
+        bytes32 nonce;
+        bytes32 domainSeparator;
+    }
+
+    /// @notice Public values committed by the tax-aggregate program (`AggregatedTaxPublicValues`)
+    struct AggregatePublicValues {
+        bytes32 taxProgramVkey;
+        bytes32 partsDigest;
+        uint32 partCount;
+        uint256 totalTaxPaisa;
+        uint256 professionalIncomePaisa;
+        uint256 vdaGainsPaisa;
+        uint256 vdaTaxPaisa;
+        uint256 cessPaisa;
+        bool allWalletsOwned;
+    }
+
+    /// @notice Public values of a selective-disclosure proof (`DisclosurePublicValues`): a figure
+    /// (0 = total tax, 1 = total income) lies in [lowerBoundPaisa, upperBoundPaisa)
+    struct DisclosurePublicValues {
+        bytes32 ledgerCommitment;
+        uint8 userType;
+        uint16 assessmentYear;
+        uint64 fyStart;
+        uint64 fyEnd;
+        bytes32 rateTableCommitment;
+        bytes32 pricesCommitment;
+        uint8 figure;
+        uint256 lowerBoundPaisa;
+        uint256 upperBoundPaisa;
+        bytes32 ownedWalletsCommitment;
+        bool allWalletsOwned;
+        bytes32 nonce;
+        bytes32 domainSeparator;
+    }
+
+    /// @notice Struct to store verified aggregate records
+    struct AggregateRecord {
+        uint32 partCount;
+        uint256 totalTaxPaisa;
+        uint256 professionalIncomePaisa;
+        uint256 vdaGainsPaisa;
+        uint256 vdaTaxPaisa;
+        uint256 cessPaisa;
+        bool allWalletsOwned;
+        uint256 verifiedAt;
+        address verifiedBy;
+    }
 
     /// @notice Struct to store verified tax records
     struct TaxRecord {
         uint256 totalTaxPaisa;
         uint8 userType;
         bool used44ada;
+        uint16 assessmentYear;
+        uint64 fyStart;
+        uint64 fyEnd;
+        bytes32 rateTableCommitment;
+        bytes32 pricesCommitment;
+        uint256 professionalIncomePaisa;
+        uint256 vdaGainsPaisa;
+        uint256 vdaTaxPaisa;
+        uint256 cessPaisa;
+        /// Keccak256 hash of the wallets the prover signed for, and whether they cover the ledger
+        bytes32 ownedWalletsCommitment;
+        bool allWalletsOwned;
+        bytes32 nonce;
         uint256 verifiedAt;
         address verifiedBy;
     }
 
-    /// @notice Mapping from ledger commitment to tax record
-    mapping(bytes32 => TaxRecord) public taxRecords;
+    /// @notice Emitted when a tax proof is verified
+    event TaxProofVerified(bytes32 indexed ledgerCommitment, TaxRecord record, address indexed verifiedBy);
+
+    /// @notice Emitted when an aggregate tax proof is verified
+    event AggregateTaxProofVerified(bytes32 indexed partsDigest, AggregateRecord record, address indexed verifiedBy);
+
+    /// @notice Mapping from ledger commitment to tax record (read through `getTaxRecord`; a
+    /// public getter returning every field separately would overflow the stack)
+    mapping(bytes32 => TaxRecord) internal taxRecords;
+
+    /// @notice Mapping from parts digest to aggregate record
+    mapping(bytes32 => AggregateRecord) internal aggregateRecords;
 
-    constructor(address _verifier, bytes32 _taxZkVkey) {
+    /// @notice Whether a proof of a ledger commitment with a nonce has been verified, keyed by
+    /// `keccak256(abi.encode(ledgerCommitment, nonce))`, so the same proof can't be filed twice
+    mapping(bytes32 => bool) public usedProofs;
+
+    constructor(address _verifier, bytes32 _taxZkVkey, bytes32 _taxAggregateVkey, bytes32 _taxZkVkeyDigest) {
         verifier = ISP1Verifier(_verifier);
         taxZkVkey = _taxZkVkey;
+        taxAggregateVkey = _taxAggregateVkey;
+        taxZkVkeyDigest = _taxZkVkeyDigest;
     }
 
     /// @notice Verify a tax proof and store the result
@@ -48,30 +144,81 @@ contract TaxVerifier {
         // Verify the proof with SP1 verifier
         verifier.verifyProof(taxZkVkey, publicValues, proofBytes);
 
-        // Decode public values
-        (
-            bytes32 ledgerCommitment,
-            uint256 totalTaxPaisa,
-            uint8 userType,
-            bool used44ada
-        ) = abi.decode(publicValues, (bytes32, uint256, uint8, bool));
+        // Decode public values (as one struct; as separate values they'd overflow the stack)
+        PublicValues memory values = abi.decode(publicValues, (PublicValues));
+        require(values.domainSeparator == DOMAIN_SEPARATOR, "Proof from another program version");
+        bytes32 proofKey = keccak256(abi.encode(values.ledgerCommitment, values.nonce));
+        require(!usedProofs[proofKey], "Proof already verified");
+        usedProofs[proofKey] = true;
 
         // Store the verified record
-        taxRecords[ledgerCommitment] = TaxRecord({
-            totalTaxPaisa: totalTaxPaisa,
-            userType: userType,
-            used44ada: used44ada,
+        TaxRecord memory record = TaxRecord({
+            totalTaxPaisa: values.totalTaxPaisa,
+            userType: values.userType,
+            used44ada: values.used44ada,
+            assessmentYear: values.assessmentYear,
+            fyStart: values.fyStart,
+            fyEnd: values.fyEnd,
+            rateTableCommitment: values.rateTableCommitment,
+            pricesCommitment: values.pricesCommitment,
+            professionalIncomePaisa: values.professionalIncomePaisa,
+            vdaGainsPaisa: values.vdaGainsPaisa,
+            vdaTaxPaisa: values.vdaTaxPaisa,
+            cessPaisa: values.cessPaisa,
+            ownedWalletsCommitment: values.ownedWalletsCommitment,
+            allWalletsOwned: values.allWalletsOwned,
+            nonce: values.nonce,
+            verifiedAt: block.timestamp,
+            verifiedBy: msg.sender
+        });
+        taxRecords[values.ledgerCommitment] = record;
+
+        emit TaxProofVerified(values.ledgerCommitment, record, msg.sender);
+    }
+
+    /// @notice Verify an aggregate tax proof (of a ledger proved in parts) and store the result
+    /// @param proofBytes The SP1 proof bytes
+    /// @param publicValues The ABI-encoded public values from the aggregate proof
+    function verifyAggregateTaxProof(
+        bytes calldata proofBytes,
+        bytes calldata publicValues
+    ) external {
+        verifier.verifyProof(taxAggregateVkey, publicValues, proofBytes);
+
+        AggregatePublicValues memory values = abi.decode(publicValues, (AggregatePublicValues));
+        // The aggregate program verifies its parts against whichever key it's given
+        require(values.taxProgramVkey == taxZkVkeyDigest, "Parts not proved with the tax program");
+        // The parts' nonces are in the digest, so a fresh filing has a fresh digest
+        require(aggregateRecords[values.partsDigest].verifiedAt == 0, "Proof already verified");
+
+        AggregateRecord memory record = AggregateRecord({
+            partCount: values.partCount,
+            totalTaxPaisa: values.totalTaxPaisa,
+            professionalIncomePaisa: values.professionalIncomePaisa,
+            vdaGainsPaisa: values.vdaGainsPaisa,
+            vdaTaxPaisa: values.vdaTaxPaisa,
+            cessPaisa: values.cessPaisa,
+            allWalletsOwned: values.allWalletsOwned,
             verifiedAt: block.timestamp,
             verifiedBy: msg.sender
         });
+        aggregateRecords[values.partsDigest] = record;
 
-        emit TaxProofVerified(
-            ledgerCommitment,
-            totalTaxPaisa,
-            userType,
-            used44ada,
-            msg.sender
-        );
+        emit AggregateTaxProofVerified(values.partsDigest, record, msg.sender);
+    }
+
+    /// @notice Verify a selective-disclosure proof and return what it states, without storing
+    /// anything (e.g. for a lender's contract to check before extending credit)
+    /// @param proofBytes The SP1 proof bytes
+    /// @param publicValues The ABI-encoded public values from the proof
+    function verifyDisclosureProof(
+        bytes calldata proofBytes,
+        bytes calldata publicValues
+    ) external view returns (DisclosurePublicValues memory) {
+        verifier.verifyProof(taxZkVkey, publicValues, proofBytes);
+        DisclosurePublicValues memory values = abi.decode(publicValues, (DisclosurePublicValues));
+        require(values.domainSeparator == DOMAIN_SEPARATOR, "Proof from another program version");
+        return values;
     }
 
     /// @notice Check if a ledger commitment has been verified
@@ -83,4 +230,9 @@ contract TaxVerifier {
     function getTaxRecord(bytes32 ledgerCommitment) external view returns (TaxRecord memory) {
         return taxRecords[ledgerCommitment];
     }
+
+    /// @notice Get the aggregate record for a parts digest
+    function getAggregateRecord(bytes32 partsDigest) external view returns (AggregateRecord memory) {
+        return aggregateRecords[partsDigest];
+    }
 }
@@ -1,10 +1,104 @@
 //! Alchemy Transfers API client for fetching wallet transactions
 
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
 use anyhow::{anyhow, Result};
-use financoor_core::{Category, Direction, LedgerRow};
+use async_trait::async_trait;
+use financoor_core::{Address, Category, Direction, LedgerRow};
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::Instant;
+
+use crate::cache::TransferCache;
+use crate::chains::ChainProvider;
+use crate::metrics::METRICS;
+
+/// An Alchemy network
+#[derive(Debug, Clone, Copy)]
+struct Network {
+    /// Subdomain of the Alchemy API URL
+    slug: &'static str,
+    chain_id: u64,
+    /// Whether Alchemy indexes internal (contract-initiated) ETH transfers here
+    internal_transfers: bool,
+}
+
+/// Networks transfers can be fetched from; Alchemy only traces internal transfers on
+/// Ethereum and Polygon mainnet
+const NETWORKS: [Network; 6] = [
+    Network {
+        slug: "eth-mainnet",
+        chain_id: 1,
+        internal_transfers: true,
+    },
+    Network {
+        slug: "eth-sepolia",
+        chain_id: 11155111,
+        internal_transfers: false,
+    },
+    Network {
+        slug: "polygon-mainnet",
+        chain_id: 137,
+        internal_transfers: true,
+    },
+    Network {
+        slug: "opt-mainnet",
+        chain_id: 10,
+        internal_transfers: false,
+    },
+    Network {
+        slug: "arb-mainnet",
+        chain_id: 42161,
+        internal_transfers: false,
+    },
+    Network {
+        slug: "base-mainnet",
+        chain_id: 8453,
+        internal_transfers: false,
+    },
+];
+
+/// The network used unless the configuration says otherwise
+pub const DEFAULT_NETWORK: &str = "eth-sepolia";
+
+/// Most transfers fetched per wallet and direction by default
+pub const DEFAULT_MAX_TRANSFERS: usize = 10_000;
+
+/// Receipts requested per JSON-RPC batch
+const RECEIPT_BATCH_SIZE: usize = 100;
+
+/// Requests in flight at once by default
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Requests started per second by default; the free tier allows about two
+/// `alchemy_getAssetTransfers` calls a second
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 2.0;
+
+/// Retries of a request that was rate limited or hit a server error
+const MAX_RETRIES: u32 = 5;
+
+/// Wait before the first retry; doubled for each one after it
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest wait between retries
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(16);
+
+/// Alchemy was still rate limiting a request after every retry
+#[derive(Debug)]
+pub struct RateLimited;
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Alchemy API still rate limited after {} retries", MAX_RETRIES)
+    }
+}
 
-const ALCHEMY_SEPOLIA_URL: &str = "https://eth-sepolia.g.alchemy.com/v2";
+impl std::error::Error for RateLimited {}
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,6 +112,9 @@ struct GetAssetTransfersParams {
     category: Vec<String>,
     with_metadata: bool,
     max_count: String,
+    /// Continues from where the previous page ended
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_key: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -39,10 +136,25 @@ struct JsonRpcError {
     message: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct ReceiptResponse {
+    result: Option<TransactionReceipt>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionReceipt {
+    transaction_hash: String,
+    /// "0x1" on success, "0x0" if the transaction reverted
+    status: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct TransfersResult {
     transfers: Vec<AlchemyTransfer>,
+    /// Set when there are more transfers to fetch
+    page_key: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,6 +168,27 @@ struct AlchemyTransfer {
     asset: Option<String>,
     category: String,
     metadata: TransferMetadata,
+    /// Hex token ID, for ERC-721 transfers
+    erc721_token_id: Option<String>,
+    /// Hex token IDs and quantities, for ERC-1155 transfers
+    erc1155_metadata: Option<Vec<Erc1155Token>>,
+    raw_contract: RawContract,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Erc1155Token {
+    token_id: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawContract {
+    address: Option<String>,
+    /// Hex amount in the token's smallest unit
+    value: Option<String>,
+    /// Hex number of decimals of the token
+    decimal: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -64,9 +197,60 @@ struct TransferMetadata {
     block_timestamp: String,
 }
 
+/// Body of an Address Activity webhook notification
+#[derive(Debug, Deserialize)]
+pub struct WebhookNotification {
+    pub event: ActivityEvent,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityEvent {
+    /// "ETH_MAINNET", "ETH_SEPOLIA", ...
+    pub network: String,
+    pub activity: Vec<AddressActivity>,
+}
+
+/// A transfer from or to a watched address
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressActivity {
+    from_address: String,
+    to_address: Option<String>,
+    /// Hex block number
+    block_num: String,
+    hash: String,
+    value: Option<f64>,
+    asset: Option<String>,
+    /// "external", "internal", "token", "erc721" or "erc1155"
+    category: String,
+    erc721_token_id: Option<String>,
+    erc1155_metadata: Option<Vec<Erc1155Token>>,
+    #[serde(default)]
+    raw_contract: ActivityContract,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActivityContract {
+    raw_value: Option<String>,
+    address: Option<String>,
+    decimals: Option<u8>,
+}
+
 pub struct AlchemyClient {
     client: reqwest::Client,
     api_key: String,
+    network: Network,
+    /// Most transfers fetched per wallet and direction; the rest are dropped with a warning
+    max_transfers: usize,
+    /// Limits the requests in flight across all wallets
+    permits: Semaphore,
+    /// Least time between the starts of two requests, to stay within the key's rate limit
+    min_interval: Duration,
+    /// When the next request may start
+    next_request: Mutex<Instant>,
+    /// Transfers fetched before, reused instead of downloading them again
+    cache: Option<TransferCache>,
 }
 
 impl AlchemyClient {
@@ -74,130 +258,520 @@ impl AlchemyClient {
         Self {
             client: reqwest::Client::new(),
             api_key,
+            network: network(DEFAULT_NETWORK).expect("default network is known"),
+            max_transfers: DEFAULT_MAX_TRANSFERS,
+            permits: Semaphore::new(DEFAULT_MAX_CONCURRENT_REQUESTS),
+            min_interval: Duration::from_secs_f64(1.0 / DEFAULT_REQUESTS_PER_SECOND),
+            next_request: Mutex::new(Instant::now()),
+            cache: None,
+        }
+    }
+
+    /// Fetch from the network with this Alchemy slug ("eth-mainnet", "polygon-mainnet", ...)
+    pub fn with_network(mut self, slug: &str) -> Result<Self> {
+        self.network = network(slug).ok_or_else(|| anyhow!("Unsupported Alchemy network: {}", slug))?;
+        Ok(self)
+    }
+
+    fn url(&self) -> String {
+        format!("https://{}.g.alchemy.com/v2/{}", self.network.slug, self.api_key)
+    }
+
+    pub fn with_max_transfers(mut self, max_transfers: usize) -> Self {
+        self.max_transfers = max_transfers;
+        self
+    }
+
+    /// Reuse transfers cached on disk, and cache those fetched
+    pub fn with_cache(mut self, cache: TransferCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Allow up to `max_concurrent` requests in flight and start at most `requests_per_second`
+    pub fn with_rate_limit(mut self, max_concurrent: usize, requests_per_second: f64) -> Result<Self> {
+        if max_concurrent == 0 || requests_per_second.is_nan() || requests_per_second <= 0.0 {
+            return Err(anyhow!("Alchemy rate limit must allow at least one request"));
         }
+        self.permits = Semaphore::new(max_concurrent);
+        self.min_interval = Duration::from_secs_f64(1.0 / requests_per_second);
+        Ok(self)
     }
 
-    /// Fetch all transfers for a wallet address on Sepolia
+    /// POST a JSON-RPC request (or batch) within the rate limit, retrying with exponential
+    /// backoff when rate limited, on server errors and on connection failures
+    async fn rpc<T: DeserializeOwned>(&self, request: &impl Serialize) -> Result<T> {
+        let url = self.url();
+        let mut attempt = 0;
+        loop {
+            let permit = self.permits.acquire().await?;
+            self.wait_for_rate_budget().await;
+            let result = self.client.post(&url).json(request).send().await;
+            drop(permit);
+
+            let rate_limited =
+                matches!(&result, Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS);
+            METRICS.alchemy_request(match &result {
+                Ok(_) if rate_limited => "rate_limited",
+                Ok(response) if response.status().is_success() => "ok",
+                Ok(_) => "http_error",
+                Err(_) => "connection_error",
+            });
+            let retry_after = match result {
+                Ok(response) if retryable(response.status()) => response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .map(Duration::from_secs),
+                Ok(response) => {
+                    let status = response.status();
+                    if !status.is_success() {
+                        return Err(anyhow!("Alchemy API error: HTTP {}", status));
+                    }
+                    return Ok(response.json().await?);
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => {
+                    if attempt >= MAX_RETRIES {
+                        return Err(e.into());
+                    }
+                    None
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if attempt >= MAX_RETRIES && rate_limited {
+                return Err(RateLimited.into());
+            }
+            if attempt >= MAX_RETRIES {
+                return Err(anyhow!("Alchemy API still failing after {} retries", MAX_RETRIES));
+            }
+
+            let delay = retry_after.unwrap_or_else(|| backoff(attempt)).min(RETRY_MAX_DELAY);
+            tracing::debug!("Retrying Alchemy request in {:?} (attempt {})", delay, attempt + 1);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Take the next start slot, waiting for it if requests are coming in too fast
+    async fn wait_for_rate_budget(&self) {
+        let slot = {
+            let mut next_request = self.next_request.lock().await;
+            let slot = (*next_request).max(Instant::now());
+            *next_request = slot + self.min_interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    /// Fetch all transfers for a wallet address
     pub async fn get_transfers(&self, wallet: &str) -> Result<Vec<LedgerRow>> {
-        let url = format!("{}/{}", ALCHEMY_SEPOLIA_URL, self.api_key);
+        self.transfers_in_range(wallet, "0x0".to_string(), "latest".to_string()).await
+    }
+
+    /// Fetch a wallet's transfers in blocks `from_block..=to_block`
+    pub async fn get_transfers_between(&self, wallet: &str, from_block: u64, to_block: u64) -> Result<Vec<LedgerRow>> {
+        if from_block > to_block {
+            return Ok(Vec::new());
+        }
+        self.transfers_in_range(wallet, format!("{:#x}", from_block), format!("{:#x}", to_block))
+            .await
+    }
+
+    /// Number of the latest block
+    pub async fn block_number(&self) -> Result<u64> {
+        let response: serde_json::Value = self.rpc(&json_rpc("eth_blockNumber", serde_json::json!([]))).await?;
+        parse_hex_result(&response)
+    }
+
+    /// Chain ID of the network fetched from
+    pub fn chain_id(&self) -> u64 {
+        self.network.chain_id
+    }
+
+    /// Call a contract at the latest block and return what it returned
+    pub async fn call(&self, to: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let params = serde_json::json!([{ "to": to, "data": format!("0x{}", hex::encode(data)) }, "latest"]);
+        let response: serde_json::Value = self.rpc(&json_rpc("eth_call", params)).await?;
+        let result = response["result"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Alchemy API error: {}", response["error"]["message"]))?;
+        Ok(hex::decode(result.trim_start_matches("0x"))?)
+    }
+
+    /// Timestamp (unix seconds) of a block
+    async fn block_timestamp(&self, block: u64) -> Result<u64> {
+        let params = serde_json::json!([format!("{:#x}", block), false]);
+        let response: serde_json::Value = self.rpc(&json_rpc("eth_getBlockByNumber", params)).await?;
+        let timestamp = response["result"]["timestamp"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Alchemy API error: no block {}", block))?;
+        Ok(u64::from_str_radix(timestamp.trim_start_matches("0x"), 16)?)
+    }
+
+    /// First block at or after `timestamp`; one past the latest block if there is none yet
+    ///
+    /// Searches between genesis and the latest block, guessing from the block times on
+    /// either side so that it takes a handful of lookups rather than one per halving.
+    pub async fn block_at(&self, timestamp: u64) -> Result<u64> {
+        let (mut hi, mut lo) = (self.block_number().await?, 0);
+        let (mut hi_time, mut lo_time) = (self.block_timestamp(hi).await?, self.block_timestamp(lo).await?);
+        if timestamp > hi_time {
+            return Ok(hi + 1);
+        }
+        if timestamp <= lo_time {
+            return Ok(lo);
+        }
+
+        // time(lo) < timestamp <= time(hi)
+        let mut bisect = false;
+        while hi - lo > 1 {
+            let width = hi - lo;
+            let probe = next_probe((lo, lo_time), (hi, hi_time), timestamp, bisect);
+            let probe_time = self.block_timestamp(probe).await?;
+            if probe_time < timestamp {
+                (lo, lo_time) = (probe, probe_time);
+            } else {
+                (hi, hi_time) = (probe, probe_time);
+            }
+            // Fall back to halving when a guess barely narrowed the range
+            bisect = hi - lo > width / 2;
+        }
+        Ok(hi)
+    }
+
+    /// Ledger rows of the watched wallets for activity pushed by a webhook; activity on
+    /// another network is ignored
+    pub async fn activity_rows(&self, event: ActivityEvent, wallets: &[String]) -> Result<Vec<LedgerRow>> {
+        if !event.network.eq_ignore_ascii_case(&self.network.slug.replace('-', "_")) {
+            tracing::debug!("Ignoring webhook activity on {}", event.network);
+            return Ok(Vec::new());
+        }
+        let watched = |address: &str| wallets.iter().find(|wallet| wallet.eq_ignore_ascii_case(address));
+
+        let mut block_times: HashMap<String, u64> = HashMap::new();
+        let mut ledger = Vec::new();
+        for activity in event.activity {
+            let from = watched(&activity.from_address);
+            let to = activity.to_address.as_deref().and_then(watched);
+            if from.is_none() && to.is_none() {
+                continue;
+            }
+
+            let block_time = match block_times.get(&activity.block_num) {
+                Some(&block_time) => block_time,
+                None => {
+                    let block = u64::from_str_radix(activity.block_num.trim_start_matches("0x"), 16)?;
+                    let block_time = self.block_timestamp(block).await?;
+                    block_times.insert(activity.block_num.clone(), block_time);
+                    block_time
+                }
+            };
+            let block_timestamp = chrono::DateTime::from_timestamp(block_time as i64, 0)
+                .map(|time| time.to_rfc3339())
+                .unwrap_or_default();
+
+            let transfer = AlchemyTransfer {
+                block_num: activity.block_num,
+                hash: activity.hash,
+                from: activity.from_address,
+                to: activity.to_address,
+                value: activity.value,
+                asset: activity.asset,
+                category: activity.category,
+                metadata: TransferMetadata { block_timestamp },
+                erc721_token_id: activity.erc721_token_id,
+                erc1155_metadata: activity.erc1155_metadata,
+                raw_contract: RawContract {
+                    address: activity.raw_contract.address,
+                    value: activity.raw_contract.raw_value,
+                    decimal: activity.raw_contract.decimals.map(|decimals| format!("{:#x}", decimals)),
+                },
+            };
+            if let Some(wallet) = to {
+                ledger.extend(self.normalize_transfer(&transfer, wallet, Direction::In));
+            }
+            if let Some(wallet) = from {
+                ledger.extend(self.normalize_transfer(&transfer, wallet, Direction::Out));
+            }
+        }
+
+        ledger.sort_by_key(|row| row.block_time);
+        Ok(ledger)
+    }
+
+    async fn transfers_in_range(&self, wallet: &str, from_block: String, to_block: String) -> Result<Vec<LedgerRow>> {
+        let blocks = (from_block.as_str(), to_block.as_str());
+        let chain_id = self.network.chain_id;
+        if let Some(ledger) = self.cache.as_ref().and_then(|cache| cache.get(chain_id, wallet, blocks.0, blocks.1)) {
+            tracing::debug!("Using cached transfers for {} in blocks {}..{}", wallet, blocks.0, blocks.1);
+            return Ok(ledger);
+        }
 
         // Fetch incoming transfers
-        let incoming = self.fetch_transfers(&url, None, Some(wallet.to_string())).await?;
+        let incoming = self.fetch_transfers(blocks, None, Some(wallet.to_string())).await?;
 
         // Fetch outgoing transfers
-        let outgoing = self.fetch_transfers(&url, Some(wallet.to_string()), None).await?;
+        let outgoing = self.fetch_transfers(blocks, Some(wallet.to_string()), None).await?;
 
         // Combine and normalize
         let mut ledger: Vec<LedgerRow> = Vec::new();
 
         for transfer in incoming {
-            if let Some(row) = self.normalize_transfer(&transfer, wallet, Direction::In) {
-                ledger.push(row);
-            }
+            ledger.extend(self.normalize_transfer(&transfer, wallet, Direction::In));
         }
 
         for transfer in outgoing {
-            if let Some(row) = self.normalize_transfer(&transfer, wallet, Direction::Out) {
-                ledger.push(row);
-            }
+            ledger.extend(self.normalize_transfer(&transfer, wallet, Direction::Out));
         }
 
         // Sort by block time
         ledger.sort_by(|a, b| a.block_time.cmp(&b.block_time));
 
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.put(chain_id, wallet, blocks.0, blocks.1, &ledger) {
+                tracing::warn!("Failed to cache transfers for {}: {}", wallet, e);
+            }
+        }
+
         Ok(ledger)
     }
 
+    /// Fetch transfers page by page (1000 per page) until there are no more or
+    /// `max_transfers` is reached
     async fn fetch_transfers(
         &self,
-        url: &str,
+        (from_block, to_block): (&str, &str),
         from_address: Option<String>,
         to_address: Option<String>,
     ) -> Result<Vec<AlchemyTransfer>> {
-        let params = GetAssetTransfersParams {
-            from_block: "0x0".to_string(),
-            to_block: "latest".to_string(),
-            from_address,
-            to_address,
-            category: vec![
-                "external".to_string(),
-                "erc20".to_string(),
-                "erc721".to_string(),
-                "erc1155".to_string(),
-            ],
-            with_metadata: true,
-            max_count: "0x3e8".to_string(), // 1000
-        };
-
-        let request = JsonRpcRequest {
-            id: 1,
-            jsonrpc: "2.0",
-            method: "alchemy_getAssetTransfers",
-            params: vec![params],
-        };
+        let wallet = from_address.clone().or_else(|| to_address.clone()).unwrap_or_default();
+        let mut transfers: Vec<AlchemyTransfer> = Vec::new();
+        let mut page_key: Option<String> = None;
+        let mut categories = vec!["external", "erc20", "erc721", "erc1155"];
+        if self.network.internal_transfers {
+            categories.push("internal");
+        }
 
-        let response: JsonRpcResponse = self
-            .client
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .json()
-            .await?;
+        loop {
+            let params = GetAssetTransfersParams {
+                from_block: from_block.to_string(),
+                to_block: to_block.to_string(),
+                from_address: from_address.clone(),
+                to_address: to_address.clone(),
+                category: categories.iter().map(|category| category.to_string()).collect(),
+                with_metadata: true,
+                max_count: "0x3e8".to_string(), // 1000
+                page_key: page_key.take(),
+            };
+
+            let request = JsonRpcRequest {
+                id: 1,
+                jsonrpc: "2.0",
+                method: "alchemy_getAssetTransfers",
+                params: vec![params],
+            };
+
+            let response: JsonRpcResponse = self.rpc(&request).await?;
+
+            if let Some(error) = response.error {
+                return Err(anyhow!("Alchemy API error: {}", error.message));
+            }
 
-        if let Some(error) = response.error {
-            return Err(anyhow!("Alchemy API error: {}", error.message));
+            let Some(result) = response.result else {
+                break;
+            };
+            transfers.extend(result.transfers);
+            tracing::debug!("Fetched {} transfers for {}", transfers.len(), wallet);
+
+            match result.page_key {
+                Some(next) if transfers.len() < self.max_transfers => page_key = Some(next),
+                Some(_) => {
+                    tracing::warn!(
+                        "Stopped fetching transfers for {} at the cap of {}; later transfers are missing",
+                        wallet,
+                        self.max_transfers
+                    );
+                    transfers.truncate(self.max_transfers);
+                    break;
+                }
+                None => break,
+            }
         }
 
-        Ok(response
-            .result
-            .map(|r| r.transfers)
-            .unwrap_or_default())
+        Ok(transfers)
     }
 
+    /// Hashes (lowercase) of the given transactions that reverted, from their receipts
+    pub async fn failed_transactions(&self, tx_hashes: &[String]) -> Result<HashSet<String>> {
+        let mut failed = HashSet::new();
+        for batch in tx_hashes.chunks(RECEIPT_BATCH_SIZE) {
+            let requests: Vec<serde_json::Value> = batch
+                .iter()
+                .enumerate()
+                .map(|(id, tx_hash)| {
+                    serde_json::json!({
+                        "id": id,
+                        "jsonrpc": "2.0",
+                        "method": "eth_getTransactionReceipt",
+                        "params": [tx_hash],
+                    })
+                })
+                .collect();
+
+            let responses: Vec<ReceiptResponse> = self.rpc(&requests).await?;
+
+            failed.extend(
+                responses
+                    .into_iter()
+                    .filter_map(|response| response.result)
+                    .filter(|receipt| receipt.status.as_deref() == Some("0x0"))
+                    .map(|receipt| receipt.transaction_hash.to_lowercase()),
+            );
+        }
+        Ok(failed)
+    }
+
+    /// Ledger rows for a transfer: one for fungible transfers, one per token ID for NFTs
     fn normalize_transfer(
         &self,
         transfer: &AlchemyTransfer,
         owner_wallet: &str,
         direction: Direction,
-    ) -> Option<LedgerRow> {
-        let value = transfer.value.unwrap_or(0.0);
-        if value == 0.0 {
-            return None;
-        }
-
+    ) -> Vec<LedgerRow> {
         // Parse block timestamp
         let block_time = parse_timestamp(&transfer.metadata.block_timestamp).unwrap_or(0);
 
-        // Determine asset and decimals
-        let (asset, decimals) = match transfer.category.as_str() {
-            "external" => ("ETH".to_string(), 18u8),
-            _ => (
-                transfer.asset.clone().unwrap_or_else(|| "UNKNOWN".to_string()),
-                18u8, // Default to 18, could be improved with token metadata lookup
-            ),
-        };
-
         // Determine counterparty
         let counterparty = match direction {
-            Direction::In => Some(transfer.from.clone()),
-            Direction::Out => transfer.to.clone(),
+            Direction::In => Some(Address::from(transfer.from.as_str())),
+            Direction::Out => transfer.to.as_deref().map(Address::from),
         };
 
-        Some(LedgerRow {
-            chain_id: 11155111, // Sepolia
-            owner_wallet: owner_wallet.to_lowercase(),
+        let row = |asset: String, amount: String, decimals: u8, nft: Option<(String, String)>| LedgerRow {
+            chain_id: self.network.chain_id,
+            owner_wallet: owner_wallet.into(),
             tx_hash: transfer.hash.clone(),
             block_time,
             asset,
-            amount: value.to_string(),
+            amount,
             decimals,
             direction,
-            counterparty,
+            counterparty: counterparty.clone(),
             category: Category::Unknown, // Will be categorized later
             confidence: 0.0,
             user_override: false,
-        })
+            contract_address: transfer.raw_contract.address.as_ref().map(|address| address.to_lowercase()),
+            token_id: nft.as_ref().map(|(token_id, _)| token_id.clone()),
+            quantity: nft.map(|(_, quantity)| quantity),
+            counterparty_label: None, // Will be labeled later
+            counterparty_ens: None,
+            model_confidence: None,
+            inr_value: None,
+        };
+        let collection = || transfer.asset.clone().unwrap_or_else(|| "NFT".to_string());
+
+        match transfer.category.as_str() {
+            "erc721" => transfer
+                .erc721_token_id
+                .as_deref()
+                .map(|token_id| {
+                    row(collection(), "1".to_string(), 0, Some((hex_to_decimal(token_id), "1".to_string())))
+                })
+                .into_iter()
+                .collect(),
+            "erc1155" => transfer
+                .erc1155_metadata
+                .iter()
+                .flatten()
+                .map(|token| {
+                    let quantity = hex_to_decimal(&token.value);
+                    row(collection(), quantity.clone(), 0, Some((hex_to_decimal(&token.token_id), quantity)))
+                })
+                .collect(),
+            category => {
+                let asset = match category {
+                    // Native transfers, sent by the wallet or by a contract it called
+                    "external" | "internal" => transfer.asset.clone().unwrap_or_else(|| "ETH".to_string()),
+                    _ => transfer.asset.clone().unwrap_or_else(|| "UNKNOWN".to_string()),
+                };
+                let decimals = transfer
+                    .raw_contract
+                    .decimal
+                    .as_deref()
+                    .and_then(|decimal| u8::from_str_radix(decimal.trim_start_matches("0x"), 16).ok())
+                    .unwrap_or(18);
+
+                // The exact raw amount when Alchemy gives one, its rounded `value` otherwise.
+                // Zero-value transfers are kept: approvals and other contract calls are
+                // marked non-taxable, zero-value inflows spam.
+                let amount = transfer
+                    .raw_contract
+                    .value
+                    .as_deref()
+                    .and_then(hex_digits)
+                    .map(|raw| format_units(&raw, decimals))
+                    .unwrap_or_else(|| transfer.value.unwrap_or(0.0).to_string());
+                vec![row(asset, amount, decimals, None)]
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ChainProvider for AlchemyClient {
+    fn name(&self) -> &'static str {
+        "alchemy"
+    }
+
+    async fn get_transfers(&self, wallet: &str) -> Result<Vec<LedgerRow>> {
+        AlchemyClient::get_transfers(self, wallet).await
+    }
+}
+
+fn network(slug: &str) -> Option<Network> {
+    NETWORKS.iter().copied().find(|network| network.slug == slug)
+}
+
+/// Whether transfers can be fetched from the network with this Alchemy slug
+pub fn is_supported_network(slug: &str) -> bool {
+    network(slug).is_some()
+}
+
+/// "0x1f" -> "31"; anything that isn't hex is kept as is
+fn hex_to_decimal(hex: &str) -> String {
+    hex_digits(hex).unwrap_or_else(|| hex.to_lowercase())
+}
+
+/// Decimal digits of a hex number of any width (token amounts and IDs are uint256)
+fn hex_digits(hex: &str) -> Option<String> {
+    // Little-endian decimal digits, multiplied by 16 and added to per hex digit
+    let mut digits: Vec<u32> = vec![0];
+    for c in hex.trim_start_matches("0x").chars() {
+        let mut carry = c.to_digit(16)?;
+        for digit in digits.iter_mut() {
+            let value = *digit * 16 + carry;
+            *digit = value % 10;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            digits.push(carry % 10);
+            carry /= 10;
+        }
+    }
+    Some(digits.iter().rev().map(|&digit| char::from_digit(digit, 10).unwrap_or('0')).collect())
+}
+
+/// Raw integer amount -> decimal string: ("1500000", 6) -> "1.5"
+pub(crate) fn format_units(raw: &str, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    let padded = format!("{:0>width$}", raw, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
     }
 }
 
@@ -209,6 +783,60 @@ fn parse_timestamp(timestamp: &str) -> Option<u64> {
         .map(|dt| dt.timestamp() as u64)
 }
 
+/// Whether `signature` (the `X-Alchemy-Signature` header) is the hex HMAC-SHA256 of the
+/// webhook body under the webhook's signing key
+pub fn verify_webhook_signature(signing_key: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// A JSON-RPC request body
+fn json_rpc(method: &str, params: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "id": 1,
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+    })
+}
+
+/// A JSON-RPC result that is a hex number
+fn parse_hex_result(response: &serde_json::Value) -> Result<u64> {
+    let value = response["result"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Alchemy API error: {}", response["error"]["message"]))?;
+    Ok(u64::from_str_radix(value.trim_start_matches("0x"), 16)?)
+}
+
+/// Next block to look at when searching between `lo` and `hi` (more than one apart) for
+/// the first block at `timestamp`: the midpoint, or where the timestamp falls if blocks
+/// between them came at an even pace
+fn next_probe((lo, lo_time): (u64, u64), (hi, hi_time): (u64, u64), timestamp: u64, bisect: bool) -> u64 {
+    let guess = if bisect || hi_time <= lo_time {
+        lo + (hi - lo) / 2
+    } else {
+        let elapsed = u128::from(timestamp.saturating_sub(lo_time));
+        lo + (elapsed * u128::from(hi - lo) / u128::from(hi_time - lo_time)) as u64
+    };
+    guess.clamp(lo + 1, hi - 1)
+}
+
+/// Whether a response status is worth retrying: rate limited or a server error
+fn retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Wait before retry number `attempt` (from 0): doubling from the base delay, plus up to
+/// a quarter more so that concurrent requests don't retry in lockstep
+fn backoff(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY.saturating_mul(1 << attempt.min(16)).min(RETRY_MAX_DELAY);
+    delay + delay.mul_f64(rand::random::<f64>() / 4.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,4 +846,80 @@ mod tests {
         let ts = parse_timestamp("2024-01-15T10:30:00.000Z");
         assert!(ts.is_some());
     }
+
+    #[test]
+    fn test_internal_transfers_only_on_traced_networks() {
+        let client = AlchemyClient::new("key".to_string());
+        assert!(!client.network.internal_transfers);
+
+        let client = client.with_network("eth-mainnet").unwrap();
+        assert!(client.network.internal_transfers);
+        assert_eq!(client.url(), "https://eth-mainnet.g.alchemy.com/v2/key");
+
+        assert!(AlchemyClient::new("key".to_string()).with_network("doge-mainnet").is_err());
+    }
+
+    #[test]
+    fn test_raw_amount_keeps_full_precision() {
+        // 1.234567890123456789 ETH, which an f64 rounds
+        let raw = hex_digits("0x112210f47de98115").unwrap();
+        assert_eq!(raw, "1234567890123456789");
+        assert_eq!(format_units(&raw, 18), "1.234567890123456789");
+        assert_eq!(format_units("1500000", 6), "1.5");
+        assert_eq!(format_units("5", 18), "0.000000000000000005");
+        assert_eq!(format_units("0", 18), "0");
+
+        // Wider than u128
+        assert_eq!(hex_digits(&format!("0x1{}", "0".repeat(32))).unwrap(), "340282366920938463463374607431768211456");
+    }
+
+    #[test]
+    fn test_block_search_probes() {
+        // Blocks every 12 seconds from t=1000: block 500 is at t=7000
+        assert_eq!(next_probe((0, 1000), (1000, 13_000), 7000, false), 500);
+        assert_eq!(next_probe((0, 1000), (1000, 13_000), 7000, true), 500);
+        assert_eq!(next_probe((0, 1000), (1000, 13_000), 1001, false), 1);
+        // Always strictly inside the range
+        assert_eq!(next_probe((10, 1000), (12, 1024), 1024, false), 11);
+        assert_eq!(next_probe((10, 1000), (20, 1000), 1000, false), 15);
+    }
+
+    #[test]
+    fn test_webhook_signature_and_activity() {
+        let body = br#"{"webhookId":"wh_1","type":"ADDRESS_ACTIVITY","event":{"network":"ETH_SEPOLIA","activity":[
+            {"fromAddress":"0xaaa","toAddress":"0xbbb","blockNum":"0x10","hash":"0x1","value":0.5,"asset":"ETH",
+             "category":"external","rawContract":{"rawValue":"0x6f05b59d3b20000","decimals":18}}]}}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"whsec_test").unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(verify_webhook_signature("whsec_test", body, &signature));
+        assert!(!verify_webhook_signature("whsec_other", body, &signature));
+        assert!(!verify_webhook_signature("whsec_test", body, "not-hex"));
+
+        let notification: WebhookNotification = serde_json::from_slice(body).unwrap();
+        assert_eq!(notification.event.network, "ETH_SEPOLIA");
+        assert_eq!(notification.event.activity[0].raw_contract.decimals, Some(18));
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        assert!(retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(retryable(StatusCode::BAD_GATEWAY));
+        assert!(!retryable(StatusCode::BAD_REQUEST));
+
+        let first = backoff(0);
+        assert!(first >= RETRY_BASE_DELAY && first <= RETRY_BASE_DELAY.mul_f64(1.25));
+        assert!(backoff(2) >= RETRY_BASE_DELAY * 4);
+        assert!(backoff(30) <= RETRY_MAX_DELAY.mul_f64(1.25));
+    }
+
+    #[test]
+    fn test_page_key_parsed() {
+        let result: TransfersResult = serde_json::from_str(r#"{"transfers": [], "pageKey": "abc-123"}"#).unwrap();
+        assert_eq!(result.page_key.as_deref(), Some("abc-123"));
+
+        let last: TransfersResult = serde_json::from_str(r#"{"transfers": []}"#).unwrap();
+        assert!(last.page_key.is_none());
+    }
 }
@@ -0,0 +1,215 @@
+//! API keys for integrators
+//!
+//! Accounting firms and frontends call the API with an `X-API-Key` header rather than
+//! signing in with a wallet. Each key is a tenant of its own: its wallets, ledger and
+//! proof jobs belong to the key's account (`key-<id>`), apart from every other key and
+//! from accounts signed in with Ethereum.
+//!
+//! A key is limited to its scopes and to a number of requests a minute. Keys are issued
+//! and revoked with the operator's `API_KEY_ADMIN_TOKEN`; only a hash of each is stored,
+//! so a key is shown once, when it's issued.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::auth::Account;
+use crate::storage::Repository;
+
+/// Requests a key can make a minute unless it's issued with another limit
+pub const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// What a key may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Read stored data and calculate tax
+    Read,
+    /// Change wallets, the ledger and settings
+    Write,
+    /// Submit, dry-run and cancel proofs
+    Prove,
+}
+
+impl Scope {
+    /// Scope a request needs
+    pub fn of(method: &Method, path: &str) -> Self {
+        const CALCULATIONS: [&str; 6] =
+            ["/tax", "/tax/simulate", "/tax/multi-year", "/verify", "/tds/reconcile", "/schedule-fa"];
+        if method == Method::GET || CALCULATIONS.contains(&path) {
+            Scope::Read
+        } else if path.starts_with("/proofs") {
+            Scope::Prove
+        } else {
+            Scope::Write
+        }
+    }
+}
+
+/// An issued key, without the key itself
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    /// Who the key was issued to, e.g. the accounting firm
+    pub name: String,
+    /// First characters of the key, to tell keys apart
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub rate_limit_per_minute: u32,
+    /// Unix times
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<u64>,
+}
+
+impl ApiKey {
+    /// Account the key's data belongs to
+    pub fn account(&self) -> Account {
+        Account(format!("key-{}", self.id))
+    }
+}
+
+/// Why a request's key was turned away
+#[derive(Debug, PartialEq)]
+pub enum KeyError {
+    Unknown,
+    Revoked,
+    MissingScope(Scope),
+    /// Seconds until the key can be used again
+    RateLimited(u64),
+}
+
+impl std::fmt::Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::Unknown => write!(f, "Unknown API key"),
+            KeyError::Revoked => write!(f, "API key revoked"),
+            KeyError::MissingScope(scope) => write!(f, "API key lacks the {:?} scope", scope),
+            KeyError::RateLimited(retry_after) => {
+                write!(f, "API key rate limit reached, retry in {} seconds", retry_after)
+            }
+        }
+    }
+}
+
+/// A key as stored: its record and SHA-256 hash
+#[derive(Serialize, Deserialize)]
+struct StoredKey {
+    #[serde(flatten)]
+    key: ApiKey,
+    key_hash: String,
+}
+
+pub struct ApiKeys {
+    keys: RwLock<Repository<Vec<StoredKey>>>,
+    /// Start of each key's current window, and its requests in it
+    usage: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl ApiKeys {
+    /// Keys saved in `dir`
+    pub fn load(dir: Option<&Path>) -> Result<Self> {
+        Ok(Self {
+            keys: RwLock::new(Repository::load(dir, "api_keys")?),
+            usage: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Issue a key; returns it with its record, the only time the key itself is seen
+    pub async fn issue(&self, name: String, scopes: Vec<Scope>, rate_limit_per_minute: u32) -> (ApiKey, String) {
+        let secret = format!("fk_{}", hex::encode(rand::random::<[u8; 24]>()));
+        let key = ApiKey {
+            id: format!("{:x}", rand::random::<u64>()),
+            name,
+            prefix: secret[..10].to_string(),
+            scopes,
+            rate_limit_per_minute,
+            created_at: chrono::Utc::now().timestamp().max(0) as u64,
+            revoked_at: None,
+        };
+        let mut keys = self.keys.write().await;
+        keys.push(StoredKey {
+            key: key.clone(),
+            key_hash: hash(&secret),
+        });
+        keys.save();
+        (key, secret)
+    }
+
+    /// Every key issued, revoked ones included
+    pub async fn list(&self) -> Vec<ApiKey> {
+        self.keys.read().await.iter().map(|stored| stored.key.clone()).collect()
+    }
+
+    /// Revoke a key; None if there's no such key
+    pub async fn revoke(&self, id: &str) -> Option<ApiKey> {
+        let mut keys = self.keys.write().await;
+        let key = &mut keys.iter_mut().find(|stored| stored.key.id == id)?.key;
+        key.revoked_at.get_or_insert(chrono::Utc::now().timestamp().max(0) as u64);
+        let key = key.clone();
+        keys.save();
+        Some(key)
+    }
+
+    /// Account of a request made with a key, if the key has the scope and is within its limit
+    pub async fn authorize(&self, secret: &str, scope: Scope) -> Result<Account, KeyError> {
+        let key_hash = hash(secret);
+        let keys = self.keys.read().await;
+        let key = &keys.iter().find(|stored| stored.key_hash == key_hash).ok_or(KeyError::Unknown)?.key;
+        if key.revoked_at.is_some() {
+            return Err(KeyError::Revoked);
+        }
+        if !key.scopes.contains(&scope) {
+            return Err(KeyError::MissingScope(scope));
+        }
+
+        let mut usage = self.usage.lock().await;
+        let (window, count) = usage.entry(key.id.clone()).or_insert((Instant::now(), 0));
+        if window.elapsed() >= RATE_LIMIT_WINDOW {
+            *window = Instant::now();
+            *count = 0;
+        }
+        if *count >= key.rate_limit_per_minute {
+            let retry_after = RATE_LIMIT_WINDOW.saturating_sub(window.elapsed()).as_secs() + 1;
+            return Err(KeyError::RateLimited(retry_after));
+        }
+        *count += 1;
+        Ok(key.account())
+    }
+}
+
+fn hash(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_keys_are_scoped_limited_and_revocable() {
+        let keys = ApiKeys::load(None).unwrap();
+        let (key, secret) = keys.issue("Firm".to_string(), vec![Scope::Read, Scope::Prove], 2).await;
+        assert!(secret.starts_with(&key.prefix));
+
+        assert_eq!(keys.authorize(&secret, Scope::Read).await, Ok(Account(format!("key-{}", key.id))));
+        assert_eq!(keys.authorize(&secret, Scope::Write).await, Err(KeyError::MissingScope(Scope::Write)));
+        assert_eq!(keys.authorize("fk_other", Scope::Read).await, Err(KeyError::Unknown));
+        assert!(keys.authorize(&secret, Scope::Prove).await.is_ok());
+        assert!(matches!(keys.authorize(&secret, Scope::Read).await, Err(KeyError::RateLimited(_))));
+
+        assert!(keys.revoke(&key.id).await.is_some());
+        assert_eq!(keys.authorize(&secret, Scope::Read).await, Err(KeyError::Revoked));
+        assert_eq!(Scope::of(&Method::POST, "/proofs/batch"), Scope::Prove);
+        assert_eq!(Scope::of(&Method::POST, "/tax"), Scope::Read);
+        assert_eq!(Scope::of(&Method::PATCH, "/wallets/0xabc"), Scope::Write);
+    }
+}
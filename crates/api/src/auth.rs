@@ -0,0 +1,195 @@
+//! Sign-In with Ethereum
+//!
+//! A user signs in by signing an EIP-4361 message with one of their wallets: the API
+//! issues the message with a single-use nonce, and exchanges its `personal_sign` signature
+//! for a session token. The signing address names the user's account, which their wallets,
+//! ledger and proof jobs belong to. Requests without a token are turned away, unless
+//! `REQUIRE_AUTH=false` lets them share one anonymous account (for a single-user setup,
+//! since everyone without a token sees the same jobs, wallets and ledger).
+//!
+//! Sessions are kept in memory: a restart signs everyone out.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use alloy_primitives::Signature;
+use financoor_core::{evm_address_bytes, signature_bytes, Address};
+use tokio::sync::Mutex;
+
+/// How long a signed-in session lasts
+pub const SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long an issued message can be signed for
+const NONCE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The account of a request: the address it signed in with, lowercase, or `key-<id>` for
+/// an API key's (see `api_keys`); empty for the anonymous account
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Account(pub String);
+
+impl Account {
+    pub fn is_anonymous(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Why a sign-in failed
+#[derive(Debug, PartialEq)]
+pub enum SignInError {
+    /// No message was issued with the nonce, or it expired or was used
+    UnknownNonce,
+    /// The signature isn't the address's signature of the issued message
+    InvalidSignature(String),
+}
+
+impl std::fmt::Display for SignInError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignInError::UnknownNonce => write!(f, "Unknown or expired nonce: request a new sign-in message"),
+            SignInError::InvalidSignature(reason) => write!(f, "Invalid sign-in signature: {}", reason),
+        }
+    }
+}
+
+struct IssuedMessage {
+    address: Address,
+    message: String,
+    issued: Instant,
+}
+
+struct Session {
+    account: Account,
+    expires: Instant,
+}
+
+/// Domain and URI sign-in messages are issued for
+pub struct SiweConfig {
+    pub domain: String,
+    pub uri: String,
+}
+
+impl SiweConfig {
+    /// From `SIWE_DOMAIN` and `SIWE_URI`, the web app's local address by default
+    pub fn from_env() -> Self {
+        Self {
+            domain: std::env::var("SIWE_DOMAIN").unwrap_or_else(|_| "localhost:3000".to_string()),
+            uri: std::env::var("SIWE_URI").unwrap_or_else(|_| "http://localhost:3000".to_string()),
+        }
+    }
+}
+
+pub struct Sessions {
+    config: SiweConfig,
+    /// Messages waiting to be signed, by nonce
+    issued: Mutex<HashMap<String, IssuedMessage>>,
+    /// Signed-in sessions, by token
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl Sessions {
+    pub fn new(config: SiweConfig) -> Self {
+        Self {
+            config,
+            issued: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// An EIP-4361 message for an EVM address to sign, and its nonce
+    pub async fn issue(&self, address: Address, chain_id: u64) -> (String, String) {
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+        let message = format!(
+            "{} wants you to sign in with your Ethereum account:\n{}\n\n\
+             Sign in to Financoor to keep your wallets, ledger and proofs.\n\n\
+             URI: {}\nVersion: 1\nChain ID: {}\nNonce: {}\nIssued At: {}",
+            self.config.domain,
+            address.as_str(),
+            self.config.uri,
+            chain_id,
+            nonce,
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        );
+        let mut issued = self.issued.lock().await;
+        issued.retain(|_, issued| issued.issued.elapsed() < NONCE_TTL);
+        let entry = IssuedMessage {
+            address,
+            message: message.clone(),
+            issued: Instant::now(),
+        };
+        issued.insert(nonce.clone(), entry);
+        (nonce, message)
+    }
+
+    /// Exchange the signature of an issued message for a session token; the nonce can't be
+    /// used again either way
+    pub async fn sign_in(&self, nonce: &str, signature: &str) -> Result<(String, Account), SignInError> {
+        let issued = self.issued.lock().await.remove(nonce);
+        let issued = issued.filter(|issued| issued.issued.elapsed() < NONCE_TTL).ok_or(SignInError::UnknownNonce)?;
+        let invalid = |reason: String| SignInError::InvalidSignature(reason);
+        let signature = signature_bytes(signature).map_err(|e| invalid(e.to_string()))?;
+        let signer = Signature::from_raw(&signature)
+            .and_then(|signature| signature.recover_address_from_msg(&issued.message))
+            .map_err(|e| invalid(e.to_string()))?;
+        if evm_address_bytes(&issued.address) != Some(signer.0 .0) {
+            return Err(invalid(format!("signed by {}", signer)));
+        }
+
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        let account = Account(issued.address.as_str().to_lowercase());
+        let mut sessions = self.sessions.lock().await;
+        sessions.retain(|_, session| session.expires > Instant::now());
+        let session = Session {
+            account: account.clone(),
+            expires: Instant::now() + SESSION_TTL,
+        };
+        sessions.insert(token.clone(), session);
+        Ok((token, account))
+    }
+
+    /// Account a session token is signed in as; None if it's unknown or expired
+    pub async fn account(&self, token: &str) -> Option<Account> {
+        let sessions = self.sessions.lock().await;
+        let session = sessions.get(token).filter(|session| session.expires > Instant::now())?;
+        Some(session.account.clone())
+    }
+
+    /// End a session
+    pub async fn sign_out(&self, token: &str) -> bool {
+        self.sessions.lock().await.remove(token).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sign_in_with_ethereum() {
+        let sessions = Sessions::new(SiweConfig {
+            domain: "example.com".to_string(),
+            uri: "https://example.com".to_string(),
+        });
+        let signer = PrivateKeySigner::random();
+        let address = Address::from(signer.address().to_string().as_str());
+        let (nonce, message) = sessions.issue(address.clone(), 1).await;
+        assert!(message.contains(&format!("Nonce: {}", nonce)));
+
+        let signature = format!("0x{}", hex::encode(signer.sign_message_sync(message.as_bytes()).unwrap().as_bytes()));
+        let other = PrivateKeySigner::random().sign_message_sync(message.as_bytes()).unwrap();
+        let (other_nonce, _) = sessions.issue(address.clone(), 1).await;
+        assert!(matches!(
+            sessions.sign_in(&other_nonce, &format!("0x{}", hex::encode(other.as_bytes()))).await,
+            Err(SignInError::InvalidSignature(_))
+        ));
+
+        let (token, account) = sessions.sign_in(&nonce, &signature).await.unwrap();
+        assert_eq!(account.0, address.as_str().to_lowercase());
+        assert_eq!(sessions.account(&token).await, Some(account));
+        assert_eq!(sessions.sign_in(&nonce, &signature).await, Err(SignInError::UnknownNonce));
+        assert!(sessions.sign_out(&token).await);
+        assert_eq!(sessions.account(&token).await, None);
+    }
+}
@@ -0,0 +1,126 @@
+//! On-disk cache of fetched transfers
+//!
+//! Normalized rows are kept per chain, wallet and block range, one JSON file each, so that
+//! fetching the same history again (a new session, a restart) doesn't download it again.
+//! A range ending at a block number never changes and is kept until the file is removed; a
+//! range ending at the latest block is fetched again once it is older than the TTL.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use financoor_core::LedgerRow;
+use serde::{Deserialize, Serialize};
+
+/// Where transfers are cached unless `TRANSFER_CACHE_DIR` says otherwise
+pub const DEFAULT_TRANSFER_CACHE_DIR: &str = "transfer_cache";
+
+/// How long transfers up to the latest block are reused
+pub const DEFAULT_TIP_TTL: Duration = Duration::from_secs(600);
+
+/// Block tag of ranges ending at the chain tip
+const LATEST: &str = "latest";
+
+pub struct TransferCache {
+    dir: PathBuf,
+    tip_ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// Unix time the rows were fetched
+    fetched_at: u64,
+    rows: Vec<LedgerRow>,
+}
+
+impl TransferCache {
+    pub fn new(dir: impl Into<PathBuf>, tip_ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            tip_ttl,
+        }
+    }
+
+    fn path(&self, chain_id: u64, wallet: &str, from_block: &str, to_block: &str) -> PathBuf {
+        self.dir
+            .join(chain_id.to_string())
+            .join(wallet.to_lowercase())
+            .join(format!("{}-{}.json", from_block, to_block))
+    }
+
+    /// Cached transfers of a wallet in a block range; None if not cached or the tip expired
+    pub fn get(&self, chain_id: u64, wallet: &str, from_block: &str, to_block: &str) -> Option<Vec<LedgerRow>> {
+        let path = self.path(chain_id, wallet, from_block, to_block);
+        let contents = std::fs::read_to_string(&path).ok()?;
+        let entry: CacheEntry = match serde_json::from_str(&contents) {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable transfer cache file {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        if to_block == LATEST && unix_now().saturating_sub(entry.fetched_at) >= self.tip_ttl.as_secs() {
+            return None;
+        }
+        Some(entry.rows)
+    }
+
+    /// Cache the transfers of a wallet in a block range
+    pub fn put(&self, chain_id: u64, wallet: &str, from_block: &str, to_block: &str, rows: &[LedgerRow]) -> Result<()> {
+        let path = self.path(chain_id, wallet, from_block, to_block);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let entry = CacheEntry {
+            fetched_at: unix_now(),
+            rows: rows.to_vec(),
+        };
+        std::fs::write(&path, serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+}
+
+fn unix_now() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use financoor_core::{Category, Direction};
+
+    #[test]
+    fn test_tip_expires_but_history_stays() {
+        let dir = std::env::temp_dir().join(format!("financoor-cache-{}", std::process::id()));
+        let cache = TransferCache::new(&dir, Duration::ZERO);
+        let row = LedgerRow {
+            chain_id: 1,
+            owner_wallet: "0xabc".into(),
+            tx_hash: "0x1".to_string(),
+            block_time: 1750000000,
+            asset: "ETH".to_string(),
+            amount: "1".to_string(),
+            decimals: 18,
+            direction: Direction::In,
+            counterparty: None,
+            category: Category::Unknown,
+            confidence: 0.0,
+            user_override: false,
+            token_id: None,
+            quantity: None,
+            contract_address: None,
+            counterparty_label: None,
+            counterparty_ens: None,
+            model_confidence: None,
+            inr_value: None,
+        };
+
+        cache.put(1, "0xABC", "0x0", "0x10", std::slice::from_ref(&row)).unwrap();
+        cache.put(1, "0xABC", "0x0", LATEST, std::slice::from_ref(&row)).unwrap();
+
+        assert_eq!(cache.get(1, "0xabc", "0x0", "0x10").unwrap()[0].tx_hash, "0x1");
+        assert!(cache.get(1, "0xabc", "0x0", LATEST).is_none());
+        assert!(cache.get(137, "0xabc", "0x0", "0x10").is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
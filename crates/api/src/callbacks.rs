@@ -0,0 +1,249 @@
+//! Callbacks on proof completion
+//!
+//! A proof request may name a `callback_url`; once its job is done or has failed, the job's
+//! status is POSTed there as JSON (the body `GET /proofs/{job_id}` returns, with an `event`
+//! of `proof.done` or `proof.error`), so backends needn't poll. Every delivery is signed
+//! with the server's callback secret:
+//!
+//! ```text
+//! X-Financoor-Timestamp: 1748736000
+//! X-Financoor-Signature: sha256=<hex HMAC-SHA256 of "<timestamp>.<body>">
+//! ```
+//!
+//! Receivers recompute the signature and refuse old timestamps, so a delivery can't be
+//! replayed. Deliveries that fail to connect or get a 429 or 5xx are retried with backoff;
+//! retries don't outlive a restart.
+//!
+//! Callbacks only go to public addresses: a URL whose host is or resolves to a loopback,
+//! private or link-local address (like cloud metadata at 169.254.169.254) is refused when
+//! the proof is requested, hosts are resolved the same way on every delivery, and
+//! redirects aren't followed, so a caller can't point the server at its own network.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::jobs::{JobRecord, ProofJobStatus};
+
+pub const TIMESTAMP_HEADER: &str = "X-Financoor-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Financoor-Signature";
+
+/// Waits before each retry of a failed delivery
+const RETRY_DELAYS: [Duration; 5] = [
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(30 * 60),
+    Duration::from_secs(2 * 60 * 60),
+];
+
+/// How long a receiver gets to answer a delivery
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct Delivery<'a> {
+    event: String,
+    job_id: &'a str,
+    #[serde(flatten)]
+    status: &'a ProofJobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finished_at: Option<u64>,
+}
+
+pub struct Callbacks {
+    client: reqwest::Client,
+    secret: String,
+}
+
+impl Callbacks {
+    pub fn new(secret: String) -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .expect("callback client settings are valid");
+        Self { client, secret }
+    }
+
+    /// POST a finished job's status to `url` in the background, retrying until it's
+    /// accepted or the retries run out
+    pub fn send(&self, url: String, record: &JobRecord) {
+        let delivery = Delivery {
+            event: format!("proof.{}", record.status.name()),
+            job_id: &record.id,
+            status: &record.status,
+            finished_at: record.finished_at,
+        };
+        let body = match serde_json::to_vec(&delivery) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Failed to serialize callback for proof job {}: {}", record.id, e);
+                return;
+            }
+        };
+        let (client, secret, job_id) = (self.client.clone(), self.secret.clone(), record.id.clone());
+        tokio::spawn(async move {
+            for delay in std::iter::once(Duration::ZERO).chain(RETRY_DELAYS) {
+                tokio::time::sleep(delay).await;
+                match deliver(&client, &secret, &url, &body).await {
+                    Ok(()) => return,
+                    Err((e, retry)) => {
+                        tracing::warn!("Callback for proof job {} to {} failed: {}", job_id, url, e);
+                        if !retry {
+                            return;
+                        }
+                    }
+                }
+            }
+            tracing::warn!("Gave up on the callback for proof job {} to {}", job_id, url);
+        });
+    }
+}
+
+/// One delivery; on failure, why and whether it's worth retrying
+async fn deliver(client: &reqwest::Client, secret: &str, url: &str, body: &[u8]) -> Result<(), (String, bool)> {
+    let timestamp = chrono::Utc::now().timestamp().max(0) as u64;
+    let response = client
+        .post(url)
+        .timeout(DELIVERY_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(TIMESTAMP_HEADER, timestamp)
+        .header(SIGNATURE_HEADER, sign(secret, timestamp, body))
+        .body(body.to_vec())
+        .send()
+        .await
+        .map_err(|e| (e.to_string(), true))?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(())
+    } else {
+        let retry = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+        Err((format!("HTTP {}", status), retry))
+    }
+}
+
+/// The signature header's value: `sha256=` and the hex HMAC-SHA256 of
+/// `<timestamp>.<body>` under `secret`
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Check a callback URL: absolute http(s) with a host that is, or resolves only to,
+/// public addresses
+pub async fn check_url(url: &str) -> Result<(), String> {
+    let host = match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => parsed.host_str().map(str::to_string),
+        _ => None,
+    };
+    let Some(host) = host else {
+        return Err(format!("Callback URL is not an http(s) URL: {}", url));
+    };
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) if is_public(ip) => Ok(()),
+        Ok(ip) => Err(format!("Callback URL is not a public address: {}", ip)),
+        Err(_) => public_addrs(&host).await.map(|_| ()),
+    }
+}
+
+/// The addresses `host` resolves to, unless any isn't public
+async fn public_addrs(host: &str) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+        .await
+        .map_err(|e| format!("Callback host {} doesn't resolve: {}", host, e))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("Callback host {} doesn't resolve", host));
+    }
+    match addrs.iter().find(|addr| !is_public(addr.ip())) {
+        Some(addr) => Err(format!("Callback host {} resolves to a non-public address: {}", host, addr.ip())),
+        None => Ok(addrs),
+    }
+}
+
+/// Whether `ip` is reachable on the internet, rather than this host or its network
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || a == 0
+                // Shared address space (carrier-grade NAT)
+                || a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                // Unique local (fc00::/7) and link-local (fe80::/10) aren't public either
+                !(ip.is_loopback() || ip.is_unspecified() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resolves hosts for deliveries, failing for any that resolve to a non-public address,
+/// so a host can't pass `check_url` and later resolve to the server's network
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = public_addrs(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_deliveries_are_signed_over_timestamp_and_body() {
+        let body = br#"{"event":"proof.error","job_id":"1f","status":"error","error":"Out of cycles"}"#;
+        let signature = sign("cbsec_test", 1_748_736_000, body);
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"cbsec_test").unwrap();
+        mac.update(b"1748736000.");
+        mac.update(body);
+        assert_eq!(signature, format!("sha256={}", hex::encode(mac.finalize().into_bytes())));
+        assert_ne!(signature, sign("cbsec_test", 1_748_736_001, body));
+
+        assert!(check_url("https://93.184.215.14/hooks/proofs").await.is_ok());
+        assert!(check_url("ftp://example.com/hooks").await.is_err());
+        assert!(check_url("/hooks/proofs").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_callbacks_to_the_servers_network_are_refused() {
+        for url in [
+            "http://localhost:3001/admin",
+            "http://127.0.0.1/hooks",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.5/hooks",
+            "http://172.16.3.4/hooks",
+            "https://192.168.1.10/hooks",
+            "http://0.0.0.0:3001/",
+            "http://[::1]/hooks",
+            "http://[::ffff:127.0.0.1]/hooks",
+            "http://[fd00::1]/hooks",
+        ] {
+            assert!(check_url(url).await.is_err(), "{} is allowed", url);
+        }
+    }
+}
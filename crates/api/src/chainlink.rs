@@ -0,0 +1,218 @@
+//! Chainlink price feeds
+//!
+//! Reads the USD answers of Chainlink aggregators over JSON-RPC: an on-chain price source
+//! users can cross-check against CoinGecko. A feed proxy numbers its rounds
+//! `phase << 64 | round`, with rounds starting again from 1 each time the proxy moves to a
+//! new aggregator (phase). A day's price is the answer in effect at 00:00 UTC, found by
+//! binary search over the update times of the rounds of the phase that covers it.
+
+use std::collections::HashMap;
+
+use alloy_primitives::keccak256;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use financoor_core::PriceEntry;
+
+use crate::alchemy::format_units;
+use crate::prices::PriceProvider;
+
+/// USD feed proxies on Ethereum mainnet; `CHAINLINK_FEEDS` adds to or replaces them
+pub const DEFAULT_FEEDS: [(&str, &str); 7] = [
+    ("ETH", "0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419"),
+    ("WETH", "0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419"),
+    ("BTC", "0xF4030086522a5bEEa4988F8cA5B36dbC97BeE88c"),
+    ("LINK", "0x2c1d072e956AFFC0D435Cb7AC38EF18d24d9127c"),
+    ("USDC", "0x8fFfFfd4AfB6115b954Bd326cbe7B4BA576818f6"),
+    ("USDT", "0x3E7d1eAB13ad0104d2750B8863b489D65364e32D"),
+    ("DAI", "0xAed0c38402a5d19df6E4c03F4E2DceD6e29c1ee9"),
+];
+
+/// One round of a feed
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Round {
+    answer: i128,
+    /// When the answer was last updated (unix seconds)
+    updated_at: u64,
+}
+
+pub struct ChainlinkClient {
+    client: reqwest::Client,
+    rpc_url: String,
+    /// Feed proxy address per asset symbol (uppercase)
+    feeds: HashMap<String, String>,
+}
+
+impl ChainlinkClient {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            rpc_url,
+            feeds: DEFAULT_FEEDS
+                .iter()
+                .map(|(asset, feed)| (asset.to_string(), feed.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Add or replace feed proxies, e.g. for another network or more assets
+    pub fn with_feeds(mut self, feeds: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.feeds.extend(feeds.into_iter().map(|(asset, feed)| (asset.to_uppercase(), feed)));
+        self
+    }
+
+    /// `eth_call` at the latest block; None when the call reverts (e.g. a round that
+    /// doesn't exist)
+    async fn call(&self, to: &str, signature: &str, argument: Option<u128>) -> Result<Option<Vec<u8>>> {
+        let mut data = keccak256(signature.as_bytes())[..4].to_vec();
+        if let Some(argument) = argument {
+            data.extend_from_slice(&[0; 16]);
+            data.extend_from_slice(&argument.to_be_bytes());
+        }
+        let request = serde_json::json!({
+            "id": 1,
+            "jsonrpc": "2.0",
+            "method": "eth_call",
+            "params": [{ "to": to, "data": format!("0x{}", hex::encode(data)) }, "latest"],
+        });
+        let response: serde_json::Value = self.client.post(&self.rpc_url).json(&request).send().await?.json().await?;
+        match response["result"].as_str() {
+            Some(result) => Ok(Some(hex::decode(result.trim_start_matches("0x"))?)),
+            None if response["error"].is_object() => Ok(None),
+            None => Err(anyhow!("Chainlink RPC error: unexpected response {}", response)),
+        }
+    }
+
+    async fn round(&self, feed: &str, round_id: u128) -> Result<Option<Round>> {
+        let returned = self.call(feed, "getRoundData(uint80)", Some(round_id)).await?;
+        Ok(returned.as_deref().and_then(decode_round).filter(|round| round.updated_at > 0))
+    }
+
+    /// Last round of each phase, latest phase first
+    async fn phases(&self, feed: &str) -> Result<Vec<(u128, u64)>> {
+        let latest = self
+            .call(feed, "latestRoundData()", None)
+            .await?
+            .and_then(|returned| word(&returned, 0))
+            .ok_or_else(|| anyhow!("Chainlink feed {} has no rounds", feed))?;
+        let current = latest >> 64;
+        let mut phases = vec![(current, latest as u64)];
+        for phase in (1..current).rev() {
+            let aggregator = self.call(feed, "phaseAggregators(uint16)", Some(phase)).await?;
+            let Some(aggregator) = aggregator.and_then(|returned| returned.get(12..32).map(hex::encode)) else {
+                continue;
+            };
+            if aggregator.bytes().all(|digit| digit == b'0') {
+                continue;
+            }
+            let last = self.call(&format!("0x{}", aggregator), "latestRound()", None).await?;
+            if let Some(last) = last.and_then(|returned| word(&returned, 0)) {
+                phases.push((phase, last as u64));
+            }
+        }
+        Ok(phases)
+    }
+
+    /// Answer in effect at `timestamp`; None before the feed's first round
+    async fn answer_at(&self, feed: &str, phases: &[(u128, u64)], timestamp: u64) -> Result<Option<i128>> {
+        for &(phase, last) in phases {
+            let id = |round: u64| phase << 64 | u128::from(round);
+            match self.round(feed, id(1)).await? {
+                Some(first) if first.updated_at <= timestamp => {}
+                _ => continue,
+            }
+
+            // updated_at(lo) <= timestamp < updated_at(hi)
+            let (mut lo, mut hi) = (1, last + 1);
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                match self.round(feed, id(mid)).await? {
+                    Some(round) if round.updated_at <= timestamp => lo = mid,
+                    _ => hi = mid,
+                }
+            }
+            return Ok(self.round(feed, id(lo)).await?.map(|round| round.answer));
+        }
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl PriceProvider for ChainlinkClient {
+    fn name(&self) -> &'static str {
+        "chainlink"
+    }
+
+    async fn daily_prices(&self, asset: &str, dates: &[String]) -> Result<Vec<PriceEntry>> {
+        let Some(feed) = self.feeds.get(&asset.to_uppercase()) else {
+            return Ok(Vec::new());
+        };
+        if dates.is_empty() {
+            return Ok(Vec::new());
+        }
+        let decimals = self
+            .call(feed, "decimals()", None)
+            .await?
+            .and_then(|returned| word(&returned, 0))
+            .ok_or_else(|| anyhow!("Chainlink feed {} has no decimals", feed))?;
+        let phases = self.phases(feed).await?;
+
+        let mut prices = Vec::new();
+        for date in dates {
+            let midnight = NaiveDate::parse_from_str(date, "%Y-%m-%d")?
+                .and_hms_opt(0, 0, 0)
+                .map_or(0, |time| time.and_utc().timestamp() as u64);
+            if let Some(answer) = self.answer_at(feed, &phases, midnight).await?.filter(|answer| *answer > 0) {
+                prices.push(PriceEntry {
+                    asset: asset.to_string(),
+                    usd_price: format_units(&answer.to_string(), decimals as u8),
+                    date: Some(date.clone()),
+                    user_override: false,
+                });
+            }
+        }
+        Ok(prices)
+    }
+}
+
+/// Low 128 bits of the `index`th 32-byte word of a return value
+fn word(returned: &[u8], index: usize) -> Option<u128> {
+    let word = returned.get(index * 32..(index + 1) * 32)?;
+    Some(u128::from_be_bytes(word[16..].try_into().ok()?))
+}
+
+/// `getRoundData` / `latestRoundData` return value: (roundId, answer, startedAt, updatedAt,
+/// answeredInRound)
+fn decode_round(returned: &[u8]) -> Option<Round> {
+    Some(Round {
+        answer: word(returned, 1)? as i128,
+        updated_at: word(returned, 3)? as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_round() {
+        let word = |value: u128| {
+            let mut word = [0u8; 32];
+            word[16..].copy_from_slice(&value.to_be_bytes());
+            word
+        };
+        let round_id = 6u128 << 64 | 1234;
+        let returned = [word(round_id), word(250_012_345_678), word(1_750_000_000), word(1_750_000_012), word(round_id)]
+            .concat();
+
+        assert_eq!(
+            decode_round(&returned),
+            Some(Round {
+                answer: 250_012_345_678,
+                updated_at: 1_750_000_012,
+            })
+        );
+        assert_eq!(format_units("250012345678", 8), "2500.12345678");
+        assert_eq!(decode_round(&returned[..64]), None);
+    }
+}
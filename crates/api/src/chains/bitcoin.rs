@@ -0,0 +1,180 @@
+//! Bitcoin addresses over an Esplora API (Blockstream, mempool.space or self-hosted)
+//!
+//! Confirmed transactions come from `/address/{address}/txs/chain`, 25 a page. Each is
+//! one row for the address's net change: outputs paid to it less the inputs it spent.
+//! When every input is the address's own, the fee is left out of what was sent, as gas
+//! is on EVM chains.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use financoor_core::{Address, Category, Direction, LedgerRow, BITCOIN_CHAIN_ID};
+use serde::Deserialize;
+
+use super::ChainProvider;
+use crate::alchemy::format_units;
+
+pub const DEFAULT_ESPLORA_URL: &str = "https://blockstream.info/api";
+
+/// Most transactions fetched per address
+const MAX_TRANSACTIONS: usize = 1000;
+
+pub struct EsploraClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transaction {
+    txid: String,
+    vin: Vec<Input>,
+    vout: Vec<Output>,
+    /// Satoshis
+    fee: u64,
+    status: Status,
+}
+
+#[derive(Debug, Deserialize)]
+struct Input {
+    /// None for coinbase inputs
+    prevout: Option<Output>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Output {
+    scriptpubkey_address: Option<String>,
+    /// Satoshis
+    value: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Status {
+    confirmed: bool,
+    block_time: Option<u64>,
+}
+
+impl EsploraClient {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ChainProvider for EsploraClient {
+    fn name(&self) -> &'static str {
+        "bitcoin"
+    }
+
+    async fn get_transfers(&self, wallet: &str) -> Result<Vec<LedgerRow>> {
+        let mut transactions: Vec<Transaction> = Vec::new();
+        loop {
+            let url = match transactions.last() {
+                Some(last) => format!("{}/address/{}/txs/chain/{}", self.base_url, wallet, last.txid),
+                None => format!("{}/address/{}/txs/chain", self.base_url, wallet),
+            };
+            let response = self.client.get(url).send().await?;
+            if !response.status().is_success() {
+                return Err(anyhow!("Esplora API error: {}", response.text().await?));
+            }
+            let page: Vec<Transaction> = response.json().await?;
+            if page.is_empty() {
+                break;
+            }
+            transactions.extend(page);
+
+            if transactions.len() >= MAX_TRANSACTIONS {
+                tracing::warn!(
+                    "Stopped fetching Bitcoin transactions for {} at the cap of {}",
+                    wallet,
+                    MAX_TRANSACTIONS
+                );
+                transactions.truncate(MAX_TRANSACTIONS);
+                break;
+            }
+        }
+
+        let mut ledger: Vec<LedgerRow> = transactions
+            .iter()
+            .filter(|transaction| transaction.status.confirmed)
+            .filter_map(|transaction| transaction_row(transaction, wallet))
+            .collect();
+        ledger.sort_by_key(|row| row.block_time);
+        Ok(ledger)
+    }
+}
+
+/// The address's net change in a transaction, if any
+fn transaction_row(transaction: &Transaction, wallet: &str) -> Option<LedgerRow> {
+    let ours = |output: &&Output| output.scriptpubkey_address.as_deref() == Some(wallet);
+    let prevouts = || transaction.vin.iter().filter_map(|input| input.prevout.as_ref());
+    let spent: u64 = prevouts().filter(ours).map(|output| output.value).sum();
+    let received: u64 = transaction.vout.iter().filter(ours).map(|output| output.value).sum();
+
+    let (direction, amount, counterparty) = if received >= spent {
+        // The sender: whoever funded the first input
+        let sender = prevouts().find(|o| !ours(o));
+        (Direction::In, received - spent, sender.and_then(|o| o.scriptpubkey_address.clone()))
+    } else {
+        let all_inputs_ours = transaction.vin.iter().all(|input| input.prevout.as_ref().is_some_and(|o| ours(&o)));
+        let fee = if all_inputs_ours { transaction.fee } else { 0 };
+        let recipient = transaction.vout.iter().find(|o| !ours(o) && o.scriptpubkey_address.is_some());
+        (Direction::Out, (spent - received).saturating_sub(fee), recipient.and_then(|o| o.scriptpubkey_address.clone()))
+    };
+    if amount == 0 {
+        return None;
+    }
+
+    Some(LedgerRow {
+        chain_id: BITCOIN_CHAIN_ID,
+        owner_wallet: wallet.into(),
+        tx_hash: transaction.txid.clone(),
+        block_time: transaction.status.block_time.unwrap_or(0),
+        asset: "BTC".to_string(),
+        amount: format_units(&amount.to_string(), 8),
+        decimals: 8,
+        direction,
+        counterparty: counterparty.map(Address::from),
+        category: Category::Unknown,
+        confidence: 0.0,
+        user_override: false,
+        token_id: None,
+        quantity: None,
+        contract_address: None,
+        counterparty_label: None,
+        counterparty_ens: None,
+        model_confidence: None,
+        inr_value: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_net_change_without_fee() {
+        let transaction: Transaction = serde_json::from_value(serde_json::json!({
+            "txid": "ab",
+            "vin": [{ "prevout": { "scriptpubkey_address": "bc1qme", "value": 100_000 } }],
+            "vout": [
+                { "scriptpubkey_address": "bc1qfriend", "value": 60_000 },
+                { "scriptpubkey_address": "bc1qme", "value": 39_000 }
+            ],
+            "fee": 1_000,
+            "status": { "confirmed": true, "block_time": 1750000000 }
+        }))
+        .unwrap();
+
+        let sent = transaction_row(&transaction, "bc1qme").unwrap();
+        assert_eq!(sent.direction, Direction::Out);
+        assert_eq!(sent.amount, "0.0006");
+        assert_eq!(sent.counterparty.as_deref(), Some("bc1qfriend"));
+
+        let received = transaction_row(&transaction, "bc1qfriend").unwrap();
+        assert_eq!(received.direction, Direction::In);
+        assert_eq!(received.amount, "0.0006");
+        assert_eq!(received.counterparty.as_deref(), Some("bc1qme"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Chain providers
+//!
+//! A `ChainProvider` fetches a wallet's transfers on one chain and normalizes them to
+//! ledger rows, so wallets on any chain end up in the same ledger (and proof). EVM
+//! wallets go through Alchemy; Solana wallets through a JSON-RPC node and Bitcoin
+//! addresses through an Esplora API, recorded under core's `SOLANA_CHAIN_ID` and
+//! `BITCOIN_CHAIN_ID`. The address format decides which provider a wallet uses.
+
+mod bitcoin;
+mod solana;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use financoor_core::LedgerRow;
+
+pub use bitcoin::{EsploraClient, DEFAULT_ESPLORA_URL};
+pub use solana::{SolanaClient, DEFAULT_SOLANA_RPC_URL};
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Source of a wallet's transfers on one chain
+#[async_trait]
+pub trait ChainProvider: Send + Sync {
+    /// Name used in logs and errors ("alchemy", "solana", ...)
+    fn name(&self) -> &'static str;
+
+    /// All transfers of the wallet, oldest first
+    async fn get_transfers(&self, wallet: &str) -> Result<Vec<LedgerRow>>;
+}
+
+/// Which kind of chain an address belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressKind {
+    Evm,
+    Solana,
+    Bitcoin,
+}
+
+/// The chain an address belongs to, from its format; None if it isn't a known one
+///
+/// Legacy Bitcoin addresses and Solana public keys are both base58 and can be the same
+/// length, so they are told apart by their decoded size (25 and 32 bytes).
+pub fn address_kind(address: &str) -> Option<AddressKind> {
+    let is_hex = |s: &str| s.chars().all(|c| c.is_ascii_hexdigit());
+    if address.len() == 42 && address.starts_with("0x") && is_hex(&address[2..]) {
+        return Some(AddressKind::Evm);
+    }
+
+    let lower = address.to_lowercase();
+    if (lower.starts_with("bc1") || lower.starts_with("tb1")) && (14..=74).contains(&address.len()) {
+        return Some(AddressKind::Bitcoin);
+    }
+    match base58_decoded_len(address)? {
+        32 => Some(AddressKind::Solana),
+        25 if address.starts_with(['1', '3', 'm', 'n', '2']) => Some(AddressKind::Bitcoin),
+        _ => None,
+    }
+}
+
+/// Number of bytes a base58 string decodes to; None if it isn't base58
+fn base58_decoded_len(value: &str) -> Option<usize> {
+    if value.is_empty() {
+        return None;
+    }
+    // Big-endian bytes of the number, built up digit by digit
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in value.chars() {
+        let mut carry = BASE58_ALPHABET.find(c)? as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    // Each leading '1' is a leading zero byte
+    let zeros = value.chars().take_while(|&c| c == '1').count();
+    Some(zeros + bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_kinds() {
+        assert_eq!(address_kind("0x742d35Cc6634C0532925a3b844Bc454e4438f44e"), Some(AddressKind::Evm));
+        assert_eq!(address_kind("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"), Some(AddressKind::Bitcoin));
+        assert_eq!(address_kind("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"), Some(AddressKind::Bitcoin));
+        assert_eq!(address_kind("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"), Some(AddressKind::Bitcoin));
+        assert_eq!(address_kind("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM"), Some(AddressKind::Solana));
+        assert_eq!(address_kind("So11111111111111111111111111111111111111112"), Some(AddressKind::Solana));
+        assert_eq!(address_kind("0xnothex"), None);
+        assert_eq!(address_kind("not an address"), None);
+    }
+}
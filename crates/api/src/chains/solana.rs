@@ -0,0 +1,261 @@
+//! Solana wallets over JSON-RPC
+//!
+//! Signatures come from `getSignaturesForAddress` (newest first, 1000 a page) and each
+//! transaction from `getTransaction` with parsed encoding. What the wallet gained or lost
+//! is read from the balances before and after: lamports for SOL (the fee added back, as
+//! gas isn't a transfer on EVM chains either) and token balances owned by the wallet for
+//! SPL tokens, one row per mint.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use financoor_core::{Category, Direction, LedgerRow, SOLANA_CHAIN_ID};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::ChainProvider;
+use crate::alchemy::format_units;
+
+pub const DEFAULT_SOLANA_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+
+/// Most transactions fetched per wallet
+const MAX_SIGNATURES: usize = 1000;
+
+/// Symbols of well-known SPL mints; other tokens are recorded under their mint address
+const KNOWN_MINTS: [(&str, &str); 3] = [
+    ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USDC"),
+    ("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", "USDT"),
+    ("So11111111111111111111111111111111111111112", "WSOL"),
+];
+
+pub struct SolanaClient {
+    client: reqwest::Client,
+    rpc_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignatureInfo {
+    signature: String,
+    /// Set if the transaction failed
+    err: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transaction {
+    block_time: Option<u64>,
+    meta: TransactionMeta,
+    transaction: TransactionBody,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionMeta {
+    fee: u64,
+    pre_balances: Vec<u64>,
+    post_balances: Vec<u64>,
+    #[serde(default)]
+    pre_token_balances: Vec<TokenBalance>,
+    #[serde(default)]
+    post_token_balances: Vec<TokenBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenBalance {
+    mint: String,
+    owner: Option<String>,
+    ui_token_amount: TokenAmount,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenAmount {
+    /// Raw integer amount
+    amount: String,
+    decimals: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransactionBody {
+    message: Message,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Message {
+    account_keys: Vec<AccountKey>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountKey {
+    pubkey: String,
+}
+
+impl SolanaClient {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            rpc_url,
+        }
+    }
+
+    async fn rpc<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> Result<T> {
+        let request = serde_json::json!({ "id": 1, "jsonrpc": "2.0", "method": method, "params": params });
+        let response: RpcResponse<T> = self.client.post(&self.rpc_url).json(&request).send().await?.json().await?;
+        if let Some(error) = response.error {
+            return Err(anyhow!("Solana RPC error: {}", error.message));
+        }
+        response.result.ok_or_else(|| anyhow!("Solana RPC error: no result for {}", method))
+    }
+
+    /// Signatures of the wallet's successful transactions, newest first
+    async fn signatures(&self, wallet: &str) -> Result<Vec<String>> {
+        let mut signatures: Vec<String> = Vec::new();
+        let mut before: Option<String> = None;
+        loop {
+            let mut options = serde_json::json!({ "limit": 1000 });
+            if let Some(before) = &before {
+                options["before"] = serde_json::json!(before);
+            }
+            let page: Vec<SignatureInfo> =
+                self.rpc("getSignaturesForAddress", serde_json::json!([wallet, options])).await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            before = Some(last.signature.clone());
+            signatures.extend(page.into_iter().filter(|info| info.err.is_none()).map(|info| info.signature));
+
+            if signatures.len() >= MAX_SIGNATURES {
+                tracing::warn!("Stopped fetching Solana transactions for {} at the cap of {}", wallet, MAX_SIGNATURES);
+                signatures.truncate(MAX_SIGNATURES);
+                break;
+            }
+        }
+        Ok(signatures)
+    }
+}
+
+#[async_trait]
+impl ChainProvider for SolanaClient {
+    fn name(&self) -> &'static str {
+        "solana"
+    }
+
+    async fn get_transfers(&self, wallet: &str) -> Result<Vec<LedgerRow>> {
+        let mut ledger = Vec::new();
+        for signature in self.signatures(wallet).await? {
+            let options = serde_json::json!({ "encoding": "jsonParsed", "maxSupportedTransactionVersion": 0 });
+            let transaction: Transaction = self.rpc("getTransaction", serde_json::json!([signature, options])).await?;
+            ledger.extend(transaction_rows(&transaction, wallet, &signature));
+        }
+        ledger.sort_by_key(|row| row.block_time);
+        Ok(ledger)
+    }
+}
+
+/// The wallet's SOL and SPL token balance changes in a transaction
+fn transaction_rows(transaction: &Transaction, wallet: &str, signature: &str) -> Vec<LedgerRow> {
+    let meta = &transaction.meta;
+    let row = |asset: &str, delta: i128, decimals: u8, mint: Option<&str>| LedgerRow {
+        chain_id: SOLANA_CHAIN_ID,
+        owner_wallet: wallet.into(),
+        tx_hash: signature.to_string(),
+        block_time: transaction.block_time.unwrap_or(0),
+        asset: asset.to_string(),
+        amount: format_units(&delta.unsigned_abs().to_string(), decimals),
+        decimals,
+        direction: if delta > 0 { Direction::In } else { Direction::Out },
+        counterparty: None,
+        category: Category::Unknown,
+        confidence: 0.0,
+        user_override: false,
+        token_id: None,
+        quantity: None,
+        contract_address: mint.map(str::to_string),
+        counterparty_label: None,
+        counterparty_ens: None,
+        model_confidence: None,
+        inr_value: None,
+    };
+
+    let mut rows = Vec::new();
+    let keys = &transaction.transaction.message.account_keys;
+    if let Some(index) = keys.iter().position(|key| key.pubkey == wallet) {
+        let pre = meta.pre_balances.get(index).copied().unwrap_or(0) as i128;
+        let post = meta.post_balances.get(index).copied().unwrap_or(0) as i128;
+        // The first account pays the fee
+        let fee = if index == 0 { meta.fee as i128 } else { 0 };
+        let delta = post - pre + fee;
+        if delta != 0 {
+            rows.push(row("SOL", delta, 9, None));
+        }
+    }
+
+    // Token balances are per token account; sum the wallet's accounts per mint
+    let mut deltas: BTreeMap<&str, (i128, u8)> = BTreeMap::new();
+    let owned = |balance: &&TokenBalance| balance.owner.as_deref() == Some(wallet);
+    for (balances, sign) in [(&meta.pre_token_balances, -1), (&meta.post_token_balances, 1)] {
+        for balance in balances.iter().filter(owned) {
+            let amount: i128 = balance.ui_token_amount.amount.parse().unwrap_or(0);
+            let entry = deltas.entry(&balance.mint).or_insert((0, balance.ui_token_amount.decimals));
+            entry.0 += sign * amount;
+        }
+    }
+    for (mint, (delta, decimals)) in deltas.into_iter().filter(|(_, (delta, _))| *delta != 0) {
+        let asset = KNOWN_MINTS.iter().find(|(known, _)| *known == mint).map_or(mint, |(_, symbol)| symbol);
+        rows.push(row(asset, delta, decimals, Some(mint)));
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_changes_become_rows() {
+        let wallet = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+        let transaction: Transaction = serde_json::from_value(serde_json::json!({
+            "blockTime": 1750000000,
+            "meta": {
+                "fee": 5000,
+                "preBalances": [2_000_000_000u64, 0],
+                "postBalances": [1_499_995_000u64, 500_000_000],
+                "preTokenBalances": [
+                    { "accountIndex": 2, "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "owner": wallet,
+                      "uiTokenAmount": { "amount": "1000000", "decimals": 6 } }
+                ],
+                "postTokenBalances": [
+                    { "accountIndex": 2, "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "owner": wallet,
+                      "uiTokenAmount": { "amount": "251000000", "decimals": 6 } }
+                ]
+            },
+            "transaction": { "message": { "accountKeys": [{ "pubkey": wallet }, { "pubkey": "Other111" }] } }
+        }))
+        .unwrap();
+
+        let rows = transaction_rows(&transaction, wallet, "sig1");
+
+        // Swapped 0.5 SOL (the fee isn't part of it) for 250 USDC
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].asset, "SOL");
+        assert_eq!(rows[0].amount, "0.5");
+        assert_eq!(rows[0].direction, Direction::Out);
+        assert_eq!(rows[1].asset, "USDC");
+        assert_eq!(rows[1].amount, "250");
+        assert_eq!(rows[1].direction, Direction::In);
+        assert_eq!(rows[1].chain_id, SOLANA_CHAIN_ID);
+    }
+}
@@ -0,0 +1,366 @@
+//! Server configuration
+//!
+//! Settings are read from a TOML file, then overridden by environment variables and then
+//! by command-line flags, and checked before anything starts:
+//!
+//! ```toml
+//! bind = "0.0.0.0"
+//! port = 3001
+//! cors_origins = ["https://financoor.app"]
+//! storage_dir = "data"
+//!
+//! [alchemy]
+//! api_key = "your-alchemy-api-key"
+//! network = "eth-mainnet"
+//!
+//! [chains]
+//! solana_rpc_url = "https://api.mainnet-beta.solana.com"
+//! esplora_url = "https://blockstream.info/api"
+//!
+//! [ens]
+//! subgraph_url = "https://api.thegraph.com/subgraphs/name/ensdomains/ens"
+//!
+//! [prover]
+//! mode = "groth16"
+//! workers = 1
+//! shutdown_grace_secs = 300
+//! callback_secret = "your-callback-signing-secret"
+//! ```
+//!
+//! The file is `--config`, else `FINANCOOR_CONFIG`, else `financoor.toml` if there is one.
+//! Every setting has a default, so neither the file nor any variable is required.
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use axum::http::HeaderValue;
+use clap::Parser;
+use financoor_prover::ProofMode;
+use serde::Deserialize;
+
+use crate::alchemy::{is_supported_network, DEFAULT_NETWORK};
+use crate::chains::{DEFAULT_ESPLORA_URL, DEFAULT_SOLANA_RPC_URL};
+use crate::ens::DEFAULT_SUBGRAPH_URL;
+use crate::jobs::{DEFAULT_PROOF_WORKERS, DEFAULT_SHUTDOWN_GRACE};
+use crate::storage::DEFAULT_STORAGE_DIR;
+
+/// Configuration file read when neither `--config` nor `FINANCOOR_CONFIG` names one
+pub const DEFAULT_CONFIG_PATH: &str = "financoor.toml";
+
+pub const DEFAULT_PORT: u16 = 3001;
+
+/// Command-line flags, which override the file and the environment
+#[derive(Debug, Default, Parser)]
+#[command(name = "api", version, about = "Financoor API server")]
+pub struct Cli {
+    /// TOML configuration file (`FINANCOOR_CONFIG`, else financoor.toml if there is one)
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Address to listen on (`BIND_ADDRESS`)
+    #[arg(long)]
+    pub bind: Option<IpAddr>,
+    /// Port to listen on (`PORT`)
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Origin browsers may call the API from, repeated for several (`CORS_ORIGINS`)
+    #[arg(long = "cors-origin")]
+    pub cors_origins: Vec<String>,
+    /// Directory accounts' data is kept in, empty to keep it in memory only (`STORAGE_DIR`)
+    #[arg(long)]
+    pub storage_dir: Option<String>,
+    /// Alchemy network EVM wallets are fetched from (`ALCHEMY_NETWORK`)
+    #[arg(long)]
+    pub alchemy_network: Option<String>,
+    /// ENS subgraph subdomains are resolved through (`ENS_SUBGRAPH_URL`)
+    #[arg(long)]
+    pub subgraph_url: Option<String>,
+    /// Proof mode of requests that don't choose one (`PROOF_MODE`)
+    #[arg(long)]
+    pub proof_mode: Option<ProofMode>,
+    /// Proofs generated at once (`PROOF_WORKERS`)
+    #[arg(long)]
+    pub proof_workers: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind: IpAddr,
+    pub port: u16,
+    /// Origins browsers may call the API from, e.g. `https://financoor.app`; any if empty
+    pub cors_origins: Vec<String>,
+    /// Where accounts' data is kept; empty to keep it in memory only
+    pub storage_dir: String,
+    pub alchemy: AlchemyConfig,
+    pub chains: ChainsConfig,
+    pub ens: EnsConfig,
+    pub prover: ProvingConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlchemyConfig {
+    /// None for Alchemy's heavily rate-limited demo key
+    pub api_key: Option<String>,
+    /// Network EVM wallets are fetched from, by its Alchemy slug
+    pub network: String,
+}
+
+/// Providers of the non-EVM chains
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChainsConfig {
+    pub solana_rpc_url: String,
+    pub esplora_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnsConfig {
+    pub subgraph_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProvingConfig {
+    /// Mode of proof requests that don't choose one
+    pub mode: ProofMode,
+    /// Proofs generated at once
+    pub workers: usize,
+    /// Seconds running proofs get to finish on shutdown before they're interrupted
+    pub shutdown_grace_secs: u64,
+    /// Key proof callbacks are signed with; None to refuse requests with a `callback_url`
+    pub callback_secret: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: DEFAULT_PORT,
+            cors_origins: Vec::new(),
+            storage_dir: DEFAULT_STORAGE_DIR.to_string(),
+            alchemy: AlchemyConfig::default(),
+            chains: ChainsConfig::default(),
+            ens: EnsConfig::default(),
+            prover: ProvingConfig::default(),
+        }
+    }
+}
+
+impl Default for AlchemyConfig {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            network: DEFAULT_NETWORK.to_string(),
+        }
+    }
+}
+
+impl Default for ChainsConfig {
+    fn default() -> Self {
+        Self {
+            solana_rpc_url: DEFAULT_SOLANA_RPC_URL.to_string(),
+            esplora_url: DEFAULT_ESPLORA_URL.to_string(),
+        }
+    }
+}
+
+impl Default for EnsConfig {
+    fn default() -> Self {
+        Self {
+            subgraph_url: DEFAULT_SUBGRAPH_URL.to_string(),
+        }
+    }
+}
+
+impl Default for ProvingConfig {
+    fn default() -> Self {
+        Self {
+            mode: ProofMode::default(),
+            workers: DEFAULT_PROOF_WORKERS,
+            shutdown_grace_secs: DEFAULT_SHUTDOWN_GRACE.as_secs(),
+            callback_secret: None,
+        }
+    }
+}
+
+impl Config {
+    /// The configuration file, the environment over it and `cli` over both, checked
+    pub fn load(cli: &Cli) -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok();
+        let path = cli.config.clone().or_else(|| var("FINANCOOR_CONFIG").map(PathBuf::from));
+        let mut config = match path {
+            Some(path) => Self::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?,
+            None => Self::default(),
+        };
+        config.apply_env(var)?;
+        config.apply_cli(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Parsing {}", path.display()))
+    }
+
+    /// Settings the variables `var` reads are set; an empty `STORAGE_DIR` counts, as it
+    /// keeps data in memory only, but other empty variables are ignored
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        let set = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        if let Some(bind) = set("BIND_ADDRESS") {
+            self.bind = bind.trim().parse().context("BIND_ADDRESS is not an IP address")?;
+        }
+        if let Some(port) = set("PORT") {
+            self.port = port.trim().parse().context("PORT is not a port number")?;
+        }
+        if let Some(origins) = set("CORS_ORIGINS") {
+            self.cors_origins = origins.split(',').map(str::trim).filter(|o| !o.is_empty()).map(String::from).collect();
+        }
+        if let Some(storage_dir) = var("STORAGE_DIR") {
+            self.storage_dir = storage_dir;
+        }
+        if let Some(api_key) = set("ALCHEMY_API_KEY") {
+            self.alchemy.api_key = Some(api_key);
+        }
+        for (name, value) in [
+            ("ALCHEMY_NETWORK", &mut self.alchemy.network),
+            ("SOLANA_RPC_URL", &mut self.chains.solana_rpc_url),
+            ("ESPLORA_URL", &mut self.chains.esplora_url),
+            ("ENS_SUBGRAPH_URL", &mut self.ens.subgraph_url),
+        ] {
+            if let Some(setting) = set(name) {
+                *value = setting;
+            }
+        }
+        if let Some(mode) = set("PROOF_MODE") {
+            self.prover.mode = mode.parse()?;
+        }
+        if let Some(workers) = set("PROOF_WORKERS") {
+            self.prover.workers = workers.trim().parse().context("PROOF_WORKERS is not a number")?;
+        }
+        if let Some(secs) = set("PROOF_SHUTDOWN_GRACE_SECS") {
+            self.prover.shutdown_grace_secs = secs.trim().parse().context("PROOF_SHUTDOWN_GRACE_SECS is not a number")?;
+        }
+        if let Some(secret) = set("PROOF_CALLBACK_SECRET") {
+            self.prover.callback_secret = Some(secret);
+        }
+        Ok(())
+    }
+
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(bind) = cli.bind {
+            self.bind = bind;
+        }
+        if let Some(port) = cli.port {
+            self.port = port;
+        }
+        if !cli.cors_origins.is_empty() {
+            self.cors_origins = cli.cors_origins.clone();
+        }
+        if let Some(storage_dir) = &cli.storage_dir {
+            self.storage_dir = storage_dir.clone();
+        }
+        if let Some(network) = &cli.alchemy_network {
+            self.alchemy.network = network.clone();
+        }
+        if let Some(subgraph_url) = &cli.subgraph_url {
+            self.ens.subgraph_url = subgraph_url.clone();
+        }
+        if let Some(mode) = cli.proof_mode {
+            self.prover.mode = mode;
+        }
+        if let Some(workers) = cli.proof_workers {
+            self.prover.workers = workers;
+        }
+    }
+
+    /// Fail on settings the server can't start with, rather than on the first request
+    /// that uses them
+    pub fn validate(&self) -> Result<()> {
+        if self.port == 0 {
+            bail!("The port must not be 0");
+        }
+        for origin in &self.cors_origins {
+            let url = reqwest::Url::parse(origin).with_context(|| format!("CORS origin {} is not a URL", origin))?;
+            let is_origin = matches!(url.scheme(), "http" | "https") && url.path() == "/";
+            if !is_origin || HeaderValue::from_str(origin).is_err() {
+                bail!("CORS origin {} is not a scheme and host, like https://financoor.app", origin);
+            }
+        }
+        if !is_supported_network(&self.alchemy.network) {
+            bail!("Unsupported Alchemy network: {}", self.alchemy.network);
+        }
+        for (name, url) in [
+            ("Solana RPC URL", &self.chains.solana_rpc_url),
+            ("Esplora URL", &self.chains.esplora_url),
+            ("ENS subgraph URL", &self.ens.subgraph_url),
+        ] {
+            match reqwest::Url::parse(url) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                _ => bail!("The {} is not an http(s) URL: {}", name, url),
+            }
+        }
+        if self.prover.workers == 0 {
+            bail!("At least one proof worker is needed");
+        }
+        if self.prover.callback_secret.as_ref().is_some_and(|secret| secret.trim().is_empty()) {
+            bail!("The proof callback secret must not be empty");
+        }
+        Ok(())
+    }
+
+    /// Where accounts' data is kept; None to keep it in memory only
+    pub fn storage_dir(&self) -> Option<PathBuf> {
+        (!self.storage_dir.is_empty()).then(|| PathBuf::from(&self.storage_dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_then_env_then_cli() {
+        let mut config: Config = toml::from_str(
+            r#"
+            port = 8080
+            cors_origins = ["https://financoor.app"]
+
+            [alchemy]
+            network = "eth-mainnet"
+
+            [prover]
+            mode = "core"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.alchemy.network, "eth-mainnet");
+        assert_eq!(config.storage_dir, DEFAULT_STORAGE_DIR);
+
+        let env = |name: &str| match name {
+            "PORT" => Some("9000".to_string()),
+            "STORAGE_DIR" => Some(String::new()),
+            "PROOF_MODE" => Some("compressed".to_string()),
+            _ => None,
+        };
+        config.apply_env(env).unwrap();
+        let cli = Cli::parse_from(["api", "--port", "9100", "--cors-origin", "http://localhost:3000"]);
+        config.apply_cli(&cli);
+        config.validate().unwrap();
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.cors_origins, ["http://localhost:3000"]);
+        assert_eq!(config.storage_dir(), None);
+        assert_eq!(config.prover.mode, ProofMode::Compressed);
+
+        config.alchemy.network = "doge-mainnet".to_string();
+        assert!(config.validate().is_err());
+        config.alchemy.network = DEFAULT_NETWORK.to_string();
+        config.cors_origins = vec!["https://financoor.app/app".to_string()];
+        assert!(config.validate().is_err());
+        assert!(toml::from_str::<Config>("prot = 8080").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Binance spot account connector
+//!
+//! Fills come from `myTrades`, one trading pair at a time; each fill is a base-asset leg
+//! and a quote-asset leg valued at the quote amount. Deposits and withdrawals come from
+//! the capital endpoints, which only return the last 90 days from `since`.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use financoor_core::Direction;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{exchange_row, sign, ApiCredentials, ExchangeRecord, FiatValue, RecordKind, TradeSource};
+
+const BINANCE_API_URL: &str = "https://api.binance.com";
+
+/// Binance account with a read-only API key
+pub struct BinanceSource {
+    client: reqwest::Client,
+    credentials: ApiCredentials,
+    /// Trading pairs to fetch fills for
+    symbols: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExchangeInfo {
+    symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolInfo {
+    base_asset: String,
+    quote_asset: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Fill {
+    id: u64,
+    qty: String,
+    quote_qty: String,
+    /// Milliseconds
+    time: u64,
+    is_buyer: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Deposit {
+    amount: String,
+    coin: String,
+    tx_id: String,
+    /// Milliseconds
+    insert_time: u64,
+    /// 1 once credited
+    status: u8,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Withdrawal {
+    id: String,
+    amount: String,
+    coin: String,
+    /// "2025-06-15 10:30:00" (UTC)
+    apply_time: String,
+    /// 6 once completed
+    status: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceError {
+    msg: String,
+}
+
+impl BinanceSource {
+    pub fn new(credentials: ApiCredentials, symbols: Vec<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            credentials,
+            symbols,
+        }
+    }
+
+    /// GET a signed endpoint
+    async fn signed_get<T: DeserializeOwned>(&self, path: &str, query: &str) -> Result<T> {
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let query = format!("{}&timestamp={}", query, timestamp);
+        let signature = sign(&self.credentials.api_secret, &query);
+
+        let response = self
+            .client
+            .get(format!("{}{}?{}&signature={}", BINANCE_API_URL, path, query, signature))
+            .header("X-MBX-APIKEY", &self.credentials.api_key)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let error: BinanceError = response.json().await?;
+            return Err(anyhow!("Binance API error: {}", error.msg));
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn fills(&self, symbol: &str, since: u64) -> Result<Vec<ExchangeRecord>> {
+        let info: ExchangeInfo = self
+            .client
+            .get(format!("{}/api/v3/exchangeInfo?symbol={}", BINANCE_API_URL, symbol))
+            .send()
+            .await?
+            .json()
+            .await?;
+        let pair = info
+            .symbols
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Unknown Binance symbol: {}", symbol))?;
+
+        let fills: Vec<Fill> = self
+            .signed_get("/api/v3/myTrades", &format!("symbol={}&startTime={}&limit=1000", symbol, since * 1000))
+            .await?;
+
+        let mut records = Vec::new();
+        for fill in fills {
+            let id = format!("binance:{}:{}", symbol, fill.id);
+            let value = FiatValue {
+                currency: pair.quote_asset.clone(),
+                amount: fill.quote_qty.clone(),
+            };
+            let (base_direction, quote_direction) = if fill.is_buyer {
+                (Direction::In, Direction::Out)
+            } else {
+                (Direction::Out, Direction::In)
+            };
+            let time = fill.time / 1000;
+            let leg = |asset: &str, amount: &str, direction| ExchangeRecord {
+                kind: RecordKind::Trade,
+                row: exchange_row(self.name(), RecordKind::Trade, id.clone(), time, asset, amount, direction),
+                fiat_value: Some(value.clone()),
+            };
+            records.push(leg(&pair.base_asset, &fill.qty, base_direction));
+            records.push(leg(&pair.quote_asset, &fill.quote_qty, quote_direction));
+        }
+        Ok(records)
+    }
+}
+
+#[async_trait]
+impl TradeSource for BinanceSource {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    async fn fetch(&self, since: u64) -> Result<Vec<ExchangeRecord>> {
+        let mut records = Vec::new();
+        for symbol in &self.symbols {
+            records.extend(self.fills(&symbol.to_uppercase(), since).await?);
+        }
+
+        let start = format!("startTime={}", since * 1000);
+        let deposits: Vec<Deposit> = self.signed_get("/sapi/v1/capital/deposit/hisrec", &start).await?;
+        for deposit in deposits.into_iter().filter(|deposit| deposit.status == 1) {
+            records.push(ExchangeRecord {
+                kind: RecordKind::Deposit,
+                row: exchange_row(
+                    self.name(),
+                    RecordKind::Deposit,
+                    deposit.tx_id,
+                    deposit.insert_time / 1000,
+                    &deposit.coin,
+                    &deposit.amount,
+                    Direction::In,
+                ),
+                fiat_value: None,
+            });
+        }
+
+        let withdrawals: Vec<Withdrawal> = self.signed_get("/sapi/v1/capital/withdraw/history", &start).await?;
+        for withdrawal in withdrawals.into_iter().filter(|withdrawal| withdrawal.status == 6) {
+            let applied = chrono::NaiveDateTime::parse_from_str(&withdrawal.apply_time, "%Y-%m-%d %H:%M:%S")
+                .map(|time| time.and_utc().timestamp() as u64)
+                .unwrap_or(0);
+            records.push(ExchangeRecord {
+                kind: RecordKind::Withdrawal,
+                row: exchange_row(
+                    self.name(),
+                    RecordKind::Withdrawal,
+                    format!("binance:withdrawal:{}", withdrawal.id),
+                    applied,
+                    &withdrawal.coin,
+                    &withdrawal.amount,
+                    Direction::Out,
+                ),
+                fiat_value: None,
+            });
+        }
+
+        records.sort_by_key(|record| record.row.block_time);
+        Ok(records)
+    }
+}
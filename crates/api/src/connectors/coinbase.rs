@@ -0,0 +1,160 @@
+//! Coinbase account connector
+//!
+//! Reads every account's transactions from the v2 API with a legacy (HMAC) API key.
+//! Each transaction carries its value in the user's native currency, which is used as
+//! the fiat value.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use financoor_core::Direction;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{exchange_row, sign, ApiCredentials, ExchangeRecord, FiatValue, RecordKind, TradeSource};
+
+const COINBASE_API_URL: &str = "https://api.coinbase.com";
+
+/// API version sent with every request
+const COINBASE_API_VERSION: &str = "2024-01-01";
+
+/// Coinbase account with a read-only API key
+pub struct CoinbaseSource {
+    client: reqwest::Client,
+    credentials: ApiCredentials,
+}
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    pagination: Pagination,
+    data: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pagination {
+    next_uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Account {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transaction {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    status: String,
+    /// Signed; negative for outflows
+    amount: Money,
+    native_amount: Option<Money>,
+    /// RFC 3339
+    created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Money {
+    amount: String,
+    currency: String,
+}
+
+impl CoinbaseSource {
+    pub fn new(credentials: ApiCredentials) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            credentials,
+        }
+    }
+
+    /// All pages of a signed GET
+    async fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut next = Some(path.to_string());
+        while let Some(path) = next {
+            let timestamp = chrono::Utc::now().timestamp().to_string();
+            let signature = sign(&self.credentials.api_secret, &format!("{}GET{}", timestamp, path));
+
+            let response = self
+                .client
+                .get(format!("{}{}", COINBASE_API_URL, path))
+                .header("CB-ACCESS-KEY", &self.credentials.api_key)
+                .header("CB-ACCESS-SIGN", signature)
+                .header("CB-ACCESS-TIMESTAMP", timestamp)
+                .header("CB-VERSION", COINBASE_API_VERSION)
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(anyhow!("Coinbase API error: {}", response.text().await?));
+            }
+
+            let page: Page<T> = response.json().await?;
+            items.extend(page.data);
+            next = page.pagination.next_uri;
+        }
+        Ok(items)
+    }
+}
+
+/// Trade, deposit or withdrawal, from the transaction type and the sign of its amount
+fn record_kind(kind: &str, direction: Direction) -> RecordKind {
+    match (kind, direction) {
+        ("send" | "fiat_deposit" | "fiat_withdrawal" | "exchange_deposit" | "exchange_withdrawal", Direction::In) => {
+            RecordKind::Deposit
+        }
+        ("send" | "fiat_deposit" | "fiat_withdrawal" | "exchange_deposit" | "exchange_withdrawal", Direction::Out) => {
+            RecordKind::Withdrawal
+        }
+        _ => RecordKind::Trade,
+    }
+}
+
+#[async_trait]
+impl TradeSource for CoinbaseSource {
+    fn name(&self) -> &'static str {
+        "coinbase"
+    }
+
+    async fn fetch(&self, since: u64) -> Result<Vec<ExchangeRecord>> {
+        let accounts: Vec<Account> = self.get_all("/v2/accounts?limit=100").await?;
+
+        let mut records = Vec::new();
+        for account in accounts {
+            let transactions: Vec<Transaction> =
+                self.get_all(&format!("/v2/accounts/{}/transactions?limit=100", account.id)).await?;
+            for transaction in transactions.into_iter().filter(|transaction| transaction.status == "completed") {
+                let created = chrono::DateTime::parse_from_rfc3339(&transaction.created_at)
+                    .map(|time| time.timestamp() as u64)
+                    .unwrap_or(0);
+                if created < since {
+                    continue;
+                }
+
+                let direction = if transaction.amount.amount.starts_with('-') {
+                    Direction::Out
+                } else {
+                    Direction::In
+                };
+                let kind = record_kind(&transaction.kind, direction);
+                records.push(ExchangeRecord {
+                    kind,
+                    row: exchange_row(
+                        self.name(),
+                        kind,
+                        format!("coinbase:{}", transaction.id),
+                        created,
+                        &transaction.amount.currency,
+                        &transaction.amount.amount,
+                        direction,
+                    ),
+                    fiat_value: transaction.native_amount.map(|value| FiatValue {
+                        currency: value.currency,
+                        amount: value.amount.trim_start_matches('-').to_string(),
+                    }),
+                });
+            }
+        }
+
+        records.sort_by_key(|record| record.row.block_time);
+        Ok(records)
+    }
+}
@@ -0,0 +1,199 @@
+//! Exchange connectors
+//!
+//! A `TradeSource` pulls an account's fills, deposits and withdrawals from an exchange
+//! API with a read-only key and turns them into ledger rows, each with what it was worth
+//! in fiat at the time. Deposits and withdrawals are exchange transfers; trade legs are
+//! left Unknown for review, and purchases become acquisition lots so that later
+//! disposals have an accurate cost basis.
+
+mod binance;
+mod coinbase;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use financoor_core::{AcquisitionLot, Category, Direction, LedgerRow};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+pub use binance::BinanceSource;
+pub use coinbase::CoinbaseSource;
+
+/// Currencies priced at one US dollar when converting fiat values to INR
+const USD_CURRENCIES: [&str; 5] = ["USD", "USDT", "USDC", "BUSD", "FDUSD"];
+
+/// What kind of account activity a record comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    Trade,
+    Deposit,
+    Withdrawal,
+}
+
+/// Value of a record in a fiat (or dollar-pegged) currency at the time
+#[derive(Debug, Clone)]
+pub struct FiatValue {
+    pub currency: String,
+    pub amount: String,
+}
+
+/// A ledger row from an exchange account
+#[derive(Debug, Clone)]
+pub struct ExchangeRecord {
+    pub kind: RecordKind,
+    pub row: LedgerRow,
+    pub fiat_value: Option<FiatValue>,
+}
+
+/// Read-only API credentials
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+/// An exchange account that trades, deposits and withdrawals can be fetched from
+#[async_trait]
+pub trait TradeSource: Send + Sync {
+    /// Account name recorded as the owner wallet of its rows ("binance", ...)
+    fn name(&self) -> &'static str;
+
+    /// Activity since `since` (unix seconds), oldest first
+    async fn fetch(&self, since: u64) -> Result<Vec<ExchangeRecord>>;
+}
+
+/// The connector for an exchange by name
+///
+/// `symbols` lists the trading pairs to fetch fills for where the exchange needs them
+/// (Binance: "BTCUSDT", "ETHINR", ...).
+pub fn trade_source(exchange: &str, credentials: ApiCredentials, symbols: Vec<String>) -> Result<Box<dyn TradeSource>> {
+    match exchange {
+        "binance" => Ok(Box::new(BinanceSource::new(credentials, symbols))),
+        "coinbase" => Ok(Box::new(CoinbaseSource::new(credentials))),
+        other => Err(anyhow!("Unsupported exchange: {}", other)),
+    }
+}
+
+/// Acquisition lots for the assets bought in trades, costed in INR
+///
+/// Fiat values in INR are taken as is and dollar values are converted at `usd_inr_rate`;
+/// purchases valued in anything else are skipped.
+pub fn acquisition_lots(records: &[ExchangeRecord], usd_inr_rate: f64) -> Vec<AcquisitionLot> {
+    records
+        .iter()
+        .filter(|record| record.kind == RecordKind::Trade && record.row.direction == Direction::In)
+        .filter(|record| !is_fiat(&record.row.asset))
+        .filter_map(|record| {
+            let value = record.fiat_value.as_ref()?;
+            let amount: f64 = value.amount.parse().ok()?;
+            let cost_inr = if value.currency.eq_ignore_ascii_case("INR") {
+                amount
+            } else if USD_CURRENCIES.iter().any(|usd| usd.eq_ignore_ascii_case(&value.currency)) {
+                amount * usd_inr_rate
+            } else {
+                return None;
+            };
+            Some(AcquisitionLot {
+                asset: record.row.asset.clone(),
+                amount: record.row.amount.clone(),
+                cost_inr: format!("{:.2}", cost_inr.abs()),
+                acquired_at: record.row.block_time,
+            })
+        })
+        .collect()
+}
+
+fn is_fiat(asset: &str) -> bool {
+    asset.eq_ignore_ascii_case("INR") || USD_CURRENCIES.iter().any(|usd| usd.eq_ignore_ascii_case(asset))
+}
+
+/// A ledger row of an exchange account; deposits and withdrawals are exchange transfers,
+/// trades are left for review
+fn exchange_row(
+    exchange: &str,
+    kind: RecordKind,
+    id: String,
+    block_time: u64,
+    asset: &str,
+    amount: &str,
+    direction: Direction,
+) -> LedgerRow {
+    let amount = amount.trim_start_matches('-').to_string();
+    let (category, confidence) = match kind {
+        RecordKind::Trade => (Category::Unknown, 0.0),
+        RecordKind::Deposit | RecordKind::Withdrawal => (Category::ExchangeTransfer, 0.9),
+    };
+    LedgerRow {
+        chain_id: 0,
+        owner_wallet: exchange.into(),
+        tx_hash: id,
+        block_time,
+        asset: asset.to_uppercase(),
+        decimals: amount.split_once('.').map_or(0, |(_, fraction)| fraction.len() as u8),
+        amount,
+        direction,
+        counterparty: None,
+        category,
+        confidence,
+        user_override: false,
+        token_id: None,
+        quantity: None,
+        contract_address: None,
+        counterparty_label: Some(exchange.to_string()),
+        counterparty_ens: None,
+        model_confidence: None,
+        inr_value: None,
+    }
+}
+
+/// Hex HMAC-SHA256 signature of `message`
+fn sign(secret: &str, message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_purchases_become_lots() {
+        let buy = |asset: &str, currency: &str, value: &str| ExchangeRecord {
+            kind: RecordKind::Trade,
+            row: exchange_row("binance", RecordKind::Trade, "1".to_string(), 1_750_000_000, asset, "1", Direction::In),
+            fiat_value: Some(FiatValue {
+                currency: currency.to_string(),
+                amount: value.to_string(),
+            }),
+        };
+        let records = vec![
+            buy("ETH", "USDT", "1000"),
+            buy("BTC", "INR", "2500000"),
+            buy("SOL", "EUR", "100"),
+            // Selling ETH for USDT: the USDT received isn't a lot
+            buy("USDT", "USDT", "1000"),
+        ];
+
+        let lots = acquisition_lots(&records, 83.0);
+
+        assert_eq!(lots.len(), 2);
+        assert_eq!(lots[0].asset, "ETH");
+        assert_eq!(lots[0].cost_inr, "83000.00");
+        assert_eq!(lots[0].acquired_at, 1_750_000_000);
+        assert_eq!(lots[1].cost_inr, "2500000.00");
+    }
+
+    #[test]
+    fn test_hmac_signature() {
+        // Example from the Binance API documentation
+        let query = concat!(
+            "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1",
+            "&recvWindow=5000&timestamp=1499827319559"
+        );
+        assert_eq!(
+            sign("NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j", query),
+            "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+}
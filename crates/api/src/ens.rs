@@ -1,20 +1,31 @@
 //! ENS (Ethereum Name Service) subdomain resolver
 //!
 //! Resolves a root ENS name to its subdomains and their addresses
-//! via the ENS subgraph.
+//! via the ENS subgraph, and addresses to their primary names through
+//! the ENS registry on-chain.
 
+use std::collections::HashMap;
+
+use alloy_primitives::{keccak256, B256};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::alchemy::AlchemyClient;
+
+/// ENS registry, at the same address on mainnet and Sepolia
+const ENS_REGISTRY: &str = "0x00000000000C2E074eC69A0bFb2933Ba0d0d0c4A";
+
+/// Chains the ENS registry is deployed on (mainnet, Sepolia)
+pub const ENS_CHAIN_IDS: [u64; 2] = [1, 11155111];
+
+/// Most addresses reverse-resolved on-chain per call; the rest wait for a later fetch
+const MAX_REVERSE_LOOKUPS: usize = 50;
 
 /// ENS Subgraph URL - uses Sepolia by default for testnet development
 /// Mainnet: https://api.thegraph.com/subgraphs/name/ensdomains/ens
 /// Sepolia: https://api.studio.thegraph.com/query/49574/enssepolia/version/latest
-fn get_subgraph_url() -> String {
-    std::env::var("ENS_SUBGRAPH_URL").unwrap_or_else(|_| {
-        // Default to Sepolia for development/hackathon
-        "https://api.studio.thegraph.com/query/49574/enssepolia/version/latest".to_string()
-    })
-}
+pub const DEFAULT_SUBGRAPH_URL: &str = "https://api.studio.thegraph.com/query/49574/enssepolia/version/latest";
 
 /// Resolved subdomain with its address
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +41,10 @@ pub struct ResolvedSubdomain {
 /// ENS resolver client
 pub struct EnsResolver {
     client: reqwest::Client,
+    /// Subgraph subdomains are resolved through
+    subgraph_url: String,
+    /// Primary names by lowercase address; None for addresses without one
+    names: RwLock<HashMap<String, Option<String>>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -77,10 +92,53 @@ struct SubdomainNode {
 }
 
 impl EnsResolver {
-    pub fn new() -> Self {
+    pub fn new(subgraph_url: String) -> Self {
         Self {
             client: reqwest::Client::new(),
+            subgraph_url,
+            names: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Primary ENS names of addresses, by lowercase address
+    ///
+    /// Looked up through `alchemy`'s network and cached, including addresses without a
+    /// name. A name only counts if it resolves back to the address, as anyone can set
+    /// any name as their reverse record.
+    pub async fn reverse_resolve(&self, alchemy: &AlchemyClient, addresses: &[String]) -> HashMap<String, String> {
+        let mut pending: Vec<String> = Vec::new();
+        {
+            let names = self.names.read().await;
+            for address in addresses {
+                let address = address.to_lowercase();
+                if !names.contains_key(&address) && !pending.contains(&address) {
+                    pending.push(address);
+                }
+            }
         }
+
+        let lookups = pending.into_iter().take(MAX_REVERSE_LOOKUPS).map(|address| async move {
+            let name = primary_name(alchemy, &address).await;
+            (address, name)
+        });
+        for (address, name) in futures::future::join_all(lookups).await {
+            match name {
+                Ok(name) => {
+                    self.names.write().await.insert(address, name);
+                }
+                Err(e) => tracing::warn!("Failed to reverse-resolve {}: {}", address, e),
+            }
+        }
+
+        let names = self.names.read().await;
+        addresses
+            .iter()
+            .filter_map(|address| {
+                let address = address.to_lowercase();
+                let name = names.get(&address)?.clone()?;
+                Some((address, name))
+            })
+            .collect()
     }
 
     /// Resolve a root ENS name to its subdomains
@@ -93,7 +151,7 @@ impl EnsResolver {
     pub async fn resolve_subdomains(&self, root_name: &str) -> Result<Vec<ResolvedSubdomain>> {
         // Normalize the root name
         let root_name = root_name.trim().to_lowercase();
-        let subgraph_url = get_subgraph_url();
+        let subgraph_url = &self.subgraph_url;
         eprintln!("[ENS] Resolving '{}' via subgraph: {}", root_name, subgraph_url);
 
         // GraphQL query to get domain and its subdomains
@@ -123,7 +181,7 @@ impl EnsResolver {
 
         let response: GraphQLResponse = self
             .client
-            .post(&subgraph_url)
+            .post(subgraph_url)
             .json(&request)
             .send()
             .await?
@@ -170,9 +228,68 @@ impl EnsResolver {
     }
 }
 
+/// The name an address's reverse record points to, if it resolves back to the address
+async fn primary_name(alchemy: &AlchemyClient, address: &str) -> Result<Option<String>> {
+    let reverse_node = namehash(&format!("{}.addr.reverse", address.trim_start_matches("0x")));
+    let Some(resolver) = resolver_of(alchemy, reverse_node).await? else {
+        return Ok(None);
+    };
+    let returned = alchemy.call(&resolver, &node_call("name(bytes32)", reverse_node)).await?;
+    let Some(name) = decode_string(&returned).filter(|name| !name.is_empty()) else {
+        return Ok(None);
+    };
+
+    let node = namehash(&name);
+    let Some(resolver) = resolver_of(alchemy, node).await? else {
+        return Ok(None);
+    };
+    let returned = alchemy.call(&resolver, &node_call("addr(bytes32)", node)).await?;
+    Ok(decode_address(&returned).is_some_and(|resolved| resolved.eq_ignore_ascii_case(address)).then_some(name))
+}
+
+/// Resolver contract set for a node in the registry, if any
+async fn resolver_of(alchemy: &AlchemyClient, node: B256) -> Result<Option<String>> {
+    let returned = alchemy.call(ENS_REGISTRY, &node_call("resolver(bytes32)", node)).await?;
+    Ok(decode_address(&returned).filter(|resolver| resolver.trim_start_matches("0x").chars().any(|c| c != '0')))
+}
+
+/// Calldata of a function taking a single `bytes32 node`
+fn node_call(signature: &str, node: B256) -> Vec<u8> {
+    [&keccak256(signature.as_bytes())[..4], node.as_slice()].concat()
+}
+
+/// An ABI-encoded `address` return value
+fn decode_address(returned: &[u8]) -> Option<String> {
+    let word = returned.get(..32)?;
+    Some(format!("0x{}", hex::encode(&word[12..])))
+}
+
+/// An ABI-encoded `string` return value: offset, length, then the bytes
+fn decode_string(returned: &[u8]) -> Option<String> {
+    let word = |at: usize| -> Option<usize> {
+        let word = returned.get(at..at + 32)?;
+        // Anything that doesn't fit in 8 bytes is far past the end
+        if word[..24].iter().any(|&byte| byte != 0) {
+            return None;
+        }
+        Some(u64::from_be_bytes(word[24..].try_into().ok()?) as usize)
+    };
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let bytes = returned.get(offset + 32..(offset + 32).checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// ENS namehash of a name (labels hashed right to left)
+fn namehash(name: &str) -> B256 {
+    name.rsplit('.').filter(|label| !label.is_empty()).fold(B256::ZERO, |node, label| {
+        keccak256([node.as_slice(), keccak256(label.as_bytes()).as_slice()].concat())
+    })
+}
+
 impl Default for EnsResolver {
     fn default() -> Self {
-        Self::new()
+        Self::new(DEFAULT_SUBGRAPH_URL.to_string())
     }
 }
 
@@ -180,10 +297,31 @@ impl Default for EnsResolver {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_namehash() {
+        assert_eq!(namehash(""), B256::ZERO);
+        assert_eq!(
+            namehash("eth").to_string(),
+            "0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+        );
+        assert_eq!(
+            namehash("addr.reverse").to_string(),
+            "0x91d1777781884d03a6757a803996e38de2a42967fb37eeaca72729271025a9e2"
+        );
+
+        // resolver(bytes32), then a name(bytes32) result of "alice.eth"
+        assert_eq!(hex::encode(&node_call("resolver(bytes32)", B256::ZERO)[..4]), "0178b8bf");
+        let mut returned = vec![0u8; 96];
+        returned[31] = 32;
+        returned[63] = 9;
+        returned[64..73].copy_from_slice(b"alice.eth");
+        assert_eq!(decode_string(&returned).as_deref(), Some("alice.eth"));
+    }
+
     #[tokio::test]
     #[ignore] // Requires network access
     async fn test_resolve_vitalik_eth() {
-        let resolver = EnsResolver::new();
+        let resolver = EnsResolver::default();
         let result = resolver.resolve_subdomains("vitalik.eth").await;
         // May or may not have subdomains, but should not error
         assert!(result.is_ok());
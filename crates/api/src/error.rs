@@ -0,0 +1,160 @@
+//! Errors the API responds with
+//!
+//! Every failure is a JSON body with a stable code, a message for people, details for
+//! the code that need them and whether the same request may succeed if tried again:
+//!
+//! ```json
+//! { "code": "PRICE_MISSING", "message": "...", "details": { "issues": [...] }, "retryable": false }
+//! ```
+//!
+//! Clients branch on the code; the message is free to change.
+
+use axum::http::header::RETRY_AFTER;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+/// What went wrong, as clients see it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request is malformed or asks for something that can't be done
+    InvalidRequest,
+    /// Fields of a tax or proof request are invalid; `details.issues` lists them
+    ValidationFailed,
+    /// Taxable rows are of assets with no price; `details.issues` lists them
+    PriceMissing,
+    /// No session or API key, or one that isn't valid
+    Unauthorized,
+    /// The API key lacks the scope the endpoint needs
+    Forbidden,
+    /// The API key made too many requests; `details.retry_after_secs` says when to retry
+    RateLimited,
+    NotFound,
+    /// The feature isn't configured on this server
+    NotConfigured,
+    /// The resource isn't in a state the request can act on
+    Conflict,
+    /// Too many proof jobs are queued or running for the wallets
+    TooManyJobs,
+    /// The job hasn't finished yet
+    ProofPending,
+    /// The job failed to prove; `details.error` says why
+    ProofFailed,
+    /// Proof artifacts that don't verify
+    ProofInvalid,
+    /// Alchemy kept rate limiting the API's requests
+    AlchemyRateLimited,
+    /// A provider (Alchemy, a price source, an RPC node) failed
+    UpstreamFailed,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::InvalidRequest | ErrorCode::ProofInvalid => StatusCode::BAD_REQUEST,
+            ErrorCode::ValidationFailed | ErrorCode::PriceMissing => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::RateLimited | ErrorCode::TooManyJobs => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::NotFound | ErrorCode::NotConfigured => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict | ErrorCode::ProofPending | ErrorCode::ProofFailed => StatusCode::CONFLICT,
+            ErrorCode::AlchemyRateLimited => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::UpstreamFailed => StatusCode::BAD_GATEWAY,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Whether the same request may succeed later
+    pub fn retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::RateLimited
+                | ErrorCode::TooManyJobs
+                | ErrorCode::ProofPending
+                | ErrorCode::AlchemyRateLimited
+                | ErrorCode::UpstreamFailed
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    pub retryable: bool,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+            retryable: code.retryable(),
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidRequest, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// A failure of Alchemy, told apart from its rate limit
+    pub fn alchemy(e: &anyhow::Error, context: &str) -> Self {
+        let code = match e.downcast_ref::<crate::alchemy::RateLimited>() {
+            Some(_) => ErrorCode::AlchemyRateLimited,
+            None => ErrorCode::UpstreamFailed,
+        };
+        Self::new(code, format!("{}: {}", context, e))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let retry_after = self.details.as_ref().and_then(|details| details["retry_after_secs"].as_u64());
+        let mut response = (self.code.status(), Json(self)).into_response();
+        if let Some(retry_after) = retry_after {
+            response.headers_mut().insert(RETRY_AFTER, retry_after.into());
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_body_and_status() {
+        let error = ApiError::new(ErrorCode::RateLimited, "Slow down")
+            .with_details(serde_json::json!({ "retry_after_secs": 12 }));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "RATE_LIMITED",
+                "message": "Slow down",
+                "details": { "retry_after_secs": 12 },
+                "retryable": true,
+            })
+        );
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "12");
+
+        let error = serde_json::to_value(ApiError::invalid("Bad")).unwrap();
+        assert_eq!(error, serde_json::json!({ "code": "INVALID_REQUEST", "message": "Bad", "retryable": false }));
+    }
+}
@@ -0,0 +1,66 @@
+//! Etherscan name tag lookup
+//!
+//! Fetches the public name tag ("Binance 14", "Uniswap V3: Router") of an address via
+//! the Etherscan v2 multichain API. Only used when `ETHERSCAN_API_KEY` is set.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+const ETHERSCAN_API_URL: &str = "https://api.etherscan.io/v2/api";
+
+/// Etherscan API client
+pub struct EtherscanClient {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EtherscanResponse {
+    status: String,
+    message: String,
+    result: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddressTag {
+    #[serde(default)]
+    nametag: String,
+}
+
+impl EtherscanClient {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+        }
+    }
+
+    /// The name tag of `address` on `chain_id`, if Etherscan has one
+    pub async fn name_tag(&self, chain_id: u64, address: &str) -> Result<Option<String>> {
+        let response: EtherscanResponse = self
+            .client
+            .get(ETHERSCAN_API_URL)
+            .query(&[
+                ("chainid", chain_id.to_string().as_str()),
+                ("module", "nametag"),
+                ("action", "getaddresstag"),
+                ("address", address),
+                ("apikey", self.api_key.as_str()),
+            ])
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if response.status != "1" {
+            // "No data found" is an untagged address, anything else is an API error
+            if response.message.starts_with("No data") {
+                return Ok(None);
+            }
+            return Err(anyhow!("Etherscan error: {} ({})", response.message, response.result));
+        }
+
+        let tags: Vec<AddressTag> = serde_json::from_value(response.result)?;
+        Ok(tags.into_iter().map(|tag| tag.nametag).find(|nametag| !nametag.is_empty()))
+    }
+}
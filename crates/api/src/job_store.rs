@@ -0,0 +1,83 @@
+//! On-disk record of proof jobs
+//!
+//! Each job is one JSON file in the job directory, written when it's queued and again when
+//! it finishes or is cancelled, so proofs outlive a restart, and deleted with the job. A pending job's file also
+//! holds its input (and the parts of an aggregate), so that a job a restart interrupted
+//! can be queued again.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use financoor_core::TaxInput;
+use serde::{Deserialize, Serialize};
+
+use crate::jobs::JobRecord;
+
+/// Where proof jobs are kept unless `PROOF_JOB_DIR` says otherwise
+pub const DEFAULT_PROOF_JOB_DIR: &str = "proof_jobs";
+
+#[derive(Serialize, Deserialize)]
+pub struct StoredJob {
+    #[serde(flatten)]
+    pub record: JobRecord,
+    /// Input of a job that is still pending
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<TaxInput>,
+    /// Parts of the input a pending aggregate job proves
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<TaxInput>,
+}
+
+pub struct JobStore {
+    dir: PathBuf,
+}
+
+impl JobStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Write a job, replacing what was kept about it
+    pub fn put(&self, job: &StoredJob) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        // Write then rename, so a crash mid-write doesn't leave a truncated job behind
+        let path = self.path(&job.record.id);
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_vec(job)?)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    /// Delete a job; nothing to do if it was never written
+    pub fn remove(&self, id: &str) -> Result<()> {
+        match std::fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every job kept, skipping files that can't be read
+    pub fn load(&self) -> Vec<StoredJob> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .filter_map(|path| {
+                let contents = std::fs::read(&path).ok()?;
+                match serde_json::from_slice(&contents) {
+                    Ok(job) => Some(job),
+                    Err(e) => {
+                        tracing::warn!("Ignoring unreadable proof job file {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
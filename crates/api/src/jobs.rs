@@ -0,0 +1,1177 @@
+//! Proof job queue
+//!
+//! Proofs are queued for a fixed number of workers, kept on disk with a `JobStore`, and
+//! belong to the account that submitted them.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use financoor_core::{Disclosure, TaxInput};
+use financoor_prover::{ProofArtifacts, ProofMode, ProvingBackend, ProvingEstimate, TaxProver};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, Mutex, Notify};
+use tracing::Instrument;
+
+use crate::callbacks::Callbacks;
+use crate::job_store::{JobStore, StoredJob};
+use crate::metrics::METRICS;
+
+/// How long finished jobs are kept unless `PROOF_JOB_RETENTION_SECS` says otherwise
+pub const DEFAULT_JOB_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How often jobs past their retention are dropped
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Proofs generated at once unless `PROOF_WORKERS` says otherwise
+pub const DEFAULT_PROOF_WORKERS: usize = 1;
+
+/// Jobs an account can have queued or running unless `MAX_PROOF_JOBS_PER_USER` says otherwise
+pub const DEFAULT_MAX_JOBS_PER_USER: usize = 2;
+
+/// How long running jobs get to finish on shutdown unless the configuration says otherwise
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "status")]
+pub enum ProofJobStatus {
+    #[serde(rename = "pending")]
+    Pending {
+        stage: ProofStage,
+        /// Cycles the program runs for, once executed
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cycles: Option<u64>,
+        /// Rough time from leaving the queue to the proof, once executed (seconds)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        estimated_seconds: Option<u64>,
+        /// Time since the job left the queue (seconds)
+        elapsed_seconds: u64,
+    },
+    #[serde(rename = "done")]
+    Done { result: Box<ProofResult> },
+    #[serde(rename = "error")]
+    Error { error: String },
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+impl ProofJobStatus {
+    /// Name of the status, as serialized
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProofJobStatus::Pending { .. } => "pending",
+            ProofJobStatus::Done { .. } => "done",
+            ProofJobStatus::Error { .. } => "error",
+            ProofJobStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProofResult {
+    #[serde(flatten)]
+    pub artifacts: ProofArtifacts,
+    pub user_type_code: u8,
+    pub used_44ada: bool,
+    pub assessment_year: u16,
+}
+
+/// Where a pending job is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofStage {
+    /// Waiting for a worker
+    Queued,
+    /// Executing the program to count its cycles
+    Executing,
+    /// Proving the execution
+    Proving,
+    /// Compressing and wrapping the proof (estimated)
+    Wrapping,
+}
+
+/// Order in which queued jobs are picked up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// A proof to generate
+pub struct ProofJob {
+    pub id: String,
+    /// Account the job belongs to, and counts against (no other account can see, cancel or
+    /// list it); empty for the anonymous account
+    pub account: String,
+    pub priority: JobPriority,
+    pub input: TaxInput,
+    /// Parts of `input` to prove separately and aggregate; empty to prove `input` itself
+    pub parts: Vec<TaxInput>,
+    /// Statement to commit in place of the figures
+    pub disclosure: Option<Disclosure>,
+    pub mode: ProofMode,
+    pub backend: ProvingBackend,
+    /// Reported with the result
+    pub user_type_code: u8,
+    pub used_44ada: bool,
+    pub assessment_year: u16,
+    /// The batch the job was submitted in
+    pub batch: Option<BatchEntry>,
+    /// What the job proves: jobs with the same key produce the same proof
+    pub proof_key: String,
+    /// URL to call back when the job finishes; kept in the record's `callbacks` once queued
+    pub callback_url: Option<String>,
+}
+
+/// A job's place in a batch
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BatchEntry {
+    pub id: String,
+    /// Position of the job in the batch as submitted
+    pub index: usize,
+    /// What the submitter calls the job, e.g. the client's name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl ProofJob {
+    fn record(&self, submitted_at: u64) -> JobRecord {
+        JobRecord {
+            id: self.id.clone(),
+            account: self.account.clone(),
+            wallets: ledger_wallets(&self.input),
+            priority: self.priority,
+            disclosure: self.disclosure.clone(),
+            mode: self.mode,
+            backend: self.backend,
+            user_type_code: self.user_type_code,
+            used_44ada: self.used_44ada,
+            assessment_year: self.assessment_year,
+            batch: self.batch.clone(),
+            proof_key: self.proof_key.clone(),
+            callbacks: self.callback_url.iter().cloned().collect(),
+            submitted_at,
+            finished_at: None,
+            status: queued_status(),
+        }
+    }
+}
+
+/// Everything about a job but its input, and its latest status
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub id: String,
+    #[serde(default)]
+    pub account: String,
+    /// Wallets the job's ledger belongs to, comma-separated
+    #[serde(alias = "user")]
+    pub wallets: String,
+    pub priority: JobPriority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disclosure: Option<Disclosure>,
+    pub mode: ProofMode,
+    pub backend: ProvingBackend,
+    pub user_type_code: u8,
+    pub used_44ada: bool,
+    pub assessment_year: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchEntry>,
+    /// Empty for jobs stored before jobs had keys, which are never reused
+    #[serde(default)]
+    pub proof_key: String,
+    /// URLs to call back when the job is done or fails
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub callbacks: Vec<String>,
+    /// Unix time the job was submitted
+    pub submitted_at: u64,
+    /// Unix time the job finished or was cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    pub status: ProofJobStatus,
+}
+
+impl JobRecord {
+    /// The job again, to queue it with its input
+    fn job(&self, input: TaxInput, parts: Vec<TaxInput>) -> ProofJob {
+        ProofJob {
+            id: self.id.clone(),
+            account: self.account.clone(),
+            priority: self.priority,
+            input,
+            parts,
+            disclosure: self.disclosure.clone(),
+            mode: self.mode,
+            backend: self.backend,
+            user_type_code: self.user_type_code,
+            used_44ada: self.used_44ada,
+            assessment_year: self.assessment_year,
+            batch: self.batch.clone(),
+            proof_key: self.proof_key.clone(),
+            callback_url: None,
+        }
+    }
+}
+
+/// Which of an account's jobs to list; every job by default
+#[derive(Default, Deserialize)]
+pub struct JobFilter {
+    /// Set from the request's session rather than the query
+    #[serde(skip)]
+    pub account: String,
+    /// "pending", "done", "error" or "cancelled"
+    pub status: Option<String>,
+    /// One of the wallets the job's ledger belongs to
+    pub wallet: Option<String>,
+    pub assessment_year: Option<u16>,
+    /// Batch the job was submitted in
+    pub batch: Option<String>,
+}
+
+impl JobFilter {
+    fn matches(&self, record: &JobRecord) -> bool {
+        record.account == self.account
+            && self.status.as_ref().is_none_or(|status| status == record.status.name())
+            && self.wallet.as_ref().is_none_or(|wallet| {
+                record.wallets.split(',').any(|owner| owner.eq_ignore_ascii_case(wallet))
+            })
+            && self.assessment_year.is_none_or(|year| year == record.assessment_year)
+            && self.batch.as_ref().is_none_or(|id| record.batch.as_ref().is_some_and(|batch| &batch.id == id))
+    }
+}
+
+/// A job as listed: its record without the proof
+#[derive(Serialize)]
+pub struct JobSummary {
+    pub job_id: String,
+    pub status: &'static str,
+    pub mode: ProofMode,
+    pub backend: ProvingBackend,
+    pub assessment_year: u16,
+    pub submitted_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ledger_commitment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_tax_paisa: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JobSummary {
+    fn new(record: &JobRecord, status: &ProofJobStatus) -> Self {
+        let (ledger_commitment, total_tax_paisa, error) = match status {
+            ProofJobStatus::Done { result } => {
+                // A selective-disclosure proof doesn't commit the total
+                let total_tax_paisa = result.artifacts.disclosed.is_none().then_some(result.artifacts.total_tax_paisa);
+                (Some(result.artifacts.ledger_commitment.clone()), total_tax_paisa, None)
+            }
+            ProofJobStatus::Error { error } => (None, None, Some(error.clone())),
+            ProofJobStatus::Pending { .. } | ProofJobStatus::Cancelled => (None, None, None),
+        };
+        Self {
+            job_id: record.id.clone(),
+            status: status.name(),
+            mode: record.mode,
+            backend: record.backend,
+            assessment_year: record.assessment_year,
+            submitted_at: record.submitted_at,
+            ledger_commitment,
+            total_tax_paisa,
+            error,
+        }
+    }
+}
+
+/// A job of a batch, with its status
+#[derive(Serialize)]
+pub struct BatchJob {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(flatten)]
+    pub summary: JobSummary,
+}
+
+/// Where a batch's jobs are
+#[derive(Serialize)]
+pub struct BatchStatus {
+    pub batch_id: String,
+    /// "pending" while any job is, then "done" (though some may have failed or been cancelled)
+    pub status: &'static str,
+    pub total: usize,
+    pub pending: usize,
+    pub done: usize,
+    pub error: usize,
+    pub cancelled: usize,
+    /// In the order they were submitted
+    pub jobs: Vec<BatchJob>,
+}
+
+impl BatchStatus {
+    fn new(batch_id: &str, jobs: Vec<BatchJob>) -> Self {
+        let count = |status: &str| jobs.iter().filter(|job| job.summary.status == status).count();
+        let (pending, done, error, cancelled) = (count("pending"), count("done"), count("error"), count("cancelled"));
+        Self {
+            batch_id: batch_id.to_string(),
+            status: if pending > 0 { "pending" } else { "done" },
+            total: jobs.len(),
+            pending,
+            done,
+            error,
+            cancelled,
+            jobs,
+        }
+    }
+}
+
+/// What became of a submitted job
+#[derive(Debug, PartialEq)]
+pub enum Submitted {
+    Queued,
+    /// The account already has a job proving the same, done or still pending, with this id
+    Existing(String),
+}
+
+/// Why a job couldn't be queued
+#[derive(Debug, PartialEq)]
+pub enum SubmitError {
+    /// The account already has this many jobs queued or running
+    TooManyJobs(usize),
+}
+
+/// Outcome of cancelling a job
+#[derive(Debug, PartialEq)]
+pub enum Cancelled {
+    /// Removed from the queue before it started
+    Queued,
+    /// Already proving; its proof will be discarded
+    Running,
+    /// Already done, failed or cancelled
+    Finished,
+}
+
+/// A job a worker has taken
+struct RunningJob {
+    account: String,
+    mode: ProofMode,
+    started: Instant,
+    stage: ProofStage,
+    cycles: Option<u64>,
+    /// When proving started, and how long it should take
+    proving: Option<(Instant, ProvingEstimate)>,
+}
+
+impl RunningJob {
+    /// Pending status with the elapsed and estimated time, for progress bars; the SDK proves
+    /// and wraps in one call, so the switch to wrapping is timed from the estimate
+    fn status(&self) -> ProofJobStatus {
+        let mut stage = self.stage;
+        let mut estimated_seconds = None;
+        if let Some((since, estimate)) = self.proving {
+            if self.mode != ProofMode::Core && since.elapsed() > estimate.proving {
+                stage = ProofStage::Wrapping;
+            }
+            estimated_seconds = Some((since - self.started + estimate.proving + estimate.wrapping).as_secs());
+        }
+        ProofJobStatus::Pending {
+            stage,
+            cycles: self.cycles,
+            estimated_seconds,
+            elapsed_seconds: self.started.elapsed().as_secs(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// Every job; while one is running, its status only says that it's pending
+    jobs: HashMap<String, JobRecord>,
+    /// Waiting jobs, in the order they'll run
+    queued: Vec<ProofJob>,
+    running: HashMap<String, RunningJob>,
+}
+
+/// Status of a job that hasn't left the queue
+fn queued_status() -> ProofJobStatus {
+    ProofJobStatus::Pending {
+        stage: ProofStage::Queued,
+        cycles: None,
+        estimated_seconds: None,
+        elapsed_seconds: 0,
+    }
+}
+
+/// Jobs waiting, highest priority first and FIFO within a priority, for one of a fixed number
+/// of workers, as a proof takes minutes of CPU and gigabytes of memory; each account can only
+/// have a few jobs queued or running at once
+pub struct ProofQueue {
+    state: Mutex<QueueState>,
+    /// Wakes a worker when a job is queued
+    queued: Notify,
+    max_jobs_per_user: usize,
+    store: Option<JobStore>,
+    /// Bumped whenever a job is queued, changes stage or finishes
+    updates: watch::Sender<u64>,
+    /// Set on shutdown, after which workers take no more jobs
+    closing: AtomicBool,
+    /// Sends jobs' callbacks, when a secret to sign them with is configured
+    callbacks: Option<Callbacks>,
+}
+
+/// Wallets a ledger belongs to, sorted and comma-separated
+fn ledger_wallets(input: &TaxInput) -> String {
+    let mut wallets: Vec<&str> = input.ledger.iter().map(|row| row.owner_wallet.as_str()).collect();
+    wallets.sort_unstable();
+    wallets.dedup();
+    wallets.join(",")
+}
+
+impl ProofQueue {
+    pub fn new(max_jobs_per_user: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState::default()),
+            queued: Notify::new(),
+            max_jobs_per_user,
+            store: None,
+            updates: watch::Sender::new(0),
+            closing: AtomicBool::new(false),
+            callbacks: None,
+        }
+    }
+
+    /// Call jobs' callback URLs when they finish
+    pub fn with_callbacks(mut self, callbacks: Callbacks) -> Self {
+        self.callbacks = Some(callbacks);
+        self
+    }
+
+    /// Whether jobs can have callback URLs
+    pub fn sends_callbacks(&self) -> bool {
+        self.callbacks.is_some()
+    }
+
+    fn call_back(&self, record: &JobRecord, url: String) {
+        if let Some(callbacks) = &self.callbacks {
+            callbacks.send(url, record);
+        }
+    }
+
+    /// Keep jobs in a store, loading the ones it already has: finished jobs are kept, and
+    /// pending ones the restart interrupted are queued again, in the order they would have run
+    pub fn with_store(mut self, store: JobStore) -> Self {
+        let state = self.state.get_mut();
+        for StoredJob { mut record, input, parts } in store.load() {
+            if matches!(record.status, ProofJobStatus::Pending { .. }) {
+                match input {
+                    Some(input) => {
+                        record.status = queued_status();
+                        state.queued.push(record.job(input, parts));
+                    }
+                    None => {
+                        record.status = ProofJobStatus::Error {
+                            error: "Interrupted by a restart".to_string(),
+                        };
+                        record.finished_at = Some(unix_now());
+                        put(&store, &record, None);
+                    }
+                }
+            }
+            state.jobs.insert(record.id.clone(), record);
+        }
+
+        let jobs = &state.jobs;
+        state.queued.sort_by_key(|job| (Reverse(job.priority), jobs[&job.id].submitted_at));
+        if !state.queued.is_empty() {
+            tracing::info!("Queued {} proof jobs again after a restart", state.queued.len());
+        }
+        self.store = Some(store);
+        self
+    }
+
+    /// Keep a job in the store, if there is one
+    fn persist(&self, record: &JobRecord, job: Option<&ProofJob>) {
+        if let Some(store) = &self.store {
+            put(store, record, job);
+        }
+    }
+
+    /// Whether jobs outlive a restart
+    pub fn persists(&self) -> bool {
+        self.store.is_some()
+    }
+
+    fn unpersist(&self, id: &str) {
+        if let Some(Err(e)) = self.store.as_ref().map(|store| store.remove(id)) {
+            tracing::warn!("Failed to delete stored proof job {}: {}", id, e);
+        }
+    }
+
+    /// Queue a job behind the jobs of the same or higher priority, unless the account has
+    /// one proving the same (with the same key) already, done or still pending, so asking
+    /// again after a page refresh doesn't prove it twice
+    pub async fn submit(&self, job: ProofJob) -> Result<Submitted, SubmitError> {
+        let mut state = self.state.lock().await;
+        if let Some(existing) = same_proof(&state, &job.account, &job.proof_key) {
+            let id = existing.id.clone();
+            if let Some(url) = job.callback_url {
+                self.add_callback(&mut state, &id, url);
+            }
+            return Ok(Submitted::Existing(id));
+        }
+        let active = active_jobs(&state, &job.account);
+        if active >= self.max_jobs_per_user {
+            return Err(SubmitError::TooManyJobs(active));
+        }
+        self.enqueue(&mut state, job, unix_now());
+        Ok(Submitted::Queued)
+    }
+
+    /// Call `url` back too when a job finishes (see `callbacks`), or now if it has
+    fn add_callback(&self, state: &mut QueueState, id: &str, url: String) {
+        let Some(record) = state.jobs.get_mut(id) else {
+            return;
+        };
+        if !matches!(record.status, ProofJobStatus::Pending { .. }) {
+            self.call_back(record, url);
+            return;
+        }
+        if !record.callbacks.contains(&url) {
+            record.callbacks.push(url);
+        }
+        // A running job's input is only in its stored file, which keeps the callbacks it had
+        if let Some(queued) = state.queued.iter().find(|queued| queued.id == id) {
+            self.persist(record, Some(queued));
+        }
+    }
+
+    /// Queue a batch of jobs (an accountant proving each of their clients), all or none of
+    /// them: none if any account would have too many. Each is still an ordinary job; the
+    /// batch only groups them, for its status and its bundle of proofs
+    pub async fn submit_batch(&self, jobs: Vec<ProofJob>) -> Result<(), SubmitError> {
+        let mut state = self.state.lock().await;
+        let mut batched: HashMap<&str, usize> = HashMap::new();
+        for job in &jobs {
+            *batched.entry(&job.account).or_default() += 1;
+        }
+        for (account, count) in batched {
+            let active = active_jobs(&state, account);
+            if active + count > self.max_jobs_per_user {
+                return Err(SubmitError::TooManyJobs(active));
+            }
+        }
+
+        let submitted_at = unix_now();
+        for job in jobs {
+            self.enqueue(&mut state, job, submitted_at);
+        }
+        Ok(())
+    }
+
+    /// Insert a job behind the queued jobs of the same or higher priority
+    fn enqueue(&self, state: &mut QueueState, job: ProofJob, submitted_at: u64) {
+        let position = state.queued.iter().take_while(|queued| queued.priority >= job.priority).count();
+        let record = job.record(submitted_at);
+        self.persist(&record, Some(&job));
+        state.jobs.insert(job.id.clone(), record);
+        state.queued.insert(position, job);
+        self.queued.notify_one();
+        self.updated();
+    }
+
+    /// Wakes whenever a job is queued, changes stage or finishes; statuses then have to be
+    /// read again, as the places of the other queued jobs may have changed too
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.updates.subscribe()
+    }
+
+    fn updated(&self) {
+        self.updates.send_modify(|version| *version += 1);
+    }
+
+    /// Status of an account's job, with its place in the queue (1 = next) while it waits
+    pub async fn status(&self, id: &str, account: &str) -> Option<(ProofJobStatus, Option<usize>)> {
+        let state = self.state.lock().await;
+        let record = state.jobs.get(id).filter(|record| record.account == account)?;
+        let status = match state.running.get(id) {
+            Some(running) => running.status(),
+            None => record.status.clone(),
+        };
+        let position = state.queued.iter().position(|job| job.id == id).map(|index| index + 1);
+        Some((status, position))
+    }
+
+    /// Cancel an account's job; None if it has no such job. A running job can't be
+    /// interrupted, so only its proof is discarded
+    pub async fn cancel(&self, id: &str, account: &str) -> Option<Cancelled> {
+        let mut state = self.state.lock().await;
+        let record = state.jobs.get_mut(id).filter(|record| record.account == account)?;
+        if !matches!(record.status, ProofJobStatus::Pending { .. }) {
+            return Some(Cancelled::Finished);
+        }
+        record.status = ProofJobStatus::Cancelled;
+        record.finished_at = Some(unix_now());
+        self.persist(record, None);
+        self.updated();
+
+        if state.running.remove(id).is_some() {
+            return Some(Cancelled::Running);
+        }
+        state.queued.retain(|job| job.id != id);
+        Some(Cancelled::Queued)
+    }
+
+    /// Delete an account's finished job and its proof; false if it has no such job, or the
+    /// job is still pending
+    pub async fn purge(&self, id: &str, account: &str) -> bool {
+        let mut state = self.state.lock().await;
+        let finished = state.jobs.get(id).is_some_and(|record| {
+            record.account == account && !matches!(record.status, ProofJobStatus::Pending { .. })
+        });
+        if finished {
+            state.jobs.remove(id);
+            self.unpersist(id);
+        }
+        finished
+    }
+
+    /// Drop the jobs that finished longer than `retention` ago; returns how many
+    pub async fn expire(&self, retention: Duration) -> usize {
+        let mut state = self.state.lock().await;
+        let now = unix_now();
+        let expired: Vec<String> = state
+            .jobs
+            .values()
+            .filter(|record| !matches!(record.status, ProofJobStatus::Pending { .. }))
+            .filter(|record| record.finished_at.unwrap_or(record.submitted_at) + retention.as_secs() <= now)
+            .map(|record| record.id.clone())
+            .collect();
+        for id in &expired {
+            state.jobs.remove(id);
+            self.unpersist(id);
+        }
+        expired.len()
+    }
+
+    /// An account's batch's jobs and how many are in each status; None if it has no such batch
+    pub async fn batch(&self, id: &str, account: &str) -> Option<BatchStatus> {
+        let state = self.state.lock().await;
+        let jobs: Vec<BatchJob> = batch_jobs(&state, id, account)
+            .into_iter()
+            .map(|(record, status)| BatchJob {
+                label: record.batch.as_ref().and_then(|batch| batch.label.clone()),
+                summary: JobSummary::new(record, &status),
+            })
+            .collect();
+        (!jobs.is_empty()).then(|| BatchStatus::new(id, jobs))
+    }
+
+    /// An account's batch's jobs with their statuses, proofs included
+    pub async fn batch_results(&self, id: &str, account: &str) -> Vec<(JobRecord, ProofJobStatus)> {
+        let state = self.state.lock().await;
+        batch_jobs(&state, id, account).into_iter().map(|(record, status)| (record.clone(), status)).collect()
+    }
+
+    /// Jobs queued, and jobs running
+    pub async fn depth(&self) -> (usize, usize) {
+        let state = self.state.lock().await;
+        (state.queued.len(), state.running.len())
+    }
+
+    /// Jobs matching a filter, newest first
+    pub async fn list(&self, filter: &JobFilter) -> Vec<JobSummary> {
+        let state = self.state.lock().await;
+        let mut records: Vec<&JobRecord> = state.jobs.values().filter(|record| filter.matches(record)).collect();
+        records.sort_by_key(|record| Reverse(record.submitted_at));
+        records
+            .into_iter()
+            .map(|record| match state.running.get(&record.id) {
+                Some(running) => JobSummary::new(record, &running.status()),
+                None => JobSummary::new(record, &record.status),
+            })
+            .collect()
+    }
+
+    /// Wait for the next job and mark it running; None once the queue is shutting down
+    async fn next(&self) -> Option<ProofJob> {
+        loop {
+            // Waiting from before the check, so a shutdown in between still wakes us
+            let notified = self.queued.notified();
+            if self.is_closing() {
+                return None;
+            }
+            {
+                let mut state = self.state.lock().await;
+                if !state.queued.is_empty() {
+                    let job = state.queued.remove(0);
+                    let running = RunningJob {
+                        account: job.account.clone(),
+                        mode: job.mode,
+                        started: Instant::now(),
+                        stage: ProofStage::Executing,
+                        cycles: None,
+                        proving: None,
+                    };
+                    state.running.insert(job.id.clone(), running);
+                    self.updated();
+                    return Some(job);
+                }
+            }
+            notified.await;
+        }
+    }
+
+    /// Stop the workers taking queued jobs, for a shutdown (they stay stored for the next
+    /// start); subscribers are woken to see it
+    pub fn shutdown(&self) {
+        self.closing.store(true, Ordering::Relaxed);
+        self.queued.notify_waiters();
+        self.updated();
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.closing.load(Ordering::Relaxed)
+    }
+
+    /// Wait up to `grace` for the running jobs to finish; returns the ids of those that
+    /// didn't, whose stored input queues them again on the next start
+    pub async fn drain(&self, grace: Duration) -> Vec<String> {
+        let deadline = tokio::time::Instant::now() + grace;
+        let mut updates = self.subscribe();
+        loop {
+            {
+                let state = self.state.lock().await;
+                if state.running.is_empty() || tokio::time::Instant::now() >= deadline {
+                    return state.running.keys().cloned().collect();
+                }
+            }
+            let _ = tokio::time::timeout_at(deadline, updates.changed()).await;
+        }
+    }
+
+    /// Record that a running job was executed and is now being proved
+    async fn executed(&self, id: &str, cycles: u64, estimate: ProvingEstimate) {
+        let mut state = self.state.lock().await;
+        if let Some(running) = state.running.get_mut(id) {
+            running.stage = ProofStage::Proving;
+            running.cycles = Some(cycles);
+            running.proving = Some((Instant::now(), estimate));
+            self.updated();
+        }
+    }
+
+    /// Record a running job's outcome, unless it was cancelled meanwhile
+    async fn finish(&self, id: &str, status: ProofJobStatus) {
+        let mut state = self.state.lock().await;
+        if state.running.remove(id).is_some() {
+            if let Some(record) = state.jobs.get_mut(id) {
+                record.status = status;
+                record.finished_at = Some(unix_now());
+                self.persist(record, None);
+                self.updated();
+                for url in record.callbacks.clone() {
+                    self.call_back(record, url);
+                }
+            }
+        }
+    }
+
+    /// Drop jobs past their retention every hour
+    pub fn spawn_expiry(self: &Arc<Self>, retention: Duration) {
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+            loop {
+                interval.tick().await;
+                let expired = queue.expire(retention).await;
+                if expired > 0 {
+                    tracing::info!("Dropped {} proof jobs past their retention", expired);
+                }
+            }
+        });
+    }
+
+    /// Start `workers` workers proving queued jobs
+    pub fn spawn_workers(self: &Arc<Self>, prover: Arc<TaxProver>, workers: usize) {
+        for _ in 0..workers {
+            let queue = self.clone();
+            let prover = prover.clone();
+            tokio::spawn(async move {
+                while let Some(job) = queue.next().await {
+                    let (id, mode) = (job.id.clone(), job.mode);
+                    tracing::info!("Starting proof generation for job {}", id);
+                    METRICS.proof_started();
+                    let started = Instant::now();
+                    let status = prove(&queue, prover.clone(), job)
+                        .instrument(tracing::info_span!("proof_job", id = %id))
+                        .await;
+                    let outcome = if matches!(status, ProofJobStatus::Done { .. }) { "done" } else { "error" };
+                    METRICS.proof_finished(mode, outcome, started.elapsed());
+                    queue.finish(&id, status).await;
+                }
+            });
+        }
+    }
+}
+
+/// An account's batch's jobs in the order they were submitted, with their current statuses
+fn batch_jobs<'a>(state: &'a QueueState, id: &str, account: &str) -> Vec<(&'a JobRecord, ProofJobStatus)> {
+    let mut records: Vec<&JobRecord> = state
+        .jobs
+        .values()
+        .filter(|record| record.account == account && record.batch.as_ref().is_some_and(|batch| batch.id == id))
+        .collect();
+    records.sort_by_key(|record| record.batch.as_ref().map(|batch| batch.index));
+    records
+        .into_iter()
+        .map(|record| match state.running.get(&record.id) {
+            Some(running) => (record, running.status()),
+            None => (record, record.status.clone()),
+        })
+        .collect()
+}
+
+/// An account's newest job with the key that's done, else its newest still pending
+fn same_proof<'a>(state: &'a QueueState, account: &str, key: &str) -> Option<&'a JobRecord> {
+    state
+        .jobs
+        .values()
+        .filter(|record| !key.is_empty() && record.proof_key == key && record.account == account)
+        .filter(|record| matches!(record.status, ProofJobStatus::Pending { .. } | ProofJobStatus::Done { .. }))
+        .max_by_key(|record| (matches!(record.status, ProofJobStatus::Done { .. }), record.submitted_at))
+}
+
+/// Jobs an account has queued or running
+fn active_jobs(state: &QueueState, account: &str) -> usize {
+    state.queued.iter().filter(|queued| queued.account == account).count()
+        + state.running.values().filter(|running| running.account == account).count()
+}
+
+fn put(store: &JobStore, record: &JobRecord, job: Option<&ProofJob>) {
+    let job = StoredJob {
+        record: record.clone(),
+        input: job.map(|job| job.input.clone()),
+        parts: job.map(|job| job.parts.clone()).unwrap_or_default(),
+    };
+    if let Err(e) = store.put(&job) {
+        tracing::warn!("Failed to store proof job {}: {}", record.id, e);
+    }
+}
+
+fn unix_now() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
+/// Execute a job, which fails fast on inputs that can't be proved and gives the cycle count
+/// to estimate proving time from, then prove it; a job in parts is executed part by part and
+/// its parts' proofs aggregated
+async fn prove(queue: &ProofQueue, prover: Arc<TaxProver>, job: ProofJob) -> ProofJobStatus {
+    let ProofJob {
+        id,
+        input,
+        parts,
+        disclosure,
+        mode,
+        backend,
+        user_type_code,
+        used_44ada,
+        assessment_year,
+        ..
+    } = job;
+
+    let (input, parts) = (Arc::new(input), Arc::new(parts));
+
+    // Execute first: a quick failure, and the cycle count to estimate proving time from
+    let execution = {
+        let (prover, input, parts) = (prover.clone(), input.clone(), parts.clone());
+        tokio::task::spawn_blocking(move || {
+            if parts.is_empty() {
+                prover.execute(&input).map(|execution| execution.cycles)
+            } else {
+                parts.iter().map(|part| prover.execute(part).map(|execution| execution.cycles)).sum()
+            }
+        })
+        .await
+    };
+    match execution {
+        Ok(Ok(cycles)) => {
+            tracing::info!("Job {} executes in {} cycles", id, cycles);
+            METRICS.proof_executed(cycles);
+            queue.executed(&id, cycles, mode.estimate(cycles)).await;
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Execution failed for job {}: {}", id, e);
+            return ProofJobStatus::Error {
+                error: format!("Execution failed: {}", e),
+            };
+        }
+        Err(e) => {
+            tracing::error!("Task panic for job {}: {}", id, e);
+            return ProofJobStatus::Error {
+                error: format!("Task panic: {}", e),
+            };
+        }
+    }
+
+    // Run proof generation in blocking task (it's CPU-intensive)
+    let result = tokio::task::spawn_blocking(move || {
+        if parts.is_empty() {
+            prover.prove(&input, disclosure.as_ref(), mode, backend)
+        } else {
+            prover.prove_aggregate(&parts, mode, backend)
+        }
+    })
+    .await;
+
+    match result {
+        Ok(Ok(proof_artifacts)) => {
+            tracing::info!("Proof generated successfully for job {}", id);
+            ProofJobStatus::Done {
+                result: Box::new(ProofResult {
+                    artifacts: proof_artifacts,
+                    user_type_code,
+                    used_44ada,
+                    assessment_year,
+                }),
+            }
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Proof generation failed for job {}: {}", id, e);
+            ProofJobStatus::Error {
+                error: format!("Proof generation failed: {}", e),
+            }
+        }
+        Err(e) => {
+            tracing::error!("Task panic for job {}: {}", id, e);
+            ProofJobStatus::Error {
+                error: format!("Task panic: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, account: &str, priority: JobPriority) -> ProofJob {
+        let input = serde_json::json!({
+            "user_type": "individual",
+            "wallets": [],
+            "ledger": [],
+            "prices": [],
+            "usd_inr_rate": "83",
+            "use_44ada": false,
+        });
+        ProofJob {
+            id: id.to_string(),
+            account: account.to_string(),
+            priority,
+            input: serde_json::from_value(input).unwrap(),
+            parts: Vec::new(),
+            disclosure: None,
+            mode: ProofMode::Core,
+            backend: ProvingBackend::Local,
+            user_type_code: 0,
+            used_44ada: false,
+            assessment_year: 2026,
+            batch: None,
+            proof_key: format!("key-{}", id),
+            callback_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_order_limits_and_cancellation() {
+        let queue = ProofQueue::new(2);
+        let mut updates = queue.subscribe();
+        queue.submit(job("a", "alice", JobPriority::Normal)).await.unwrap();
+        assert!(updates.has_changed().unwrap());
+        updates.mark_unchanged();
+        queue.submit(job("b", "bob", JobPriority::Low)).await.unwrap();
+        queue.submit(job("c", "carol", JobPriority::High)).await.unwrap();
+        queue.submit(job("d", "alice", JobPriority::Normal)).await.unwrap();
+        assert_eq!(
+            queue.submit(job("e", "alice", JobPriority::High)).await,
+            Err(SubmitError::TooManyJobs(2))
+        );
+
+        // High first, then FIFO among Normal, then Low
+        assert_eq!(queue.status("b", "bob").await.unwrap().1, Some(4));
+        assert!(queue.status("b", "0xabc").await.is_none());
+        assert_eq!(queue.cancel("b", "0xabc").await, None);
+        assert_eq!(queue.next().await.unwrap().id, "c");
+        queue.executed("c", 1_000_000, ProofMode::Core.estimate(1_000_000)).await;
+        assert!(matches!(
+            queue.status("c", "carol").await.unwrap().0,
+            ProofJobStatus::Pending {
+                stage: ProofStage::Proving,
+                cycles: Some(1_000_000),
+                ..
+            }
+        ));
+        assert_eq!(queue.status("c", "carol").await.unwrap().1, None);
+        assert_eq!(queue.status("d", "alice").await.unwrap().1, Some(2));
+
+        assert_eq!(queue.cancel("a", "alice").await, Some(Cancelled::Queued));
+        assert_eq!(queue.status("d", "alice").await.unwrap().1, Some(1));
+        assert_eq!(queue.cancel("c", "carol").await, Some(Cancelled::Running));
+        queue.finish("c", ProofJobStatus::Error { error: "late".to_string() }).await;
+        assert!(matches!(queue.status("c", "carol").await.unwrap().0, ProofJobStatus::Cancelled));
+        assert!(matches!(
+            queue.status("d", "alice").await.unwrap().0,
+            ProofJobStatus::Pending {
+                stage: ProofStage::Queued,
+                ..
+            }
+        ));
+        assert_eq!(queue.cancel("c", "carol").await, Some(Cancelled::Finished));
+        assert_eq!(queue.cancel("zzz", "").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_store_keeps_jobs_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("financoor-jobs-{}", std::process::id()));
+        let queue = ProofQueue::new(2).with_store(JobStore::new(&dir));
+        let mut owned = job("a", "alice", JobPriority::Low);
+        owned.input.ledger = vec![serde_json::from_value(serde_json::json!({
+            "chain_id": 1,
+            "owner_wallet": "0xa11ce",
+            "tx_hash": "0x1",
+            "block_time": 1_750_000_000,
+            "asset": "ETH",
+            "amount": "1",
+            "decimals": 18,
+            "direction": "in",
+            "counterparty": null,
+            "category": "income",
+            "confidence": 1.0,
+            "user_override": false,
+        }))
+        .unwrap()];
+        queue.submit(owned).await.unwrap();
+        queue.submit(job("b", "bob", JobPriority::High)).await.unwrap();
+        queue.submit(job("c", "carol", JobPriority::Normal)).await.unwrap();
+        assert_eq!(queue.next().await.unwrap().id, "b");
+        assert_eq!(queue.cancel("c", "carol").await, Some(Cancelled::Queued));
+
+        // The running job and the queued one are queued again, the cancelled one is kept
+        let queue = ProofQueue::new(2).with_store(JobStore::new(&dir));
+        assert_eq!(queue.status("b", "bob").await.unwrap().1, Some(1));
+        assert_eq!(queue.status("a", "alice").await.unwrap().1, Some(2));
+        assert!(matches!(queue.status("c", "carol").await.unwrap().0, ProofJobStatus::Cancelled));
+
+        let filter = JobFilter {
+            account: "alice".to_string(),
+            status: Some("pending".to_string()),
+            wallet: Some("0xA11CE".to_string()),
+            assessment_year: None,
+            batch: None,
+        };
+        let listed = queue.list(&filter).await;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].job_id, "a");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_finished_jobs_expire_or_are_purged() {
+        let dir = std::env::temp_dir().join(format!("financoor-jobs-expiry-{}", std::process::id()));
+        let queue = ProofQueue::new(3).with_store(JobStore::new(&dir));
+        queue.submit(job("a", "alice", JobPriority::High)).await.unwrap();
+        queue.submit(job("b", "alice", JobPriority::Normal)).await.unwrap();
+        queue.submit(job("c", "alice", JobPriority::Low)).await.unwrap();
+        assert_eq!(queue.next().await.unwrap().id, "a");
+        queue.finish("a", ProofJobStatus::Error { error: "failed".to_string() }).await;
+        assert_eq!(queue.cancel("b", "alice").await, Some(Cancelled::Queued));
+
+        // Only finished jobs go, whether purged or expired
+        assert!(!queue.purge("c", "alice").await);
+        assert!(!queue.purge("a", "0xabc").await);
+        assert!(queue.purge("a", "alice").await);
+        assert!(queue.status("a", "alice").await.is_none());
+        assert_eq!(queue.expire(Duration::from_secs(3600)).await, 0);
+        assert_eq!(queue.expire(Duration::ZERO).await, 1);
+
+        let reloaded = ProofQueue::new(3).with_store(JobStore::new(&dir));
+        assert!(reloaded.status("b", "alice").await.is_none());
+        assert_eq!(reloaded.status("c", "alice").await.unwrap().1, Some(1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_is_queued_whole_and_reported_in_order() {
+        let batched = |id: &str, client: &str, index: usize| ProofJob {
+            batch: Some(BatchEntry {
+                id: "x".to_string(),
+                index,
+                label: Some(client.to_string()),
+            }),
+            ..job(id, "firm", JobPriority::Normal)
+        };
+        let queue = ProofQueue::new(2);
+        queue.submit(job("a", "firm", JobPriority::Normal)).await.unwrap();
+
+        // The firm can only have one more job, so none of the batch is queued
+        let batch = vec![batched("b", "bob", 0), batched("c", "carol", 1)];
+        assert_eq!(queue.submit_batch(batch).await, Err(SubmitError::TooManyJobs(1)));
+        assert!(queue.status("b", "firm").await.is_none());
+
+        assert_eq!(queue.cancel("a", "firm").await, Some(Cancelled::Queued));
+        queue.submit_batch(vec![batched("c", "carol", 1), batched("b", "bob", 0)]).await.unwrap();
+        assert_eq!(queue.cancel("c", "firm").await, Some(Cancelled::Queued));
+        let status = queue.batch("x", "firm").await.unwrap();
+        assert_eq!((status.status, status.total, status.pending, status.cancelled), ("pending", 2, 1, 1));
+        assert_eq!(status.jobs[0].summary.job_id, "b");
+        assert_eq!(status.jobs[1].label.as_deref(), Some("carol"));
+        assert!(queue.batch("y", "firm").await.is_none());
+
+        assert_eq!(queue.cancel("b", "firm").await, Some(Cancelled::Queued));
+        assert_eq!(queue.batch("x", "firm").await.unwrap().status, "done");
+        assert_eq!(queue.batch_results("x", "firm").await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_same_proof_reuses_the_job() {
+        let queue = ProofQueue::new(1);
+        let same = |id: &str, account: &str| ProofJob {
+            account: account.to_string(),
+            proof_key: "key".to_string(),
+            ..job(id, account, JobPriority::Normal)
+        };
+        assert_eq!(queue.submit(same("a", "")).await, Ok(Submitted::Queued));
+        // Even past the account's limit, and only within the account
+        let again = ProofJob {
+            callback_url: Some("https://example.com/hooks".to_string()),
+            ..same("b", "")
+        };
+        assert_eq!(queue.submit(again).await, Ok(Submitted::Existing("a".to_string())));
+        // Whoever asked again is called back too
+        assert_eq!(queue.state.lock().await.jobs["a"].callbacks, ["https://example.com/hooks"]);
+        assert_eq!(queue.submit(same("c", "0xabc")).await, Ok(Submitted::Queued));
+
+        // A failed job isn't reused
+        assert_eq!(queue.next().await.unwrap().id, "a");
+        queue.finish("a", ProofJobStatus::Error { error: "failed".to_string() }).await;
+        assert_eq!(queue.submit(same("d", "")).await, Ok(Submitted::Queued));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_running_jobs() {
+        let queue = ProofQueue::new(3);
+        queue.submit(job("a", "alice", JobPriority::Normal)).await.unwrap();
+        queue.submit(job("b", "alice", JobPriority::Normal)).await.unwrap();
+        queue.submit(job("c", "alice", JobPriority::Normal)).await.unwrap();
+        assert_eq!(queue.next().await.unwrap().id, "a");
+        assert_eq!(queue.next().await.unwrap().id, "b");
+
+        // Queued jobs are left queued, and running ones get the grace period
+        queue.shutdown();
+        assert!(queue.next().await.is_none());
+        queue.finish("a", ProofJobStatus::Error { error: "failed".to_string() }).await;
+        assert_eq!(queue.drain(Duration::from_millis(10)).await, ["b"]);
+        assert_eq!(queue.status("c", "alice").await.unwrap().1, Some(1));
+    }
+}
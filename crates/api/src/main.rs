@@ -3,62 +3,362 @@
 //! Axum-based backend for wallet data fetching, categorization, and proof generation.
 
 mod alchemy;
+mod api_keys;
+mod auth;
+mod cache;
+mod callbacks;
+mod chainlink;
+mod chains;
+mod config;
+mod connectors;
 mod ens;
+mod error;
+mod etherscan;
+mod job_store;
+mod jobs;
+mod metrics;
+#[cfg(feature = "ml")]
+mod ml;
+mod prices;
+mod relayer;
+mod reports;
+mod simulate;
+mod storage;
+mod sync;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+use alloy_sol_types::SolType;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    routing::{get, post},
+    body::Bytes,
+    extract::{FromRequestParts, MatchedPath, Path, Query, Request, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        request::Parts,
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{delete, get, patch, post},
     Json, Router,
 };
-use financoor_core::{calculate_tax, categorize_ledger, LedgerRow, PriceEntry, TaxBreakdown, TaxInput, UserType};
-use financoor_prover::TaxProver;
+use financoor_core::{
+    calculate_tax, calculate_tax_by_group, calculate_tax_multi_year, canonical_rate_table, categorize_ledger_with_rules,
+    category_changes, compare_regimes, compute_ledger_commitment, drop_failed_transactions, evm_address_bytes,
+    flag_non_taxable, flag_spam, flag_stablecoin_swaps, flag_wash_transfers, import_ledger_csv, label_counterparties,
+    ledger_commitment, ownership_message, parse_exchange_statement, parse_form_26as_csv, parse_reference_rates_csv,
+    price_date, price_stablecoins, receipt_check_hashes, reconcile_tds, restore_overrides, review_queue, review_row,
+    schedule_fa_period, schedule_fa_rows, split_by_group, split_by_year, validate_input, value_ledger, verify_ownership,
+    AcquisitionLot, Address, AddressLabel, AddressLabels, BridgeMatching, CategorizationRule, CategorizationRules,
+    Category, CategoryChange, ColumnMapping, ContractRegistry, CorporateRegime, Deductions, Direction, Disclosure,
+    ForeignAccount, GroupTaxBreakdown, GstSettings, IndianExchange, IssueCode, KnownContract, LabelSource, LedgerRow,
+    LossCarryForward, ManualIncomeEntry, MultiYearTaxBreakdown, PriceEntry, ReferenceRate, RegimeComparison,
+    ResidentialStatus, ReviewItem, ScheduleFaRow, SpamSettings, StablecoinRegistry, TaxBreakdown, TaxInput,
+    TaxProofPublicValues, TaxRegime, TdsEntry, TdsReconciliation, UserType, Wallet, WalletGroup, WalletSource,
+    WashChain, YearSettings, DEFAULT_ASSESSMENT_YEAR, DEFAULT_REVIEW_THRESHOLD, TAX_PROGRAM_VERSION,
+};
+use financoor_prover::{
+    DecodedPublicValues, NetworkConfig, OnchainCalldata, PartCache, ProgramVersion, ProofArtifacts, ProofMode,
+    ProverConfig, ProverInfo, ProvingBackend, TaxProver, DEFAULT_PART_PROOF_CACHE_DIR,
+};
+use futures::Stream;
 use serde::{Deserialize, Serialize};
-use tower_http::cors::{Any, CorsLayer};
+use sha2::{Digest, Sha256};
+use clap::Parser;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::Instrument;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::alchemy::AlchemyClient;
-use crate::ens::EnsResolver;
+use crate::alchemy::{
+    verify_webhook_signature, AlchemyClient, WebhookNotification, DEFAULT_MAX_CONCURRENT_REQUESTS,
+    DEFAULT_REQUESTS_PER_SECOND,
+};
+use crate::api_keys::{ApiKey, ApiKeys, KeyError, Scope, DEFAULT_RATE_LIMIT_PER_MINUTE};
+use crate::auth::{Account, Sessions, SiweConfig, SESSION_TTL};
+use crate::cache::{TransferCache, DEFAULT_TIP_TTL, DEFAULT_TRANSFER_CACHE_DIR};
+use crate::callbacks::Callbacks;
+use crate::chainlink::ChainlinkClient;
+use crate::chains::{address_kind, AddressKind, ChainProvider, EsploraClient, SolanaClient};
+use crate::config::{Cli, Config};
+use crate::connectors::{trade_source, ApiCredentials};
+use crate::ens::{EnsResolver, ENS_CHAIN_IDS};
+use crate::error::{ApiError, ErrorCode};
+use crate::etherscan::EtherscanClient;
+use crate::job_store::{JobStore, DEFAULT_PROOF_JOB_DIR};
+use crate::jobs::{
+    BatchEntry, BatchStatus, Cancelled, JobFilter, JobPriority, JobSummary, ProofJob, ProofJobStatus, ProofQueue,
+    ProofResult, SubmitError, Submitted, DEFAULT_JOB_RETENTION, DEFAULT_MAX_JOBS_PER_USER,
+};
+use crate::metrics::METRICS;
+use crate::prices::{
+    parse_asset_pairs, CoinGeckoClient, PriceOverrides, PriceProvider, PriceSource, DEFAULT_PRICE_OVERRIDES_PATH,
+};
+use crate::relayer::{Relayer, Submission};
+use crate::reports::{ReportProof, ReportSummary, ReportVersion, TaxReport, VersionSummary};
+use crate::simulate::Scenario;
+use crate::storage::Repository;
+use crate::sync::{SyncState, DEFAULT_SYNC_STATE_PATH};
 
-// ============================================================================
-// PROOF JOB TYPES
-// ============================================================================
+struct AppState {
+    alchemy: AlchemyClient,
+    /// Providers of Solana and Bitcoin wallets; EVM wallets go through `alchemy`
+    solana: SolanaClient,
+    bitcoin: EsploraClient,
+    ens: EnsResolver,
+    /// Daily USD prices for `historical_prices` requests: CoinGecko, or Chainlink for the
+    /// assets `PRICE_SOURCES` says (only with `CHAINLINK_RPC_URL`)
+    coingecko: CoinGeckoClient,
+    chainlink: Option<ChainlinkClient>,
+    price_sources: HashMap<String, PriceSource>,
+    prover: Arc<TaxProver>,
+    jobs: Arc<ProofQueue>,
+    /// Sends finished proofs to `TaxVerifier` (only with `RELAYER_PRIVATE_KEY`)
+    relayer: Option<Relayer>,
+    categorization_rules: RwLock<CategorizationRules>,
+    contracts: RwLock<ContractRegistry>,
+    spam_settings: RwLock<SpamSettings>,
+    /// Name tag lookups for unlabeled counterparties (only with `ETHERSCAN_API_KEY`)
+    etherscan: Option<EtherscanClient>,
+    labels: RwLock<AddressLabels>,
+    /// Counterparties Etherscan has no name tag for, so they aren't looked up again
+    untagged: RwLock<HashSet<(u64, String)>>,
+    /// Most rows a ledger sent to /tax or /proofs can have
+    max_ledger_rows: usize,
+    /// Mode of proof requests that don't choose one
+    default_proof_mode: ProofMode,
+    /// Where accounts' data is kept (None to keep it in memory only), and the anonymous
+    /// account's sync cursors and price overrides
+    storage_dir: Option<PathBuf>,
+    sync_state_path: PathBuf,
+    price_overrides_path: PathBuf,
+    /// Wallets, ledger and sync cursors of each account, loaded when first used
+    accounts: RwLock<HashMap<Account, Arc<AccountData>>>,
+    /// Wallets each stored account watched at startup, so webhooks reach accounts that
+    /// haven't been used since
+    stored_wallets: HashMap<Account, Vec<String>>,
+    sessions: Sessions,
+    /// Turn away requests without a session rather than use the anonymous account
+    require_auth: bool,
+    api_keys: ApiKeys,
+    /// Token the operator issues and revokes API keys, and changes the rules, contracts, labels
+    /// and spam whitelist every account shares, with (only with `API_KEY_ADMIN_TOKEN`)
+    api_key_admin_token: Option<String>,
+    /// Key Alchemy signs webhook notifications with (only with `ALCHEMY_WEBHOOK_SIGNING_KEY`)
+    webhook_signing_key: Option<String>,
+    /// Model consulted for rows the rules leave Unknown (only with `ML_CATEGORIZER`)
+    #[cfg(feature = "ml")]
+    model: Option<Arc<ml::ModelCategorizer>>,
+}
+
+impl AppState {
+    /// Provider of a Solana or Bitcoin wallet; None for EVM wallets, which go through Alchemy
+    fn provider(&self, wallet: &str) -> Option<&dyn ChainProvider> {
+        match address_kind(wallet)? {
+            AddressKind::Evm => None,
+            AddressKind::Solana => Some(&self.solana),
+            AddressKind::Bitcoin => Some(&self.bitcoin),
+        }
+    }
+
+    /// Provider of an asset's historical prices
+    fn price_provider(&self, asset: &str) -> &dyn PriceProvider {
+        match (self.price_sources.get(&asset.to_uppercase()), &self.chainlink) {
+            (Some(PriceSource::Chainlink), Some(chainlink)) => chainlink,
+            _ => &self.coingecko,
+        }
+    }
 
-#[derive(Clone, Serialize)]
-#[serde(tag = "status")]
-enum ProofJobStatus {
-    #[serde(rename = "pending")]
-    Pending,
-    #[serde(rename = "done")]
-    Done { result: ProofResult },
-    #[serde(rename = "error")]
-    Error { error: String },
+    /// Data of an account, loaded from storage the first time it's used
+    async fn account_data(&self, account: &Account) -> Result<Arc<AccountData>, ApiError> {
+        if let Some(data) = self.accounts.read().await.get(account) {
+            return Ok(data.clone());
+        }
+        let mut accounts = self.accounts.write().await;
+        if let Some(data) = accounts.get(account) {
+            return Ok(data.clone());
+        }
+        let data = AccountData::load(
+            self.storage_dir.as_deref(),
+            &self.sync_state_path,
+            &self.price_overrides_path,
+            account,
+        )
+        .map_err(|e| ApiError::new(ErrorCode::Internal, format!("Failed to load the account's data: {}", e)))?;
+        let data = Arc::new(data);
+        accounts.insert(account.clone(), data.clone());
+        Ok(data)
+    }
 }
 
-#[derive(Clone, Serialize)]
-struct ProofResult {
-    ledger_commitment: String,
-    total_tax_paisa: u64,
-    user_type_code: u8,
-    used_44ada: bool,
-    proof: String,
-    public_values: String,
-    vk_hash: String,
+/// Account of a request: its API key's (see `api_key_auth`), or its session's, from its
+/// `Authorization: Bearer <token>` header; without either, the anonymous account if
+/// `REQUIRE_AUTH=false`, else the request is unauthorized
+impl FromRequestParts<Arc<AppState>> for Account {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let unauthorized = |error: &str| {
+            ApiError::new(ErrorCode::Unauthorized, error.to_string())
+        };
+        if let Some(account) = parts.extensions.get::<Account>() {
+            return Ok(account.clone());
+        }
+        match bearer_token(&parts.headers) {
+            Some(token) => state
+                .sessions
+                .account(token)
+                .await
+                .ok_or_else(|| unauthorized("Unknown or expired session: sign in again")),
+            None if state.require_auth => Err(unauthorized("Sign in with one of your wallets first")),
+            None => Ok(Account::default()),
+        }
+    }
 }
 
-type ProofJobs = Arc<RwLock<HashMap<String, ProofJobStatus>>>;
+/// Check a request's `X-API-Key`, if it has one, and make its account the key's
+///
+/// Every request made with a key counts against its rate limit, whether or not the
+/// endpoint has an account.
+async fn api_key_auth(State(state): State<Arc<AppState>>, mut request: Request, next: Next) -> Response {
+    let Some(secret) = request.headers().get("x-api-key") else {
+        return next.run(request).await;
+    };
+    let secret = secret.to_str().unwrap_or_default().trim().to_string();
+    let scope = Scope::of(request.method(), request.uri().path());
+    match state.api_keys.authorize(&secret, scope).await {
+        Ok(account) => {
+            request.extensions_mut().insert(account);
+            next.run(request).await
+        }
+        Err(e) => {
+            let error = match e {
+                KeyError::Unknown | KeyError::Revoked => ApiError::new(ErrorCode::Unauthorized, e.to_string()),
+                KeyError::MissingScope(_) => ApiError::new(ErrorCode::Forbidden, e.to_string()),
+                KeyError::RateLimited(retry_after) => ApiError::new(ErrorCode::RateLimited, e.to_string())
+                    .with_details(serde_json::json!({ "retry_after_secs": retry_after })),
+            };
+            error.into_response()
+        }
+    }
+}
 
-struct AppState {
-    alchemy: AlchemyClient,
-    ens: EnsResolver,
-    prover: Arc<TaxProver>,
-    jobs: ProofJobs,
+/// Run a request in a span tagged with its correlation ID and record its latency
+///
+/// The ID is the request's `X-Request-Id`, or a random one, and is echoed back in the
+/// response's so clients can quote it.
+async fn trace_requests(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 64)
+        .map(str::to_string)
+        .unwrap_or_else(|| hex::encode(rand::random::<[u8; 8]>()));
+    let method = request.method().to_string();
+    // Matched routes keep the metrics' labels few, whatever paths are requested
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+
+    let span = tracing::info_span!("request", id = %id, method = %method, route = %route);
+    let started = std::time::Instant::now();
+    let mut response = next.run(request).instrument(span).await;
+    METRICS.request(&method, &route, response.status().as_u16(), started.elapsed());
+    if let Ok(id) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert("x-request-id", id);
+    }
+    response
+}
+
+/// Token of an `Authorization: Bearer <token>` header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    value.strip_prefix("Bearer ").map(str::trim).filter(|token| !token.is_empty())
+}
+
+/// An account's wallets, with their groups and ownership signatures, its ledger, its sync
+/// cursors and its tax reports
+struct AccountData {
+    /// Ledger from the last `/transfers` call, for the review queue
+    ledger: RwLock<Repository<StoredLedger>>,
+    /// Wallets the user fetched or added, with their groups and ownership signatures
+    wallets: RwLock<Repository<Vec<Wallet>>>,
+    wallet_groups: RwLock<Repository<Vec<WalletGroup>>>,
+    /// Saved tax sessions, with every version of each
+    reports: RwLock<Repository<Vec<TaxReport>>>,
+    /// Last block scanned per wallet, for incremental syncs
+    sync: RwLock<SyncState>,
+    /// Prices set through `PUT /prices`, which replace provider and request prices
+    price_overrides: RwLock<PriceOverrides>,
+}
+
+impl AccountData {
+    /// The anonymous account's data is kept in the storage directory itself (its cursors at
+    /// `SYNC_STATE_PATH` and its prices at `PRICE_OVERRIDES_PATH`), any other account's under
+    /// `accounts/<account>`
+    fn load(
+        storage_dir: Option<&std::path::Path>,
+        sync_state_path: &std::path::Path,
+        price_overrides_path: &std::path::Path,
+        account: &Account,
+    ) -> anyhow::Result<Self> {
+        let (dir, sync_state_path, price_overrides_path) = if account.is_anonymous() {
+            let dir = storage_dir.map(PathBuf::from);
+            (dir, Some(sync_state_path.to_path_buf()), Some(price_overrides_path.to_path_buf()))
+        } else {
+            let dir = storage_dir.map(|dir| dir.join("accounts").join(&account.0));
+            let sync_state_path = dir.as_ref().map(|dir| dir.join("sync_state.json"));
+            let price_overrides_path = dir.as_ref().map(|dir| dir.join("price_overrides.json"));
+            (dir, sync_state_path, price_overrides_path)
+        };
+        Ok(Self {
+            ledger: RwLock::new(Repository::load(dir.as_deref(), "ledger")?),
+            wallets: RwLock::new(Repository::load(dir.as_deref(), "wallets")?),
+            wallet_groups: RwLock::new(Repository::load(dir.as_deref(), "wallet_groups")?),
+            reports: RwLock::new(Repository::load(dir.as_deref(), "reports")?),
+            sync: RwLock::new(SyncState::load(sync_state_path)?),
+            price_overrides: RwLock::new(PriceOverrides::load(price_overrides_path)?),
+        })
+    }
+
+    /// Wallets the account has fetched or synced, whose activity webhooks add to its ledger
+    async fn watched_wallets(&self) -> Vec<String> {
+        let mut wallets: Vec<String> = self.sync.read().await.wallets().map(str::to_string).collect();
+        let stored = self.ledger.read().await;
+        for row in stored.rows.iter().filter(|row| row.chain_id != 0) {
+            if !wallets.iter().any(|wallet| row.owner_wallet == *wallet) {
+                wallets.push(row.owner_wallet.to_string());
+            }
+        }
+        wallets
+    }
+}
+
+/// Categorized ledger and the prices it was fetched with
+#[derive(Default, Serialize, Deserialize)]
+struct StoredLedger {
+    rows: Vec<LedgerRow>,
+    prices: Vec<PriceEntry>,
+    /// Reverted transactions among the rows, from their receipts
+    failed_txs: HashSet<String>,
+    /// Rows imported from CSV, kept when the on-chain transfers are refetched
+    imported: Vec<LedgerRow>,
 }
 
+/// Most Etherscan lookups per `/transfers` request, to stay within the API rate limit
+const MAX_ETHERSCAN_LOOKUPS: usize = 20;
+
 #[derive(Serialize)]
 struct HealthResponse {
     status: &'static str,
@@ -75,12 +375,39 @@ async fn health() -> Json<HealthResponse> {
 #[derive(Deserialize)]
 struct TransfersRequest {
     wallets: Vec<String>,
+    /// Known prices; when given, unpriced tokens the user never sent on are flagged as spam
+    #[serde(default)]
+    prices: Vec<PriceEntry>,
+    /// Treat swaps from one stablecoin to another as internal moves rather than taxable transfers
+    #[serde(default)]
+    stablecoin_swaps_internal: bool,
+    /// Keep the stored rows of wallets not in this request, e.g. when retrying the wallets
+    /// that failed
+    #[serde(default)]
+    merge: bool,
+    /// Only fetch transfers from this time on (unix seconds), e.g. the start of the financial year
+    #[serde(default)]
+    from_time: Option<u64>,
+    /// Only fetch transfers up to this time (unix seconds, inclusive)
+    #[serde(default)]
+    to_time: Option<u64>,
+    /// Leave rows of reverted transactions out of the ledger rather than marking them non-taxable
+    #[serde(default)]
+    drop_failed_txs: bool,
+    /// Fetch each asset's USD price on the days it moved, so rows are valued at their own
+    /// day's price; given prices for the same asset and day take precedence
+    #[serde(default)]
+    historical_prices: bool,
 }
 
 #[derive(Serialize)]
 struct TransfersResponse {
     ledger: Vec<LedgerRow>,
     wallet_counts: Vec<WalletCount>,
+    /// Wallets whose transfers couldn't be fetched; their previously stored rows are kept
+    errors: Vec<WalletError>,
+    /// Prices the ledger was categorized with, including fetched daily prices
+    prices: Vec<PriceEntry>,
 }
 
 #[derive(Serialize)]
@@ -90,258 +417,2727 @@ struct WalletCount {
 }
 
 #[derive(Serialize)]
-struct ErrorResponse {
+struct WalletError {
+    wallet: String,
     error: String,
 }
 
+/// Reject wallets that aren't addresses of a supported chain, e.g. with a mistyped
+/// checksum; they'd otherwise reach providers and the transfer cache's paths
+fn validate_wallets<'a>(
+    wallets: impl IntoIterator<Item = &'a String>,
+) -> Result<(), ApiError> {
+    for wallet in wallets {
+        Address::parse(wallet).map_err(|e| ApiError::new(ErrorCode::ValidationFailed, e.to_string()))?;
+        if address_kind(wallet).is_none() {
+            let message = format!("Not an address of a supported chain: {}", wallet);
+            return Err(ApiError::new(ErrorCode::ValidationFailed, message));
+        }
+    }
+    Ok(())
+}
+
 async fn get_transfers(
     State(state): State<Arc<AppState>>,
+    account: Account,
     Json(payload): Json<TransfersRequest>,
-) -> Result<Json<TransfersResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<TransfersResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
     if payload.wallets.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "No wallets provided".to_string(),
-            }),
-        ));
+        return Err(ApiError::invalid("No wallets provided"));
     }
+    validate_wallets(&payload.wallets)?;
 
-    let mut all_ledger: Vec<LedgerRow> = Vec::new();
-    let mut wallet_counts: Vec<WalletCount> = Vec::new();
+    // Everything up to here is fetched, so later syncs can start after it
+    let head = state
+        .alchemy
+        .block_number()
+        .await
+        .inspect_err(|e| tracing::warn!("Failed to fetch the latest block: {}", e))
+        .ok();
 
+    // A wallet listed twice (or in overlapping groups) is fetched once
+    let mut wallets: Vec<&String> = Vec::new();
     for wallet in &payload.wallets {
-        match state.alchemy.get_transfers(wallet).await {
+        if !wallets.iter().any(|w| w.eq_ignore_ascii_case(wallet)) {
+            wallets.push(wallet);
+        }
+    }
+
+    // Blocks covering the requested time range, looked up once for all wallets
+    let bad_gateway = |e: anyhow::Error| {
+        tracing::error!("Failed to look up blocks by time: {}", e);
+        ApiError::alchemy(&e, "Failed to look up blocks by time")
+    };
+    let from_block = match payload.from_time {
+        Some(from_time) => Some(state.alchemy.block_at(from_time).await.map_err(bad_gateway)?),
+        None => None,
+    };
+    let to_block = match payload.to_time {
+        Some(to_time) => Some(state.alchemy.block_at(to_time + 1).await.map_err(bad_gateway)?.saturating_sub(1)),
+        None => None,
+    };
+    let blocks = match (from_block, to_block.or(head)) {
+        _ if from_block.is_none() && to_block.is_none() => None,
+        (from_block, Some(to_block)) => Some((from_block.unwrap_or(0), to_block)),
+        (from_block, None) => {
+            let head = state.alchemy.block_number().await.map_err(bad_gateway)?;
+            Some((from_block.unwrap_or(0), head))
+        }
+    };
+
+    // Fetch every wallet at once; one failing doesn't fail the others
+    let state_ref = &*state;
+    let in_range = |row: &LedgerRow| {
+        payload.from_time.is_none_or(|from_time| row.block_time >= from_time)
+            && payload.to_time.is_none_or(|to_time| row.block_time <= to_time)
+    };
+    let results = futures::future::join_all(wallets.into_iter().map(|wallet| async move {
+        let result = match (state_ref.provider(wallet), blocks) {
+            // Other chains' providers have no block ranges, so their rows are filtered by time
+            (Some(provider), _) => provider
+                .get_transfers(wallet)
+                .await
+                .map(|rows| rows.into_iter().filter(|row| in_range(row)).collect()),
+            (None, Some((from_block, to_block))) => {
+                state_ref.alchemy.get_transfers_between(wallet, from_block, to_block).await
+            }
+            (None, None) => state_ref.alchemy.get_transfers(wallet).await,
+        };
+        (wallet, result)
+    }))
+    .await;
+
+    let mut all_ledger: Vec<LedgerRow> = Vec::new();
+    let mut wallet_counts: Vec<WalletCount> = Vec::new();
+    let mut errors: Vec<WalletError> = Vec::new();
+    let mut rate_limited = false;
+    // Transfers already taken from an earlier wallet's results; rows sharing a key within one
+    // wallet's results are separate transfers of the same transaction and are all kept
+    let mut seen: HashSet<(String, Address, Direction)> = HashSet::new();
+    for (wallet, result) in results {
+        match result {
             Ok(ledger) => {
-                let count = ledger.len();
+                let ledger: Vec<LedgerRow> = ledger
+                    .into_iter()
+                    .filter(|row| {
+                        !seen.contains(&(row.tx_hash.to_lowercase(), row.owner_wallet.clone(), row.direction))
+                    })
+                    .collect();
+                seen.extend(
+                    ledger
+                        .iter()
+                        .map(|row| (row.tx_hash.to_lowercase(), row.owner_wallet.clone(), row.direction)),
+                );
                 wallet_counts.push(WalletCount {
                     wallet: wallet.clone(),
-                    count,
+                    count: ledger.len(),
                 });
                 all_ledger.extend(ledger);
             }
             Err(e) => {
                 tracing::error!("Failed to fetch transfers for {}: {}", wallet, e);
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: format!("Failed to fetch transfers for {}: {}", wallet, e),
-                    }),
-                ));
+                rate_limited |= e.is::<alchemy::RateLimited>();
+                errors.push(WalletError {
+                    wallet: wallet.clone(),
+                    error: e.to_string(),
+                });
             }
         }
     }
+    if wallet_counts.is_empty() {
+        let failures: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.wallet, e.error)).collect();
+        let message = format!("Failed to fetch transfers: {}", failures.join("; "));
+        let code = if rate_limited { ErrorCode::AlchemyRateLimited } else { ErrorCode::UpstreamFailed };
+        return Err(ApiError::new(code, message));
+    }
 
     // Sort all ledger entries by block time
     all_ledger.sort_by(|a, b| a.block_time.cmp(&b.block_time));
 
-    // Categorize transactions based on heuristics
-    categorize_ledger(&mut all_ledger, &payload.wallets);
+    // Label counterparties so that rules can tell exchanges from other senders
+    lookup_etherscan_labels(&state, &all_ledger, &payload.wallets).await;
+
+    // Reverted transactions move nothing taxable
+    let failed_txs = failed_transactions(&state, &all_ledger).await;
+
+    // Stablecoins count as priced at $1 (prices only matter for spam detection when some were given)
+    let mut prices = payload.prices;
+    data.price_overrides.read().await.merge_into(&mut prices);
+    if payload.historical_prices {
+        let fetched = fetch_daily_prices(&state, &all_ledger, &prices).await;
+        prices.extend(fetched);
+    }
+    if !prices.is_empty() {
+        price_stablecoins(&mut prices, &all_ledger, StablecoinRegistry::bundled());
+    }
+
+    // Stored on-chain rows of the wallets that failed (and, when merging, of wallets not
+    // requested) are kept as they are
+    let (kept, mut wallets, failed_txs) = {
+        let stored = data.ledger.read().await;
+        let requested = |owner: &str| payload.wallets.iter().any(|wallet| wallet.eq_ignore_ascii_case(owner));
+        let failed = |owner: &str| errors.iter().any(|e| e.wallet.eq_ignore_ascii_case(owner));
+        let kept: Vec<LedgerRow> = stored
+            .rows
+            .iter()
+            .filter(|row| {
+                !stored.imported.iter().any(|i| i.tx_hash == row.tx_hash && i.owner_wallet == row.owner_wallet)
+            })
+            .filter(|row| failed(&row.owner_wallet) || (payload.merge && !requested(&row.owner_wallet)))
+            .cloned()
+            .collect();
+        let mut failed_txs = failed_txs;
+        if !kept.is_empty() {
+            failed_txs.extend(stored.failed_txs.iter().cloned());
+        }
+        (kept, payload.wallets.clone(), failed_txs)
+    };
+    // Transfers to the other wallets are still internal
+    for row in &kept {
+        if !wallets.iter().any(|wallet| row.owner_wallet == *wallet) {
+            wallets.push(row.owner_wallet.to_string());
+        }
+    }
+
+    let mut all_ledger = categorize_rows(
+        &state,
+        all_ledger,
+        &wallets,
+        &failed_txs,
+        &prices,
+        payload.stablecoin_swaps_internal,
+    )
+    .await?;
+    if payload.drop_failed_txs {
+        drop_failed_transactions(&mut all_ledger, &failed_txs);
+    }
+    attach_ens_names(&state, &mut all_ledger).await;
+
+    // Merge in imported off-chain rows, keep categories the user already reviewed, then
+    // store the ledger for review
+    let mut stored = data.ledger.write().await;
+    all_ledger.extend(kept);
+    all_ledger.extend(stored.imported.iter().cloned());
+    all_ledger.sort_by_key(|row| row.block_time);
+    restore_overrides(&mut all_ledger, &stored.rows);
+    let imported = std::mem::take(&mut stored.imported);
+    **stored = StoredLedger {
+        rows: all_ledger.clone(),
+        prices: prices.clone(),
+        failed_txs,
+        imported,
+    };
+    stored.save();
+    drop(stored);
+    remember_wallets(&data, &payload.wallets).await;
+
+    // A range ending before the latest block leaves later transfers for a sync to miss
+    if let (Some(head), None) = (head, payload.to_time) {
+        let mut sync = data.sync.write().await;
+        for count in wallet_counts.iter().filter(|count| state.provider(&count.wallet).is_none()) {
+            if let Err(e) = sync.advance(&count.wallet, head) {
+                tracing::warn!("Failed to save the sync cursor for {}: {}", count.wallet, e);
+            }
+        }
+    }
+
+    Ok(Json(TransfersResponse {
+        ledger: all_ledger,
+        wallet_counts,
+        errors,
+        prices,
+    }))
+}
+
+#[derive(Deserialize, Default)]
+struct SyncRequest {
+    /// All the user's wallets, so that transfers between them are recognized
+    #[serde(default)]
+    wallets: Vec<String>,
+    /// Treat swaps from one stablecoin to another as internal moves rather than taxable transfers
+    #[serde(default)]
+    stablecoin_swaps_internal: bool,
+    /// Leave rows of reverted transactions out of the ledger rather than marking them non-taxable
+    #[serde(default)]
+    drop_failed_txs: bool,
+}
+
+#[derive(Serialize)]
+struct SyncResponse {
+    wallet: String,
+    /// First and last block scanned
+    from_block: u64,
+    to_block: u64,
+    /// New rows appended to the stored ledger
+    added: Vec<LedgerRow>,
+}
+
+/// Fetch a wallet's transfers since its last sync and append them to the stored ledger
+///
+/// A wallet never synced, or with no rows in the stored ledger (e.g. after a restart), is
+/// fetched from the first block and its stored rows replaced.
+async fn sync_wallet(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(wallet): Path<String>,
+    payload: Option<Json<SyncRequest>>,
+) -> Result<Json<SyncResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let Json(payload) = payload.unwrap_or_default();
+    let bad_gateway = |e: anyhow::Error, context: String| {
+        let error = ApiError::alchemy(&e, &context);
+        tracing::error!("{}", error.message);
+        error
+    };
+    validate_wallets(std::iter::once(&wallet).chain(&payload.wallets))?;
+    let owned_by = |row: &LedgerRow| row.owner_wallet == wallet;
+    if let Some(provider) = state.provider(&wallet) {
+        return Err(ApiError::invalid(format!(
+            "Wallets fetched through {} have no block cursor; refresh them with /transfers",
+            provider.name()
+        )));
+    }
+
+    let to_block = state
+        .alchemy
+        .block_number()
+        .await
+        .map_err(|e| bad_gateway(e, "Failed to fetch the latest block".to_string()))?;
+    let from_block = {
+        let stored = data.ledger.read().await;
+        let has_rows = stored
+            .rows
+            .iter()
+            .any(|row| owned_by(row) && !stored.imported.iter().any(|i| i.tx_hash == row.tx_hash && owned_by(i)));
+        match data.sync.read().await.last_block(&wallet) {
+            Some(last_block) if has_rows => last_block + 1,
+            _ => 0,
+        }
+    };
+    if from_block > to_block {
+        return Ok(Json(SyncResponse {
+            wallet,
+            from_block,
+            to_block,
+            added: Vec::new(),
+        }));
+    }
+
+    let rows = state
+        .alchemy
+        .get_transfers_between(&wallet, from_block, to_block)
+        .await
+        .map_err(|e| bad_gateway(e, format!("Failed to fetch transfers for {}", wallet)))?;
+
+    let failed_txs = failed_transactions(&state, &rows).await;
+
+    let mut wallets = payload.wallets;
+    if !wallets.iter().any(|w| w.eq_ignore_ascii_case(&wallet)) {
+        wallets.push(wallet.clone());
+    }
+    lookup_etherscan_labels(&state, &rows, &wallets).await;
+    let prices = data.ledger.read().await.prices.clone();
+    let mut rows =
+        categorize_rows(&state, rows, &wallets, &failed_txs, &prices, payload.stablecoin_swaps_internal).await?;
+    if payload.drop_failed_txs {
+        drop_failed_transactions(&mut rows, &failed_txs);
+    }
+    attach_ens_names(&state, &mut rows).await;
+
+    let mut stored = data.ledger.write().await;
+    if from_block == 0 {
+        restore_overrides(&mut rows, &stored.rows);
+        let StoredLedger { rows: stored_rows, imported, .. } = &mut **stored;
+        stored_rows
+            .retain(|row| !owned_by(row) || imported.iter().any(|i| i.tx_hash == row.tx_hash && owned_by(i)));
+    }
+    // The last full fetch may have gone past the cursor
+    let rows = append_new_rows(&mut stored, rows);
+    stored.failed_txs.extend(failed_txs);
+    stored.save();
+    drop(stored);
+    remember_wallets(&data, &wallets).await;
+
+    if let Err(e) = data.sync.write().await.advance(&wallet, to_block) {
+        tracing::warn!("Failed to save the sync cursor for {}: {}", wallet, e);
+    }
+
+    Ok(Json(SyncResponse {
+        wallet,
+        from_block,
+        to_block,
+        added: rows,
+    }))
+}
+
+/// Append rows to the stored ledger, skipping transfers it already has; returns the rows added
+fn append_new_rows(stored: &mut StoredLedger, mut rows: Vec<LedgerRow>) -> Vec<LedgerRow> {
+    rows.retain(|row| {
+        !stored.rows.iter().any(|existing| {
+            existing.tx_hash == row.tx_hash
+                && existing.owner_wallet == row.owner_wallet
+                && existing.direction == row.direction
+                && existing.asset == row.asset
+                && existing.token_id == row.token_id
+        })
+    });
+    stored.rows.extend(rows.iter().cloned());
+    stored.rows.sort_by_key(|row| row.block_time);
+    rows
+}
+
+#[derive(Serialize)]
+struct WebhookResponse {
+    /// New rows appended to the stored ledger
+    added: usize,
+}
+
+/// Append transfers pushed by an Alchemy Address Activity webhook to the stored ledger
+///
+/// The body must be signed with `ALCHEMY_WEBHOOK_SIGNING_KEY`. Only activity of wallets
+/// already in a stored ledger or synced before is kept, and goes to the ledger of each
+/// account watching the wallet; a failure responds with an error so that Alchemy delivers
+/// the notification again.
+async fn alchemy_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<WebhookResponse>, ApiError> {
+    let Some(signing_key) = &state.webhook_signing_key else {
+        return Err(ApiError::new(ErrorCode::NotConfigured, "Webhooks are not configured"));
+    };
+    let signature = headers
+        .get("x-alchemy-signature")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !verify_webhook_signature(signing_key, &body, signature) {
+        return Err(ApiError::new(ErrorCode::Unauthorized, "Invalid webhook signature"));
+    }
+    let notification: WebhookNotification =
+        serde_json::from_slice(&body).map_err(|e| ApiError::invalid(e.to_string()))?;
+
+    // Wallets each account watches: loaded accounts' as they are now, the others' as they
+    // were stored at startup, since only a request can have changed them
+    let loaded: Vec<(Account, Arc<AccountData>)> =
+        state.accounts.read().await.iter().map(|(account, data)| (account.clone(), data.clone())).collect();
+    let mut watched: Vec<(Account, Vec<String>)> = Vec::with_capacity(loaded.len());
+    for (account, data) in &loaded {
+        watched.push((account.clone(), data.watched_wallets().await));
+    }
+    for (account, wallets) in &state.stored_wallets {
+        if !loaded.iter().any(|(loaded, _)| loaded == account) {
+            watched.push((account.clone(), wallets.clone()));
+        }
+    }
+    let mut all_wallets: Vec<String> = Vec::new();
+    for wallet in watched.iter().flat_map(|(_, wallets)| wallets) {
+        if !all_wallets.iter().any(|known| known.eq_ignore_ascii_case(wallet)) {
+            all_wallets.push(wallet.clone());
+        }
+    }
+
+    let rows = state
+        .alchemy
+        .activity_rows(notification.event, &all_wallets)
+        .await
+        .map_err(|e| ApiError::alchemy(&e, "Failed to read webhook activity"))?;
+    if rows.is_empty() {
+        return Ok(Json(WebhookResponse { added: 0 }));
+    }
+
+    lookup_etherscan_labels(&state, &rows, &all_wallets).await;
+    let failed_txs = failed_transactions(&state, &rows).await;
+    let mut added = 0;
+    for (account, wallets) in &watched {
+        let rows: Vec<LedgerRow> = rows
+            .iter()
+            .filter(|row| wallets.iter().any(|wallet| row.owner_wallet == *wallet))
+            .cloned()
+            .collect();
+        if rows.is_empty() {
+            continue;
+        }
+        let data = state.account_data(account).await?;
+        let prices = data.ledger.read().await.prices.clone();
+        let mut rows = categorize_rows(&state, rows, wallets, &failed_txs, &prices, false).await?;
+        attach_ens_names(&state, &mut rows).await;
+
+        let mut stored = data.ledger.write().await;
+        added += append_new_rows(&mut stored, rows).len();
+        stored.failed_txs.extend(failed_txs.iter().cloned());
+        stored.save();
+    }
+    tracing::info!("Added {} rows from an Alchemy webhook", added);
+
+    Ok(Json(WebhookResponse { added }))
+}
+
+/// Reverted transactions among the rows worth checking, from their receipts; none if the
+/// receipts can't be fetched
+async fn failed_transactions(state: &AppState, ledger: &[LedgerRow]) -> HashSet<String> {
+    let tx_hashes = receipt_check_hashes(ledger);
+    if tx_hashes.is_empty() {
+        return HashSet::new();
+    }
+    state.alchemy.failed_transactions(&tx_hashes).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to fetch transaction receipts: {}", e);
+        HashSet::new()
+    })
+}
+
+/// Label and categorize a ledger: rules, then non-taxable noise, the model (with the `ml`
+/// feature), stablecoin swaps and spam. User overrides are left alone.
+async fn categorize_rows(
+    state: &AppState,
+    mut ledger: Vec<LedgerRow>,
+    wallets: &[String],
+    failed_txs: &HashSet<String>,
+    prices: &[PriceEntry],
+    stablecoin_swaps_internal: bool,
+) -> Result<Vec<LedgerRow>, ApiError> {
+    label_counterparties(&mut ledger, &*state.labels.read().await);
+
+    // Categorize transactions with the built-in and user-defined rules
+    categorize_ledger_with_rules(
+        &mut ledger,
+        wallets,
+        &*state.categorization_rules.read().await,
+        &*state.contracts.read().await,
+    );
+
+    // Approvals, reverted transactions and self-sends move nothing taxable
+    flag_non_taxable(&mut ledger, failed_txs);
+
+    #[cfg(feature = "ml")]
+    if let Some(model) = state.model.clone() {
+        let wallets = wallets.to_vec();
+        let contracts = state.contracts.read().await.clone();
+        let categorized = tokio::task::spawn_blocking(move || {
+            let categorized = financoor_core::categorize_unknown_with(&mut ledger, &wallets, &contracts, &*model);
+            (ledger, categorized)
+        })
+        .await
+        .map_err(|e| ApiError::new(ErrorCode::Internal, format!("Model categorization failed: {}", e)))?;
+        ledger = categorized.0;
+        tracing::info!("Model categorized {} rows the rules left unknown", categorized.1);
+    }
+    if stablecoin_swaps_internal {
+        flag_stablecoin_swaps(&mut ledger, StablecoinRegistry::bundled());
+    }
+
+    flag_spam(&mut ledger, prices, &*state.spam_settings.read().await);
+    Ok(ledger)
+}
+
+/// Attach the primary ENS names of counterparties, on chains with ENS
+async fn attach_ens_names(state: &AppState, ledger: &mut [LedgerRow]) {
+    let chain_id = state.alchemy.chain_id();
+    if !ENS_CHAIN_IDS.contains(&chain_id) {
+        return;
+    }
+    let on_chain =
+        |row: &LedgerRow| row.chain_id == chain_id && row.counterparty.as_ref().is_some_and(|cp| cp.is_evm());
+
+    let mut counterparties: Vec<String> = Vec::new();
+    for row in ledger.iter().filter(|row| on_chain(row)) {
+        let counterparty = row.counterparty.as_ref().map(Address::to_string).unwrap_or_default();
+        if !counterparties.contains(&counterparty) {
+            counterparties.push(counterparty);
+        }
+    }
+    let names = state.ens.reverse_resolve(&state.alchemy, &counterparties).await;
+    for row in ledger.iter_mut().filter(|row| on_chain(row)) {
+        let counterparty = row.counterparty.as_deref().unwrap_or_default();
+        row.counterparty_ens = names.get(counterparty).cloned();
+    }
+}
+
+/// Daily USD prices of the ledger's assets on the days they moved, except days `given`
+/// already prices; an asset the provider fails on is left to the given prices
+async fn fetch_daily_prices(state: &AppState, ledger: &[LedgerRow], given: &[PriceEntry]) -> Vec<PriceEntry> {
+    let mut dates: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for row in ledger.iter().filter(|row| row.token_id.is_none()) {
+        let date = price_date(row.block_time);
+        let priced = given.iter().any(|price| {
+            price.asset.eq_ignore_ascii_case(&row.asset) && price.date.as_deref() == Some(date.as_str())
+        });
+        if !priced {
+            dates.entry(row.asset.clone()).or_default().insert(date);
+        }
+    }
+
+    let mut prices = Vec::new();
+    for (asset, dates) in dates {
+        let dates: Vec<String> = dates.into_iter().collect();
+        let provider = state.price_provider(&asset);
+        match provider.daily_prices(&asset, &dates).await {
+            Ok(fetched) => prices.extend(fetched),
+            Err(e) => tracing::warn!("Failed to fetch {} prices from {}: {}", asset, provider.name(), e),
+        }
+    }
+    prices
+}
+
+/// Fetch Etherscan name tags for counterparties without a label
+async fn lookup_etherscan_labels(state: &AppState, ledger: &[LedgerRow], wallets: &[String]) {
+    let Some(etherscan) = &state.etherscan else {
+        return;
+    };
+
+    let mut pending: Vec<(u64, String)> = Vec::new();
+    {
+        let labels = state.labels.read().await;
+        let untagged = state.untagged.read().await;
+        for row in ledger {
+            let Some(counterparty) = row.counterparty.as_ref() else {
+                continue;
+            };
+            let key = (row.chain_id, counterparty.to_string());
+            // Etherscan only knows EVM addresses
+            if !counterparty.is_evm()
+                || wallets.iter().any(|w| counterparty == w)
+                || labels.lookup(row.chain_id, counterparty).is_some()
+                || untagged.contains(&key)
+                || pending.contains(&key)
+            {
+                continue;
+            }
+            pending.push(key);
+        }
+    }
+
+    for (chain_id, address) in pending.into_iter().take(MAX_ETHERSCAN_LOOKUPS) {
+        match etherscan.name_tag(chain_id, &address).await {
+            Ok(Some(nametag)) => {
+                let label = AddressLabel {
+                    address: address.clone(),
+                    label: nametag,
+                    chain_id: Some(chain_id),
+                    source: LabelSource::Etherscan,
+                };
+                if let Err(e) = state.labels.write().await.add(label) {
+                    tracing::warn!("Ignoring Etherscan label for {}: {}", address, e);
+                }
+            }
+            Ok(None) => {
+                state.untagged.write().await.insert((chain_id, address));
+            }
+            Err(e) => {
+                tracing::warn!("Etherscan lookup failed for {}: {}", address, e);
+                break;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// SIGN-IN WITH ETHEREUM
+// ============================================================================
+
+#[derive(Deserialize)]
+struct NonceRequest {
+    /// EVM wallet to sign in with
+    address: String,
+    /// Chain the message names; Ethereum mainnet by default
+    chain_id: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct NonceResponse {
+    nonce: String,
+    /// EIP-4361 message to sign with `personal_sign`
+    message: String,
+}
+
+/// Issue a sign-in message for a wallet to sign
+async fn auth_nonce(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<NonceRequest>,
+) -> Result<Json<NonceResponse>, ApiError> {
+    let address = Address::parse(&payload.address).map_err(tax_error)?;
+    if evm_address_bytes(&address).is_none() {
+        return Err(ApiError::invalid(format!("Sign in with an EVM wallet, not {}", address.as_str())));
+    }
+    let (nonce, message) = state.sessions.issue(address, payload.chain_id.unwrap_or(1)).await;
+    Ok(Json(NonceResponse { nonce, message }))
+}
+
+#[derive(Deserialize)]
+struct SignInRequest {
+    nonce: String,
+    /// `personal_sign` signature of the issued message
+    signature: String,
+}
+
+#[derive(Serialize)]
+struct SignInResponse {
+    /// Session token, to send as `Authorization: Bearer <token>`
+    token: String,
+    /// Address the account is named after, lowercase
+    account: String,
+    /// Seconds until the session expires
+    expires_in: u64,
+}
+
+/// Exchange the signature of a sign-in message for a session token
+async fn auth_verify(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SignInRequest>,
+) -> Result<Json<SignInResponse>, ApiError> {
+    let (token, account) = state
+        .sessions
+        .sign_in(&payload.nonce, &payload.signature)
+        .await
+        .map_err(|e| ApiError::new(ErrorCode::Unauthorized, e.to_string()))?;
+    // Loaded now, so that webhooks reach the account's wallets
+    state.account_data(&account).await?;
+    Ok(Json(SignInResponse {
+        token,
+        account: account.0,
+        expires_in: SESSION_TTL.as_secs(),
+    }))
+}
+
+/// End the request's session
+async fn auth_logout(State(state): State<Arc<AppState>>, headers: HeaderMap) -> StatusCode {
+    if let Some(token) = bearer_token(&headers) {
+        state.sessions.sign_out(token).await;
+    }
+    StatusCode::NO_CONTENT
+}
+
+// ============================================================================
+// API KEYS
+// ============================================================================
+
+#[derive(Deserialize)]
+struct IssueApiKeyRequest {
+    /// Who the key is for, e.g. the accounting firm
+    name: String,
+    scopes: Vec<Scope>,
+    rate_limit_per_minute: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct IssueApiKeyResponse {
+    #[serde(flatten)]
+    key: ApiKey,
+    /// The key itself, to send as `X-API-Key`; it can't be seen again
+    api_key: String,
+}
+
+/// Check a request's `X-Admin-Token` against `API_KEY_ADMIN_TOKEN`
+fn check_admin(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(admin_token) = &state.api_key_admin_token else {
+        return Err(ApiError::new(ErrorCode::NotConfigured, "Admin endpoints are not configured"));
+    };
+    // Digests are compared rather than the tokens, so the time taken says nothing about the token
+    let digest = |token: &str| Sha256::digest(token.as_bytes());
+    let token = headers.get("x-admin-token").and_then(|value| value.to_str().ok());
+    if token.map(digest) != Some(digest(admin_token)) {
+        return Err(ApiError::new(ErrorCode::Unauthorized, "Invalid admin token"));
+    }
+    Ok(())
+}
+
+/// Issue an API key for an integrator
+async fn issue_api_key(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<IssueApiKeyRequest>,
+) -> Result<Json<IssueApiKeyResponse>, ApiError> {
+    check_admin(&state, &headers)?;
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::invalid("An API key needs a name"));
+    }
+    if payload.scopes.is_empty() {
+        return Err(ApiError::invalid("An API key needs at least one scope"));
+    }
+    let rate_limit = payload.rate_limit_per_minute.unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE);
+    if rate_limit == 0 {
+        return Err(ApiError::invalid("rate_limit_per_minute must be at least 1"));
+    }
+    let (key, api_key) = state.api_keys.issue(payload.name, payload.scopes, rate_limit).await;
+    tracing::info!("Issued API key {} to {}", key.id, key.name);
+    Ok(Json(IssueApiKeyResponse { key, api_key }))
+}
+
+/// Every API key issued, revoked ones included
+async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ApiKey>>, ApiError> {
+    check_admin(&state, &headers)?;
+    Ok(Json(state.api_keys.list().await))
+}
+
+/// Revoke an API key; its account's data is kept
+async fn revoke_api_key(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(key_id): Path<String>,
+) -> Result<Json<ApiKey>, ApiError> {
+    check_admin(&state, &headers)?;
+    let key = state.api_keys.revoke(&key_id).await;
+    key.map(Json).ok_or_else(|| ApiError::not_found(format!("API key not found: {}", key_id)))
+}
+
+// ============================================================================
+// WALLETS AND WALLET GROUPS
+// ============================================================================
+
+/// A field that may be given as null, to tell clearing it (`Some(None)`) from leaving it
+/// alone (`None`)
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize)]
+struct AddWalletRequest {
+    address: String,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    group_id: Option<String>,
+    /// How the wallet was found: "manual" (default), "ens_text_record" or "ens_subdomain"
+    #[serde(default = "manual_source")]
+    source: WalletSource,
+    /// The wallet's signature of its ownership message (`GET /proofs/ownership-message`)
+    #[serde(default)]
+    ownership_signature: Option<String>,
+}
+
+fn manual_source() -> WalletSource {
+    WalletSource::Manual
+}
+
+#[derive(Deserialize)]
+struct UpdateWalletRequest {
+    #[serde(default, deserialize_with = "nullable")]
+    label: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    group_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    ownership_signature: Option<Option<String>>,
+}
+
+#[derive(Deserialize)]
+struct AddWalletGroupRequest {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateWalletGroupRequest {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    description: Option<Option<String>>,
+}
+
+/// Check that a wallet's group exists and its ownership signature is the wallet's
+async fn check_wallet(data: &AccountData, wallet: &Wallet) -> Result<(), ApiError> {
+    if let Some(group_id) = &wallet.group_id {
+        if !data.wallet_groups.read().await.iter().any(|group| group.id == *group_id) {
+            return Err(ApiError::not_found(format!("Wallet group not found: {}", group_id)));
+        }
+    }
+    if let Some(signature) = &wallet.ownership_signature {
+        verify_ownership(&wallet.address, signature).map_err(tax_error)?;
+    }
+    Ok(())
+}
+
+/// The user's wallets
+async fn list_wallets(
+    State(state): State<Arc<AppState>>, account: Account,
+) -> Result<Json<Vec<Wallet>>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let wallets = data.wallets.read().await.to_vec();
+    Ok(Json(wallets))
+}
+
+/// Add a wallet, or replace the stored wallet with the same address
+async fn add_wallet(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<AddWalletRequest>,
+) -> Result<Json<Wallet>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let address = Address::parse(&payload.address).map_err(tax_error)?;
+    let mut wallet = Wallet {
+        id: format!("{:x}", rand::random::<u64>()),
+        address,
+        label: payload.label,
+        group_id: payload.group_id,
+        source: payload.source,
+        ownership_signature: payload.ownership_signature,
+    };
+    check_wallet(&data, &wallet).await?;
+
+    let mut wallets = data.wallets.write().await;
+    if let Some(stored) = wallets.iter_mut().find(|stored| stored.address == wallet.address) {
+        wallet.id = stored.id.clone();
+        *stored = wallet.clone();
+    } else {
+        wallets.push(wallet.clone());
+    }
+    wallets.save();
+    Ok(Json(wallet))
+}
+
+/// Change a wallet's label, group or ownership signature; the wallet is named by its ID
+/// or address
+async fn update_wallet(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(wallet): Path<String>,
+    Json(payload): Json<UpdateWalletRequest>,
+) -> Result<Json<Wallet>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let named = |stored: &Wallet| stored.id == wallet || stored.address == wallet;
+    let Some(mut updated) = data.wallets.read().await.iter().find(|stored| named(stored)).cloned() else {
+        return Err(ApiError::not_found(format!("Wallet not found: {}", wallet)));
+    };
+    if let Some(label) = payload.label {
+        updated.label = label;
+    }
+    if let Some(group_id) = payload.group_id {
+        updated.group_id = group_id;
+    }
+    if let Some(signature) = payload.ownership_signature {
+        updated.ownership_signature = signature;
+    }
+    check_wallet(&data, &updated).await?;
+
+    let mut wallets = data.wallets.write().await;
+    let Some(stored) = wallets.iter_mut().find(|stored| stored.id == updated.id) else {
+        return Err(ApiError::not_found(format!("Wallet not found: {}", wallet)));
+    };
+    *stored = updated.clone();
+    wallets.save();
+    Ok(Json(updated))
+}
+
+/// Remove a wallet, named by its ID or address; its ledger rows are kept
+async fn delete_wallet(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(wallet): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let data = state.account_data(&account).await?;
+    let mut wallets = data.wallets.write().await;
+    let count = wallets.len();
+    wallets.retain(|stored| stored.id != wallet && stored.address != wallet);
+    if wallets.len() == count {
+        return Err(ApiError::not_found(format!("Wallet not found: {}", wallet)));
+    }
+    wallets.save();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_wallet_groups(
+    State(state): State<Arc<AppState>>, account: Account,
+) -> Result<Json<Vec<WalletGroup>>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let wallet_groups = data.wallet_groups.read().await.to_vec();
+    Ok(Json(wallet_groups))
+}
+
+async fn add_wallet_group(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<AddWalletGroupRequest>,
+) -> Result<Json<WalletGroup>, ApiError> {
+    let data = state.account_data(&account).await?;
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::invalid("A wallet group needs a name"));
+    }
+    let group = WalletGroup {
+        id: format!("{:x}", rand::random::<u64>()),
+        name: payload.name,
+        description: payload.description,
+    };
+    let mut groups = data.wallet_groups.write().await;
+    groups.push(group.clone());
+    groups.save();
+    Ok(Json(group))
+}
+
+async fn update_wallet_group(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(group_id): Path<String>,
+    Json(payload): Json<UpdateWalletGroupRequest>,
+) -> Result<Json<WalletGroup>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let mut groups = data.wallet_groups.write().await;
+    let Some(group) = groups.iter_mut().find(|group| group.id == group_id) else {
+        return Err(ApiError::not_found(format!("Wallet group not found: {}", group_id)));
+    };
+    if let Some(name) = payload.name.filter(|name| !name.trim().is_empty()) {
+        group.name = name;
+    }
+    if let Some(description) = payload.description {
+        group.description = description;
+    }
+    let group = group.clone();
+    groups.save();
+    Ok(Json(group))
+}
+
+/// Remove a wallet group; its wallets are left in no group
+async fn delete_wallet_group(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(group_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let data = state.account_data(&account).await?;
+    let mut groups = data.wallet_groups.write().await;
+    let count = groups.len();
+    groups.retain(|group| group.id != group_id);
+    if groups.len() == count {
+        return Err(ApiError::not_found(format!("Wallet group not found: {}", group_id)));
+    }
+    groups.save();
+    drop(groups);
+
+    let mut wallets = data.wallets.write().await;
+    let mut ungrouped = false;
+    for wallet in wallets.iter_mut().filter(|wallet| wallet.group_id.as_ref() == Some(&group_id)) {
+        wallet.group_id = None;
+        ungrouped = true;
+    }
+    if ungrouped {
+        wallets.save();
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// LEDGER REVIEW
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ReviewQuery {
+    /// Rows categorized below this confidence are listed
+    #[serde(default = "default_review_threshold")]
+    threshold: f32,
+    /// USD/INR rate used to value rows for ordering (the calculator's default when omitted)
+    #[serde(default)]
+    usd_inr_rate: Option<String>,
+}
+
+fn default_review_threshold() -> f32 {
+    DEFAULT_REVIEW_THRESHOLD
+}
+
+#[derive(Serialize)]
+struct ReviewResponse {
+    rows: Vec<ReviewItem>,
+}
+
+/// Unreviewed rows of the stored ledger that are Unknown or low-confidence, largest INR value first
+async fn get_review_queue(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Query(query): Query<ReviewQuery>,
+) -> Result<Json<ReviewResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let stored = data.ledger.read().await;
+    Ok(Json(ReviewResponse {
+        rows: review_queue(
+            &stored.rows,
+            &stored.prices,
+            query.usd_inr_rate.as_deref().unwrap_or_default(),
+            query.threshold,
+        ),
+    }))
+}
+
+#[derive(Deserialize)]
+struct ReviewRowRequest {
+    /// New category; omit to accept the current one
+    #[serde(default)]
+    category: Option<Category>,
+}
+
+/// Accept or override the category of a stored ledger row (marks it `user_override`)
+async fn review_ledger_row(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(row_id): Path<usize>,
+    Json(payload): Json<ReviewRowRequest>,
+) -> Result<Json<LedgerRow>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let mut stored = data.ledger.write().await;
+    let Some(row) = stored.rows.get_mut(row_id) else {
+        return Err(ApiError::not_found(format!("Ledger row not found: {}", row_id)));
+    };
+    review_row(row, payload.category);
+    let row = row.clone();
+    stored.save();
+    Ok(Json(row))
+}
+
+#[derive(Deserialize)]
+struct ImportLedgerRequest {
+    /// Exchange export or manual records
+    csv: String,
+    mapping: ColumnMapping,
+    /// User wallets, so that transfers to or from them are recognized
+    #[serde(default)]
+    wallets: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ImportLedgerResponse {
+    imported: usize,
+    /// The stored ledger with the imported rows merged in
+    ledger: Vec<LedgerRow>,
+}
+
+/// Import off-chain ledger rows from CSV, categorize them and merge them into the stored
+/// ledger; importing the same rows again replaces them
+async fn import_ledger(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<ImportLedgerRequest>,
+) -> Result<Json<ImportLedgerResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    validate_wallets(&payload.wallets)?;
+    let rows = import_ledger_csv(&payload.csv, &payload.mapping).map_err(|e| ApiError::invalid(e.to_string()))?;
+
+    // Explorer exports can list reverted transactions too
+    let failed_txs = failed_transactions(&state, &rows).await;
+    let prices = data.ledger.read().await.prices.clone();
+    let rows = categorize_rows(&state, rows, &payload.wallets, &failed_txs, &prices, false).await?;
+
+    let mut stored = data.ledger.write().await;
+    let imported = rows.len();
+    stored.failed_txs.extend(failed_txs);
+    merge_imported(&mut stored, rows);
+    stored.save();
+
+    Ok(Json(ImportLedgerResponse {
+        imported,
+        ledger: stored.rows.clone(),
+    }))
+}
+
+/// Store wallets the user fetched that aren't stored yet
+async fn remember_wallets<'a>(data: &AccountData, wallets: impl IntoIterator<Item = &'a String>) {
+    let mut stored = data.wallets.write().await;
+    let count = stored.len();
+    for wallet in wallets {
+        if !stored.iter().any(|stored| stored.address == *wallet) {
+            stored.push(Wallet {
+                id: format!("{:x}", rand::random::<u64>()),
+                address: Address::from(wallet.as_str()),
+                label: None,
+                group_id: None,
+                source: WalletSource::Manual,
+                ownership_signature: None,
+            });
+        }
+    }
+    if stored.len() > count {
+        stored.save();
+    }
+}
+
+/// The stored wallets a ledger has rows for, for a proof request that doesn't give its own
+async fn stored_wallets(data: &AccountData, ledger: &[LedgerRow]) -> Vec<Wallet> {
+    let stored = data.wallets.read().await;
+    stored
+        .iter()
+        .filter(|wallet| ledger.iter().any(|row| row.owner_wallet == wallet.address))
+        .cloned()
+        .collect()
+}
+
+/// Add off-chain rows to the stored ledger, replacing earlier imports of the same rows
+fn merge_imported(stored: &mut StoredLedger, rows: Vec<LedgerRow>) {
+    let reimported =
+        |row: &LedgerRow| rows.iter().any(|new| new.tx_hash == row.tx_hash && new.owner_wallet == row.owner_wallet);
+    stored.imported.retain(|row| !reimported(row));
+    stored.rows.retain(|row| !reimported(row));
+    stored.imported.extend(rows.iter().cloned());
+    stored.rows.extend(rows);
+    stored.rows.sort_by_key(|row| row.block_time);
+}
+
+#[derive(Deserialize)]
+struct StatementImportRequest {
+    /// Trade history or TDS report exported from the exchange
+    csv: String,
+}
+
+#[derive(Serialize)]
+struct StatementImportResponse {
+    imported: usize,
+    /// 194S TDS the exchange deducted, to be passed to /tax as TDS credit
+    tds_entries: Vec<TdsEntry>,
+}
+
+/// Import a WazirX, CoinDCX or ZebPay statement into the stored ledger and extract the
+/// TDS deducted on its trades
+async fn import_exchange_statement(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(exchange): Path<String>,
+    Json(payload): Json<StatementImportRequest>,
+) -> Result<Json<StatementImportResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let exchange: IndianExchange = serde_json::from_value(serde_json::Value::String(exchange.to_lowercase()))
+        .map_err(|_| ApiError::invalid(format!("Unsupported exchange: {}", exchange)))?;
+    let statement = parse_exchange_statement(exchange, &payload.csv).map_err(|e| ApiError::invalid(e.to_string()))?;
+
+    let imported = statement.ledger.len();
+    let mut stored = data.ledger.write().await;
+    merge_imported(&mut stored, statement.ledger);
+    stored.save();
+    drop(stored);
+
+    Ok(Json(StatementImportResponse {
+        imported,
+        tds_entries: statement.tds_entries,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ExchangeImportRequest {
+    #[serde(flatten)]
+    credentials: ApiCredentials,
+    /// Trading pairs to fetch fills for, where the exchange needs them (Binance)
+    #[serde(default)]
+    symbols: Vec<String>,
+    /// Only activity from this time on (unix seconds)
+    #[serde(default)]
+    since: u64,
+    /// Converts dollar-valued purchases to INR for their cost basis
+    usd_inr_rate: String,
+}
+
+#[derive(Serialize)]
+struct ExchangeImportResponse {
+    imported: usize,
+    /// Cost basis of the assets bought on the exchange
+    acquisition_lots: Vec<AcquisitionLot>,
+}
+
+/// Fetch an exchange account's trades, deposits and withdrawals with a read-only API key
+/// and merge them into the stored ledger
+async fn import_exchange(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(exchange): Path<String>,
+    Json(payload): Json<ExchangeImportRequest>,
+) -> Result<Json<ExchangeImportResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let usd_inr_rate: f64 = payload
+        .usd_inr_rate
+        .parse()
+        .map_err(|_| ApiError::invalid(format!("Invalid USD/INR rate: {}", payload.usd_inr_rate)))?;
+    let source =
+        trade_source(&exchange, payload.credentials, payload.symbols).map_err(|e| ApiError::invalid(e.to_string()))?;
+
+    let records = source.fetch(payload.since).await.map_err(|e| {
+        tracing::error!("Failed to fetch {} activity: {}", exchange, e);
+        ApiError::new(ErrorCode::UpstreamFailed, format!("Failed to fetch {} activity: {}", exchange, e))
+    })?;
+
+    let acquisition_lots = connectors::acquisition_lots(&records, usd_inr_rate);
+    let rows: Vec<LedgerRow> = records.into_iter().map(|record| record.row).collect();
+    let imported = rows.len();
+    let mut stored = data.ledger.write().await;
+    merge_imported(&mut stored, rows);
+    stored.save();
+    drop(stored);
+
+    Ok(Json(ExchangeImportResponse {
+        imported,
+        acquisition_lots,
+    }))
+}
+
+#[derive(Deserialize)]
+struct RecategorizeRequest {
+    /// User wallets, including any added since the ledger was fetched
+    wallets: Vec<String>,
+    /// Treat swaps from one stablecoin to another as internal moves rather than taxable transfers
+    #[serde(default)]
+    stablecoin_swaps_internal: bool,
+    /// Keep the new categories; without it the changes are only reported
+    #[serde(default)]
+    confirm: bool,
+}
+
+#[derive(Serialize)]
+struct RecategorizeResponse {
+    changes: Vec<CategoryChange>,
+    /// Whether the stored ledger was updated
+    committed: bool,
+}
+
+/// Re-run the current labels and rules over the stored ledger (reviewed rows are kept)
+/// and report which categories change; they're only stored when `confirm` is set
+async fn recategorize_ledger(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<RecategorizeRequest>,
+) -> Result<Json<RecategorizeResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    validate_wallets(&payload.wallets)?;
+    let (rows, prices, failed_txs) = {
+        let stored = data.ledger.read().await;
+        (stored.rows.clone(), stored.prices.clone(), stored.failed_txs.clone())
+    };
+
+    let recategorized = categorize_rows(
+        &state,
+        rows.clone(),
+        &payload.wallets,
+        &failed_txs,
+        &prices,
+        payload.stablecoin_swaps_internal,
+    )
+    .await?;
+    let changes = category_changes(&rows, &recategorized);
+
+    if payload.confirm {
+        let mut stored = data.ledger.write().await;
+        // Rows reviewed while the rules ran win over the new categories
+        let mut recategorized = recategorized;
+        restore_overrides(&mut recategorized, &stored.rows);
+        stored.rows = recategorized;
+        stored.save();
+    }
+
+    Ok(Json(RecategorizeResponse {
+        changes,
+        committed: payload.confirm,
+    }))
+}
+
+#[derive(Deserialize)]
+struct WashTradesRequest {
+    /// User wallets with their groups; the stored wallets by default
+    #[serde(default)]
+    wallets: Vec<Wallet>,
+}
+
+#[derive(Serialize)]
+struct WashTradesResponse {
+    chains: Vec<WashChain>,
+}
+
+/// Report circular fund flows between wallets of the same group in the stored ledger;
+/// their rows are queued for review
+async fn wash_trades_endpoint(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<WashTradesRequest>,
+) -> Result<Json<WashTradesResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let mut wallets = payload.wallets;
+    if wallets.is_empty() {
+        wallets = data.wallets.read().await.to_vec();
+    }
+    let mut stored = data.ledger.write().await;
+    let chains = flag_wash_transfers(&mut stored.rows, &wallets);
+    stored.save();
+    Ok(Json(WashTradesResponse { chains }))
+}
+
+#[derive(Deserialize)]
+struct TaxRequest {
+    user_type: String,
+    /// User wallets with their groups (only needed for the per-group breakdown)
+    #[serde(default)]
+    wallets: Vec<Wallet>,
+    ledger: Vec<LedgerRow>,
+    prices: Vec<PriceEntry>,
+    usd_inr_rate: String,
+    use_44ada: bool,
+    #[serde(default = "default_digital_receipts")]
+    digital_receipts_95pct: bool,
+    #[serde(default)]
+    regime: TaxRegime,
+    #[serde(default)]
+    deductions: Deductions,
+    #[serde(default = "default_assessment_year")]
+    assessment_year: u16,
+    #[serde(default)]
+    acquisition_lots: Vec<AcquisitionLot>,
+    #[serde(default)]
+    corporate_regime: CorporateRegime,
+    #[serde(default)]
+    capital_assets: Vec<String>,
+    #[serde(default)]
+    manual_income: Vec<ManualIncomeEntry>,
+    #[serde(default)]
+    residential_status: ResidentialStatus,
+    /// Rows whose income neither arises nor is received in India
+    #[serde(default)]
+    foreign_source_tx_hashes: Vec<String>,
+    /// TDS/TCS entries credited against tax payable
+    #[serde(default)]
+    tds_entries: Vec<TdsEntry>,
+    /// Return filing date (unix seconds), for 234A interest and the 234F fee
+    #[serde(default)]
+    filing_date: Option<u64>,
+    /// HUF wallets holding assets transferred by members (flagged for clubbing)
+    #[serde(default)]
+    member_transferred_wallets: Vec<String>,
+    /// Estimate GST on professional receipts
+    #[serde(default)]
+    gst: Option<GstSettings>,
+    /// Losses carried forward from earlier years
+    #[serde(default)]
+    brought_forward_losses: LossCarryForward,
+    /// USD/INR reference rates by date (see `/reference-rates/import`)
+    #[serde(default)]
+    reference_rates: Vec<ReferenceRate>,
+    /// Also compute tax separately for each wallet group
+    #[serde(default)]
+    group_breakdown: bool,
+}
+
+fn default_assessment_year() -> u16 {
+    DEFAULT_ASSESSMENT_YEAR
+}
+
+fn default_digital_receipts() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+struct TaxResponse {
+    breakdown: TaxBreakdown,
+    /// Hex keccak256 commitment a proof of this calculation will commit to
+    /// (`compute_ledger_commitment`)
+    ledger_commitment: String,
+    /// Hex SHA256 of the USD/INR rates it converts at, as the proof's `rateTableCommitment`
+    rate_table_commitment: String,
+    /// New vs old regime comparison (Individual/HUF only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    regime_comparison: Option<RegimeComparison>,
+    /// Tax per wallet group (when requested)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<GroupTaxBreakdown>>,
+}
+
+impl TaxRequest {
+    fn into_input(self, max_ledger_rows: usize) -> Result<TaxInput, ApiError> {
+        // Parse user type
+        let user_type = match self.user_type.as_str() {
+            "individual" => UserType::Individual,
+            "huf" => UserType::Huf,
+            "corporate" => UserType::Corporate,
+            _ => {
+                return Err(ApiError::invalid(format!("Invalid user type: {}", self.user_type)));
+            }
+        };
+
+        let mut input = TaxInput {
+            user_type,
+            wallets: self.wallets,
+            ledger: self.ledger,
+            prices: self.prices,
+            usd_inr_rate: self.usd_inr_rate,
+            use_44ada: self.use_44ada,
+            digital_receipts_95pct: self.digital_receipts_95pct,
+            regime: self.regime,
+            deductions: self.deductions,
+            assessment_year: self.assessment_year,
+            acquisition_lots: self.acquisition_lots,
+            corporate_regime: self.corporate_regime,
+            capital_assets: self.capital_assets,
+            manual_income: self.manual_income,
+            tds_entries: self.tds_entries,
+            residential_status: self.residential_status,
+            foreign_source_tx_hashes: self.foreign_source_tx_hashes,
+            filing_date: self.filing_date,
+            member_transferred_wallets: self.member_transferred_wallets,
+            gst: self.gst,
+            brought_forward_losses: self.brought_forward_losses,
+            reference_rates: self.reference_rates,
+            nonce: [0; 32],
+        };
+        validate(&input, max_ledger_rows)?;
+
+        // Value every row once, on its day, for all the calculations made from this input
+        value_ledger(&mut input.ledger, &input.prices, &input.usd_inr_rate, &input.reference_rates);
+        Ok(input)
+    }
+}
+
+/// Most rows a ledger can have unless `MAX_LEDGER_ROWS` says otherwise
+const DEFAULT_MAX_LEDGER_ROWS: usize = 50_000;
+
+/// Reject input the calculation would quietly make sense of, with every problem found in
+/// `details.issues`
+fn validate(input: &TaxInput, max_ledger_rows: usize) -> Result<(), ApiError> {
+    let issues = validate_input(input, max_ledger_rows);
+    let message = match issues.len() {
+        0 => return Ok(()),
+        1 => "1 problem with the request".to_string(),
+        n => format!("{} problems with the request", n),
+    };
+    // Clients that can fetch prices tell a request only missing some from a malformed one
+    let code = if issues.iter().all(|issue| issue.code == IssueCode::PriceMissing) {
+        ErrorCode::PriceMissing
+    } else {
+        ErrorCode::ValidationFailed
+    };
+    Err(ApiError::new(code, message).with_details(serde_json::json!({ "issues": issues })))
+}
+
+fn tax_error(e: financoor_core::TaxError) -> ApiError {
+    ApiError::invalid(e.to_string())
+}
+
+async fn calculate_tax_endpoint(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(mut payload): Json<TaxRequest>,
+) -> Result<Json<TaxResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    if payload.group_breakdown && payload.wallets.is_empty() {
+        payload.wallets = stored_wallets(&data, &payload.ledger).await;
+    }
+    let group_breakdown = payload.group_breakdown;
+    let input = payload.into_input(state.max_ledger_rows)?;
+    let user_type = input.user_type;
+
+    let breakdown = calculate_tax(&input).map_err(tax_error)?;
+
+    // Corporates have no regime choice
+    let regime_comparison = match user_type {
+        UserType::Individual | UserType::Huf => Some(compare_regimes(&input).map_err(tax_error)?),
+        UserType::Corporate => None,
+    };
+
+    let groups = if group_breakdown {
+        Some(calculate_tax_by_group(&input).map_err(tax_error)?.groups)
+    } else {
+        None
+    };
+
+    Ok(Json(TaxResponse {
+        breakdown,
+        ledger_commitment: hex::encode(compute_ledger_commitment(&input)),
+        rate_table_commitment: hex::encode(Sha256::digest(
+            canonical_rate_table(&input.usd_inr_rate, &input.reference_rates).as_bytes(),
+        )),
+        regime_comparison,
+        groups,
+    }))
+}
+
+#[derive(Deserialize)]
+struct SaveReportRequest {
+    /// Report to add a version to; a new report when omitted
+    #[serde(default)]
+    report_id: Option<String>,
+    /// Name of a new report, or a new name for the report
+    #[serde(default)]
+    name: Option<String>,
+    /// Finished proof of this session to attach
+    #[serde(default)]
+    job_id: Option<String>,
+    /// The session; the stored wallets are saved with it when it names none
+    #[serde(flatten)]
+    tax: TaxRequest,
+}
+
+#[derive(Deserialize)]
+struct AttachProofRequest {
+    job_id: String,
+    /// Version the job proves (the latest when omitted)
+    #[serde(default)]
+    version: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ReportQuery {
+    /// Version to return (the latest when omitted)
+    #[serde(default)]
+    version: Option<u32>,
+}
+
+/// A report with one of its versions in full
+#[derive(Serialize)]
+struct ReportResponse {
+    id: String,
+    name: String,
+    created_at: u64,
+    versions: Vec<VersionSummary>,
+    version: ReportVersion,
+}
+
+impl ReportResponse {
+    fn new(report: &TaxReport, version: &ReportVersion) -> Self {
+        Self {
+            id: report.id.clone(),
+            name: report.name.clone(),
+            created_at: report.created_at,
+            versions: report.version_summaries(),
+            version: version.clone(),
+        }
+    }
+}
+
+/// A finished proof of the account's, if it proves the ledger of `ledger_commitment`
+async fn report_proof(
+    state: &AppState,
+    account: &Account,
+    job_id: &str,
+    ledger_commitment: &str,
+) -> Result<ReportProof, ApiError> {
+    let result = finished_proof(state, account, job_id).await?;
+    if result.artifacts.ledger_commitment != ledger_commitment {
+        let message = format!("Job {} proves a different ledger than the report's", job_id);
+        return Err(ApiError::invalid(message));
+    }
+    Ok(ReportProof {
+        job_id: job_id.to_string(),
+        result,
+    })
+}
+
+/// Save a tax session as a new report, or as a new version of `report_id`: its wallets,
+/// valued ledger, prices and settings, the breakdown calculated from them and, given a
+/// finished job proving it, the proof
+async fn save_report(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<SaveReportRequest>,
+) -> Result<Json<ReportResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let mut tax = payload.tax;
+    if tax.wallets.is_empty() {
+        tax.wallets = stored_wallets(&data, &tax.ledger).await;
+    }
+    let input = tax.into_input(state.max_ledger_rows)?;
+    let breakdown = calculate_tax(&input).map_err(tax_error)?;
+    let ledger_commitment = hex::encode(compute_ledger_commitment(&input));
+    let proof = match &payload.job_id {
+        Some(job_id) => Some(report_proof(&state, &account, job_id, &ledger_commitment).await?),
+        None => None,
+    };
+    let version = ReportVersion {
+        version: 1,
+        created_at: chrono::Utc::now().timestamp().max(0) as u64,
+        input,
+        breakdown,
+        ledger_commitment,
+        category_changes: Vec::new(),
+        proof,
+    };
+
+    let mut reports = data.reports.write().await;
+    let index = match &payload.report_id {
+        Some(report_id) => {
+            let index = reports
+                .iter()
+                .position(|report| report.id == *report_id)
+                .ok_or_else(|| ApiError::not_found(format!("Report not found: {}", report_id)))?;
+            let report = &mut reports[index];
+            report.push(version);
+            if let Some(name) = payload.name {
+                report.name = name;
+            }
+            index
+        }
+        None => {
+            let id = format!("{:x}", rand::random::<u64>());
+            let name = payload.name.unwrap_or_else(|| format!("AY {}", version.input.assessment_year));
+            reports.push(TaxReport::new(id, name, version));
+            reports.len() - 1
+        }
+    };
+    let response = ReportResponse::new(&reports[index], reports[index].latest());
+    reports.save();
+    Ok(Json(response))
+}
+
+/// The account's reports, with their latest versions' figures
+async fn list_reports(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+) -> Result<Json<Vec<ReportSummary>>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let reports = data.reports.read().await;
+    Ok(Json(reports.iter().map(TaxReport::summary).collect()))
+}
+
+/// A report with a version of it in full (the latest unless `version` is given)
+async fn get_report(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(report_id): Path<String>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<ReportResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let reports = data.reports.read().await;
+    let report = reports
+        .iter()
+        .find(|report| report.id == report_id)
+        .ok_or_else(|| ApiError::not_found(format!("Report not found: {}", report_id)))?;
+    let version = match query.version {
+        Some(version) => report
+            .version(version)
+            .ok_or_else(|| ApiError::not_found(format!("Report {} has no version {}", report_id, version)))?,
+        None => report.latest(),
+    };
+    Ok(Json(ReportResponse::new(report, version)))
+}
+
+/// Attach a finished proof to a version of a report, once the job proving it is done
+async fn attach_report_proof(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(report_id): Path<String>,
+    Json(payload): Json<AttachProofRequest>,
+) -> Result<Json<ReportResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let mut reports = data.reports.write().await;
+    let report = reports
+        .iter_mut()
+        .find(|report| report.id == report_id)
+        .ok_or_else(|| ApiError::not_found(format!("Report not found: {}", report_id)))?;
+    let number = payload.version.unwrap_or_else(|| report.latest().version);
+    let version = report
+        .version_mut(number)
+        .ok_or_else(|| ApiError::not_found(format!("Report {} has no version {}", report_id, number)))?;
+    version.proof = Some(report_proof(&state, &account, &payload.job_id, &version.ledger_commitment).await?);
+    let response = ReportResponse::new(report, report.version(number).expect("the version exists"));
+    reports.save();
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+struct SimulateRequest {
+    #[serde(flatten)]
+    base: TaxRequest,
+    scenarios: Vec<Scenario>,
+}
+
+#[derive(Serialize)]
+struct ScenarioResult {
+    name: String,
+    breakdown: TaxBreakdown,
+    /// Change in total tax against the base (paisa, negative when the scenario saves tax)
+    tax_delta_paisa: i64,
+}
+
+#[derive(Serialize)]
+struct SimulateResponse {
+    base: TaxBreakdown,
+    scenarios: Vec<ScenarioResult>,
+}
+
+/// What-if analysis: tax for the base input and for each scenario applied to a copy of it
+async fn simulate_tax_endpoint(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SimulateRequest>,
+) -> Result<Json<SimulateResponse>, ApiError> {
+    let input = payload.base.into_input(state.max_ledger_rows)?;
+    let base = calculate_tax(&input).map_err(tax_error)?;
+
+    let mut scenarios = Vec::with_capacity(payload.scenarios.len());
+    for scenario in &payload.scenarios {
+        let scenario_input = scenario.apply(&input).map_err(|e| ApiError::invalid(e.to_string()))?;
+        let breakdown = calculate_tax(&scenario_input).map_err(tax_error)?;
+        scenarios.push(ScenarioResult {
+            name: scenario.name.clone(),
+            tax_delta_paisa: breakdown.total_tax_paisa as i64 - base.total_tax_paisa as i64,
+            breakdown,
+        });
+    }
+
+    Ok(Json(SimulateResponse { base, scenarios }))
+}
+
+#[derive(Deserialize)]
+struct MultiYearRequest {
+    #[serde(flatten)]
+    base: TaxRequest,
+    /// Deductions, manual income and filing date for each assessment year
+    #[serde(default)]
+    years: Vec<YearSettings>,
+}
+
+/// Tax for each financial year a combined ledger spans, with a year-on-year summary
+async fn multi_year_tax_endpoint(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MultiYearRequest>,
+) -> Result<Json<MultiYearTaxBreakdown>, ApiError> {
+    let input = payload.base.into_input(state.max_ledger_rows)?;
+    calculate_tax_multi_year(&input, &payload.years).map(Json).map_err(tax_error)
+}
+
+// ============================================================================
+// PROOF GENERATION
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ProofRequest {
+    user_type: String,
+    /// The user's wallets, with their groups (for proofs split by wallet group) and
+    /// ownership signatures
+    #[serde(default)]
+    wallets: Vec<Wallet>,
+    ledger: Vec<LedgerRow>,
+    prices: Vec<PriceEntry>,
+    usd_inr_rate: String,
+    use_44ada: bool,
+    #[serde(default = "default_digital_receipts")]
+    digital_receipts_95pct: bool,
+    #[serde(default)]
+    regime: TaxRegime,
+    #[serde(default)]
+    deductions: Deductions,
+    #[serde(default = "default_assessment_year")]
+    assessment_year: u16,
+    #[serde(default)]
+    acquisition_lots: Vec<AcquisitionLot>,
+    #[serde(default)]
+    corporate_regime: CorporateRegime,
+    #[serde(default)]
+    capital_assets: Vec<String>,
+    #[serde(default)]
+    manual_income: Vec<ManualIncomeEntry>,
+    #[serde(default)]
+    residential_status: ResidentialStatus,
+    /// Rows whose income neither arises nor is received in India
+    #[serde(default)]
+    foreign_source_tx_hashes: Vec<String>,
+    #[serde(default)]
+    brought_forward_losses: LossCarryForward,
+    #[serde(default)]
+    reference_rates: Vec<ReferenceRate>,
+    /// "core" or "compressed" for quicker off-chain proofs; "groth16" or "plonk" to verify
+    /// on-chain; the server's configured mode (groth16 by default) if not given
+    #[serde(default)]
+    mode: Option<ProofMode>,
+    /// "local" or "network"; the prover network when it's configured by default
+    #[serde(default)]
+    backend: Option<ProvingBackend>,
+    /// "low", "normal" (default) or "high": queued jobs run highest priority first
+    #[serde(default)]
+    priority: JobPriority,
+    /// A statement to prove in place of the exact figures, e.g. that tax paid is at least
+    /// some amount; the proof can't be generated unless it holds
+    #[serde(default)]
+    disclosure: Option<Disclosure>,
+    /// 32 bytes (hex) the proof commits, so a verifier can refuse it once seen; random by
+    /// default, in which case an earlier identical request's proof is returned instead of a
+    /// new one, so choose one for a proof that must be fresh
+    #[serde(default)]
+    nonce: Option<String>,
+    /// URL the job's status is POSTed to, signed, once it's done or has failed (see
+    /// `callbacks`)
+    #[serde(default)]
+    callback_url: Option<String>,
+}
+
+/// How a ledger is split into parts to prove separately
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LedgerSplit {
+    /// A part per wallet group (wallets in no group make up one more)
+    Group,
+    /// A part per financial year, with losses and unsold lots carried forward
+    Year,
+}
+
+#[derive(Deserialize)]
+struct AggregateProofRequest {
+    #[serde(flatten)]
+    base: ProofRequest,
+    split: LedgerSplit,
+    /// Deductions, manual income and filing date for each assessment year, when split by year
+    #[serde(default)]
+    years: Vec<YearSettings>,
+}
+
+#[derive(Serialize)]
+struct ProofSubmitResponse {
+    job_id: String,
+    /// Whether the job is an earlier one proving the same, done or still pending
+    reused: bool,
+}
+
+/// What the program computes for a ledger, from executing it without a proof
+#[derive(Serialize)]
+struct DryRunResponse {
+    ledger_commitment: String,
+    rate_table_commitment: String,
+    prices_commitment: String,
+    total_tax_paisa: u64,
+    professional_income_paisa: u64,
+    vda_gains_paisa: u64,
+    vda_tax_paisa: u64,
+    cess_paisa: u64,
+    user_type_code: u8,
+    used_44ada: bool,
+    assessment_year: u16,
+    fy_start: u64,
+    fy_end: u64,
+    owned_wallets_commitment: String,
+    /// Whether the wallets' ownership signatures cover every wallet with rows
+    all_wallets_owned: bool,
+    nonce: String,
+    /// Names the version of the program the proof would come from
+    domain_separator: String,
+    /// ABI-encoded public values, hex
+    public_values: String,
+    /// Instructions executed; proving time grows with it
+    cycles: u64,
+    /// Total tax of the `/tax` preview for the same input, in paisa
+    preview_total_tax_paisa: u64,
+    /// Whether a proof would carry the previewed total
+    matches_preview: bool,
+}
+
+#[derive(Serialize)]
+struct ProofListResponse {
+    jobs: Vec<JobSummary>,
+    page: usize,
+    per_page: usize,
+    /// Jobs matching the filter across every page
+    total: usize,
+}
+
+/// Jobs listed per page unless `per_page` says otherwise, and the most it can say
+const DEFAULT_PROOFS_PER_PAGE: usize = 50;
+const MAX_PROOFS_PER_PAGE: usize = 200;
+
+#[derive(Deserialize)]
+struct PageQuery {
+    /// From 1
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+/// Most proofs one batch can hold
+const MAX_BATCH_PROOFS: usize = 100;
+
+#[derive(Deserialize)]
+struct BatchProofItem {
+    /// What the proof is called in the batch's status and bundle, e.g. the client's name
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(flatten)]
+    request: ProofRequest,
+}
+
+#[derive(Deserialize)]
+struct BatchProofRequest {
+    proofs: Vec<BatchProofItem>,
+}
+
+#[derive(Serialize)]
+struct BatchSubmitResponse {
+    batch_id: String,
+    /// A job per proof, in the order they were submitted
+    job_ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BatchBundleEntry {
+    job_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(flatten)]
+    status: ProofJobStatus,
+}
+
+/// A finished batch's jobs, in the order they were submitted, with their proofs
+#[derive(Serialize)]
+struct BatchBundle {
+    batch_id: String,
+    proofs: Vec<BatchBundleEntry>,
+}
+
+#[derive(Serialize)]
+struct ProofStatusResponse {
+    job_id: String,
+    #[serde(flatten)]
+    status: ProofJobStatus,
+    /// Place in the queue (1 = next) while the job waits for a worker
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_position: Option<usize>,
+}
+
+impl ProofRequest {
+    /// The tax input to prove, with the rows valued so the committed ledger carries the
+    /// values the tax was proved on
+    fn into_input(self, max_ledger_rows: usize) -> Result<TaxInput, ApiError> {
+        // Parse user type
+        let user_type = match self.user_type.as_str() {
+            "individual" => UserType::Individual,
+            "huf" => UserType::Huf,
+            "corporate" => UserType::Corporate,
+            _ => {
+                return Err(ApiError::invalid(format!("Invalid user type: {}", self.user_type)));
+            }
+        };
+
+        // A signature the program would reject only fails once proving
+        for wallet in &self.wallets {
+            if let Some(signature) = &wallet.ownership_signature {
+                verify_ownership(&wallet.address, signature).map_err(tax_error)?;
+            }
+        }
+
+        let nonce = match &self.nonce {
+            Some(nonce) => hex::decode(nonce.strip_prefix("0x").unwrap_or(nonce))
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or_else(|| ApiError::invalid(format!("Invalid nonce (expected 32 hex bytes): {}", nonce)))?,
+            None => rand::random(),
+        };
+
+        let mut input = TaxInput {
+            user_type,
+            wallets: self.wallets,
+            ledger: self.ledger,
+            prices: self.prices,
+            usd_inr_rate: self.usd_inr_rate,
+            use_44ada: self.use_44ada,
+            digital_receipts_95pct: self.digital_receipts_95pct,
+            regime: self.regime,
+            deductions: self.deductions,
+            assessment_year: self.assessment_year,
+            acquisition_lots: self.acquisition_lots,
+            corporate_regime: self.corporate_regime,
+            capital_assets: self.capital_assets,
+            manual_income: self.manual_income,
+            tds_entries: vec![], // TDS credit doesn't change the proved liability
+            residential_status: self.residential_status,
+            foreign_source_tx_hashes: self.foreign_source_tx_hashes,
+            filing_date: None, // Late filing charges don't change the proved liability
+            member_transferred_wallets: vec![], // Clubbing is only flagged, not taxed
+            gst: None, // GST is reported separately from income tax
+            brought_forward_losses: self.brought_forward_losses,
+            reference_rates: self.reference_rates,
+            nonce,
+        };
+        validate(&input, max_ledger_rows)?;
+
+        value_ledger(&mut input.ledger, &input.prices, &input.usd_inr_rate, &input.reference_rates);
+        Ok(input)
+    }
+}
+
+/// User type as committed in the public values
+fn user_type_code(user_type: UserType) -> u8 {
+    match user_type {
+        UserType::Individual => 0,
+        UserType::Huf => 1,
+        UserType::Corporate => 2,
+    }
+}
+
+async fn submit_proof(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(mut payload): Json<ProofRequest>,
+) -> Result<Json<ProofSubmitResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    if payload.wallets.is_empty() {
+        payload.wallets = stored_wallets(&data, &payload.ledger).await;
+    }
+    let job = proof_job(&state, &account, payload, None).await?;
+    Ok(Json(queue_job(&state, job).await?))
+}
+
+/// A job proving a request, once its calculation is previewed
+async fn proof_job(
+    state: &AppState,
+    account: &Account,
+    mut payload: ProofRequest,
+    batch: Option<BatchEntry>,
+) -> Result<ProofJob, ApiError> {
+    let mode = payload.mode.unwrap_or(state.default_proof_mode);
+    let (backend, priority, assessment_year) = (payload.backend, payload.priority, payload.assessment_year);
+    let disclosure = payload.disclosure.clone();
+    let nonce_chosen = payload.nonce.is_some();
+    let callback_url = checked_callback_url(state, payload.callback_url.take()).await?;
+    let input = payload.into_input(state.max_ledger_rows)?;
+    let user_type_code = user_type_code(input.user_type);
+
+    // Preview the calculation: rejects assessment years the zkVM program has no rules
+    // for before queueing, and tells us whether 44ADA will actually be applied
+    let preview = calculate_tax(&input).map_err(|e| ApiError::invalid(e.to_string()))?;
+    // A statement that doesn't hold would only fail once proving
+    if let Some(disclosure) = &disclosure {
+        disclosure.range(preview.total_tax_paisa, preview.total_income_paisa).map_err(tax_error)?;
+    }
+
+    // Generate job ID
+    let job_id = format!("{:x}", rand::random::<u64>());
+
+    // Debug: Log categories being sent to prover
+    tracing::info!("=== PROOF REQUEST DEBUG ===");
+    tracing::info!("Job ID: {}", job_id);
+    tracing::info!("Ledger rows: {}", input.ledger.len());
+    for (i, row) in input.ledger.iter().enumerate() {
+        tracing::info!("  Row {}: asset={}, amount={}, category={:?}, direction={:?}",
+            i, row.asset, row.amount, row.category, row.direction);
+    }
+    tracing::info!("Prices: {:?}", input.prices);
+    tracing::info!("USD/INR rate: {}", input.usd_inr_rate);
+    tracing::info!("===========================");
+
+    Ok(ProofJob {
+        id: job_id,
+        account: account.0.clone(),
+        priority,
+        proof_key: proof_key(state, &input, &[], disclosure.as_ref(), mode, nonce_chosen),
+        input,
+        parts: Vec::new(),
+        disclosure,
+        mode,
+        backend: backend.unwrap_or_else(|| state.prover.default_backend()),
+        user_type_code,
+        used_44ada: preview.presumptive_44ada_applied,
+        assessment_year,
+        batch,
+        callback_url,
+    })
+}
+
+/// A request's callback URL, if it's one the server can call back
+async fn checked_callback_url(state: &AppState, url: Option<String>) -> Result<Option<String>, ApiError> {
+    let Some(url) = url else {
+        return Ok(None);
+    };
+    if !state.jobs.sends_callbacks() {
+        return Err(ApiError::new(ErrorCode::NotConfigured, "Proof callbacks are not configured"));
+    }
+    callbacks::check_url(&url).await.map_err(ApiError::invalid)?;
+    Ok(Some(url))
+}
+
+/// What a job proves: its ledger commitment, then a hash of everything else the proof
+/// depends on (the rest of the input, the parts, the statement, the mode and the program)
+///
+/// A nonce the request didn't choose is random, so it's left out; otherwise no two such
+/// requests would ever prove the same. The backend is left out too, as either proves the
+/// same.
+fn proof_key(
+    state: &AppState,
+    input: &TaxInput,
+    parts: &[TaxInput],
+    disclosure: Option<&Disclosure>,
+    mode: ProofMode,
+    nonce_chosen: bool,
+) -> String {
+    let mut options = serde_json::json!({
+        "input": input,
+        "parts": parts,
+        "disclosure": disclosure,
+        "mode": mode,
+        "vk_hash": state.prover.get_vk_hash(),
+    });
+    if !nonce_chosen {
+        options["input"]["nonce"] = serde_json::Value::Null;
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(compute_ledger_commitment(input));
+    hasher.update(Sha256::digest(options.to_string()));
+    hex::encode(hasher.finalize())
+}
+
+/// Queue a job for a proof worker, or find the account's job already proving the same
+async fn queue_job(state: &AppState, job: ProofJob) -> Result<ProofSubmitResponse, ApiError> {
+    let job_id = job.id.clone();
+    let submitted = state.jobs.submit(job).await.map_err(|SubmitError::TooManyJobs(active)| {
+        let message = format!("{} proof jobs already queued or running", active);
+        ApiError::new(ErrorCode::TooManyJobs, message)
+    })?;
+    Ok(match submitted {
+        Submitted::Queued => ProofSubmitResponse { job_id, reused: false },
+        Submitted::Existing(job_id) => {
+            tracing::info!("Reusing proof job {} for an identical request", job_id);
+            ProofSubmitResponse { job_id, reused: true }
+        }
+    })
+}
+
+/// Prove a ledger in parts, per wallet group or per financial year, and aggregate them
+/// into a single proof of the combined tax
+///
+/// Each part stays within the cycles one proof can take, however large the ledger; the
+/// aggregate commits a digest of the parts' public values rather than a ledger commitment.
+async fn submit_aggregate_proof(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<AggregateProofRequest>,
+) -> Result<Json<ProofSubmitResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let AggregateProofRequest { mut base, split, years } = payload;
+    if base.wallets.is_empty() {
+        base.wallets = stored_wallets(&data, &base.ledger).await;
+    }
+    if base.disclosure.is_some() {
+        return Err(ApiError::invalid("Aggregate proofs can't disclose a statement in place of the figures"));
+    }
+    let mode = base.mode.unwrap_or(state.default_proof_mode);
+    let (backend, priority) = (base.backend, base.priority);
+    let nonce_chosen = base.nonce.is_some();
+    let callback_url = checked_callback_url(&state, base.callback_url.take()).await?;
+    let input = base.into_input(state.max_ledger_rows)?;
+
+    let mut parts: Vec<TaxInput> = match split {
+        LedgerSplit::Group => split_by_group(&input).into_iter().map(|(_, part)| part).collect(),
+        LedgerSplit::Year => {
+            let (parts, unsupported_assessment_years) = split_by_year(&input, &years).map_err(tax_error)?;
+            if !unsupported_assessment_years.is_empty() {
+                let message = format!("No tax rules for assessment years {:?}", unsupported_assessment_years);
+                return Err(ApiError::invalid(message));
+            }
+            parts.into_iter().map(|(part, _)| part).collect()
+        }
+    };
+    if parts.is_empty() {
+        return Err(ApiError::invalid("Nothing to aggregate: the ledger is empty"));
+    }
+    // Parts are only verified within the aggregate, which commits no nonce; leaving theirs
+    // zero keeps the proof of a part that didn't change reusable
+    for part in &mut parts {
+        part.nonce = [0; 32];
+    }
+
+    // Preview every part, as a single proof is previewed
+    let mut used_44ada = false;
+    for part in &parts {
+        used_44ada |= calculate_tax(part).map_err(tax_error)?.presumptive_44ada_applied;
+    }
+    let assessment_year = parts.iter().map(|part| part.assessment_year).max().unwrap_or(input.assessment_year);
+
+    let job_id = format!("{:x}", rand::random::<u64>());
+    tracing::info!("Aggregate proof job {}: {} parts by {:?}", job_id, parts.len(), split);
+    let job = ProofJob {
+        id: job_id.clone(),
+        account: account.0.clone(),
+        priority,
+        user_type_code: user_type_code(input.user_type),
+        proof_key: proof_key(&state, &input, &parts, None, mode, nonce_chosen),
+        input,
+        parts,
+        disclosure: None,
+        mode,
+        backend: backend.unwrap_or_else(|| state.prover.default_backend()),
+        used_44ada,
+        assessment_year,
+        batch: None,
+        callback_url,
+    };
+    Ok(Json(queue_job(&state, job).await?))
+}
+
+/// Prove many ledgers at once, e.g. every client of an accountant, as one batch
+///
+/// Every proof is previewed before any is queued, and either all are queued or none. Each
+/// is an ordinary job, with its own status; the batch reports them together and bundles
+/// their proofs once all have finished.
+async fn submit_proof_batch(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<BatchProofRequest>,
+) -> Result<Json<BatchSubmitResponse>, ApiError> {
+    if payload.proofs.is_empty() {
+        return Err(ApiError::invalid("A batch needs at least one proof"));
+    }
+    if payload.proofs.len() > MAX_BATCH_PROOFS {
+        return Err(ApiError::invalid(format!("A batch can hold at most {} proofs", MAX_BATCH_PROOFS)));
+    }
+
+    let batch_id = format!("{:x}", rand::random::<u64>());
+    let mut jobs = Vec::with_capacity(payload.proofs.len());
+    for (index, BatchProofItem { label, request }) in payload.proofs.into_iter().enumerate() {
+        let batch = BatchEntry {
+            id: batch_id.clone(),
+            index,
+            label,
+        };
+        let job = proof_job(&state, &account, request, Some(batch)).await.map_err(|mut e| {
+            e.message = format!("Proof {}: {}", index, e.message);
+            for issue in e.details.iter_mut().filter_map(|details| details["issues"].as_array_mut()).flatten() {
+                issue["field"] = format!("proofs[{}].{}", index, issue["field"].as_str().unwrap_or_default()).into();
+            }
+            e
+        })?;
+        jobs.push(job);
+    }
+    let job_ids = jobs.iter().map(|job| job.id.clone()).collect();
+    tracing::info!("Proof batch {}: {} jobs", batch_id, jobs.len());
+
+    state.jobs.submit_batch(jobs).await.map_err(|SubmitError::TooManyJobs(active)| {
+        let message = format!("{} proof jobs already queued or running, too many for the batch", active);
+        ApiError::new(ErrorCode::TooManyJobs, message)
+    })?;
+
+    Ok(Json(BatchSubmitResponse { batch_id, job_ids }))
+}
+
+/// Status of every job in a batch, and how many are in each status
+async fn get_proof_batch(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(batch_id): Path<String>,
+) -> Result<Json<BatchStatus>, ApiError> {
+    let batch = state.jobs.batch(&batch_id, &account.0).await;
+    batch.map(Json).ok_or_else(|| ApiError::not_found(format!("Batch not found: {}", batch_id)))
+}
+
+/// Every proof of a batch in one JSON bundle, once none of its jobs is pending
+async fn get_proof_batch_bundle(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(batch_id): Path<String>,
+) -> Result<Json<BatchBundle>, ApiError> {
+    let results = state.jobs.batch_results(&batch_id, &account.0).await;
+    if results.is_empty() {
+        return Err(ApiError::not_found(format!("Batch not found: {}", batch_id)));
+    }
+    let pending = results.iter().filter(|(_, status)| matches!(status, ProofJobStatus::Pending { .. })).count();
+    if pending > 0 {
+        let message = format!("{} jobs of batch {} are still pending", pending, batch_id);
+        return Err(ApiError::new(ErrorCode::ProofPending, message));
+    }
+
+    let proofs = results
+        .into_iter()
+        .map(|(record, status)| BatchBundleEntry {
+            job_id: record.id,
+            label: record.batch.and_then(|batch| batch.label),
+            status,
+        })
+        .collect();
+    Ok(Json(BatchBundle { batch_id, proofs }))
+}
+
+/// Execute the program on a proof request without proving it, to check what a proof
+/// would commit before paying for one
+async fn dry_run_proof(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(mut payload): Json<ProofRequest>,
+) -> Result<Json<DryRunResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
+    if payload.wallets.is_empty() {
+        payload.wallets = stored_wallets(&data, &payload.ledger).await;
+    }
+    let input = payload.into_input(state.max_ledger_rows)?;
+    let preview = calculate_tax(&input).map_err(tax_error)?;
+
+    let prover = state.prover.clone();
+    let execution = tokio::task::spawn_blocking(move || prover.execute(&input))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result.map_err(|e| e.to_string()))
+        .map_err(|error| ApiError::new(ErrorCode::ProofFailed, error))?;
 
-    Ok(Json(TransfersResponse {
-        ledger: all_ledger,
-        wallet_counts,
+    let values = TaxProofPublicValues::abi_decode(&execution.public_values)
+        .map_err(|e| ApiError::new(ErrorCode::Internal, format!("Invalid public values: {}", e)))?;
+    let preview_total_tax_paisa = preview.total_tax_paisa;
+    let total_tax_paisa = values.totalTaxPaisa.saturating_to::<u64>();
+
+    Ok(Json(DryRunResponse {
+        ledger_commitment: hex::encode(values.ledgerCommitment),
+        rate_table_commitment: hex::encode(values.rateTableCommitment),
+        prices_commitment: hex::encode(values.pricesCommitment),
+        total_tax_paisa,
+        professional_income_paisa: values.professionalIncomePaisa.saturating_to(),
+        vda_gains_paisa: values.vdaGainsPaisa.saturating_to(),
+        vda_tax_paisa: values.vdaTaxPaisa.saturating_to(),
+        cess_paisa: values.cessPaisa.saturating_to(),
+        user_type_code: values.userType,
+        used_44ada: values.used44ada,
+        assessment_year: values.assessmentYear,
+        fy_start: values.fyStart,
+        fy_end: values.fyEnd,
+        owned_wallets_commitment: hex::encode(values.ownedWalletsCommitment),
+        all_wallets_owned: values.allWalletsOwned,
+        nonce: hex::encode(values.nonce),
+        domain_separator: hex::encode(values.domainSeparator),
+        public_values: hex::encode(&execution.public_values),
+        cycles: execution.cycles,
+        preview_total_tax_paisa,
+        matches_preview: total_tax_paisa == preview_total_tax_paisa,
     }))
 }
 
 #[derive(Deserialize)]
-struct TaxRequest {
-    user_type: String,
-    ledger: Vec<LedgerRow>,
-    prices: Vec<PriceEntry>,
-    usd_inr_rate: String,
-    use_44ada: bool,
+struct OwnershipMessageQuery {
+    wallet: String,
 }
 
 #[derive(Serialize)]
-struct TaxResponse {
-    breakdown: TaxBreakdown,
+struct OwnershipMessageResponse {
+    wallet: Address,
+    /// To sign with `personal_sign`, and pass back as the wallet's `ownership_signature`
+    message: String,
 }
 
-async fn calculate_tax_endpoint(
-    Json(payload): Json<TaxRequest>,
-) -> Result<Json<TaxResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Parse user type
-    let user_type = match payload.user_type.as_str() {
-        "individual" => UserType::Individual,
-        "huf" => UserType::Huf,
-        "corporate" => UserType::Corporate,
-        _ => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Invalid user type: {}", payload.user_type),
-                }),
-            ));
+/// Message a wallet signs to show it's the user's, so its proofs commit it as owned
+async fn get_ownership_message(
+    Query(query): Query<OwnershipMessageQuery>,
+) -> Result<Json<OwnershipMessageResponse>, ApiError> {
+    let wallet = Address::parse(&query.wallet).map_err(tax_error)?;
+    if !wallet.is_evm() {
+        return Err(tax_error(financoor_core::TaxError::InvalidOwnershipSignature(format!(
+            "{}: only EVM wallets can sign",
+            wallet.as_str()
+        ))));
+    }
+    Ok(Json(OwnershipMessageResponse {
+        message: ownership_message(&wallet),
+        wallet,
+    }))
+}
+
+/// Proof jobs, newest first, filtered by `status`, `wallet`, `assessment_year` and `batch`,
+/// a page at a time
+async fn list_proofs(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Query(mut filter): Query<JobFilter>,
+    Query(page): Query<PageQuery>,
+) -> Json<ProofListResponse> {
+    filter.account = account.0;
+    let per_page = page.per_page.unwrap_or(DEFAULT_PROOFS_PER_PAGE).clamp(1, MAX_PROOFS_PER_PAGE);
+    let page = page.page.unwrap_or(1).max(1);
+    let jobs = state.jobs.list(&filter).await;
+    let total = jobs.len();
+    Json(ProofListResponse {
+        jobs: jobs.into_iter().skip((page - 1).saturating_mul(per_page)).take(per_page).collect(),
+        page,
+        per_page,
+        total,
+    })
+}
+
+/// Result of a job that finished with a proof
+async fn finished_proof(
+    state: &AppState,
+    account: &Account,
+    job_id: &str,
+) -> Result<ProofResult, ApiError> {
+    match state.jobs.status(job_id, &account.0).await {
+        Some((ProofJobStatus::Done { result }, _)) => Ok(*result),
+        Some((ProofJobStatus::Pending { .. }, _)) => {
+            Err(ApiError::new(ErrorCode::ProofPending, format!("Job hasn't finished: {}", job_id)))
         }
-    };
+        Some((ProofJobStatus::Error { error }, _)) => {
+            Err(ApiError::new(ErrorCode::ProofFailed, format!("Job failed: {}", job_id))
+                .with_details(serde_json::json!({ "error": error })))
+        }
+        Some((ProofJobStatus::Cancelled, _)) => {
+            Err(ApiError::new(ErrorCode::Conflict, format!("Job was cancelled: {}", job_id)))
+        }
+        None => Err(ApiError::not_found(format!("Job not found: {}", job_id))),
+    }
+}
+
+/// A finished Groth16 or PLONK proof as `ISP1Verifier.verifyProof` and
+/// `TaxVerifier.verifyTaxProof` take it
+async fn get_proof_calldata(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(job_id): Path<String>,
+) -> Result<Json<OnchainCalldata>, ApiError> {
+    let result = finished_proof(&state, &account, &job_id).await?;
+    let calldata = result.artifacts.to_onchain_calldata().map_err(|e| ApiError::invalid(e.to_string()))?;
+    Ok(Json(calldata))
+}
 
-    let input = TaxInput {
-        user_type,
-        wallets: vec![], // Not needed for calculation
-        ledger: payload.ledger,
-        prices: payload.prices,
-        usd_inr_rate: payload.usd_inr_rate,
-        use_44ada: payload.use_44ada,
+/// Send a finished Groth16 or PLONK proof to `TaxVerifier` from the relayer account
+async fn submit_proof_onchain(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(job_id): Path<String>,
+) -> Result<Json<Submission>, ApiError> {
+    let Some(relayer) = &state.relayer else {
+        return Err(ApiError::new(ErrorCode::NotConfigured, "On-chain submission is not configured"));
     };
+    let result = finished_proof(&state, &account, &job_id).await?;
+    if result.artifacts.disclosed.is_some() {
+        let message = "A selective-disclosure proof is checked with a call to verifyDisclosureProof, not submitted";
+        return Err(ApiError::invalid(message));
+    }
+    let calldata = result.artifacts.to_onchain_calldata().map_err(|e| ApiError::invalid(e.to_string()))?;
+    let submission = relayer
+        .submit(&calldata)
+        .await
+        .map_err(|e| ApiError::new(ErrorCode::UpstreamFailed, format!("On-chain submission failed: {}", e)))?;
+    Ok(Json(submission))
+}
 
-    let breakdown = calculate_tax(&input);
+/// The prover's backends, keys and resources, for operators tuning it
+/// Metrics in the Prometheus text format
+async fn metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let (queued, running) = state.jobs.depth().await;
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], METRICS.render(queued, running))
+}
 
-    Ok(Json(TaxResponse { breakdown }))
+async fn get_prover_info(State(state): State<Arc<AppState>>) -> Json<ProverInfo> {
+    Json(state.prover.info())
 }
 
-// ============================================================================
-// PROOF GENERATION
-// ============================================================================
+#[derive(Serialize)]
+struct ProgramVersionsResponse {
+    /// Version proofs are generated with
+    current: u32,
+    /// Every released version, oldest first, and whether its proofs are still accepted
+    versions: Vec<ProgramVersion>,
+}
 
-#[derive(Deserialize)]
-struct ProofRequest {
-    user_type: String,
-    ledger: Vec<LedgerRow>,
-    prices: Vec<PriceEntry>,
-    usd_inr_rate: String,
-    use_44ada: bool,
+/// Versions of the tax program, to tell which one a proof is from by its verification key,
+/// domain separator or (for an aggregate) `taxProgramVkey`
+async fn get_program_versions(State(state): State<Arc<AppState>>) -> Json<ProgramVersionsResponse> {
+    Json(ProgramVersionsResponse {
+        current: TAX_PROGRAM_VERSION,
+        versions: state.prover.registry().versions().to_vec(),
+    })
 }
 
-#[derive(Serialize)]
-struct ProofSubmitResponse {
-    job_id: String,
+#[derive(Deserialize)]
+struct VerifyRequest {
+    artifacts: ProofArtifacts,
+    /// Rows the proof is over, valued as they were proved, to check its ledger commitment
+    /// (of a ledger proved without acquisition lots, manual income or brought-forward losses)
+    #[serde(default)]
+    ledger: Option<Vec<LedgerRow>>,
 }
 
 #[derive(Serialize)]
-struct ProofStatusResponse {
-    job_id: String,
-    #[serde(flatten)]
-    status: ProofJobStatus,
+struct VerifyResponse {
+    /// Verification key hash of the program the proof is from
+    vk_hash: String,
+    /// Version of the tax program the proof is from; None for one this API doesn't know
+    #[serde(skip_serializing_if = "Option::is_none")]
+    program_version: Option<u32>,
+    /// What the proof's public values state
+    claim: DecodedPublicValues,
+    /// Whether the request's ledger is the one the proof commits; None without one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ledger_matches: Option<bool>,
 }
 
-async fn submit_proof(
+/// Verify proof artifacts from anyone against this API's program keys, and what they claim
+async fn verify_proof(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<ProofRequest>,
-) -> Result<Json<ProofSubmitResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Parse user type
-    let user_type = match payload.user_type.as_str() {
-        "individual" => UserType::Individual,
-        "huf" => UserType::Huf,
-        "corporate" => UserType::Corporate,
-        _ => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Invalid user type: {}", payload.user_type),
-                }),
-            ));
-        }
+    Json(payload): Json<VerifyRequest>,
+) -> Result<Json<VerifyResponse>, ApiError> {
+    let claim = DecodedPublicValues::of(&payload.artifacts)
+        .map_err(|e| ApiError::new(ErrorCode::ProofInvalid, format!("Invalid public values: {}", e)))?;
+    let ledger_matches = match (&payload.ledger, claim.ledger_commitment()) {
+        (Some(ledger), Some(commitment)) => Some(hex::encode(ledger_commitment(ledger)) == commitment),
+        (Some(_), None) => return Err(ApiError::invalid("An aggregate proof commits its parts, not a ledger")),
+        (None, _) => None,
     };
+    let version = state.prover.registry().of(&claim);
+    if let Some(version) = version.filter(|version| !version.accepted) {
+        return Err(ApiError::invalid(format!("Proofs of tax program v{} are no longer accepted", version.version)));
+    }
+    let program_version = version.map(|version| version.version);
 
-    let user_type_code = match user_type {
-        UserType::Individual => 0u8,
-        UserType::Huf => 1u8,
-        UserType::Corporate => 2u8,
-    };
+    let prover = state.prover.clone();
+    let artifacts = payload.artifacts;
+    let vk_hash = artifacts.vk_hash.clone();
+    tokio::task::spawn_blocking(move || prover.verify(&artifacts))
+        .await
+        .map_err(|e| ApiError::new(ErrorCode::Internal, e.to_string()))?
+        .map_err(|e| ApiError::new(ErrorCode::ProofInvalid, format!("Proof doesn't verify: {}", e)))?;
 
-    // Generate job ID
-    let job_id = format!("{:x}", rand::random::<u64>());
+    Ok(Json(VerifyResponse {
+        vk_hash,
+        program_version,
+        claim,
+        ledger_matches,
+    }))
+}
 
-    // Store job as pending
-    {
-        let mut jobs = state.jobs.write().await;
-        jobs.insert(job_id.clone(), ProofJobStatus::Pending);
+async fn get_proof_status(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(job_id): Path<String>,
+) -> Result<Json<ProofStatusResponse>, ApiError> {
+    match state.jobs.status(&job_id, &account.0).await {
+        Some((status, queue_position)) => Ok(Json(ProofStatusResponse {
+            job_id,
+            status,
+            queue_position,
+        })),
+        None => Err(ApiError::not_found(format!("Job not found: {}", job_id))),
     }
+}
 
-    // Build TaxInput for the SP1 prover
-    let input = TaxInput {
-        user_type,
-        wallets: vec![],
-        ledger: payload.ledger,
-        prices: payload.prices,
-        usd_inr_rate: payload.usd_inr_rate.clone(),
-        use_44ada: payload.use_44ada,
-    };
+/// How often a pending job's progress is sent while nothing else happens to it
+const PROOF_EVENT_INTERVAL: Duration = Duration::from_secs(5);
 
-    // Debug: Log categories being sent to prover
-    tracing::info!("=== PROOF REQUEST DEBUG ===");
-    tracing::info!("Job ID: {}", job_id);
-    tracing::info!("Ledger rows: {}", input.ledger.len());
-    for (i, row) in input.ledger.iter().enumerate() {
-        tracing::info!("  Row {}: asset={}, amount={}, category={:?}, direction={:?}",
-            i, row.asset, row.amount, row.category, row.direction);
+/// A job's status as server-sent events, named after the status: one now, then one
+/// whenever it changes (its stage, its place in the queue, its progress), until it finishes
+async fn proof_events(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Path(job_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    if state.jobs.status(&job_id, &account.0).await.is_none() {
+        return Err(ApiError::not_found(format!("Job not found: {}", job_id)));
     }
-    tracing::info!("Prices: {:?}", input.prices);
-    tracing::info!("USD/INR rate: {}", input.usd_inr_rate);
-    tracing::info!("===========================");
-
-    // Spawn background task to generate proof
-    let prover = state.prover.clone();
-    let jobs = state.jobs.clone();
-    let job_id_clone = job_id.clone();
-    let used_44ada = payload.use_44ada;
-
-    tokio::spawn(async move {
-        tracing::info!("Starting proof generation for job {}", job_id_clone);
-
-        // Run proof generation in blocking task (it's CPU-intensive)
-        let result = tokio::task::spawn_blocking(move || {
-            prover.prove(&input)
-        }).await;
-
-        let status = match result {
-            Ok(Ok(proof_artifacts)) => {
-                tracing::info!("Proof generated successfully for job {}", job_id_clone);
-                ProofJobStatus::Done {
-                    result: ProofResult {
-                        ledger_commitment: proof_artifacts.ledger_commitment,
-                        total_tax_paisa: proof_artifacts.total_tax_paisa,
-                        user_type_code,
-                        used_44ada,
-                        proof: proof_artifacts.proof,
-                        public_values: proof_artifacts.public_values,
-                        vk_hash: proof_artifacts.vk_hash,
-                    },
-                }
-            }
-            Ok(Err(e)) => {
-                tracing::error!("Proof generation failed for job {}: {}", job_id_clone, e);
-                ProofJobStatus::Error {
-                    error: format!("Proof generation failed: {}", e),
+    let updates = state.jobs.subscribe();
+    let events = futures::stream::unfold(Some((updates, String::new())), move |watching| {
+        let (state, account, job_id) = (state.clone(), account.clone(), job_id.clone());
+        async move {
+            let (mut updates, mut last) = watching?;
+            loop {
+                // End the stream on shutdown, so the server isn't kept waiting for it
+                if state.jobs.is_closing() {
+                    return None;
                 }
-            }
-            Err(e) => {
-                tracing::error!("Task panic for job {}: {}", job_id_clone, e);
-                ProofJobStatus::Error {
-                    error: format!("Task panic: {}", e),
+                let (status, queue_position) = state.jobs.status(&job_id, &account.0).await?;
+                let name = status.name();
+                let finished = !matches!(status, ProofJobStatus::Pending { .. });
+                let response = ProofStatusResponse {
+                    job_id: job_id.clone(),
+                    status,
+                    queue_position,
+                };
+                let data = serde_json::to_string(&response).unwrap_or_default();
+                if data != last {
+                    let event = Event::default().event(name).data(&data);
+                    last = data;
+                    return Some((Ok(event), (!finished).then_some((updates, last))));
                 }
+                // Wait for the queue to move, or for the elapsed time to
+                let _ = tokio::time::timeout(PROOF_EVENT_INTERVAL, updates.changed()).await;
             }
-        };
-
-        // Update job status
-        let mut jobs = jobs.write().await;
-        jobs.insert(job_id_clone, status);
+        }
     });
-
-    Ok(Json(ProofSubmitResponse { job_id }))
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
 }
 
-async fn get_proof_status(
+/// Cancel a pending proof job: a queued one never runs, a running one's proof is discarded
+///
+/// A finished job is deleted instead, with its proof, rather than kept until it expires.
+async fn cancel_proof(
     State(state): State<Arc<AppState>>,
+    account: Account,
     Path(job_id): Path<String>,
-) -> Result<Json<ProofStatusResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let jobs = state.jobs.read().await;
-
-    match jobs.get(&job_id) {
-        Some(status) => Ok(Json(ProofStatusResponse {
+) -> Result<Response, ApiError> {
+    match state.jobs.cancel(&job_id, &account.0).await {
+        Some(Cancelled::Queued | Cancelled::Running) => Ok(Json(ProofStatusResponse {
             job_id,
-            status: status.clone(),
-        })),
-        None => Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: format!("Job not found: {}", job_id),
-            }),
-        )),
+            status: ProofJobStatus::Cancelled,
+            queue_position: None,
+        })
+        .into_response()),
+        // Gone meanwhile if another request purged it first
+        Some(Cancelled::Finished) if state.jobs.purge(&job_id, &account.0).await => {
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        _ => Err(ApiError::not_found(format!("Job not found: {}", job_id))),
     }
 }
 
+// ============================================================================
+// TDS RECONCILIATION
+// ============================================================================
+
+#[derive(Deserialize)]
+struct TdsReconcileRequest {
+    /// Form 26AS / AIS CSV export
+    csv: String,
+    ledger: Vec<LedgerRow>,
+    prices: Vec<PriceEntry>,
+    usd_inr_rate: String,
+}
+
+async fn reconcile_tds_endpoint(
+    Json(payload): Json<TdsReconcileRequest>,
+) -> Result<Json<TdsReconciliation>, ApiError> {
+    let entries = parse_form_26as_csv(&payload.csv).map_err(|e| ApiError::invalid(e.to_string()))?;
+
+    Ok(Json(reconcile_tds(
+        &entries,
+        &payload.ledger,
+        &payload.prices,
+        &payload.usd_inr_rate,
+    )))
+}
+
+// ============================================================================
+// REFERENCE RATES
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ReferenceRatesImportRequest {
+    /// RBI/FBIL reference rate or SBI TT rate CSV download
+    csv: String,
+}
+
+/// Parse a reference rate download into the `reference_rates` of a tax or proof request
+async fn import_reference_rates(
+    Json(payload): Json<ReferenceRatesImportRequest>,
+) -> Result<Json<Vec<ReferenceRate>>, ApiError> {
+    parse_reference_rates_csv(&payload.csv).map(Json).map_err(tax_error)
+}
+
+// ============================================================================
+// SCHEDULE FA
+// ============================================================================
+
+#[derive(Deserialize)]
+struct ScheduleFaRequest {
+    /// Wallets/accounts held with foreign exchanges or custodians
+    accounts: Vec<ForeignAccount>,
+    ledger: Vec<LedgerRow>,
+    /// Prices as of the end of the reporting period
+    prices: Vec<PriceEntry>,
+    usd_inr_rate: String,
+    #[serde(default = "default_assessment_year")]
+    assessment_year: u16,
+}
+
+#[derive(Serialize)]
+struct ScheduleFaResponse {
+    /// Reporting period (calendar year, unix seconds)
+    period_start: u64,
+    period_end: u64,
+    rows: Vec<ScheduleFaRow>,
+}
+
+async fn schedule_fa_endpoint(Json(payload): Json<ScheduleFaRequest>) -> Json<ScheduleFaResponse> {
+    let (period_start, period_end) = schedule_fa_period(payload.assessment_year);
+
+    Json(ScheduleFaResponse {
+        period_start,
+        period_end,
+        rows: schedule_fa_rows(
+            &payload.accounts,
+            &payload.ledger,
+            &payload.prices,
+            &payload.usd_inr_rate,
+            payload.assessment_year,
+        ),
+    })
+}
+
 // ============================================================================
 // ENS SUBDOMAIN RESOLUTION
 // ============================================================================
@@ -349,6 +3145,9 @@ async fn get_proof_status(
 #[derive(Deserialize)]
 struct EnsResolveRequest {
     root_name: String,
+    /// Store the resolved wallets, labeled with their subdomains
+    #[serde(default)]
+    save: bool,
 }
 
 #[derive(Serialize)]
@@ -363,17 +3162,40 @@ struct EnsSubdomain {
     address: String,
 }
 
+/// Store wallets resolved from ENS subdomains, keeping the group and signature of any
+/// already stored
+async fn save_ens_wallets(data: &AccountData, subdomains: &[EnsSubdomain]) {
+    let mut wallets = data.wallets.write().await;
+    for subdomain in subdomains {
+        let Ok(address) = Address::parse(&subdomain.address) else {
+            continue;
+        };
+        match wallets.iter_mut().find(|wallet| wallet.address == address) {
+            Some(wallet) => {
+                wallet.label = Some(subdomain.name.clone());
+                wallet.source = WalletSource::EnsSubdomain;
+            }
+            None => wallets.push(Wallet {
+                id: format!("{:x}", rand::random::<u64>()),
+                address,
+                label: Some(subdomain.name.clone()),
+                group_id: None,
+                source: WalletSource::EnsSubdomain,
+                ownership_signature: None,
+            }),
+        }
+    }
+    wallets.save();
+}
+
 async fn resolve_ens(
     State(state): State<Arc<AppState>>,
+    account: Account,
     Json(payload): Json<EnsResolveRequest>,
-) -> Result<Json<EnsResolveResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<EnsResolveResponse>, ApiError> {
+    let data = state.account_data(&account).await?;
     if payload.root_name.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Root name is required".to_string(),
-            }),
-        ));
+        return Err(ApiError::invalid("Root name is required"));
     }
 
     match state.ens.resolve_subdomains(&payload.root_name).await {
@@ -388,19 +3210,220 @@ async fn resolve_ens(
                     })
                 })
                 .collect();
+            if payload.save {
+                save_ens_wallets(&data, &subdomains).await;
+            }
 
             Ok(Json(EnsResolveResponse { subdomains }))
         }
         Err(e) => {
             tracing::error!("Failed to resolve ENS subdomains: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to resolve ENS: {}", e),
-                }),
-            ))
+            Err(ApiError::new(ErrorCode::UpstreamFailed, format!("Failed to resolve ENS: {}", e)))
+        }
+    }
+}
+
+// ============================================================================
+// CATEGORIZATION RULES, KNOWN CONTRACTS AND SPAM
+// ============================================================================
+
+#[derive(Deserialize)]
+struct AddRulesRequest {
+    #[serde(default)]
+    rules: Vec<CategorizationRule>,
+    /// Replaces the bridge matching tolerance and window when given
+    #[serde(default)]
+    bridge_matching: Option<BridgeMatching>,
+}
+
+/// All rules in effect, built-in and user-defined
+async fn get_rules(State(state): State<Arc<AppState>>) -> Json<CategorizationRules> {
+    Json(state.categorization_rules.read().await.clone())
+}
+
+/// Add categorization rules; they apply to every account's subsequent `/transfers` calls,
+/// so only the operator can add them
+async fn add_rules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<AddRulesRequest>,
+) -> Result<Json<CategorizationRules>, ApiError> {
+    check_admin(&state, &headers)?;
+    let mut rules = state.categorization_rules.write().await;
+    let mut updated = rules.clone();
+    if let Some(bridge_matching) = payload.bridge_matching {
+        updated.bridge_matching = bridge_matching;
+    }
+    updated.extend(payload.rules).map_err(tax_error)?;
+    *rules = updated;
+    Ok(Json(rules.clone()))
+}
+
+/// Built-in rules plus any from `CATEGORIZATION_RULES_PATH` (JSON, or TOML by extension),
+/// whose bridge matching settings replace the defaults
+fn load_categorization_rules() -> anyhow::Result<CategorizationRules> {
+    let mut rules = CategorizationRules::default();
+    if let Ok(path) = std::env::var("CATEGORIZATION_RULES_PATH") {
+        let contents = std::fs::read_to_string(&path)?;
+        let extra = if path.ends_with(".toml") {
+            CategorizationRules::from_toml(&contents)?
+        } else {
+            CategorizationRules::from_json(&contents)?
+        };
+        tracing::info!("Loaded {} categorization rules from {}", extra.rules.len(), path);
+        rules.bridge_matching = extra.bridge_matching;
+        rules.extend(extra.rules)?;
+    }
+    Ok(rules)
+}
+
+#[derive(Deserialize)]
+struct RegisterContractsRequest {
+    contracts: Vec<KnownContract>,
+}
+
+/// Registered contracts
+async fn get_contracts(State(state): State<Arc<AppState>>) -> Json<ContractRegistry> {
+    Json(state.contracts.read().await.clone())
+}
+
+/// Register contracts (replacing existing entries for the same address and chain) for
+/// every account; operator only
+async fn register_contracts(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<RegisterContractsRequest>,
+) -> Result<Json<ContractRegistry>, ApiError> {
+    check_admin(&state, &headers)?;
+    let mut contracts = state.contracts.write().await;
+    // Validate everything before registering anything
+    let mut updated = contracts.clone();
+    for contract in payload.contracts {
+        updated.register(contract).map_err(tax_error)?;
+    }
+    *contracts = updated;
+    Ok(Json(contracts.clone()))
+}
+
+#[derive(Deserialize)]
+struct PriceOverridesRequest {
+    prices: Vec<PriceEntry>,
+}
+
+/// USD prices set by the account
+async fn get_price_overrides(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+) -> Result<Json<Vec<PriceEntry>>, ApiError> {
+    let data = state.account_data(&account).await?;
+    let prices = data.price_overrides.read().await.prices().to_vec();
+    Ok(Json(prices))
+}
+
+/// Set or correct the account's USD prices per (asset, date), e.g. for tokens the price
+/// provider doesn't index; they replace fetched and request prices from then on, and in its
+/// stored ledger
+async fn set_price_overrides(
+    State(state): State<Arc<AppState>>,
+    account: Account,
+    Json(payload): Json<PriceOverridesRequest>,
+) -> Result<Json<Vec<PriceEntry>>, ApiError> {
+    for price in &payload.prices {
+        if !price.usd_price.parse::<f64>().is_ok_and(|usd| usd.is_finite() && usd >= 0.0) {
+            return Err(ApiError::invalid(format!("Invalid price for {}: {}", price.asset, price.usd_price)));
+        }
+        if let Some(date) = &price.date {
+            chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| {
+                    ApiError::invalid(format!("Invalid date for {}: {} (expected YYYY-MM-DD)", price.asset, date))
+                })?;
+        }
+    }
+
+    let data = state.account_data(&account).await?;
+    let mut overrides = data.price_overrides.write().await;
+    overrides
+        .set(payload.prices)
+        .map_err(|e| ApiError::new(ErrorCode::Internal, format!("Failed to save price overrides: {}", e)))?;
+    let mut ledger = data.ledger.write().await;
+    overrides.merge_into(&mut ledger.prices);
+    ledger.save();
+    Ok(Json(overrides.prices().to_vec()))
+}
+
+#[derive(Deserialize)]
+struct AddLabelsRequest {
+    labels: Vec<AddressLabel>,
+}
+
+/// Known counterparty labels (bundled, Etherscan and user)
+async fn get_labels(State(state): State<Arc<AppState>>) -> Json<AddressLabels> {
+    Json(state.labels.read().await.clone())
+}
+
+/// Add user labels, which take precedence over bundled and Etherscan labels for every
+/// account; operator only
+async fn add_labels(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<AddLabelsRequest>,
+) -> Result<Json<AddressLabels>, ApiError> {
+    check_admin(&state, &headers)?;
+    let mut labels = state.labels.write().await;
+    // Validate everything before adding anything
+    let mut updated = labels.clone();
+    for label in payload.labels {
+        updated
+            .add(AddressLabel {
+                source: LabelSource::User,
+                ..label
+            })
+            .map_err(tax_error)?;
+    }
+    *labels = updated;
+    Ok(Json(labels.clone()))
+}
+
+#[derive(Deserialize)]
+struct SpamWhitelistRequest {
+    assets: Vec<String>,
+}
+
+/// Current spam whitelist and known-spam list
+async fn get_spam_settings(State(state): State<Arc<AppState>>) -> Json<SpamSettings> {
+    Json(state.spam_settings.read().await.clone())
+}
+
+/// Whitelist tokens so spam detection never flags them in any account; operator only
+async fn whitelist_tokens(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<SpamWhitelistRequest>,
+) -> Result<Json<SpamSettings>, ApiError> {
+    check_admin(&state, &headers)?;
+    let mut settings = state.spam_settings.write().await;
+    for asset in payload.assets {
+        if !settings.whitelist.iter().any(|w| w.eq_ignore_ascii_case(&asset)) {
+            settings.known_spam.retain(|s| !s.eq_ignore_ascii_case(&asset));
+            settings.whitelist.push(asset);
         }
     }
+    Ok(Json(settings.clone()))
+}
+
+/// Contracts from `CONTRACT_REGISTRY_PATH` (JSON, or TOML by extension), or the Sepolia demo contracts
+fn load_contract_registry() -> anyhow::Result<ContractRegistry> {
+    let Ok(path) = std::env::var("CONTRACT_REGISTRY_PATH") else {
+        return Ok(ContractRegistry::sepolia_demo());
+    };
+    let contents = std::fs::read_to_string(&path)?;
+    let registry = if path.ends_with(".toml") {
+        ContractRegistry::from_toml(&contents)?
+    } else {
+        ContractRegistry::from_json(&contents)?
+    };
+    tracing::info!("Loaded {} known contracts from {}", registry.contracts().len(), path);
+    Ok(registry)
 }
 
 #[tokio::main]
@@ -417,51 +3440,320 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Get Alchemy API key from environment
-    let alchemy_api_key = std::env::var("ALCHEMY_API_KEY")
-        .unwrap_or_else(|_| {
-            tracing::warn!("ALCHEMY_API_KEY not set, using demo key (rate limited)");
-            "demo".to_string()
-        });
+    let config = Config::load(&Cli::parse())?;
+
+    let alchemy_api_key = config.alchemy.api_key.clone().unwrap_or_else(|| {
+        tracing::warn!("No Alchemy API key configured, using demo key (rate limited)");
+        "demo".to_string()
+    });
+    let mut alchemy = AlchemyClient::new(alchemy_api_key).with_network(&config.alchemy.network)?;
+    if let Ok(max_transfers) = std::env::var("ALCHEMY_MAX_TRANSFERS") {
+        alchemy = alchemy.with_max_transfers(max_transfers.parse()?);
+    }
+    let max_concurrent = std::env::var("ALCHEMY_MAX_CONCURRENT_REQUESTS").ok();
+    let requests_per_second = std::env::var("ALCHEMY_REQUESTS_PER_SECOND").ok();
+    if max_concurrent.is_some() || requests_per_second.is_some() {
+        alchemy = alchemy.with_rate_limit(
+            max_concurrent.map_or(Ok(DEFAULT_MAX_CONCURRENT_REQUESTS), |value| value.parse())?,
+            requests_per_second.map_or(Ok(DEFAULT_REQUESTS_PER_SECOND), |value| value.parse())?,
+        )?;
+    }
+    // An empty TRANSFER_CACHE_DIR turns the cache off
+    let cache_dir = std::env::var("TRANSFER_CACHE_DIR").unwrap_or_else(|_| DEFAULT_TRANSFER_CACHE_DIR.to_string());
+    if !cache_dir.is_empty() {
+        let tip_ttl = match std::env::var("TRANSFER_CACHE_TTL_SECS") {
+            Ok(secs) => Duration::from_secs(secs.parse()?),
+            Err(_) => DEFAULT_TIP_TTL,
+        };
+        alchemy = alchemy.with_cache(TransferCache::new(cache_dir, tip_ttl));
+    }
+
+    let solana = SolanaClient::new(config.chains.solana_rpc_url.clone());
+    let bitcoin = EsploraClient::new(config.chains.esplora_url.clone());
+
+    let coingecko = CoinGeckoClient::new(
+        std::env::var("COINGECKO_API_URL").unwrap_or_else(|_| prices::DEFAULT_COINGECKO_API_URL.to_string()),
+        std::env::var("COINGECKO_API_KEY").ok(),
+    );
+    let chainlink = match std::env::var("CHAINLINK_RPC_URL") {
+        Ok(rpc_url) => Some(
+            ChainlinkClient::new(rpc_url)
+                .with_feeds(parse_asset_pairs(&std::env::var("CHAINLINK_FEEDS").unwrap_or_default())?),
+        ),
+        Err(_) => None,
+    };
+    let mut price_sources = HashMap::new();
+    for (asset, source) in parse_asset_pairs(&std::env::var("PRICE_SOURCES").unwrap_or_default())? {
+        let source: PriceSource = source.parse()?;
+        if source == PriceSource::Chainlink && chainlink.is_none() {
+            anyhow::bail!("PRICE_SOURCES uses Chainlink for {} but CHAINLINK_RPC_URL is not set", asset);
+        }
+        price_sources.insert(asset, source);
+    }
+
+    let price_overrides_path = PathBuf::from(
+        std::env::var("PRICE_OVERRIDES_PATH").unwrap_or_else(|_| DEFAULT_PRICE_OVERRIDES_PATH.to_string()),
+    );
+
+    let etherscan = std::env::var("ETHERSCAN_API_KEY").ok().map(EtherscanClient::new);
+    if etherscan.is_none() {
+        tracing::info!("ETHERSCAN_API_KEY not set, labeling counterparties from bundled and user labels only");
+    }
 
     // Initialize SP1 prover (this loads proving parameters)
     tracing::info!("Initializing SP1 prover...");
-    let prover = Arc::new(TaxProver::new()?);
+    let mut prover = TaxProver::with_config(ProverConfig::from_env()?)?;
+    match NetworkConfig::from_env() {
+        Some(config) => {
+            tracing::info!("Proving on the Succinct prover network, falling back to local proving");
+            prover = prover.with_network(config);
+        }
+        None => tracing::info!("NETWORK_PRIVATE_KEY not set, proving locally"),
+    }
+    // Part proofs are kept so that proving a ledger in parts again only proves the parts that
+    // changed; an empty PART_PROOF_CACHE_DIR proves every part every time
+    let part_cache_dir =
+        std::env::var("PART_PROOF_CACHE_DIR").unwrap_or_else(|_| DEFAULT_PART_PROOF_CACHE_DIR.to_string());
+    if !part_cache_dir.is_empty() {
+        prover = prover.with_part_cache(PartCache::new(part_cache_dir));
+    }
+    let prover = Arc::new(prover);
     tracing::info!("SP1 prover initialized successfully");
     tracing::info!("VK hash: {}", prover.get_vk_hash());
+    tracing::info!("VK digest (for aggregation): {}", prover.get_vk_digest());
+
+    // Queue proof jobs for a fixed number of workers
+    let max_jobs_per_user = match std::env::var("MAX_PROOF_JOBS_PER_USER") {
+        Ok(max) => max.parse()?,
+        Err(_) => DEFAULT_MAX_JOBS_PER_USER,
+    };
+    let workers = config.prover.workers;
+    // Jobs are kept on disk so proofs outlive a restart; an empty PROOF_JOB_DIR keeps them
+    // in memory only
+    let mut jobs = ProofQueue::new(max_jobs_per_user);
+    let job_dir = std::env::var("PROOF_JOB_DIR").unwrap_or_else(|_| DEFAULT_PROOF_JOB_DIR.to_string());
+    if !job_dir.is_empty() {
+        jobs = jobs.with_store(JobStore::new(job_dir));
+    }
+    if let Some(secret) = &config.prover.callback_secret {
+        jobs = jobs.with_callbacks(Callbacks::new(secret.clone()));
+    }
+    let jobs = Arc::new(jobs);
+    jobs.spawn_workers(prover.clone(), workers);
+    tracing::info!("Proving up to {} jobs at once", workers);
+    let job_retention = match std::env::var("PROOF_JOB_RETENTION_SECS") {
+        Ok(secs) => Duration::from_secs(secs.parse()?),
+        Err(_) => DEFAULT_JOB_RETENTION,
+    };
+    jobs.spawn_expiry(job_retention);
+    let max_ledger_rows = match std::env::var("MAX_LEDGER_ROWS") {
+        Ok(rows) => rows.parse()?,
+        Err(_) => DEFAULT_MAX_LEDGER_ROWS,
+    };
+
+    let relayer = Relayer::from_env()?;
+    match &relayer {
+        Some(relayer) => tracing::info!("Submitting proofs on-chain from {}", relayer.address()),
+        None => tracing::info!("RELAYER_PRIVATE_KEY not set, proofs are submitted on-chain by users only"),
+    }
+
+    let categorization_rules = RwLock::new(load_categorization_rules()?);
+    let contracts = RwLock::new(load_contract_registry()?);
+    let sync_state_path =
+        PathBuf::from(std::env::var("SYNC_STATE_PATH").unwrap_or_else(|_| DEFAULT_SYNC_STATE_PATH.to_string()));
+    // The ledger and wallets are kept on disk; an empty storage directory keeps them in
+    // memory only
+    let storage_dir = config.storage_dir();
+    // Accounts with stored data are indexed by wallet now, so that webhooks reach them
+    let mut stored_wallets = HashMap::new();
+    let mut stored_accounts = vec![Account::default()];
+    if let Some(Ok(dirs)) = storage_dir.as_ref().map(|dir| std::fs::read_dir(dir.join("accounts"))) {
+        for dir in dirs.filter_map(Result::ok).filter(|dir| dir.path().is_dir()) {
+            stored_accounts.push(Account(dir.file_name().to_string_lossy().into_owned()));
+        }
+    }
+    for account in stored_accounts {
+        let data = AccountData::load(storage_dir.as_deref(), &sync_state_path, &price_overrides_path, &account)?;
+        stored_wallets.insert(account, data.watched_wallets().await);
+    }
+    let require_auth = !std::env::var("REQUIRE_AUTH").is_ok_and(|value| value == "false" || value == "0");
+    if !require_auth {
+        tracing::warn!("REQUIRE_AUTH=false: requests without a session share the anonymous account's data and jobs");
+    }
+    let api_keys = ApiKeys::load(storage_dir.as_deref())?;
+    let api_key_admin_token = std::env::var("API_KEY_ADMIN_TOKEN").ok().filter(|token| !token.is_empty());
+    if api_key_admin_token.is_none() {
+        tracing::info!("API_KEY_ADMIN_TOKEN not set, API keys, rules, contracts, labels and spam whitelist are fixed");
+    }
 
-    // Initialize job storage
-    let jobs: ProofJobs = Arc::new(RwLock::new(HashMap::new()));
+    #[cfg(feature = "ml")]
+    let model = ml::ModelCategorizer::from_env()?.map(Arc::new);
 
     let state = Arc::new(AppState {
-        alchemy: AlchemyClient::new(alchemy_api_key),
-        ens: EnsResolver::new(),
+        alchemy,
+        solana,
+        bitcoin,
+        ens: EnsResolver::new(config.ens.subgraph_url.clone()),
+        coingecko,
+        chainlink,
+        price_sources,
         prover,
-        jobs,
+        jobs: jobs.clone(),
+        relayer,
+        categorization_rules,
+        contracts,
+        spam_settings: RwLock::new(SpamSettings::default()),
+        etherscan,
+        labels: RwLock::new(AddressLabels::bundled()),
+        untagged: RwLock::new(HashSet::new()),
+        max_ledger_rows,
+        default_proof_mode: config.prover.mode,
+        storage_dir,
+        sync_state_path,
+        price_overrides_path,
+        accounts: RwLock::new(HashMap::new()),
+        stored_wallets,
+        sessions: Sessions::new(SiweConfig::from_env()),
+        require_auth,
+        api_keys,
+        api_key_admin_token,
+        webhook_signing_key: std::env::var("ALCHEMY_WEBHOOK_SIGNING_KEY").ok(),
+        #[cfg(feature = "ml")]
+        model,
     });
 
-    // CORS configuration
+    // CORS configuration: the configured origins, else any
+    let origins = config.cors_origins.iter().map(|origin| origin.parse()).collect::<Result<Vec<_>, _>>()?;
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(if origins.is_empty() { AllowOrigin::from(Any) } else { AllowOrigin::list(origins) })
         .allow_methods(Any)
         .allow_headers(Any);
 
     // Build router
     let app = Router::new()
         .route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .route("/auth/nonce", post(auth_nonce))
+        .route("/auth/verify", post(auth_verify))
+        .route("/auth/logout", post(auth_logout))
+        .route("/api-keys", get(list_api_keys).post(issue_api_key))
+        .route("/api-keys/{key_id}", delete(revoke_api_key))
         .route("/transfers", post(get_transfers))
+        .route("/wallets", get(list_wallets).post(add_wallet))
+        .route("/wallets/{wallet}", patch(update_wallet).delete(delete_wallet))
+        .route("/wallets/{wallet}/sync", post(sync_wallet))
+        .route("/wallet-groups", get(list_wallet_groups).post(add_wallet_group))
+        .route("/wallet-groups/{group_id}", patch(update_wallet_group).delete(delete_wallet_group))
+        .route("/webhooks/alchemy", post(alchemy_webhook))
+        .route("/ledger/review", get(get_review_queue))
+        .route("/ledger/import", post(import_ledger))
+        .route("/exchanges/{exchange}/import", post(import_exchange))
+        .route("/exchanges/{exchange}/statement", post(import_exchange_statement))
+        .route("/ledger/recategorize", post(recategorize_ledger))
+        .route("/ledger/wash-trades", post(wash_trades_endpoint))
+        .route("/ledger/{row_id}", patch(review_ledger_row))
         .route("/tax", post(calculate_tax_endpoint))
-        .route("/proofs", post(submit_proof))
-        .route("/proofs/{job_id}", get(get_proof_status))
+        .route("/tax/simulate", post(simulate_tax_endpoint))
+        .route("/tax/multi-year", post(multi_year_tax_endpoint))
+        .route("/reports", get(list_reports).post(save_report))
+        .route("/reports/{report_id}", get(get_report))
+        .route("/reports/{report_id}/proof", post(attach_report_proof))
+        .route("/proofs", get(list_proofs).post(submit_proof))
+        .route("/proofs/dry-run", post(dry_run_proof))
+        .route("/proofs/aggregate", post(submit_aggregate_proof))
+        .route("/proofs/batch", post(submit_proof_batch))
+        .route("/proofs/batch/{batch_id}", get(get_proof_batch))
+        .route("/proofs/batch/{batch_id}/bundle", get(get_proof_batch_bundle))
+        .route("/proofs/ownership-message", get(get_ownership_message))
+        .route("/proofs/{job_id}", get(get_proof_status).delete(cancel_proof))
+        .route("/proofs/{job_id}/events", get(proof_events))
+        .route("/proofs/{job_id}/calldata", get(get_proof_calldata))
+        .route("/proofs/{job_id}/submit-onchain", post(submit_proof_onchain))
+        .route("/verify", post(verify_proof))
+        .route("/prover/info", get(get_prover_info))
+        .route("/prover/versions", get(get_program_versions))
+        .route("/tds/reconcile", post(reconcile_tds_endpoint))
+        .route("/reference-rates/import", post(import_reference_rates))
+        .route("/schedule-fa", post(schedule_fa_endpoint))
+        .route("/rules", get(get_rules).post(add_rules))
+        .route("/contracts", get(get_contracts).post(register_contracts))
+        .route("/labels", get(get_labels).post(add_labels))
+        .route("/prices", get(get_price_overrides).put(set_price_overrides))
+        .route("/spam", get(get_spam_settings))
+        .route("/spam/whitelist", post(whitelist_tokens))
         .route("/ens/resolve", post(resolve_ens))
+        .layer(middleware::from_fn_with_state(state.clone(), api_key_auth))
         .layer(cors)
+        .layer(middleware::from_fn(trace_requests))
         .with_state(state);
 
     // Start server
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3001").await?;
-    tracing::info!("🚀 Financoor API running on http://localhost:3001");
+    let address = SocketAddr::new(config.bind, config.port);
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    tracing::info!("🚀 Financoor API running on http://{}", address);
 
-    axum::serve(listener, app).await?;
+    // On a signal, stop accepting requests and taking queued jobs, let the requests in
+    // flight finish (event streams end), then give the running proofs their grace period
+    let queue = jobs.clone();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            tracing::info!("Shutting down: no longer accepting requests or starting proof jobs");
+            queue.shutdown();
+        })
+        .await?;
 
+    let grace = Duration::from_secs(config.prover.shutdown_grace_secs);
+    let interrupted = jobs.drain(grace).await;
+    let (queued, _) = jobs.depth().await;
+    if jobs.persists() {
+        if !interrupted.is_empty() || queued > 0 {
+            tracing::warn!(
+                "Interrupted {} running proof jobs ({}); they and {} queued jobs run again on the next start",
+                interrupted.len(),
+                interrupted.join(", "),
+                queued
+            );
+        }
+    } else if !interrupted.is_empty() || queued > 0 {
+        tracing::warn!(
+            "Dropping {} running and {} queued proof jobs, which aren't stored (PROOF_JOB_DIR is empty)",
+            interrupted.len(),
+            queued
+        );
+    }
+    tracing::info!("Shut down");
+    // Proofs still running hold blocking threads the runtime would wait for
+    if !interrupted.is_empty() {
+        std::process::exit(0);
+    }
     Ok(())
 }
+
+/// Resolves on Ctrl+C or, on Unix, SIGTERM (what `docker stop` and systemd send)
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
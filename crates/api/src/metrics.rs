@@ -0,0 +1,179 @@
+//! Prometheus metrics
+//!
+//! Request latencies, Alchemy requests, proof durations and cycle counts are recorded as
+//! they happen into the process-wide `METRICS`, and `GET /metrics` renders them in the
+//! Prometheus text format along with the proof queue's depth.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use financoor_prover::ProofMode;
+
+/// Upper bounds of the request latency buckets (seconds)
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Upper bounds of the proof duration buckets (seconds)
+const PROOF_BUCKETS: [f64; 10] = [1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0];
+
+/// Upper bounds of the cycle count buckets
+const CYCLE_BUCKETS: [f64; 6] = [1e5, 1e6, 1e7, 1e8, 1e9, 1e10];
+
+pub static METRICS: Metrics = Metrics::new();
+
+/// Counts of observations up to each bucket's bound, and their sum
+#[derive(Default)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, bounds: &[f64], value: f64) {
+        self.buckets.resize(bounds.len(), 0);
+        for (bucket, bound) in self.buckets.iter_mut().zip(bounds) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// `name_bucket`, `name_sum` and `name_count` lines, with `labels` (`key="value"`, ...)
+    fn render(&self, out: &mut String, name: &str, labels: &str, bounds: &[f64]) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (bucket, bound) in self.buckets.iter().zip(bounds) {
+            let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, separator, bound, bucket);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, separator, self.count);
+        let labels = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, self.count);
+    }
+}
+
+pub struct Metrics {
+    /// By method, route and status
+    requests: Mutex<BTreeMap<(String, String, u16), Histogram>>,
+    /// Alchemy HTTP requests, by outcome
+    alchemy_requests: Mutex<BTreeMap<&'static str, u64>>,
+    /// Proof jobs' time from starting to finishing, by mode and outcome
+    proofs: Mutex<BTreeMap<(String, &'static str), Histogram>>,
+    cycles: Mutex<Histogram>,
+    /// Jobs started since the process started
+    proofs_started: AtomicU64,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            requests: Mutex::new(BTreeMap::new()),
+            alchemy_requests: Mutex::new(BTreeMap::new()),
+            proofs: Mutex::new(BTreeMap::new()),
+            cycles: Mutex::new(Histogram {
+                buckets: Vec::new(),
+                sum: 0.0,
+                count: 0,
+            }),
+            proofs_started: AtomicU64::new(0),
+        }
+    }
+
+    /// A request answered; `route` is the matched route, e.g. `/proofs/{job_id}`
+    pub fn request(&self, method: &str, route: &str, status: u16, latency: Duration) {
+        let mut requests = self.requests.lock().unwrap();
+        let histogram = requests.entry((method.to_string(), route.to_string(), status)).or_default();
+        histogram.observe(&LATENCY_BUCKETS, latency.as_secs_f64());
+    }
+
+    /// An Alchemy request sent: "ok", "rate_limited", "http_error" or "connection_error"
+    pub fn alchemy_request(&self, outcome: &'static str) {
+        *self.alchemy_requests.lock().unwrap().entry(outcome).or_default() += 1;
+    }
+
+    pub fn proof_started(&self) {
+        self.proofs_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A job's program executed in `cycles`
+    pub fn proof_executed(&self, cycles: u64) {
+        self.cycles.lock().unwrap().observe(&CYCLE_BUCKETS, cycles as f64);
+    }
+
+    /// A job finished: "done" or "error"
+    pub fn proof_finished(&self, mode: ProofMode, outcome: &'static str, duration: Duration) {
+        let mode = format!("{:?}", mode).to_lowercase();
+        let mut proofs = self.proofs.lock().unwrap();
+        proofs.entry((mode, outcome)).or_default().observe(&PROOF_BUCKETS, duration.as_secs_f64());
+    }
+
+    /// Every metric in the Prometheus text format, with the jobs queued and running now
+    pub fn render(&self, queued: usize, running: usize) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP financoor_http_request_duration_seconds Time to answer a request\n");
+        out.push_str("# TYPE financoor_http_request_duration_seconds histogram\n");
+        for ((method, route, status), histogram) in self.requests.lock().unwrap().iter() {
+            let labels = format!("method=\"{}\",route=\"{}\",status=\"{}\"", method, route, status);
+            histogram.render(&mut out, "financoor_http_request_duration_seconds", &labels, &LATENCY_BUCKETS);
+        }
+
+        out.push_str("# HELP financoor_alchemy_requests_total Requests sent to Alchemy, retries included\n");
+        out.push_str("# TYPE financoor_alchemy_requests_total counter\n");
+        for (outcome, count) in self.alchemy_requests.lock().unwrap().iter() {
+            let _ = writeln!(out, "financoor_alchemy_requests_total{{outcome=\"{}\"}} {}", outcome, count);
+        }
+
+        out.push_str("# HELP financoor_proofs_started_total Proof jobs started\n");
+        out.push_str("# TYPE financoor_proofs_started_total counter\n");
+        let _ = writeln!(out, "financoor_proofs_started_total {}", self.proofs_started.load(Ordering::Relaxed));
+
+        out.push_str("# HELP financoor_proof_duration_seconds Time from starting a proof job to finishing it\n");
+        out.push_str("# TYPE financoor_proof_duration_seconds histogram\n");
+        for ((mode, outcome), histogram) in self.proofs.lock().unwrap().iter() {
+            let labels = format!("mode=\"{}\",outcome=\"{}\"", mode, outcome);
+            histogram.render(&mut out, "financoor_proof_duration_seconds", &labels, &PROOF_BUCKETS);
+        }
+
+        out.push_str("# HELP financoor_proof_cycles Cycles the tax program ran for, per proof job\n");
+        out.push_str("# TYPE financoor_proof_cycles histogram\n");
+        self.cycles.lock().unwrap().render(&mut out, "financoor_proof_cycles", "", &CYCLE_BUCKETS);
+
+        out.push_str("# HELP financoor_proof_jobs_queued Proof jobs waiting for a worker\n");
+        out.push_str("# TYPE financoor_proof_jobs_queued gauge\n");
+        let _ = writeln!(out, "financoor_proof_jobs_queued {}", queued);
+        out.push_str("# HELP financoor_proof_jobs_running Proof jobs being proved\n");
+        out.push_str("# TYPE financoor_proof_jobs_running gauge\n");
+        let _ = writeln!(out, "financoor_proof_jobs_running {}", running);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_render_as_prometheus_text() {
+        let metrics = Metrics::new();
+        metrics.request("GET", "/proofs/{job_id}", 200, Duration::from_millis(20));
+        metrics.request("GET", "/proofs/{job_id}", 200, Duration::from_secs(3));
+        metrics.alchemy_request("rate_limited");
+        metrics.proof_executed(2_000_000);
+        metrics.proof_finished(ProofMode::Groth16, "done", Duration::from_secs(90));
+
+        let text = metrics.render(2, 1);
+        let route = "method=\"GET\",route=\"/proofs/{job_id}\",status=\"200\"";
+        assert!(text.contains(&format!("financoor_http_request_duration_seconds_bucket{{{},le=\"0.025\"}} 1", route)));
+        assert!(text.contains(&format!("financoor_http_request_duration_seconds_count{{{}}} 2", route)));
+        assert!(text.contains("financoor_alchemy_requests_total{outcome=\"rate_limited\"} 1"));
+        assert!(text.contains("financoor_proof_cycles_bucket{le=\"10000000\"} 1"));
+        let proofs = "mode=\"groth16\",outcome=\"done\"";
+        assert!(text.contains(&format!("financoor_proof_duration_seconds_bucket{{{},le=\"120\"}} 1", proofs)));
+        assert!(text.contains("financoor_proof_jobs_queued 2"));
+    }
+}
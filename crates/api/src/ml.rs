@@ -0,0 +1,239 @@
+//! Model-assisted categorization (`ml` feature)
+//!
+//! Rows the rules leave Unknown can be passed to a model: a local ONNX classifier served
+//! over the KServe v2 inference protocol (ONNX Runtime server, Triton), or an LLM behind
+//! an OpenAI-compatible chat completions endpoint. The model's confidence is recorded in
+//! `LedgerRow.model_confidence`, separately from the rule confidence. Enabled by setting
+//! `ML_CATEGORIZER` to `onnx` or `llm`.
+
+use anyhow::{anyhow, Result};
+use financoor_core::{CategorizationContext, CategorizationResult, Categorizer, Category, Direction, LedgerRow};
+use serde::Deserialize;
+use serde_json::json;
+
+const DEFAULT_ONNX_URL: &str = "http://localhost:8000/v2/models/financoor/infer";
+const DEFAULT_LLM_URL: &str = "http://localhost:11434/v1/chat/completions";
+const DEFAULT_LLM_MODEL: &str = "llama3.1";
+
+/// Output classes of the ONNX model, in order
+const ONNX_CATEGORIES: [Category; 8] = [
+    Category::Income,
+    Category::Gains,
+    Category::Losses,
+    Category::Gift,
+    Category::Airdrop,
+    Category::Fees,
+    Category::Internal,
+    Category::StakingReward,
+];
+
+/// Where the model runs
+pub enum ModelBackend {
+    /// ONNX classifier at a KServe v2 `infer` URL
+    Onnx { url: String },
+    /// OpenAI-compatible chat completions endpoint
+    Llm {
+        url: String,
+        model: String,
+        api_key: Option<String>,
+    },
+}
+
+/// Categorizer backed by a model
+pub struct ModelCategorizer {
+    client: reqwest::Client,
+    backend: ModelBackend,
+    /// Runtime to make requests on; `categorize` is called from a blocking task
+    runtime: tokio::runtime::Handle,
+}
+
+#[derive(Debug, Deserialize)]
+struct InferResponse {
+    outputs: Vec<InferOutput>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InferOutput {
+    data: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+/// What the LLM is asked to reply with
+#[derive(Debug, Deserialize)]
+struct LlmAnswer {
+    category: Category,
+    confidence: f32,
+}
+
+impl ModelCategorizer {
+    pub fn new(backend: ModelBackend) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            backend,
+            runtime: tokio::runtime::Handle::current(),
+        }
+    }
+
+    /// Configure from `ML_CATEGORIZER` (`onnx` or `llm`) and `ML_ONNX_URL`, or `ML_LLM_URL`,
+    /// `ML_LLM_MODEL` and `ML_LLM_API_KEY`; `None` when unset
+    pub fn from_env() -> Result<Option<Self>> {
+        let var = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+        let backend = match std::env::var("ML_CATEGORIZER").ok().as_deref() {
+            None | Some("") => return Ok(None),
+            Some("onnx") => ModelBackend::Onnx {
+                url: var("ML_ONNX_URL", DEFAULT_ONNX_URL),
+            },
+            Some("llm") => ModelBackend::Llm {
+                url: var("ML_LLM_URL", DEFAULT_LLM_URL),
+                model: var("ML_LLM_MODEL", DEFAULT_LLM_MODEL),
+                api_key: std::env::var("ML_LLM_API_KEY").ok(),
+            },
+            Some(other) => return Err(anyhow!("ML_CATEGORIZER must be onnx or llm, got {}", other)),
+        };
+        Ok(Some(Self::new(backend)))
+    }
+
+    async fn classify(&self, row: &LedgerRow, context: &CategorizationContext<'_>) -> Result<CategorizationResult> {
+        match &self.backend {
+            ModelBackend::Onnx { url } => {
+                let features = features(row, context);
+                let request = json!({
+                    "inputs": [{
+                        "name": "features",
+                        "shape": [1, features.len()],
+                        "datatype": "FP32",
+                        "data": features,
+                    }]
+                });
+                let response: InferResponse =
+                    self.client.post(url).json(&request).send().await?.error_for_status()?.json().await?;
+                let probabilities = response.outputs.into_iter().next().map(|output| output.data).unwrap_or_default();
+                let (class, confidence) = probabilities
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .ok_or_else(|| anyhow!("model returned no probabilities"))?;
+                let category = *ONNX_CATEGORIES.get(class).ok_or_else(|| {
+                    anyhow!("model returned {} classes, expected {}", probabilities.len(), ONNX_CATEGORIES.len())
+                })?;
+                Ok(CategorizationResult { category, confidence })
+            }
+            ModelBackend::Llm { url, model, api_key } => {
+                let request = json!({
+                    "model": model,
+                    "temperature": 0,
+                    "messages": [
+                        { "role": "system", "content": LLM_INSTRUCTIONS },
+                        { "role": "user", "content": describe(row, context) },
+                    ],
+                });
+                let mut builder = self.client.post(url).json(&request);
+                if let Some(api_key) = api_key {
+                    builder = builder.bearer_auth(api_key);
+                }
+                let response: ChatResponse = builder.send().await?.error_for_status()?.json().await?;
+                let content = response
+                    .choices
+                    .into_iter()
+                    .next()
+                    .map(|choice| choice.message.content)
+                    .ok_or_else(|| anyhow!("LLM returned no answer"))?;
+                let answer = parse_answer(&content)?;
+                Ok(CategorizationResult {
+                    category: answer.category,
+                    confidence: answer.confidence.clamp(0.0, 1.0),
+                })
+            }
+        }
+    }
+}
+
+impl Categorizer for ModelCategorizer {
+    fn categorize(&self, row: &LedgerRow, context: &CategorizationContext) -> CategorizationResult {
+        match self.runtime.block_on(self.classify(row, context)) {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::warn!("Model categorization of {} failed: {}", row.tx_hash, e);
+                CategorizationResult {
+                    category: Category::Unknown,
+                    confidence: 0.0,
+                }
+            }
+        }
+    }
+}
+
+const LLM_INSTRUCTIONS: &str = "You categorize crypto transfers for Indian income tax. Reply with only a JSON \
+object {\"category\": ..., \"confidence\": ...} where category is one of income, gains, losses, gift, airdrop, \
+fees, internal, staking_reward or unknown, and confidence is between 0 and 1.";
+
+/// Model inputs: inflow, ln(1 + amount), native asset, NFT, labeled counterparty, own-wallet counterparty
+fn features(row: &LedgerRow, context: &CategorizationContext) -> Vec<f32> {
+    let flag = |value: bool| if value { 1.0 } else { 0.0 };
+    let amount = row.amount.parse::<f32>().unwrap_or(0.0);
+    vec![
+        flag(row.direction == Direction::In),
+        amount.max(0.0).ln_1p(),
+        flag(row.contract_address.is_none()),
+        flag(row.token_id.is_some()),
+        flag(row.counterparty_label.is_some()),
+        flag(row.counterparty.as_deref().is_some_and(|cp| context.is_own_wallet(cp))),
+    ]
+}
+
+/// The transfer as the LLM sees it
+fn describe(row: &LedgerRow, context: &CategorizationContext) -> String {
+    let counterparty = row.counterparty.as_deref().unwrap_or("none");
+    let own = row.counterparty.as_deref().is_some_and(|cp| context.is_own_wallet(cp));
+    format!(
+        "Chain {}: {} {} {} {} counterparty {} (label: {}, user's own wallet: {}), token id: {}",
+        row.chain_id,
+        if row.direction == Direction::In { "received" } else { "sent" },
+        row.amount,
+        row.asset,
+        if row.direction == Direction::In { "from" } else { "to" },
+        counterparty,
+        row.counterparty_label.as_deref().unwrap_or("none"),
+        own,
+        row.token_id.as_deref().unwrap_or("none"),
+    )
+}
+
+/// The JSON object in an LLM reply, which may be wrapped in prose or a code fence
+fn parse_answer(content: &str) -> Result<LlmAnswer> {
+    let start = content.find('{').ok_or_else(|| anyhow!("no JSON in LLM answer: {}", content))?;
+    let end = content
+        .rfind('}')
+        .filter(|&end| end > start)
+        .ok_or_else(|| anyhow!("no JSON in LLM answer: {}", content))?;
+    Ok(serde_json::from_str(&content[start..=end])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fenced_llm_answer() {
+        let answer = parse_answer("```json\n{\"category\": \"staking_reward\", \"confidence\": 0.75}\n```").unwrap();
+        assert_eq!(answer.category, Category::StakingReward);
+        assert_eq!(answer.confidence, 0.75);
+
+        assert!(parse_answer("I can't tell").is_err());
+    }
+}
@@ -0,0 +1,271 @@
+//! Historical USD prices
+//!
+//! A `PriceProvider` gives an asset's USD price on each of a set of days, as dated
+//! `PriceEntry`s, so that every row is valued on the day it happened rather than at one
+//! price for the whole year. CoinGecko serves them from `/coins/{id}/market_chart/range`,
+//! which returns daily points for ranges over 90 days and finer ones otherwise; the first
+//! point of each UTC day is taken as that day's price.
+//!
+//! Chainlink feeds (see `chainlink`) can stand in for CoinGecko per asset through
+//! `PRICE_SOURCES`.
+//!
+//! Prices users set themselves, e.g. for illiquid tokens CoinGecko doesn't index, are kept
+//! in a JSON file per account and replace provider and request prices for the same asset
+//! and day.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use financoor_core::{price_date, PriceEntry};
+use serde::Deserialize;
+
+pub const DEFAULT_COINGECKO_API_URL: &str = "https://api.coingecko.com/api/v3";
+
+/// Where the anonymous account's price overrides are kept unless `PRICE_OVERRIDES_PATH`
+/// says otherwise
+pub const DEFAULT_PRICE_OVERRIDES_PATH: &str = "price_overrides.json";
+
+/// CoinGecko IDs of the symbols priced; other assets are left to user prices
+const COIN_IDS: [(&str, &str); 14] = [
+    ("ETH", "ethereum"),
+    ("WETH", "weth"),
+    ("BTC", "bitcoin"),
+    ("WBTC", "wrapped-bitcoin"),
+    ("SOL", "solana"),
+    ("POL", "polygon-ecosystem-token"),
+    ("MATIC", "matic-network"),
+    ("USDC", "usd-coin"),
+    ("USDT", "tether"),
+    ("DAI", "dai"),
+    ("ARB", "arbitrum"),
+    ("OP", "optimism"),
+    ("LINK", "chainlink"),
+    ("UNI", "uniswap"),
+];
+
+/// Provider an asset's historical prices are fetched from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceSource {
+    CoinGecko,
+    Chainlink,
+}
+
+impl std::str::FromStr for PriceSource {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "coingecko" => Ok(Self::CoinGecko),
+            "chainlink" => Ok(Self::Chainlink),
+            other => Err(anyhow!("Unknown price source: {}", other)),
+        }
+    }
+}
+
+/// Parse comma-separated `ASSET=value` pairs (`PRICE_SOURCES`, `CHAINLINK_FEEDS`); assets
+/// are uppercased
+pub fn parse_asset_pairs(value: &str) -> Result<Vec<(String, String)>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (asset, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected ASSET=value, got '{}'", pair.trim()))?;
+            Ok((asset.trim().to_uppercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Source of historical USD prices
+#[async_trait]
+pub trait PriceProvider: Send + Sync {
+    /// Name used in logs and errors ("coingecko", ...)
+    fn name(&self) -> &'static str;
+
+    /// USD price of an asset on each of `dates` ("YYYY-MM-DD", UTC) it has one for
+    async fn daily_prices(&self, asset: &str, dates: &[String]) -> Result<Vec<PriceEntry>>;
+}
+
+pub struct CoinGeckoClient {
+    client: reqwest::Client,
+    base_url: String,
+    /// Demo or Pro API key; the public API works without one at a lower rate limit
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MarketChart {
+    /// (unix milliseconds, USD price)
+    prices: Vec<(f64, f64)>,
+}
+
+impl CoinGeckoClient {
+    pub fn new(base_url: String, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for CoinGeckoClient {
+    fn name(&self) -> &'static str {
+        "coingecko"
+    }
+
+    async fn daily_prices(&self, asset: &str, dates: &[String]) -> Result<Vec<PriceEntry>> {
+        let Some((_, id)) = COIN_IDS.iter().find(|(symbol, _)| symbol.eq_ignore_ascii_case(asset)) else {
+            return Ok(Vec::new());
+        };
+        let days: Vec<NaiveDate> = dates
+            .iter()
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d"))
+            .collect::<Result<_, _>>()?;
+        let (Some(first), Some(last)) = (days.iter().min(), days.iter().max()) else {
+            return Ok(Vec::new());
+        };
+        let timestamp = |day: &NaiveDate| day.and_hms_opt(0, 0, 0).map_or(0, |time| time.and_utc().timestamp());
+
+        let url = format!(
+            "{}/coins/{}/market_chart/range?vs_currency=usd&from={}&to={}",
+            self.base_url,
+            id,
+            timestamp(first),
+            timestamp(last) + 86_400
+        );
+        let mut request = self.client.get(url);
+        if let Some(api_key) = &self.api_key {
+            let header = if self.base_url.contains("pro-api") { "x-cg-pro-api-key" } else { "x-cg-demo-api-key" };
+            request = request.header(header, api_key);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("CoinGecko API error: HTTP {}", response.status()));
+        }
+        let chart: MarketChart = response.json().await?;
+
+        let daily = daily_prices(&chart.prices);
+        Ok(dates
+            .iter()
+            .filter_map(|date| {
+                daily.get(date).map(|price| PriceEntry {
+                    asset: asset.to_string(),
+                    usd_price: price.to_string(),
+                    date: Some(date.clone()),
+                    user_override: false,
+                })
+            })
+            .collect())
+    }
+}
+
+/// USD prices set by an account per (asset, date), persisted to a file
+pub struct PriceOverrides {
+    /// None to keep the overrides in memory only
+    path: Option<PathBuf>,
+    prices: Vec<PriceEntry>,
+}
+
+impl PriceOverrides {
+    /// Overrides saved at `path`; none if the file doesn't exist yet
+    pub fn load(path: Option<PathBuf>) -> Result<Self> {
+        let prices = match path.as_ref().map(std::fs::read_to_string) {
+            Some(Ok(contents)) => serde_json::from_str(&contents)?,
+            Some(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => Vec::new(),
+        };
+        Ok(Self { path, prices })
+    }
+
+    pub fn prices(&self) -> &[PriceEntry] {
+        &self.prices
+    }
+
+    /// Set or replace the prices of each entry's asset and date, and save the overrides
+    pub fn set(&mut self, entries: Vec<PriceEntry>) -> Result<()> {
+        for entry in entries {
+            self.prices.retain(|price| !same_day(price, &entry));
+            self.prices.push(PriceEntry {
+                user_override: true,
+                ..entry
+            });
+        }
+        self.prices.sort_by(|a, b| (&a.asset, &a.date).cmp(&(&b.asset, &b.date)));
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, serde_json::to_string_pretty(&self.prices)?)?;
+        }
+        Ok(())
+    }
+
+    /// Replace prices for the same asset and date with the overrides, and add the rest
+    pub fn merge_into(&self, prices: &mut Vec<PriceEntry>) {
+        prices.retain(|price| !self.prices.iter().any(|user| same_day(price, user)));
+        prices.extend(self.prices.iter().cloned());
+    }
+}
+
+fn same_day(a: &PriceEntry, b: &PriceEntry) -> bool {
+    a.asset.eq_ignore_ascii_case(&b.asset) && a.date == b.date
+}
+
+/// First price of each UTC day in a series of (unix milliseconds, price) points
+fn daily_prices(points: &[(f64, f64)]) -> BTreeMap<String, f64> {
+    let mut daily = BTreeMap::new();
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (millis, price) in points {
+        daily.entry(price_date((millis / 1000.0) as u64)).or_insert(price);
+    }
+    daily
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_point_of_each_day() {
+        let points = [
+            (1_750_032_000_000.0, 2510.0), // 2025-06-16 00:00 UTC
+            (1_749_945_600_000.0, 2500.0), // 2025-06-15 00:00 UTC
+            (1_749_990_000_000.0, 2550.0), // later on the 15th
+        ];
+        let daily = daily_prices(&points);
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily["2025-06-15"], 2500.0);
+        assert_eq!(daily["2025-06-16"], 2510.0);
+    }
+
+    #[test]
+    fn test_overrides_replace_provider_prices() {
+        let path = std::env::temp_dir().join(format!("financoor-prices-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let price = |asset: &str, usd_price: &str, date: Option<&str>| PriceEntry {
+            asset: asset.to_string(),
+            usd_price: usd_price.to_string(),
+            date: date.map(str::to_string),
+            user_override: false,
+        };
+
+        let mut overrides = PriceOverrides::load(Some(path.clone())).unwrap();
+        overrides.set(vec![price("ILLQ", "0.42", Some("2025-06-15"))]).unwrap();
+        overrides.set(vec![price("ILLQ", "0.40", Some("2025-06-15"))]).unwrap();
+
+        let reloaded = PriceOverrides::load(Some(path.clone())).unwrap();
+        let mut prices = vec![price("ILLQ", "9.99", Some("2025-06-15")), price("ETH", "2500", Some("2025-06-15"))];
+        reloaded.merge_into(&mut prices);
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[1].usd_price, "0.40");
+        assert!(prices[1].user_override);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+//! Submitting proofs to `TaxVerifier`
+//!
+//! The relayer account sends `verifyTaxProof(proofBytes, publicValues)` (or
+//! `verifyAggregateTaxProof`) for a finished Groth16 or PLONK proof, so users need neither
+//! a funded wallet nor to build the calldata themselves. The transaction is built and
+//! signed here (EIP-1559) and sent raw over JSON-RPC. The contract checks the proof with
+//! the SP1 verifier and records the tax under the ledger commitment (or parts digest); a
+//! proof it would reject fails gas estimation, before anything is spent.
+
+use std::time::{Duration, Instant};
+
+use alloy_consensus::{SignableTransaction, TxEip1559, TxEnvelope};
+use alloy_eips::eip2718::Encodable2718;
+use alloy_primitives::{Address, TxKind, U256};
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use anyhow::{anyhow, Context, Result};
+use financoor_prover::OnchainCalldata;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+/// Sepolia, unless `RELAYER_CHAIN_ID` says otherwise
+pub const DEFAULT_RELAYER_CHAIN_ID: u64 = 11_155_111;
+
+/// How long a submission waits for its transaction to be mined before reporting it pending
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(90);
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Where a sent transaction is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    /// Not mined yet
+    Pending,
+    /// Mined, and the proof was recorded
+    Confirmed,
+    /// Mined, but reverted
+    Reverted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Submission {
+    pub tx_hash: String,
+    pub status: TxStatus,
+    /// Block the transaction was mined in, once it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    pub chain_id: u64,
+    /// `TaxVerifier` the proof was sent to
+    pub contract: String,
+}
+
+pub struct Relayer {
+    client: reqwest::Client,
+    rpc_url: String,
+    chain_id: u64,
+    contract: Address,
+    signer: PrivateKeySigner,
+    /// Held from picking a nonce until the transaction is sent, so submissions don't race
+    /// for the same nonce
+    sending: Mutex<()>,
+}
+
+impl Relayer {
+    pub fn new(rpc_url: String, chain_id: u64, contract: &str, private_key: &str) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            rpc_url,
+            chain_id,
+            contract: contract.parse().context("Invalid TaxVerifier address")?,
+            signer: private_key.parse().context("Invalid relayer private key")?,
+            sending: Mutex::new(()),
+        })
+    }
+
+    /// `RELAYER_PRIVATE_KEY`, `RELAYER_RPC_URL`, `TAX_VERIFIER_ADDRESS` and
+    /// `RELAYER_CHAIN_ID`; None without a key
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(private_key) = std::env::var("RELAYER_PRIVATE_KEY").ok().filter(|key| !key.is_empty()) else {
+            return Ok(None);
+        };
+        let rpc_url = std::env::var("RELAYER_RPC_URL").context("RELAYER_RPC_URL is required with a relayer key")?;
+        let contract =
+            std::env::var("TAX_VERIFIER_ADDRESS").context("TAX_VERIFIER_ADDRESS is required with a relayer key")?;
+        let chain_id = match std::env::var("RELAYER_CHAIN_ID") {
+            Ok(chain_id) => chain_id.parse()?,
+            Err(_) => DEFAULT_RELAYER_CHAIN_ID,
+        };
+        Self::new(rpc_url, chain_id, &contract, &private_key).map(Some)
+    }
+
+    /// Account the transactions are sent from
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    async fn rpc(&self, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+        let request = serde_json::json!({
+            "id": 1,
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        });
+        let mut response: serde_json::Value =
+            self.client.post(&self.rpc_url).json(&request).send().await?.json().await?;
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("{} failed: {}", method, error["message"].as_str().unwrap_or("unknown error")));
+        }
+        Ok(response["result"].take())
+    }
+
+    /// Send a proof to `TaxVerifier`, and wait a while for the transaction to be mined
+    pub async fn submit(&self, calldata: &OnchainCalldata) -> Result<Submission> {
+        let input = hex::decode(calldata.verify_tax_proof_calldata.trim_start_matches("0x"))?;
+        let from = self.address().to_string();
+        let call = serde_json::json!({
+            "from": from,
+            "to": self.contract.to_string(),
+            "data": format!("0x{}", hex::encode(&input)),
+        });
+
+        let tx_hash = {
+            let _sending = self.sending.lock().await;
+            // Fails (with the revert reason) for a proof the verifier rejects
+            let gas = quantity(&self.rpc("eth_estimateGas", serde_json::json!([call])).await?)?;
+            let nonce = quantity(&self.rpc("eth_getTransactionCount", serde_json::json!([from, "pending"])).await?)?;
+            let tip = quantity(&self.rpc("eth_maxPriorityFeePerGas", serde_json::json!([])).await?)?;
+            let block = self.rpc("eth_getBlockByNumber", serde_json::json!(["latest", false])).await?;
+            let base_fee = quantity(&block["baseFeePerGas"])?;
+
+            let tx = TxEip1559 {
+                chain_id: self.chain_id,
+                nonce: u64::try_from(nonce)?,
+                // Headroom over the estimate, in case state changes before it's mined
+                gas_limit: u64::try_from(gas + gas / 5)?,
+                max_fee_per_gas: base_fee * 2 + tip,
+                max_priority_fee_per_gas: tip,
+                to: TxKind::Call(self.contract),
+                value: U256::ZERO,
+                access_list: Default::default(),
+                input: input.into(),
+            };
+            let raw = self.sign(tx)?;
+            let sent = self.rpc("eth_sendRawTransaction", serde_json::json!([format!("0x{}", hex::encode(raw))]));
+            sent.await?
+                .as_str()
+                .ok_or_else(|| anyhow!("eth_sendRawTransaction returned no transaction hash"))?
+                .to_string()
+        };
+        tracing::info!("Sent proof to TaxVerifier in {}", tx_hash);
+
+        let deadline = Instant::now() + CONFIRMATION_TIMEOUT;
+        let (status, block_number) = loop {
+            let (status, block_number) = self.status(&tx_hash).await?;
+            if status != TxStatus::Pending || Instant::now() >= deadline {
+                break (status, block_number);
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        };
+
+        Ok(Submission {
+            tx_hash,
+            status,
+            block_number,
+            chain_id: self.chain_id,
+            contract: self.contract.to_string(),
+        })
+    }
+
+    /// Status of a sent transaction, with the block it was mined in
+    pub async fn status(&self, tx_hash: &str) -> Result<(TxStatus, Option<u64>)> {
+        let receipt = self.rpc("eth_getTransactionReceipt", serde_json::json!([tx_hash])).await?;
+        if receipt.is_null() {
+            return Ok((TxStatus::Pending, None));
+        }
+        let block_number = quantity(&receipt["blockNumber"]).ok().and_then(|block| u64::try_from(block).ok());
+        let status = match quantity(&receipt["status"])? {
+            1 => TxStatus::Confirmed,
+            _ => TxStatus::Reverted,
+        };
+        Ok((status, block_number))
+    }
+
+    /// Signed, EIP-2718 encoded transaction
+    fn sign(&self, tx: TxEip1559) -> Result<Vec<u8>> {
+        let signature = self.signer.sign_hash_sync(&tx.signature_hash())?;
+        Ok(TxEnvelope::Eip1559(tx.into_signed(signature)).encoded_2718())
+    }
+}
+
+/// Hex quantity ("0x1a") of a JSON-RPC result
+fn quantity(value: &serde_json::Value) -> Result<u128> {
+    let hex = value.as_str().ok_or_else(|| anyhow!("Expected a hex quantity, got {}", value))?;
+    Ok(u128::from_str_radix(hex.trim_start_matches("0x"), 16)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantities() {
+        assert_eq!(quantity(&serde_json::json!("0x1a")).unwrap(), 26);
+        assert!(quantity(&serde_json::Value::Null).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! Tax reports
+//!
+//! A report is a durable record of a tax session: the wallets, the categorized ledger as
+//! it was, the prices and settings the tax was calculated with, the breakdown and, once
+//! one is attached, the proof. Saving a report again adds a version rather than replacing
+//! it, with the rows whose categories changed since the previous version, so what was
+//! filed can still be told apart from the edits made since.
+//!
+//! Reports belong to an account and are kept in its storage with its ledger and wallets.
+
+use std::collections::HashMap;
+
+use financoor_core::{CategoryChange, LedgerRow, TaxBreakdown, TaxInput};
+use serde::{Deserialize, Serialize};
+
+use crate::jobs::ProofResult;
+
+/// A report and every version of it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TaxReport {
+    pub id: String,
+    pub name: String,
+    /// Unix time of the first version
+    pub created_at: u64,
+    /// Oldest first: version n is at index n - 1
+    pub versions: Vec<ReportVersion>,
+}
+
+/// A tax session as saved
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReportVersion {
+    pub version: u32,
+    pub created_at: u64,
+    /// Wallets, valued ledger, prices and settings the breakdown was calculated from
+    pub input: TaxInput,
+    pub breakdown: TaxBreakdown,
+    /// Hex commitment a proof of this version commits to (`compute_ledger_commitment`)
+    pub ledger_commitment: String,
+    /// Rows whose category differs from the previous version's
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category_changes: Vec<CategoryChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<ReportProof>,
+}
+
+/// A finished proof of a version
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReportProof {
+    pub job_id: String,
+    #[serde(flatten)]
+    pub result: ProofResult,
+}
+
+/// A report as listed: its latest version's figures, without the ledger
+#[derive(Serialize)]
+pub struct ReportSummary {
+    pub id: String,
+    pub name: String,
+    pub created_at: u64,
+    /// Unix time of the latest version
+    pub updated_at: u64,
+    pub version: u32,
+    pub assessment_year: u16,
+    pub total_tax_paisa: u64,
+    pub ledger_commitment: String,
+    /// Whether the latest version has a proof
+    pub proved: bool,
+}
+
+/// A version as listed
+#[derive(Serialize)]
+pub struct VersionSummary {
+    pub version: u32,
+    pub created_at: u64,
+    pub total_tax_paisa: u64,
+    /// Rows recategorized since the previous version
+    pub category_changes: usize,
+    pub proved: bool,
+}
+
+impl TaxReport {
+    /// A report of a first version
+    pub fn new(id: String, name: String, mut first: ReportVersion) -> Self {
+        first.version = 1;
+        first.category_changes.clear();
+        Self {
+            id,
+            name,
+            created_at: first.created_at,
+            versions: vec![first],
+        }
+    }
+
+    pub fn latest(&self) -> &ReportVersion {
+        self.versions.last().expect("a report has a version")
+    }
+
+    pub fn version(&self, version: u32) -> Option<&ReportVersion> {
+        self.versions.get((version as usize).checked_sub(1)?)
+    }
+
+    pub fn version_mut(&mut self, version: u32) -> Option<&mut ReportVersion> {
+        self.versions.get_mut((version as usize).checked_sub(1)?)
+    }
+
+    /// Add a version after the latest, with the rows recategorized since it; returns its
+    /// number
+    pub fn push(&mut self, mut version: ReportVersion) -> u32 {
+        let latest = self.latest();
+        version.version = latest.version + 1;
+        version.category_changes = changed_categories(&latest.input.ledger, &version.input.ledger);
+        self.versions.push(version);
+        self.latest().version
+    }
+
+    pub fn summary(&self) -> ReportSummary {
+        let latest = self.latest();
+        ReportSummary {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            created_at: self.created_at,
+            updated_at: latest.created_at,
+            version: latest.version,
+            assessment_year: latest.input.assessment_year,
+            total_tax_paisa: latest.breakdown.total_tax_paisa,
+            ledger_commitment: latest.ledger_commitment.clone(),
+            proved: latest.proof.is_some(),
+        }
+    }
+
+    pub fn version_summaries(&self) -> Vec<VersionSummary> {
+        self.versions
+            .iter()
+            .map(|version| VersionSummary {
+                version: version.version,
+                created_at: version.created_at,
+                total_tax_paisa: version.breakdown.total_tax_paisa,
+                category_changes: version.category_changes.len(),
+                proved: version.proof.is_some(),
+            })
+            .collect()
+    }
+}
+
+/// Rows of `after` whose category differs from the same row's in `before`
+///
+/// Rows are matched by chain, transaction, owner, asset, direction and amount rather than
+/// by position, as the ledger may have been refetched or had rows imported in between;
+/// rows only in one of the ledgers aren't changes.
+pub fn changed_categories(before: &[LedgerRow], after: &[LedgerRow]) -> Vec<CategoryChange> {
+    let key = |row: &LedgerRow| {
+        let owner = row.owner_wallet.as_str().to_lowercase();
+        (row.chain_id, row.tx_hash.to_lowercase(), owner, row.asset.clone(), row.direction)
+    };
+    // Rows that only differ in amount are told apart by it, else taken in order
+    let mut earlier: HashMap<_, Vec<&LedgerRow>> = HashMap::new();
+    for row in before.iter().rev() {
+        earlier.entry(key(row)).or_default().push(row);
+    }
+    after
+        .iter()
+        .enumerate()
+        .filter_map(|(i, row)| {
+            let candidates = earlier.get_mut(&key(row))?;
+            let position = match candidates.iter().rposition(|old| old.amount == row.amount) {
+                Some(position) => position,
+                None => candidates.len().checked_sub(1)?,
+            };
+            let old = candidates.remove(position);
+            (old.category != row.category).then(|| CategoryChange {
+                row_id: i,
+                tx_hash: row.tx_hash.clone(),
+                from: old.category,
+                to: row.category,
+                confidence: row.confidence,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use financoor_core::Category;
+
+    use super::*;
+
+    fn row(tx_hash: &str, amount: &str, category: &str) -> LedgerRow {
+        serde_json::from_value(serde_json::json!({
+            "chain_id": 1,
+            "owner_wallet": "0x1111111111111111111111111111111111111111",
+            "tx_hash": tx_hash,
+            "block_time": 1_748_736_000,
+            "asset": "ETH",
+            "amount": amount,
+            "decimals": 18,
+            "direction": "in",
+            "counterparty": null,
+            "category": category,
+            "confidence": 1.0,
+            "user_override": true,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_versions_record_recategorized_rows() {
+        let before = vec![row("0x01", "1", "income"), row("0x02", "2", "internal"), row("0x02", "3", "income")];
+        // Reordered, one row new, and two recategorized
+        let after = vec![
+            row("0x03", "1", "income"),
+            row("0x02", "3", "gift"),
+            row("0x02", "2", "internal"),
+            row("0x01", "1", "spam"),
+        ];
+        let changes = changed_categories(&before, &after);
+        let changed: Vec<(usize, Category, Category)> =
+            changes.iter().map(|change| (change.row_id, change.from, change.to)).collect();
+        assert_eq!(changed, [(1, Category::Income, Category::Gift), (3, Category::Income, Category::Spam)]);
+    }
+}
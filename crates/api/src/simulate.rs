@@ -0,0 +1,160 @@
+//! What-if tax scenarios
+//!
+//! A scenario is a list of hypothetical modifications applied to a copy of the
+//! base tax input; the stored ledger and settings are never touched.
+
+use anyhow::{anyhow, Result};
+use financoor_core::{Category, IncomeSource, LedgerRow, ManualIncomeEntry, TaxInput, TaxRegime};
+use serde::Deserialize;
+
+/// A named set of modifications to simulate
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub modifications: Vec<Modification>,
+}
+
+/// One hypothetical change to the tax input
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Modification {
+    /// Flip the 44ADA election
+    #[serde(rename = "toggle_44ada")]
+    Toggle44ada,
+    SetRegime { regime: TaxRegime },
+    /// Change the category of the ledger row at `row` (index into the base ledger)
+    Recategorize { row: usize, category: Category },
+    /// Drop the ledger row at `row` (index into the base ledger)
+    RemoveRow { row: usize },
+    /// Add a ledger row
+    AddRow { row: Box<LedgerRow> },
+    /// Add non-crypto income
+    AddIncome {
+        #[serde(default = "default_income_source")]
+        source: IncomeSource,
+        amount_inr: String,
+    },
+}
+
+fn default_income_source() -> IncomeSource {
+    IncomeSource::Other
+}
+
+impl Scenario {
+    /// Copy of `base` with this scenario's modifications applied in order
+    ///
+    /// Row indices always refer to the base ledger, so removing a row doesn't shift
+    /// the rows later modifications point at.
+    pub fn apply(&self, base: &TaxInput) -> Result<TaxInput> {
+        let mut input = base.clone();
+        let mut removed = vec![false; base.ledger.len()];
+
+        for modification in &self.modifications {
+            match modification {
+                Modification::Toggle44ada => input.use_44ada = !input.use_44ada,
+                Modification::SetRegime { regime } => input.regime = *regime,
+                Modification::Recategorize { row, category } => {
+                    let ledger_row = input
+                        .ledger
+                        .get_mut(*row)
+                        .filter(|_| *row < base.ledger.len())
+                        .ok_or_else(|| anyhow!("scenario '{}': row {} out of range", self.name, row))?;
+                    ledger_row.category = *category;
+                    ledger_row.user_override = true;
+                }
+                Modification::RemoveRow { row } => {
+                    *removed
+                        .get_mut(*row)
+                        .ok_or_else(|| anyhow!("scenario '{}': row {} out of range", self.name, row))? = true;
+                }
+                Modification::AddRow { row } => input.ledger.push(row.as_ref().clone()),
+                Modification::AddIncome { source, amount_inr } => input.manual_income.push(ManualIncomeEntry {
+                    source: *source,
+                    amount_inr: amount_inr.clone(),
+                    description: Some(format!("What-if: {}", self.name)),
+                }),
+            }
+        }
+
+        // Added rows sit past the end of the base ledger and are never removed
+        let mut index = 0;
+        input.ledger.retain(|_| {
+            let keep = !removed.get(index).copied().unwrap_or(false);
+            index += 1;
+            keep
+        });
+
+        Ok(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> TaxInput {
+        serde_json::from_value(serde_json::json!({
+            "user_type": "individual",
+            "wallets": [],
+            "ledger": [
+                {
+                    "chain_id": 1, "owner_wallet": "0xabc", "tx_hash": "0x1", "block_time": 1750000000,
+                    "asset": "INR", "amount": "100000", "decimals": 2, "direction": "in",
+                    "counterparty": null, "category": "income", "confidence": 1.0, "user_override": false
+                },
+                {
+                    "chain_id": 1, "owner_wallet": "0xabc", "tx_hash": "0x2", "block_time": 1750000000,
+                    "asset": "INR", "amount": "5000", "decimals": 2, "direction": "in",
+                    "counterparty": null, "category": "income", "confidence": 1.0, "user_override": false
+                }
+            ],
+            "prices": [],
+            "usd_inr_rate": "1",
+            "use_44ada": false
+        }))
+        .unwrap()
+    }
+
+    fn scenario(modifications: serde_json::Value) -> Scenario {
+        serde_json::from_value(serde_json::json!({ "name": "test", "modifications": modifications })).unwrap()
+    }
+
+    #[test]
+    fn test_apply_leaves_base_untouched() {
+        let base = base();
+        let input = scenario(serde_json::json!([
+            { "type": "toggle_44ada" },
+            { "type": "recategorize", "row": 1, "category": "internal" },
+            { "type": "add_income", "source": "salary", "amount_inr": "600000" }
+        ]))
+        .apply(&base)
+        .unwrap();
+
+        assert!(input.use_44ada);
+        assert_eq!(input.ledger[1].category, Category::Internal);
+        assert_eq!(input.manual_income.len(), 1);
+        assert!(!base.use_44ada);
+        assert_eq!(base.ledger[1].category, Category::Income);
+    }
+
+    #[test]
+    fn test_row_indices_refer_to_base_ledger() {
+        let input = scenario(serde_json::json!([
+            { "type": "remove_row", "row": 0 },
+            { "type": "recategorize", "row": 1, "category": "gains" }
+        ]))
+        .apply(&base())
+        .unwrap();
+
+        assert_eq!(input.ledger.len(), 1);
+        assert_eq!(input.ledger[0].tx_hash, "0x2");
+        assert_eq!(input.ledger[0].category, Category::Gains);
+    }
+
+    #[test]
+    fn test_out_of_range_row_rejected() {
+        let result = scenario(serde_json::json!([{ "type": "remove_row", "row": 5 }])).apply(&base());
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! Persistent storage of the user's wallets, wallet groups and ledger
+//!
+//! Each repository is one JSON file in the storage directory, loaded at startup and written
+//! again whenever a handler changes it, so wallets and the categorized ledger (with the
+//! prices it was fetched with) outlive a request and a restart. Without a directory, the
+//! repositories are kept in memory only.
+
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Where repositories are kept unless `STORAGE_DIR` says otherwise
+pub const DEFAULT_STORAGE_DIR: &str = "data";
+
+/// Records of one kind, kept in a JSON file
+pub struct Repository<T> {
+    /// None to keep the records in memory only
+    path: Option<PathBuf>,
+    records: T,
+}
+
+impl<T: Default + Serialize + DeserializeOwned> Repository<T> {
+    /// Records saved as `name.json` in `dir`; none if the file doesn't exist yet
+    pub fn load(dir: Option<&Path>, name: &str) -> Result<Self> {
+        let path = dir.map(|dir| dir.join(format!("{}.json", name)));
+        let records = match path.as_ref().map(std::fs::read_to_string) {
+            Some(Ok(contents)) => serde_json::from_str(&contents)?,
+            Some(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => T::default(),
+        };
+        Ok(Self { path, records })
+    }
+
+    /// Write the records after changing them; a failure is logged, and they're kept in
+    /// memory until the next write
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = write(path, &self.records) {
+            tracing::warn!("Failed to save {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Write then rename, so a crash mid-write doesn't leave a truncated file behind
+fn write<T: Serialize>(path: &Path, records: &T) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("json.partial");
+    std::fs::write(&partial, serde_json::to_vec_pretty(records)?)?;
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+impl<T> Deref for Repository<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.records
+    }
+}
+
+impl<T> DerefMut for Repository<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repository_survives_reload() {
+        let dir = std::env::temp_dir().join(format!("financoor-storage-{}", std::process::id()));
+        let mut wallets: Repository<Vec<String>> = Repository::load(Some(&dir), "wallets").unwrap();
+        assert!(wallets.is_empty());
+        wallets.push("0xabc".to_string());
+        wallets.save();
+
+        let reloaded: Repository<Vec<String>> = Repository::load(Some(&dir), "wallets").unwrap();
+        assert_eq!(*reloaded, ["0xabc"]);
+        let in_memory: Repository<Vec<String>> = Repository::load(None, "wallets").unwrap();
+        in_memory.save();
+        assert!(in_memory.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+//! Per-wallet sync cursors
+//!
+//! Remembers the last block scanned for each wallet, so that a sync only fetches the
+//! blocks after it. Cursors are written to a JSON file and survive restarts, as the stored
+//! ledger does; a wallet with no rows in the stored ledger is fetched from the start. Each
+//! account has its own cursors, as it has its own ledger.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+/// Where cursors are kept unless `SYNC_STATE_PATH` says otherwise
+pub const DEFAULT_SYNC_STATE_PATH: &str = "sync_state.json";
+
+/// Last block scanned per wallet (lowercase address), persisted to a file
+pub struct SyncState {
+    /// None to keep the cursors in memory only
+    path: Option<PathBuf>,
+    cursors: HashMap<String, u64>,
+}
+
+impl SyncState {
+    /// Cursors saved at `path`; none if the file doesn't exist yet
+    pub fn load(path: Option<PathBuf>) -> Result<Self> {
+        let cursors = match path.as_ref().map(std::fs::read_to_string) {
+            Some(Ok(contents)) => serde_json::from_str(&contents)?,
+            Some(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => HashMap::new(),
+        };
+        Ok(Self { path, cursors })
+    }
+
+    /// Last block scanned for a wallet
+    pub fn last_block(&self, wallet: &str) -> Option<u64> {
+        self.cursors.get(&wallet.to_lowercase()).copied()
+    }
+
+    /// Wallets synced at least once
+    pub fn wallets(&self) -> impl Iterator<Item = &str> {
+        self.cursors.keys().map(String::as_str)
+    }
+
+    /// Record that a wallet was scanned up to `block` and save the cursors
+    pub fn advance(&mut self, wallet: &str, block: u64) -> Result<()> {
+        self.cursors.insert(wallet.to_lowercase(), block);
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.cursors)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursors_survive_reload() {
+        let path = std::env::temp_dir().join(format!("financoor-sync-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut state = SyncState::load(Some(path.clone())).unwrap();
+        assert_eq!(state.last_block("0xAbC"), None);
+        state.advance("0xAbC", 7_000_000).unwrap();
+
+        let reloaded = SyncState::load(Some(path.clone())).unwrap();
+        assert_eq!(reloaded.last_block("0xabc"), Some(7_000_000));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
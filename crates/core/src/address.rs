@@ -0,0 +1,173 @@
+//! Wallet and counterparty addresses
+//!
+//! EVM addresses are case-insensitive but often written in mixed case, the EIP-55
+//! checksum. `Address` keeps them lowercase so that the same address always compares
+//! equal, and `Address::parse` rejects a mixed-case address whose checksum is wrong.
+//! Anything else (Solana and Bitcoin addresses, exchange and CSV accounts) is kept as
+//! given, since base58 is case-sensitive.
+
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::TaxError;
+
+/// A normalized wallet or counterparty address
+///
+/// Converting from a string only normalizes; use `Address::parse` for input that needs
+/// validating. Compares equal to strings naming the same address in any case.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Address(String);
+
+impl Address {
+    /// Parse an address, checking the checksum of a mixed-case EVM address
+    pub fn parse(value: &str) -> Result<Self, TaxError> {
+        let value = value.trim();
+        let invalid = |reason: &str| TaxError::InvalidAddress(format!("{}: {}", value, reason));
+        if value.is_empty() {
+            return Err(invalid("empty"));
+        }
+        if value.chars().any(char::is_whitespace) {
+            return Err(invalid("contains whitespace"));
+        }
+        if !has_hex_prefix(value) {
+            return Ok(Self(value.to_string()));
+        }
+
+        let digits = &value[2..];
+        if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid("expected 0x and 40 hex digits"));
+        }
+        let mixed_case =
+            digits.chars().any(|c| c.is_ascii_uppercase()) && digits.chars().any(|c| c.is_ascii_lowercase());
+        let address = Self::from(value);
+        if mixed_case && address.to_checksum().as_deref() != Some(value) {
+            return Err(invalid("checksum mismatch"));
+        }
+        Ok(address)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether this is an EVM address (0x and 40 hex digits)
+    pub fn is_evm(&self) -> bool {
+        has_hex_prefix(&self.0) && self.0.len() == 42 && self.0[2..].chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    /// EIP-55 mixed-case form of an EVM address
+    pub fn to_checksum(&self) -> Option<String> {
+        if !self.is_evm() {
+            return None;
+        }
+        let address: alloy_primitives::Address = self.0.parse().ok()?;
+        Some(address.to_checksum(None))
+    }
+}
+
+fn has_hex_prefix(value: &str) -> bool {
+    value.starts_with("0x") || value.starts_with("0X")
+}
+
+impl From<String> for Address {
+    fn from(value: String) -> Self {
+        if has_hex_prefix(&value) {
+            Self(value.to_lowercase())
+        } else {
+            Self(value)
+        }
+    }
+}
+
+impl From<&str> for Address {
+    fn from(value: &str) -> Self {
+        Self::from(value.to_string())
+    }
+}
+
+impl From<Address> for String {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+impl FromStr for Address {
+    type Err = TaxError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse(value)
+    }
+}
+
+impl Deref for Address {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Address {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PartialEq<str> for Address {
+    fn eq(&self, other: &str) -> bool {
+        if has_hex_prefix(other) {
+            self.0.eq_ignore_ascii_case(other)
+        } else {
+            self.0 == other
+        }
+    }
+}
+
+impl PartialEq<&str> for Address {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Address {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_checks_checksum_and_normalizes() {
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let address = Address::parse(checksummed).unwrap();
+        assert_eq!(address.as_str(), "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(address.to_checksum().as_deref(), Some(checksummed));
+        assert_eq!(address, checksummed);
+
+        // One letter's case flipped
+        assert!(Address::parse("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+        // All one case carries no checksum
+        assert!(Address::parse("0X5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED").is_ok());
+        assert!(Address::parse("0x1234").is_err());
+        assert!(Address::parse("").is_err());
+
+        // Base58 is case-sensitive
+        let solana = Address::parse("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM").unwrap();
+        assert_eq!(solana.as_str(), "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM");
+        assert_ne!(solana, "9wzdxwbbmkg8ztbnmquxvqrayrzzdsgydlvl9zytawwm");
+    }
+}
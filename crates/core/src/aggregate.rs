@@ -0,0 +1,111 @@
+//! Aggregating tax proofs
+//!
+//! A ledger with thousands of rows is proved in parts (one per wallet group, or per
+//! financial year), so that no single proof runs for too many cycles. The `tax_aggregate`
+//! program then verifies the parts' proofs and commits their combined figures, a single
+//! proof to verify on-chain. The parts themselves are bound by a digest of their public
+//! values, so each can still be checked against its own ledger commitment.
+
+use alloy_primitives::{keccak256, FixedBytes, U256};
+use alloy_sol_types::{sol, SolType};
+
+use crate::prelude::*;
+use crate::{TaxError, TaxProofPublicValues};
+
+sol! {
+    /// Public values output by the SP1 aggregation program
+    struct AggregatedTaxPublicValues {
+        /// Verification key digest of the tax program every part was proved with (its
+        /// eight words, big-endian)
+        bytes32 taxProgramVkey;
+        /// Keccak256 hash of the parts' ABI-encoded public values, concatenated in order
+        bytes32 partsDigest;
+        /// Number of parts
+        uint32 partCount;
+        /// Total tax payable across the parts, in paisa
+        uint256 totalTaxPaisa;
+        /// Total professional income across the parts, in paisa
+        uint256 professionalIncomePaisa;
+        /// VDA gains across the parts, in paisa
+        uint256 vdaGainsPaisa;
+        /// VDA tax across the parts, in paisa
+        uint256 vdaTaxPaisa;
+        /// Health & Education Cess across the parts, in paisa
+        uint256 cessPaisa;
+        /// Whether every part proved ownership of all the wallets it has rows for
+        bool allWalletsOwned;
+    }
+}
+
+/// Combined public values of the parts' (ABI-encoded) public values
+pub fn aggregate_public_values(
+    tax_program_vkey: [u8; 32],
+    parts: &[Vec<u8>],
+) -> Result<AggregatedTaxPublicValues, TaxError> {
+    let mut aggregated = AggregatedTaxPublicValues {
+        taxProgramVkey: FixedBytes(tax_program_vkey),
+        partsDigest: keccak256(parts.concat()),
+        partCount: u32::try_from(parts.len()).map_err(|_| TaxError::InvalidPublicValues("too many parts".into()))?,
+        totalTaxPaisa: U256::ZERO,
+        professionalIncomePaisa: U256::ZERO,
+        vdaGainsPaisa: U256::ZERO,
+        vdaTaxPaisa: U256::ZERO,
+        cessPaisa: U256::ZERO,
+        allWalletsOwned: true,
+    };
+    for (index, part) in parts.iter().enumerate() {
+        let values = TaxProofPublicValues::abi_decode(part)
+            .map_err(|e| TaxError::InvalidPublicValues(format!("part {}: {}", index, e)))?;
+        aggregated.totalTaxPaisa = aggregated.totalTaxPaisa.saturating_add(values.totalTaxPaisa);
+        aggregated.professionalIncomePaisa =
+            aggregated.professionalIncomePaisa.saturating_add(values.professionalIncomePaisa);
+        aggregated.vdaGainsPaisa = aggregated.vdaGainsPaisa.saturating_add(values.vdaGainsPaisa);
+        aggregated.vdaTaxPaisa = aggregated.vdaTaxPaisa.saturating_add(values.vdaTaxPaisa);
+        aggregated.cessPaisa = aggregated.cessPaisa.saturating_add(values.cessPaisa);
+        aggregated.allWalletsOwned &= values.allWalletsOwned;
+    }
+    Ok(aggregated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(total_tax_paisa: u64, cess_paisa: u64) -> Vec<u8> {
+        let values = TaxProofPublicValues {
+            ledgerCommitment: FixedBytes([1; 32]),
+            totalTaxPaisa: U256::from(total_tax_paisa),
+            userType: 0,
+            used44ada: false,
+            assessmentYear: 2026,
+            fyStart: 0,
+            fyEnd: 1,
+            rateTableCommitment: FixedBytes([2; 32]),
+            pricesCommitment: FixedBytes([3; 32]),
+            professionalIncomePaisa: U256::ZERO,
+            vdaGainsPaisa: U256::ZERO,
+            vdaTaxPaisa: U256::ZERO,
+            cessPaisa: U256::from(cess_paisa),
+            ownedWalletsCommitment: FixedBytes([4; 32]),
+            allWalletsOwned: true,
+            nonce: FixedBytes([5; 32]),
+            domainSeparator: FixedBytes([6; 32]),
+        };
+        TaxProofPublicValues::abi_encode(&values)
+    }
+
+    #[test]
+    fn test_aggregate_sums_parts_and_binds_them() {
+        let parts = [part(1_000, 40), part(2_500, 100)];
+        let aggregated = aggregate_public_values([9; 32], &parts).unwrap();
+        assert_eq!(aggregated.partCount, 2);
+        assert_eq!(aggregated.totalTaxPaisa, U256::from(3_500));
+        assert_eq!(aggregated.cessPaisa, U256::from(140));
+        assert_eq!(aggregated.taxProgramVkey.0, [9; 32]);
+
+        // The digest depends on the order of the parts
+        let swapped = aggregate_public_values([9; 32], &[parts[1].clone(), parts[0].clone()]).unwrap();
+        assert_ne!(swapped.partsDigest, aggregated.partsDigest);
+        assert!(aggregate_public_values([9; 32], &[vec![0; 3]]).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+//! Rule-based transaction categorization
+//!
+//! Each rule matches on counterparty, asset, amount range, direction and chain (or on
+//! the category of a registered contract), and assigns a category with a confidence.
+//! The highest-priority matching rule wins; ties go to the rule listed first. The
+//! built-in heuristics ship as `rules/categorization.json` and users can add their own
+//! rules (JSON or TOML) on top.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Address, CategorizationResult, Category, ContractRegistry, Direction, LedgerRow, TaxError};
+
+const BUILTIN_RULES: &str = include_str!("../rules/categorization.json");
+
+/// What a ledger row must look like for a rule to apply; unset conditions match anything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleConditions {
+    /// Counterparty must be one of these addresses (case-insensitive)
+    #[serde(default)]
+    pub counterparty: Vec<String>,
+    /// Whether the counterparty must (or must not) be one of the user's own wallets
+    #[serde(default)]
+    pub counterparty_is_own_wallet: Option<bool>,
+    /// Counterparty label must contain one of these (case-insensitive)
+    #[serde(default)]
+    pub counterparty_label: Vec<String>,
+    /// Counterparty must be a registered contract of this category
+    #[serde(default)]
+    pub contract_category: Option<Category>,
+    /// Asset symbol (case-insensitive)
+    #[serde(default)]
+    pub asset: Option<String>,
+    /// Minimum amount, inclusive
+    #[serde(default)]
+    pub min_amount: Option<f64>,
+    /// Maximum amount, exclusive
+    #[serde(default)]
+    pub max_amount: Option<f64>,
+    #[serde(default)]
+    pub direction: Option<Direction>,
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+}
+
+/// A categorization rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorizationRule {
+    pub name: String,
+    /// Higher priorities are tried first
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub conditions: RuleConditions,
+    pub category: Category,
+    /// Confidence assigned to matching rows, 0.0 to 1.0
+    pub confidence: f32,
+}
+
+/// How outflows on one chain are paired with inflows on another as bridge transfers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BridgeMatching {
+    /// How far below the outflow the inflow may be, for bridge fees (basis points)
+    pub tolerance_bps: u64,
+    /// Longest time between the outflow and the inflow (seconds)
+    pub window_secs: u64,
+}
+
+impl Default for BridgeMatching {
+    fn default() -> Self {
+        Self {
+            tolerance_bps: 200,
+            window_secs: 3_600,
+        }
+    }
+}
+
+/// What rules match rows against: the user's wallets (normalized once into a set) and
+/// the registered contracts. Build it once per ledger, not per row.
+pub struct CategorizationContext<'a> {
+    user_wallets: HashSet<Address>,
+    contracts: &'a ContractRegistry,
+}
+
+impl<'a> CategorizationContext<'a> {
+    pub fn new(user_wallets: &[String], contracts: &'a ContractRegistry) -> Self {
+        Self {
+            user_wallets: user_wallets.iter().map(|wallet| Address::from(wallet.as_str())).collect(),
+            contracts,
+        }
+    }
+
+    /// Whether `address` is one of the user's wallets
+    pub fn is_own_wallet(&self, address: &str) -> bool {
+        self.user_wallets.contains(&Address::from(address))
+    }
+}
+
+/// An ordered set of categorization rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorizationRules {
+    pub rules: Vec<CategorizationRule>,
+    #[serde(default)]
+    pub bridge_matching: BridgeMatching,
+}
+
+impl CategorizationRule {
+    /// Whether the rule applies to `row`, given whether its counterparty is an own wallet
+    fn matches(&self, row: &LedgerRow, counterparty_is_own: bool, contracts: &ContractRegistry) -> bool {
+        let conditions = &self.conditions;
+        let counterparty = row.counterparty.as_ref();
+
+        if !conditions.counterparty.is_empty()
+            && !counterparty.is_some_and(|cp| conditions.counterparty.iter().any(|c| cp == c))
+        {
+            return false;
+        }
+        if conditions.counterparty_is_own_wallet.is_some_and(|own_wallet| own_wallet != counterparty_is_own) {
+            return false;
+        }
+        if !conditions.counterparty_label.is_empty() {
+            let label = row.counterparty_label.as_deref().unwrap_or_default();
+            if !conditions.counterparty_label.iter().any(|part| contains_ignore_ascii_case(label, part)) {
+                return false;
+            }
+        }
+        if let Some(category) = conditions.contract_category {
+            let contract = counterparty.and_then(|cp| contracts.lookup(row.chain_id, cp));
+            if contract.and_then(|contract| contract.category) != Some(category) {
+                return false;
+            }
+        }
+        if conditions.asset.as_ref().is_some_and(|asset| !asset.eq_ignore_ascii_case(&row.asset)) {
+            return false;
+        }
+        if conditions.direction.is_some_and(|direction| direction != row.direction) {
+            return false;
+        }
+        if conditions.chain_id.is_some_and(|chain_id| chain_id != row.chain_id) {
+            return false;
+        }
+        if conditions.min_amount.is_some() || conditions.max_amount.is_some() {
+            let Ok(amount) = row.amount.parse::<f64>() else {
+                return false;
+            };
+            if conditions.min_amount.is_some_and(|min| amount < min)
+                || conditions.max_amount.is_some_and(|max| amount >= max)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl CategorizationRules {
+    /// The built-in heuristics
+    pub fn builtin() -> &'static Self {
+        static BUILTIN: OnceLock<CategorizationRules> = OnceLock::new();
+        BUILTIN.get_or_init(|| Self::from_json(BUILTIN_RULES).expect("built-in categorization rules are valid"))
+    }
+
+    /// Parse and validate rules from JSON (`{ "rules": [...] }`)
+    pub fn from_json(json: &str) -> Result<Self, TaxError> {
+        let rules: Self = serde_json::from_str(json).map_err(|e| TaxError::InvalidCategorizationRules(e.to_string()))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Parse and validate rules from TOML (`[[rules]]` tables)
+    pub fn from_toml(toml: &str) -> Result<Self, TaxError> {
+        let rules: Self = toml::from_str(toml).map_err(|e| TaxError::InvalidCategorizationRules(e.to_string()))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Add rules after validating them
+    pub fn extend(&mut self, rules: Vec<CategorizationRule>) -> Result<(), TaxError> {
+        Self {
+            rules: rules.clone(),
+            bridge_matching: self.bridge_matching,
+        }
+        .validate()?;
+        self.rules.extend(rules);
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), TaxError> {
+        for rule in &self.rules {
+            let invalid = |reason: &str| Err(TaxError::InvalidCategorizationRules(format!("{}: {}", rule.name, reason)));
+            if rule.name.is_empty() {
+                return Err(TaxError::InvalidCategorizationRules("rule without a name".to_string()));
+            }
+            if !(0.0..=1.0).contains(&rule.confidence) {
+                return invalid("confidence must be between 0 and 1");
+            }
+            if let (Some(min), Some(max)) = (rule.conditions.min_amount, rule.conditions.max_amount) {
+                if min >= max {
+                    return invalid("min_amount must be below max_amount");
+                }
+            }
+        }
+        if self.bridge_matching.tolerance_bps > 10_000 {
+            return Err(TaxError::InvalidCategorizationRules(
+                "bridge tolerance must be at most 10000 bps".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Categorize a row with the highest-priority matching rule (Unknown if none match)
+    pub fn categorize(&self, row: &LedgerRow, context: &CategorizationContext) -> CategorizationResult {
+        let counterparty_is_own = row.counterparty.as_deref().is_some_and(|cp| context.is_own_wallet(cp));
+        let mut best: Option<&CategorizationRule> = None;
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(row, counterparty_is_own, context.contracts))
+        {
+            if best.is_none_or(|best| rule.priority > best.priority) {
+                best = Some(rule);
+            }
+        }
+
+        best.map_or(
+            CategorizationResult {
+                category: Category::Unknown,
+                confidence: 0.0,
+            },
+            |rule| CategorizationResult {
+                category: rule.category,
+                confidence: rule.confidence,
+            },
+        )
+    }
+}
+
+/// Something that can categorize ledger rows: the rule engine, or a model (see the api
+/// crate's `ml` feature) consulted for rows the rules leave Unknown
+pub trait Categorizer: Send + Sync {
+    fn categorize(&self, row: &LedgerRow, context: &CategorizationContext) -> CategorizationResult;
+}
+
+impl Categorizer for CategorizationRules {
+    fn categorize(&self, row: &LedgerRow, context: &CategorizationContext) -> CategorizationResult {
+        CategorizationRules::categorize(self, row, context)
+    }
+}
+
+impl Default for CategorizationRules {
+    fn default() -> Self {
+        Self::builtin().clone()
+    }
+}
+
+/// Whether `haystack` contains `needle`, ignoring ASCII case, without allocating
+fn contains_ignore_ascii_case(haystack: &str, needle: &str) -> bool {
+    needle.is_empty()
+        || haystack
+            .as_bytes()
+            .windows(needle.len())
+            .any(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ledger_row;
+
+    fn row(direction: Direction, asset: &str, amount: &str, counterparty: &str) -> LedgerRow {
+        LedgerRow {
+            asset: asset.to_string(),
+            amount: amount.to_string(),
+            direction,
+            counterparty: Some(counterparty.into()),
+            confidence: 0.0,
+            ..ledger_row()
+        }
+    }
+
+    #[test]
+    fn test_builtin_rules_follow_priority() {
+        let rules = CategorizationRules::builtin();
+        let registry = ContractRegistry::default();
+        let context = CategorizationContext::new(&["0xABC".to_string(), "0xdef".to_string()], &registry);
+
+        // A small ETH transfer to an own wallet is internal, not gas
+        let internal = rules.categorize(&row(Direction::Out, "ETH", "0.001", "0xDEF"), &context);
+        assert_eq!(internal.category, Category::Internal);
+
+        let gas = rules.categorize(&row(Direction::Out, "ETH", "0.001", "0x123"), &context);
+        assert_eq!(gas.category, Category::Fees);
+
+        let unknown = rules.categorize(&row(Direction::Out, "ETH", "0.01", "0x123"), &context);
+        assert_eq!(unknown.category, Category::Unknown);
+    }
+
+    #[test]
+    fn test_user_rule_from_toml_overrides_fallback() {
+        let mut rules = CategorizationRules::builtin().clone();
+        let user = CategorizationRules::from_toml(
+            r#"
+            [[rules]]
+            name = "salary_from_employer"
+            priority = 60
+            category = "income"
+            confidence = 0.9
+
+            [rules.conditions]
+            direction = "in"
+            asset = "usdc"
+            counterparty = ["0xEMPLOYER"]
+            min_amount = 100.0
+            "#,
+        )
+        .unwrap();
+        rules.extend(user.rules).unwrap();
+
+        let registry = ContractRegistry::default();
+        let context = CategorizationContext::new(&[], &registry);
+        let salary = rules.categorize(&row(Direction::In, "USDC", "2500", "0xemployer"), &context);
+        assert_eq!(salary.confidence, 0.9);
+
+        let small = rules.categorize(&row(Direction::In, "USDC", "50", "0xemployer"), &context);
+        assert_eq!(small.confidence, 0.6);
+    }
+
+    #[test]
+    fn test_registered_contract_category() {
+        let rules = CategorizationRules::builtin();
+        let registry = ContractRegistry::sepolia_demo();
+        let context = CategorizationContext::new(&[], &registry);
+        let mut deposit = row(Direction::Out, "ETH", "0.001", "0x754F565155B363F94657AC7E106E361297CD6EBE");
+
+        // On Sepolia the deposit is part of a loss event, elsewhere it looks like gas
+        deposit.chain_id = 11155111;
+        assert_eq!(rules.categorize(&deposit, &context).category, Category::Losses);
+        deposit.chain_id = 1;
+        assert_eq!(rules.categorize(&deposit, &context).category, Category::Fees);
+    }
+
+    #[test]
+    fn test_invalid_confidence_rejected() {
+        let result = CategorizationRules::from_json(
+            r#"{ "rules": [{ "name": "bad", "category": "income", "confidence": 1.5 }] }"#,
+        );
+
+        assert!(matches!(result, Err(TaxError::InvalidCategorizationRules(_))));
+    }
+
+    struct AlwaysGift;
+
+    impl Categorizer for AlwaysGift {
+        fn categorize(&self, _row: &LedgerRow, _context: &CategorizationContext) -> CategorizationResult {
+            CategorizationResult {
+                category: Category::Gift,
+                confidence: 0.8,
+            }
+        }
+    }
+
+    #[test]
+    fn test_model_only_fills_in_unknown_rows() {
+        let mut ledger = vec![
+            row(Direction::Out, "ETH", "0.01", "0x123"),
+            row(Direction::Out, "ETH", "0.001", "0x123"),
+        ];
+        crate::categorize_ledger(&mut ledger, &[], &ContractRegistry::default());
+
+        let categorized = crate::categorize_unknown_with(&mut ledger, &[], &ContractRegistry::default(), &AlwaysGift);
+
+        assert_eq!(categorized, 1);
+        assert_eq!(ledger[0].category, Category::Gift);
+        assert_eq!(ledger[0].model_confidence, Some(0.8));
+        assert_eq!(ledger[0].confidence, 0.0);
+        assert_eq!(ledger[1].category, Category::Fees);
+        assert_eq!(ledger[1].model_confidence, None);
+    }
+
+}
@@ -0,0 +1,376 @@
+//! Canonical binary encoding of the ledger, and the commitment over it
+//!
+//! The proof's `ledgerCommitment` is the keccak256 hash of this encoding rather than of
+//! JSON, so it doesn't depend on float formatting or field order and can be recomputed in
+//! any language. Values are encoded as:
+//!
+//! - `u8`, `bool`: one byte (`bool` as 0 or 1)
+//! - `u16`, `u32`, `u64`: little-endian
+//! - `f32`: its IEEE 754 bits, little-endian
+//! - string (and `Address`, normalized): `u32` byte length, then the UTF-8 bytes
+//! - option: `0x00` for none, or `0x01` followed by the value
+//! - list: `u32` count, then each item
+//! - enum: one byte, the variant's position in its declaration (`Direction::In` = 0,
+//!   `Category::Income` = 0, ...)
+//! - struct: its fields in declaration order
+//!
+//! A commitment covers the ledger (a list of `LedgerRow`s), followed by each of the
+//! acquisition lots (tag 1), manual income (2), foreign-source tx hashes (3) and
+//! brought-forward losses (4) that isn't empty, as its tag byte and then the list. The
+//! tax settings follow the same way whenever they aren't their defaults: regime (5),
+//! deductions (6), residential status (7), capital assets (8), corporate regime (9),
+//! digital receipts share (10), member-transferred wallets (11) and filing date (12). A
+//! ledger with none of those commits to `ledger_commitment(&input.ledger)`.
+
+use crate::prelude::*;
+use crate::{
+    AcquisitionLot, Address, Category, CorporateRegime, Deductions, Direction, IncomeSource, LedgerRow, LossEntry,
+    LossHead, ManualIncomeEntry, ResidentialStatus, TaxInput, TaxRegime,
+};
+
+/// A value with a canonical encoding
+trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+impl Encode for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+}
+
+impl Encode for u16 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Encode for f32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_bits().to_le_bytes());
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_len(self.len(), out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl Encode for Address {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode(out);
+            }
+        }
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_len(self.len(), out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+
+fn encode_len(len: usize, out: &mut Vec<u8>) {
+    out.extend_from_slice(&u32::try_from(len).unwrap_or(u32::MAX).to_le_bytes());
+}
+
+impl Encode for Direction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            Direction::In => 0,
+            Direction::Out => 1,
+        };
+        tag.encode(out);
+    }
+}
+
+impl Encode for Category {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            Category::Income => 0,
+            Category::Gains => 1,
+            Category::Losses => 2,
+            Category::CapitalGains => 3,
+            Category::Gift => 4,
+            Category::Airdrop => 5,
+            Category::Fees => 6,
+            Category::Internal => 7,
+            Category::Unknown => 8,
+            Category::Spam => 9,
+            Category::StakingReward => 10,
+            Category::NftPurchase => 11,
+            Category::NftSale => 12,
+            Category::ExchangeTransfer => 13,
+            Category::NonTaxable => 14,
+        };
+        tag.encode(out);
+    }
+}
+
+impl Encode for IncomeSource {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            IncomeSource::Salary => 0,
+            IncomeSource::Interest => 1,
+            IncomeSource::Rent => 2,
+            IncomeSource::Other => 3,
+        };
+        tag.encode(out);
+    }
+}
+
+impl Encode for LossHead {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            LossHead::Business => 0,
+            LossHead::ShortTermCapital => 1,
+            LossHead::LongTermCapital => 2,
+        };
+        tag.encode(out);
+    }
+}
+
+impl Encode for TaxRegime {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            TaxRegime::New => 0,
+            TaxRegime::Old => 1,
+        };
+        tag.encode(out);
+    }
+}
+
+impl Encode for ResidentialStatus {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            ResidentialStatus::Resident => 0,
+            ResidentialStatus::Rnor => 1,
+            ResidentialStatus::NonResident => 2,
+        };
+        tag.encode(out);
+    }
+}
+
+impl Encode for CorporateRegime {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            CorporateRegime::Section115baa => 0,
+            CorporateRegime::Normal => 1,
+        };
+        tag.encode(out);
+    }
+}
+
+impl Encode for LedgerRow {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.chain_id.encode(out);
+        self.owner_wallet.encode(out);
+        self.tx_hash.encode(out);
+        self.block_time.encode(out);
+        self.asset.encode(out);
+        self.amount.encode(out);
+        self.decimals.encode(out);
+        self.direction.encode(out);
+        self.counterparty.encode(out);
+        self.category.encode(out);
+        self.confidence.encode(out);
+        self.user_override.encode(out);
+        self.token_id.encode(out);
+        self.quantity.encode(out);
+        self.contract_address.encode(out);
+        self.counterparty_label.encode(out);
+        self.counterparty_ens.encode(out);
+        self.model_confidence.encode(out);
+        self.inr_value.encode(out);
+    }
+}
+
+impl Encode for AcquisitionLot {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.asset.encode(out);
+        self.amount.encode(out);
+        self.cost_inr.encode(out);
+        self.acquired_at.encode(out);
+    }
+}
+
+impl Encode for ManualIncomeEntry {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.source.encode(out);
+        self.amount_inr.encode(out);
+        self.description.encode(out);
+    }
+}
+
+impl Encode for Deductions {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.section_80c.encode(out);
+        self.section_80d.encode(out);
+        self.section_80ccd_1b.encode(out);
+        self.section_80e.encode(out);
+        self.section_80g.encode(out);
+        self.section_80tta.encode(out);
+    }
+}
+
+impl Encode for LossEntry {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.head.encode(out);
+        self.assessment_year.encode(out);
+        self.amount_inr.encode(out);
+    }
+}
+
+/// Canonical encoding of a ledger
+pub fn encode_ledger(ledger: &[LedgerRow]) -> Vec<u8> {
+    let mut out = Vec::new();
+    ledger.encode(&mut out);
+    out
+}
+
+/// Keccak256 hash of a ledger's canonical encoding
+pub fn ledger_commitment(ledger: &[LedgerRow]) -> [u8; 32] {
+    alloy_primitives::keccak256(encode_ledger(ledger)).0
+}
+
+/// Append `value` under `tag`
+fn encode_tagged<T: Encode + ?Sized>(tag: u8, value: &T, out: &mut Vec<u8>) {
+    out.push(tag);
+    value.encode(out);
+}
+
+/// What `compute_ledger_commitment` hashes: the encoded ledger, followed by the tagged
+/// lists and settings of everything else about it that changes the proved tax
+pub fn ledger_commitment_bytes(input: &TaxInput) -> Vec<u8> {
+    let mut out = encode_ledger(&input.ledger);
+    if !input.acquisition_lots.is_empty() {
+        out.push(1);
+        input.acquisition_lots.encode(&mut out);
+    }
+    if !input.manual_income.is_empty() {
+        out.push(2);
+        input.manual_income.encode(&mut out);
+    }
+    if !input.foreign_source_tx_hashes.is_empty() {
+        out.push(3);
+        input.foreign_source_tx_hashes.encode(&mut out);
+    }
+    if !input.brought_forward_losses.losses.is_empty() {
+        out.push(4);
+        input.brought_forward_losses.losses.encode(&mut out);
+    }
+    if input.regime != TaxRegime::default() {
+        encode_tagged(5, &input.regime, &mut out);
+    }
+    if input.deductions != Deductions::default() {
+        encode_tagged(6, &input.deductions, &mut out);
+    }
+    if input.residential_status != ResidentialStatus::default() {
+        encode_tagged(7, &input.residential_status, &mut out);
+    }
+    if !input.capital_assets.is_empty() {
+        encode_tagged(8, input.capital_assets.as_slice(), &mut out);
+    }
+    if input.corporate_regime != CorporateRegime::default() {
+        encode_tagged(9, &input.corporate_regime, &mut out);
+    }
+    if !input.digital_receipts_95pct {
+        encode_tagged(10, &input.digital_receipts_95pct, &mut out);
+    }
+    if !input.member_transferred_wallets.is_empty() {
+        encode_tagged(11, input.member_transferred_wallets.as_slice(), &mut out);
+    }
+    if let Some(filing_date) = input.filing_date {
+        encode_tagged(12, &filing_date, &mut out);
+    }
+    out
+}
+
+/// Keccak256 commitment to everything about the ledger that changes the proved tax, as
+/// committed in `ledgerCommitment`
+pub fn compute_ledger_commitment(input: &TaxInput) -> [u8; 32] {
+    alloy_primitives::keccak256(ledger_commitment_bytes(input)).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_row_encoding_layout() {
+        let row = LedgerRow {
+            chain_id: 1,
+            owner_wallet: "0xAB".into(),
+            tx_hash: "0x1".to_string(),
+            block_time: 2,
+            asset: "ETH".to_string(),
+            amount: "1.5".to_string(),
+            decimals: 18,
+            direction: Direction::In,
+            counterparty: None,
+            category: Category::StakingReward,
+            confidence: 1.0,
+            user_override: true,
+            token_id: None,
+            quantity: None,
+            contract_address: None,
+            counterparty_label: None,
+            counterparty_ens: None,
+            model_confidence: None,
+            inr_value: Some(300),
+        };
+
+        let mut expected = vec![1, 0, 0, 0]; // one row
+        expected.extend_from_slice(&1u64.to_le_bytes());
+        expected.extend_from_slice(&[4, 0, 0, 0]);
+        expected.extend_from_slice(b"0xab"); // normalized
+        expected.extend_from_slice(&[3, 0, 0, 0]);
+        expected.extend_from_slice(b"0x1");
+        expected.extend_from_slice(&2u64.to_le_bytes());
+        expected.extend_from_slice(&[3, 0, 0, 0]);
+        expected.extend_from_slice(b"ETH");
+        expected.extend_from_slice(&[3, 0, 0, 0]);
+        expected.extend_from_slice(b"1.5");
+        expected.extend_from_slice(&[18, 0, 0, 10]); // decimals, In, no counterparty, StakingReward
+        expected.extend_from_slice(&1.0f32.to_bits().to_le_bytes());
+        expected.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 1]); // user override, five nones, some
+        expected.extend_from_slice(&300u64.to_le_bytes());
+
+        let ledger = [row];
+        assert_eq!(encode_ledger(&ledger), expected);
+        assert_eq!(ledger_commitment(&ledger), alloy_primitives::keccak256(&expected).0);
+    }
+}
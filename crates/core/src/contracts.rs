@@ -0,0 +1,130 @@
+//! Registry of known contracts
+//!
+//! Maps contract addresses to a label and, optionally, the category that transfers to
+//! or from the contract fall under. Categorization rules match on that category with the
+//! `contract_category` condition, so deployments can register their own contracts at
+//! runtime instead of recompiling.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Category, TaxError};
+
+const SEPOLIA_DEMO_CONTRACTS: &str = include_str!("../rules/contracts-sepolia.json");
+
+/// A contract with a known purpose
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownContract {
+    pub address: String,
+    pub label: String,
+    /// Chain the address is deployed on (`None` matches every chain)
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// Category of transfers to or from the contract (`None` for label-only entries)
+    #[serde(default)]
+    pub category: Option<Category>,
+}
+
+/// Known contracts, looked up by counterparty address
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContractRegistry {
+    contracts: Vec<KnownContract>,
+}
+
+impl ContractRegistry {
+    /// The Financoor demo contracts on Sepolia
+    pub fn sepolia_demo() -> Self {
+        Self::from_json(SEPOLIA_DEMO_CONTRACTS).expect("embedded contract registry is valid")
+    }
+
+    /// Parse a registry from JSON (`{ "contracts": [...] }`)
+    pub fn from_json(json: &str) -> Result<Self, TaxError> {
+        let parsed: Self = serde_json::from_str(json).map_err(|e| TaxError::InvalidContractRegistry(e.to_string()))?;
+        let mut registry = Self::default();
+        for contract in parsed.contracts {
+            registry.register(contract)?;
+        }
+        Ok(registry)
+    }
+
+    /// Parse a registry from TOML (`[[contracts]]` tables)
+    pub fn from_toml(toml: &str) -> Result<Self, TaxError> {
+        let parsed: Self = toml::from_str(toml).map_err(|e| TaxError::InvalidContractRegistry(e.to_string()))?;
+        let mut registry = Self::default();
+        for contract in parsed.contracts {
+            registry.register(contract)?;
+        }
+        Ok(registry)
+    }
+
+    /// Add a contract, replacing any existing entry for the same address and chain
+    pub fn register(&mut self, contract: KnownContract) -> Result<(), TaxError> {
+        let hex = contract.address.strip_prefix("0x").unwrap_or_default();
+        if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(TaxError::InvalidContractRegistry(format!("invalid address '{}'", contract.address)));
+        }
+
+        let contract = KnownContract {
+            address: contract.address.to_lowercase(),
+            ..contract
+        };
+        self.contracts
+            .retain(|existing| existing.address != contract.address || existing.chain_id != contract.chain_id);
+        self.contracts.push(contract);
+        Ok(())
+    }
+
+    /// The contract at `address` on `chain_id`, preferring a chain-specific entry
+    pub fn lookup(&self, chain_id: u64, address: &str) -> Option<&KnownContract> {
+        let mut fallback = None;
+        for contract in self.contracts.iter().filter(|contract| contract.address.eq_ignore_ascii_case(address)) {
+            match contract.chain_id {
+                Some(chain) if chain == chain_id => return Some(contract),
+                None => fallback = Some(contract),
+                Some(_) => {}
+            }
+        }
+        fallback
+    }
+
+    pub fn contracts(&self) -> &[KnownContract] {
+        &self.contracts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contract(address: &str, chain_id: Option<u64>, category: Option<Category>) -> KnownContract {
+        KnownContract {
+            address: address.to_string(),
+            label: "Test".to_string(),
+            chain_id,
+            category,
+        }
+    }
+
+    #[test]
+    fn test_lookup_prefers_chain_specific_entry() {
+        let address = "0x00000000000000000000000000000000000000AA";
+        let mut registry = ContractRegistry::default();
+        registry.register(contract(address, None, Some(Category::Gains))).unwrap();
+        registry.register(contract(address, Some(1), Some(Category::Losses))).unwrap();
+
+        assert_eq!(registry.lookup(1, &address.to_lowercase()).unwrap().category, Some(Category::Losses));
+        assert_eq!(registry.lookup(10, address).unwrap().category, Some(Category::Gains));
+        assert!(registry.lookup(1, "0x00000000000000000000000000000000000000bb").is_none());
+    }
+
+    #[test]
+    fn test_register_replaces_and_validates() {
+        let mut registry = ContractRegistry::sepolia_demo();
+        let count = registry.contracts().len();
+        registry
+            .register(contract("0xB99DB0D6A22EEB129E5AEBB4C94E46CB1640F465", Some(11155111), None))
+            .unwrap();
+
+        assert_eq!(registry.contracts().len(), count);
+        assert!(registry.register(contract("0x1234", None, None)).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Selective disclosure
+//!
+//! A proof normally commits the exact tax and income figures. To show a lender that tax
+//! was paid, or which band income falls in, without revealing either, the program can
+//! commit a statement instead: that one figure lies in a range. The statement is checked
+//! inside the program, so one that doesn't hold can't be proved at all.
+
+use alloy_primitives::U256;
+use alloy_sol_types::sol;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::{TaxError, TaxProofPublicValues};
+
+sol! {
+    /// Public values output by the SP1 program for a selective-disclosure proof
+    struct DisclosurePublicValues {
+        /// Keccak256 hash of the input ledger's canonical encoding (`compute_ledger_commitment`)
+        bytes32 ledgerCommitment;
+        /// User type (0=Individual, 1=HUF, 2=Corporate)
+        uint8 userType;
+        /// First year of the assessment year (2026 = AY 2026-27)
+        uint16 assessmentYear;
+        /// Start of the financial year whose ledger rows were taxed (unix seconds)
+        uint64 fyStart;
+        /// End of that financial year, inclusive (unix seconds)
+        uint64 fyEnd;
+        /// SHA256 hash of the USD/INR rates rows were converted at (`canonical_rate_table`)
+        bytes32 rateTableCommitment;
+        /// SHA256 hash of the canonical USD price table (`canonical_price_table`)
+        bytes32 pricesCommitment;
+        /// Figure the statement is about (0=total tax, 1=total income)
+        uint8 figure;
+        /// The figure is at least this much, in paisa
+        uint256 lowerBoundPaisa;
+        /// The figure is less than this much, in paisa (`type(uint256).max` when unbounded)
+        uint256 upperBoundPaisa;
+        /// Keccak256 hash of the wallets whose ownership signatures were verified (`owned_wallets_bytes`)
+        bytes32 ownedWalletsCommitment;
+        /// Whether those cover every wallet the ledger has rows for
+        bool allWalletsOwned;
+        /// The input's nonce
+        bytes32 nonce;
+        /// Keccak256 hash of `domain_separator_bytes`, naming the program version
+        bytes32 domainSeparator;
+    }
+}
+
+/// A statement a proof commits in place of the exact figures
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Disclosure {
+    /// Total tax payable is at least `paisa`
+    TaxAtLeast { paisa: u64 },
+    /// Total income lies in one of the bands `bounds_paisa` (ascending) divides it into;
+    /// the band is disclosed, not the income
+    IncomeBand { bounds_paisa: Vec<u64> },
+}
+
+/// Figure a disclosed statement is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisclosedFigure {
+    TotalTax,
+    TotalIncome,
+}
+
+impl DisclosedFigure {
+    /// Code of the figure in the public values
+    pub fn code(self) -> u8 {
+        match self {
+            DisclosedFigure::TotalTax => 0,
+            DisclosedFigure::TotalIncome => 1,
+        }
+    }
+}
+
+/// What a selective-disclosure proof states: a figure lies in `[lower, upper)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisclosedRange {
+    pub figure: DisclosedFigure,
+    pub lower_bound_paisa: u64,
+    /// None when unbounded
+    pub upper_bound_paisa: Option<u64>,
+}
+
+impl Disclosure {
+    /// The range this statement discloses for the given figures; an error if it doesn't
+    /// hold or its bands aren't ascending
+    pub fn range(&self, total_tax_paisa: u64, total_income_paisa: u64) -> Result<DisclosedRange, TaxError> {
+        match self {
+            Disclosure::TaxAtLeast { paisa } => {
+                if total_tax_paisa < *paisa {
+                    return Err(TaxError::InvalidDisclosure(format!("total tax is less than {} paisa", paisa)));
+                }
+                Ok(DisclosedRange {
+                    figure: DisclosedFigure::TotalTax,
+                    lower_bound_paisa: *paisa,
+                    upper_bound_paisa: None,
+                })
+            }
+            Disclosure::IncomeBand { bounds_paisa } => {
+                if bounds_paisa.is_empty() || bounds_paisa.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    return Err(TaxError::InvalidDisclosure("income bands must be ascending".to_string()));
+                }
+                let band = bounds_paisa.partition_point(|bound| *bound <= total_income_paisa);
+                Ok(DisclosedRange {
+                    figure: DisclosedFigure::TotalIncome,
+                    lower_bound_paisa: band.checked_sub(1).map_or(0, |below| bounds_paisa[below]),
+                    upper_bound_paisa: bounds_paisa.get(band).copied(),
+                })
+            }
+        }
+    }
+}
+
+/// Public values of a proof that discloses only `range`, and the context of `values`
+pub fn disclosure_public_values(values: &TaxProofPublicValues, range: &DisclosedRange) -> DisclosurePublicValues {
+    DisclosurePublicValues {
+        ledgerCommitment: values.ledgerCommitment,
+        userType: values.userType,
+        assessmentYear: values.assessmentYear,
+        fyStart: values.fyStart,
+        fyEnd: values.fyEnd,
+        rateTableCommitment: values.rateTableCommitment,
+        pricesCommitment: values.pricesCommitment,
+        figure: range.figure.code(),
+        lowerBoundPaisa: U256::from(range.lower_bound_paisa),
+        upperBoundPaisa: range.upper_bound_paisa.map_or(U256::MAX, U256::from),
+        ownedWalletsCommitment: values.ownedWalletsCommitment,
+        allWalletsOwned: values.allWalletsOwned,
+        nonce: values.nonce,
+        domainSeparator: values.domainSeparator,
+    }
+}
+
+impl DisclosedRange {
+    /// The range disclosed by a proof's public values
+    pub fn from_public_values(values: &DisclosurePublicValues) -> Result<Self, TaxError> {
+        let figure = match values.figure {
+            0 => DisclosedFigure::TotalTax,
+            1 => DisclosedFigure::TotalIncome,
+            code => return Err(TaxError::InvalidPublicValues(format!("unknown disclosed figure {}", code))),
+        };
+        let paisa = |bound: U256| {
+            u64::try_from(bound).map_err(|_| TaxError::InvalidPublicValues("disclosed bound out of range".to_string()))
+        };
+        Ok(DisclosedRange {
+            figure,
+            lower_bound_paisa: paisa(values.lowerBoundPaisa)?,
+            upper_bound_paisa: match values.upperBoundPaisa {
+                U256::MAX => None,
+                bound => Some(paisa(bound)?),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::FixedBytes;
+
+    use super::*;
+
+    #[test]
+    fn test_disclosed_ranges() {
+        let tax = Disclosure::TaxAtLeast { paisa: 50_000 };
+        assert_eq!(tax.range(50_000, 0).unwrap().upper_bound_paisa, None);
+        assert!(tax.range(49_999, 0).is_err());
+
+        let bands = Disclosure::IncomeBand {
+            bounds_paisa: vec![50_000_000, 100_000_000, 250_000_000],
+        };
+        let band = |income| {
+            let range = bands.range(0, income).unwrap();
+            (range.lower_bound_paisa, range.upper_bound_paisa)
+        };
+        assert_eq!(band(100), (0, Some(50_000_000)));
+        assert_eq!(band(100_000_000), (100_000_000, Some(250_000_000)));
+        assert_eq!(band(900_000_000), (250_000_000, None));
+        let unsorted = Disclosure::IncomeBand {
+            bounds_paisa: vec![2, 1],
+        };
+        assert!(unsorted.range(0, 0).is_err());
+    }
+
+    #[test]
+    fn test_range_survives_public_values() {
+        let range = DisclosedRange {
+            figure: DisclosedFigure::TotalIncome,
+            lower_bound_paisa: 250_000_000,
+            upper_bound_paisa: None,
+        };
+        let values = TaxProofPublicValues {
+            ledgerCommitment: FixedBytes([1; 32]),
+            totalTaxPaisa: U256::from(700_000_000u64),
+            userType: 0,
+            used44ada: false,
+            assessmentYear: 2026,
+            fyStart: 0,
+            fyEnd: 1,
+            rateTableCommitment: FixedBytes([2; 32]),
+            pricesCommitment: FixedBytes([3; 32]),
+            professionalIncomePaisa: U256::from(3_000_000_000u64),
+            vdaGainsPaisa: U256::ZERO,
+            vdaTaxPaisa: U256::ZERO,
+            cessPaisa: U256::ZERO,
+            ownedWalletsCommitment: FixedBytes([4; 32]),
+            allWalletsOwned: true,
+            nonce: FixedBytes([5; 32]),
+            domainSeparator: FixedBytes([6; 32]),
+        };
+        let values = disclosure_public_values(&values, &range);
+        assert_eq!(values.ledgerCommitment, FixedBytes([1; 32]));
+        assert!(values.allWalletsOwned);
+        assert_eq!((values.nonce, values.domainSeparator), (FixedBytes([5; 32]), FixedBytes([6; 32])));
+        assert_eq!(DisclosedRange::from_public_values(&values).unwrap(), range);
+    }
+}
@@ -0,0 +1,97 @@
+//! GST estimate on professional receipts
+//!
+//! Service providers must register (and charge GST) once aggregate turnover crosses the
+//! registration threshold. Exports of services count towards turnover but are zero-rated.
+//! GST is reported alongside income tax and never changes the income tax liability.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::rules::GstRules;
+use crate::{apply_bps, format_paisa};
+
+/// Opt-in settings for the GST estimate
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GstSettings {
+    /// Counterparties (addresses) whose payments are for export of services (zero-rated)
+    #[serde(default)]
+    pub export_counterparties: Vec<String>,
+}
+
+/// Estimated output GST for the financial year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GstEstimate {
+    /// All professional receipts, domestic and export (INR)
+    pub aggregate_turnover_inr: String,
+    /// Zero-rated export receipts (INR)
+    pub export_turnover_inr: String,
+    /// Whether aggregate turnover exceeds the registration threshold
+    pub registration_required: bool,
+    /// Output GST on domestic receipts, treating receipts as exclusive of GST (INR)
+    pub output_gst_inr: String,
+}
+
+/// Estimate output GST from professional receipts, given as (counterparty, INR paisa)
+pub(crate) fn estimate_gst(receipts: &[(Option<&str>, u64)], settings: &GstSettings, rules: &GstRules) -> GstEstimate {
+    let is_export = |counterparty: Option<&str>| {
+        counterparty.is_some_and(|counterparty| {
+            settings
+                .export_counterparties
+                .iter()
+                .any(|export| export.eq_ignore_ascii_case(counterparty))
+        })
+    };
+
+    let aggregate: u64 = receipts.iter().map(|(_, value)| value).sum();
+    let exports: u64 = receipts
+        .iter()
+        .filter(|(counterparty, _)| is_export(*counterparty))
+        .map(|(_, value)| value)
+        .sum();
+    let registration_required = aggregate > rules.registration_threshold * 100;
+    let output_gst = if registration_required {
+        apply_bps(aggregate - exports, rules.services_rate_bps)
+    } else {
+        0
+    };
+
+    GstEstimate {
+        aggregate_turnover_inr: format_paisa(aggregate),
+        export_turnover_inr: format_paisa(exports),
+        registration_required,
+        output_gst_inr: format_paisa(output_gst),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: GstRules = GstRules {
+        registration_threshold: 2_000_000,
+        services_rate_bps: 1800,
+    };
+
+    #[test]
+    fn test_below_threshold_owes_nothing() {
+        let estimate = estimate_gst(&[(Some("0xclient"), 150_000_000)], &GstSettings::default(), &RULES);
+
+        assert!(!estimate.registration_required);
+        assert_eq!(estimate.output_gst_inr, "0.00");
+    }
+
+    #[test]
+    fn test_exports_count_towards_threshold_but_are_zero_rated() {
+        let settings = GstSettings {
+            export_counterparties: vec!["0xFOREIGN".to_string()],
+        };
+        let receipts = [(Some("0xforeign"), 150_000_000), (Some("0xclient"), 100_000_000)];
+
+        let estimate = estimate_gst(&receipts, &settings, &RULES);
+
+        assert!(estimate.registration_required);
+        assert_eq!(estimate.aggregate_turnover_inr, "2500000.00");
+        assert_eq!(estimate.export_turnover_inr, "1500000.00");
+        assert_eq!(estimate.output_gst_inr, "180000.00");
+    }
+}
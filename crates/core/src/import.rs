@@ -0,0 +1,226 @@
+//! CSV ledger import
+//!
+//! Trades, deposits and withdrawals on exchanges (and anything else recorded by hand)
+//! never show up on-chain. A CSV export is turned into ledger rows through a column
+//! mapping naming which header holds the date, asset, amount and so on, so any
+//! exchange's format can be imported without a dedicated parser.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::tds::{parse_date, split_csv_line};
+use crate::{Address, Category, Direction, LedgerRow, TaxError};
+
+/// Which CSV columns (by header, case-insensitive) hold which ledger fields
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    /// Date or time of the transaction: a date (taken as IST), a UTC date and time
+    /// ("2025-06-15 10:30:00", "2025-06-15T10:30:00Z") or unix seconds or milliseconds
+    pub date: String,
+    pub asset: String,
+    /// Amount moved; negative amounts are outflows when there is no direction column
+    pub amount: String,
+    /// "in"/"out", "buy"/"sell", "deposit"/"withdrawal", "credit"/"debit", ...
+    #[serde(default)]
+    pub direction: Option<String>,
+    /// Exchange order or transaction ID; rows get a generated one otherwise
+    #[serde(default)]
+    pub tx_id: Option<String>,
+    #[serde(default)]
+    pub counterparty: Option<String>,
+    /// Category the user assigned (snake_case or spaced, e.g. "staking reward"); such rows
+    /// are kept as user overrides
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Account the rows belong to (e.g. "binance"), recorded as their owner wallet
+    pub account: String,
+    /// Chain the rows are on; 0 for off-chain records
+    #[serde(default)]
+    pub chain_id: u64,
+}
+
+/// Parse a CSV export into ledger rows according to `mapping`
+///
+/// The first non-blank line is the header; blank lines after it are skipped.
+pub fn import_ledger_csv(csv: &str, mapping: &ColumnMapping) -> Result<Vec<LedgerRow>, TaxError> {
+    let invalid = |msg: String| TaxError::InvalidCsvImport(msg);
+    let mut lines = csv.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+    let header = lines
+        .next()
+        .map(|(_, line)| split_csv_line(line))
+        .ok_or_else(|| invalid("empty CSV".to_string()))?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| invalid(format!("missing column: {}", name)))
+    };
+    let optional_column = |name: &Option<String>| name.as_deref().map(column).transpose();
+
+    let date_col = column(&mapping.date)?;
+    let asset_col = column(&mapping.asset)?;
+    let amount_col = column(&mapping.amount)?;
+    let direction_col = optional_column(&mapping.direction)?;
+    let tx_id_col = optional_column(&mapping.tx_id)?;
+    let counterparty_col = optional_column(&mapping.counterparty)?;
+    let category_col = optional_column(&mapping.category)?;
+
+    let mut ledger = Vec::new();
+    for (index, line) in lines {
+        let fields = split_csv_line(line);
+        let field = |col: usize| fields.get(col).map(|s| s.trim()).unwrap_or("");
+        let optional_field = |col: Option<usize>| col.map(field).filter(|value| !value.is_empty());
+        let line_no = index + 1;
+
+        let block_time = parse_timestamp(field(date_col))
+            .ok_or_else(|| invalid(format!("line {line_no}: invalid date '{}'", field(date_col))))?;
+
+        let amount = field(amount_col).replace(',', "");
+        let (negative, amount) = match amount.strip_prefix('-') {
+            Some(amount) => (true, amount.to_string()),
+            None => (false, amount),
+        };
+        if amount.parse::<f64>().is_err() {
+            return Err(invalid(format!("line {line_no}: invalid amount '{}'", field(amount_col))));
+        }
+
+        let direction = match direction_col {
+            Some(col) => parse_direction(field(col))
+                .ok_or_else(|| invalid(format!("line {line_no}: invalid direction '{}'", field(col))))?,
+            None if negative => Direction::Out,
+            None => Direction::In,
+        };
+
+        let category = optional_field(category_col)
+            .map(|value| {
+                serde_json::from_value::<Category>(serde_json::Value::String(
+                    value.to_lowercase().replace([' ', '-'], "_"),
+                ))
+                .map_err(|_| invalid(format!("line {line_no}: unknown category '{}'", value)))
+            })
+            .transpose()?;
+
+        ledger.push(LedgerRow {
+            chain_id: mapping.chain_id,
+            owner_wallet: mapping.account.to_lowercase().into(),
+            tx_hash: optional_field(tx_id_col)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("csv:{}:{}", mapping.account.to_lowercase(), line_no)),
+            block_time,
+            asset: field(asset_col).to_uppercase(),
+            decimals: amount.split_once('.').map_or(0, |(_, fraction)| fraction.len() as u8),
+            amount,
+            direction,
+            counterparty: optional_field(counterparty_col).map(Address::from),
+            category: category.unwrap_or(Category::Unknown),
+            confidence: if category.is_some() { 1.0 } else { 0.0 },
+            user_override: category.is_some(),
+            token_id: None,
+            quantity: None,
+            contract_address: None,
+            counterparty_label: None,
+            counterparty_ens: None,
+            model_confidence: None,
+            inr_value: None,
+        });
+    }
+
+    Ok(ledger)
+}
+
+fn parse_direction(value: &str) -> Option<Direction> {
+    match value.to_lowercase().as_str() {
+        "in" | "buy" | "deposit" | "receive" | "received" | "credit" | "reward" => Some(Direction::In),
+        "out" | "sell" | "withdraw" | "withdrawal" | "send" | "sent" | "debit" => Some(Direction::Out),
+        _ => None,
+    }
+}
+
+/// Unix seconds of a date (midnight IST), a UTC date and time, or a unix timestamp in
+/// seconds or milliseconds
+pub(crate) fn parse_timestamp(value: &str) -> Option<u64> {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        let timestamp: u64 = value.parse().ok()?;
+        return Some(if timestamp > 100_000_000_000 { timestamp / 1000 } else { timestamp });
+    }
+
+    let Some((date, time)) = value.split_once([' ', 'T']) else {
+        return parse_date(value);
+    };
+    let time = time.trim_end_matches('Z');
+    let time = time.split_once('.').map_or(time, |(time, _)| time);
+    let mut parts = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (parts.next()??, parts.next()??, parts.next().flatten().unwrap_or(0));
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    // parse_date gives midnight IST; move to midnight UTC
+    let midnight_utc = parse_date(date)? + crate::tds::IST_OFFSET_SECS;
+    Some(midnight_utc + hour * 3600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping() -> ColumnMapping {
+        ColumnMapping {
+            date: "Date(UTC)".to_string(),
+            asset: "Coin".to_string(),
+            amount: "Change".to_string(),
+            direction: Some("Operation".to_string()),
+            tx_id: Some("TxID".to_string()),
+            counterparty: None,
+            category: Some("Category".to_string()),
+            account: "Binance".to_string(),
+            chain_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_import_exchange_export() {
+        let csv = "\
+Date(UTC),Operation,Coin,Change,TxID,Category
+2025-06-15 10:30:00,Deposit,usdt,\"1,000.50\",abc1,
+2025-07-01T08:00:00Z,Withdraw,ETH,0.25,,
+2025-07-02,Reward,ETH,0.01,abc3,Staking Reward
+";
+        let ledger = import_ledger_csv(csv, &mapping()).unwrap();
+
+        assert_eq!(ledger.len(), 3);
+        assert_eq!(ledger[0].block_time, 1_749_983_400);
+        assert_eq!(ledger[0].asset, "USDT");
+        assert_eq!(ledger[0].amount, "1000.50");
+        assert_eq!(ledger[0].direction, Direction::In);
+        assert_eq!(ledger[0].owner_wallet, "binance");
+        assert_eq!(ledger[1].direction, Direction::Out);
+        assert_eq!(ledger[1].tx_hash, "csv:binance:3");
+        assert_eq!(ledger[2].category, Category::StakingReward);
+        assert!(ledger[2].user_override);
+        assert!(!ledger[0].user_override);
+    }
+
+    #[test]
+    fn test_import_reports_bad_rows() {
+        let mut mapping = mapping();
+        mapping.direction = None;
+        mapping.category = None;
+        mapping.tx_id = None;
+
+        let err = import_ledger_csv("Date(UTC),Coin,Change\n2025-06-15,ETH,abc\n", &mapping).unwrap_err();
+        assert!(err.to_string().contains("line 2: invalid amount"));
+
+        // Without a direction column the sign decides
+        let ledger = import_ledger_csv("Date(UTC),Coin,Change\n1750000000000,ETH,-0.5\n", &mapping).unwrap();
+        assert_eq!(ledger[0].direction, Direction::Out);
+        assert_eq!(ledger[0].amount, "0.5");
+        assert_eq!(ledger[0].block_time, 1_750_000_000);
+
+        assert!(matches!(
+            import_ledger_csv("Time,Coin,Change\n", &mapping),
+            Err(TaxError::InvalidCsvImport(_))
+        ));
+    }
+}
@@ -0,0 +1,277 @@
+//! Indian exchange statements
+//!
+//! WazirX, CoinDCX and ZebPay export trade history and TDS reports as CSV, each with
+//! its own headers, pair notation and date format (all in IST). Every trade becomes a
+//! base-asset and a quote-asset ledger row, left Unknown for review, and the 1% TDS
+//! the exchange deducted under section 194S on INR trades becomes a `TdsEntry` for the
+//! TDS credit. A TDS report (TDS column but no quantities) only yields TDS entries.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::import::parse_timestamp;
+use crate::tds::{find_column, split_csv_line, IST_OFFSET_SECS};
+use crate::{Category, Direction, LedgerRow, TaxError, TdsEntry};
+
+/// Quote currencies pairs are split on when written without a separator ("BTCINR")
+const QUOTE_ASSETS: [&str; 4] = ["INR", "USDT", "WRX", "BTC"];
+
+/// A supported Indian exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndianExchange {
+    WazirX,
+    CoinDcx,
+    ZebPay,
+}
+
+/// Header names of an exchange's export, in order of preference (case-insensitive, partial)
+struct Layout {
+    deductor: &'static str,
+    date: &'static [&'static str],
+    pair: &'static [&'static str],
+    side: &'static [&'static str],
+    quantity: &'static [&'static str],
+    total: &'static [&'static str],
+    tds: &'static [&'static str],
+    order_id: &'static [&'static str],
+}
+
+impl IndianExchange {
+    fn layout(self) -> Layout {
+        match self {
+            // Date,Market,Price,Volume,Total,Trade,Fee Currency,Fee,TDS
+            IndianExchange::WazirX => Layout {
+                deductor: "Zanmai Labs Pvt Ltd (WazirX)",
+                date: &["date"],
+                pair: &["market"],
+                side: &["trade", "side"],
+                quantity: &["volume"],
+                total: &["total"],
+                tds: &["tds"],
+                order_id: &["order id"],
+            },
+            // Order ID,Date,Pair,Side,Price,Quantity,Total,Fee,TDS Amount
+            IndianExchange::CoinDcx => Layout {
+                deductor: "Neblio Technologies Pvt Ltd (CoinDCX)",
+                date: &["date"],
+                pair: &["pair", "market"],
+                side: &["side"],
+                quantity: &["quantity"],
+                total: &["total"],
+                tds: &["tds"],
+                order_id: &["order id"],
+            },
+            // Transaction Date,Order Id,Order Type,Trade Pair,Quantity,Rate,Amount,Fee,TDS
+            IndianExchange::ZebPay => Layout {
+                deductor: "Awlencan Innovations India Ltd (ZebPay)",
+                date: &["transaction date", "date"],
+                pair: &["trade pair", "pair"],
+                side: &["order type", "type"],
+                quantity: &["quantity"],
+                total: &["amount", "total"],
+                tds: &["tds"],
+                order_id: &["order id"],
+            },
+        }
+    }
+
+    /// Account name recorded as the owner wallet of the rows
+    fn account(self) -> &'static str {
+        match self {
+            IndianExchange::WazirX => "wazirx",
+            IndianExchange::CoinDcx => "coindcx",
+            IndianExchange::ZebPay => "zebpay",
+        }
+    }
+}
+
+/// Ledger rows and TDS deducted from an exchange statement
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExchangeStatement {
+    pub ledger: Vec<LedgerRow>,
+    pub tds_entries: Vec<TdsEntry>,
+}
+
+/// Parse a trade history or TDS report exported from an Indian exchange
+pub fn parse_exchange_statement(exchange: IndianExchange, csv: &str) -> Result<ExchangeStatement, TaxError> {
+    let invalid = |msg: String| TaxError::InvalidCsvImport(msg);
+    let layout = exchange.layout();
+    let mut lines = csv.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+    let header = lines
+        .next()
+        .map(|(_, line)| split_csv_line(line))
+        .ok_or_else(|| invalid("empty CSV".to_string()))?;
+    let column = |names: &[&str]| {
+        find_column(&header, names).ok_or_else(|| invalid(format!("missing column: {}", names[0])))
+    };
+    let date_col = column(layout.date)?;
+    let pair_col = column(layout.pair)?;
+    let side_col = column(layout.side)?;
+    let total_col = column(layout.total)?;
+    // TDS reports have no quantities
+    let quantity_col = find_column(&header, layout.quantity);
+    let tds_col = find_column(&header, layout.tds);
+    let order_col = find_column(&header, layout.order_id);
+
+    let mut statement = ExchangeStatement::default();
+    for (index, line) in lines {
+        let fields = split_csv_line(line);
+        let field = |col: usize| fields.get(col).map(|s| s.trim()).unwrap_or("");
+        let line_no = index + 1;
+        let amount = |col: usize| {
+            let value = field(col).replace(',', "");
+            let value = value.trim_start_matches('₹').trim().to_string();
+            value
+                .parse::<f64>()
+                .map(|_| value.clone())
+                .map_err(|_| invalid(format!("line {line_no}: invalid amount '{}'", field(col))))
+        };
+
+        let block_time = parse_ist_timestamp(field(date_col))
+            .ok_or_else(|| invalid(format!("line {line_no}: invalid date '{}'", field(date_col))))?;
+        let (base, quote) = split_pair(field(pair_col))
+            .ok_or_else(|| invalid(format!("line {line_no}: invalid pair '{}'", field(pair_col))))?;
+        let buy = match field(side_col).to_lowercase().as_str() {
+            "buy" | "bid" => true,
+            "sell" | "ask" => false,
+            other => return Err(invalid(format!("line {line_no}: invalid side '{}'", other))),
+        };
+        let total = amount(total_col)?;
+        let id = order_col
+            .map(field)
+            .filter(|id| !id.is_empty())
+            .map(|id| format!("{}:{}", exchange.account(), id))
+            .unwrap_or_else(|| format!("{}:{}", exchange.account(), line_no));
+
+        if let Some(quantity_col) = quantity_col {
+            let quantity = amount(quantity_col)?;
+            let (base_direction, quote_direction) = if buy {
+                (Direction::In, Direction::Out)
+            } else {
+                (Direction::Out, Direction::In)
+            };
+            let row = |asset: &str, amount: &str, direction| LedgerRow {
+                chain_id: 0,
+                owner_wallet: exchange.account().into(),
+                tx_hash: id.clone(),
+                block_time,
+                asset: asset.to_string(),
+                amount: amount.to_string(),
+                decimals: amount.split_once('.').map_or(0, |(_, fraction)| fraction.len() as u8),
+                direction,
+                counterparty: None,
+                category: Category::Unknown,
+                confidence: 0.0,
+                user_override: false,
+                token_id: None,
+                quantity: None,
+                contract_address: None,
+                counterparty_label: Some(exchange.account().to_string()),
+                counterparty_ens: None,
+                model_confidence: None,
+                inr_value: None,
+            };
+            statement.ledger.push(row(&base, &quantity, base_direction));
+            statement.ledger.push(row(&quote, &total, quote_direction));
+        }
+
+        // TDS on INR trades is credited in rupees; on crypto-to-crypto trades it's deducted
+        // in the coin and isn't part of the credit
+        let tds_col = tds_col.filter(|&col| quote == "INR" && !field(col).is_empty());
+        if let Some(tds_col) = tds_col {
+            let tax_deducted_inr = amount(tds_col)?;
+            if tax_deducted_inr.parse::<f64>().is_ok_and(|tds| tds > 0.0) {
+                statement.tds_entries.push(TdsEntry {
+                    deductor_name: layout.deductor.to_string(),
+                    tan: String::new(),
+                    section: "194S".to_string(),
+                    transaction_date: ist_midnight(block_time),
+                    amount_paid_inr: total,
+                    tax_deducted_inr,
+                });
+            }
+        }
+    }
+
+    Ok(statement)
+}
+
+/// Base and quote asset of a pair: "BTC/INR", "BTC-INR", "BTC_INR" or "btcinr"
+fn split_pair(pair: &str) -> Option<(String, String)> {
+    let pair = pair.to_uppercase();
+    if let Some((base, quote)) = pair.split_once(['/', '-', '_']) {
+        return Some((base.to_string(), quote.to_string())).filter(|_| !base.is_empty() && !quote.is_empty());
+    }
+    QUOTE_ASSETS.iter().find_map(|quote| {
+        pair.strip_suffix(quote)
+            .filter(|base| !base.is_empty())
+            .map(|base| (base.to_string(), quote.to_string()))
+    })
+}
+
+/// Exchange timestamps are IST; dates alone already parse to midnight IST
+fn parse_ist_timestamp(value: &str) -> Option<u64> {
+    let timestamp = parse_timestamp(value)?;
+    if value.contains(':') {
+        timestamp.checked_sub(IST_OFFSET_SECS)
+    } else {
+        Some(timestamp)
+    }
+}
+
+/// Midnight IST of the day `timestamp` falls on, the way 26AS dates are recorded
+fn ist_midnight(timestamp: u64) -> u64 {
+    ((timestamp + IST_OFFSET_SECS) / 86_400 * 86_400).saturating_sub(IST_OFFSET_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wazirx_trades_and_tds() {
+        let csv = "\
+Date,Market,Price,Volume,Total,Trade,Fee Currency,Fee,TDS
+2025-06-15 16:00:00,BTCINR,\"50,00,000\",0.02,\"1,00,000\",Sell,INR,200,1000
+2025-06-16 09:30:00,ETHUSDT,2500,1.5,3750,Buy,USDT,3.75,0.015
+";
+        let statement = parse_exchange_statement(IndianExchange::WazirX, csv).unwrap();
+
+        assert_eq!(statement.ledger.len(), 4);
+        assert_eq!(statement.ledger[0].asset, "BTC");
+        assert_eq!(statement.ledger[0].direction, Direction::Out);
+        assert_eq!(statement.ledger[1].asset, "INR");
+        assert_eq!(statement.ledger[1].amount, "100000");
+        assert_eq!(statement.ledger[1].direction, Direction::In);
+        // 2025-06-15 16:00 IST
+        assert_eq!(statement.ledger[0].block_time, 1_749_983_400);
+        assert_eq!(statement.ledger[2].asset, "ETH");
+
+        // Only the INR trade's TDS is a rupee credit
+        assert_eq!(statement.tds_entries.len(), 1);
+        assert_eq!(statement.tds_entries[0].section, "194S");
+        assert_eq!(statement.tds_entries[0].tax_deducted_inr, "1000");
+        assert_eq!(statement.tds_entries[0].amount_paid_inr, "100000");
+        assert_eq!(statement.tds_entries[0].transaction_date, parse_ist_timestamp("2025-06-15").unwrap());
+    }
+
+    #[test]
+    fn test_zebpay_tds_report_and_coindcx_pairs() {
+        let csv = "\
+Transaction Date,Order Id,Order Type,Trade Pair,Rate,Amount,TDS
+15-06-2025 10:00,Z1,SELL,BTC-INR,5000000,50000,500
+";
+        let statement = parse_exchange_statement(IndianExchange::ZebPay, csv).unwrap();
+        assert!(statement.ledger.is_empty());
+        assert_eq!(statement.tds_entries.len(), 1);
+        assert!(statement.tds_entries[0].deductor_name.contains("ZebPay"));
+
+        let csv = "Order ID,Date,Pair,Side,Price,Quantity,Total,Fee\nC1,2025-06-15,SOL_INR,buy,12000,2,24000,48\n";
+        let statement = parse_exchange_statement(IndianExchange::CoinDcx, csv).unwrap();
+        assert_eq!(statement.ledger[0].asset, "SOL");
+        assert_eq!(statement.ledger[0].tx_hash, "coindcx:C1");
+        assert!(statement.tds_entries.is_empty());
+    }
+}
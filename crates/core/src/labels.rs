@@ -0,0 +1,169 @@
+//! Counterparty labels
+//!
+//! Names for addresses the user transacts with ("Binance 14", "Employer payroll"), from
+//! three sources: a bundled dataset of exchange hot wallets, Etherscan name tags looked up
+//! at runtime, and labels the user adds. The label is stored on each ledger row so that
+//! categorization rules can match on it with the `counterparty_label` condition; a
+//! transfer to or from an exchange is an `ExchangeTransfer`, not income.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::{LedgerRow, TaxError};
+
+const BUNDLED_EXCHANGE_LABELS: &str = include_str!("../rules/exchange-labels.json");
+
+/// Where a label came from, in increasing order of precedence
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelSource {
+    #[default]
+    Bundled,
+    Etherscan,
+    User,
+}
+
+/// A name for an address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressLabel {
+    pub address: String,
+    pub label: String,
+    /// Chain the label applies to (`None` matches every chain)
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    #[serde(default)]
+    pub source: LabelSource,
+}
+
+/// Known address labels, looked up by counterparty address
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AddressLabels {
+    labels: Vec<AddressLabel>,
+}
+
+impl AddressLabels {
+    /// The bundled exchange hot wallet labels
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_EXCHANGE_LABELS).expect("bundled exchange labels are valid")
+    }
+
+    /// Parse labels from JSON (`{ "labels": [...] }`)
+    pub fn from_json(json: &str) -> Result<Self, TaxError> {
+        let parsed: Self = serde_json::from_str(json).map_err(|e| TaxError::InvalidAddressLabels(e.to_string()))?;
+        let mut labels = Self::default();
+        for label in parsed.labels {
+            labels.add(label)?;
+        }
+        Ok(labels)
+    }
+
+    /// Add a label; an existing label for the same address and chain is replaced unless it
+    /// came from a higher-precedence source
+    pub fn add(&mut self, label: AddressLabel) -> Result<(), TaxError> {
+        let hex = label.address.strip_prefix("0x").unwrap_or_default();
+        if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(TaxError::InvalidAddressLabels(format!("invalid address '{}'", label.address)));
+        }
+        if label.label.trim().is_empty() {
+            return Err(TaxError::InvalidAddressLabels(format!("empty label for '{}'", label.address)));
+        }
+
+        let label = AddressLabel {
+            address: label.address.to_lowercase(),
+            ..label
+        };
+        let existing = self
+            .labels
+            .iter()
+            .position(|existing| existing.address == label.address && existing.chain_id == label.chain_id);
+        match existing {
+            Some(i) if self.labels[i].source > label.source => {}
+            Some(i) => self.labels[i] = label,
+            None => self.labels.push(label),
+        }
+        Ok(())
+    }
+
+    /// The label for `address` on `chain_id`, preferring a chain-specific entry
+    pub fn lookup(&self, chain_id: u64, address: &str) -> Option<&AddressLabel> {
+        let mut fallback = None;
+        for label in self.labels.iter().filter(|label| label.address.eq_ignore_ascii_case(address)) {
+            match label.chain_id {
+                Some(chain) if chain == chain_id => return Some(label),
+                None => fallback = Some(label),
+                Some(_) => {}
+            }
+        }
+        fallback
+    }
+
+    pub fn labels(&self) -> &[AddressLabel] {
+        &self.labels
+    }
+}
+
+/// Set `counterparty_label` on every row whose counterparty has a label; returns how many
+/// rows were labeled
+pub fn label_counterparties(ledger: &mut [LedgerRow], labels: &AddressLabels) -> usize {
+    let mut labeled = 0;
+    for row in ledger.iter_mut() {
+        let label = row
+            .counterparty
+            .as_deref()
+            .and_then(|counterparty| labels.lookup(row.chain_id, counterparty));
+        if let Some(label) = label {
+            row.counterparty_label = Some(label.label.clone());
+            labeled += 1;
+        }
+    }
+    labeled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Category;
+    use crate::testing::ledger_row;
+
+    fn label(address: &str, text: &str, source: LabelSource) -> AddressLabel {
+        AddressLabel {
+            address: address.to_string(),
+            label: text.to_string(),
+            chain_id: None,
+            source,
+        }
+    }
+
+    #[test]
+    fn test_user_labels_take_precedence() {
+        let address = "0x28C6C06298D514DB089934071355E5743BF21D60";
+        let mut labels = AddressLabels::bundled();
+        assert_eq!(labels.lookup(1, address).unwrap().label, "Binance 14");
+
+        labels.add(label(address, "My Binance deposits", LabelSource::User)).unwrap();
+        labels.add(label(address, "Binance 14", LabelSource::Etherscan)).unwrap();
+
+        assert_eq!(labels.lookup(11155111, address).unwrap().label, "My Binance deposits");
+        assert!(labels.add(label("0x1234", "Bad", LabelSource::User)).is_err());
+    }
+
+    #[test]
+    fn test_exchange_withdrawal_categorized_as_exchange_transfer() {
+        let mut ledger = vec![LedgerRow {
+            asset: "USDC".to_string(),
+            amount: "500".to_string(),
+            decimals: 6,
+            counterparty: Some("0x28c6c06298d514db089934071355e5743bf21d60".into()),
+            confidence: 0.0,
+            ..ledger_row()
+        }];
+
+        assert_eq!(label_counterparties(&mut ledger, &AddressLabels::bundled()), 1);
+        crate::categorize_ledger(&mut ledger, &["0xabc".to_string()], &crate::ContractRegistry::default());
+
+        assert_eq!(ledger[0].counterparty_label.as_deref(), Some("Binance 14"));
+        assert_eq!(ledger[0].category, Category::ExchangeTransfer);
+        // Withdrawals need a cost basis, so they're left for review
+        assert!(ledger[0].confidence < crate::DEFAULT_REVIEW_THRESHOLD);
+    }
+}
@@ -1,10 +1,136 @@
 //! Financoor Core - shared types, tax math, and categorization logic
 //!
-//! This crate is used by both the API server and the SP1 zkVM program.
+//! This crate is used by both the API server and the SP1 zkVM program. The zkVM program
+//! builds it without the default `std` feature: the types and tax math are then no_std +
+//! alloc, and the ingestion helpers that need std (categorization, the contract and
+//! stablecoin registries, receipt checks) are left out.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod address;
+pub mod aggregate;
+#[cfg(feature = "std")]
+pub mod categorization;
+pub mod commitment;
+#[cfg(feature = "std")]
+pub mod contracts;
+pub mod disclosure;
+pub mod gst;
+pub mod import;
+pub mod indian_exchanges;
+pub mod labels;
+pub mod losses;
+mod nft;
+#[cfg(feature = "std")]
+pub mod noise;
+pub mod ownership;
+pub mod reference_rates;
+pub mod review;
+pub mod rules;
+pub mod schedule_fa;
+pub mod spam;
+#[cfg(feature = "std")]
+pub mod stablecoins;
+pub mod tds;
+#[cfg(test)]
+mod testing;
+pub mod validate;
+pub mod valuation;
+pub mod wash;
+
+#[cfg(feature = "std")]
+use std::collections::HashSet;
 
 use alloy_sol_types::sol;
 use serde::{Deserialize, Serialize};
 
+use crate::prelude::*;
+use reference_rates::UsdInrRates;
+
+pub use address::Address;
+pub use aggregate::{aggregate_public_values, AggregatedTaxPublicValues};
+#[cfg(feature = "std")]
+pub use categorization::{
+    BridgeMatching, CategorizationContext, CategorizationRule, CategorizationRules, Categorizer, RuleConditions,
+};
+pub use commitment::{compute_ledger_commitment, encode_ledger, ledger_commitment, ledger_commitment_bytes};
+#[cfg(feature = "std")]
+pub use contracts::{ContractRegistry, KnownContract};
+pub use disclosure::{
+    disclosure_public_values, DisclosedFigure, DisclosedRange, Disclosure, DisclosurePublicValues,
+};
+pub use gst::{GstEstimate, GstSettings};
+pub use import::{import_ledger_csv, ColumnMapping};
+pub use indian_exchanges::{parse_exchange_statement, ExchangeStatement, IndianExchange};
+pub use labels::{label_counterparties, AddressLabel, AddressLabels, LabelSource};
+pub use losses::{LossCarryForward, LossEntry, LossHead, LossSetOff};
+#[cfg(feature = "std")]
+pub use noise::{drop_failed_transactions, flag_non_taxable, receipt_check_hashes};
+#[cfg(feature = "std")]
+pub use ownership::verify_ownership;
+pub use ownership::{
+    all_wallets_owned, evm_address_bytes, owned_wallets_bytes, ownership_message, personal_message, signature_bytes,
+};
+pub use reference_rates::{canonical_rate_table, parse_reference_rates_csv, ReferenceRate};
+pub use review::{
+    category_changes, restore_overrides, review_queue, review_row, CategoryChange, ReviewItem, DEFAULT_REVIEW_THRESHOLD,
+};
+pub use rules::{
+    assessment_year_of, financial_year_bounds, CapitalGainsRules, CorporateRules, CostInflationIndex, GstRules,
+    LateFilingRules, RegimeRules, Rebate87A, Slab, SurchargeTier, TaxRules, DEFAULT_ASSESSMENT_YEAR,
+};
+pub use schedule_fa::{schedule_fa_period, schedule_fa_rows, ForeignAccount, ScheduleFaRow};
+pub use spam::{flag_spam, SpamSettings};
+#[cfg(feature = "std")]
+pub use stablecoins::{
+    depegged_stablecoins, flag_stablecoin_swaps, price_stablecoins, Stablecoin, StablecoinRegistry, DEPEG_THRESHOLD_BPS,
+};
+pub use tds::{parse_form_26as_csv, reconcile_tds, TdsEntry, TdsMatch, TdsReconciliation};
+pub use validate::{validate_input, IssueCode, ValidationIssue};
+pub use valuation::{misvalued_row, value_ledger};
+pub use wash::{flag_wash_transfers, WashChain, WASH_WINDOW_SECS};
+
+/// alloc types the modules use in place of the std prelude
+mod prelude {
+    pub(crate) use alloc::collections::BTreeMap;
+    pub(crate) use alloc::string::{String, ToString};
+    pub(crate) use alloc::vec::Vec;
+    pub(crate) use alloc::{format, vec};
+}
+
+/// Errors from tax calculation
+#[derive(Debug, thiserror::Error)]
+pub enum TaxError {
+    #[error("No tax rules for assessment year {0}")]
+    UnsupportedAssessmentYear(u16),
+    #[error("Invalid tax rules: {0}")]
+    InvalidRules(String),
+    #[error("Invalid Form 26AS import: {0}")]
+    InvalidTdsImport(String),
+    #[error("Invalid categorization rules: {0}")]
+    InvalidCategorizationRules(String),
+    #[error("Invalid contract registry: {0}")]
+    InvalidContractRegistry(String),
+    #[error("Invalid address labels: {0}")]
+    InvalidAddressLabels(String),
+    #[error("Invalid CSV import: {0}")]
+    InvalidCsvImport(String),
+    #[error("Invalid address {0}")]
+    InvalidAddress(String),
+    #[error("Invalid reference rates: {0}")]
+    InvalidReferenceRates(String),
+    #[error("Invalid public values: {0}")]
+    InvalidPublicValues(String),
+    #[error("Invalid disclosure: {0}")]
+    InvalidDisclosure(String),
+    #[error("Invalid ownership signature: {0}")]
+    InvalidOwnershipSignature(String),
+    #[error("Amounts are too large to calculate tax on")]
+    Overflow,
+}
+
 /// User entity type for tax calculation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -24,44 +150,277 @@ pub enum Category {
     Gains,
     /// VDA/crypto losses from demo contracts
     Losses,
+    /// Disposal of a token treated as a capital asset rather than a VDA
+    CapitalGains,
+    /// Received without consideration from a non-relative (gifts from relatives are Internal)
+    Gift,
+    /// Tokens received for free in an airdrop
+    Airdrop,
     /// Gas/transaction fees paid
     Fees,
     /// Transfers between user's own wallets
     Internal,
     /// Unclassified - needs review
     Unknown,
+    /// Spam or scam token; never taxed
+    Spam,
+    /// Staking or yield reward, taxed as income from other sources at FMV on receipt
+    StakingReward,
+    /// Payment for an NFT, or the NFT received (sets its cost of acquisition)
+    NftPurchase,
+    /// Proceeds from selling an NFT, or the NFT sent (taxed as a VDA disposal)
+    NftSale,
+    /// Sent to or withdrawn from a centralized exchange; any disposal or purchase happens on
+    /// the exchange side, so the transfer itself isn't taxed
+    ExchangeTransfer,
+    /// Moves nothing of value: a token approval or other zero-value contract call, a
+    /// failed transaction or a send from a wallet to itself
+    NonTaxable,
 }
 
-/// Direction of a transaction
+/// Income tax regime for Individual/HUF (corporates are unaffected)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxRegime {
+    /// Section 115BAC new regime (default from AY 2024-25)
+    #[default]
+    New,
+    /// Old regime with Chapter VI-A deductions
+    Old,
+}
+
+/// Residential status for the financial year (Section 6)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResidentialStatus {
+    #[default]
+    Resident,
+    /// Resident but not ordinarily resident: foreign income isn't taxed
+    Rnor,
+    /// Only India-sourced or India-received income is taxed
+    NonResident,
+}
+
+/// Why a ledger row didn't count towards the tax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionReason {
+    /// Outside the financial year of the assessment year
+    OutsideFinancialYear,
+    /// Foreign-sourced income of a non-resident or RNOR
+    ForeignIncomeOfNonResident,
+    /// Categorized as a spam or scam token
+    Spam,
+    /// An approval, failed transaction or self-send
+    NonTaxable,
+}
+
+/// A ledger row left out of the calculation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedRow {
+    pub tx_hash: String,
+    pub reason: ExclusionReason,
+}
+
+/// Kind of warning attached to a breakdown
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// A selected option doesn't apply to the entity type and was ignored
+    InvalidForEntity,
+    /// Income that may be taxable in someone else's hands under the clubbing provisions
+    ClubbedIncome,
+    /// This year's losses can't be carried forward (belated return)
+    LossNotCarriedForward,
+    /// Tokens withdrawn from an exchange with no acquisition lot to give their cost
+    MissingCostBasis,
+    /// A stablecoin is priced away from $1
+    StablecoinDepeg,
+}
+
+/// Something the user should review; the tax is computed regardless
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxWarning {
+    pub kind: WarningKind,
+    pub message: String,
+    /// Ledger rows the warning is about, if any
+    pub tx_hashes: Vec<String>,
+}
+
+impl TaxWarning {
+    fn invalid_for_entity(message: impl Into<String>) -> Self {
+        Self {
+            kind: WarningKind::InvalidForEntity,
+            message: message.into(),
+            tx_hashes: Vec::new(),
+        }
+    }
+}
+
+/// Corporate tax regime
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorporateRegime {
+    /// Section 115BAA: 22% with a flat 10% surcharge, no exemptions
+    #[default]
+    #[serde(rename = "section_115baa")]
+    Section115baa,
+    /// Normal provisions: 25% (turnover up to ₹400Cr) or 30%, surcharge 7% above ₹1Cr, 12% above ₹10Cr
+    Normal,
+}
+
+/// Deductions claimable under the old regime (amounts in INR)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deductions {
+    /// Section 80C investments (capped at ₹1.5L)
+    #[serde(default)]
+    pub section_80c: String,
+    /// Section 80D health insurance premium (capped at ₹25,000)
+    #[serde(default)]
+    pub section_80d: String,
+    /// Section 80CCD(1B) additional NPS contribution (capped at ₹50,000)
+    #[serde(default)]
+    pub section_80ccd_1b: String,
+    /// Section 80E education loan interest (uncapped)
+    #[serde(default)]
+    pub section_80e: String,
+    /// Section 80G qualifying donations (capped at 10% of adjusted gross total income)
+    #[serde(default)]
+    pub section_80g: String,
+    /// Section 80TTA savings account interest (capped at ₹10,000)
+    #[serde(default)]
+    pub section_80tta: String,
+}
+
+/// A deduction claimed under one section, and how much of it was allowed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedDeduction {
+    /// Section name (e.g. "80C")
+    pub section: String,
+    pub claimed_inr: String,
+    pub allowed_inr: String,
+}
+
+/// Direction of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Direction {
     In,
     Out,
 }
 
+/// Chain ID recorded for Solana rows, which have no EVM chain ID (LI.FI's convention)
+pub const SOLANA_CHAIN_ID: u64 = 1_151_111_081_099_710;
+
+/// Chain ID recorded for Bitcoin rows (LI.FI's convention)
+pub const BITCOIN_CHAIN_ID: u64 = 20_000_000_000_001;
+
 /// A normalized ledger row (chain-agnostic)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LedgerRow {
+    /// EVM chain ID, `SOLANA_CHAIN_ID` or `BITCOIN_CHAIN_ID`; 0 for off-chain records
     pub chain_id: u64,
-    pub owner_wallet: String,
+    pub owner_wallet: Address,
     pub tx_hash: String,
     pub block_time: u64,
     pub asset: String,
     pub amount: String, // String to preserve precision
     pub decimals: u8,
     pub direction: Direction,
-    pub counterparty: Option<String>,
+    pub counterparty: Option<Address>,
     pub category: Category,
     pub confidence: f32,
     pub user_override: bool,
+    /// Token ID, for NFT (ERC-721/1155) transfers
+    #[serde(default)]
+    pub token_id: Option<String>,
+    /// Number of items of the token ID moved, for NFT transfers (1 for ERC-721)
+    #[serde(default)]
+    pub quantity: Option<String>,
+    /// Token contract address (lowercase), for token and NFT transfers
+    #[serde(default)]
+    pub contract_address: Option<String>,
+    /// Name of the counterparty (exchange, Etherscan name tag or user label), if known
+    #[serde(default)]
+    pub counterparty_label: Option<String>,
+    /// Primary ENS name of the counterparty, if it has one that resolves back to it
+    #[serde(default)]
+    pub counterparty_ens: Option<String>,
+    /// Confidence of the model that categorized the row, if the rules couldn't
+    /// (`confidence` stays the rules' own)
+    #[serde(default)]
+    pub model_confidence: Option<f32>,
+    /// INR value (paisa) of the row, set once by `value_ledger`: FMV on the day for
+    /// receipts, sale value for disposals. Rows without one are valued as they're used.
+    #[serde(default)]
+    pub inr_value: Option<u64>,
 }
 
 /// Price entry for an asset (used in tax calculation)
+///
+/// A dated entry prices the asset's rows on that day; an undated one prices its rows
+/// on days without one.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PriceEntry {
     pub asset: String,
     pub usd_price: String, // String to preserve precision
+    /// Day the price is for ("YYYY-MM-DD", UTC)
+    #[serde(default)]
+    pub date: Option<String>,
+    /// Set by the user rather than fetched from a price provider
+    #[serde(default)]
+    pub user_override: bool,
+}
+
+/// Day (UTC, "YYYY-MM-DD") a price entry for a timestamp is dated
+pub fn price_date(timestamp: u64) -> String {
+    let (year, month, day) = rules::civil_from_days(timestamp / 86_400);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Canonical JSON of a price table, whose SHA256 hash the proof commits to as
+/// `pricesCommitment`: `[asset, date, usd_price]` per entry, sorted. Who set a price
+/// doesn't change it, so `user_override` is left out.
+pub fn canonical_price_table(prices: &[PriceEntry]) -> String {
+    let mut table: Vec<(&str, Option<&str>, &str)> = prices
+        .iter()
+        .map(|p| (p.asset.as_str(), p.date.as_deref(), p.usd_price.as_str()))
+        .collect();
+    table.sort_unstable();
+    serde_json::to_string(&table).unwrap_or_default()
+}
+
+/// A VDA acquisition lot, matched FIFO against disposals of the same asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcquisitionLot {
+    pub asset: String,
+    /// Quantity acquired
+    pub amount: String, // String to preserve precision
+    /// Total cost of acquisition for the lot (INR)
+    pub cost_inr: String,
+    /// When the lot was acquired (unix seconds, 0 if unknown); sets the capital gains holding period
+    #[serde(default)]
+    pub acquired_at: u64,
+}
+
+/// Kind of non-crypto income entered manually
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncomeSource {
+    Salary,
+    Interest,
+    Rent,
+    Other,
+}
+
+/// A manually entered non-crypto income item, taxed at slab rates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManualIncomeEntry {
+    pub source: IncomeSource,
+    /// Gross amount for the year (INR)
+    pub amount_inr: String,
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 /// Source of wallet discovery
@@ -77,10 +436,13 @@ pub enum WalletSource {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Wallet {
     pub id: String,
-    pub address: String,
+    pub address: Address,
     pub label: Option<String>,
     pub group_id: Option<String>,
     pub source: WalletSource,
+    /// The wallet's signature of `ownership_message`, proving it belongs to the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ownership_signature: Option<String>,
 }
 
 /// A group of wallets (e.g., family member, business unit)
@@ -101,36 +463,271 @@ pub struct TaxInput {
     pub usd_inr_rate: String,
     /// Whether to apply 44ADA presumptive taxation (Individual only)
     pub use_44ada: bool,
+    /// At least 95% of gross receipts were received digitally, which raises the
+    /// 44ADA cap from ₹50L to ₹75L (on-chain receipts always are)
+    #[serde(default = "default_digital_receipts")]
+    pub digital_receipts_95pct: bool,
+    /// Tax regime for Individual/HUF (defaults to the new regime)
+    #[serde(default)]
+    pub regime: TaxRegime,
+    /// Old regime deductions (ignored under the new regime)
+    #[serde(default)]
+    pub deductions: Deductions,
+    /// First year of the assessment year (2026 = AY 2026-27)
+    #[serde(default = "default_assessment_year")]
+    pub assessment_year: u16,
+    /// Acquisition lots deducted from VDA disposals (Section 115BBH allows cost only)
+    #[serde(default)]
+    pub acquisition_lots: Vec<AcquisitionLot>,
+    /// Corporate regime (ignored for Individual/HUF)
+    #[serde(default)]
+    pub corporate_regime: CorporateRegime,
+    /// Assets whose disposals are treated as capital assets (LTCG/STCG) rather than VDAs
+    #[serde(default)]
+    pub capital_assets: Vec<String>,
+    /// Non-crypto income (salary, interest, rent) stacked under crypto income in the slabs
+    #[serde(default)]
+    pub manual_income: Vec<ManualIncomeEntry>,
+    /// TDS/TCS entries (e.g. imported from Form 26AS), credited against tax payable
+    #[serde(default)]
+    pub tds_entries: Vec<TdsEntry>,
+    #[serde(default)]
+    pub residential_status: ResidentialStatus,
+    /// Rows (by tx hash) whose income neither arises nor is received in India;
+    /// only excluded for non-residents and RNORs
+    #[serde(default)]
+    pub foreign_source_tx_hashes: Vec<String>,
+    /// When the return is (or will be) filed (unix seconds); a date after the due date
+    /// adds 234A interest and the 234F fee
+    #[serde(default)]
+    pub filing_date: Option<u64>,
+    /// HUF wallets holding assets a member converted into or transferred to the HUF;
+    /// income from them is flagged for clubbing with the member's income (Section 64(2))
+    #[serde(default)]
+    pub member_transferred_wallets: Vec<String>,
+    /// Estimate GST on professional receipts when set
+    #[serde(default)]
+    pub gst: Option<GstSettings>,
+    /// Unabsorbed losses from earlier assessment years
+    #[serde(default)]
+    pub brought_forward_losses: LossCarryForward,
+    /// USD/INR reference rates by date; `usd_inr_rate` covers days before the first one
+    #[serde(default)]
+    pub reference_rates: Vec<ReferenceRate>,
+    /// Committed as-is, so each filing's proof is distinct and a verifier can refuse one
+    /// it has already seen
+    #[serde(default)]
+    pub nonce: [u8; 32],
+}
+
+fn default_assessment_year() -> u16 {
+    DEFAULT_ASSESSMENT_YEAR
+}
+
+fn default_digital_receipts() -> bool {
+    true
 }
 
 /// Tax calculation breakdown
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaxBreakdown {
+    /// Assessment year whose rules were applied
+    pub assessment_year: u16,
+    /// Start of the financial year taxed (unix seconds, inclusive)
+    pub fy_start: u64,
+    /// End of the financial year taxed (unix seconds, inclusive)
+    pub fy_end: u64,
+    /// Regime the slab tax was computed under
+    pub regime: TaxRegime,
+    /// Corporate regime applied (corporates only)
+    pub corporate_regime: Option<CorporateRegime>,
+    pub residential_status: ResidentialStatus,
+    /// Ledger rows that didn't count towards the tax, and why
+    pub excluded_rows: Vec<ExcludedRow>,
+    /// Total income after deductions and set-off, slab-rate and special-rate (INR)
+    pub total_income_inr: String,
     /// Total professional income (INR)
     pub professional_income_inr: String,
-    /// Taxable professional income after 44ADA (if applicable)
+    /// Manually entered income after standard deductions (INR)
+    pub other_income_inr: String,
+    /// Gifts and airdrops taxable as income from other sources under Section 56(2)(x) (INR)
+    pub other_sources_income_inr: String,
+    /// Staking and yield rewards at FMV on receipt, taxed as income from other sources (INR)
+    pub staking_income_inr: String,
+    /// Chapter VI-A deductions applied (old regime only)
+    pub deductions_inr: String,
+    /// Per-section breakdown of the deductions claimed (old regime only)
+    pub applied_deductions: Vec<AppliedDeduction>,
+    /// Whether 44ADA presumptive taxation was applied
+    pub presumptive_44ada_applied: bool,
+    /// Why a requested 44ADA election was not applied
+    pub presumptive_rejected_reason: Option<String>,
+    /// Taxable professional income after 44ADA and deductions (if applicable)
     pub taxable_professional_income_inr: String,
-    /// VDA gains (INR)
+    /// VDA gains (INR), sale proceeds net of cost of acquisition
     pub vda_gains_inr: String,
+    /// Cost of acquisition matched against VDA disposals (INR)
+    pub vda_cost_of_acquisition_inr: String,
     /// VDA losses (INR) - displayed but not offset
     pub vda_losses_inr: String,
-    /// Professional income tax before rebate (slab-based)
+    /// Net short-term capital gains on tokens treated as capital assets (INR, taxed at slab rates)
+    pub short_term_capital_gains_inr: String,
+    /// Net long-term capital gains after indexation and set-off (INR)
+    pub long_term_capital_gains_inr: String,
+    /// Tax on professional income and STCG before rebate (slab-based)
     pub professional_tax_inr: String,
-    /// Section 87A rebate (for Individual/HUF with income ≤ ₹12L)
+    /// Section 87A rebate (resident individuals with income ≤ ₹12L, incl. marginal relief)
     pub section_87a_rebate_inr: String,
     /// VDA tax at 30%
     pub vda_tax_inr: String,
-    /// Health & Education Cess (4%)
+    /// Tax on long-term capital gains
+    pub ltcg_tax_inr: String,
+    /// Surcharge on income tax (after marginal relief)
+    pub surcharge_inr: String,
+    /// Health & Education Cess (4% of tax plus surcharge)
     pub cess_inr: String,
     /// Total tax payable
     pub total_tax_inr: String,
+    /// Total tax payable in paisa (matches `totalTaxPaisa` in the proof's public values)
+    pub total_tax_paisa: u64,
+    /// `total_income_inr` in paisa
+    pub total_income_paisa: u64,
+    /// `professional_income_inr` in paisa (`professionalIncomePaisa`)
+    pub professional_income_paisa: u64,
+    /// `vda_gains_inr` in paisa (`vdaGainsPaisa`)
+    pub vda_gains_paisa: u64,
+    /// `vda_tax_inr` in paisa (`vdaTaxPaisa`)
+    pub vda_tax_paisa: u64,
+    /// `cess_inr` in paisa (`cessPaisa`)
+    pub cess_paisa: u64,
+    /// TDS/TCS deducted during the financial year
+    pub tds_credit_inr: String,
+    /// Months (or part months) the return is filed after the due date
+    pub months_late: u64,
+    /// Interest under Section 234A on tax unpaid at the due date
+    pub interest_234a_inr: String,
+    /// Late filing fee under Section 234F
+    pub late_fee_234f_inr: String,
+    /// Tax still payable after TDS credit, plus any 234A interest and 234F fee
+    pub net_tax_payable_inr: String,
+    /// Total tax as a share of total income (basis points)
+    pub effective_tax_rate_bps: u64,
+    /// Rate on the next rupee of slab income, including surcharge and cess (basis points)
+    pub marginal_tax_rate_bps: u64,
+    /// Total tax (including surcharge and cess) attributed to each kind of income
+    pub tax_by_category: Vec<CategoryTax>,
+    /// Taxable receipts by calendar month (IST), in ascending order
+    pub monthly_income: Vec<MonthlyIncome>,
+    /// Options ignored for this entity type and income to review for clubbing
+    pub warnings: Vec<TaxWarning>,
+    /// GST on professional receipts (when requested), separate from income tax
+    pub gst: Option<GstEstimate>,
+    /// Brought-forward losses set off this year
+    pub brought_forward_set_off: Vec<LossSetOff>,
+    /// Losses to carry into the next assessment year (pass back as `brought_forward_losses`)
+    pub losses_carried_forward: LossCarryForward,
+}
+
+/// Kind of income a share of the tax is attributed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxCategory {
+    ProfessionalIncome,
+    /// Manual income plus gifts and airdrops
+    OtherIncome,
+    ShortTermCapitalGains,
+    LongTermCapitalGains,
+    Vda,
+}
+
+/// Taxable income of one kind and the tax attributed to it
+///
+/// Slab tax is shared pro rata over the slab-rate income; surcharge and cess follow the
+/// tax they're levied on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryTax {
+    pub category: TaxCategory,
+    pub income_inr: String,
+    pub tax_inr: String,
+}
+
+/// Receipts in one calendar month, before deductions and set-off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthlyIncome {
+    /// "YYYY-MM"
+    pub month: String,
+    pub professional_income_inr: String,
+    /// VDA disposal proceeds net of cost of acquisition (gains only)
+    pub vda_gains_inr: String,
+    /// Gifts and airdrops at FMV on receipt
+    pub gifts_received_inr: String,
+    /// Staking and yield rewards at FMV on receipt
+    pub staking_rewards_inr: String,
+}
+
+/// Side-by-side tax under both regimes, to help users choose
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegimeComparison {
+    pub new_regime: TaxBreakdown,
+    pub old_regime: TaxBreakdown,
+    /// Regime with the lower total tax (new regime wins ties)
+    pub recommended: TaxRegime,
+}
+
+/// Tax on the ledger rows owned by one wallet group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupTaxBreakdown {
+    /// Group id (`None` for ungrouped wallets and rows from wallets not in the input)
+    pub group_id: Option<String>,
+    pub breakdown: TaxBreakdown,
+}
+
+/// Per-year settings for a multi-year calculation (anything annual that the ledger can't provide)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearSettings {
+    pub assessment_year: u16,
+    #[serde(default)]
+    pub deductions: Deductions,
+    #[serde(default)]
+    pub manual_income: Vec<ManualIncomeEntry>,
+    #[serde(default)]
+    pub filing_date: Option<u64>,
+}
+
+/// One row of the multi-year comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearSummary {
+    pub assessment_year: u16,
+    pub total_income_inr: String,
+    pub total_tax_inr: String,
+    pub effective_tax_rate_bps: u64,
+    /// Change in total tax from the previous year (paisa)
+    pub tax_change_paisa: i64,
+}
+
+/// Breakdowns for each financial year a ledger spans, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiYearTaxBreakdown {
+    pub years: Vec<TaxBreakdown>,
+    pub summary: Vec<YearSummary>,
+    /// Total tax across all years (INR)
+    pub total_tax_inr: String,
+    /// Years with ledger rows but no rule table; their rows are left out
+    pub unsupported_assessment_years: Vec<u16>,
+}
+
+/// Per-group tax alongside the consolidated figure for the whole input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupedTaxBreakdown {
+    pub groups: Vec<GroupTaxBreakdown>,
+    pub consolidated: TaxBreakdown,
 }
 
 // ABI-encodable struct for on-chain verification
 sol! {
     /// Public values output by the SP1 program
     struct TaxProofPublicValues {
-        /// Keccak256 hash of the input ledger
+        /// Keccak256 hash of the input ledger's canonical encoding (`compute_ledger_commitment`)
         bytes32 ledgerCommitment;
         /// Total tax payable in paisa (INR * 100)
         uint256 totalTaxPaisa;
@@ -138,16 +735,42 @@ sol! {
         uint8 userType;
         /// Whether 44ADA was applied
         bool used44ada;
+        /// First year of the assessment year (2026 = AY 2026-27)
+        uint16 assessmentYear;
+        /// Start of the financial year whose ledger rows were taxed (unix seconds)
+        uint64 fyStart;
+        /// End of that financial year, inclusive (unix seconds)
+        uint64 fyEnd;
+        /// SHA256 hash of the USD/INR rates rows were converted at (`canonical_rate_table`)
+        bytes32 rateTableCommitment;
+        /// SHA256 hash of the canonical USD price table (`canonical_price_table`)
+        bytes32 pricesCommitment;
+        /// Total professional income in paisa
+        uint256 professionalIncomePaisa;
+        /// VDA gains in paisa, net of cost of acquisition
+        uint256 vdaGainsPaisa;
+        /// VDA tax (30%) in paisa
+        uint256 vdaTaxPaisa;
+        /// Health & Education Cess in paisa
+        uint256 cessPaisa;
+        /// Keccak256 hash of the wallets whose ownership signatures were verified (`owned_wallets_bytes`)
+        bytes32 ownedWalletsCommitment;
+        /// Whether those cover every wallet the ledger has rows for
+        bool allWalletsOwned;
+        /// The input's nonce
+        bytes32 nonce;
+        /// Keccak256 hash of `domain_separator_bytes`, naming the program version
+        bytes32 domainSeparator;
     }
 }
 
-/// Known demo contract addresses on Sepolia (lowercase)
-pub mod demo_contracts {
-    pub const DEMO_TOKEN: &str = "0x5815605f56c90e2b6467f489bd3b6e18bba1aff1";
-    pub const PROFIT_MACHINE: &str = "0xb99db0d6a22eeb129e5aebb4c94e46cb1640f465";
-    pub const LOSS_MACHINE: &str = "0x754f565155b363f94657ac7e106e361297cd6ebe";
-    pub const YIELD_FARM: &str = "0xfd3e2e9db59b9611fa14560c79316f6ce6714f9b";
-    pub const TAX_VERIFIER: &str = "0x1e0b2f7d1b1cef9aa03dad058b6665ca5ab2622c";
+/// Version of what the tax program proves, named by its domain separator; bumped with
+/// every change to the public values or the checks behind them
+pub const TAX_PROGRAM_VERSION: u32 = 1;
+
+/// What `domainSeparator` hashes (keccak256)
+pub fn domain_separator_bytes() -> Vec<u8> {
+    format!("financoor.tax_zk.v{}", TAX_PROGRAM_VERSION).into_bytes()
 }
 
 /// Result of categorization with confidence score
@@ -157,348 +780,2758 @@ pub struct CategorizationResult {
     pub confidence: f32,
 }
 
-/// Categorize a ledger row based on heuristics
+/// Categorize a ledger row with the built-in rules
 ///
-/// Rules:
+/// Rules (highest priority first, see `rules/categorization.json`):
 /// 1. INTERNAL: counterparty is in user's wallet list
-/// 2. GAINS: inflow from ProfitMachine or YieldFarm
-/// 3. LOSSES: outflow to LossMachine (the return is categorized separately)
-/// 4. FEES: small ETH outflows (likely gas)
-/// 5. INCOME: other inflows
-/// 6. UNKNOWN: can't determine
+/// 2. EXCHANGE_TRANSFER: counterparty is labeled as a centralized exchange
+/// 3. GAINS / LOSSES / STAKING_REWARD: inflows from a registered contract of that category
+/// 4. GAINS / LOSSES: deposits into those contracts
+/// 5. FEES: small ETH outflows (likely gas)
+/// 6. INCOME: other inflows
+/// 7. UNKNOWN: can't determine
+#[cfg(feature = "std")]
 pub fn categorize_transaction(
     row: &LedgerRow,
     user_wallets: &[String],
+    contracts: &ContractRegistry,
 ) -> CategorizationResult {
-    let counterparty = row.counterparty.as_ref().map(|s| s.to_lowercase());
-    let user_wallets_lower: Vec<String> = user_wallets.iter().map(|w| w.to_lowercase()).collect();
-
-    // Rule 1: Internal transfer between user's own wallets
-    if let Some(ref cp) = counterparty {
-        if user_wallets_lower.contains(cp) {
-            return CategorizationResult {
-                category: Category::Internal,
-                confidence: 1.0,
-            };
-        }
+    CategorizationRules::builtin().categorize(row, &CategorizationContext::new(user_wallets, contracts))
+}
+
+/// Categorize all rows in a ledger with the built-in rules
+#[cfg(feature = "std")]
+pub fn categorize_ledger(ledger: &mut [LedgerRow], user_wallets: &[String], contracts: &ContractRegistry) {
+    categorize_ledger_with_rules(ledger, user_wallets, CategorizationRules::builtin(), contracts);
+}
+
+/// Categorize all rows in a ledger with the given rules; user overrides are left alone
+#[cfg(feature = "std")]
+pub fn categorize_ledger_with_rules(
+    ledger: &mut [LedgerRow],
+    user_wallets: &[String],
+    rules: &CategorizationRules,
+    contracts: &ContractRegistry,
+) {
+    let context = CategorizationContext::new(user_wallets, contracts);
+    for row in ledger.iter_mut().filter(|row| !row.user_override) {
+        let result = rules.categorize(row, &context);
+        row.category = result.category;
+        row.confidence = result.confidence;
     }
 
-    // Rule 2: Check known demo contracts for gains
-    if row.direction == Direction::In {
-        if let Some(ref cp) = counterparty {
-            // Inflow from ProfitMachine or YieldFarm = Gains
-            if !demo_contracts::PROFIT_MACHINE.is_empty() && cp == demo_contracts::PROFIT_MACHINE {
-                return CategorizationResult {
-                    category: Category::Gains,
-                    confidence: 0.95,
-                };
-            }
-            if !demo_contracts::YIELD_FARM.is_empty() && cp == demo_contracts::YIELD_FARM {
-                return CategorizationResult {
-                    category: Category::Gains,
-                    confidence: 0.95,
-                };
-            }
-            // Inflow from LossMachine = still a return, but it's a loss scenario
-            // The loss is the difference, but we categorize the return as part of a loss event
-            if !demo_contracts::LOSS_MACHINE.is_empty() && cp == demo_contracts::LOSS_MACHINE {
-                return CategorizationResult {
-                    category: Category::Losses,
-                    confidence: 0.95,
-                };
-            }
+    nft::pair_nft_trades(ledger);
+    match_bridge_transfers(ledger, &rules.bridge_matching);
+    flag_staking_rewards(ledger);
+    flag_candidate_airdrops(ledger);
+}
+
+/// Ask `categorizer` about the rows the rules left Unknown (user overrides excepted),
+/// recording its confidence in `model_confidence`; the rule confidence stays 0 so the rows
+/// remain in the review queue. Returns how many rows the categorizer could place.
+#[cfg(feature = "std")]
+pub fn categorize_unknown_with(
+    ledger: &mut [LedgerRow],
+    user_wallets: &[String],
+    contracts: &ContractRegistry,
+    categorizer: &dyn Categorizer,
+) -> usize {
+    let context = CategorizationContext::new(user_wallets, contracts);
+    let mut categorized = 0;
+    for row in ledger
+        .iter_mut()
+        .filter(|row| !row.user_override && row.category == Category::Unknown)
+    {
+        let result = categorizer.categorize(row, &context);
+        if result.category != Category::Unknown {
+            row.category = result.category;
+            row.model_confidence = Some(result.confidence);
+            categorized += 1;
         }
     }
+    categorized
+}
 
-    // Rule 3: Outflows to known contracts
-    if row.direction == Direction::Out {
-        if let Some(ref cp) = counterparty {
-            // Outflow to demo contracts - these are deposits, categorize based on contract
-            if !demo_contracts::PROFIT_MACHINE.is_empty() && cp == demo_contracts::PROFIT_MACHINE {
-                return CategorizationResult {
-                    category: Category::Gains, // Part of a gain-generating event
-                    confidence: 0.9,
-                };
-            }
-            if !demo_contracts::LOSS_MACHINE.is_empty() && cp == demo_contracts::LOSS_MACHINE {
-                return CategorizationResult {
-                    category: Category::Losses, // Part of a loss-generating event
-                    confidence: 0.9,
-                };
-            }
-            if !demo_contracts::YIELD_FARM.is_empty() && cp == demo_contracts::YIELD_FARM {
-                return CategorizationResult {
-                    category: Category::Gains, // Staking for yield
-                    confidence: 0.9,
-                };
-            }
+/// Pair each outflow with the first later inflow of the same asset on a different chain,
+/// within the time window and no more than the tolerance below it, and mark both as
+/// Internal (bridging). Rows a specific rule matched with high confidence, and user
+/// overrides, are left alone.
+#[cfg(feature = "std")]
+fn match_bridge_transfers(ledger: &mut [LedgerRow], matching: &BridgeMatching) {
+    let eligible = |row: &LedgerRow| {
+        !row.user_override
+            && !matches!(row.category, Category::Internal | Category::ExchangeTransfer | Category::NonTaxable)
+            && row.confidence < 0.9
+    };
+    let mut order: Vec<usize> = (0..ledger.len()).collect();
+    order.sort_by_key(|&i| ledger[i].block_time);
+    let mut paired = vec![false; ledger.len()];
+
+    for (position, &out) in order.iter().enumerate() {
+        let sent = &ledger[out];
+        if sent.direction != Direction::Out || !eligible(sent) {
+            continue;
         }
+        let Ok(sent_amount) = sent.amount.parse::<f64>() else {
+            continue;
+        };
+        let min_received = sent_amount * (10_000 - matching.tolerance_bps) as f64 / 10_000.0;
 
-        // Rule 4: Small ETH outflows are likely fees
-        if row.asset == "ETH" {
-            if let Ok(amount) = row.amount.parse::<f64>() {
-                // Less than 0.01 ETH is likely gas
-                if amount < 0.01 {
-                    return CategorizationResult {
-                        category: Category::Fees,
-                        confidence: 0.8,
-                    };
-                }
+        // Rows are in time order, so stop at the end of the window
+        let received = order[position + 1..]
+            .iter()
+            .copied()
+            .take_while(|&i| ledger[i].block_time - sent.block_time <= matching.window_secs)
+            .find(|&i| {
+                let row = &ledger[i];
+                !paired[i]
+                    && row.direction == Direction::In
+                    && row.chain_id != sent.chain_id
+                    && row.asset.eq_ignore_ascii_case(&sent.asset)
+                    && eligible(row)
+                    && row
+                        .amount
+                        .parse::<f64>()
+                        .is_ok_and(|amount| amount >= min_received && amount <= sent_amount)
+            });
+
+        if let Some(received) = received {
+            paired[out] = true;
+            paired[received] = true;
+            for i in [out, received] {
+                ledger[i].category = Category::Internal;
+                ledger[i].confidence = 0.8;
             }
         }
     }
+}
 
-    // Rule 5: Other inflows = Income (professional income)
-    if row.direction == Direction::In {
-        return CategorizationResult {
-            category: Category::Income,
-            confidence: 0.6, // Lower confidence, user should review
-        };
+/// Minimum number of inflows from one source before they're treated as a reward stream
+#[cfg(feature = "std")]
+const STAKING_MIN_REWARDS: usize = 3;
+
+/// Flag periodic inflows of similar size from the same counterparty and asset, with
+/// nothing paid in the same transaction, as staking rewards. Periodic means the longest
+/// gap between rewards is at most twice the shortest, similar size means the largest is
+/// at most four times the smallest. Only catch-all income guesses are reclassified.
+#[cfg(feature = "std")]
+fn flag_staking_rewards(ledger: &mut [LedgerRow]) {
+    let paid_txs = txs_with_outflow(ledger);
+    let candidates: Vec<usize> = (0..ledger.len())
+        .filter(|&i| {
+            let row = &ledger[i];
+            row.direction == Direction::In
+                && !row.user_override
+                && row.category == Category::Income
+                && row.confidence < 0.7
+                && row.counterparty.is_some()
+                && !paid_txs.contains(row.tx_hash.as_str())
+        })
+        .collect();
+
+    // Candidate rows grouped by (counterparty, asset)
+    let mut streams: BTreeMap<(Address, String), Vec<usize>> = BTreeMap::new();
+    for i in candidates {
+        let row = &ledger[i];
+        let counterparty = row.counterparty.clone().unwrap_or_default();
+        streams.entry((counterparty, row.asset.clone())).or_default().push(i);
     }
 
-    // Rule 6: Can't determine
-    CategorizationResult {
-        category: Category::Unknown,
-        confidence: 0.0,
+    for mut stream in streams.into_values().filter(|stream| stream.len() >= STAKING_MIN_REWARDS) {
+        stream.sort_by_key(|&i| ledger[i].block_time);
+
+        let gaps: Vec<u64> = stream
+            .windows(2)
+            .map(|pair| ledger[pair[1]].block_time - ledger[pair[0]].block_time)
+            .collect();
+        let (min_gap, max_gap) = (gaps.iter().min().copied(), gaps.iter().max().copied());
+        let periodic = matches!((min_gap, max_gap), (Some(min), Some(max)) if min > 0 && max <= min * 2);
+
+        let amounts: Vec<f64> = stream.iter().filter_map(|&i| ledger[i].amount.parse::<f64>().ok()).collect();
+        let min_amount = amounts.iter().copied().fold(f64::INFINITY, f64::min);
+        let max_amount = amounts.iter().copied().fold(0.0, f64::max);
+        let similar = amounts.len() == stream.len() && min_amount > 0.0 && max_amount <= min_amount * 4.0;
+
+        if periodic && similar {
+            for i in stream {
+                ledger[i].category = Category::StakingReward;
+                ledger[i].confidence = 0.7;
+            }
+        }
     }
 }
 
-/// Categorize all rows in a ledger
-pub fn categorize_ledger(ledger: &mut [LedgerRow], user_wallets: &[String]) {
-    for row in ledger.iter_mut() {
-        let result = categorize_transaction(row, user_wallets);
-        row.category = result.category;
-        row.confidence = result.confidence;
+/// Flag inflows of tokens the user has never held, with nothing paid in the same
+/// transaction, as candidate airdrops. Only catch-all income guesses are reclassified.
+#[cfg(feature = "std")]
+fn flag_candidate_airdrops(ledger: &mut [LedgerRow]) {
+    let paid_txs = txs_with_outflow(ledger);
+    let mut seen_assets: HashSet<&str> = HashSet::new();
+    let mut order: Vec<usize> = (0..ledger.len()).collect();
+    order.sort_by_key(|&i| ledger[i].block_time);
+
+    let mut airdrops = Vec::new();
+    for i in order {
+        let row = &ledger[i];
+        let first_seen = seen_assets.insert(&row.asset);
+        let fallback_income = !row.user_override && row.category == Category::Income && row.confidence < 0.7;
+        let paid_in_same_tx = paid_txs.contains(row.tx_hash.as_str());
+
+        if first_seen && row.direction == Direction::In && row.asset != "ETH" && fallback_income && !paid_in_same_tx {
+            airdrops.push(i);
+        }
     }
+
+    for i in airdrops {
+        ledger[i].category = Category::Airdrop;
+        ledger[i].confidence = 0.5;
+    }
+}
+
+/// Hashes of the transactions in which the user paid something out
+#[cfg(feature = "std")]
+fn txs_with_outflow(ledger: &[LedgerRow]) -> HashSet<&str> {
+    ledger
+        .iter()
+        .filter(|row| row.direction == Direction::Out)
+        .map(|row| row.tx_hash.as_str())
+        .collect()
 }
 
 // ============================================================================
 // TAX CALCULATOR
 // ============================================================================
+//
+// All arithmetic is done in integer paisa (INR * 100) with the same truncation
+// order as the zkVM program, so the `/tax` preview matches the proved amount exactly.
+// Token quantities are fixed-point at `QUANTITY_DECIMALS` and USD prices at
+// `PRICE_DECIMALS`, parsed exactly from their decimal strings; no floats are involved.
+
+/// USD/INR rate (in paisa) used when the request's rate can't be parsed
+pub(crate) const DEFAULT_USD_INR_RATE_PAISA: u64 = 8_300;
+
+/// Decimals token quantities are kept at: the full precision of any token with up to 18
+pub(crate) const QUANTITY_DECIMALS: u32 = 18;
+const QUANTITY_SCALE: u128 = 10u128.pow(QUANTITY_DECIMALS);
+
+/// Decimals USD prices are kept at, so sub-cent tokens keep their value
+pub(crate) const PRICE_DECIMALS: u32 = 8;
+const PRICE_SCALE: u128 = 10u128.pow(PRICE_DECIMALS);
 
-/// New regime tax slabs for AY 2026-27 (Individual/HUF)
-const NEW_REGIME_SLABS: [(u64, u64, f64); 7] = [
-    (0, 400_000, 0.0),           // Up to 4L: 0%
-    (400_001, 800_000, 0.05),    // 4L-8L: 5%
-    (800_001, 1_200_000, 0.10),  // 8L-12L: 10%
-    (1_200_001, 1_600_000, 0.15), // 12L-16L: 15%
-    (1_600_001, 2_000_000, 0.20), // 16L-20L: 20%
-    (2_000_001, 2_400_000, 0.25), // 20L-24L: 25%
-    (2_400_001, u64::MAX, 0.30),  // Above 24L: 30%
-];
+/// Parse a non-negative decimal string into units of `10^-decimals`, truncating extra
+/// precision; None if it isn't one or doesn't fit
+pub(crate) fn parse_fixed(s: &str, decimals: u32) -> Option<u128> {
+    let s = s.trim();
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && fraction.is_empty() || !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let fraction = fraction.bytes().chain(core::iter::repeat(b'0')).take(decimals as usize);
+    whole
+        .bytes()
+        .chain(fraction)
+        .try_fold(0u128, |value, digit| value.checked_mul(10)?.checked_add(u128::from(digit - b'0')))
+}
+
+/// Parse a decimal string into hundredths (paisa / cents), truncating extra precision
+pub(crate) fn parse_hundredths(s: &str) -> Option<u64> {
+    parse_fixed(s, 2).and_then(|hundredths| u64::try_from(hundredths).ok())
+}
 
-/// VDA tax rate under Section 115BBH
-const VDA_TAX_RATE: f64 = 0.30;
+/// Parse a token quantity into units of `10^-QUANTITY_DECIMALS`
+pub(crate) fn parse_quantity(s: &str) -> Option<u128> {
+    parse_fixed(s, QUANTITY_DECIMALS)
+}
+
+/// Format a quantity in units of `10^-QUANTITY_DECIMALS` as a decimal string, without
+/// trailing zeros
+pub(crate) fn format_quantity(quantity: u128) -> String {
+    let fraction = format!("{:018}", quantity % QUANTITY_SCALE);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}", quantity / QUANTITY_SCALE)
+    } else {
+        format!("{}.{}", quantity / QUANTITY_SCALE, fraction)
+    }
+}
 
-/// Corporate tax rate under Section 115BAA
-const CORPORATE_TAX_RATE: f64 = 0.22;
+/// `value * part / whole` (with `part <= whole`), truncated; quantities of 2^64 units and
+/// more lose their low bits first so the product fits
+fn pro_rata(value: u64, part: u128, whole: u128) -> u64 {
+    // Below 2^64 the product always fits
+    let shift = 64u32.saturating_sub(whole.leading_zeros());
+    let (part, whole) = (part >> shift, (whole >> shift).max(1));
+    (u128::from(value) * part / whole) as u64
+}
 
-/// Corporate surcharge rate
-const CORPORATE_SURCHARGE_RATE: f64 = 0.10;
+/// Format a paisa amount as an INR string with two decimals
+pub(crate) fn format_paisa(paisa: u64) -> String {
+    format!("{}.{:02}", paisa / 100, paisa % 100)
+}
 
-/// Health & Education Cess rate
-const CESS_RATE: f64 = 0.04;
+/// Apply a basis-point rate to a paisa amount (saturating, for rates above 100%)
+pub(crate) fn apply_bps(amount: u64, bps: u64) -> u64 {
+    u64::try_from(u128::from(amount) * u128::from(bps) / 10_000).unwrap_or(u64::MAX)
+}
 
-/// 44ADA presumptive income rate
-const PRESUMPTIVE_44ADA_RATE: f64 = 0.50;
+/// Add a paisa amount to a running total, failing rather than wrapping
+fn add_paisa(total: &mut u64, amount: u64) -> Result<(), TaxError> {
+    *total = total.checked_add(amount).ok_or(TaxError::Overflow)?;
+    Ok(())
+}
 
-/// Section 87A rebate limit (for Individual/HUF under new regime)
-/// For FY 2025-26 (AY 2026-27): Rebate up to ₹60,000 if taxable income ≤ ₹12 lakh
-const SECTION_87A_INCOME_LIMIT: u64 = 1_200_000; // ₹12 lakh
-const SECTION_87A_REBATE_MAX: u64 = 60_000; // ₹60,000
+/// Sum paisa amounts, failing rather than wrapping
+pub(crate) fn sum_paisa(amounts: impl IntoIterator<Item = u64>) -> Result<u64, TaxError> {
+    amounts
+        .into_iter()
+        .try_fold(0u64, |total, amount| total.checked_add(amount))
+        .ok_or(TaxError::Overflow)
+}
 
-/// Calculate slab tax for Individual/HUF under new regime
-fn calculate_slab_tax(taxable_income: u64) -> u64 {
+/// Calculate slab tax (whole INR) for Individual/HUF under the given slab table
+fn calculate_slab_tax(taxable_income: u64, slabs: &[Slab]) -> u64 {
     let mut tax: u64 = 0;
+    let mut lower: u64 = 0;
 
-    for (lower, upper, rate) in NEW_REGIME_SLABS.iter() {
-        if taxable_income > *lower {
-            let amount_in_slab = if taxable_income >= *upper {
-                upper - lower
-            } else {
-                taxable_income.saturating_sub(*lower)
-            };
-            tax += (amount_in_slab as f64 * rate) as u64;
+    for slab in slabs {
+        let upper = slab.upto.unwrap_or(u64::MAX);
+        if taxable_income > lower {
+            let amount_in_slab = taxable_income.min(upper) - lower;
+            tax += apply_bps(amount_in_slab, slab.rate_bps);
         }
 
-        if taxable_income <= *upper {
+        if taxable_income <= upper {
             break;
         }
+        lower = upper;
     }
 
     tax
 }
 
-/// Convert amount to INR using prices and USD/INR rate
-fn amount_to_inr(
+/// Convert amount to INR paisa using the asset's USD price at `timestamp` (missing or
+/// invalid = $1.00) and the USD/INR rate of that day
+pub(crate) fn amount_to_inr_paisa(
     amount: &str,
     asset: &str,
+    timestamp: u64,
+    prices: &[PriceEntry],
+    usd_inr_rate: &UsdInrRates,
+) -> u64 {
+    quantity_to_inr_paisa(parse_quantity(amount).unwrap_or(0), asset, timestamp, prices, usd_inr_rate)
+}
+
+/// INR paisa value of a quantity (units of `10^-QUANTITY_DECIMALS`) at `timestamp`,
+/// saturating at `u64::MAX`
+pub(crate) fn quantity_to_inr_paisa(
+    quantity: u128,
+    asset: &str,
+    timestamp: u64,
     prices: &[PriceEntry],
-    usd_inr_rate: f64,
-) -> f64 {
-    let amount_val: f64 = amount.parse().unwrap_or(0.0);
+    usd_inr_rate: &UsdInrRates,
+) -> u64 {
+    checked_quantity_to_inr_paisa(quantity, asset, timestamp, prices, usd_inr_rate).unwrap_or(u64::MAX)
+}
 
-    // Find USD price for this asset
-    let usd_price: f64 = prices
+/// INR paisa value of a quantity at `timestamp`, or `None` if it doesn't fit in a `u64`
+pub(crate) fn checked_quantity_to_inr_paisa(
+    quantity: u128,
+    asset: &str,
+    timestamp: u64,
+    prices: &[PriceEntry],
+    usd_inr_rate: &UsdInrRates,
+) -> Option<u64> {
+    // USD price (scaled by PRICE_SCALE) for that day, else the undated one
+    let date = price_date(timestamp);
+    let usd_price: u128 = prices
         .iter()
-        .find(|p| p.asset == asset)
-        .map(|p| p.usd_price.parse().unwrap_or(1.0))
-        .unwrap_or(1.0);
+        .filter(|p| p.asset == asset)
+        .find(|p| p.date.as_deref() == Some(date.as_str()))
+        .or_else(|| prices.iter().find(|p| p.asset == asset && p.date.is_none()))
+        .and_then(|p| parse_fixed(&p.usd_price, PRICE_DECIMALS))
+        .unwrap_or(PRICE_SCALE);
+
+    // Paisa per whole unit, scaled by PRICE_SCALE. Whole units and the fraction are
+    // multiplied separately so that nothing overflows, and the remainders of both are
+    // kept so the result is only truncated once.
+    let per_unit = usd_price.checked_mul(u128::from(usd_inr_rate.at(timestamp)))?;
+    let whole = (quantity / QUANTITY_SCALE).checked_mul(per_unit)?;
+    let fraction = (whole % PRICE_SCALE) * QUANTITY_SCALE + (quantity % QUANTITY_SCALE).checked_mul(per_unit)?;
+    u64::try_from(whole / PRICE_SCALE + fraction / (QUANTITY_SCALE * PRICE_SCALE)).ok()
+}
 
-    amount_val * usd_price * usd_inr_rate
+/// Remaining quantity and cost (paisa) of an acquisition lot
+struct OpenLot<'a> {
+    asset: &'a str,
+    quantity: u128,
+    cost: u64,
+    acquired_at: u64,
 }
 
-/// Calculate tax based on categorized ledger and user inputs
-pub fn calculate_tax(input: &TaxInput) -> TaxBreakdown {
-    let usd_inr_rate: f64 = input.usd_inr_rate.parse().unwrap_or(83.0);
+fn open_lots(lots: &[AcquisitionLot]) -> Vec<OpenLot<'_>> {
+    lots.iter()
+        .map(|lot| OpenLot {
+            asset: &lot.asset,
+            quantity: parse_quantity(&lot.amount).unwrap_or(0),
+            cost: parse_hundredths(&lot.cost_inr).unwrap_or(0),
+            acquired_at: lot.acquired_at,
+        })
+        .collect()
+}
 
-    // Sum up amounts by category
-    let mut professional_income_inr: f64 = 0.0;
-    let mut vda_gains_inr: f64 = 0.0;
-    let mut vda_losses_inr: f64 = 0.0;
+/// Cost of acquisition (paisa) matched against one disposal, split by holding period
+#[derive(Default)]
+struct MatchedCost {
+    short_term: u64,
+    long_term: u64,
+    /// Quantity drawn from long-term lots
+    long_term_quantity: u128,
+}
 
-    for row in &input.ledger {
-        let inr_value = amount_to_inr(&row.amount, &row.asset, &input.prices, usd_inr_rate);
+/// Consume a disposal FIFO from the open lots of the same asset acquired by then and
+/// return its cost of acquisition. Quantity with no matching lot carries no cost.
+///
+/// With capital gains rules, lots held longer than the long-term period (and with a known
+/// acquisition time) are long-term, and their cost is indexed when the rules allow it.
+/// Without (VDA), all cost is short-term and unindexed.
+fn match_acquisition_cost(
+    lots: &mut [OpenLot],
+    asset: &str,
+    quantity: u128,
+    disposed_at: u64,
+    capital_gains: Option<&CapitalGainsRules>,
+) -> MatchedCost {
+    let mut remaining = quantity;
+    let mut matched = MatchedCost::default();
 
-        match row.category {
-            Category::Income => {
-                if row.direction == Direction::In {
-                    professional_income_inr += inr_value;
-                }
-            }
-            Category::Gains => {
-                // For gains, we count inflows as gains
-                if row.direction == Direction::In {
-                    vda_gains_inr += inr_value;
-                }
+    for lot in lots.iter_mut().filter(|lot| lot.asset == asset && lot.acquired_at <= disposed_at) {
+        if remaining == 0 {
+            break;
+        }
+        if lot.quantity == 0 {
+            continue;
+        }
+
+        // Take cost pro rata; the last unit of a lot takes whatever cost is left
+        let take = remaining.min(lot.quantity);
+        let take_cost = pro_rata(lot.cost, take, lot.quantity);
+        lot.quantity -= take;
+        lot.cost -= take_cost;
+        remaining -= take;
+
+        let long_term = capital_gains.filter(|cg| {
+            lot.acquired_at > 0 && disposed_at.saturating_sub(lot.acquired_at) > cg.long_term_holding_days * 86_400
+        });
+        match long_term {
+            Some(cg) if cg.indexation => {
+                let indexed = (take_cost as u128 * cg.cost_inflation_index_at(disposed_at) as u128)
+                    / cg.cost_inflation_index_at(lot.acquired_at) as u128;
+                matched.long_term = matched.long_term.saturating_add(u64::try_from(indexed).unwrap_or(u64::MAX));
+                matched.long_term_quantity += take;
             }
-            Category::Losses => {
-                // For losses, the inflow from LossMachine is less than deposit
-                // We track this separately (losses are not offset per 115BBH)
-                if row.direction == Direction::In {
-                    vda_losses_inr += inr_value;
-                }
+            Some(_) => {
+                matched.long_term = matched.long_term.saturating_add(take_cost);
+                matched.long_term_quantity += take;
             }
-            // Internal, Fees, Unknown don't contribute to taxable income in this MVP
-            _ => {}
+            None => matched.short_term = matched.short_term.saturating_add(take_cost),
         }
     }
 
-    // Apply 44ADA if enabled (Individual only)
-    let taxable_professional_income_inr = if input.use_44ada && input.user_type == UserType::Individual {
-        professional_income_inr * PRESUMPTIVE_44ADA_RATE
+    matched
+}
+
+/// Section 87A rebate on slab tax (whole INR) for resident individuals
+///
+/// Eligibility goes by total income, special-rate income included, though only slab tax is
+/// rebated. With marginal relief (new regime), slab tax just above the income limit is
+/// reduced so it never exceeds the income above the limit. Without it (old regime), the
+/// rebate simply stops at the limit.
+fn section_87a_rebate(total_income: u64, slab_tax: u64, rebate: &Rebate87A) -> u64 {
+    if total_income <= rebate.income_limit {
+        // Rebate is min(tax, regime maximum)
+        slab_tax.min(rebate.max_rebate)
+    } else if rebate.marginal_relief {
+        // Marginal relief: tax payable capped at income exceeding the limit
+        slab_tax.saturating_sub(total_income - rebate.income_limit)
     } else {
-        professional_income_inr
-    };
+        0
+    }
+}
 
-    // Calculate professional income tax based on user type
-    let (professional_tax_before_rebate, section_87a_rebate_inr) = match input.user_type {
-        UserType::Individual | UserType::Huf => {
-            let slab_tax = calculate_slab_tax(taxable_professional_income_inr as u64) as f64;
-
-            // Apply Section 87A rebate for Individual/HUF if taxable income ≤ ₹12 lakh
-            // Note: Rebate applies to total taxable income (professional + VDA)
-            // For simplicity, we apply to professional income only since VDA has flat 30%
-            let rebate = if (taxable_professional_income_inr as u64) <= SECTION_87A_INCOME_LIMIT {
-                // Rebate is min(tax, ₹60,000)
-                slab_tax.min(SECTION_87A_REBATE_MAX as f64)
-            } else {
-                0.0
-            };
-            (slab_tax, rebate)
-        }
-        UserType::Corporate => {
-            let base_tax = taxable_professional_income_inr * CORPORATE_TAX_RATE;
-            let surcharge = base_tax * CORPORATE_SURCHARGE_RATE;
-            // No rebate for corporates
-            (base_tax + surcharge, 0.0)
-        }
+/// Applicable surcharge tier for a total income in paisa, if any
+fn surcharge_tier(total_income: u64, tiers: &[SurchargeTier]) -> Option<&SurchargeTier> {
+    tiers.iter().rev().find(|tier| total_income > tier.above * 100)
+}
+
+/// Individual/HUF tax before surcharge and cess, in paisa: slab tax less 87A rebate (by
+/// total income), plus VDA tax
+fn individual_tax_before_surcharge(
+    slab_income: u64,
+    total_income: u64,
+    vda_gains: u64,
+    regime: &RegimeRules,
+    rules: &TaxRules,
+) -> u64 {
+    let slab_tax_inr = calculate_slab_tax(slab_income / 100, &regime.slabs);
+    let rebate_inr = section_87a_rebate(total_income / 100, slab_tax_inr, &regime.rebate_87a);
+
+    (slab_tax_inr - rebate_inr) * 100 + apply_bps(vda_gains, rules.vda_tax_rate_bps)
+}
+
+/// Surcharge in paisa, with marginal relief
+///
+/// Surcharge is levied on total income (slab income plus VDA gains) above the first tier.
+/// Marginal relief caps the extra tax + surcharge over a tier threshold at the income above
+/// it; when computing tax at the threshold, slab income is reduced first, then VDA gains.
+fn surcharge_with_relief(
+    slab_income: u64,
+    vda_gains: u64,
+    tiers: &[SurchargeTier],
+    tax_before_surcharge: impl Fn(u64, u64) -> u64,
+) -> u64 {
+    let total_income = slab_income + vda_gains;
+    let tier = match surcharge_tier(total_income, tiers) {
+        Some(tier) => tier,
+        None => return 0,
     };
 
-    // Professional tax after rebate
-    let professional_tax_inr = professional_tax_before_rebate - section_87a_rebate_inr;
+    let tax = tax_before_surcharge(slab_income, vda_gains);
+    let surcharge = apply_bps(tax, tier.rate_bps);
 
-    // VDA tax at 30% (only on gains, losses cannot be offset)
-    // Note: VDA tax doesn't get 87A rebate
-    let vda_tax_inr = vda_gains_inr * VDA_TAX_RATE;
+    // Marginal relief: tax + surcharge at exactly the threshold, plus the income above it
+    let threshold = tier.above * 100;
+    let excess = total_income - threshold;
+    let threshold_slab_income = slab_income.saturating_sub(excess);
+    let threshold_vda_gains = threshold - threshold_slab_income;
+    let threshold_tax = tax_before_surcharge(threshold_slab_income, threshold_vda_gains);
+    let threshold_rate = surcharge_tier(threshold, tiers).map_or(0, |t| t.rate_bps);
+    let max_payable = threshold_tax
+        .saturating_add(apply_bps(threshold_tax, threshold_rate))
+        .saturating_add(excess);
 
-    // Total tax before cess
-    let total_before_cess = professional_tax_inr + vda_tax_inr;
+    surcharge.min(max_payable.saturating_sub(tax))
+}
 
-    // Health & Education Cess at 4%
-    let cess_inr = total_before_cess * CESS_RATE;
+/// Old regime deductions in paisa after the per-section caps, itemized by section
+///
+/// 80G is limited to a share of gross total income after the other deductions, and the
+/// total never exceeds gross total income.
+fn old_regime_deductions(
+    deductions: &Deductions,
+    user_type: UserType,
+    gross_total_income: u64,
+    rules: &TaxRules,
+) -> (u64, Vec<AppliedDeduction>) {
+    let claim = |amount: &str| parse_hundredths(amount).unwrap_or(0);
+    // 80CCD(1B) (NPS) and 80E (education loan) are only available to individuals
+    let individual_limit = |limit: Option<u64>| if user_type == UserType::Individual { limit } else { Some(0) };
+    let capped = [
+        ("80C", claim(&deductions.section_80c), Some(rules.section_80c_limit)),
+        ("80D", claim(&deductions.section_80d), Some(rules.section_80d_limit)),
+        ("80CCD(1B)", claim(&deductions.section_80ccd_1b), individual_limit(Some(rules.section_80ccd_1b_limit))),
+        ("80E", claim(&deductions.section_80e), individual_limit(None)),
+        ("80TTA", claim(&deductions.section_80tta), Some(rules.section_80tta_limit)),
+    ];
 
-    // Total tax payable
-    let total_tax_inr = total_before_cess + cess_inr;
+    let mut total: u64 = 0;
+    let mut applied = Vec::new();
+    let mut record = |section: &str, claimed: u64, allowed: u64| {
+        if claimed > 0 {
+            applied.push(AppliedDeduction {
+                section: section.to_string(),
+                claimed_inr: format_paisa(claimed),
+                allowed_inr: format_paisa(allowed),
+            });
+        }
+    };
 
-    TaxBreakdown {
-        professional_income_inr: format!("{:.2}", professional_income_inr),
-        taxable_professional_income_inr: format!("{:.2}", taxable_professional_income_inr),
-        vda_gains_inr: format!("{:.2}", vda_gains_inr),
-        vda_losses_inr: format!("{:.2}", vda_losses_inr),
-        professional_tax_inr: format!("{:.2}", professional_tax_before_rebate),
-        section_87a_rebate_inr: format!("{:.2}", section_87a_rebate_inr),
-        vda_tax_inr: format!("{:.2}", vda_tax_inr),
-        cess_inr: format!("{:.2}", cess_inr),
-        total_tax_inr: format!("{:.2}", total_tax_inr),
+    for (section, claimed, limit) in capped {
+        let allowed = limit.map_or(claimed, |limit| claimed.min(limit * 100));
+        total = total.saturating_add(allowed);
+        record(section, claimed, allowed);
     }
+
+    let section_80g = claim(&deductions.section_80g);
+    let allowed_80g = section_80g.min(apply_bps(gross_total_income.saturating_sub(total), rules.section_80g_limit_bps));
+    total = total.saturating_add(allowed_80g);
+    record("80G", section_80g, allowed_80g);
+
+    (total.min(gross_total_income), applied)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Net manual income in paisa: salary less the regime's standard deduction, rent less
+/// the Section 24(a) deduction, interest and other income in full
+fn manual_income(
+    entries: &[ManualIncomeEntry],
+    regime: &RegimeRules,
+    rules: &TaxRules,
+) -> Result<u64, TaxError> {
+    let mut salary: u64 = 0;
+    let mut other: u64 = 0;
 
-    #[test]
-    fn test_user_type_serialization() {
-        let ut = UserType::Individual;
-        let json = serde_json::to_string(&ut).unwrap();
-        assert_eq!(json, "\"individual\"");
+    for entry in entries {
+        let amount = parse_hundredths(&entry.amount_inr).unwrap_or(0);
+        match entry.source {
+            IncomeSource::Salary => add_paisa(&mut salary, amount)?,
+            IncomeSource::Rent => {
+                add_paisa(&mut other, amount - apply_bps(amount, rules.house_property_deduction_bps))?
+            }
+            IncomeSource::Interest | IncomeSource::Other => add_paisa(&mut other, amount)?,
+        }
     }
 
-    #[test]
-    fn test_internal_categorization() {
-        let row = LedgerRow {
-            chain_id: 11155111,
-            owner_wallet: "0xabc".to_string(),
-            tx_hash: "0x123".to_string(),
-            block_time: 1234567890,
-            asset: "ETH".to_string(),
-            amount: "1.0".to_string(),
-            decimals: 18,
-            direction: Direction::In,
-            counterparty: Some("0xdef".to_string()),
-            category: Category::Unknown,
-            confidence: 0.0,
-            user_override: false,
-        };
+    sum_paisa([salary.saturating_sub(regime.salary_standard_deduction * 100), other])
+}
 
-        let wallets = vec!["0xabc".to_string(), "0xdef".to_string()];
-        let result = categorize_transaction(&row, &wallets);
+/// Gross receipts cap (INR) for 44ADA, depending on the share of digital receipts
+fn presumptive_44ada_limit(input: &TaxInput, rules: &TaxRules) -> u64 {
+    if input.digital_receipts_95pct {
+        rules.presumptive_44ada_digital_limit
+    } else {
+        rules.presumptive_44ada_limit
+    }
+}
 
-        assert_eq!(result.category, Category::Internal);
-        assert_eq!(result.confidence, 1.0);
+/// Reason a requested 44ADA election can't be applied, if any
+///
+/// When gross receipts exceed the cap, the whole professional income falls back to
+/// full taxation rather than being partially presumptive.
+fn presumptive_44ada_rejection(input: &TaxInput, gross_receipts: u64, rules: &TaxRules) -> Option<String> {
+    if input.user_type != UserType::Individual {
+        return Some("44ADA is only available to individuals".to_string());
+    }
+    if input.residential_status == ResidentialStatus::NonResident {
+        return Some("44ADA is only available to residents".to_string());
     }
 
-    #[test]
+    let limit = presumptive_44ada_limit(input, rules);
+    if gross_receipts > limit * 100 {
+        return Some(format!(
+            "Gross receipts of ₹{} exceed the 44ADA limit of ₹{}",
+            format_paisa(gross_receipts),
+            limit
+        ));
+    }
+
+    None
+}
+
+/// Calculate tax based on categorized ledger and user inputs, using the embedded
+/// rule table for the input's assessment year
+pub fn calculate_tax(input: &TaxInput) -> Result<TaxBreakdown, TaxError> {
+    let rules = TaxRules::for_assessment_year(input.assessment_year)?;
+    calculate_tax_with_rules(input, &rules)
+}
+
+/// Calculate tax against an explicit rule table (e.g. loaded from JSON)
+///
+/// Fails with `TaxError::Overflow` when income or tax doesn't fit in a `u64` of paisa.
+pub fn calculate_tax_with_rules(input: &TaxInput, rules: &TaxRules) -> Result<TaxBreakdown, TaxError> {
+    let usd_inr_rate = UsdInrRates::new(&input.usd_inr_rate, &input.reference_rates);
+
+    // Sum up amounts by category (all in paisa)
+    let mut professional_income: u64 = 0;
+    let mut vda_gains: u64 = 0;
+    let mut vda_losses: u64 = 0;
+    let mut vda_cost_of_acquisition: u64 = 0;
+    let mut short_term_gains: u64 = 0;
+    let mut short_term_losses: u64 = 0;
+    let mut long_term_gains: u64 = 0;
+    let mut long_term_losses: u64 = 0;
+    let mut gifts_received: u64 = 0;
+    let mut staking_rewards: u64 = 0;
+    let acquisition_lots =
+        valuation::lots_with_receipts(&input.acquisition_lots, &input.ledger, &input.prices, &usd_inr_rate);
+    let mut lots = open_lots(&acquisition_lots);
+    let (fy_start, fy_end) = financial_year_bounds(rules.assessment_year);
+
+    let taxes_foreign_income = input.residential_status == ResidentialStatus::Resident;
+    let mut excluded_rows = Vec::new();
+    // (year, month) -> [professional income, VDA gains, gifts received, staking rewards]
+    let mut monthly: BTreeMap<(u64, u64), [u64; 4]> = BTreeMap::new();
+    let mut clubbed_income: u64 = 0;
+    let mut clubbed_rows = Vec::new();
+    // (counterparty, INR value) of each professional receipt, for the GST estimate
+    let mut professional_receipts = Vec::new();
+    let nft_purchases = nft::nft_purchases(&input.ledger, &input.prices, &usd_inr_rate);
+    // (tx hash, (year, month), proceeds) of each NFT sale
+    let mut nft_sales: Vec<(&str, (u64, u64), u64)> = Vec::new();
+    // Exchange withdrawals that the open lots of the asset acquired by then don't cover
+    let mut missing_cost_basis = Vec::new();
+
+    for row in &input.ledger {
+        // Only rows inside the financial year count towards this assessment year, and
+        // foreign income only for residents
+        let exclusion = if !(fy_start..=fy_end).contains(&row.block_time) {
+            Some(ExclusionReason::OutsideFinancialYear)
+        } else if !taxes_foreign_income && input.foreign_source_tx_hashes.contains(&row.tx_hash) {
+            Some(ExclusionReason::ForeignIncomeOfNonResident)
+        } else if row.category == Category::Spam {
+            Some(ExclusionReason::Spam)
+        } else if row.category == Category::NonTaxable {
+            Some(ExclusionReason::NonTaxable)
+        } else {
+            None
+        };
+        if let Some(reason) = exclusion {
+            excluded_rows.push(ExcludedRow {
+                tx_hash: row.tx_hash.clone(),
+                reason,
+            });
+            continue;
+        }
+
+        let inr_value = valuation::row_value(row, &input.prices, &usd_inr_rate);
+        let (year, month, _) = rules::ist_date(row.block_time);
+        let month_totals = monthly.entry((year, month)).or_default();
+
+        let income_row = row.direction == Direction::In
+            && matches!(
+                row.category,
+                Category::Income
+                    | Category::Gains
+                    | Category::CapitalGains
+                    | Category::Gift
+                    | Category::Airdrop
+                    | Category::StakingReward
+                    | Category::NftSale
+            );
+        if input.user_type == UserType::Huf
+            && income_row
+            && input
+                .member_transferred_wallets
+                .iter()
+                .any(|wallet| row.owner_wallet == *wallet)
+        {
+            add_paisa(&mut clubbed_income, inr_value)?;
+            clubbed_rows.push(row.tx_hash.clone());
+        }
+
+        match (row.category, row.direction) {
+            (Category::Income, Direction::In) => {
+                add_paisa(&mut professional_income, inr_value)?;
+                add_paisa(&mut month_totals[0], inr_value)?;
+                professional_receipts.push((row.counterparty.as_deref(), inr_value));
+            }
+            (Category::Gains | Category::CapitalGains, Direction::In)
+                if row.category == Category::CapitalGains || input.capital_assets.contains(&row.asset) =>
+            {
+                // Disposal of a token treated as a capital asset: split proceeds by the
+                // holding period of the lots they're matched against
+                let quantity = parse_quantity(&row.amount).unwrap_or(0);
+                let cost = match_acquisition_cost(&mut lots, &row.asset, quantity, row.block_time, Some(&rules.capital_gains));
+                let long_term_proceeds = pro_rata(inr_value, cost.long_term_quantity, quantity);
+                let short_term_proceeds = inr_value - long_term_proceeds;
+                add_paisa(&mut short_term_gains, short_term_proceeds.saturating_sub(cost.short_term))?;
+                add_paisa(&mut short_term_losses, cost.short_term.saturating_sub(short_term_proceeds))?;
+                add_paisa(&mut long_term_gains, long_term_proceeds.saturating_sub(cost.long_term))?;
+                add_paisa(&mut long_term_losses, cost.long_term.saturating_sub(long_term_proceeds))?;
+            }
+            (Category::Gains, Direction::In) => {
+                // Gains inflows are disposal proceeds; only cost of acquisition is deductible,
+                // and a loss on one disposal can't offset another (115BBH)
+                let quantity = parse_quantity(&row.amount).unwrap_or(0);
+                let cost = match_acquisition_cost(&mut lots, &row.asset, quantity, row.block_time, None).short_term;
+                add_paisa(&mut vda_cost_of_acquisition, cost)?;
+                if inr_value >= cost {
+                    add_paisa(&mut vda_gains, inr_value - cost)?;
+                    add_paisa(&mut month_totals[1], inr_value - cost)?;
+                } else {
+                    add_paisa(&mut vda_losses, cost - inr_value)?;
+                }
+            }
+            (Category::Gift | Category::Airdrop, Direction::In) => {
+                // Valued at FMV on receipt
+                add_paisa(&mut gifts_received, inr_value)?;
+                add_paisa(&mut month_totals[2], inr_value)?;
+            }
+            (Category::StakingReward, Direction::In) => {
+                // Valued at FMV on receipt, with no 56(2)(x) exemption
+                add_paisa(&mut staking_rewards, inr_value)?;
+                add_paisa(&mut month_totals[3], inr_value)?;
+            }
+            (Category::NftSale, Direction::In) if row.token_id.is_none() => {
+                // Proceeds are netted against cost per sale transaction, after the loop
+                match nft_sales.iter_mut().find(|(tx_hash, _, _)| *tx_hash == row.tx_hash) {
+                    Some((_, _, proceeds)) => add_paisa(proceeds, inr_value)?,
+                    None => nft_sales.push((&row.tx_hash, (year, month), inr_value)),
+                }
+            }
+            (Category::ExchangeTransfer, Direction::In) => {
+                // Bought on the exchange: not taxed, but a later disposal needs its cost, so
+                // the lots (given or from receipts) acquired by then must still hold enough
+                let quantity = parse_quantity(&row.amount).unwrap_or(0);
+                let held: u128 = lots
+                    .iter()
+                    .filter(|lot| lot.asset.eq_ignore_ascii_case(&row.asset) && lot.acquired_at <= row.block_time)
+                    .map(|lot| lot.quantity)
+                    .sum();
+                if held == 0 || held < quantity {
+                    missing_cost_basis.push(row.tx_hash.clone());
+                }
+            }
+            (Category::Losses, Direction::In) => {
+                // For losses, the inflow from LossMachine is less than deposit
+                // We track this separately (losses are not offset per 115BBH)
+                add_paisa(&mut vda_losses, inr_value)?;
+            }
+            // Internal, Fees, Unknown, exchange deposits and outflows don't contribute to taxable income in this MVP
+            _ => {}
+        }
+    }
+
+    // Each NFT sale is a separate VDA disposal: a loss on one can't offset another
+    for (tx_hash, month, proceeds) in nft_sales {
+        let cost = nft::nft_sale_cost(&input.ledger, tx_hash, &nft_purchases);
+        add_paisa(&mut vda_cost_of_acquisition, cost)?;
+        if proceeds >= cost {
+            add_paisa(&mut vda_gains, proceeds - cost)?;
+            add_paisa(&mut monthly.entry(month).or_default()[1], proceeds - cost)?;
+        } else {
+            add_paisa(&mut vda_losses, cost - proceeds)?;
+        }
+    }
+
+    // Short-term capital losses set off against STCG, then LTCG; long-term losses only
+    // against LTCG (anything left over is carried forward)
+    let short_term_capital_loss = short_term_losses
+        .saturating_sub(short_term_gains)
+        .saturating_sub(long_term_gains.saturating_sub(long_term_losses));
+    let long_term_capital_loss = long_term_losses.saturating_sub(long_term_gains);
+    let short_term_capital_gains = short_term_gains.saturating_sub(short_term_losses);
+    let long_term_capital_gains = long_term_gains
+        .saturating_sub(long_term_losses)
+        .saturating_sub(short_term_losses.saturating_sub(short_term_gains));
+    let ltcg_rate = rules.capital_gains.ltcg_rate_bps;
+
+    // Apply 44ADA if enabled (Individual only, up to the gross receipts cap)
+    let presumptive_rejected_reason = if !input.use_44ada {
+        None
+    } else {
+        presumptive_44ada_rejection(input, professional_income, rules)
+    };
+    let presumptive_44ada_applied = input.use_44ada && presumptive_rejected_reason.is_none();
+    let presumptive_income = if presumptive_44ada_applied {
+        apply_bps(professional_income, rules.presumptive_44ada_rate_bps)
+    } else {
+        professional_income
+    };
+
+    // Brought-forward business and capital losses, oldest first
+    let brought_forward = losses::set_off_brought_forward(
+        &input.brought_forward_losses,
+        rules.assessment_year,
+        presumptive_income,
+        short_term_capital_gains,
+        long_term_capital_gains,
+    );
+    let presumptive_income = brought_forward.business_income;
+    let short_term_capital_gains = brought_forward.short_term_capital_gains;
+    let long_term_capital_gains = brought_forward.long_term_capital_gains;
+
+    // Regime only matters for Individual/HUF; corporates pick between 115BAA and normal rates
+    let regime = match input.user_type {
+        UserType::Individual | UserType::Huf => input.regime,
+        UserType::Corporate => TaxRegime::New,
+    };
+    // The 87A rebate is only for resident individuals
+    let mut regime_rules = rules.regime(regime).clone();
+    if input.residential_status == ResidentialStatus::NonResident || input.user_type != UserType::Individual {
+        regime_rules.rebate_87a.max_rebate = 0;
+        regime_rules.rebate_87a.marginal_relief = false;
+    }
+    let regime_rules = &regime_rules;
+    let corporate_regime = (input.user_type == UserType::Corporate).then_some(input.corporate_regime);
+    let corporate_rules = rules.corporate(input.corporate_regime);
+    // Gross receipts stand in for turnover when picking the normal regime's rate
+    let corporate_rate = corporate_rules.rate_for_turnover(professional_income);
+
+    let manual_income = manual_income(&input.manual_income, regime_rules, rules)?;
+
+    // 56(2)(x): gifts and airdrops are taxed in full once they exceed the exemption in aggregate
+    let other_sources_income = if gifts_received > rules.gift_exemption_limit * 100 {
+        gifts_received
+    } else {
+        0
+    };
+    let other_income = sum_paisa([manual_income, other_sources_income, staking_rewards])?;
+
+    // Chapter VI-A deductions (old regime only, never against VDA income per 115BBH),
+    // set against professional income first, then other income
+    let (deductions, applied_deductions) = match regime {
+        TaxRegime::Old => {
+            let gross_total_income = sum_paisa([presumptive_income, other_income])?;
+            old_regime_deductions(&input.deductions, input.user_type, gross_total_income, rules)
+        }
+        TaxRegime::New => (0, Vec::new()),
+    };
+    let taxable_professional_income = presumptive_income.saturating_sub(deductions);
+    let taxable_other_income = other_income - deductions.saturating_sub(presumptive_income);
+
+    // Other income and STCG on capital assets are taxed at normal rates alongside professional income
+    let slab_income = sum_paisa([taxable_professional_income, taxable_other_income, short_term_capital_gains])?;
+    // Flat-rate income: VDA gains at 30%, LTCG at the capital gains rate
+    let special_income = sum_paisa([vda_gains, long_term_capital_gains])?;
+    let total_income = sum_paisa([slab_income, special_income])?;
+
+    // Calculate professional income tax based on user type
+    let (professional_tax_before_rebate, section_87a_rebate) = match input.user_type {
+        UserType::Individual | UserType::Huf => {
+            // Slabs work in whole INR
+            let taxable_inr = slab_income / 100;
+            let slab_tax_inr = calculate_slab_tax(taxable_inr, &regime_rules.slabs);
+
+            // Apply Section 87A rebate (zeroed above for HUFs and non-residents): eligibility
+            // goes by total income, but only slab tax is rebated
+            let rebate_inr = section_87a_rebate(total_income / 100, slab_tax_inr, &regime_rules.rebate_87a);
+            (slab_tax_inr * 100, rebate_inr * 100)
+        }
+        UserType::Corporate => {
+            // No rebate for corporates
+            (apply_bps(slab_income, corporate_rate), 0)
+        }
+    };
+
+    // Professional tax after rebate
+    let professional_tax = professional_tax_before_rebate - section_87a_rebate;
+
+    // VDA tax at 30% (only on gains, losses cannot be offset)
+    // Note: VDA tax doesn't get 87A rebate
+    let vda_tax = apply_bps(vda_gains, rules.vda_tax_rate_bps);
+    let ltcg_tax = apply_bps(long_term_capital_gains, ltcg_rate);
+
+    // Surcharge on income tax including VDA and LTCG tax, tiered with marginal relief.
+    // At a threshold, flat-rate income is reduced from LTCG before VDA gains.
+    let surcharge = match input.user_type {
+        UserType::Individual | UserType::Huf => {
+            surcharge_with_relief(slab_income, special_income, &regime_rules.surcharge, |slab_income, special| {
+                let gains = special.min(vda_gains);
+                individual_tax_before_surcharge(slab_income, slab_income + special, gains, regime_rules, rules)
+                    + apply_bps(special - gains, ltcg_rate)
+            })
+        }
+        UserType::Corporate => {
+            surcharge_with_relief(slab_income, special_income, &corporate_rules.surcharge, |income, special| {
+                let gains = special.min(vda_gains);
+                apply_bps(income, corporate_rate)
+                    + apply_bps(gains, rules.vda_tax_rate_bps)
+                    + apply_bps(special - gains, ltcg_rate)
+            })
+        }
+    };
+
+    // Total tax before cess
+    let total_before_cess = sum_paisa([professional_tax, vda_tax, ltcg_tax, surcharge])?;
+
+    // Health & Education Cess
+    let cess = apply_bps(total_before_cess, rules.cess_rate_bps);
+
+    // Total tax payable
+    let total_tax = sum_paisa([total_before_cess, cess])?;
+
+    // TDS is a credit against tax payable, not part of the liability being proved
+    let tds_credit = tds::tds_credit(&input.tds_entries, fy_start, fy_end)?;
+
+    // Analytics: attribute the tax to each kind of income and find the rate on the next rupee
+    let slab_tax_shares = apportion(
+        professional_tax,
+        &[taxable_professional_income, taxable_other_income, short_term_capital_gains],
+    );
+    let category_tax = apportion(
+        total_tax,
+        &[slab_tax_shares[0], slab_tax_shares[1], slab_tax_shares[2], ltcg_tax, vda_tax],
+    );
+    let tax_by_category = [
+        (TaxCategory::ProfessionalIncome, taxable_professional_income),
+        (TaxCategory::OtherIncome, taxable_other_income),
+        (TaxCategory::ShortTermCapitalGains, short_term_capital_gains),
+        (TaxCategory::LongTermCapitalGains, long_term_capital_gains),
+        (TaxCategory::Vda, vda_gains),
+    ]
+    .into_iter()
+    .zip(category_tax)
+    .filter(|((_, income), tax)| *income > 0 || *tax > 0)
+    .map(|((category, income), tax)| CategoryTax {
+        category,
+        income_inr: format_paisa(income),
+        tax_inr: format_paisa(tax),
+    })
+    .collect();
+
+    let effective_tax_rate_bps = if total_income == 0 {
+        0
+    } else {
+        ((total_tax as u128 * 10_000) / total_income as u128) as u64
+    };
+    let (marginal_slab_rate, surcharge_tiers) = match input.user_type {
+        UserType::Individual | UserType::Huf => {
+            let taxable_inr = slab_income / 100;
+            let rate = regime_rules
+                .slabs
+                .iter()
+                .find(|slab| slab.upto.is_none_or(|upto| taxable_inr < upto))
+                .map_or(0, |slab| slab.rate_bps);
+            (rate, &regime_rules.surcharge)
+        }
+        UserType::Corporate => (corporate_rate, &corporate_rules.surcharge),
+    };
+    let marginal_surcharge_rate = surcharge_tier(total_income, surcharge_tiers).map_or(0, |tier| tier.rate_bps);
+    let marginal_tax_rate_bps = marginal_slab_rate * (10_000 + marginal_surcharge_rate) / 10_000
+        * (10_000 + rules.cess_rate_bps)
+        / 10_000;
+
+    let monthly_income = monthly
+        .into_iter()
+        .filter(|(_, totals)| totals.iter().any(|&total| total > 0))
+        .map(|((year, month), [professional, gains, gifts, staking])| MonthlyIncome {
+            month: format!("{year}-{month:02}"),
+            professional_income_inr: format_paisa(professional),
+            vda_gains_inr: format_paisa(gains),
+            gifts_received_inr: format_paisa(gifts),
+            staking_rewards_inr: format_paisa(staking),
+        })
+        .collect();
+
+    let gst = input
+        .gst
+        .as_ref()
+        .map(|settings| gst::estimate_gst(&professional_receipts, settings, &rules.gst));
+
+    let mut warnings = entity_warnings(input);
+    if !clubbed_rows.is_empty() {
+        warnings.push(TaxWarning {
+            kind: WarningKind::ClubbedIncome,
+            message: format!(
+                "₹{} received on assets members transferred to the HUF may be taxable in the members' hands \
+                 (Section 64(2))",
+                format_paisa(clubbed_income)
+            ),
+            tx_hashes: clubbed_rows,
+        });
+    }
+
+    #[cfg(feature = "std")]
+    for price in depegged_stablecoins(&input.prices, StablecoinRegistry::bundled()) {
+        let rows: Vec<String> = input
+            .ledger
+            .iter()
+            .filter(|row| row.asset.eq_ignore_ascii_case(&price.asset) && (fy_start..=fy_end).contains(&row.block_time))
+            .map(|row| row.tx_hash.clone())
+            .collect();
+        if !rows.is_empty() {
+            warnings.push(TaxWarning {
+                kind: WarningKind::StablecoinDepeg,
+                message: format!(
+                    "{} is priced at ${}, off its $1 peg; check the price used",
+                    price.asset, price.usd_price
+                ),
+                tx_hashes: rows,
+            });
+        }
+    }
+
+    if !missing_cost_basis.is_empty() {
+        warnings.push(TaxWarning {
+            kind: WarningKind::MissingCostBasis,
+            message: format!(
+                "{} withdrawal(s) from exchanges have no acquisition lot; add their cost of acquisition so \
+                 later disposals aren't taxed on the full proceeds",
+                missing_cost_basis.len()
+            ),
+            tx_hashes: missing_cost_basis,
+        });
+    }
+
+    // Belated returns: interest and fee are payable on top of the tax, but aren't part of it either
+    let basic_exemption = match input.user_type {
+        UserType::Individual | UserType::Huf => {
+            regime_rules.slabs.first().filter(|s| s.rate_bps == 0).and_then(|s| s.upto)
+        }
+        UserType::Corporate => None,
+    };
+    let late_filing = late_filing_charges(
+        input,
+        rules,
+        total_income,
+        basic_exemption.unwrap_or(0),
+        total_tax.saturating_sub(tds_credit),
+    );
+
+    // This year's unabsorbed capital losses can only be carried forward on a timely return (Section 80)
+    let mut losses_carried_forward = LossCarryForward {
+        losses: brought_forward.remaining,
+    };
+    let current_losses = [
+        (LossHead::ShortTermCapital, short_term_capital_loss),
+        (LossHead::LongTermCapital, long_term_capital_loss),
+    ];
+    if late_filing.months_late == 0 {
+        losses_carried_forward.losses.extend(
+            current_losses
+                .into_iter()
+                .filter(|(_, amount)| *amount > 0)
+                .map(|(head, amount)| LossEntry {
+                    head,
+                    assessment_year: rules.assessment_year,
+                    amount_inr: format_paisa(amount),
+                }),
+        );
+    } else if current_losses.iter().any(|(_, amount)| *amount > 0) {
+        warnings.push(TaxWarning {
+            kind: WarningKind::LossNotCarriedForward,
+            message: "This year's capital losses can't be carried forward on a return filed after the due date"
+                .to_string(),
+            tx_hashes: Vec::new(),
+        });
+    }
+
+    let net_tax_payable = sum_paisa([total_tax, late_filing.interest_234a, late_filing.fee_234f])?;
+
+    Ok(TaxBreakdown {
+        assessment_year: rules.assessment_year,
+        fy_start,
+        fy_end,
+        regime,
+        corporate_regime,
+        residential_status: input.residential_status,
+        excluded_rows,
+        total_income_inr: format_paisa(total_income),
+        professional_income_inr: format_paisa(professional_income),
+        other_income_inr: format_paisa(manual_income),
+        other_sources_income_inr: format_paisa(other_sources_income),
+        staking_income_inr: format_paisa(staking_rewards),
+        deductions_inr: format_paisa(deductions),
+        applied_deductions,
+        presumptive_44ada_applied,
+        presumptive_rejected_reason,
+        taxable_professional_income_inr: format_paisa(taxable_professional_income),
+        vda_gains_inr: format_paisa(vda_gains),
+        vda_cost_of_acquisition_inr: format_paisa(vda_cost_of_acquisition),
+        vda_losses_inr: format_paisa(vda_losses),
+        short_term_capital_gains_inr: format_paisa(short_term_capital_gains),
+        long_term_capital_gains_inr: format_paisa(long_term_capital_gains),
+        professional_tax_inr: format_paisa(professional_tax_before_rebate),
+        section_87a_rebate_inr: format_paisa(section_87a_rebate),
+        vda_tax_inr: format_paisa(vda_tax),
+        ltcg_tax_inr: format_paisa(ltcg_tax),
+        surcharge_inr: format_paisa(surcharge),
+        cess_inr: format_paisa(cess),
+        total_tax_inr: format_paisa(total_tax),
+        total_tax_paisa: total_tax,
+        total_income_paisa: total_income,
+        professional_income_paisa: professional_income,
+        vda_gains_paisa: vda_gains,
+        vda_tax_paisa: vda_tax,
+        cess_paisa: cess,
+        tds_credit_inr: format_paisa(tds_credit),
+        months_late: late_filing.months_late,
+        interest_234a_inr: format_paisa(late_filing.interest_234a),
+        late_fee_234f_inr: format_paisa(late_filing.fee_234f),
+        net_tax_payable_inr: format_paisa(net_tax_payable.saturating_sub(tds_credit)),
+        effective_tax_rate_bps,
+        marginal_tax_rate_bps,
+        tax_by_category,
+        monthly_income,
+        warnings,
+        gst,
+        brought_forward_set_off: brought_forward.set_off,
+        losses_carried_forward,
+    })
+}
+
+/// Options the input selects that don't apply to its entity type
+fn entity_warnings(input: &TaxInput) -> Vec<TaxWarning> {
+    let mut warnings = Vec::new();
+    let claimed = |amount: &str| parse_hundredths(amount).unwrap_or(0) > 0;
+    let deductions = &input.deductions;
+
+    match input.user_type {
+        UserType::Individual => {}
+        UserType::Huf => {
+            if input.use_44ada {
+                warnings.push(TaxWarning::invalid_for_entity(
+                    "44ADA is only available to individuals and partnerships, not a HUF",
+                ));
+            }
+            let individual_deductions = claimed(&deductions.section_80ccd_1b) || claimed(&deductions.section_80e);
+            if input.regime == TaxRegime::Old && individual_deductions {
+                warnings.push(TaxWarning::invalid_for_entity(
+                    "80CCD(1B) and 80E deductions are only available to individuals",
+                ));
+            }
+            if input.manual_income.iter().any(|entry| entry.source == IncomeSource::Salary) {
+                warnings.push(TaxWarning::invalid_for_entity(
+                    "A HUF can't earn salary; salary paid to a member is taxable in the member's hands",
+                ));
+            }
+        }
+        UserType::Corporate => {
+            if input.use_44ada {
+                warnings.push(TaxWarning::invalid_for_entity("44ADA isn't available to companies"));
+            }
+            if input.regime == TaxRegime::Old {
+                warnings.push(TaxWarning::invalid_for_entity(
+                    "The old regime only applies to individuals and HUFs; the corporate regime was used instead",
+                ));
+            }
+            if [
+                &deductions.section_80c,
+                &deductions.section_80d,
+                &deductions.section_80ccd_1b,
+                &deductions.section_80e,
+                &deductions.section_80g,
+                &deductions.section_80tta,
+            ]
+            .into_iter()
+            .any(|amount| claimed(amount))
+            {
+                warnings.push(TaxWarning::invalid_for_entity(
+                    "Chapter VI-A deductions were ignored; they aren't supported for companies",
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Split `total` in proportion to `weights`; the rounding remainder goes to the (last) largest weight
+fn apportion<const N: usize>(total: u64, weights: &[u64; N]) -> [u64; N] {
+    let sum: u128 = weights.iter().map(|&w| w as u128).sum();
+    if sum == 0 {
+        return [0; N];
+    }
+    let mut shares = weights.map(|w| ((total as u128 * w as u128) / sum) as u64);
+    let remainder = total - shares.iter().sum::<u64>();
+    if let Some(largest) = (0..N).max_by_key(|&i| weights[i]) {
+        shares[largest] += remainder;
+    }
+    shares
+}
+
+/// Charges for filing after the Section 139(1) due date, in paisa
+struct LateFilingCharges {
+    months_late: u64,
+    interest_234a: u64,
+    fee_234f: u64,
+}
+
+/// 234A interest and 234F fee for the input's filing date
+///
+/// Interest runs for each month or part of a month after the due date, on the unpaid
+/// tax rounded down to a multiple of ₹100 (Rule 119A). No fee is due when total income
+/// is within the basic exemption limit.
+fn late_filing_charges(
+    input: &TaxInput,
+    rules: &TaxRules,
+    total_income: u64,
+    basic_exemption: u64,
+    unpaid_tax: u64,
+) -> LateFilingCharges {
+    let late_filing = &rules.late_filing;
+    let (due_year, due_month, due_day) = late_filing.due_date(input.user_type);
+    let months_late = input.filing_date.map_or(0, |filing_date| {
+        let (year, month, day) = rules::ist_date(filing_date);
+        let months = (year * 12 + month) as i64 - (due_year * 12 + due_month) as i64 + i64::from(day > due_day);
+        months.max(0) as u64
+    });
+
+    if months_late == 0 {
+        return LateFilingCharges {
+            months_late,
+            interest_234a: 0,
+            fee_234f: 0,
+        };
+    }
+
+    let interest_base = unpaid_tax / 10_000 * 10_000;
+    let fee_234f = if total_income <= basic_exemption * 100 {
+        0
+    } else if total_income <= late_filing.fee_234f_reduced_income_limit * 100 {
+        late_filing.fee_234f_reduced * 100
+    } else {
+        late_filing.fee_234f * 100
+    };
+
+    LateFilingCharges {
+        months_late,
+        interest_234a: apply_bps(interest_base, late_filing.interest_234a_rate_bps.saturating_mul(months_late)),
+        fee_234f,
+    }
+}
+
+/// The input of each wallet group, in order of first appearance in the ledger
+///
+/// Ledger rows are assigned to groups through `Wallet.group_id` of their owner wallet.
+/// Manual income, deductions, acquisition lots and brought-forward losses aren't tied to
+/// a wallet, so no group's input has them.
+pub fn split_by_group(input: &TaxInput) -> Vec<(Option<String>, TaxInput)> {
+    let group_of = |owner: &str| {
+        input
+            .wallets
+            .iter()
+            .find(|wallet| wallet.address == *owner)
+            .and_then(|wallet| wallet.group_id.clone())
+    };
+
+    let mut group_ids: Vec<Option<String>> = Vec::new();
+    for row in &input.ledger {
+        let group_id = group_of(&row.owner_wallet);
+        if !group_ids.contains(&group_id) {
+            group_ids.push(group_id);
+        }
+    }
+
+    group_ids
+        .into_iter()
+        .map(|group_id| {
+            let group_input = TaxInput {
+                ledger: input
+                    .ledger
+                    .iter()
+                    .filter(|row| group_of(&row.owner_wallet) == group_id)
+                    .cloned()
+                    .collect(),
+                deductions: Deductions::default(),
+                acquisition_lots: Vec::new(),
+                manual_income: Vec::new(),
+                brought_forward_losses: LossCarryForward::default(),
+                ..input.clone()
+            };
+            (group_id, group_input)
+        })
+        .collect()
+}
+
+/// Calculate tax separately for each wallet group, plus the consolidated tax
+///
+/// Groups are split as `split_by_group` does, so manual income, deductions and
+/// acquisition lots only count towards the consolidated breakdown.
+pub fn calculate_tax_by_group(input: &TaxInput) -> Result<GroupedTaxBreakdown, TaxError> {
+    let rules = TaxRules::for_assessment_year(input.assessment_year)?;
+    let groups = split_by_group(input)
+        .into_iter()
+        .map(|(group_id, group_input)| {
+            Ok(GroupTaxBreakdown {
+                breakdown: calculate_tax_with_rules(&group_input, &rules)?,
+                group_id,
+            })
+        })
+        .collect::<Result<_, TaxError>>()?;
+
+    Ok(GroupedTaxBreakdown {
+        groups,
+        consolidated: calculate_tax_with_rules(input, &rules)?,
+    })
+}
+
+/// Each financial year's input with its breakdown, and the years with no rule table
+pub type YearSplit = (Vec<(TaxInput, TaxBreakdown)>, Vec<u16>);
+
+/// The input of each financial year the ledger spans, with its breakdown, and the years
+/// left out for having no rule table
+///
+/// Each year is computed on its own, with losses carried forward and acquisition lots
+/// left unsold passed on to the next year. Deductions, manual income and filing dates
+/// come from `year_settings`; the input's own values for those are ignored.
+pub fn split_by_year(
+    input: &TaxInput,
+    year_settings: &[YearSettings],
+) -> Result<YearSplit, TaxError> {
+    let mut years: Vec<u16> = input.ledger.iter().map(|row| assessment_year_of(row.block_time)).collect();
+    years.extend(year_settings.iter().map(|settings| settings.assessment_year));
+    years.sort_unstable();
+    years.dedup();
+
+    let mut year_inputs = Vec::new();
+    let mut unsupported_assessment_years = Vec::new();
+    let mut brought_forward_losses = input.brought_forward_losses.clone();
+    let mut acquisition_lots = input.acquisition_lots.clone();
+
+    for assessment_year in years {
+        let Ok(rules) = TaxRules::for_assessment_year(assessment_year) else {
+            unsupported_assessment_years.push(assessment_year);
+            continue;
+        };
+        let settings = year_settings.iter().find(|settings| settings.assessment_year == assessment_year);
+        let ledger: Vec<LedgerRow> = input
+            .ledger
+            .iter()
+            .filter(|row| assessment_year_of(row.block_time) == assessment_year)
+            .cloned()
+            .collect();
+
+        let year_input = TaxInput {
+            assessment_year,
+            deductions: settings.map(|s| s.deductions.clone()).unwrap_or_default(),
+            manual_income: settings.map(|s| s.manual_income.clone()).unwrap_or_default(),
+            filing_date: settings.and_then(|s| s.filing_date),
+            acquisition_lots: acquisition_lots.clone(),
+            brought_forward_losses,
+            ledger,
+            ..input.clone()
+        };
+        let breakdown = calculate_tax_with_rules(&year_input, &rules)?;
+
+        acquisition_lots = unsold_lots(&year_input, &rules);
+        brought_forward_losses = breakdown.losses_carried_forward.clone();
+        year_inputs.push((year_input, breakdown));
+    }
+
+    Ok((year_inputs, unsupported_assessment_years))
+}
+
+/// Calculate tax for every financial year the ledger spans
+///
+/// The ledger is split by financial year as `split_by_year` does.
+pub fn calculate_tax_multi_year(
+    input: &TaxInput,
+    year_settings: &[YearSettings],
+) -> Result<MultiYearTaxBreakdown, TaxError> {
+    let (year_inputs, unsupported_assessment_years) = split_by_year(input, year_settings)?;
+    let breakdowns: Vec<TaxBreakdown> = year_inputs.into_iter().map(|(_, breakdown)| breakdown).collect();
+
+    let mut previous_tax = None;
+    let summary = breakdowns
+        .iter()
+        .map(|breakdown| {
+            let tax_change_paisa =
+                previous_tax.map_or(0, |previous: u64| breakdown.total_tax_paisa as i64 - previous as i64);
+            previous_tax = Some(breakdown.total_tax_paisa);
+            YearSummary {
+                assessment_year: breakdown.assessment_year,
+                total_income_inr: breakdown.total_income_inr.clone(),
+                total_tax_inr: breakdown.total_tax_inr.clone(),
+                effective_tax_rate_bps: breakdown.effective_tax_rate_bps,
+                tax_change_paisa,
+            }
+        })
+        .collect();
+
+    Ok(MultiYearTaxBreakdown {
+        total_tax_inr: format_paisa(sum_paisa(breakdowns.iter().map(|breakdown| breakdown.total_tax_paisa))?),
+        years: breakdowns,
+        summary,
+        unsupported_assessment_years,
+    })
+}
+
+/// Lots (or parts of lots), including this year's receipts, left after this year's
+/// disposals are matched against them
+fn unsold_lots(input: &TaxInput, rules: &TaxRules) -> Vec<AcquisitionLot> {
+    let (fy_start, fy_end) = financial_year_bounds(rules.assessment_year);
+    let usd_inr_rate = UsdInrRates::new(&input.usd_inr_rate, &input.reference_rates);
+    let lots = valuation::lots_with_receipts(&input.acquisition_lots, &input.ledger, &input.prices, &usd_inr_rate);
+    let mut open = open_lots(&lots);
+
+    for row in input.ledger.iter().filter(|row| (fy_start..=fy_end).contains(&row.block_time)) {
+        if matches!(row.category, Category::Gains | Category::CapitalGains) && row.direction == Direction::In {
+            let quantity = parse_quantity(&row.amount).unwrap_or(0);
+            match_acquisition_cost(&mut open, &row.asset, quantity, row.block_time, None);
+        }
+    }
+
+    open.into_iter()
+        .filter(|lot| lot.quantity > 0)
+        .map(|lot| AcquisitionLot {
+            asset: lot.asset.to_string(),
+            amount: format_quantity(lot.quantity),
+            cost_inr: format_paisa(lot.cost),
+            acquired_at: lot.acquired_at,
+        })
+        .collect()
+}
+
+/// Calculate tax under both regimes and recommend the cheaper one
+pub fn compare_regimes(input: &TaxInput) -> Result<RegimeComparison, TaxError> {
+    let rules = TaxRules::for_assessment_year(input.assessment_year)?;
+    let mut scenario = input.clone();
+
+    scenario.regime = TaxRegime::New;
+    let new_regime = calculate_tax_with_rules(&scenario, &rules)?;
+
+    scenario.regime = TaxRegime::Old;
+    let old_regime = calculate_tax_with_rules(&scenario, &rules)?;
+
+    let recommended = if old_regime.total_tax_paisa < new_regime.total_tax_paisa {
+        TaxRegime::Old
+    } else {
+        TaxRegime::New
+    };
+
+    Ok(RegimeComparison {
+        new_regime,
+        old_regime,
+        recommended,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ledger_row;
+
+    #[test]
+    fn test_user_type_serialization() {
+        let ut = UserType::Individual;
+        let json = serde_json::to_string(&ut).unwrap();
+        assert_eq!(json, "\"individual\"");
+    }
+
+    #[test]
+    fn test_internal_categorization() {
+        let row = LedgerRow {
+            chain_id: 11155111,
+            tx_hash: "0x123".to_string(),
+            block_time: 1234567890,
+            amount: "1.0".to_string(),
+            counterparty: Some("0xdef".into()),
+            confidence: 0.0,
+            ..ledger_row()
+        };
+
+        let wallets = vec!["0xabc".to_string(), "0xdef".to_string()];
+        let result = categorize_transaction(&row, &wallets, &ContractRegistry::default());
+
+        assert_eq!(result.category, Category::Internal);
+        assert_eq!(result.confidence, 1.0);
+    }
+
+    #[test]
     fn test_small_eth_outflow_is_fee() {
         let row = LedgerRow {
             chain_id: 11155111,
-            owner_wallet: "0xabc".to_string(),
             tx_hash: "0x123".to_string(),
             block_time: 1234567890,
-            asset: "ETH".to_string(),
             amount: "0.005".to_string(),
-            decimals: 18,
             direction: Direction::Out,
-            counterparty: Some("0xcontract".to_string()),
-            category: Category::Unknown,
+            counterparty: Some("0xcontract".into()),
             confidence: 0.0,
-            user_override: false,
+            ..ledger_row()
         };
 
         let wallets = vec!["0xabc".to_string()];
-        let result = categorize_transaction(&row, &wallets);
+        let result = categorize_transaction(&row, &wallets, &ContractRegistry::default());
 
         assert_eq!(result.category, Category::Fees);
     }
+
+    fn income_input(amount: &str) -> TaxInput {
+        TaxInput {
+            user_type: UserType::Individual,
+            wallets: vec![],
+            ledger: vec![LedgerRow {
+                chain_id: 11155111,
+                tx_hash: "0x123".to_string(),
+                block_time: 1750000000, // June 2025, FY 2025-26
+                asset: "INR".to_string(),
+                amount: amount.to_string(),
+                decimals: 2,
+                counterparty: Some("0xclient".into()),
+                category: Category::Income,
+                user_override: true,
+                ..ledger_row()
+            }],
+            prices: vec![],
+            usd_inr_rate: "1".to_string(),
+            use_44ada: false,
+            digital_receipts_95pct: true,
+            regime: TaxRegime::New,
+            deductions: Deductions::default(),
+            assessment_year: DEFAULT_ASSESSMENT_YEAR,
+            acquisition_lots: vec![],
+            corporate_regime: CorporateRegime::default(),
+            capital_assets: vec![],
+            manual_income: vec![],
+            tds_entries: vec![],
+            residential_status: ResidentialStatus::default(),
+            foreign_source_tx_hashes: vec![],
+            filing_date: None,
+            member_transferred_wallets: vec![],
+            gst: None,
+            brought_forward_losses: LossCarryForward::default(),
+            reference_rates: vec![],
+            nonce: [0; 32],
+        }
+    }
+
+    #[test]
+    fn test_old_regime_applies_capped_deductions() {
+        let mut input = income_input("1000000");
+        input.regime = TaxRegime::Old;
+        input.deductions = Deductions {
+            section_80c: "200000".to_string(),
+            section_80d: "25000".to_string(),
+            ..Deductions::default()
+        };
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.regime, TaxRegime::Old);
+        assert_eq!(breakdown.deductions_inr, "175000.00");
+        assert_eq!(breakdown.taxable_professional_income_inr, "825000.00");
+        assert_eq!(breakdown.professional_tax_inr, "77500.00");
+    }
+
+    #[test]
+    fn test_old_regime_slab_boundaries() {
+        // 5% on ₹2.5L-5L, 20% on ₹5L-10L, 30% above: each boundary taxed at the lower rate
+        for (income, tax) in [
+            ("250000", "0.00"),
+            ("500000", "12500.00"),
+            ("500010", "12502.00"),
+            ("1000000", "112500.00"),
+            ("1000010", "112503.00"),
+        ] {
+            let mut input = income_input(income);
+            input.regime = TaxRegime::Old;
+            let breakdown = calculate_tax(&input).unwrap();
+            assert_eq!(breakdown.professional_tax_inr, tax, "taxable income ₹{}", income);
+        }
+    }
+
+    #[test]
+    fn test_old_regime_itemizes_deductions() {
+        let mut input = income_input("1000000");
+        input.regime = TaxRegime::Old;
+        input.deductions = Deductions {
+            section_80c: "150000".to_string(),
+            section_80ccd_1b: "60000".to_string(),
+            section_80e: "40000".to_string(),
+            section_80g: "100000".to_string(),
+            ..Deductions::default()
+        };
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // 80G limited to 10% of (₹10L - ₹2.4L of other deductions)
+        let allowed: Vec<(&str, &str)> = breakdown
+            .applied_deductions
+            .iter()
+            .map(|d| (d.section.as_str(), d.allowed_inr.as_str()))
+            .collect();
+        assert_eq!(
+            allowed,
+            [("80C", "150000.00"), ("80CCD(1B)", "50000.00"), ("80E", "40000.00"), ("80G", "76000.00")]
+        );
+        assert_eq!(breakdown.deductions_inr, "316000.00");
+    }
+
+    #[test]
+    fn test_new_regime_ignores_deductions() {
+        let mut input = income_input("1000000");
+        input.deductions.section_80c = "150000".to_string();
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.deductions_inr, "0.00");
+        assert_eq!(breakdown.total_tax_inr, "0.00");
+    }
+
+    #[test]
+    fn test_old_regime_87a_rebate() {
+        let mut input = income_input("500000");
+        input.regime = TaxRegime::Old;
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.section_87a_rebate_inr, breakdown.professional_tax_inr);
+        assert_eq!(breakdown.total_tax_inr, "0.00");
+    }
+
+    #[test]
+    fn test_compare_regimes_recommends_cheaper() {
+        let comparison = compare_regimes(&income_input("1000000")).unwrap();
+
+        assert_eq!(comparison.new_regime.regime, TaxRegime::New);
+        assert_eq!(comparison.old_regime.regime, TaxRegime::Old);
+        assert_eq!(comparison.recommended, TaxRegime::New);
+    }
+
+    #[test]
+    fn test_87a_full_rebate_at_threshold() {
+        let breakdown = calculate_tax(&income_input("1200000")).unwrap();
+
+        assert_eq!(breakdown.professional_tax_inr, "60000.00");
+        assert_eq!(breakdown.section_87a_rebate_inr, "60000.00");
+        assert_eq!(breakdown.total_tax_inr, "0.00");
+    }
+
+    #[test]
+    fn test_87a_marginal_relief_above_threshold() {
+        let breakdown = calculate_tax(&income_input("1210000")).unwrap();
+
+        // Slab tax of ₹61,500 is capped at the ₹10,000 earned above ₹12L
+        assert_eq!(breakdown.professional_tax_inr, "61500.00");
+        assert_eq!(breakdown.section_87a_rebate_inr, "51500.00");
+        assert_eq!(breakdown.total_tax_inr, "10400.00");
+    }
+
+    #[test]
+    fn test_87a_marginal_relief_phases_out() {
+        let breakdown = calculate_tax(&income_input("1300000")).unwrap();
+
+        assert_eq!(breakdown.section_87a_rebate_inr, "0.00");
+    }
+
+    fn with_vda_gains(mut input: TaxInput, gains: &str) -> TaxInput {
+        input.ledger.push(disposal("1"));
+        input.prices = vec![PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: gains.to_string(),
+            date: None,
+            user_override: false,
+        }];
+        input
+    }
+
+    #[test]
+    fn test_87a_limit_counts_special_rate_income() {
+        // ₹11L of slab income and ₹1L of VDA gains: ₹12L in total, within the limit
+        let breakdown = calculate_tax(&with_vda_gains(income_input("1100000"), "100000")).unwrap();
+        assert_eq!(breakdown.professional_tax_inr, "50000.00");
+        assert_eq!(breakdown.section_87a_rebate_inr, "50000.00");
+
+        // ₹5L of VDA gains push the total to ₹16L, past the limit and the marginal relief band
+        let breakdown = calculate_tax(&with_vda_gains(income_input("1100000"), "500000")).unwrap();
+        assert_eq!(breakdown.vda_gains_inr, "500000.00");
+        assert_eq!(breakdown.section_87a_rebate_inr, "0.00");
+    }
+
+    #[test]
+    fn test_87a_marginal_relief_counts_special_rate_income() {
+        // ₹11.9L of slab income and ₹20K of VDA gains: slab tax of ₹59,000 is capped at the
+        // ₹10,000 of total income above ₹12L, and the VDA tax is due in full
+        let breakdown = calculate_tax(&with_vda_gains(income_input("1190000"), "20000")).unwrap();
+
+        assert_eq!(breakdown.professional_tax_inr, "59000.00");
+        assert_eq!(breakdown.section_87a_rebate_inr, "49000.00");
+        assert_eq!(breakdown.vda_tax_inr, "6000.00");
+        // (₹10,000 + ₹6,000) plus 4% cess
+        assert_eq!(breakdown.total_tax_inr, "16640.00");
+    }
+
+    #[test]
+    fn test_surcharge_marginal_relief_above_50l() {
+        let breakdown = calculate_tax(&income_input("5100000")).unwrap();
+
+        // 10% surcharge (₹1,11,000) is cut down to the ₹1L earned above ₹50L
+        // less the ₹30,000 of extra slab tax on it
+        assert_eq!(breakdown.professional_tax_inr, "1110000.00");
+        assert_eq!(breakdown.surcharge_inr, "70000.00");
+    }
+
+    #[test]
+    fn test_surcharge_without_relief() {
+        let breakdown = calculate_tax(&income_input("6000000")).unwrap();
+
+        assert_eq!(breakdown.surcharge_inr, "138000.00");
+    }
+
+    #[test]
+    fn test_new_regime_surcharge_capped_at_25_percent() {
+        let rules = TaxRules::for_assessment_year(DEFAULT_ASSESSMENT_YEAR).unwrap();
+        let income = 60_000_000 * 100;
+
+        let new_tier = surcharge_tier(income, &rules.new_regime.surcharge).unwrap();
+        let old_tier = surcharge_tier(income, &rules.old_regime.surcharge).unwrap();
+        assert_eq!(new_tier.rate_bps, 2500);
+        assert_eq!(old_tier.rate_bps, 3700);
+        assert!(surcharge_tier(5_000_000 * 100, &rules.new_regime.surcharge).is_none());
+    }
+
+    #[test]
+    fn test_prior_assessment_year_slabs() {
+        let mut input = income_input("1000000");
+        input.assessment_year = 2025;
+        input.ledger[0].block_time = 1720000000; // July 2024, FY 2024-25
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // AY 2025-26: 5% on 3L-7L, 10% on 7L-10L, and no 87A rebate above ₹7L
+        // (marginal relief is exhausted well before ₹10L)
+        assert_eq!(breakdown.assessment_year, 2025);
+        assert_eq!(breakdown.professional_tax_inr, "50000.00");
+        assert_eq!(breakdown.section_87a_rebate_inr, "0.00");
+    }
+
+    #[test]
+    fn test_rows_outside_financial_year_ignored() {
+        let mut input = income_input("1000000");
+        let mut prior_year = input.ledger[0].clone();
+        prior_year.block_time = 1720000000; // FY 2024-25
+        input.ledger.push(prior_year);
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.professional_income_inr, "1000000.00");
+        assert_eq!((breakdown.fy_start, breakdown.fy_end), financial_year_bounds(2026));
+    }
+
+    #[test]
+    fn test_unsupported_assessment_year() {
+        let mut input = income_input("1000000");
+        input.assessment_year = 2019;
+
+        assert!(matches!(calculate_tax(&input), Err(TaxError::UnsupportedAssessmentYear(2019))));
+    }
+
+    #[test]
+    fn test_income_beyond_u64_paisa_overflows() {
+        // ₹10^17 is 10^19 paisa, just under u64::MAX; twice that isn't
+        let mut input = income_input("100000000000000000");
+        assert!(calculate_tax(&input).is_ok());
+
+        input.ledger.push(input.ledger[0].clone());
+        assert!(matches!(calculate_tax(&input), Err(TaxError::Overflow)));
+        assert!(matches!(compare_regimes(&input), Err(TaxError::Overflow)));
+    }
+
+    #[test]
+    fn test_44ada_within_digital_cap() {
+        let mut input = income_input("7000000");
+        input.use_44ada = true;
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert!(breakdown.presumptive_44ada_applied);
+        assert_eq!(breakdown.presumptive_rejected_reason, None);
+        assert_eq!(breakdown.taxable_professional_income_inr, "3500000.00");
+    }
+
+    #[test]
+    fn test_44ada_rejected_above_non_digital_cap() {
+        let mut input = income_input("7000000");
+        input.use_44ada = true;
+        input.digital_receipts_95pct = false;
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // Receipts above ₹50L fall back to full taxation
+        assert!(!breakdown.presumptive_44ada_applied);
+        assert!(breakdown.presumptive_rejected_reason.unwrap().contains("5000000"));
+        assert_eq!(breakdown.taxable_professional_income_inr, "7000000.00");
+    }
+
+    #[test]
+    fn test_44ada_rejected_above_digital_cap() {
+        let mut input = income_input("7600000");
+        input.use_44ada = true;
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert!(!breakdown.presumptive_44ada_applied);
+    }
+
+    fn disposal(amount: &str) -> LedgerRow {
+        LedgerRow {
+            asset: "ETH".to_string(),
+            category: Category::Gains,
+            ..income_input(amount).ledger.remove(0)
+        }
+    }
+
+    fn lot(amount: &str, cost_inr: &str) -> AcquisitionLot {
+        AcquisitionLot {
+            asset: "ETH".to_string(),
+            amount: amount.to_string(),
+            cost_inr: cost_inr.to_string(),
+            acquired_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_vda_gains_net_of_fifo_cost() {
+        let mut input = income_input("0");
+        input.prices = vec![PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: "2000".to_string(),
+            date: None,
+            user_override: false,
+        }];
+        input.ledger = vec![disposal("1.5"), disposal("0.5")];
+        input.acquisition_lots = vec![lot("1", "1000"), lot("1", "3000")];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // First disposal: 3000 - (1000 + 1500) = 500 gain
+        // Second disposal: 1000 - 1500 = 500 loss, not offset
+        assert_eq!(breakdown.vda_cost_of_acquisition_inr, "4000.00");
+        assert_eq!(breakdown.vda_gains_inr, "500.00");
+        assert_eq!(breakdown.vda_losses_inr, "500.00");
+    }
+
+    #[test]
+    fn test_fixed_point_quantities_keep_full_precision() {
+        assert_eq!(parse_hundredths("1234.56"), Some(123_456));
+        assert_eq!(parse_quantity("0.005"), Some(5_000_000_000_000_000));
+        assert_eq!(parse_quantity("1e5"), None);
+        assert_eq!(format_quantity(1_500_000_000_000_000_000), "1.5");
+
+        let prices = [("ETH", "2000.12345678"), ("SHIB", "0.00001234")].map(|(asset, usd_price)| PriceEntry {
+            asset: asset.to_string(),
+            usd_price: usd_price.to_string(),
+            date: None,
+            user_override: false,
+        });
+        let rates = UsdInrRates::flat("83.50");
+        // 0.005 ETH at $2000.12345678 and ₹83.50 is ₹835.05...
+        assert_eq!(amount_to_inr_paisa("0.005", "ETH", 0, &prices, &rates), 83_505);
+        // and a token worth a fraction of a cent still has a value
+        assert_eq!(amount_to_inr_paisa("1000000", "SHIB", 0, &prices, &rates), 103_039);
+    }
+
+    #[test]
+    fn test_ledger_commitment_covers_acquisition_lots() {
+        let mut input = income_input("1000");
+        assert_eq!(compute_ledger_commitment(&input), ledger_commitment(&input.ledger));
+
+        // Acquisition lots change the tax, so they change the commitment
+        input.acquisition_lots = vec![lot("1", "1000")];
+        let mut expected = encode_ledger(&input.ledger);
+        expected.push(1);
+        expected.extend_from_slice(&[1, 0, 0, 0]);
+        for field in ["ETH", "1", "1000"] {
+            expected.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            expected.extend_from_slice(field.as_bytes());
+        }
+        expected.extend_from_slice(&input.acquisition_lots[0].acquired_at.to_le_bytes());
+        assert_eq!(ledger_commitment_bytes(&input), expected);
+        assert_ne!(compute_ledger_commitment(&input), ledger_commitment(&input.ledger));
+    }
+
+    #[test]
+    fn test_ledger_commitment_covers_tax_settings() {
+        let base = income_input("1000");
+        let settings: [fn(&mut TaxInput); 8] = [
+            |input| input.regime = TaxRegime::Old,
+            |input| input.deductions.section_80c = "150000".to_string(),
+            |input| input.residential_status = ResidentialStatus::NonResident,
+            |input| input.capital_assets = vec!["ETH".to_string()],
+            |input| input.corporate_regime = CorporateRegime::Normal,
+            |input| input.digital_receipts_95pct = false,
+            |input| input.member_transferred_wallets = vec!["0xabc".to_string()],
+            |input| input.filing_date = Some(1_767_225_600),
+        ];
+
+        for (i, change) in settings.into_iter().enumerate() {
+            let mut input = base.clone();
+            change(&mut input);
+            let bytes = ledger_commitment_bytes(&input);
+            let ledger_len = encode_ledger(&input.ledger).len();
+            // Tagged 5 onwards, after the ledger
+            assert_eq!(bytes[ledger_len], 5 + i as u8);
+            assert_ne!(compute_ledger_commitment(&input), compute_ledger_commitment(&base));
+        }
+
+        let mut input = base.clone();
+        input.filing_date = Some(1_767_225_600);
+        let mut expected = encode_ledger(&input.ledger);
+        expected.push(12);
+        expected.extend_from_slice(&1_767_225_600u64.to_le_bytes());
+        assert_eq!(ledger_commitment_bytes(&input), expected);
+    }
+
+    #[test]
+    fn test_canonical_price_table_ignores_order_and_source() {
+        let price = |asset: &str, date: Option<&str>, user_override: bool| PriceEntry {
+            asset: asset.to_string(),
+            usd_price: "2000".to_string(),
+            date: date.map(str::to_string),
+            user_override,
+        };
+        let fetched = [price("ETH", Some("2025-06-16"), false), price("BTC", None, false), price("ETH", None, false)];
+        let edited = [price("ETH", None, false), price("ETH", Some("2025-06-16"), true), price("BTC", None, false)];
+
+        assert_eq!(canonical_price_table(&fetched), canonical_price_table(&edited));
+        assert_eq!(
+            canonical_price_table(&fetched),
+            r#"[["BTC",null,"2000"],["ETH",null,"2000"],["ETH","2025-06-16","2000"]]"#
+        );
+    }
+
+    #[test]
+    fn test_rows_priced_on_their_day() {
+        let mut input = income_input("0");
+        let price = |date: Option<&str>, usd_price: &str| PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: usd_price.to_string(),
+            date: date.map(str::to_string),
+            user_override: false,
+        };
+        input.prices = vec![price(Some("2025-06-15"), "2000"), price(Some("2025-06-16"), "3000"), price(None, "1000")];
+        let mut next_day = disposal("1");
+        next_day.block_time += 86_400;
+        let mut undated_day = disposal("1");
+        undated_day.block_time += 2 * 86_400;
+        input.ledger = vec![disposal("1"), next_day, undated_day];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // $2000 on the 15th, $3000 on the 16th and the undated $1000 on the 17th
+        assert_eq!(breakdown.vda_gains_inr, "6000.00");
+    }
+
+    #[test]
+    fn test_disposal_of_airdrop_taxed_on_gain_over_fmv() {
+        let mut input = income_input("0");
+        let price = |date: &str, usd_price: &str| PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: usd_price.to_string(),
+            date: Some(date.to_string()),
+            user_override: false,
+        };
+        input.prices = vec![price("2025-06-15", "2000"), price("2025-06-16", "2500")];
+        let airdrop = LedgerRow {
+            category: Category::Airdrop,
+            ..disposal("1")
+        };
+        let mut sale = disposal("1");
+        sale.block_time += 86_400;
+        input.ledger = vec![sale, airdrop];
+        valuation::value_ledger(&mut input.ledger, &input.prices, &input.usd_inr_rate, &input.reference_rates);
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // Costed at its FMV on receipt, so only the rise to the sale value is a gain
+        assert_eq!(breakdown.vda_cost_of_acquisition_inr, "2000.00");
+        assert_eq!(breakdown.vda_gains_inr, "500.00");
+    }
+
+    #[test]
+    fn test_vda_disposal_without_lot_taxed_gross() {
+        let mut input = income_input("0");
+        input.ledger = vec![disposal("100")];
+        input.acquisition_lots = vec![AcquisitionLot {
+            asset: "BTC".to_string(),
+            ..lot("1", "50")
+        }];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.vda_cost_of_acquisition_inr, "0.00");
+        assert_eq!(breakdown.vda_gains_inr, "100.00");
+    }
+
+    fn corporate_input(amount: &str, corporate_regime: CorporateRegime) -> TaxInput {
+        TaxInput {
+            user_type: UserType::Corporate,
+            corporate_regime,
+            ..income_input(amount)
+        }
+    }
+
+    #[test]
+    fn test_corporate_115baa_flat_surcharge() {
+        let breakdown = calculate_tax(&corporate_input("5000000", CorporateRegime::Section115baa)).unwrap();
+
+        // 22% of ₹50L, 10% surcharge regardless of income
+        assert_eq!(breakdown.corporate_regime, Some(CorporateRegime::Section115baa));
+        assert_eq!(breakdown.professional_tax_inr, "1100000.00");
+        assert_eq!(breakdown.surcharge_inr, "110000.00");
+    }
+
+    #[test]
+    fn test_corporate_normal_no_surcharge_below_1cr() {
+        let breakdown = calculate_tax(&corporate_input("10000000", CorporateRegime::Normal)).unwrap();
+
+        assert_eq!(breakdown.professional_tax_inr, "2500000.00");
+        assert_eq!(breakdown.surcharge_inr, "0.00");
+    }
+
+    #[test]
+    fn test_corporate_normal_surcharge_marginal_relief() {
+        let breakdown = calculate_tax(&corporate_input("10010000", CorporateRegime::Normal)).unwrap();
+
+        // 7% would be ₹1,75,175; relief caps tax + surcharge at ₹25L + the ₹10,000 above ₹1Cr
+        assert_eq!(breakdown.professional_tax_inr, "2502500.00");
+        assert_eq!(breakdown.surcharge_inr, "7500.00");
+    }
+
+    #[test]
+    fn test_corporate_normal_surcharge_above_10cr() {
+        let breakdown = calculate_tax(&corporate_input("200000000", CorporateRegime::Normal)).unwrap();
+
+        // 25% of ₹20Cr with 12% surcharge
+        assert_eq!(breakdown.professional_tax_inr, "50000000.00");
+        assert_eq!(breakdown.surcharge_inr, "6000000.00");
+    }
+
+    fn capital_asset_input(price: &str, disposed: &str, lots: Vec<AcquisitionLot>) -> TaxInput {
+        let mut input = income_input("0");
+        input.prices = vec![PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: price.to_string(),
+            date: None,
+            user_override: false,
+        }];
+        input.ledger = vec![disposal(disposed)];
+        input.acquisition_lots = lots;
+        input.capital_assets = vec!["ETH".to_string()];
+        input
+    }
+
+    #[test]
+    fn test_capital_asset_split_by_holding_period() {
+        let input = capital_asset_input(
+            "2000",
+            "2",
+            vec![
+                AcquisitionLot { acquired_at: 1650000000, ..lot("1", "1000") }, // June 2022: long-term
+                AcquisitionLot { acquired_at: 1740000000, ..lot("1", "1500") }, // Feb 2025: short-term
+            ],
+        );
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.vda_gains_inr, "0.00");
+        assert_eq!(breakdown.long_term_capital_gains_inr, "1000.00");
+        assert_eq!(breakdown.short_term_capital_gains_inr, "500.00");
+        assert_eq!(breakdown.ltcg_tax_inr, "125.00");
+    }
+
+    #[test]
+    fn test_short_term_loss_sets_off_long_term_gain() {
+        let input = capital_asset_input(
+            "2000",
+            "2",
+            vec![
+                AcquisitionLot { acquired_at: 1650000000, ..lot("1", "1000") },
+                AcquisitionLot { acquired_at: 1740000000, ..lot("1", "3000") },
+            ],
+        );
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.long_term_capital_gains_inr, "0.00");
+        assert_eq!(breakdown.short_term_capital_gains_inr, "0.00");
+    }
+
+    #[test]
+    fn test_long_term_cost_indexed_before_ay_2025() {
+        let mut input = capital_asset_input(
+            "1000",
+            "1",
+            vec![AcquisitionLot { acquired_at: 1500000000, ..lot("1", "272") }], // FY 2017-18, CII 272
+        );
+        input.assessment_year = 2024;
+        input.ledger[0].block_time = 1700000000; // FY 2023-24, CII 348
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // Indexed cost 272 * 348 / 272 = 348; LTCG taxed at 20%
+        assert_eq!(breakdown.long_term_capital_gains_inr, "652.00");
+        assert_eq!(breakdown.ltcg_tax_inr, "130.40");
+    }
+
+    fn manual(source: IncomeSource, amount_inr: &str) -> ManualIncomeEntry {
+        ManualIncomeEntry {
+            source,
+            amount_inr: amount_inr.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_manual_income_net_of_standard_deductions() {
+        let mut input = income_input("0");
+        input.manual_income = vec![
+            manual(IncomeSource::Salary, "1000000"),
+            manual(IncomeSource::Rent, "100000"),
+            manual(IncomeSource::Interest, "5000"),
+        ];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // ₹10L - ₹75,000 standard deduction + 70% of rent + interest
+        assert_eq!(breakdown.other_income_inr, "1000000.00");
+    }
+
+    #[test]
+    fn test_manual_income_pushes_crypto_income_into_higher_slab() {
+        let mut input = income_input("500000");
+        input.manual_income = vec![manual(IncomeSource::Interest, "1500000")];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // ₹20L total: 20K + 40K + 60K + 80K on the slabs up to ₹20L, and no 87A rebate
+        assert_eq!(breakdown.professional_tax_inr, "200000.00");
+        assert_eq!(breakdown.section_87a_rebate_inr, "0.00");
+    }
+
+    #[test]
+    fn test_old_regime_deductions_spill_over_to_other_income() {
+        let mut input = income_input("100000");
+        input.regime = TaxRegime::Old;
+        input.deductions.section_80c = "150000".to_string();
+        input.manual_income = vec![manual(IncomeSource::Interest, "400000")];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.deductions_inr, "150000.00");
+        assert_eq!(breakdown.taxable_professional_income_inr, "0.00");
+        assert_eq!(breakdown.other_income_inr, "400000.00");
+    }
+
+    #[test]
+    fn test_tax_by_group() {
+        let mut input = income_input("800000");
+        let mut spouse_row = input.ledger[0].clone();
+        spouse_row.owner_wallet = "0xDEF".into();
+        let mut unknown_row = input.ledger[0].clone();
+        unknown_row.owner_wallet = "0x999".into();
+        input.ledger.extend([spouse_row, unknown_row]);
+        input.wallets = vec![
+            Wallet {
+                id: "w1".to_string(),
+                address: "0xabc".into(),
+                label: None,
+                group_id: Some("self".to_string()),
+                source: WalletSource::Manual,
+                ownership_signature: None,
+            },
+            Wallet {
+                id: "w2".to_string(),
+                address: "0xdef".into(),
+                label: None,
+                group_id: Some("spouse".to_string()),
+                source: WalletSource::Manual,
+                ownership_signature: None,
+            },
+        ];
+
+        let grouped = calculate_tax_by_group(&input).unwrap();
+
+        let group_ids: Vec<Option<&str>> = grouped.groups.iter().map(|g| g.group_id.as_deref()).collect();
+        assert_eq!(group_ids, [Some("self"), Some("spouse"), None]);
+        // ₹8L each is fully rebated, but ₹24L together is not
+        assert!(grouped.groups.iter().all(|g| g.breakdown.total_tax_paisa == 0));
+        assert_eq!(grouped.consolidated.professional_income_inr, "2400000.00");
+        assert!(grouped.consolidated.total_tax_paisa > 0);
+    }
+
+    #[test]
+    fn test_tds_credit_reduces_net_payable() {
+        let mut input = income_input("2000000");
+        let entry = TdsEntry {
+            deductor_name: "Acme".to_string(),
+            tan: "BLRA12345B".to_string(),
+            section: "194J".to_string(),
+            transaction_date: 1750000000,
+            amount_paid_inr: "2000000".to_string(),
+            tax_deducted_inr: "200000".to_string(),
+        };
+        let prior_year = TdsEntry {
+            transaction_date: 1720000000,
+            ..entry.clone()
+        };
+        input.tds_entries = vec![entry, prior_year];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // ₹2L slab tax + 4% cess = ₹2,08,000; only this year's TDS is credited
+        assert_eq!(breakdown.total_tax_inr, "208000.00");
+        assert_eq!(breakdown.tds_credit_inr, "200000.00");
+        assert_eq!(breakdown.net_tax_payable_inr, "8000.00");
+    }
+
+    fn gift_row(category: Category, amount: &str) -> LedgerRow {
+        LedgerRow {
+            category,
+            ..income_input(amount).ledger.remove(0)
+        }
+    }
+
+    #[test]
+    fn test_gifts_under_exemption_not_taxed() {
+        let mut input = income_input("0");
+        input.ledger = vec![gift_row(Category::Gift, "30000"), gift_row(Category::Airdrop, "20000")];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.other_sources_income_inr, "0.00");
+    }
+
+    #[test]
+    fn test_gifts_above_exemption_taxed_in_full() {
+        let mut input = income_input("1000000");
+        input.ledger.push(gift_row(Category::Gift, "30000"));
+        input.ledger.push(gift_row(Category::Airdrop, "20001"));
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.other_sources_income_inr, "50001.00");
+        // ₹10,50,001 of slab income: 20K + 25K, within the 87A limit
+        assert_eq!(breakdown.professional_tax_inr, "45000.00");
+    }
+
+    #[test]
+    fn test_first_inflow_of_new_token_flagged_as_airdrop() {
+        let row = |tx_hash: &str, asset: &str, direction: Direction, block_time: u64| LedgerRow {
+            tx_hash: tx_hash.to_string(),
+            asset: asset.to_string(),
+            direction,
+            block_time,
+            counterparty: Some("0xstranger".into()),
+            user_override: false,
+            ..income_input("100").ledger.remove(0)
+        };
+        let mut ledger = vec![
+            row("0x1", "ARB", Direction::In, 1),
+            row("0x2", "ARB", Direction::In, 2),
+            row("0x3", "UNI", Direction::In, 3),
+            row("0x3", "USDC", Direction::Out, 3),
+        ];
+
+        categorize_ledger(&mut ledger, &["0xabc".to_string()], &ContractRegistry::default());
+
+        assert_eq!(ledger[0].category, Category::Airdrop);
+        assert_eq!(ledger[1].category, Category::Income);
+        // Paid for in the same transaction, so a swap rather than an airdrop
+        assert_eq!(ledger[2].category, Category::Income);
+    }
+
+    #[test]
+    fn test_non_resident_excludes_foreign_income() {
+        let mut input = income_input("600000");
+        let mut foreign = input.ledger[0].clone();
+        foreign.tx_hash = "0xforeign".to_string();
+        input.ledger.push(foreign);
+        input.foreign_source_tx_hashes = vec!["0xforeign".to_string()];
+
+        let resident = calculate_tax(&input).unwrap();
+        input.residential_status = ResidentialStatus::Rnor;
+        let rnor = calculate_tax(&input).unwrap();
+
+        assert_eq!(resident.professional_income_inr, "1200000.00");
+        assert!(resident.excluded_rows.is_empty());
+        assert_eq!(rnor.professional_income_inr, "600000.00");
+        assert_eq!(rnor.excluded_rows[0].tx_hash, "0xforeign");
+        assert_eq!(rnor.excluded_rows[0].reason, ExclusionReason::ForeignIncomeOfNonResident);
+    }
+
+    #[test]
+    fn test_non_resident_gets_no_87a_rebate_or_44ada() {
+        let mut input = income_input("1000000");
+        input.use_44ada = true;
+        input.residential_status = ResidentialStatus::NonResident;
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert!(!breakdown.presumptive_44ada_applied);
+        assert_eq!(breakdown.section_87a_rebate_inr, "0.00");
+        assert_eq!(breakdown.professional_tax_inr, "40000.00");
+    }
+
+    #[test]
+    fn test_rows_outside_financial_year_reported() {
+        let mut input = income_input("100");
+        input.ledger[0].block_time = 1720000000;
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.excluded_rows[0].reason, ExclusionReason::OutsideFinancialYear);
+    }
+
+    #[test]
+    fn test_late_filing_interest_and_fee() {
+        let mut input = income_input("2000000");
+        // 2026-10-01 IST: the part month of October counts in full
+        input.filing_date = Some(1_790_793_000);
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.total_tax_inr, "208000.00");
+        assert_eq!(breakdown.months_late, 3);
+        // 1% a month on ₹2,08,000
+        assert_eq!(breakdown.interest_234a_inr, "6240.00");
+        assert_eq!(breakdown.late_fee_234f_inr, "5000.00");
+        assert_eq!(breakdown.net_tax_payable_inr, "219240.00");
+    }
+
+    #[test]
+    fn test_on_time_filing_has_no_charges() {
+        let mut input = income_input("2000000");
+        // 2026-07-31 23:00 IST
+        input.filing_date = Some(1_785_519_000);
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.months_late, 0);
+        assert_eq!(breakdown.interest_234a_inr, "0.00");
+        assert_eq!(breakdown.late_fee_234f_inr, "0.00");
+    }
+
+    #[test]
+    fn test_late_fee_reduced_for_small_income() {
+        let mut input = income_input("450000");
+        input.filing_date = Some(1_790_793_000);
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // Rebate leaves no tax, so no interest, but the reduced fee is still due
+        assert_eq!(breakdown.interest_234a_inr, "0.00");
+        assert_eq!(breakdown.late_fee_234f_inr, "1000.00");
+    }
+
+    #[test]
+    fn test_tax_analytics() {
+        let mut input = income_input("2000000");
+        input.ledger.push(disposal("1"));
+        input.prices = vec![PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: "100000".to_string(),
+            date: None,
+            user_override: false,
+        }];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // ₹2,08,000 on ₹20L plus 30% VDA tax and cess on ₹1L
+        assert_eq!(breakdown.total_tax_inr, "239200.00");
+        assert_eq!(breakdown.effective_tax_rate_bps, 1139);
+        // 25% slab on the next rupee, plus cess
+        assert_eq!(breakdown.marginal_tax_rate_bps, 2600);
+        assert_eq!(breakdown.tax_by_category.len(), 2);
+        assert_eq!(breakdown.tax_by_category[0].tax_inr, "208000.00");
+        assert_eq!(breakdown.tax_by_category[1].category, TaxCategory::Vda);
+        assert_eq!(breakdown.tax_by_category[1].tax_inr, "31200.00");
+        assert_eq!(breakdown.monthly_income.len(), 1);
+        assert_eq!(breakdown.monthly_income[0].month, "2025-06");
+        assert_eq!(breakdown.monthly_income[0].vda_gains_inr, "100000.00");
+    }
+
+    #[test]
+    fn test_apportion_keeps_total() {
+        assert_eq!(apportion(100, &[1, 1, 1]), [33, 33, 34]);
+        assert_eq!(apportion(100, &[0, 0]), [0, 0]);
+    }
+
+    #[test]
+    fn test_huf_gets_no_87a_rebate_or_individual_deductions() {
+        let mut input = income_input("1000000");
+        input.user_type = UserType::Huf;
+        input.use_44ada = true;
+        input.regime = TaxRegime::Old;
+        input.deductions = Deductions {
+            section_80c: "150000".to_string(),
+            section_80e: "40000".to_string(),
+            ..Deductions::default()
+        };
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert!(!breakdown.presumptive_44ada_applied);
+        assert_eq!(breakdown.deductions_inr, "150000.00");
+        assert_eq!(breakdown.applied_deductions[1].allowed_inr, "0.00");
+        assert_eq!(breakdown.section_87a_rebate_inr, "0.00");
+        let kinds: Vec<WarningKind> = breakdown.warnings.iter().map(|w| w.kind).collect();
+        assert_eq!(kinds, vec![WarningKind::InvalidForEntity, WarningKind::InvalidForEntity]);
+    }
+
+    #[test]
+    fn test_huf_flags_income_from_member_transferred_wallets() {
+        let mut input = income_input("100000");
+        input.user_type = UserType::Huf;
+        input.member_transferred_wallets = vec!["0xABC".to_string()];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.warnings.len(), 1);
+        assert_eq!(breakdown.warnings[0].kind, WarningKind::ClubbedIncome);
+        assert_eq!(breakdown.warnings[0].tx_hashes, vec!["0x123".to_string()]);
+        // Flagged only; still taxed in the HUF's hands
+        assert_eq!(breakdown.professional_income_inr, "100000.00");
+    }
+
+    #[test]
+    fn test_exchange_withdrawal_untaxed_and_needs_cost_basis() {
+        let mut input = income_input("100000");
+        input.ledger[0].category = Category::ExchangeTransfer;
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.professional_income_inr, "0.00");
+        assert_eq!(breakdown.warnings.len(), 1);
+        assert_eq!(breakdown.warnings[0].kind, WarningKind::MissingCostBasis);
+        assert_eq!(breakdown.warnings[0].tx_hashes, vec!["0x123".to_string()]);
+
+        input.acquisition_lots = vec![AcquisitionLot {
+            asset: input.ledger[0].asset.clone(),
+            amount: "100000".to_string(),
+            cost_inr: "100000".to_string(),
+            acquired_at: 0,
+        }];
+        assert!(calculate_tax(&input).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_exchange_withdrawal_needs_a_lot_not_yet_disposed_of() {
+        // The only lot of ETH is sold before the withdrawal
+        let mut input = income_input("0");
+        input.acquisition_lots = vec![lot("1", "100000")];
+        let withdrawal = LedgerRow {
+            tx_hash: "0xwithdrawal".to_string(),
+            category: Category::ExchangeTransfer,
+            direction: Direction::In,
+            block_time: 1_750_000_000 + 86_400,
+            ..disposal("1")
+        };
+        input.ledger = vec![disposal("1"), withdrawal];
+
+        let breakdown = calculate_tax(&input).unwrap();
+        assert_eq!(breakdown.warnings.len(), 1);
+        assert_eq!(breakdown.warnings[0].kind, WarningKind::MissingCostBasis);
+        assert_eq!(breakdown.warnings[0].tx_hashes, vec!["0xwithdrawal".to_string()]);
+
+        // ETH received as income before the withdrawal is a lot too
+        input.ledger.insert(
+            0,
+            LedgerRow {
+                tx_hash: "0xreward".to_string(),
+                category: Category::Income,
+                ..disposal("1")
+            },
+        );
+        assert!(calculate_tax(&input).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_depegged_stablecoin_price_warned() {
+        let mut input = income_input("1000");
+        input.ledger[0].asset = "USDT".to_string();
+        input.prices = vec![PriceEntry {
+            asset: "USDT".to_string(),
+            usd_price: "0.90".to_string(),
+            date: None,
+            user_override: false,
+        }];
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.warnings.len(), 1);
+        assert_eq!(breakdown.warnings[0].kind, WarningKind::StablecoinDepeg);
+        // Still taxed at the price given
+        assert_eq!(breakdown.professional_income_inr, "900.00");
+    }
+
+    #[test]
+    fn test_corporate_warns_on_individual_options() {
+        let mut input = corporate_input("100000", CorporateRegime::Normal);
+        input.regime = TaxRegime::Old;
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.warnings.len(), 1);
+        assert!(breakdown.warnings[0].message.contains("old regime"));
+    }
+
+    #[test]
+    fn test_brought_forward_losses_and_carry_forward() {
+        // Short-term capital loss of ₹2,000 this year
+        let mut input = capital_asset_input(
+            "1000",
+            "1",
+            vec![AcquisitionLot { acquired_at: 1740000000, ..lot("1", "3000") }],
+        );
+        input.brought_forward_losses = LossCarryForward {
+            losses: vec![LossEntry {
+                head: LossHead::Business,
+                assessment_year: 2023,
+                amount_inr: "25000".to_string(),
+            }],
+        };
+        input.ledger.push(income_input("100000").ledger.remove(0));
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.brought_forward_set_off[0].amount_inr, "25000.00");
+        assert_eq!(breakdown.taxable_professional_income_inr, "75000.00");
+        let carried = &breakdown.losses_carried_forward.losses;
+        assert_eq!(carried.len(), 1);
+        assert_eq!(carried[0].head, LossHead::ShortTermCapital);
+        assert_eq!(carried[0].assessment_year, 2026);
+        assert_eq!(carried[0].amount_inr, "2000.00");
+    }
+
+    #[test]
+    fn test_multi_year_splits_ledger_by_financial_year() {
+        let mut input = income_input("100000");
+        let mut earlier = input.ledger[0].clone();
+        earlier.block_time = 1720000000; // July 2024, FY 2024-25
+        earlier.amount = "50000".to_string();
+        input.ledger.push(earlier);
+
+        let settings = [YearSettings {
+            assessment_year: 2026,
+            deductions: Deductions::default(),
+            manual_income: vec![ManualIncomeEntry {
+                source: IncomeSource::Other,
+                amount_inr: "20000".to_string(),
+                description: None,
+            }],
+            filing_date: None,
+        }];
+        let result = calculate_tax_multi_year(&input, &settings).unwrap();
+
+        assert_eq!(result.years.len(), 2);
+        assert_eq!(result.years[0].assessment_year, 2025);
+        assert_eq!(result.years[0].professional_income_inr, "50000.00");
+        assert_eq!(result.years[1].assessment_year, 2026);
+        assert_eq!(result.years[1].professional_income_inr, "100000.00");
+        assert_eq!(result.years[1].total_income_inr, "120000.00");
+        assert_eq!(result.summary[1].total_income_inr, "120000.00");
+        assert!(result.unsupported_assessment_years.is_empty());
+    }
+
+    #[test]
+    fn test_multi_year_chains_lots_and_losses() {
+        let mut input = income_input("0");
+        input.prices = vec![PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: "1000".to_string(),
+            date: None,
+            user_override: false,
+        }];
+        input.acquisition_lots = vec![lot("2", "3000")];
+        input.ledger = vec![
+            // FY 2024-25: one ETH sold at cost
+            LedgerRow {
+                block_time: 1720000000,
+                ..disposal("1")
+            },
+            // FY 2025-26: the other ETH, whose cost carries over from the opening lot
+            disposal("1"),
+        ];
+        input.ledger.push(LedgerRow {
+            block_time: 1720000000,
+            ..income_input("10000").ledger.remove(0)
+        });
+        input.brought_forward_losses = LossCarryForward {
+            losses: vec![LossEntry {
+                head: LossHead::Business,
+                assessment_year: 2024,
+                amount_inr: "25000".to_string(),
+            }],
+        };
+
+        let result = calculate_tax_multi_year(&input, &[]).unwrap();
+
+        assert_eq!(result.years[0].vda_cost_of_acquisition_inr, "1500.00");
+        assert_eq!(result.years[1].vda_cost_of_acquisition_inr, "1500.00");
+        // ₹10k absorbed in AY 2025-26, the rest brought into AY 2026-27
+        let remaining = &result.years[0].losses_carried_forward.losses;
+        assert_eq!(remaining[0].amount_inr, "15000.00");
+        assert_eq!(result.years[1].brought_forward_set_off.len(), 0);
+    }
+
+    #[test]
+    fn test_multi_year_reports_unsupported_years() {
+        let mut input = income_input("100000");
+        input.ledger[0].block_time = 1600000000; // September 2020
+
+        let result = calculate_tax_multi_year(&input, &[]).unwrap();
+
+        assert!(result.years.is_empty());
+        assert_eq!(result.unsupported_assessment_years, vec![2021]);
+        assert_eq!(result.total_tax_inr, "0.00");
+    }
+
+    #[test]
+    fn test_spam_rows_excluded() {
+        let mut input = income_input("100000");
+        let mut spam = input.ledger[0].clone();
+        spam.tx_hash = "0xspam".to_string();
+        spam.category = Category::Spam;
+        input.ledger.push(spam);
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        assert_eq!(breakdown.professional_income_inr, "100000.00");
+        assert_eq!(breakdown.excluded_rows.len(), 1);
+        assert_eq!(breakdown.excluded_rows[0].reason, ExclusionReason::Spam);
+    }
+
+    fn reward_row(block_time: u64, amount: &str) -> LedgerRow {
+        LedgerRow {
+            tx_hash: format!("0x{block_time}"),
+            block_time,
+            asset: "ETH".to_string(),
+            amount: amount.to_string(),
+            counterparty: Some("0xValidatorPool".into()),
+            category: Category::Unknown,
+            user_override: false,
+            ..income_input("0").ledger.remove(0)
+        }
+    }
+
+    #[test]
+    fn test_periodic_inflows_flagged_as_staking_rewards() {
+        let day = 86_400;
+        let mut ledger = vec![
+            reward_row(1750000000, "0.010"),
+            reward_row(1750000000 + 7 * day, "0.012"),
+            reward_row(1750000000 + 14 * day, "0.011"),
+            reward_row(1750000000 + 22 * day, "0.010"),
+        ];
+        // An irregular, larger inflow from the same pool stays income
+        let mut irregular = ledger.clone();
+        irregular[3].amount = "5".to_string();
+
+        categorize_ledger(&mut ledger, &["0xabc".to_string()], &ContractRegistry::default());
+        categorize_ledger(&mut irregular, &["0xabc".to_string()], &ContractRegistry::default());
+
+        assert!(ledger.iter().all(|row| row.category == Category::StakingReward));
+        assert!(irregular.iter().all(|row| row.category == Category::Income));
+    }
+
+    #[test]
+    fn test_staking_rewards_taxed_as_other_income() {
+        let mut input = income_input("100000");
+        let mut reward = input.ledger[0].clone();
+        reward.category = Category::StakingReward;
+        reward.amount = "30000".to_string();
+        input.ledger.push(reward);
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // No 56(2)(x) exemption, unlike gifts
+        assert_eq!(breakdown.staking_income_inr, "30000.00");
+        assert_eq!(breakdown.total_income_inr, "130000.00");
+        assert_eq!(breakdown.monthly_income[0].staking_rewards_inr, "30000.00");
+    }
+
+    #[test]
+    fn test_bridge_transfers_paired_across_chains() {
+        let bridge_row = |chain_id: u64, block_time: u64, amount: &str, direction: Direction| LedgerRow {
+            chain_id,
+            block_time,
+            direction,
+            tx_hash: format!("0x{chain_id}{block_time}"),
+            counterparty: Some("0xbridge".into()),
+            ..reward_row(block_time, amount)
+        };
+        let mut ledger = vec![
+            bridge_row(1, 1750000000, "1.0", Direction::Out),
+            bridge_row(10, 1750000600, "0.995", Direction::In),
+            // Same chain: a real disposal and income, not a bridge
+            bridge_row(1, 1750100000, "2.0", Direction::Out),
+            bridge_row(1, 1750100600, "2.0", Direction::In),
+            // Too late
+            bridge_row(1, 1750200000, "3.0", Direction::Out),
+            bridge_row(10, 1750200000 + 7_200, "3.0", Direction::In),
+        ];
+
+        categorize_ledger(&mut ledger, &["0xabc".to_string()], &ContractRegistry::default());
+
+        assert_eq!(ledger[0].category, Category::Internal);
+        assert_eq!(ledger[1].category, Category::Internal);
+        assert!(ledger[2..].iter().all(|row| row.category != Category::Internal));
+    }
+
+    #[test]
+    fn test_domain_separator_matches_contract() {
+        // TaxVerifier.DOMAIN_SEPARATOR hashes the same string
+        assert_eq!(domain_separator_bytes(), b"financoor.tax_zk.v1");
+    }
 }
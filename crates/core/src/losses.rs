@@ -0,0 +1,173 @@
+//! Loss carry-forward across assessment years
+//!
+//! Business losses (Section 72) and capital losses (Section 74) that can't be set off in
+//! the year they arise are carried forward for eight assessment years. Brought-forward
+//! losses are set off oldest first: business losses against business income, short-term
+//! capital losses against STCG and then LTCG, long-term capital losses against LTCG only.
+//! VDA losses can never be set off or carried forward (Section 115BBH).
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::{format_paisa, parse_hundredths};
+
+/// Number of assessment years a loss can be carried forward
+pub const CARRY_FORWARD_YEARS: u16 = 8;
+
+/// Head of income a loss arose under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LossHead {
+    /// Non-speculative business or profession
+    Business,
+    ShortTermCapital,
+    LongTermCapital,
+}
+
+/// Unabsorbed loss from one assessment year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LossEntry {
+    pub head: LossHead,
+    /// Assessment year the loss arose in
+    pub assessment_year: u16,
+    pub amount_inr: String,
+}
+
+/// Losses carried forward from earlier years, persisted between calculations
+///
+/// Each breakdown returns the structure to carry into the next assessment year.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LossCarryForward {
+    pub losses: Vec<LossEntry>,
+}
+
+/// A brought-forward loss set off this year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LossSetOff {
+    pub head: LossHead,
+    pub assessment_year: u16,
+    pub amount_inr: String,
+}
+
+/// Income after setting off brought-forward losses, in paisa
+pub(crate) struct BroughtForwardSetOff {
+    pub business_income: u64,
+    pub short_term_capital_gains: u64,
+    pub long_term_capital_gains: u64,
+    pub set_off: Vec<LossSetOff>,
+    /// Losses still available after this year (expired ones dropped)
+    pub remaining: Vec<LossEntry>,
+}
+
+/// Set off brought-forward losses, oldest first, against this year's income
+pub(crate) fn set_off_brought_forward(
+    carry_forward: &LossCarryForward,
+    assessment_year: u16,
+    business_income: u64,
+    short_term_capital_gains: u64,
+    long_term_capital_gains: u64,
+) -> BroughtForwardSetOff {
+    let mut result = BroughtForwardSetOff {
+        business_income,
+        short_term_capital_gains,
+        long_term_capital_gains,
+        set_off: Vec::new(),
+        remaining: Vec::new(),
+    };
+
+    let mut losses: Vec<&LossEntry> = carry_forward
+        .losses
+        .iter()
+        .filter(|loss| {
+            loss.assessment_year < assessment_year && assessment_year - loss.assessment_year <= CARRY_FORWARD_YEARS
+        })
+        .collect();
+    losses.sort_by_key(|loss| loss.assessment_year);
+
+    for loss in losses {
+        let amount = parse_hundredths(&loss.amount_inr).unwrap_or(0);
+        let absorb = |income: &mut u64, left: u64| {
+            let used = left.min(*income);
+            *income -= used;
+            left - used
+        };
+
+        let left = match loss.head {
+            LossHead::Business => absorb(&mut result.business_income, amount),
+            LossHead::ShortTermCapital => {
+                let left = absorb(&mut result.short_term_capital_gains, amount);
+                absorb(&mut result.long_term_capital_gains, left)
+            }
+            LossHead::LongTermCapital => absorb(&mut result.long_term_capital_gains, amount),
+        };
+
+        if left < amount {
+            result.set_off.push(LossSetOff {
+                head: loss.head,
+                assessment_year: loss.assessment_year,
+                amount_inr: format_paisa(amount - left),
+            });
+        }
+        if left > 0 {
+            result.remaining.push(LossEntry {
+                head: loss.head,
+                assessment_year: loss.assessment_year,
+                amount_inr: format_paisa(left),
+            });
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loss(head: LossHead, assessment_year: u16, amount_inr: &str) -> LossEntry {
+        LossEntry {
+            head,
+            assessment_year,
+            amount_inr: amount_inr.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_oldest_losses_set_off_first() {
+        let carry_forward = LossCarryForward {
+            losses: vec![
+                loss(LossHead::ShortTermCapital, 2025, "30000"),
+                loss(LossHead::ShortTermCapital, 2024, "50000"),
+            ],
+        };
+
+        // ₹40k STCG and ₹20k LTCG
+        let result = set_off_brought_forward(&carry_forward, 2026, 0, 4_000_000, 2_000_000);
+
+        assert_eq!(result.short_term_capital_gains, 0);
+        assert_eq!(result.long_term_capital_gains, 0);
+        assert_eq!(result.set_off[0].assessment_year, 2024);
+        assert_eq!(result.set_off[0].amount_inr, "50000.00");
+        assert_eq!(result.remaining.len(), 1);
+        assert_eq!(result.remaining[0].amount_inr, "20000.00");
+    }
+
+    #[test]
+    fn test_long_term_loss_only_against_ltcg_and_expiry() {
+        let carry_forward = LossCarryForward {
+            losses: vec![
+                loss(LossHead::LongTermCapital, 2025, "10000"),
+                // Nine years back: expired
+                loss(LossHead::Business, 2017, "10000"),
+            ],
+        };
+
+        let result = set_off_brought_forward(&carry_forward, 2026, 5_000_000, 5_000_000, 0);
+
+        assert_eq!(result.business_income, 5_000_000);
+        assert_eq!(result.short_term_capital_gains, 5_000_000);
+        assert!(result.set_off.is_empty());
+        assert_eq!(result.remaining.len(), 1);
+        assert_eq!(result.remaining[0].head, LossHead::LongTermCapital);
+    }
+}
@@ -0,0 +1,240 @@
+//! NFT purchases and sales
+//!
+//! An NFT bought for ETH (or any fungible token) shows up as a fungible outflow and an
+//! NFT inflow in the same transaction; a sale is the reverse. What was paid becomes the
+//! cost of acquisition of the NFTs received, and a sale's proceeds less that cost are a
+//! VDA gain (or a loss that can't be set off) under Section 115BBH.
+
+use crate::prelude::*;
+use crate::reference_rates::UsdInrRates;
+use crate::valuation::row_value;
+use crate::{Category, Direction, LedgerRow, PriceEntry};
+
+/// Cost of acquisition of one NFT, from the transaction that bought it
+pub(crate) struct NftPurchase<'a> {
+    contract: &'a str,
+    token_id: &'a str,
+    block_time: u64,
+    /// Items of the token ID bought (more than one for ERC-1155)
+    quantity: u64,
+    /// INR paisa, for all of them
+    cost: u64,
+}
+
+/// Items moved by an NFT row; 1 when the source didn't say
+fn quantity(row: &LedgerRow) -> u64 {
+    row.quantity.as_deref().and_then(|quantity| quantity.parse().ok()).unwrap_or(1).max(1)
+}
+
+/// Mark NFT trades: fungible out + NFT in within one transaction is a purchase, NFT out
+/// + fungible in is a sale. User overrides are left alone.
+#[cfg(feature = "std")]
+pub(crate) fn pair_nft_trades(ledger: &mut [LedgerRow]) {
+    // What moved in each transaction: [fungible out, NFT in, NFT out, fungible in]
+    let mut moved: BTreeMap<&str, [bool; 4]> = BTreeMap::new();
+    for row in ledger.iter() {
+        let slot = match (row.direction, row.token_id.is_some()) {
+            (Direction::Out, false) => 0,
+            (Direction::In, true) => 1,
+            (Direction::Out, true) => 2,
+            (Direction::In, false) => 3,
+        };
+        moved.entry(&row.tx_hash).or_default()[slot] = true;
+    }
+
+    let trades: Vec<(usize, Category)> = ledger
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| !row.user_override)
+        .filter_map(|(i, row)| match moved[row.tx_hash.as_str()] {
+            [true, true, ..] => Some((i, Category::NftPurchase)),
+            [_, _, true, true] => Some((i, Category::NftSale)),
+            _ => None,
+        })
+        .collect();
+
+    for (i, category) in trades {
+        ledger[i].category = category;
+        ledger[i].confidence = 0.9;
+    }
+}
+
+/// Every NFT bought in the ledger, with what was paid split across the NFTs received in
+/// the same transaction by quantity (any remainder on the last one)
+pub(crate) fn nft_purchases<'a>(
+    ledger: &'a [LedgerRow],
+    prices: &[PriceEntry],
+    usd_inr_rate: &UsdInrRates,
+) -> Vec<NftPurchase<'a>> {
+    let mut purchases = Vec::new();
+    let mut seen_txs: Vec<&str> = Vec::new();
+
+    for row in ledger.iter().filter(|row| row.category == Category::NftPurchase) {
+        if seen_txs.contains(&row.tx_hash.as_str()) {
+            continue;
+        }
+        seen_txs.push(&row.tx_hash);
+
+        let tx_rows = ledger
+            .iter()
+            .filter(|other| other.tx_hash == row.tx_hash && other.category == Category::NftPurchase);
+        let paid: u64 = tx_rows
+            .clone()
+            .filter(|other| other.direction == Direction::Out && other.token_id.is_none())
+            .map(|other| row_value(other, prices, usd_inr_rate))
+            .fold(0, u64::saturating_add);
+        let nfts: Vec<&LedgerRow> = tx_rows
+            .filter(|other| other.direction == Direction::In && other.token_id.is_some())
+            .collect();
+
+        let total: u64 = nfts.iter().map(|nft| quantity(nft)).fold(0, u64::saturating_add);
+        let mut allocated = 0;
+        for (i, nft) in nfts.iter().enumerate() {
+            let cost = if i + 1 == nfts.len() {
+                paid - allocated
+            } else {
+                (u128::from(paid) * u128::from(quantity(nft)) / u128::from(total)) as u64
+            };
+            allocated += cost;
+            purchases.push(NftPurchase {
+                contract: nft.contract_address.as_deref().unwrap_or_default(),
+                token_id: nft.token_id.as_deref().unwrap_or_default(),
+                block_time: nft.block_time,
+                quantity: quantity(nft),
+                cost,
+            });
+        }
+    }
+    purchases
+}
+
+/// Cost of the NFTs sent out in a sale transaction, each from its latest purchase before
+/// the sale, per item sold (zero for NFTs bought outside the ledger)
+pub(crate) fn nft_sale_cost(ledger: &[LedgerRow], tx_hash: &str, purchases: &[NftPurchase]) -> u64 {
+    ledger
+        .iter()
+        .filter(|row| {
+            row.tx_hash == tx_hash
+                && row.category == Category::NftSale
+                && row.direction == Direction::Out
+                && row.token_id.is_some()
+        })
+        .map(|nft| {
+            let contract = nft.contract_address.as_deref().unwrap_or_default();
+            let token_id = nft.token_id.as_deref().unwrap_or_default();
+            purchases
+                .iter()
+                .filter(|purchase| {
+                    purchase.contract.eq_ignore_ascii_case(contract)
+                        && purchase.token_id == token_id
+                        && purchase.block_time <= nft.block_time
+                })
+                .max_by_key(|purchase| purchase.block_time)
+                .map_or(0, |purchase| {
+                    let sold = quantity(nft).min(purchase.quantity);
+                    (u128::from(purchase.cost) * u128::from(sold) / u128::from(purchase.quantity)) as u64
+                })
+        })
+        .fold(0, u64::saturating_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{calculate_tax, TaxInput};
+    use crate::testing::ledger_row;
+
+    fn row(tx_hash: &str, block_time: u64, asset: &str, amount: &str, direction: Direction) -> LedgerRow {
+        LedgerRow {
+            tx_hash: tx_hash.to_string(),
+            block_time,
+            asset: asset.to_string(),
+            amount: amount.to_string(),
+            direction,
+            counterparty: Some("0xmarketplace".into()),
+            confidence: 0.0,
+            ..ledger_row()
+        }
+    }
+
+    fn nft(tx_hash: &str, block_time: u64, token_id: &str, direction: Direction) -> LedgerRow {
+        LedgerRow {
+            token_id: Some(token_id.to_string()),
+            contract_address: Some("0xpunks".to_string()),
+            ..row(tx_hash, block_time, "PUNK", "1", direction)
+        }
+    }
+
+    fn trade_ledger() -> Vec<LedgerRow> {
+        let mut ledger = vec![
+            // Two NFTs bought together for 2 ETH
+            row("0xbuy", 1750000000, "ETH", "2", Direction::Out),
+            nft("0xbuy", 1750000000, "7", Direction::In),
+            nft("0xbuy", 1750000000, "8", Direction::In),
+            // #7 sold for 1.5 ETH, #8 for 0.5 ETH
+            nft("0xsell7", 1760000000, "7", Direction::Out),
+            row("0xsell7", 1760000000, "ETH", "1.5", Direction::In),
+            nft("0xsell8", 1760000000, "8", Direction::Out),
+            row("0xsell8", 1760000000, "ETH", "0.5", Direction::In),
+        ];
+        pair_nft_trades(&mut ledger);
+        ledger
+    }
+
+    #[test]
+    fn test_trades_paired_and_cost_split() {
+        let ledger = trade_ledger();
+
+        assert!(ledger[..3].iter().all(|row| row.category == Category::NftPurchase));
+        assert!(ledger[3..].iter().all(|row| row.category == Category::NftSale));
+
+        let prices = vec![PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: "1000".to_string(),
+            date: None,
+            user_override: false,
+        }];
+        let purchases = nft_purchases(&ledger, &prices, &UsdInrRates::flat("1"));
+        assert_eq!(purchases.len(), 2);
+        assert_eq!(nft_sale_cost(&ledger, "0xsell7", &purchases), 100_000);
+
+        // 10 ERC-1155 items of one ID and 30 of another for 4 ETH; 5 of the first sold
+        let edition = |tx_hash: &str, token_id: &str, quantity: &str, direction| LedgerRow {
+            amount: quantity.to_string(),
+            quantity: Some(quantity.to_string()),
+            ..nft(tx_hash, 1750000000, token_id, direction)
+        };
+        let mut ledger = vec![
+            row("0xmint", 1750000000, "ETH", "4", Direction::Out),
+            edition("0xmint", "1", "10", Direction::In),
+            edition("0xmint", "2", "30", Direction::In),
+            edition("0xsell", "1", "5", Direction::Out),
+            row("0xsell", 1760000000, "ETH", "1", Direction::In),
+        ];
+        pair_nft_trades(&mut ledger);
+        let purchases = nft_purchases(&ledger, &prices, &UsdInrRates::flat("1"));
+        assert_eq!(purchases[0].cost, 100_000);
+        assert_eq!(purchases[1].cost, 300_000);
+        assert_eq!(nft_sale_cost(&ledger, "0xsell", &purchases), 50_000);
+    }
+
+    #[test]
+    fn test_nft_sales_taxed_per_disposal() {
+        let input: TaxInput = serde_json::from_value(serde_json::json!({
+            "user_type": "individual",
+            "wallets": [],
+            "ledger": trade_ledger(),
+            "prices": [{ "asset": "ETH", "usd_price": "1000" }],
+            "usd_inr_rate": "1",
+            "use_44ada": false
+        }))
+        .unwrap();
+
+        let breakdown = calculate_tax(&input).unwrap();
+
+        // ₹1,500 - ₹1,000 gain on #7; the ₹500 loss on #8 can't offset it
+        assert_eq!(breakdown.vda_cost_of_acquisition_inr, "2000.00");
+        assert_eq!(breakdown.vda_gains_inr, "500.00");
+        assert_eq!(breakdown.vda_losses_inr, "500.00");
+    }
+}
@@ -0,0 +1,122 @@
+//! Non-taxable noise
+//!
+//! Token approvals and other contract calls show up as zero-value outflows, reverted
+//! transactions can still leave transfer records behind, and a wallet can send to
+//! itself. None of these move anything of value, so they get the `NonTaxable` category
+//! with full confidence instead of sitting Unknown in the review queue. Zero-value
+//! inflows are left to spam detection (address poisoning). Rows of failed transactions can
+//! instead be dropped from the ledger altogether.
+
+use std::collections::HashSet;
+
+use crate::{Category, Direction, LedgerRow};
+
+/// Mark approvals and other zero-value outflows, rows of failed transactions (by tx hash,
+/// lowercase) and self-sends as `NonTaxable`; user overrides are left alone. Returns how
+/// many rows were marked.
+pub fn flag_non_taxable(ledger: &mut [LedgerRow], failed_txs: &HashSet<String>) -> usize {
+    let mut flagged = 0;
+    for row in ledger.iter_mut().filter(|row| !row.user_override) {
+        let zero_value_call =
+            row.direction == Direction::Out && row.amount.parse::<f64>().is_ok_and(|amount| amount == 0.0);
+        let failed = failed_txs.contains(&row.tx_hash.to_lowercase());
+        let self_send = row.counterparty.as_ref() == Some(&row.owner_wallet);
+        if zero_value_call || failed || self_send {
+            row.category = Category::NonTaxable;
+            row.confidence = 1.0;
+            flagged += 1;
+        }
+    }
+    flagged
+}
+
+/// Hashes (lowercase, deduplicated) of the on-chain transactions whose receipts are worth
+/// checking for a revert: those the user sent and those paying the user native coin, as
+/// some sources list the value of a reverted call. Token and NFT inflows come from event
+/// logs, which a reverted transaction never emits, so they aren't checked.
+pub fn receipt_check_hashes(ledger: &[LedgerRow]) -> Vec<String> {
+    let mut hashes: Vec<String> = ledger
+        .iter()
+        .filter(|row| row.chain_id != 0 && row.tx_hash.starts_with("0x") && row.tx_hash.len() == 66)
+        .filter(|row| row.direction == Direction::Out || row.contract_address.is_none())
+        .map(|row| row.tx_hash.to_lowercase())
+        .collect();
+    hashes.sort();
+    hashes.dedup();
+    hashes
+}
+
+/// Remove the rows of failed transactions (by tx hash, lowercase) instead of flagging them;
+/// user overrides are kept. Returns how many rows were removed.
+pub fn drop_failed_transactions(ledger: &mut Vec<LedgerRow>, failed_txs: &HashSet<String>) -> usize {
+    let before = ledger.len();
+    ledger.retain(|row| row.user_override || !failed_txs.contains(&row.tx_hash.to_lowercase()));
+    before - ledger.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ledger_row;
+
+    fn row(tx_hash: &str, amount: &str, direction: Direction, counterparty: &str) -> LedgerRow {
+        LedgerRow {
+            tx_hash: tx_hash.to_string(),
+            amount: amount.to_string(),
+            direction,
+            counterparty: Some(counterparty.into()),
+            confidence: 0.0,
+            ..ledger_row()
+        }
+    }
+
+    #[test]
+    fn test_approvals_failed_txs_and_self_sends_flagged() {
+        let mut ledger = vec![
+            row("0x1", "0", Direction::Out, "0xtoken"),
+            row("0xFA", "1.5", Direction::Out, "0xrouter"),
+            row("0x3", "2", Direction::Out, "0xABC"),
+            row("0x4", "0", Direction::In, "0xpoisoner"),
+            row("0x5", "1", Direction::Out, "0xfriend"),
+        ];
+        let failed = HashSet::from(["0xfa".to_string()]);
+
+        assert_eq!(flag_non_taxable(&mut ledger, &failed), 3);
+
+        let categories: Vec<Category> = ledger.iter().map(|row| row.category).collect();
+        assert_eq!(
+            categories,
+            vec![
+                Category::NonTaxable,
+                Category::NonTaxable,
+                Category::NonTaxable,
+                Category::Unknown,
+                Category::Unknown
+            ]
+        );
+        assert!(crate::review_queue(&ledger[..3], &[], "83", crate::DEFAULT_REVIEW_THRESHOLD).is_empty());
+    }
+
+    #[test]
+    fn test_receipt_checks_and_dropping_failed_txs() {
+        let hash = |n: u8| format!("0x{}", format!("{:02x}", n).repeat(32));
+        let mut token_in = row(&hash(3), "5", Direction::In, "0xsender");
+        token_in.contract_address = Some("0xtoken".to_string());
+        let mut off_chain = row("binance:1", "1", Direction::Out, "0xfriend");
+        off_chain.chain_id = 0;
+        let mut ledger = vec![
+            row(&hash(0xab).to_uppercase().replacen("0X", "0x", 1), "1", Direction::Out, "0xrouter"),
+            row(&hash(2), "0.5", Direction::In, "0xcontract"),
+            token_in,
+            off_chain,
+        ];
+
+        assert_eq!(receipt_check_hashes(&ledger), vec![hash(2), hash(0xab)]);
+
+        ledger[1].user_override = true;
+        let failed = HashSet::from([hash(0xab), hash(2)]);
+        assert_eq!(drop_failed_transactions(&mut ledger, &failed), 1);
+        assert_eq!(ledger.len(), 3);
+        assert_eq!(ledger[0].tx_hash, hash(2));
+    }
+}
@@ -0,0 +1,106 @@
+//! Wallet ownership
+//!
+//! Ledgers are public, so anyone could prove tax over someone else's wallets. A wallet
+//! proves it belongs to the user by signing `ownership_message` (EIP-191 `personal_sign`),
+//! kept on the `Wallet` in the private input. The program recovers each signer with the
+//! secp256k1 precompile and commits a hash of the wallets proven owned, and whether they
+//! cover every wallet the ledger has rows for.
+
+use crate::prelude::*;
+use crate::{Address, LedgerRow, TaxError};
+
+/// Message a wallet signs to show it belongs to the user proving its tax
+pub fn ownership_message(wallet: &Address) -> String {
+    format!("Financoor: I own wallet {} and allow tax proofs over its transactions", wallet.as_str())
+}
+
+/// What `personal_sign` hashes (keccak256) and signs: the EIP-191 prefixed message
+pub fn personal_message(message: &str) -> Vec<u8> {
+    let mut out = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    out.extend_from_slice(message.as_bytes());
+    out
+}
+
+/// The 20 bytes of an EVM address; None for other addresses, which can't sign
+pub fn evm_address_bytes(address: &Address) -> Option<[u8; 20]> {
+    if !address.is_evm() {
+        return None;
+    }
+    let mut bytes = [0u8; 20];
+    for (byte, pair) in bytes.iter_mut().zip(address.as_bytes()[2..].chunks(2)) {
+        *byte = u8::from_str_radix(core::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// A 65-byte `r ‖ s ‖ v` signature, from hex
+pub fn signature_bytes(signature: &str) -> Result<[u8; 65], TaxError> {
+    let invalid = || TaxError::InvalidOwnershipSignature("expected 0x and 65 hex bytes".to_string());
+    let digits = signature.strip_prefix("0x").unwrap_or(signature);
+    if digits.len() != 130 {
+        return Err(invalid());
+    }
+    let mut bytes = [0u8; 65];
+    for (byte, pair) in bytes.iter_mut().zip(digits.as_bytes().chunks(2)) {
+        let pair = core::str::from_utf8(pair).map_err(|_| invalid())?;
+        *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+/// What `ownedWalletsCommitment` hashes (keccak256): the owned wallets' bytes, sorted and
+/// without duplicates
+pub fn owned_wallets_bytes(owned: &[[u8; 20]]) -> Vec<u8> {
+    let mut owned = owned.to_vec();
+    owned.sort_unstable();
+    owned.dedup();
+    owned.concat()
+}
+
+/// Whether every wallet the ledger has rows for is among the owned ones
+pub fn all_wallets_owned(ledger: &[LedgerRow], owned: &[[u8; 20]]) -> bool {
+    ledger
+        .iter()
+        .all(|row| evm_address_bytes(&row.owner_wallet).is_some_and(|wallet| owned.contains(&wallet)))
+}
+
+/// Check a wallet's ownership signature before proving, as the program will
+#[cfg(feature = "std")]
+pub fn verify_ownership(wallet: &Address, signature: &str) -> Result<(), TaxError> {
+    let invalid = |reason: String| TaxError::InvalidOwnershipSignature(format!("{}: {}", wallet.as_str(), reason));
+    let expected = evm_address_bytes(wallet).ok_or_else(|| invalid("not an EVM wallet".to_string()))?;
+    let signature = alloy_primitives::Signature::from_raw(&signature_bytes(signature)?)
+        .map_err(|e| invalid(e.to_string()))?;
+    let signer = signature
+        .recover_address_from_msg(ownership_message(wallet))
+        .map_err(|e| invalid(e.to_string()))?;
+    if signer.0 .0 != expected {
+        return Err(invalid(format!("signed by {}", signer)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLET: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+    const SIGNATURE: &str = "0x9b2f4479ae5741419c7e856cbf173eabb18227262bbb832ba112eadd386a7a9d\
+                             11dd9630b81e821125ba2e7320b613f270caa9b128bbb81379491b7b7a4f40bb1b";
+
+    #[test]
+    fn test_ownership_signature() {
+        verify_ownership(&Address::from(WALLET), SIGNATURE).unwrap();
+        let other = Address::from("0x0000000000000000000000000000000000000001");
+        assert!(verify_ownership(&other, SIGNATURE).is_err());
+        assert!(verify_ownership(&Address::from(WALLET), "0x1234").is_err());
+    }
+
+    #[test]
+    fn test_owned_wallets_bytes_are_canonical() {
+        let (a, b) = ([1u8; 20], [2u8; 20]);
+        assert_eq!(owned_wallets_bytes(&[b, a, b]), owned_wallets_bytes(&[a, b]));
+        assert_eq!(evm_address_bytes(&Address::from(WALLET)).unwrap()[0], 0x2c);
+        assert_eq!(evm_address_bytes(&Address::from("So1anaAddress")), None);
+    }
+}
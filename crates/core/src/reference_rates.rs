@@ -0,0 +1,179 @@
+//! USD/INR reference rates by date
+//!
+//! Income and disposals are converted to INR at the reference rate for the day they
+//! happen (the RBI/FBIL reference rate, or the SBI TT buying rate where the rules call
+//! for it) rather than one flat rate for the year. Rates aren't published on weekends
+//! and bank holidays, so a day without one uses the last rate published before it; days
+//! before the table starts, or any day when there's no table, use the flat
+//! `usd_inr_rate`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::rules::{civil_from_days, ist_date};
+use crate::tds::{find_column, parse_date, split_csv_line, IST_OFFSET_SECS};
+use crate::{parse_hundredths, TaxError, DEFAULT_USD_INR_RATE_PAISA};
+
+/// USD/INR reference rate published for one day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceRate {
+    /// Indian calendar date ("YYYY-MM-DD")
+    pub date: String,
+    /// INR per USD
+    pub usd_inr: String,
+}
+
+/// USD/INR rate (paisa per USD) applicable on each day
+pub(crate) struct UsdInrRates {
+    flat: u64,
+    /// (date, paisa per USD), sorted by date
+    table: Vec<(String, u64)>,
+}
+
+impl UsdInrRates {
+    pub(crate) fn new(flat: &str, reference_rates: &[ReferenceRate]) -> Self {
+        let mut table: Vec<(String, u64)> = reference_rates
+            .iter()
+            .filter_map(|rate| Some((rate.date.clone(), parse_hundredths(&rate.usd_inr)?)))
+            .collect();
+        table.sort();
+        Self {
+            flat: parse_hundredths(flat).unwrap_or(DEFAULT_USD_INR_RATE_PAISA),
+            table,
+        }
+    }
+
+    /// Flat rate for every day
+    pub(crate) fn flat(flat: &str) -> Self {
+        Self::new(flat, &[])
+    }
+
+    /// Rate for the Indian calendar date of `timestamp`
+    pub(crate) fn at(&self, timestamp: u64) -> u64 {
+        let (year, month, day) = ist_date(timestamp);
+        let date = format!("{:04}-{:02}-{:02}", year, month, day);
+        match self.table.partition_point(|(published, _)| *published <= date) {
+            0 => self.flat,
+            i => self.table[i - 1].1,
+        }
+    }
+}
+
+/// Canonical JSON of the rates rows are converted at, whose SHA256 hash the proof commits
+/// to as `rateTableCommitment`: the flat rate, then `[date, usd_inr]` per reference rate,
+/// sorted. The flat rate is part of it since days before the table use it.
+pub fn canonical_rate_table(usd_inr_rate: &str, reference_rates: &[ReferenceRate]) -> String {
+    let mut table: Vec<(&str, &str)> = reference_rates
+        .iter()
+        .map(|rate| (rate.date.as_str(), rate.usd_inr.as_str()))
+        .collect();
+    table.sort_unstable();
+    serde_json::to_string(&(usd_inr_rate, table)).unwrap_or_default()
+}
+
+/// Parse USD/INR reference rates from a CSV download of RBI/FBIL reference rates or
+/// SBI TT rates
+///
+/// The header is the first line naming a date column and a USD or TT buying rate column;
+/// dates may be "02-Apr-2025", "02/04/2025" or "2025-04-02" (anything after a space,
+/// like a time of day, is ignored). Rows without a rate (holidays) are skipped.
+pub fn parse_reference_rates_csv(csv: &str) -> Result<Vec<ReferenceRate>, TaxError> {
+    let invalid = |msg: String| TaxError::InvalidReferenceRates(msg);
+    let rate_columns = ["tt buy", "us dollar", "usd", "rate"];
+    let mut lines = csv.lines().enumerate();
+
+    let header = lines
+        .by_ref()
+        .map(|(_, line)| split_csv_line(line))
+        .find(|fields| find_column(fields, &["date"]).is_some() && find_column(fields, &rate_columns).is_some())
+        .ok_or_else(|| invalid("no header row with date and USD rate columns".to_string()))?;
+    let date_col = find_column(&header, &["date"]).unwrap_or_default();
+    let rate_col = find_column(&header, &rate_columns).unwrap_or_default();
+
+    let mut rates = Vec::new();
+    for (index, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(line);
+        let field = |col: usize| fields.get(col).map(|s| s.trim()).unwrap_or("");
+        let line_no = index + 1;
+
+        let rate = field(rate_col).replace(',', "");
+        if rate.is_empty() || rate == "-" || rate.parse::<f64>().is_ok_and(|rate| rate == 0.0) {
+            continue;
+        }
+        if rate.parse::<f64>().is_err() {
+            return Err(invalid(format!("line {line_no}: invalid rate '{}'", field(rate_col))));
+        }
+        let day = field(date_col).split_whitespace().next().unwrap_or_default();
+        let midnight = parse_date(day)
+            .ok_or_else(|| invalid(format!("line {line_no}: invalid date '{}'", field(date_col))))?;
+        let (year, month, day) = civil_from_days((midnight + IST_OFFSET_SECS) / 86_400);
+
+        rates.push(ReferenceRate {
+            date: format!("{:04}-{:02}-{:02}", year, month, day),
+            usd_inr: rate,
+        });
+    }
+
+    if rates.is_empty() {
+        return Err(invalid("no rates found".to_string()));
+    }
+    rates.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rbi_and_sbi_downloads() {
+        let rbi = "Reference Rate Archive\nDate,US Dollar,Pound Sterling\n03-Jun-2025,85.61,115.9\n\
+                   02-Jun-2025,85.52,115.4\n";
+        let rates = parse_reference_rates_csv(rbi).unwrap();
+        assert_eq!(rates[0].date, "2025-06-02");
+        assert_eq!(rates[0].usd_inr, "85.52");
+        assert_eq!(rates[1].date, "2025-06-03");
+
+        let sbi = "DATE,PDF FILE,TT BUY,TT SELL\n2025-06-02 09:00,a.pdf,85.10,86.02\n\
+                   2025-06-07 09:00,b.pdf,0.00,0.00\n";
+        let rates = parse_reference_rates_csv(sbi).unwrap();
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].usd_inr, "85.10");
+    }
+
+    #[test]
+    fn test_rate_of_last_published_day() {
+        let rate = |date: &str, usd_inr: &str| ReferenceRate {
+            date: date.to_string(),
+            usd_inr: usd_inr.to_string(),
+        };
+        let rates = UsdInrRates::new("83", &[rate("2025-06-06", "85.75"), rate("2025-06-02", "85.52")]);
+
+        // 2025-06-02 23:00 IST
+        assert_eq!(rates.at(1_748_885_400), 8_552);
+        // Saturday 2025-06-07 uses Friday's rate
+        assert_eq!(rates.at(1_749_254_400), 8_575);
+        // Before the table starts
+        assert_eq!(rates.at(1_748_700_000), 8_300);
+    }
+
+    #[test]
+    fn test_canonical_rate_table_covers_flat_rate() {
+        let rate = |date: &str, usd_inr: &str| ReferenceRate {
+            date: date.to_string(),
+            usd_inr: usd_inr.to_string(),
+        };
+        let rates = [rate("2025-06-06", "85.75"), rate("2025-06-02", "85.52")];
+        let reordered = [rates[1].clone(), rates[0].clone()];
+
+        assert_eq!(
+            canonical_rate_table("83", &rates),
+            r#"["83",[["2025-06-02","85.52"],["2025-06-06","85.75"]]]"#
+        );
+        assert_eq!(canonical_rate_table("83", &rates), canonical_rate_table("83", &reordered));
+        assert_ne!(canonical_rate_table("83", &rates), canonical_rate_table("95", &rates));
+    }
+}
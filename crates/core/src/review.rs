@@ -0,0 +1,188 @@
+//! Review queue for uncertain categorizations
+//!
+//! Rows the categorizer is unsure about (Unknown, or below a confidence threshold) are
+//! listed for the user to confirm or correct, largest INR value first, since a wrong
+//! category on a big transfer moves the most tax. Reviewed rows are marked
+//! `user_override` and are never recategorized, so rules can be re-run over a reviewed
+//! ledger and the changes shown before they're kept.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::reference_rates::UsdInrRates;
+use crate::{amount_to_inr_paisa, format_paisa, Category, LedgerRow, PriceEntry};
+
+/// Rows categorized below this confidence are queued for review by default
+pub const DEFAULT_REVIEW_THRESHOLD: f32 = 0.7;
+
+/// A ledger row awaiting review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewItem {
+    /// Index of the row in the ledger
+    pub row_id: usize,
+    /// INR value of the row, i.e. how much income or gain a wrong category adds or hides
+    pub value_inr: String,
+    pub row: LedgerRow,
+}
+
+/// Rows that are Unknown or categorized below `threshold` and not yet reviewed, ordered
+/// by INR value (largest first)
+pub fn review_queue(
+    ledger: &[LedgerRow],
+    prices: &[PriceEntry],
+    usd_inr_rate: &str,
+    threshold: f32,
+) -> Vec<ReviewItem> {
+    let usd_inr_rate = UsdInrRates::flat(usd_inr_rate);
+
+    let mut queue: Vec<(u64, usize)> = ledger
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| !row.user_override && (row.category == Category::Unknown || row.confidence < threshold))
+        .map(|(i, row)| (amount_to_inr_paisa(&row.amount, &row.asset, row.block_time, prices, &usd_inr_rate), i))
+        .collect();
+    queue.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    queue
+        .into_iter()
+        .map(|(value, i)| ReviewItem {
+            row_id: i,
+            value_inr: format_paisa(value),
+            row: ledger[i].clone(),
+        })
+        .collect()
+}
+
+/// A row whose category changes when the ledger is recategorized
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryChange {
+    /// Index of the row in the ledger
+    pub row_id: usize,
+    pub tx_hash: String,
+    pub from: Category,
+    pub to: Category,
+    /// Confidence of the new category
+    pub confidence: f32,
+}
+
+/// Rows whose category differs between a ledger and its recategorized copy (same rows, same order)
+pub fn category_changes(before: &[LedgerRow], after: &[LedgerRow]) -> Vec<CategoryChange> {
+    before
+        .iter()
+        .zip(after)
+        .enumerate()
+        .filter(|(_, (old, new))| old.category != new.category)
+        .map(|(i, (old, new))| CategoryChange {
+            row_id: i,
+            tx_hash: new.tx_hash.clone(),
+            from: old.category,
+            to: new.category,
+            confidence: new.confidence,
+        })
+        .collect()
+}
+
+/// Mark a row as reviewed, keeping its category or replacing it with `category`
+pub fn review_row(row: &mut LedgerRow, category: Option<Category>) {
+    if let Some(category) = category {
+        row.category = category;
+    }
+    row.confidence = 1.0;
+    row.user_override = true;
+}
+
+/// Copy reviewed categories from `previous` onto the matching rows of a refetched ledger
+/// (same chain, transaction, wallet, asset, direction, amount and token); returns how many
+/// rows were restored
+pub fn restore_overrides(ledger: &mut [LedgerRow], previous: &[LedgerRow]) -> usize {
+    let same_transfer = |a: &LedgerRow, b: &LedgerRow| {
+        a.chain_id == b.chain_id
+            && a.tx_hash.eq_ignore_ascii_case(&b.tx_hash)
+            && a.owner_wallet == b.owner_wallet
+            && a.asset == b.asset
+            && a.direction == b.direction
+            && a.amount == b.amount
+            && a.token_id == b.token_id
+    };
+
+    let mut restored = 0;
+    for row in ledger.iter_mut() {
+        let reviewed = previous
+            .iter()
+            .find(|reviewed| reviewed.user_override && same_transfer(reviewed, row));
+        if let Some(reviewed) = reviewed {
+            row.category = reviewed.category;
+            row.confidence = reviewed.confidence;
+            row.user_override = true;
+            restored += 1;
+        }
+    }
+    restored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ledger_row;
+
+    fn row(tx_hash: &str, amount: &str, category: Category, confidence: f32) -> LedgerRow {
+        LedgerRow {
+            tx_hash: tx_hash.to_string(),
+            asset: "USDC".to_string(),
+            amount: amount.to_string(),
+            decimals: 6,
+            counterparty: Some("0xsender".into()),
+            category,
+            confidence,
+            ..ledger_row()
+        }
+    }
+
+    #[test]
+    fn test_queue_orders_uncertain_rows_by_value() {
+        let mut ledger = vec![
+            row("0x1", "100", Category::Income, 0.6),
+            row("0x2", "5000", Category::Income, 0.95),
+            row("0x3", "2000", Category::Unknown, 0.0),
+            row("0x4", "300", Category::Income, 0.5),
+        ];
+        review_row(&mut ledger[3], None);
+
+        let queue = review_queue(&ledger, &[], "83", DEFAULT_REVIEW_THRESHOLD);
+
+        let ids: Vec<usize> = queue.iter().map(|item| item.row_id).collect();
+        assert_eq!(ids, vec![2, 0]);
+        assert_eq!(queue[0].value_inr, "166000.00");
+    }
+
+    #[test]
+    fn test_overrides_survive_recategorization_and_refetch() {
+        let mut ledger = vec![row("0x1", "100", Category::Unknown, 0.0)];
+        review_row(&mut ledger[0], Some(Category::Gift));
+
+        crate::categorize_ledger(&mut ledger, &["0xabc".to_string()], &crate::ContractRegistry::default());
+        assert_eq!(ledger[0].category, Category::Gift);
+
+        let mut refetched = vec![row("0x1", "100", Category::Income, 0.6), row("0x2", "100", Category::Income, 0.6)];
+        assert_eq!(restore_overrides(&mut refetched, &ledger), 1);
+        assert_eq!(refetched[0].category, Category::Gift);
+        assert!(refetched[0].user_override);
+        assert!(!refetched[1].user_override);
+    }
+
+    #[test]
+    fn test_recategorization_diff_skips_reviewed_rows() {
+        let mut ledger = vec![row("0x1", "100", Category::Unknown, 0.0), row("0x2", "100", Category::Unknown, 0.0)];
+        review_row(&mut ledger[1], Some(Category::Gift));
+
+        let mut recategorized = ledger.clone();
+        crate::categorize_ledger(&mut recategorized, &["0xabc".to_string()], &crate::ContractRegistry::default());
+
+        let changes = category_changes(&ledger, &recategorized);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].row_id, 0);
+        assert_eq!(changes[0].from, Category::Unknown);
+        // An unpriced token inflow the user never sent on
+        assert_eq!(changes[0].to, Category::Airdrop);
+    }
+}
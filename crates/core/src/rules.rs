@@ -0,0 +1,395 @@
+//! Versioned tax rule tables by assessment year
+//!
+//! Each supported assessment year ships as an embedded JSON table under `rules/`.
+//! Rates are stored in basis points so the same tables can drive both this crate
+//! and the integer-only zkVM program.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::{CorporateRegime, TaxError, TaxRegime, UserType};
+
+/// Assessment year used when a request doesn't specify one (AY 2026-27)
+pub const DEFAULT_ASSESSMENT_YEAR: u16 = 2026;
+
+/// IST is UTC+5:30; the financial year runs on Indian time
+const IST_OFFSET_SECS: u64 = 19_800;
+
+/// Embedded rule tables, keyed by the first year of the assessment year
+const EMBEDDED_RULES: [(u16, &str); 3] = [
+    (2024, include_str!("../rules/ay2024-25.json")),
+    (2025, include_str!("../rules/ay2025-26.json")),
+    (2026, include_str!("../rules/ay2026-27.json")),
+];
+
+/// A tax slab: income up to `upto` (INR) is taxed at `rate_bps`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Slab {
+    /// Upper bound of the slab in INR (`None` for the top slab)
+    pub upto: Option<u64>,
+    /// Rate in basis points (500 = 5%)
+    pub rate_bps: u64,
+}
+
+/// Section 87A rebate parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rebate87A {
+    /// Maximum total income (INR), special-rate income included, eligible for the full rebate
+    pub income_limit: u64,
+    /// Maximum rebate (INR)
+    pub max_rebate: u64,
+    /// Whether tax just above the limit is capped at the income exceeding it
+    pub marginal_relief: bool,
+}
+
+/// A surcharge tier: applies when total income exceeds `above` (INR)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurchargeTier {
+    pub above: u64,
+    /// Rate in basis points
+    pub rate_bps: u64,
+}
+
+/// Slabs, rebate, and surcharge for one regime (Individual/HUF)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegimeRules {
+    pub slabs: Vec<Slab>,
+    /// Standard deduction from salary income (INR)
+    pub salary_standard_deduction: u64,
+    pub rebate_87a: Rebate87A,
+    /// Tiers in ascending order of threshold
+    pub surcharge: Vec<SurchargeTier>,
+}
+
+/// Tax rate and surcharge for one corporate regime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorporateRules {
+    /// Rate in basis points
+    pub rate_bps: u64,
+    /// Turnover (INR) above which `high_turnover_rate_bps` applies (`None` = flat rate)
+    pub turnover_limit: Option<u64>,
+    pub high_turnover_rate_bps: u64,
+    /// Tiers in ascending order of threshold
+    pub surcharge: Vec<SurchargeTier>,
+}
+
+impl CorporateRules {
+    /// Tax rate for a turnover in paisa
+    pub fn rate_for_turnover(&self, turnover: u64) -> u64 {
+        match self.turnover_limit {
+            Some(limit) if turnover > limit * 100 => self.high_turnover_rate_bps,
+            _ => self.rate_bps,
+        }
+    }
+}
+
+/// Cost Inflation Index for one financial year (Section 48)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostInflationIndex {
+    /// First year of the financial year (2023 = FY 2023-24)
+    pub fy: u16,
+    pub index: u64,
+}
+
+/// Capital gains on tokens treated as capital assets rather than VDAs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapitalGainsRules {
+    /// Holding period (days) beyond which gains are long-term
+    pub long_term_holding_days: u64,
+    /// LTCG rate in basis points (STCG is taxed at slab rates)
+    pub ltcg_rate_bps: u64,
+    /// Whether long-term cost of acquisition is indexed
+    pub indexation: bool,
+    /// CII table in ascending order of year (required when `indexation` is set)
+    #[serde(default)]
+    pub cost_inflation_index: Vec<CostInflationIndex>,
+}
+
+impl CapitalGainsRules {
+    /// CII for the financial year containing a timestamp, clamped to the table's range
+    pub fn cost_inflation_index_at(&self, timestamp: u64) -> u64 {
+        self.cost_inflation_index
+            .iter()
+            .rev()
+            .find(|cii| financial_year_bounds(cii.fy + 1).0 <= timestamp)
+            .or(self.cost_inflation_index.first())
+            .map_or(1, |cii| cii.index)
+    }
+}
+
+/// Return due dates and charges for belated returns (Sections 234A and 234F)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LateFilingRules {
+    /// Section 139(1) due date for Individual/HUF returns without audit (YYYY-MM-DD)
+    pub individual_due_date: String,
+    /// Section 139(1) due date for corporates, whose accounts are audited (YYYY-MM-DD)
+    pub corporate_due_date: String,
+    /// 234A interest per month or part of a month on unpaid tax
+    pub interest_234a_rate_bps: u64,
+    /// 234F fee (INR)
+    pub fee_234f: u64,
+    /// 234F fee when total income doesn't exceed `fee_234f_reduced_income_limit` (INR)
+    pub fee_234f_reduced: u64,
+    pub fee_234f_reduced_income_limit: u64,
+}
+
+impl LateFilingRules {
+    /// Due date as (year, month, day) for a user type
+    pub fn due_date(&self, user_type: UserType) -> (u64, u64, u64) {
+        let date = match user_type {
+            UserType::Individual | UserType::Huf => &self.individual_due_date,
+            UserType::Corporate => &self.corporate_due_date,
+        };
+        // Validated when the rules are loaded
+        parse_civil_date(date).unwrap_or((1970, 1, 1))
+    }
+}
+
+/// GST on professional services
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GstRules {
+    /// Aggregate turnover above which registration is required (INR)
+    pub registration_threshold: u64,
+    /// GST rate on professional services
+    pub services_rate_bps: u64,
+}
+
+/// Complete rule table for one assessment year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxRules {
+    /// First year of the assessment year (2026 = AY 2026-27)
+    pub assessment_year: u16,
+    pub new_regime: RegimeRules,
+    pub old_regime: RegimeRules,
+    /// VDA tax rate under Section 115BBH
+    pub vda_tax_rate_bps: u64,
+    /// Health & Education Cess rate
+    pub cess_rate_bps: u64,
+    /// Standard deduction on rental income under Section 24(a)
+    pub house_property_deduction_bps: u64,
+    /// Gifts/airdrops from non-relatives are taxable in full once they exceed this in aggregate (INR)
+    pub gift_exemption_limit: u64,
+    /// Section 115BAA concessional regime (22%, flat 10% surcharge)
+    pub corporate_115baa: CorporateRules,
+    /// Normal corporate regime (25%/30% by turnover, tiered surcharge)
+    pub corporate_normal: CorporateRules,
+    pub capital_gains: CapitalGainsRules,
+    /// 44ADA presumptive income rate
+    pub presumptive_44ada_rate_bps: u64,
+    /// 44ADA gross receipts cap (INR)
+    pub presumptive_44ada_limit: u64,
+    /// Enhanced 44ADA cap when at least 95% of receipts are digital (INR)
+    pub presumptive_44ada_digital_limit: u64,
+    /// Section 80C deduction cap (INR, old regime)
+    pub section_80c_limit: u64,
+    /// Section 80D deduction cap (INR, old regime)
+    pub section_80d_limit: u64,
+    /// Section 80CCD(1B) additional NPS contribution cap (INR, old regime)
+    pub section_80ccd_1b_limit: u64,
+    /// Section 80TTA savings interest cap (INR, old regime)
+    pub section_80tta_limit: u64,
+    /// Section 80G qualifying limit as a share of adjusted gross total income
+    pub section_80g_limit_bps: u64,
+    pub late_filing: LateFilingRules,
+    pub gst: GstRules,
+}
+
+impl TaxRules {
+    /// Load the embedded rule table for an assessment year
+    pub fn for_assessment_year(assessment_year: u16) -> Result<Self, TaxError> {
+        let json = EMBEDDED_RULES
+            .iter()
+            .find(|(year, _)| *year == assessment_year)
+            .map(|(_, json)| *json)
+            .ok_or(TaxError::UnsupportedAssessmentYear(assessment_year))?;
+
+        Self::from_json(json)
+    }
+
+    /// Parse and validate a rule table from JSON
+    pub fn from_json(json: &str) -> Result<Self, TaxError> {
+        let rules: TaxRules =
+            serde_json::from_str(json).map_err(|e| TaxError::InvalidRules(e.to_string()))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Assessment years with embedded rule tables
+    pub fn supported_assessment_years() -> Vec<u16> {
+        EMBEDDED_RULES.iter().map(|(year, _)| *year).collect()
+    }
+
+    /// Rules for the given regime
+    pub fn regime(&self, regime: TaxRegime) -> &RegimeRules {
+        match regime {
+            TaxRegime::New => &self.new_regime,
+            TaxRegime::Old => &self.old_regime,
+        }
+    }
+
+    /// Rules for the given corporate regime
+    pub fn corporate(&self, regime: CorporateRegime) -> &CorporateRules {
+        match regime {
+            CorporateRegime::Section115baa => &self.corporate_115baa,
+            CorporateRegime::Normal => &self.corporate_normal,
+        }
+    }
+
+    fn validate(&self) -> Result<(), TaxError> {
+        for (name, regime) in [("new_regime", &self.new_regime), ("old_regime", &self.old_regime)] {
+            let open_ended = regime.slabs.iter().filter(|s| s.upto.is_none()).count();
+            if open_ended != 1 || regime.slabs.last().and_then(|s| s.upto).is_some() {
+                return Err(TaxError::InvalidRules(format!(
+                    "{name}: exactly the last slab must have no upper bound"
+                )));
+            }
+            let bounds: Vec<u64> = regime.slabs.iter().filter_map(|s| s.upto).collect();
+            if bounds.windows(2).any(|w| w[0] >= w[1]) {
+                return Err(TaxError::InvalidRules(format!("{name}: slab bounds must be ascending")));
+            }
+            if regime.surcharge.windows(2).any(|w| w[0].above >= w[1].above) {
+                return Err(TaxError::InvalidRules(format!(
+                    "{name}: surcharge tiers must be ascending"
+                )));
+            }
+        }
+        for (name, corporate) in [("corporate_115baa", &self.corporate_115baa), ("corporate_normal", &self.corporate_normal)] {
+            if corporate.surcharge.windows(2).any(|w| w[0].above >= w[1].above) {
+                return Err(TaxError::InvalidRules(format!(
+                    "{name}: surcharge tiers must be ascending"
+                )));
+            }
+        }
+        for date in [&self.late_filing.individual_due_date, &self.late_filing.corporate_due_date] {
+            if parse_civil_date(date).is_none() {
+                return Err(TaxError::InvalidRules(format!("late_filing: invalid due date '{date}'")));
+            }
+        }
+        let cii = &self.capital_gains.cost_inflation_index;
+        if self.capital_gains.indexation && cii.is_empty() {
+            return Err(TaxError::InvalidRules("capital_gains: indexation needs a CII table".to_string()));
+        }
+        if cii.windows(2).any(|w| w[0].fy >= w[1].fy) || cii.iter().any(|c| c.index == 0) {
+            return Err(TaxError::InvalidRules(
+                "capital_gains: CII must be ascending and non-zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Days since the Unix epoch for a Gregorian date (Hinnant's `days_from_civil`)
+pub(crate) fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Gregorian date for a count of days since the Unix epoch (Hinnant's `civil_from_days`)
+pub(crate) fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Calendar date (IST) of a Unix timestamp
+pub(crate) fn ist_date(timestamp: u64) -> (u64, u64, u64) {
+    civil_from_days((timestamp + IST_OFFSET_SECS) / 86_400)
+}
+
+/// Parse a "YYYY-MM-DD" date
+fn parse_civil_date(value: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = value.split('-').map(|part| part.parse::<u64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+/// Unix timestamp range (inclusive) of the financial year an assessment year taxes:
+/// 1 April to 31 March IST, ending the year before (AY 2026-27 covers FY 2025-26)
+pub fn financial_year_bounds(assessment_year: u16) -> (u64, u64) {
+    let fy_start_at = |year: u64| days_from_civil(year, 4, 1) * 86_400 - IST_OFFSET_SECS;
+    let year = assessment_year as u64;
+    (fy_start_at(year - 1), fy_start_at(year) - 1)
+}
+
+/// Assessment year that taxes the financial year containing a timestamp
+pub fn assessment_year_of(timestamp: u64) -> u16 {
+    let (year, month, _) = ist_date(timestamp);
+    (if month >= 4 { year + 1 } else { year }) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_rules_parse() {
+        for year in TaxRules::supported_assessment_years() {
+            let rules = TaxRules::for_assessment_year(year).unwrap();
+            assert_eq!(rules.assessment_year, year);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_year() {
+        assert!(matches!(
+            TaxRules::for_assessment_year(1999),
+            Err(TaxError::UnsupportedAssessmentYear(1999))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_unbounded_middle_slab() {
+        let mut rules = TaxRules::for_assessment_year(DEFAULT_ASSESSMENT_YEAR).unwrap();
+        rules.new_regime.slabs[1].upto = None;
+        let json = serde_json::to_string(&rules).unwrap();
+
+        assert!(matches!(TaxRules::from_json(&json), Err(TaxError::InvalidRules(_))));
+    }
+
+    #[test]
+    fn test_financial_year_bounds() {
+        // FY 2025-26: 2025-04-01T00:00+05:30 to 2026-03-31T23:59:59+05:30
+        assert_eq!(financial_year_bounds(2026), (1_743_445_800, 1_774_981_799));
+    }
+
+    #[test]
+    fn test_assessment_year_of() {
+        let (fy_start, fy_end) = financial_year_bounds(2026);
+        assert_eq!(assessment_year_of(fy_start), 2026);
+        assert_eq!(assessment_year_of(fy_end), 2026);
+        assert_eq!(assessment_year_of(fy_end + 1), 2027);
+    }
+
+    #[test]
+    fn test_civil_date_round_trip() {
+        for (year, month, day) in [(1970, 1, 1), (2024, 2, 29), (2026, 7, 31)] {
+            assert_eq!(civil_from_days(days_from_civil(year, month, day)), (year, month, day));
+        }
+        // 2025-07-31T23:00 UTC is already 1 August in India
+        assert_eq!(ist_date(1_754_002_800), (2025, 8, 1));
+    }
+
+    #[test]
+    fn test_cost_inflation_index_lookup() {
+        let rules = TaxRules::for_assessment_year(2024).unwrap().capital_gains;
+
+        // FY 2017-18 = 272, before the table clamps to FY 2001-02 = 100, after to FY 2023-24
+        assert_eq!(rules.cost_inflation_index_at(1_500_000_000), 272);
+        assert_eq!(rules.cost_inflation_index_at(0), 100);
+        assert_eq!(rules.cost_inflation_index_at(1_750_000_000), 348);
+    }
+}
@@ -0,0 +1,179 @@
+//! Schedule FA (foreign assets) disclosure
+//!
+//! Crypto held with a foreign exchange or custodian has to be disclosed in Schedule FA
+//! for the calendar year ending 31 December before the assessment year (CY 2025 for
+//! AY 2026-27). Balances are rebuilt from the ledger and valued at the given prices, so
+//! pass prices as of the period end for the closing value.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::reference_rates::UsdInrRates;
+use crate::rules::days_from_civil;
+use crate::{format_paisa, parse_quantity, quantity_to_inr_paisa, Direction, LedgerRow, PriceEntry};
+
+/// IST midnight offset; the reporting period runs on Indian dates
+const IST_OFFSET_SECS: u64 = 19_800;
+
+/// A wallet or account held with a foreign exchange or custodian
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeignAccount {
+    /// Wallet address or account identifier, as it appears in the ledger's `owner_wallet`
+    pub account: String,
+    /// Exchange or custodian name
+    pub institution: String,
+    pub country: String,
+    /// ISO 3166 country code
+    pub country_code: String,
+    /// When the account was opened (unix seconds), if known
+    #[serde(default)]
+    pub opened_at: Option<u64>,
+}
+
+/// One Schedule FA row for a foreign custodial account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleFaRow {
+    pub country: String,
+    pub country_code: String,
+    pub institution: String,
+    pub account: String,
+    pub opened_at: Option<u64>,
+    /// Highest value held at any point in the period (INR)
+    pub peak_value_inr: String,
+    /// Value held at the end of the period (INR)
+    pub closing_value_inr: String,
+}
+
+/// Unix timestamp range (inclusive) of the calendar year reported for an assessment year
+pub fn schedule_fa_period(assessment_year: u16) -> (u64, u64) {
+    let year_start_at = |year: u64| days_from_civil(year, 1, 1) * 86_400 - IST_OFFSET_SECS;
+    let year = assessment_year as u64;
+    (year_start_at(year - 1), year_start_at(year) - 1)
+}
+
+/// Schedule FA rows for the given foreign accounts
+///
+/// Accounts that held nothing during the period are left out.
+pub fn schedule_fa_rows(
+    accounts: &[ForeignAccount],
+    ledger: &[LedgerRow],
+    prices: &[PriceEntry],
+    usd_inr_rate: &str,
+    assessment_year: u16,
+) -> Vec<ScheduleFaRow> {
+    let usd_inr_rate = UsdInrRates::flat(usd_inr_rate);
+    let (period_start, period_end) = schedule_fa_period(assessment_year);
+
+    accounts
+        .iter()
+        .filter_map(|account| {
+            let mut rows: Vec<&LedgerRow> = ledger
+                .iter()
+                .filter(|row| row.owner_wallet == account.account && row.block_time <= period_end)
+                .collect();
+            rows.sort_by_key(|row| row.block_time);
+
+            // Quantity held per asset
+            let mut balances: BTreeMap<&str, u128> = BTreeMap::new();
+            let value = |balances: &BTreeMap<&str, u128>, at: u64| -> u64 {
+                balances
+                    .iter()
+                    .map(|(asset, quantity)| quantity_to_inr_paisa(*quantity, asset, at, prices, &usd_inr_rate))
+                    .sum()
+            };
+
+            let mut peak = None;
+            for row in rows {
+                if row.block_time >= period_start && peak.is_none() {
+                    peak = Some(value(&balances, row.block_time));
+                }
+                let quantity = parse_quantity(&row.amount).unwrap_or(0);
+                let balance = balances.entry(row.asset.as_str()).or_default();
+                *balance = match row.direction {
+                    Direction::In => balance.saturating_add(quantity),
+                    Direction::Out => balance.saturating_sub(quantity),
+                };
+                if row.block_time >= period_start {
+                    peak = peak.max(Some(value(&balances, row.block_time)));
+                }
+            }
+
+            let closing = value(&balances, period_end);
+            let peak = peak.unwrap_or(closing).max(closing);
+            (peak > 0).then(|| ScheduleFaRow {
+                country: account.country.clone(),
+                country_code: account.country_code.clone(),
+                institution: account.institution.clone(),
+                account: account.account.clone(),
+                opened_at: account.opened_at,
+                peak_value_inr: format_paisa(peak),
+                closing_value_inr: format_paisa(closing),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Category;
+    use crate::testing::ledger_row;
+
+    fn row(block_time: u64, amount: &str, direction: Direction) -> LedgerRow {
+        LedgerRow {
+            owner_wallet: "0xExchange".into(),
+            block_time,
+            amount: amount.to_string(),
+            direction,
+            category: Category::Internal,
+            ..ledger_row()
+        }
+    }
+
+    fn account() -> ForeignAccount {
+        ForeignAccount {
+            account: "0xexchange".to_string(),
+            institution: "Example Exchange".to_string(),
+            country: "Singapore".to_string(),
+            country_code: "SG".to_string(),
+            opened_at: None,
+        }
+    }
+
+    #[test]
+    fn test_schedule_fa_period_is_previous_calendar_year() {
+        // 2025-01-01T00:00+05:30 to 2025-12-31T23:59:59+05:30
+        assert_eq!(schedule_fa_period(2026), (1_735_669_800, 1_767_205_799));
+    }
+
+    #[test]
+    fn test_peak_and_closing_values() {
+        let prices = vec![PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: "1000".to_string(),
+            date: None,
+            user_override: false,
+        }];
+        let ledger = vec![
+            // Opening balance from 2024, then a deposit and a withdrawal in 2025
+            row(1_720_000_000, "1", Direction::In),
+            row(1_740_000_000, "2", Direction::In),
+            row(1_750_000_000, "2.5", Direction::Out),
+            // After the period
+            row(1_770_000_000, "10", Direction::In),
+        ];
+
+        let rows = schedule_fa_rows(&[account()], &ledger, &prices, "1", 2026);
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].peak_value_inr, "3000.00");
+        assert_eq!(rows[0].closing_value_inr, "500.00");
+    }
+
+    #[test]
+    fn test_account_without_holdings_is_skipped() {
+        let rows = schedule_fa_rows(&[account()], &[], &[], "1", 2026);
+
+        assert!(rows.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! Spam and scam token detection
+//!
+//! Wallets pick up junk airdrops (zero-value transfers used for address poisoning,
+//! phishing tokens whose symbol is a URL, unsellable tokens with no market) that would
+//! otherwise be counted as income. Flagged rows get the `Spam` category, which the
+//! calculator excludes. Whitelisted assets and user-overridden rows are never flagged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::{Category, Direction, LedgerRow, PriceEntry};
+
+/// Fragments that show up in phishing token symbols ("Visit claim-eth.xyz", ...)
+const PHISHING_MARKERS: [&str; 9] = ["http", "www", ".com", ".io", ".org", ".xyz", "t.me", "claim", "visit"];
+
+/// Assets that are never spam
+const NATIVE_ASSETS: [&str; 2] = ["ETH", "INR"];
+
+/// User lists for spam detection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpamSettings {
+    /// Assets that are never flagged (case-insensitive)
+    #[serde(default)]
+    pub whitelist: Vec<String>,
+    /// Assets that are always flagged (case-insensitive)
+    #[serde(default)]
+    pub known_spam: Vec<String>,
+}
+
+impl SpamSettings {
+    fn is_whitelisted(&self, asset: &str) -> bool {
+        NATIVE_ASSETS.contains(&asset) || self.whitelist.iter().any(|w| w.eq_ignore_ascii_case(asset))
+    }
+}
+
+/// Confidence that a row is spam, if anything about it looks like spam
+fn spam_confidence(row: &LedgerRow, ledger: &[LedgerRow], prices: &[PriceEntry], settings: &SpamSettings) -> Option<f32> {
+    if settings.known_spam.iter().any(|s| s.eq_ignore_ascii_case(&row.asset)) {
+        return Some(1.0);
+    }
+
+    let symbol = row.asset.to_lowercase();
+    if !row.asset.is_ascii() || PHISHING_MARKERS.iter().any(|marker| symbol.contains(marker)) {
+        return Some(0.9);
+    }
+
+    // Zero-value transfers are address poisoning or dust mints
+    if row.amount.parse::<f64>().is_ok_and(|amount| amount == 0.0) {
+        return Some(0.9);
+    }
+
+    // Honeypot: a token with no market that the user has never managed to send anywhere.
+    // Only meaningful when prices were supplied at all.
+    let priced = prices.iter().any(|price| price.asset.eq_ignore_ascii_case(&row.asset));
+    let ever_sent = ledger
+        .iter()
+        .any(|other| other.direction == Direction::Out && other.asset.eq_ignore_ascii_case(&row.asset));
+    if !prices.is_empty() && !priced && !ever_sent {
+        return Some(0.6);
+    }
+
+    None
+}
+
+/// Mark inflows that look like spam with the `Spam` category; returns how many were flagged
+pub fn flag_spam(ledger: &mut [LedgerRow], prices: &[PriceEntry], settings: &SpamSettings) -> usize {
+    let flagged: Vec<(usize, f32)> = ledger
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| {
+            row.direction == Direction::In
+                && !row.user_override
+                && !matches!(row.category, Category::Internal | Category::ExchangeTransfer | Category::NonTaxable)
+                && !settings.is_whitelisted(&row.asset)
+        })
+        .filter_map(|(i, row)| spam_confidence(row, ledger, prices, settings).map(|confidence| (i, confidence)))
+        .collect();
+
+    for &(i, confidence) in &flagged {
+        ledger[i].category = Category::Spam;
+        ledger[i].confidence = confidence;
+    }
+    flagged.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ledger_row;
+
+    fn row(asset: &str, amount: &str, direction: Direction) -> LedgerRow {
+        LedgerRow {
+            asset: asset.to_string(),
+            amount: amount.to_string(),
+            direction,
+            counterparty: Some("0xsender".into()),
+            category: Category::Income,
+            confidence: 0.6,
+            ..ledger_row()
+        }
+    }
+
+    fn price(asset: &str) -> PriceEntry {
+        PriceEntry {
+            asset: asset.to_string(),
+            usd_price: "1".to_string(),
+            date: None,
+            user_override: false,
+        }
+    }
+
+    #[test]
+    fn test_flags_phishing_zero_value_and_known_spam() {
+        let mut ledger = vec![
+            row("Visit-Rewards.xyz", "1000", Direction::In),
+            row("USDC", "0", Direction::In),
+            row("SCAM", "5", Direction::In),
+            row("USDC", "100", Direction::In),
+            row("ETH", "0.5", Direction::In),
+        ];
+        let settings = SpamSettings {
+            known_spam: vec!["scam".to_string()],
+            ..SpamSettings::default()
+        };
+
+        let flagged = flag_spam(&mut ledger, &[], &settings);
+
+        assert_eq!(flagged, 3);
+        assert_eq!(ledger[0].category, Category::Spam);
+        assert_eq!(ledger[1].category, Category::Spam);
+        assert_eq!(ledger[2].confidence, 1.0);
+        assert_eq!(ledger[3].category, Category::Income);
+        assert_eq!(ledger[4].category, Category::Income);
+    }
+
+    #[test]
+    fn test_unpriced_unsold_token_flagged_unless_whitelisted() {
+        let mut ledger = vec![
+            row("JUNK", "100", Direction::In),
+            row("SOLD", "100", Direction::In),
+            row("SOLD", "50", Direction::Out),
+            row("NEWCOIN", "100", Direction::In),
+        ];
+        let settings = SpamSettings {
+            whitelist: vec!["newcoin".to_string()],
+            ..SpamSettings::default()
+        };
+
+        flag_spam(&mut ledger, &[price("USDC")], &settings);
+
+        assert_eq!(ledger[0].category, Category::Spam);
+        assert_eq!(ledger[1].category, Category::Income);
+        assert_eq!(ledger[3].category, Category::Income);
+    }
+}
@@ -0,0 +1,193 @@
+//! Stablecoins
+//!
+//! USDC, USDT and DAI are recognized by contract address per chain (bundled in
+//! `rules/stablecoins.json`), so a scam token that merely calls itself "USDC" isn't
+//! treated as one. Stablecoins are priced at $1 when no price is given, a supplied price
+//! too far from $1 is reported as a depeg, and swaps from one stablecoin to another can
+//! optionally be treated as internal moves instead of taxable VDA transfers.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{parse_hundredths, Category, Direction, LedgerRow, PriceEntry};
+
+const BUNDLED_STABLECOINS: &str = include_str!("../rules/stablecoins.json");
+
+/// A price further than this from $1 is a depeg (basis points)
+pub const DEPEG_THRESHOLD_BPS: u64 = 200;
+
+/// A stablecoin deployment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stablecoin {
+    pub symbol: String,
+    /// Token contract address (lowercase)
+    pub address: String,
+    pub chain_id: u64,
+}
+
+/// Known stablecoin contracts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StablecoinRegistry {
+    stablecoins: Vec<Stablecoin>,
+}
+
+impl StablecoinRegistry {
+    /// USDC, USDT and DAI on Ethereum, Optimism, Polygon, Base, Arbitrum and Sepolia
+    pub fn bundled() -> &'static Self {
+        static BUNDLED: OnceLock<StablecoinRegistry> = OnceLock::new();
+        BUNDLED.get_or_init(|| serde_json::from_str(BUNDLED_STABLECOINS).expect("bundled stablecoins are valid"))
+    }
+
+    /// Whether a row moves a stablecoin: by contract address when the row has one, by
+    /// symbol otherwise
+    pub fn is_stablecoin(&self, row: &LedgerRow) -> bool {
+        match row.contract_address.as_deref() {
+            Some(address) => self
+                .stablecoins
+                .iter()
+                .any(|coin| coin.chain_id == row.chain_id && coin.address.eq_ignore_ascii_case(address)),
+            None => row.token_id.is_none() && self.is_stablecoin_symbol(&row.asset),
+        }
+    }
+
+    /// Whether `asset` is the symbol of a known stablecoin (case-insensitive)
+    pub fn is_stablecoin_symbol(&self, asset: &str) -> bool {
+        self.stablecoins.iter().any(|coin| coin.symbol.eq_ignore_ascii_case(asset))
+    }
+
+    pub fn stablecoins(&self) -> &[Stablecoin] {
+        &self.stablecoins
+    }
+}
+
+/// Add a $1 price for every stablecoin in the ledger that has none; returns how many
+/// prices were added
+pub fn price_stablecoins(prices: &mut Vec<PriceEntry>, ledger: &[LedgerRow], registry: &StablecoinRegistry) -> usize {
+    let mut added = 0;
+    for row in ledger.iter().filter(|row| registry.is_stablecoin(row)) {
+        if !prices.iter().any(|price| price.asset.eq_ignore_ascii_case(&row.asset)) {
+            prices.push(PriceEntry {
+                asset: row.asset.clone(),
+                usd_price: "1".to_string(),
+                date: None,
+                user_override: false,
+            });
+            added += 1;
+        }
+    }
+    added
+}
+
+/// Stablecoin prices more than `DEPEG_THRESHOLD_BPS` away from $1
+pub fn depegged_stablecoins<'a>(prices: &'a [PriceEntry], registry: &StablecoinRegistry) -> Vec<&'a PriceEntry> {
+    prices
+        .iter()
+        .filter(|price| registry.is_stablecoin_symbol(&price.asset))
+        .filter(|price| {
+            parse_hundredths(&price.usd_price).is_some_and(|cents| cents.abs_diff(100) * 100 > DEPEG_THRESHOLD_BPS)
+        })
+        .collect()
+}
+
+/// Mark transactions in which the user swapped one stablecoin for another, at most
+/// `DEPEG_THRESHOLD_BPS` apart in amount and with nothing else moved, as Internal. User
+/// overrides are left alone; returns how many rows were marked.
+pub fn flag_stablecoin_swaps(ledger: &mut [LedgerRow], registry: &StablecoinRegistry) -> usize {
+    // Per transaction: (stablecoin sent, stablecoin received, anything else moved)
+    let mut moved: HashMap<&str, (f64, f64, bool)> = HashMap::new();
+    for row in ledger.iter() {
+        let entry = moved.entry(&row.tx_hash).or_default();
+        let amount = row.amount.parse::<f64>().ok().filter(|_| registry.is_stablecoin(row));
+        match (amount, row.direction) {
+            (Some(amount), Direction::Out) => entry.0 += amount,
+            (Some(amount), Direction::In) => entry.1 += amount,
+            (None, _) => entry.2 = true,
+        }
+    }
+
+    let swaps: Vec<usize> = ledger
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| !row.user_override)
+        .filter(|(_, row)| {
+            let (sent, received, other) = moved[row.tx_hash.as_str()];
+            let close = (sent - received).abs() * 10_000.0 <= sent * DEPEG_THRESHOLD_BPS as f64;
+            !other && sent > 0.0 && received > 0.0 && close
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    for &i in &swaps {
+        ledger[i].category = Category::Internal;
+        ledger[i].confidence = 0.7;
+    }
+    swaps.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ledger_row;
+
+    fn row(asset: &str, address: Option<&str>, amount: &str, direction: Direction) -> LedgerRow {
+        LedgerRow {
+            asset: asset.to_string(),
+            amount: amount.to_string(),
+            decimals: 6,
+            direction,
+            counterparty: Some("0xrouter".into()),
+            category: Category::Income,
+            confidence: 0.6,
+            contract_address: address.map(str::to_string),
+            ..ledger_row()
+        }
+    }
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const DAI: &str = "0x6b175474e89094c44da98b954eedeac495271d0f";
+
+    #[test]
+    fn test_recognized_by_address_and_priced_at_one_dollar() {
+        let registry = StablecoinRegistry::bundled();
+        let ledger = vec![
+            row("USDC", Some(USDC), "100", Direction::In),
+            row("USDC", Some("0x000000000000000000000000000000000000dead"), "100", Direction::In),
+            row("DAI", None, "100", Direction::In),
+        ];
+
+        assert!(registry.is_stablecoin(&ledger[0]));
+        assert!(!registry.is_stablecoin(&ledger[1]));
+        assert!(registry.is_stablecoin(&ledger[2]));
+
+        let mut prices = vec![PriceEntry {
+            asset: "USDT".to_string(),
+            usd_price: "0.95".to_string(),
+            date: None,
+            user_override: false,
+        }];
+        assert_eq!(price_stablecoins(&mut prices, &ledger, registry), 2);
+        assert_eq!(prices[1].usd_price, "1");
+
+        let depegged = depegged_stablecoins(&prices, registry);
+        assert_eq!(depegged.len(), 1);
+        assert_eq!(depegged[0].asset, "USDT");
+    }
+
+    #[test]
+    fn test_stablecoin_swap_marked_internal() {
+        let registry = StablecoinRegistry::bundled();
+        let mut ledger = vec![
+            row("USDC", Some(USDC), "1000", Direction::Out),
+            row("DAI", Some(DAI), "999.5", Direction::In),
+        ];
+
+        assert_eq!(flag_stablecoin_swaps(&mut ledger, registry), 2);
+        assert!(ledger.iter().all(|row| row.category == Category::Internal));
+
+        // Swapping into anything else is a taxable transfer
+        let mut ledger = vec![row("USDC", Some(USDC), "1000", Direction::Out), row("WETH", None, "0.3", Direction::In)];
+        assert_eq!(flag_stablecoin_swaps(&mut ledger, registry), 0);
+    }
+}
@@ -0,0 +1,309 @@
+//! TDS/TCS credit import and reconciliation
+//!
+//! Parses the TDS section of a Form 26AS / AIS CSV export and matches each entry
+//! against income rows in the ledger by date and amount. The TDS itself is a credit
+//! against tax payable; it doesn't change the tax liability that gets proved.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::reference_rates::UsdInrRates;
+use crate::rules::days_from_civil;
+use crate::{
+    amount_to_inr_paisa, format_paisa, parse_hundredths, sum_paisa, Category, Direction, LedgerRow, PriceEntry,
+    TaxError,
+};
+
+/// Income rows are matched to a TDS entry within this many days of its transaction date
+const MATCH_WINDOW_DAYS: u64 = 3;
+
+/// Amount tolerance when matching (basis points of the expected amount)
+const MATCH_TOLERANCE_BPS: u64 = 100;
+
+/// IST midnight offset; 26AS dates are Indian calendar dates
+pub(crate) const IST_OFFSET_SECS: u64 = 19_800;
+
+/// One TDS/TCS entry from Form 26AS or AIS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TdsEntry {
+    pub deductor_name: String,
+    /// TAN of the deductor
+    pub tan: String,
+    /// Section the tax was deducted under (e.g. "194J", "194S")
+    pub section: String,
+    /// Transaction date (unix seconds, midnight IST)
+    pub transaction_date: u64,
+    /// Gross amount paid or credited (INR)
+    pub amount_paid_inr: String,
+    /// Tax deducted (INR)
+    pub tax_deducted_inr: String,
+}
+
+/// A TDS entry and the ledger row it was matched to, if any
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TdsMatch {
+    pub entry: TdsEntry,
+    /// Transaction hash of the matching income row
+    pub tx_hash: Option<String>,
+}
+
+/// Result of reconciling Form 26AS entries against the ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TdsReconciliation {
+    pub entries: Vec<TdsMatch>,
+    pub matched_count: usize,
+    /// Total tax deducted across all entries (INR)
+    pub tds_credit_inr: String,
+    /// Tax deducted on entries with no matching income row (INR)
+    pub unmatched_tds_inr: String,
+}
+
+/// Total TDS credit in paisa for entries inside the given (inclusive) time range
+pub(crate) fn tds_credit(entries: &[TdsEntry], fy_start: u64, fy_end: u64) -> Result<u64, TaxError> {
+    sum_paisa(
+        entries
+            .iter()
+            .filter(|entry| (fy_start..=fy_end).contains(&entry.transaction_date))
+            .map(|entry| parse_hundredths(&entry.tax_deducted_inr).unwrap_or(0)),
+    )
+}
+
+/// Parse the TDS section of a Form 26AS / AIS CSV export
+///
+/// Lines before the header (the one naming the TAN and tax deducted columns) are
+/// skipped, as is anything after the first blank line that follows it.
+pub fn parse_form_26as_csv(csv: &str) -> Result<Vec<TdsEntry>, TaxError> {
+    let invalid = |msg: String| TaxError::InvalidTdsImport(msg);
+    let mut lines = csv.lines().enumerate();
+
+    let header = lines
+        .by_ref()
+        .map(|(_, line)| split_csv_line(line))
+        .find(|fields| find_column(fields, &["tan"]).is_some() && find_column(fields, &["tax deducted"]).is_some())
+        .ok_or_else(|| invalid("no TDS header row (TAN / Tax Deducted columns)".to_string()))?;
+
+    let column = |names: &[&str]| {
+        find_column(&header, names).ok_or_else(|| invalid(format!("missing column: {}", names[0])))
+    };
+    let name_col = column(&["name of deductor", "deductor name", "name"])?;
+    let tan_col = column(&["tan"])?;
+    let section_col = column(&["section"])?;
+    let date_col = column(&["transaction date", "date"])?;
+    let amount_col = column(&["amount paid", "amount credited", "amount"])?;
+    let tax_col = column(&["tax deducted", "tds deducted"])?;
+
+    let mut entries = Vec::new();
+    for (index, line) in lines {
+        if line.trim().is_empty() {
+            break;
+        }
+        let fields = split_csv_line(line);
+        let field = |col: usize| fields.get(col).map(|s| s.trim()).unwrap_or("");
+        let line_no = index + 1;
+
+        let transaction_date = parse_date(field(date_col))
+            .ok_or_else(|| invalid(format!("line {line_no}: invalid date '{}'", field(date_col))))?;
+        let amount = |col: usize| {
+            let value = field(col).replace(',', "");
+            value
+                .parse::<f64>()
+                .map(|_| value.clone())
+                .map_err(|_| invalid(format!("line {line_no}: invalid amount '{}'", field(col))))
+        };
+
+        entries.push(TdsEntry {
+            deductor_name: field(name_col).to_string(),
+            tan: field(tan_col).to_string(),
+            section: field(section_col).to_string(),
+            transaction_date,
+            amount_paid_inr: amount(amount_col)?,
+            tax_deducted_inr: amount(tax_col)?,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Match TDS entries against ledger income rows by date and amount
+///
+/// A row matches when it falls within a few days of the transaction date and its INR
+/// value is within 1% of either the gross amount or the amount net of TDS. Each row is
+/// matched at most once.
+pub fn reconcile_tds(
+    entries: &[TdsEntry],
+    ledger: &[LedgerRow],
+    prices: &[PriceEntry],
+    usd_inr_rate: &str,
+) -> TdsReconciliation {
+    let usd_inr_rate = UsdInrRates::flat(usd_inr_rate);
+    let window = MATCH_WINDOW_DAYS * 86_400;
+    let mut used = vec![false; ledger.len()];
+    let mut matched = Vec::with_capacity(entries.len());
+    let mut matched_count = 0;
+    let mut total: u64 = 0;
+    let mut unmatched: u64 = 0;
+
+    for entry in entries {
+        let gross = parse_hundredths(&entry.amount_paid_inr).unwrap_or(0);
+        let tax = parse_hundredths(&entry.tax_deducted_inr).unwrap_or(0);
+        let within_tolerance = |value: u64, expected: u64| value.abs_diff(expected) * 10_000 <= expected * MATCH_TOLERANCE_BPS;
+
+        let row_index = ledger.iter().enumerate().position(|(i, row)| {
+            !used[i]
+                && row.category == Category::Income
+                && row.direction == Direction::In
+                && row.block_time + window >= entry.transaction_date
+                && row.block_time <= entry.transaction_date + 86_400 + window
+                && {
+                    let value = amount_to_inr_paisa(&row.amount, &row.asset, row.block_time, prices, &usd_inr_rate);
+                    within_tolerance(value, gross) || within_tolerance(value, gross.saturating_sub(tax))
+                }
+        });
+
+        total += tax;
+        if let Some(i) = row_index {
+            used[i] = true;
+            matched_count += 1;
+        } else {
+            unmatched += tax;
+        }
+        matched.push(TdsMatch {
+            entry: entry.clone(),
+            tx_hash: row_index.map(|i| ledger[i].tx_hash.clone()),
+        });
+    }
+
+    TdsReconciliation {
+        entries: matched,
+        matched_count,
+        tds_credit_inr: format_paisa(total),
+        unmatched_tds_inr: format_paisa(unmatched),
+    }
+}
+
+/// Index of the first column whose header contains any of the names (case-insensitive)
+pub(crate) fn find_column(header: &[String], names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| {
+        header
+            .iter()
+            .position(|column| column.trim().to_lowercase().contains(name))
+    })
+}
+
+/// Split one CSV line, honouring double-quoted fields (with `""` escapes)
+pub(crate) fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(core::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Parse a 26AS/AIS date ("15-Apr-2025", "15/04/2025" or "2025-04-15") to midnight IST
+pub(crate) fn parse_date(value: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+    let parts: Vec<&str> = value.split(['-', '/']).collect();
+    let [a, b, c] = parts[..] else {
+        return None;
+    };
+    let (year, month, day) = if a.len() == 4 {
+        (a, b, c)
+    } else {
+        (c, b, a)
+    };
+
+    let year: u64 = year.parse().ok()?;
+    let day: u64 = day.parse().ok()?;
+    let month: u64 = match month.parse() {
+        Ok(month) => month,
+        Err(_) => MONTHS.iter().position(|m| month.eq_ignore_ascii_case(m))? as u64 + 1,
+    };
+    if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    Some((days_from_civil(year, month, day) * 86_400).saturating_sub(IST_OFFSET_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ledger_row;
+
+    const FORM_26AS: &str = "\
+Form 26AS - Annual Tax Statement
+PAN: ABCDE1234F
+
+Sr. No.,Name of Deductor,TAN of Deductor,Section,Transaction Date,Status of Booking,Amount Paid / Credited,Tax Deducted,TDS Deposited
+1,\"Acme Labs, Pvt Ltd\",BLRA12345B,194J,15-Jun-2025,F,\"1,00,000.00\",10000.00,10000.00
+2,Exchange Co,MUMB67890C,194S,20/07/2025,F,50000,500,500
+
+Part B - TCS
+";
+
+    fn income_row(tx_hash: &str, block_time: u64, amount: &str) -> LedgerRow {
+        LedgerRow {
+            tx_hash: tx_hash.to_string(),
+            block_time,
+            asset: "INR".to_string(),
+            amount: amount.to_string(),
+            decimals: 2,
+            category: Category::Income,
+            ..ledger_row()
+        }
+    }
+
+    #[test]
+    fn test_parse_form_26as_csv() {
+        let entries = parse_form_26as_csv(FORM_26AS).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].deductor_name, "Acme Labs, Pvt Ltd");
+        assert_eq!(entries[0].section, "194J");
+        assert_eq!(entries[0].amount_paid_inr, "100000.00");
+        // 2025-06-15 00:00 IST
+        assert_eq!(entries[0].transaction_date, 1_749_925_800);
+        assert_eq!(entries[1].tax_deducted_inr, "500");
+    }
+
+    #[test]
+    fn test_parse_rejects_missing_header() {
+        assert!(matches!(
+            parse_form_26as_csv("a,b,c\n1,2,3"),
+            Err(TaxError::InvalidTdsImport(_))
+        ));
+    }
+
+    #[test]
+    fn test_reconcile_matches_gross_and_net_amounts() {
+        let entries = parse_form_26as_csv(FORM_26AS).unwrap();
+        let ledger = vec![
+            // Net of 10% TDS, a day after the transaction date
+            income_row("0x1", 1_750_000_000, "90000"),
+            // Outside the window for the second entry
+            income_row("0x2", 1_760_000_000, "50000"),
+        ];
+
+        let report = reconcile_tds(&entries, &ledger, &[], "1");
+
+        assert_eq!(report.matched_count, 1);
+        assert_eq!(report.entries[0].tx_hash.as_deref(), Some("0x1"));
+        assert_eq!(report.entries[1].tx_hash, None);
+        assert_eq!(report.tds_credit_inr, "10500.00");
+        assert_eq!(report.unmatched_tds_inr, "500.00");
+    }
+}
@@ -0,0 +1,30 @@
+//! Fixtures shared by the crate's tests
+
+use crate::prelude::*;
+use crate::{Category, Direction, LedgerRow};
+
+/// A 1 ETH receipt in June 2025 (FY 2025-26) with every optional field empty; tests set
+/// the fields they care about with `LedgerRow { amount: ..., ..ledger_row() }`
+pub(crate) fn ledger_row() -> LedgerRow {
+    LedgerRow {
+        chain_id: 1,
+        owner_wallet: "0xabc".into(),
+        tx_hash: "0x1".to_string(),
+        block_time: 1_750_000_000,
+        asset: "ETH".to_string(),
+        amount: "1".to_string(),
+        decimals: 18,
+        direction: Direction::In,
+        counterparty: None,
+        category: Category::Unknown,
+        confidence: 1.0,
+        user_override: false,
+        token_id: None,
+        quantity: None,
+        contract_address: None,
+        counterparty_label: None,
+        counterparty_ens: None,
+        model_confidence: None,
+        inr_value: None,
+    }
+}
@@ -0,0 +1,264 @@
+//! Validating tax input before calculating
+//!
+//! The calculation is lenient with what it's given: an amount that doesn't parse counts
+//! as zero, a USD/INR rate that doesn't parse falls back to 83, and an asset without a
+//! price is valued at $1. That suits ledgers already checked once, but a request that
+//! relies on any of it is almost certainly wrong, so callers validate input first and
+//! report every problem found rather than calculate tax on it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::reference_rates::UsdInrRates;
+use crate::tds::parse_date;
+use crate::{
+    checked_quantity_to_inr_paisa, parse_fixed, parse_hundredths, parse_quantity, Address, Category, PriceEntry,
+    TaxInput, PRICE_DECIMALS,
+};
+
+/// What's wrong with a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IssueCode {
+    /// More rows than the caller allows
+    LedgerTooLarge,
+    /// Not a non-negative decimal amount, or worth more paisa than fit in a `u64`
+    InvalidAmount,
+    /// Not a positive USD/INR rate
+    InvalidRate,
+    /// Not a non-negative USD price
+    InvalidPrice,
+    InvalidAddress,
+    /// Not a YYYY-MM-DD date
+    InvalidDate,
+    MissingAsset,
+    /// A taxable row of an asset with no price
+    PriceMissing,
+}
+
+/// A problem with one field of the input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// Path to the field, e.g. `ledger[3].amount`
+    pub field: String,
+    pub code: IssueCode,
+    pub message: String,
+}
+
+struct Issues(Vec<ValidationIssue>);
+
+impl Issues {
+    fn push(&mut self, field: String, code: IssueCode, message: String) {
+        self.0.push(ValidationIssue { field, code, message });
+    }
+
+    /// A non-negative decimal amount
+    fn amount(&mut self, field: String, value: &str) {
+        if parse_quantity(value).is_none() {
+            self.push(field, IssueCode::InvalidAmount, format!("not a non-negative decimal amount: {:?}", value));
+        }
+    }
+
+    /// A non-negative INR amount small enough to count in paisa
+    fn inr_amount(&mut self, field: String, value: &str) {
+        if parse_quantity(value).is_none() {
+            self.amount(field, value);
+        } else if parse_hundredths(value).is_none() {
+            self.push(field, IssueCode::InvalidAmount, format!("too large an INR amount: {:?}", value));
+        }
+    }
+
+    fn address(&mut self, field: String, address: &Address) {
+        if let Err(e) = Address::parse(address.as_str()) {
+            self.push(field, IssueCode::InvalidAddress, e.to_string());
+        }
+    }
+
+    /// A positive INR per USD rate
+    fn usd_inr(&mut self, field: String, value: &str) {
+        if parse_hundredths(value).unwrap_or(0) == 0 {
+            self.push(field, IssueCode::InvalidRate, format!("not a positive USD/INR rate: {:?}", value));
+        }
+    }
+
+    fn date(&mut self, field: String, value: &str) {
+        let bytes = value.as_bytes();
+        if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' || parse_date(value).is_none() {
+            self.push(field, IssueCode::InvalidDate, format!("not a YYYY-MM-DD date: {:?}", value));
+        }
+    }
+}
+
+/// Whether a row's value is taxed or becomes a cost, and so needs a price
+fn needs_price(category: Category) -> bool {
+    !matches!(
+        category,
+        Category::Internal | Category::Spam | Category::NonTaxable | Category::ExchangeTransfer
+    )
+}
+
+/// Every problem with `input`: malformed, negative or oversized amounts and rates, bad
+/// addresses, prices that don't parse, taxable rows of assets without a price, and a
+/// ledger of more than `max_rows` rows
+pub fn validate_input(input: &TaxInput, max_rows: usize) -> Vec<ValidationIssue> {
+    let mut issues = Issues(Vec::new());
+    if input.ledger.len() > max_rows {
+        let message = format!("{} rows, more than the limit of {}", input.ledger.len(), max_rows);
+        issues.push("ledger".to_string(), IssueCode::LedgerTooLarge, message);
+    }
+    issues.usd_inr("usd_inr_rate".to_string(), &input.usd_inr_rate);
+    let usd_inr_rate = UsdInrRates::new(&input.usd_inr_rate, &input.reference_rates);
+
+    for (i, wallet) in input.wallets.iter().enumerate() {
+        issues.address(format!("wallets[{}].address", i), &wallet.address);
+    }
+
+    for (i, price) in input.prices.iter().enumerate() {
+        if price.asset.trim().is_empty() {
+            issues.push(format!("prices[{}].asset", i), IssueCode::MissingAsset, "empty asset".to_string());
+        }
+        if parse_fixed(&price.usd_price, PRICE_DECIMALS).is_none() {
+            let message = format!("not a non-negative USD price: {:?}", price.usd_price);
+            issues.push(format!("prices[{}].usd_price", i), IssueCode::InvalidPrice, message);
+        }
+        if let Some(date) = &price.date {
+            issues.date(format!("prices[{}].date", i), date);
+        }
+    }
+
+    for (i, row) in input.ledger.iter().enumerate() {
+        let field = |name: &str| format!("ledger[{}].{}", i, name);
+        match parse_quantity(&row.amount) {
+            None => issues.amount(field("amount"), &row.amount),
+            Some(quantity) => {
+                if checked_quantity_to_inr_paisa(quantity, &row.asset, row.block_time, &input.prices, &usd_inr_rate)
+                    .is_none()
+                {
+                    let message = format!("{} {} is worth too much to count in paisa", row.amount, row.asset);
+                    issues.push(field("amount"), IssueCode::InvalidAmount, message);
+                }
+            }
+        }
+        issues.address(field("owner_wallet"), &row.owner_wallet);
+        if let Some(counterparty) = &row.counterparty {
+            issues.address(field("counterparty"), counterparty);
+        }
+        if row.asset.trim().is_empty() {
+            issues.push(field("asset"), IssueCode::MissingAsset, "empty asset".to_string());
+        } else if row.inr_value.is_none()
+            && row.token_id.is_none()
+            && needs_price(row.category)
+            && parse_quantity(&row.amount).is_some_and(|amount| amount > 0)
+            && !has_price(&input.prices, &row.asset)
+        {
+            issues.push(field("asset"), IssueCode::PriceMissing, format!("no price for {}", row.asset));
+        }
+    }
+
+    for (i, lot) in input.acquisition_lots.iter().enumerate() {
+        issues.amount(format!("acquisition_lots[{}].amount", i), &lot.amount);
+        issues.inr_amount(format!("acquisition_lots[{}].cost_inr", i), &lot.cost_inr);
+    }
+    for (i, entry) in input.manual_income.iter().enumerate() {
+        issues.inr_amount(format!("manual_income[{}].amount_inr", i), &entry.amount_inr);
+    }
+    for (i, entry) in input.tds_entries.iter().enumerate() {
+        issues.inr_amount(format!("tds_entries[{}].amount_paid_inr", i), &entry.amount_paid_inr);
+        issues.inr_amount(format!("tds_entries[{}].tax_deducted_inr", i), &entry.tax_deducted_inr);
+    }
+    for (i, rate) in input.reference_rates.iter().enumerate() {
+        issues.date(format!("reference_rates[{}].date", i), &rate.date);
+        issues.usd_inr(format!("reference_rates[{}].usd_inr", i), &rate.usd_inr);
+    }
+    issues.0
+}
+
+/// Whether `asset` is priced (rows are priced by exact symbol)
+fn has_price(prices: &[PriceEntry], asset: &str) -> bool {
+    prices.iter().any(|price| price.asset == asset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_problem_is_reported() {
+        let mut input: TaxInput = serde_json::from_value(serde_json::json!({
+            "user_type": "individual",
+            "wallets": [],
+            "ledger": [],
+            "prices": [{ "asset": "ETH", "usd_price": "3000", "date": "2025-06-01", "user_override": false }],
+            "usd_inr_rate": "83.5",
+            "use_44ada": false,
+        }))
+        .unwrap();
+        let row = |asset: &str, amount: &str, category: &str| {
+            serde_json::from_value(serde_json::json!({
+                "chain_id": 1,
+                "owner_wallet": "0x1111111111111111111111111111111111111111",
+                "tx_hash": "0x01",
+                "block_time": 1_748_736_000,
+                "asset": asset,
+                "amount": amount,
+                "decimals": 18,
+                "direction": "in",
+                "counterparty": null,
+                "category": category,
+                "confidence": 1.0,
+                "user_override": false,
+            }))
+            .unwrap()
+        };
+        input.ledger = vec![row("ETH", "1.5", "income"), row("PEPE", "10", "internal")];
+        assert!(validate_input(&input, 10).is_empty());
+
+        input.ledger.push(row("PEPE", "10", "income"));
+        input.ledger.push(row("ETH", "-1", "income"));
+        input.usd_inr_rate = "0".to_string();
+        input.prices[0].date = Some("01/06/2025".to_string());
+        let issues = validate_input(&input, 3);
+        let fields: Vec<&str> = issues.iter().map(|issue| issue.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["ledger", "usd_inr_rate", "prices[0].date", "ledger[2].asset", "ledger[3].amount"]
+        );
+        assert_eq!(issues[3].code, IssueCode::PriceMissing);
+    }
+
+    #[test]
+    fn test_amounts_worth_more_than_u64_paisa_are_invalid() {
+        let mut input: TaxInput = serde_json::from_value(serde_json::json!({
+            "user_type": "individual",
+            "wallets": [],
+            "ledger": [{
+                "chain_id": 1,
+                "owner_wallet": "0x1111111111111111111111111111111111111111",
+                "tx_hash": "0x01",
+                "block_time": 1_748_736_000,
+                "asset": "ETH",
+                "amount": "100000000000",
+                "decimals": 18,
+                "direction": "in",
+                "counterparty": null,
+                "category": "income",
+                "confidence": 1.0,
+                "user_override": false,
+            }],
+            "prices": [{ "asset": "ETH", "usd_price": "3000", "date": null, "user_override": false }],
+            "usd_inr_rate": "83.5",
+            "use_44ada": false,
+            "manual_income": [{ "source": "other", "amount_inr": "100000000000000000" }],
+        }))
+        .unwrap();
+        // ₹2.5 * 10^16 and ₹10^17 fit in u64 paisa (about ₹1.8 * 10^17)
+        assert!(validate_input(&input, 10).is_empty());
+
+        input.ledger[0].amount = "10000000000000".to_string();
+        input.manual_income[0].amount_inr = "1000000000000000000".to_string();
+        let issues = validate_input(&input, 10);
+        let fields: Vec<&str> = issues.iter().map(|issue| issue.field.as_str()).collect();
+        assert_eq!(fields, ["ledger[0].amount", "manual_income[0].amount_inr"]);
+        assert!(issues.iter().all(|issue| issue.code == IssueCode::InvalidAmount));
+    }
+}
@@ -0,0 +1,132 @@
+//! Valuing ledger rows in INR
+//!
+//! Every row is valued once, on the day it happened: income, gifts, airdrops and staking
+//! rewards at their fair market value on receipt, and disposals at their sale value. The
+//! calculators (and the zkVM program) then read `inr_value` instead of repricing rows.
+//! The zkVM program first checks every value it's given against the prices and rates it
+//! commits to (`misvalued_row`), so those commitments constrain the proved tax.
+//!
+//! What was received at FMV is also what it cost: those receipts become acquisition lots,
+//! so a later disposal of the same tokens is only taxed on the difference between its
+//! sale value and the value already taxed on receipt.
+
+use crate::prelude::*;
+use crate::reference_rates::UsdInrRates;
+use crate::{
+    amount_to_inr_paisa, format_paisa, AcquisitionLot, Category, Direction, LedgerRow, PriceEntry, ReferenceRate,
+    TaxInput,
+};
+
+/// Set the INR value (paisa) of every row from the asset's USD price and the USD/INR
+/// rate of its day
+pub fn value_ledger(
+    ledger: &mut [LedgerRow],
+    prices: &[PriceEntry],
+    usd_inr_rate: &str,
+    reference_rates: &[ReferenceRate],
+) {
+    let usd_inr_rate = UsdInrRates::new(usd_inr_rate, reference_rates);
+    for row in ledger {
+        row.inr_value = Some(amount_to_inr_paisa(&row.amount, &row.asset, row.block_time, prices, &usd_inr_rate));
+    }
+}
+
+/// Index of the first row valued at other than its asset's price and the USD/INR rate of
+/// its day, as `value_ledger` would value it; rows without a value are priced when taxed
+pub fn misvalued_row(input: &TaxInput) -> Option<usize> {
+    let usd_inr_rate = UsdInrRates::new(&input.usd_inr_rate, &input.reference_rates);
+    input.ledger.iter().position(|row| {
+        row.inr_value.is_some_and(|value| {
+            value != amount_to_inr_paisa(&row.amount, &row.asset, row.block_time, &input.prices, &usd_inr_rate)
+        })
+    })
+}
+
+/// INR value (paisa) of a row: its own if it was valued, else priced now
+pub(crate) fn row_value(row: &LedgerRow, prices: &[PriceEntry], usd_inr_rate: &UsdInrRates) -> u64 {
+    row.inr_value
+        .unwrap_or_else(|| amount_to_inr_paisa(&row.amount, &row.asset, row.block_time, prices, usd_inr_rate))
+}
+
+/// Whether a row is a receipt taxed at FMV, whose value becomes the tokens' cost
+fn received_at_fmv(row: &LedgerRow) -> bool {
+    row.direction == Direction::In
+        && matches!(
+            row.category,
+            Category::Income | Category::Gift | Category::Airdrop | Category::StakingReward
+        )
+}
+
+/// The given acquisition lots plus one lot per receipt taxed at FMV, in order of
+/// acquisition
+pub(crate) fn lots_with_receipts(
+    lots: &[AcquisitionLot],
+    ledger: &[LedgerRow],
+    prices: &[PriceEntry],
+    usd_inr_rate: &UsdInrRates,
+) -> Vec<AcquisitionLot> {
+    let mut lots = lots.to_vec();
+    lots.extend(ledger.iter().filter(|row| received_at_fmv(row)).map(|row| AcquisitionLot {
+        asset: row.asset.clone(),
+        amount: row.amount.clone(),
+        cost_inr: format_paisa(row_value(row, prices, usd_inr_rate)),
+        acquired_at: row.block_time,
+    }));
+    lots.sort_by_key(|lot| lot.acquired_at);
+    lots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ledger_row;
+
+    #[test]
+    fn test_receipts_valued_on_their_day_become_lots() {
+        let row = |tx_hash: &str, block_time: u64, category: Category| LedgerRow {
+            owner_wallet: "0xowner".into(),
+            tx_hash: tx_hash.to_string(),
+            block_time,
+            category,
+            ..ledger_row()
+        };
+        let price = |usd_price: &str, date: &str| PriceEntry {
+            asset: "ETH".to_string(),
+            usd_price: usd_price.to_string(),
+            date: Some(date.to_string()),
+            user_override: false,
+        };
+        // Sold on 2025-06-15, then paid on 2025-09-01
+        let mut ledger = vec![
+            row("0xsale", 1_749_990_000, Category::Gains),
+            row("0xincome", 1_756_720_000, Category::Income),
+        ];
+        let prices = [price("2000", "2025-06-15"), price("2500", "2025-09-01")];
+
+        value_ledger(&mut ledger, &prices, "100", &[]);
+        assert_eq!(ledger[0].inr_value, Some(20_000_000));
+        assert_eq!(ledger[1].inr_value, Some(25_000_000));
+
+        // A value other than the priced one is caught
+        let mut input: TaxInput = serde_json::from_value(serde_json::json!({
+            "user_type": "individual",
+            "wallets": [],
+            "ledger": [],
+            "prices": [],
+            "usd_inr_rate": "100",
+            "use_44ada": false,
+        }))
+        .unwrap();
+        input.ledger = ledger.clone();
+        input.prices = prices.to_vec();
+        assert_eq!(misvalued_row(&input), None);
+        input.ledger[1].inr_value = Some(1);
+        assert_eq!(misvalued_row(&input), Some(1));
+
+        // Only the income is a lot, at its value on receipt
+        let lots = lots_with_receipts(&[], &ledger, &[], &UsdInrRates::flat("83"));
+        assert_eq!(lots.len(), 1);
+        assert_eq!(lots[0].cost_inr, "250000.00");
+        assert_eq!(lots[0].acquired_at, 1_756_720_000);
+    }
+}
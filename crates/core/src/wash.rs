@@ -0,0 +1,215 @@
+//! Self-trade (wash transfer) detection
+//!
+//! Wallets in the same group trading with each other through an intermediary (a DEX
+//! pool, an OTC contract) can manufacture gains or losses without any change in who
+//! owns the funds. A hop is an outflow from one of the group's wallets to an outside
+//! counterparty followed, within a window, by an inflow from that same counterparty to
+//! another wallet of the group. Hops through one intermediary whose wallets form a cycle
+//! (A → B → A, A → B → C → A) are reported as a suspect chain, and their rows get a low
+//! confidence so they show up in the review queue. Categories are left unchanged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::{Address, Direction, LedgerRow, Wallet};
+
+/// An inflow must follow the outflow within this window to form a hop (seconds)
+pub const WASH_WINDOW_SECS: u64 = 86_400;
+
+/// Confidence given to rows in a suspect chain, below the review threshold
+const WASH_CONFIDENCE: f32 = 0.3;
+
+/// Transactions through one intermediary that move funds in a circle between a group's wallets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WashChain {
+    pub group_id: Option<String>,
+    /// Counterparty the funds were routed through
+    pub intermediary: String,
+    /// Wallets of the group involved, in order of first appearance
+    pub wallets: Vec<String>,
+    /// Transactions of the chain in time order
+    pub tx_hashes: Vec<String>,
+}
+
+/// Outflow and inflow row indices of a hop from one wallet to another via an intermediary
+struct Hop {
+    out: usize,
+    into: usize,
+}
+
+/// Find circular fund flows within each wallet group and lower the confidence of their
+/// rows (user overrides are left alone); returns the suspect chains
+///
+/// Rows are assigned to groups through `Wallet.group_id` of their owner wallet; wallets
+/// without a group form one group together. Rows of wallets not in `wallets` are ignored.
+pub fn flag_wash_transfers(ledger: &mut [LedgerRow], wallets: &[Wallet]) -> Vec<WashChain> {
+    let wallet_of = |address: &Address| wallets.iter().find(|wallet| wallet.address == *address);
+
+    let mut order: Vec<usize> = (0..ledger.len()).collect();
+    order.sort_by_key(|&i| ledger[i].block_time);
+    let mut used = vec![false; ledger.len()];
+
+    // Pair each outflow to an outside counterparty with the first later inflow from it
+    // into another wallet of the same group
+    let mut hops: Vec<Hop> = Vec::new();
+    for (position, &out) in order.iter().enumerate() {
+        let sent = &ledger[out];
+        let (Some(sender), Some(intermediary)) = (wallet_of(&sent.owner_wallet), sent.counterparty.as_ref()) else {
+            continue;
+        };
+        if sent.direction != Direction::Out || wallet_of(intermediary).is_some() {
+            continue;
+        }
+
+        let into = order[position + 1..].iter().copied().find(|&i| {
+            let row = &ledger[i];
+            !used[i]
+                && row.direction == Direction::In
+                && row.block_time - sent.block_time <= WASH_WINDOW_SECS
+                && row.owner_wallet != sent.owner_wallet
+                && row.counterparty.as_ref() == Some(intermediary)
+                && wallet_of(&row.owner_wallet).is_some_and(|receiver| receiver.group_id == sender.group_id)
+        });
+        if let Some(into) = into {
+            used[into] = true;
+            hops.push(Hop { out, into });
+        }
+    }
+
+    // Hops through the same intermediary within the same group
+    let mut routes: BTreeMap<(Option<String>, String), Vec<Hop>> = BTreeMap::new();
+    for hop in hops {
+        let group_id = wallet_of(&ledger[hop.out].owner_wallet).and_then(|wallet| wallet.group_id.clone());
+        let intermediary = ledger[hop.out].counterparty.clone().unwrap_or_default().to_string();
+        routes.entry((group_id, intermediary)).or_default().push(hop);
+    }
+
+    let mut chains = Vec::new();
+    for ((group_id, intermediary), route_hops) in routes {
+        let edges: Vec<(String, String)> = route_hops
+            .iter()
+            .map(|hop| {
+                (ledger[hop.out].owner_wallet.to_string(), ledger[hop.into].owner_wallet.to_string())
+            })
+            .collect();
+        if !has_cycle(&edges) {
+            continue;
+        }
+
+        let mut rows: Vec<usize> = route_hops.iter().flat_map(|hop| [hop.out, hop.into]).collect();
+        rows.sort_by_key(|&i| ledger[i].block_time);
+
+        let mut chain_wallets: Vec<String> = Vec::new();
+        let mut tx_hashes: Vec<String> = Vec::new();
+        for &i in &rows {
+            let row = &mut ledger[i];
+            let wallet = row.owner_wallet.to_string();
+            if !chain_wallets.contains(&wallet) {
+                chain_wallets.push(wallet);
+            }
+            if !tx_hashes.contains(&row.tx_hash) {
+                tx_hashes.push(row.tx_hash.clone());
+            }
+            if !row.user_override {
+                row.confidence = row.confidence.min(WASH_CONFIDENCE);
+            }
+        }
+
+        chains.push(WashChain {
+            group_id,
+            intermediary,
+            wallets: chain_wallets,
+            tx_hashes,
+        });
+    }
+    chains
+}
+
+/// Whether the directed graph given by its edges has a cycle
+fn has_cycle(edges: &[(String, String)]) -> bool {
+    // Repeatedly drop edges leaving nodes nothing flows into; whatever remains lies on a cycle
+    let mut remaining: Vec<&(String, String)> = edges.iter().collect();
+    loop {
+        let before = remaining.len();
+        let targets: Vec<String> = remaining.iter().map(|(_, to)| to.clone()).collect();
+        remaining.retain(|(from, _)| targets.contains(from));
+        if remaining.is_empty() {
+            return false;
+        }
+        if remaining.len() == before {
+            return true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Category, WalletSource};
+    use crate::testing::ledger_row;
+
+    fn wallet(address: &str, group_id: Option<&str>) -> Wallet {
+        Wallet {
+            id: address.to_string(),
+            address: address.into(),
+            label: None,
+            group_id: group_id.map(str::to_string),
+            source: WalletSource::Manual,
+            ownership_signature: None,
+        }
+    }
+
+    fn row(owner: &str, tx_hash: &str, direction: Direction, block_time: u64) -> LedgerRow {
+        LedgerRow {
+            owner_wallet: owner.into(),
+            tx_hash: tx_hash.to_string(),
+            block_time,
+            asset: "USDC".to_string(),
+            amount: "1000".to_string(),
+            decimals: 6,
+            direction,
+            counterparty: Some("0xpool".into()),
+            category: Category::Gains,
+            confidence: 0.9,
+            ..ledger_row()
+        }
+    }
+
+    #[test]
+    fn test_round_trip_through_pool_flagged() {
+        let wallets = vec![wallet("0xa", Some("family")), wallet("0xb", Some("family"))];
+        let mut ledger = vec![
+            row("0xa", "0x1", Direction::Out, 100),
+            row("0xb", "0x2", Direction::In, 200),
+            row("0xb", "0x3", Direction::Out, 300),
+            row("0xa", "0x4", Direction::In, 400),
+        ];
+
+        let chains = flag_wash_transfers(&mut ledger, &wallets);
+
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].group_id.as_deref(), Some("family"));
+        assert_eq!(chains[0].wallets, vec!["0xa", "0xb"]);
+        assert_eq!(chains[0].tx_hashes, vec!["0x1", "0x2", "0x3", "0x4"]);
+        assert!(ledger.iter().all(|row| row.confidence == WASH_CONFIDENCE));
+        assert!(ledger.iter().all(|row| row.category == Category::Gains));
+    }
+
+    #[test]
+    fn test_one_way_or_cross_group_flow_not_flagged() {
+        // Funds moving one way are a transfer, not a round trip
+        let wallets = vec![wallet("0xa", Some("family")), wallet("0xb", Some("family"))];
+        let mut ledger = vec![row("0xa", "0x1", Direction::Out, 100), row("0xb", "0x2", Direction::In, 200)];
+        assert!(flag_wash_transfers(&mut ledger, &wallets).is_empty());
+        assert_eq!(ledger[0].confidence, 0.9);
+
+        let wallets = vec![wallet("0xa", Some("family")), wallet("0xb", Some("business"))];
+        let mut ledger = vec![
+            row("0xa", "0x1", Direction::Out, 100),
+            row("0xb", "0x2", Direction::In, 200),
+            row("0xb", "0x3", Direction::Out, 300),
+            row("0xa", "0x4", Direction::In, 400),
+        ];
+        assert!(flag_wash_transfers(&mut ledger, &wallets).is_empty());
+    }
+}
@@ -1,7 +1,10 @@
 //! Simple CLI to test proof generation and verification locally
 
-use financoor_core::{Category, Direction, LedgerRow, PriceEntry, TaxInput, UserType};
-use financoor_prover::TaxProver;
+use financoor_core::{
+    Category, CorporateRegime, Deductions, Direction, LedgerRow, LossCarryForward, PriceEntry, ResidentialStatus,
+    TaxInput, TaxRegime, UserType, DEFAULT_ASSESSMENT_YEAR,
+};
+use financoor_prover::{ProofMode, ProvingBackend, TaxProver};
 
 fn main() -> anyhow::Result<()> {
     // Initialize logging
@@ -18,39 +21,72 @@ fn main() -> anyhow::Result<()> {
         ledger: vec![
             LedgerRow {
                 chain_id: 11155111, // Sepolia
-                owner_wallet: "0x1234...".to_string(),
+                owner_wallet: "0x1234...".into(),
                 tx_hash: "0xabc123...".to_string(),
-                block_time: 1700000000,
+                block_time: 1750000000,
                 asset: "ETH".to_string(),
                 amount: "1.5".to_string(),
                 decimals: 18,
                 direction: Direction::In,
-                counterparty: Some("0x5678...".to_string()),
+                counterparty: Some("0x5678...".into()),
                 category: Category::Income,
                 confidence: 0.95,
                 user_override: false,
+                token_id: None,
+                quantity: None,
+                contract_address: None,
+                counterparty_label: None,
+                counterparty_ens: None,
+                model_confidence: None,
+                inr_value: None,
             },
             LedgerRow {
                 chain_id: 11155111,
-                owner_wallet: "0x1234...".to_string(),
+                owner_wallet: "0x1234...".into(),
                 tx_hash: "0xdef456...".to_string(),
-                block_time: 1700100000,
+                block_time: 1750100000,
                 asset: "ETH".to_string(),
                 amount: "0.5".to_string(),
                 decimals: 18,
                 direction: Direction::In,
-                counterparty: Some("0x9abc...".to_string()),
+                counterparty: Some("0x9abc...".into()),
                 category: Category::Gains,
                 confidence: 0.90,
                 user_override: false,
+                token_id: None,
+                quantity: None,
+                contract_address: None,
+                counterparty_label: None,
+                counterparty_ens: None,
+                model_confidence: None,
+                inr_value: None,
             },
         ],
         prices: vec![PriceEntry {
             asset: "ETH".to_string(),
             usd_price: "2000.00".to_string(),
+            date: None,
+            user_override: false,
         }],
         usd_inr_rate: "83.00".to_string(),
         use_44ada: false,
+        digital_receipts_95pct: true,
+        regime: TaxRegime::New,
+        deductions: Deductions::default(),
+        assessment_year: DEFAULT_ASSESSMENT_YEAR,
+        acquisition_lots: vec![],
+        corporate_regime: CorporateRegime::default(),
+        capital_assets: vec![],
+        manual_income: vec![],
+        tds_entries: vec![],
+        residential_status: ResidentialStatus::default(),
+        foreign_source_tx_hashes: vec![],
+        filing_date: None,
+        member_transferred_wallets: vec![],
+        gst: None,
+        brought_forward_losses: LossCarryForward::default(),
+        reference_rates: vec![],
+        nonce: [0; 32],
     };
 
     // Create prover
@@ -59,12 +95,13 @@ fn main() -> anyhow::Result<()> {
 
     // Print VK hash
     println!("VK Hash: {}", prover.get_vk_hash());
+    println!("VK Digest: {}", prover.get_vk_digest());
     println!();
 
     // Generate proof
     println!("Generating proof (this may take a while in CPU mode)...");
     let start = std::time::Instant::now();
-    let artifacts = prover.prove(&input)?;
+    let artifacts = prover.prove(&input, None, ProofMode::Groth16, ProvingBackend::Local)?;
     let elapsed = start.elapsed();
 
     println!("\n=== Proof Generated ===");
@@ -2,40 +2,669 @@
 //!
 //! This crate handles setting up the SP1 prover and generating proofs
 //! for tax calculations.
+//!
+//! Proofs are generated locally (the prover `SP1_PROVER` picks, CPU by default) or, when it's
+//! configured, on the Succinct prover network, falling back to local proving if the network
+//! request fails.
+//!
+//! A large ledger can be proved in parts instead, each a compressed proof of the tax
+//! program, which the aggregation program then verifies and sums into a single proof.
+//! With a `PartCache`, parts proved before are reused, so only the parts a change touches
+//! are proved again.
+//!
+//! Given a `Disclosure`, the tax program commits only that statement about the figures
+//! (e.g. tax paid is at least some amount), for proofs shown to lenders.
+//!
+//! Artifacts proved elsewhere can be checked against this prover's keys with
+//! `TaxProver::verify`, and the version of the tax program they're from looked up in its
+//! `VkRegistry`.
+
+mod part_cache;
+pub mod vk_registry;
+
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use anyhow::Result;
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_sol_types::SolValue;
+use anyhow::{bail, Result};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde::{Deserialize, Serialize};
-use sp1_sdk::{include_elf, EnvProver, HashableKey, ProverClient, SP1ProofWithPublicValues, SP1ProvingKey, SP1Stdin, SP1VerifyingKey};
+use financoor_core::{
+    aggregate_public_values, domain_separator_bytes, AggregatedTaxPublicValues, DisclosedRange, Disclosure,
+    DisclosurePublicValues, TaxInput, TaxProofPublicValues, UserType, TAX_PROGRAM_VERSION,
+};
+use sp1_sdk::{
+    include_elf, EnvProver, HashableKey, NetworkProver, ProverClient, SP1Proof, SP1ProofMode, SP1ProofWithPublicValues,
+    SP1ProvingKey, SP1Stdin, SP1VerifyingKey,
+};
+
+pub use part_cache::{part_key, PartCache, DEFAULT_PART_PROOF_CACHE_DIR};
+pub use vk_registry::{ProgramVersion, VkRegistry};
 
 /// The ELF binary for the tax_zk SP1 program
 pub const TAX_ZK_ELF: &[u8] = include_elf!("tax-zk");
 
+/// The ELF binary for the tax_aggregate SP1 program
+pub const TAX_AGGREGATE_ELF: &[u8] = include_elf!("tax-aggregate");
+
+/// Kind of proof to generate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofMode {
+    /// STARK proof per shard: quickest to generate, largest, off-chain verification only
+    Core,
+    /// STARK compressed to a constant size: off-chain verification only
+    Compressed,
+    /// Groth16 SNARK of the compressed proof: cheapest to verify on-chain
+    #[default]
+    Groth16,
+    /// PLONK SNARK of the compressed proof: on-chain, without a circuit-specific setup
+    Plonk,
+}
+
+impl ProofMode {
+    /// Whether the proof can be submitted to `TaxVerifier`
+    pub fn on_chain(self) -> bool {
+        matches!(self, Self::Groth16 | Self::Plonk)
+    }
+
+    /// Rough time to prove `cycles` locally on a CPU, and then to wrap the proof
+    pub fn estimate(self, cycles: u64) -> ProvingEstimate {
+        let wrapping_secs = match self {
+            Self::Core => 0,
+            Self::Compressed => 30,
+            Self::Groth16 => 120,
+            Self::Plonk => 180,
+        };
+        ProvingEstimate {
+            proving: Duration::from_secs(cycles.div_ceil(LOCAL_CYCLES_PER_SEC).max(5)),
+            wrapping: Duration::from_secs(wrapping_secs),
+        }
+    }
+
+    fn sp1(self) -> SP1ProofMode {
+        match self {
+            Self::Core => SP1ProofMode::Core,
+            Self::Compressed => SP1ProofMode::Compressed,
+            Self::Groth16 => SP1ProofMode::Groth16,
+            Self::Plonk => SP1ProofMode::Plonk,
+        }
+    }
+}
+
+impl std::str::FromStr for ProofMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "core" => Ok(Self::Core),
+            "compressed" => Ok(Self::Compressed),
+            "groth16" => Ok(Self::Groth16),
+            "plonk" => Ok(Self::Plonk),
+            other => bail!("Unknown proof mode: {}", other),
+        }
+    }
+}
+
+/// Cycles a CPU proves per second, roughly, for estimates
+const LOCAL_CYCLES_PER_SEC: u64 = 500_000;
+
+/// Rough proving time, from the cycle count of an execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProvingEstimate {
+    /// Proving the execution (core STARK)
+    pub proving: Duration,
+    /// Compressing and wrapping the STARK into the requested kind of proof
+    pub wrapping: Duration,
+}
+
+/// Result of executing the program without proving it
+#[derive(Debug, Clone)]
+pub struct Execution {
+    /// ABI-encoded public values (`TaxProofPublicValues`)
+    pub public_values: Vec<u8>,
+    /// Instructions executed
+    pub cycles: u64,
+}
+
+/// Where a proof is generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvingBackend {
+    /// On this machine
+    Local,
+    /// On the Succinct prover network
+    Network,
+}
+
+/// How long to wait for the prover network to fulfil a request unless configured otherwise
+pub const DEFAULT_NETWORK_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+
+/// Succinct prover network settings
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    /// Private key of the requester account
+    pub private_key: String,
+    /// Network RPC endpoint; the SDK's default when None
+    pub rpc_url: Option<String>,
+    /// How long to wait for a request to be fulfilled before proving locally instead
+    pub timeout: Duration,
+}
+
+impl NetworkConfig {
+    /// `NETWORK_PRIVATE_KEY`, `NETWORK_RPC_URL` and `NETWORK_TIMEOUT_SECS`; None without a key
+    pub fn from_env() -> Option<Self> {
+        let private_key = std::env::var("NETWORK_PRIVATE_KEY").ok().filter(|key| !key.is_empty())?;
+        Some(Self {
+            private_key,
+            rpc_url: std::env::var("NETWORK_RPC_URL").ok().filter(|url| !url.is_empty()),
+            timeout: std::env::var("NETWORK_TIMEOUT_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .map_or(DEFAULT_NETWORK_TIMEOUT, Duration::from_secs),
+        })
+    }
+}
+
 /// Proof artifacts returned after proving
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofArtifacts {
-    /// The proof bytes (base64 encoded)
+    /// The proof (base64 encoded): the bytes `TaxVerifier` takes for on-chain modes, else the
+    /// bincode-serialized `SP1ProofWithPublicValues` for `sp1_sdk` to verify
     pub proof: String,
+    /// For on-chain modes, the bincode-serialized `SP1ProofWithPublicValues` too (base64
+    /// encoded), so the proof can be verified off-chain (`TaxProver::verify`); else empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sp1_proof: String,
+    /// Kind of proof generated
+    pub mode: ProofMode,
+    /// Where the proof was generated (local after a network fallback)
+    pub backend: ProvingBackend,
     /// Public values committed by the program (base64 encoded)
     pub public_values: String,
     /// Verification key hash (hex encoded)
     pub vk_hash: String,
-    /// Total tax in paisa (extracted from public values)
+    /// Total tax in paisa (extracted from public values); the figures are zero for a
+    /// selective-disclosure proof, which doesn't commit them
     pub total_tax_paisa: u64,
-    /// Ledger commitment hash (hex encoded)
+    /// Ledger commitment hash (hex encoded); for an aggregate, the digest of its parts'
+    /// public values
     pub ledger_commitment: String,
+    /// Hash of the reference rate table the ledger was converted at (hex encoded)
+    pub rate_table_commitment: String,
+    /// Hash of the canonical price table the ledger was valued at (hex encoded)
+    pub prices_commitment: String,
+    /// Professional income in paisa (extracted from public values)
+    pub professional_income_paisa: u64,
+    /// VDA gains in paisa (extracted from public values)
+    pub vda_gains_paisa: u64,
+    /// VDA tax in paisa (extracted from public values)
+    pub vda_tax_paisa: u64,
+    /// Cess in paisa (extracted from public values)
+    pub cess_paisa: u64,
+    /// Hash of the wallets proven owned by their signatures (hex encoded; empty for an aggregate)
+    #[serde(default)]
+    pub owned_wallets_commitment: String,
+    /// Whether the wallets proven owned cover every wallet the ledger has rows for
+    #[serde(default)]
+    pub all_wallets_owned: bool,
+    /// Nonce the proof commits (hex encoded; empty for an aggregate)
+    #[serde(default)]
+    pub nonce: String,
+    /// Domain separator naming the tax program version (hex encoded; empty for an aggregate)
+    #[serde(default)]
+    pub domain_separator: String,
+    /// Public values of the parts an aggregate proof verified (base64 encoded), in order;
+    /// empty for a proof of a single ledger
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<String>,
+    /// What a selective-disclosure proof states in place of the figures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disclosed: Option<DisclosedRange>,
+    /// The public values, decoded; the fields above are taken from them (None for proofs
+    /// from before they were kept)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodedPublicValues>,
+}
+
+/// Public values as the kind of proof they're from commits them, with hashes hex encoded
+/// and amounts in paisa
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DecodedPublicValues {
+    /// `TaxProofPublicValues`: the tax on a ledger
+    Tax {
+        ledger_commitment: String,
+        /// None for a code this version doesn't know
+        user_type: Option<UserType>,
+        used_44ada: bool,
+        assessment_year: u16,
+        fy_start: u64,
+        fy_end: u64,
+        rate_table_commitment: String,
+        prices_commitment: String,
+        total_tax_paisa: u64,
+        professional_income_paisa: u64,
+        vda_gains_paisa: u64,
+        vda_tax_paisa: u64,
+        cess_paisa: u64,
+        owned_wallets_commitment: String,
+        all_wallets_owned: bool,
+        nonce: String,
+        domain_separator: String,
+    },
+    /// `DisclosurePublicValues`: a statement about a ledger's tax or income
+    Disclosure {
+        ledger_commitment: String,
+        user_type: Option<UserType>,
+        assessment_year: u16,
+        fy_start: u64,
+        fy_end: u64,
+        rate_table_commitment: String,
+        prices_commitment: String,
+        disclosed: DisclosedRange,
+        owned_wallets_commitment: String,
+        all_wallets_owned: bool,
+        nonce: String,
+        domain_separator: String,
+    },
+    /// `AggregatedTaxPublicValues`: the combined tax of a ledger proved in parts
+    Aggregate {
+        tax_program_vkey: String,
+        parts_digest: String,
+        part_count: u32,
+        total_tax_paisa: u64,
+        professional_income_paisa: u64,
+        vda_gains_paisa: u64,
+        vda_tax_paisa: u64,
+        cess_paisa: u64,
+        all_wallets_owned: bool,
+    },
+}
+
+/// User type of its code in the public values
+fn user_type_of_code(code: u8) -> Option<UserType> {
+    match code {
+        0 => Some(UserType::Individual),
+        1 => Some(UserType::Huf),
+        2 => Some(UserType::Corporate),
+        _ => None,
+    }
+}
+
+impl DecodedPublicValues {
+    /// Decode a tax proof's public values
+    pub fn tax(public_values: &[u8]) -> Result<Self> {
+        let values = <TaxProofPublicValues as alloy_sol_types::SolType>::abi_decode(public_values)?;
+        Ok(Self::Tax {
+            ledger_commitment: hex::encode(values.ledgerCommitment),
+            user_type: user_type_of_code(values.userType),
+            used_44ada: values.used44ada,
+            assessment_year: values.assessmentYear,
+            fy_start: values.fyStart,
+            fy_end: values.fyEnd,
+            rate_table_commitment: hex::encode(values.rateTableCommitment),
+            prices_commitment: hex::encode(values.pricesCommitment),
+            total_tax_paisa: values.totalTaxPaisa.saturating_to(),
+            professional_income_paisa: values.professionalIncomePaisa.saturating_to(),
+            vda_gains_paisa: values.vdaGainsPaisa.saturating_to(),
+            vda_tax_paisa: values.vdaTaxPaisa.saturating_to(),
+            cess_paisa: values.cessPaisa.saturating_to(),
+            owned_wallets_commitment: hex::encode(values.ownedWalletsCommitment),
+            all_wallets_owned: values.allWalletsOwned,
+            nonce: hex::encode(values.nonce),
+            domain_separator: hex::encode(values.domainSeparator),
+        })
+    }
+
+    /// Decode a selective-disclosure proof's public values
+    pub fn disclosure(public_values: &[u8]) -> Result<Self> {
+        let values = <DisclosurePublicValues as alloy_sol_types::SolType>::abi_decode(public_values)?;
+        Ok(Self::Disclosure {
+            ledger_commitment: hex::encode(values.ledgerCommitment),
+            user_type: user_type_of_code(values.userType),
+            assessment_year: values.assessmentYear,
+            fy_start: values.fyStart,
+            fy_end: values.fyEnd,
+            rate_table_commitment: hex::encode(values.rateTableCommitment),
+            prices_commitment: hex::encode(values.pricesCommitment),
+            disclosed: DisclosedRange::from_public_values(&values)?,
+            owned_wallets_commitment: hex::encode(values.ownedWalletsCommitment),
+            all_wallets_owned: values.allWalletsOwned,
+            nonce: hex::encode(values.nonce),
+            domain_separator: hex::encode(values.domainSeparator),
+        })
+    }
+
+    /// Decode an aggregate proof's public values
+    pub fn aggregate(public_values: &[u8]) -> Result<Self> {
+        let values = <AggregatedTaxPublicValues as alloy_sol_types::SolType>::abi_decode(public_values)?;
+        Ok(Self::Aggregate {
+            tax_program_vkey: hex::encode(values.taxProgramVkey),
+            parts_digest: hex::encode(values.partsDigest),
+            part_count: values.partCount,
+            total_tax_paisa: values.totalTaxPaisa.saturating_to(),
+            professional_income_paisa: values.professionalIncomePaisa.saturating_to(),
+            vda_gains_paisa: values.vdaGainsPaisa.saturating_to(),
+            vda_tax_paisa: values.vdaTaxPaisa.saturating_to(),
+            cess_paisa: values.cessPaisa.saturating_to(),
+            all_wallets_owned: values.allWalletsOwned,
+        })
+    }
+
+    /// Decode artifacts' public values as the kind of proof they say they're from
+    pub fn of(artifacts: &ProofArtifacts) -> Result<Self> {
+        let public_values = BASE64.decode(&artifacts.public_values)?;
+        if !artifacts.parts.is_empty() {
+            Self::aggregate(&public_values)
+        } else if artifacts.disclosed.is_some() {
+            Self::disclosure(&public_values)
+        } else {
+            Self::tax(&public_values)
+        }
+    }
+
+    /// Commitment of the ledger proved; None for an aggregate
+    pub fn ledger_commitment(&self) -> Option<&str> {
+        match self {
+            Self::Tax { ledger_commitment, .. } | Self::Disclosure { ledger_commitment, .. } => Some(ledger_commitment),
+            Self::Aggregate { .. } => None,
+        }
+    }
+}
+
+impl ProofArtifacts {
+    /// Artifacts of a proof, with the figures of its decoded public values
+    fn new(
+        proof: &SP1ProofWithPublicValues,
+        mode: ProofMode,
+        backend: ProvingBackend,
+        vk_hash: String,
+        decoded: DecodedPublicValues,
+    ) -> Result<Self> {
+        let mut artifacts = Self {
+            proof: encode_proof(proof, mode)?,
+            sp1_proof: encode_sp1_proof(proof, mode)?,
+            mode,
+            backend,
+            public_values: BASE64.encode(proof.public_values.as_slice()),
+            vk_hash,
+            total_tax_paisa: 0,
+            ledger_commitment: String::new(),
+            rate_table_commitment: String::new(),
+            prices_commitment: String::new(),
+            professional_income_paisa: 0,
+            vda_gains_paisa: 0,
+            vda_tax_paisa: 0,
+            cess_paisa: 0,
+            owned_wallets_commitment: String::new(),
+            all_wallets_owned: false,
+            nonce: String::new(),
+            domain_separator: String::new(),
+            parts: Vec::new(),
+            disclosed: None,
+            decoded: None,
+        };
+        match &decoded {
+            DecodedPublicValues::Tax {
+                ledger_commitment,
+                rate_table_commitment,
+                prices_commitment,
+                total_tax_paisa,
+                professional_income_paisa,
+                vda_gains_paisa,
+                vda_tax_paisa,
+                cess_paisa,
+                owned_wallets_commitment,
+                all_wallets_owned,
+                nonce,
+                domain_separator,
+                ..
+            } => {
+                artifacts.ledger_commitment = ledger_commitment.clone();
+                artifacts.rate_table_commitment = rate_table_commitment.clone();
+                artifacts.prices_commitment = prices_commitment.clone();
+                artifacts.total_tax_paisa = *total_tax_paisa;
+                artifacts.professional_income_paisa = *professional_income_paisa;
+                artifacts.vda_gains_paisa = *vda_gains_paisa;
+                artifacts.vda_tax_paisa = *vda_tax_paisa;
+                artifacts.cess_paisa = *cess_paisa;
+                artifacts.owned_wallets_commitment = owned_wallets_commitment.clone();
+                artifacts.all_wallets_owned = *all_wallets_owned;
+                artifacts.nonce = nonce.clone();
+                artifacts.domain_separator = domain_separator.clone();
+            }
+            DecodedPublicValues::Disclosure {
+                ledger_commitment,
+                rate_table_commitment,
+                prices_commitment,
+                disclosed,
+                owned_wallets_commitment,
+                all_wallets_owned,
+                nonce,
+                domain_separator,
+                ..
+            } => {
+                artifacts.ledger_commitment = ledger_commitment.clone();
+                artifacts.rate_table_commitment = rate_table_commitment.clone();
+                artifacts.prices_commitment = prices_commitment.clone();
+                artifacts.disclosed = Some(*disclosed);
+                artifacts.owned_wallets_commitment = owned_wallets_commitment.clone();
+                artifacts.all_wallets_owned = *all_wallets_owned;
+                artifacts.nonce = nonce.clone();
+                artifacts.domain_separator = domain_separator.clone();
+            }
+            DecodedPublicValues::Aggregate {
+                parts_digest,
+                total_tax_paisa,
+                professional_income_paisa,
+                vda_gains_paisa,
+                vda_tax_paisa,
+                cess_paisa,
+                all_wallets_owned,
+                ..
+            } => {
+                artifacts.ledger_commitment = parts_digest.clone();
+                artifacts.total_tax_paisa = *total_tax_paisa;
+                artifacts.professional_income_paisa = *professional_income_paisa;
+                artifacts.vda_gains_paisa = *vda_gains_paisa;
+                artifacts.vda_tax_paisa = *vda_tax_paisa;
+                artifacts.cess_paisa = *cess_paisa;
+                artifacts.all_wallets_owned = *all_wallets_owned;
+            }
+        }
+        artifacts.decoded = Some(decoded);
+        Ok(artifacts)
+    }
+}
+
+/// An on-chain proof as the verifier contracts take it, hex encoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnchainCalldata {
+    /// `programVKey`: the verification key hash of the program proved
+    pub vk_hash: String,
+    /// `publicValues`: the ABI-encoded `TaxProofPublicValues` (or `AggregatedTaxPublicValues`,
+    /// or `DisclosurePublicValues`)
+    pub public_values: String,
+    /// `proofBytes`: the Groth16 or PLONK proof, led by the selector of the verifier it's for
+    pub proof_bytes: String,
+    /// Calldata of `ISP1Verifier.verifyProof(programVKey, publicValues, proofBytes)`
+    pub verify_proof_calldata: String,
+    /// Calldata of `TaxVerifier.verifyTaxProof(proofBytes, publicValues)`, or of
+    /// `verifyAggregateTaxProof` for an aggregate
+    pub verify_tax_proof_calldata: String,
+}
+
+impl ProofArtifacts {
+    /// The proof as `ISP1Verifier` and `TaxVerifier` take it; an error for the kinds of
+    /// proof only verifiable off-chain
+    pub fn to_onchain_calldata(&self) -> Result<OnchainCalldata> {
+        if !self.mode.on_chain() {
+            bail!("{:?} proofs can only be verified off-chain", self.mode);
+        }
+        let proof = BASE64.decode(&self.proof)?;
+        let public_values = BASE64.decode(&self.public_values)?;
+        let vk_hash: B256 = self.vk_hash.parse()?;
+
+        let arguments = (vk_hash, Bytes::copy_from_slice(&public_values), Bytes::copy_from_slice(&proof));
+        let verify_proof = calldata("verifyProof(bytes32,bytes,bytes)", arguments.abi_encode_params());
+        let signature = if self.disclosed.is_some() {
+            "verifyDisclosureProof(bytes,bytes)"
+        } else if !self.parts.is_empty() {
+            "verifyAggregateTaxProof(bytes,bytes)"
+        } else {
+            "verifyTaxProof(bytes,bytes)"
+        };
+        let arguments = (Bytes::copy_from_slice(&proof), Bytes::copy_from_slice(&public_values));
+        let verify_tax_proof = calldata(signature, arguments.abi_encode_params());
+        Ok(OnchainCalldata {
+            vk_hash: vk_hash.to_string(),
+            public_values: format!("0x{}", hex::encode(&public_values)),
+            proof_bytes: format!("0x{}", hex::encode(&proof)),
+            verify_proof_calldata: format!("0x{}", hex::encode(verify_proof)),
+            verify_tax_proof_calldata: format!("0x{}", hex::encode(verify_tax_proof)),
+        })
+    }
+}
+
+/// Local proving resources; what isn't set is left to the SP1 SDK, which sizes shards for
+/// the machine's memory
+///
+/// The SDK reads its settings from the environment when a prover is created, so these
+/// are applied as the variables it reads (`SP1_PROVER`, `SHARD_SIZE`, `SHARD_BATCH_SIZE`,
+/// `RAYON_NUM_THREADS`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProverConfig {
+    /// Prove on an NVIDIA GPU (the SDK's `cuda` prover) rather than the CPU
+    pub cuda: bool,
+    /// Cycles per shard (a power of two); smaller shards take less memory each
+    pub shard_size: Option<usize>,
+    /// Shards proved at a time
+    pub shard_batch_size: Option<usize>,
+    /// Threads proving runs on
+    pub threads: Option<usize>,
+    /// Memory (GB) to size shards for, when `shard_size` isn't set, instead of the machine's
+    pub memory_limit_gb: Option<usize>,
+}
+
+impl ProverConfig {
+    /// The TOML file at `PROVER_CONFIG` if it's set, with `PROVER_CUDA`, `PROVER_SHARD_SIZE`,
+    /// `PROVER_SHARD_BATCH_SIZE`, `PROVER_THREADS` and `PROVER_MEMORY_LIMIT_GB` over it
+    pub fn from_env() -> Result<Self> {
+        let mut config: Self = match std::env::var("PROVER_CONFIG") {
+            Ok(path) => toml::from_str(&std::fs::read_to_string(&path)?)?,
+            Err(_) => Self::default(),
+        };
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        if let Some(cuda) = var("PROVER_CUDA") {
+            config.cuda = cuda.parse()?;
+        }
+        for (name, value) in [
+            ("PROVER_SHARD_SIZE", &mut config.shard_size),
+            ("PROVER_SHARD_BATCH_SIZE", &mut config.shard_batch_size),
+            ("PROVER_THREADS", &mut config.threads),
+            ("PROVER_MEMORY_LIMIT_GB", &mut config.memory_limit_gb),
+        ] {
+            if let Some(number) = var(name) {
+                *value = Some(number.parse()?);
+            }
+        }
+        if config.shard_size.is_some_and(|size| !size.is_power_of_two()) {
+            bail!("The shard size must be a power of two");
+        }
+        Ok(config)
+    }
+
+    /// Shard size and batch size proving uses: as set, else the SDK's choice for
+    /// `memory_limit_gb`; None where the SDK picks for the machine
+    pub fn shard_opts(&self) -> (Option<usize>, Option<usize>) {
+        // The SDK's table of memory to shard sizes (`SP1ProverOpts::cpu`)
+        let for_memory = self.memory_limit_gb.map(|gb| match gb {
+            0..33 => (1 << 19, 1),
+            33..49 => (1 << 20, 1),
+            49..65 => (1 << 21, 1),
+            65..81 => (1 << 21, 3),
+            _ => (1 << 21, 4),
+        });
+        (
+            self.shard_size.or(for_memory.map(|(size, _)| size)),
+            self.shard_batch_size.or(for_memory.map(|(_, batch)| batch)),
+        )
+    }
+
+    /// Set the variables the SDK reads; before any prover is created
+    fn apply(&self) {
+        if self.cuda {
+            std::env::set_var("SP1_PROVER", "cuda");
+        }
+        let (shard_size, shard_batch_size) = self.shard_opts();
+        for (name, value) in [
+            ("SHARD_SIZE", shard_size),
+            ("SHARD_BATCH_SIZE", shard_batch_size),
+            ("RAYON_NUM_THREADS", self.threads),
+        ] {
+            if let Some(value) = value {
+                std::env::set_var(name, value.to_string());
+            }
+        }
+    }
+}
+
+/// What's proving, for operators
+#[derive(Debug, Clone, Serialize)]
+pub struct ProverInfo {
+    /// The prover `SP1_PROVER` picks: "cpu", "cuda", "mock" or "network"
+    pub sp1_prover: String,
+    /// Backend used when a request doesn't pick one
+    pub default_backend: ProvingBackend,
+    /// Whether the prover network is configured
+    pub network: bool,
+    /// Version of the tax program loaded (`TAX_PROGRAM_VERSION`)
+    pub program_version: u32,
+    /// Verification key hash of the tax program
+    pub vk_hash: String,
+    /// Its digest as the aggregation program commits it
+    pub vk_digest: String,
+    pub config: ProverConfig,
+    /// Shard size and batch size in use; None where the SDK picks for the machine
+    pub shard_size: Option<usize>,
+    pub shard_batch_size: Option<usize>,
+}
+
+/// Selector of a function signature followed by its ABI-encoded arguments
+fn calldata(signature: &str, arguments: Vec<u8>) -> Vec<u8> {
+    let mut data = keccak256(signature)[..4].to_vec();
+    data.extend(arguments);
+    data
 }
 
 /// Prover service that caches proving/verification keys
 pub struct TaxProver {
     client: EnvProver,
+    /// Prover network client and request timeout, when configured
+    network: Option<(NetworkProver, Duration)>,
     pk: SP1ProvingKey,
     vk: SP1VerifyingKey,
+    /// Keys of the aggregation program, set up the first time a ledger is proved in parts
+    aggregate_keys: OnceLock<(SP1ProvingKey, SP1VerifyingKey)>,
+    config: ProverConfig,
+    /// Proofs of parts proved before, reused when a ledger is proved in parts again
+    part_cache: Option<PartCache>,
+    /// Released versions of the tax program, and the one loaded
+    registry: VkRegistry,
 }
 
 impl TaxProver {
     /// Create a new prover instance with cached keys
     pub fn new() -> Result<Self> {
+        Self::with_config(ProverConfig::default())
+    }
+
+    /// Create a new prover instance with cached keys, proving with the given resources
+    pub fn with_config(config: ProverConfig) -> Result<Self> {
+        config.apply();
         let client = ProverClient::from_env();
 
         // Setup proving and verification keys once at initialization
@@ -43,75 +672,282 @@ impl TaxProver {
         let (pk, vk) = client.setup(TAX_ZK_ELF);
         tracing::info!("Keys setup complete");
 
-        Ok(Self { client, pk, vk })
+        let mut prover = Self {
+            client,
+            network: None,
+            pk,
+            vk,
+            aggregate_keys: OnceLock::new(),
+            config,
+            part_cache: None,
+            registry: VkRegistry::default(),
+        };
+        let current = ProgramVersion {
+            version: TAX_PROGRAM_VERSION,
+            vk_hash: prover.get_vk_hash(),
+            vk_digest: prover.get_vk_digest(),
+            elf_digest: keccak256(TAX_ZK_ELF).to_string(),
+            domain_separator: keccak256(domain_separator_bytes()).to_string(),
+            accepted: true,
+        };
+        prover.registry = VkRegistry::bundled().with_current(current);
+        Ok(prover)
     }
 
-    /// Execute the program without generating a proof (for testing)
-    pub fn execute(&self, input: &financoor_core::TaxInput) -> Result<Vec<u8>> {
-        let mut stdin = SP1Stdin::new();
-        stdin.write(&input);
+    /// Keep part proofs in a cache, and reuse the ones it has
+    pub fn with_part_cache(mut self, cache: PartCache) -> Self {
+        self.part_cache = Some(cache);
+        self
+    }
+
+    /// Prove on the Succinct prover network when a request asks for it (or by default)
+    pub fn with_network(mut self, config: NetworkConfig) -> Self {
+        let mut builder = ProverClient::builder().network().private_key(&config.private_key);
+        if let Some(rpc_url) = &config.rpc_url {
+            builder = builder.rpc_url(rpc_url);
+        }
+        self.network = Some((builder.build(), config.timeout));
+        self
+    }
+
+    /// Backend used when a request doesn't pick one: the network if it's configured
+    pub fn default_backend(&self) -> ProvingBackend {
+        if self.network.is_some() {
+            ProvingBackend::Network
+        } else {
+            ProvingBackend::Local
+        }
+    }
 
-        let (output, report) = self.client.execute(TAX_ZK_ELF, &stdin).run()?;
+    /// Execute the program without generating a proof: what it would commit, and how
+    /// long it runs
+    pub fn execute(&self, input: &TaxInput) -> Result<Execution> {
+        let (output, report) = self.client.execute(TAX_ZK_ELF, &tax_stdin(input, None)).run()?;
 
         tracing::info!(
             "Execution complete. Cycles: {}",
             report.total_instruction_count()
         );
 
-        Ok(output.as_slice().to_vec())
+        Ok(Execution {
+            public_values: output.as_slice().to_vec(),
+            cycles: report.total_instruction_count(),
+        })
     }
 
-    /// Generate a proof for the given tax input
-    pub fn prove(&self, input: &financoor_core::TaxInput) -> Result<ProofArtifacts> {
-        let mut stdin = SP1Stdin::new();
-        stdin.write(&input);
-
-        tracing::info!("Generating Groth16 proof for on-chain verification...");
+    /// Prove a program's execution on the given backend, falling back to local proving
+    fn prove_with(
+        &self,
+        pk: &SP1ProvingKey,
+        stdin: &SP1Stdin,
+        mode: ProofMode,
+        backend: ProvingBackend,
+    ) -> Result<(SP1ProofWithPublicValues, ProvingBackend)> {
+        let network = self.network.as_ref().filter(|_| backend == ProvingBackend::Network);
+        let network_proof = network.and_then(|(network, timeout)| {
+            tracing::info!("Requesting {:?} proof from the prover network...", mode);
+            network
+                .prove(pk, stdin)
+                .mode(mode.sp1())
+                .timeout(*timeout)
+                .run()
+                .inspect_err(|e| tracing::warn!("Prover network failed, proving locally: {}", e))
+                .ok()
+        });
+        match network_proof {
+            Some(proof) => Ok((proof, ProvingBackend::Network)),
+            None => {
+                tracing::info!("Generating {:?} proof locally...", mode);
+                Ok((self.client.prove(pk, stdin).mode(mode.sp1()).run()?, ProvingBackend::Local))
+            }
+        }
+    }
 
-        // Generate a Groth16 proof using cached keys
-        let proof: SP1ProofWithPublicValues = self
-            .client
-            .prove(&self.pk, &stdin)
-            .groth16()
-            .run()?;
+    /// Generate a proof of the given kind for the given tax input, committing only what
+    /// `disclosure` states when there is one
+    ///
+    /// A network proof that fails (or times out) is generated locally instead; a network
+    /// request without a configured network is proved locally too.
+    pub fn prove(
+        &self,
+        input: &TaxInput,
+        disclosure: Option<&Disclosure>,
+        mode: ProofMode,
+        backend: ProvingBackend,
+    ) -> Result<ProofArtifacts> {
+        // Generate the proof using cached keys
+        let (proof, backend) = self.prove_with(&self.pk, &tax_stdin(input, disclosure), mode, backend)?;
 
         tracing::info!("Proof generated successfully");
 
-        // Extract public values
-        let public_values_bytes = proof.public_values.as_slice();
-
-        // Parse the ABI-encoded public values to extract tax amount and commitment
-        // Format: bytes32 ledgerCommitment, uint256 totalTaxPaisa, uint8 userType, bool used44ada
-        let ledger_commitment = if public_values_bytes.len() >= 32 {
-            hex::encode(&public_values_bytes[0..32])
-        } else {
-            String::new()
+        let public_values = proof.public_values.as_slice();
+        let decoded = match disclosure {
+            Some(_) => DecodedPublicValues::disclosure(public_values)?,
+            None => DecodedPublicValues::tax(public_values)?,
         };
+        ProofArtifacts::new(&proof, mode, backend, self.vk.bytes32(), decoded)
+    }
+
+    /// Prove a ledger in parts, and then a proof of the given kind that verifies them all
+    /// and commits their combined figures
+    ///
+    /// Each part is a compressed proof of the tax program; the parts and the aggregate
+    /// are each proved on `backend`, falling back to local proving as `prove` does.
+    pub fn prove_aggregate(
+        &self,
+        parts: &[TaxInput],
+        mode: ProofMode,
+        backend: ProvingBackend,
+    ) -> Result<ProofArtifacts> {
+        if parts.is_empty() {
+            bail!("Nothing to aggregate");
+        }
 
-        let total_tax_paisa = if public_values_bytes.len() >= 64 {
-            // uint256 is 32 bytes, but we only need the last 8 bytes for u64
-            let tax_bytes = &public_values_bytes[32..64];
-            u64::from_be_bytes(tax_bytes[24..32].try_into().unwrap_or([0u8; 8]))
+        let mut stdin = SP1Stdin::new();
+        let mut part_public_values = Vec::with_capacity(parts.len());
+        let mut part_proofs = Vec::with_capacity(parts.len());
+        for (index, part) in parts.iter().enumerate() {
+            let proof = self.prove_part(part, index, parts.len(), backend)?;
+            let SP1Proof::Compressed(reduced) = proof.proof else {
+                bail!("Part {} wasn't proved compressed", index + 1);
+            };
+            part_public_values.push(proof.public_values.to_vec());
+            part_proofs.push(*reduced);
+        }
+        stdin.write(&self.vk.hash_u32());
+        stdin.write(&part_public_values);
+        for proof in part_proofs {
+            stdin.write_proof(proof, self.vk.vk.clone());
+        }
+
+        let (pk, vk) = self.aggregate_keys.get_or_init(|| self.client.setup(TAX_AGGREGATE_ELF));
+        tracing::info!("Aggregating {} parts...", parts.len());
+        let (proof, backend) = self.prove_with(pk, &stdin, mode, backend)?;
+        let decoded = DecodedPublicValues::aggregate(proof.public_values.as_slice())?;
+        let mut artifacts = ProofArtifacts::new(&proof, mode, backend, vk.bytes32(), decoded)?;
+        artifacts.parts = part_public_values.iter().map(|public_values| BASE64.encode(public_values)).collect();
+        Ok(artifacts)
+    }
+
+    /// Compressed proof of one part of an aggregate, from the part cache if it was proved before
+    fn prove_part(
+        &self,
+        part: &TaxInput,
+        index: usize,
+        count: usize,
+        backend: ProvingBackend,
+    ) -> Result<SP1ProofWithPublicValues> {
+        let key = part_key(&self.vk_digest(), part)?;
+        if let Some(proof) = self.part_cache.as_ref().and_then(|cache| cache.get(&key)) {
+            tracing::info!("Part {} of {} is unchanged, reusing its proof", index + 1, count);
+            return Ok(proof);
+        }
+
+        tracing::info!("Proving part {} of {}", index + 1, count);
+        let (proof, _) = self.prove_with(&self.pk, &tax_stdin(part, None), ProofMode::Compressed, backend)?;
+        if let Some(cache) = &self.part_cache {
+            if let Err(e) = cache.put(&key, &proof) {
+                tracing::warn!("Failed to cache the proof of part {}: {}", index + 1, e);
+            }
+        }
+        Ok(proof)
+    }
+
+    /// Verify proof artifacts (e.g. received from someone else) against this prover's keys:
+    /// the proof itself, that it commits the artifacts' public values, and for an aggregate,
+    /// that those are what its parts add up to
+    pub fn verify(&self, artifacts: &ProofArtifacts) -> Result<()> {
+        let encoded = if artifacts.mode.on_chain() { &artifacts.sp1_proof } else { &artifacts.proof };
+        if encoded.is_empty() {
+            bail!("The artifacts carry no SP1 proof to verify off-chain; verify it with TaxVerifier instead");
+        }
+        let proof: SP1ProofWithPublicValues = bincode::deserialize(&BASE64.decode(encoded)?)?;
+        let public_values = BASE64.decode(&artifacts.public_values)?;
+        if proof.public_values.as_slice() != public_values.as_slice() {
+            bail!("The proof commits other public values than the artifacts'");
+        }
+
+        let vk = if artifacts.parts.is_empty() {
+            &self.vk
         } else {
-            0
+            let parts = artifacts.parts.iter().map(|part| BASE64.decode(part)).collect::<Result<Vec<_>, _>>()?;
+            let aggregated = aggregate_public_values(self.vk_digest(), &parts)?;
+            if <AggregatedTaxPublicValues as alloy_sol_types::SolType>::abi_encode(&aggregated) != public_values {
+                bail!("The aggregate's public values aren't what its parts add up to");
+            }
+            &self.aggregate_keys.get_or_init(|| self.client.setup(TAX_AGGREGATE_ELF)).1
         };
+        if artifacts.vk_hash != vk.bytes32() {
+            bail!("Proved with another program (verification key {})", artifacts.vk_hash);
+        }
+        self.client.verify(&proof, vk)?;
+        Ok(())
+    }
 
-        // Get raw proof bytes for on-chain verification
-        let proof_bytes = proof.bytes();
-
-        Ok(ProofArtifacts {
-            proof: BASE64.encode(&proof_bytes),
-            public_values: BASE64.encode(public_values_bytes),
-            vk_hash: self.vk.bytes32(),
-            total_tax_paisa,
-            ledger_commitment,
-        })
+    /// The local prover, backends, keys and resources in use
+    pub fn info(&self) -> ProverInfo {
+        let (shard_size, shard_batch_size) = self.config.shard_opts();
+        ProverInfo {
+            sp1_prover: std::env::var("SP1_PROVER").unwrap_or_else(|_| "cpu".to_string()),
+            default_backend: self.default_backend(),
+            network: self.network.is_some(),
+            program_version: TAX_PROGRAM_VERSION,
+            vk_hash: self.get_vk_hash(),
+            vk_digest: self.get_vk_digest(),
+            config: self.config.clone(),
+            shard_size,
+            shard_batch_size,
+        }
     }
 
     /// Get the verification key hash for the tax program
     pub fn get_vk_hash(&self) -> String {
         self.vk.bytes32()
     }
+
+    /// Every version of the tax program, and whether its proofs are still accepted
+    pub fn registry(&self) -> &VkRegistry {
+        &self.registry
+    }
+
+    /// Verification key digest of the tax program as the aggregation program commits it
+    /// (`taxProgramVkey`), hex encoded
+    pub fn get_vk_digest(&self) -> String {
+        format!("0x{}", hex::encode(self.vk_digest()))
+    }
+
+    /// The tax program's verification key digest words, big-endian
+    fn vk_digest(&self) -> [u8; 32] {
+        let mut digest = [0u8; 32];
+        for (bytes, word) in digest.chunks_mut(4).zip(self.vk.hash_u32()) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+}
+
+/// Input of the tax program: the tax input, and what to disclose in place of the figures
+fn tax_stdin(input: &TaxInput, disclosure: Option<&Disclosure>) -> SP1Stdin {
+    let mut stdin = SP1Stdin::new();
+    stdin.write(input);
+    stdin.write(&disclosure);
+    stdin
+}
+
+/// Proof bytes (base64 encoded): the raw proof for on-chain verification; STARKs are only
+/// verifiable as a whole
+fn encode_proof(proof: &SP1ProofWithPublicValues, mode: ProofMode) -> Result<String> {
+    let proof_bytes = if mode.on_chain() { proof.bytes() } else { bincode::serialize(proof)? };
+    Ok(BASE64.encode(proof_bytes))
+}
+
+/// The whole proof (base64 encoded) alongside an on-chain proof's raw bytes, which
+/// `sp1_sdk` can't verify by themselves
+fn encode_sp1_proof(proof: &SP1ProofWithPublicValues, mode: ProofMode) -> Result<String> {
+    if !mode.on_chain() {
+        return Ok(String::new());
+    }
+    Ok(BASE64.encode(bincode::serialize(proof)?))
 }
 
 impl Default for TaxProver {
@@ -124,10 +960,234 @@ impl Default for TaxProver {
 mod tests {
     use super::*;
 
+    use financoor_core::{
+        calculate_tax, compute_ledger_commitment, value_ledger, Category, CorporateRegime, Deductions, Direction,
+        LedgerRow, LossCarryForward, PriceEntry, ResidentialStatus, TaxInput, TaxProofPublicValues, TaxRegime,
+        UserType, DEFAULT_ASSESSMENT_YEAR,
+    };
+
     #[test]
     fn test_prover_creation() {
         // Just test that we can create a prover (ELF loading works)
         // Actual proving requires more setup
         let _prover = TaxProver::new().unwrap();
     }
+
+    #[test]
+    fn test_decoded_public_values() {
+        let values = TaxProofPublicValues {
+            ledgerCommitment: B256::repeat_byte(1),
+            totalTaxPaisa: alloy_primitives::U256::from(125_000u64),
+            userType: 1,
+            used44ada: false,
+            assessmentYear: 2026,
+            fyStart: 0,
+            fyEnd: 1,
+            rateTableCommitment: B256::repeat_byte(2),
+            pricesCommitment: B256::repeat_byte(3),
+            professionalIncomePaisa: alloy_primitives::U256::ZERO,
+            vdaGainsPaisa: alloy_primitives::U256::ZERO,
+            vdaTaxPaisa: alloy_primitives::U256::ZERO,
+            cessPaisa: alloy_primitives::U256::from(5_000u64),
+            ownedWalletsCommitment: B256::repeat_byte(4),
+            allWalletsOwned: true,
+            nonce: B256::repeat_byte(5),
+            domainSeparator: B256::repeat_byte(6),
+        };
+        let public_values = <TaxProofPublicValues as alloy_sol_types::SolType>::abi_encode(&values);
+        let DecodedPublicValues::Tax {
+            ledger_commitment,
+            user_type,
+            total_tax_paisa,
+            cess_paisa,
+            nonce,
+            ..
+        } = DecodedPublicValues::tax(&public_values).unwrap()
+        else {
+            panic!("not a tax proof's values");
+        };
+        assert_eq!(ledger_commitment, "01".repeat(32));
+        assert_eq!(user_type, Some(UserType::Huf));
+        assert_eq!((total_tax_paisa, cess_paisa), (125_000, 5_000));
+        assert_eq!(nonce, "05".repeat(32));
+        assert!(DecodedPublicValues::tax(&public_values[..64]).is_err());
+    }
+
+    #[test]
+    fn test_prover_config_shard_opts() {
+        assert_eq!(ProverConfig::default().shard_opts(), (None, None));
+        let config = ProverConfig {
+            memory_limit_gb: Some(40),
+            ..ProverConfig::default()
+        };
+        assert_eq!(config.shard_opts(), (Some(1 << 20), Some(1)));
+        let config = ProverConfig {
+            shard_size: Some(1 << 18),
+            ..config
+        };
+        assert_eq!(config.shard_opts(), (Some(1 << 18), Some(1)));
+    }
+
+    #[test]
+    fn test_onchain_calldata() {
+        let artifacts = |mode: ProofMode| ProofArtifacts {
+            proof: BASE64.encode([0xaa; 4]),
+            sp1_proof: String::new(),
+            mode,
+            backend: ProvingBackend::Local,
+            public_values: BASE64.encode([0xbb; 32]),
+            vk_hash: format!("0x{}", "11".repeat(32)),
+            total_tax_paisa: 0,
+            ledger_commitment: String::new(),
+            rate_table_commitment: String::new(),
+            prices_commitment: String::new(),
+            professional_income_paisa: 0,
+            vda_gains_paisa: 0,
+            vda_tax_paisa: 0,
+            cess_paisa: 0,
+            owned_wallets_commitment: String::new(),
+            all_wallets_owned: false,
+            nonce: String::new(),
+            domain_separator: String::new(),
+            parts: Vec::new(),
+            disclosed: None,
+            decoded: None,
+        };
+        assert!(artifacts(ProofMode::Compressed).to_onchain_calldata().is_err());
+
+        let calldata = artifacts(ProofMode::Groth16).to_onchain_calldata().unwrap();
+        assert_eq!(calldata.proof_bytes, "0xaaaaaaaa");
+        assert_eq!(calldata.public_values, format!("0x{}", "bb".repeat(32)));
+        // Selector, vk hash, two offsets, then the length-prefixed public values and proof
+        let verify_proof = hex::decode(&calldata.verify_proof_calldata[2..]).unwrap();
+        assert_eq!(verify_proof[..4], keccak256("verifyProof(bytes32,bytes,bytes)")[..4]);
+        assert_eq!(verify_proof[4..36], [0x11; 32]);
+        assert_eq!(verify_proof.len(), 4 + 32 * 3 + 32 * 2 + 32 * 2);
+        assert_eq!(verify_proof[4 + 32 * 6..4 + 32 * 6 + 4], [0xaa; 4]);
+    }
+
+    fn row(asset: &str, amount: &str, category: Category) -> LedgerRow {
+        LedgerRow {
+            chain_id: 11155111,
+            owner_wallet: "0xabc".into(),
+            tx_hash: "0x123".to_string(),
+            block_time: 1750000000,
+            asset: asset.to_string(),
+            amount: amount.to_string(),
+            decimals: 18,
+            direction: Direction::In,
+            counterparty: Some("0xdef".into()),
+            category,
+            confidence: 1.0,
+            user_override: false,
+            token_id: None,
+            quantity: None,
+            contract_address: None,
+            counterparty_label: None,
+            counterparty_ens: None,
+            model_confidence: None,
+            inr_value: None,
+        }
+    }
+
+    fn input(user_type: UserType, ledger: Vec<LedgerRow>) -> TaxInput {
+        TaxInput {
+            user_type,
+            wallets: vec![],
+            ledger,
+            prices: vec![PriceEntry {
+                asset: "ETH".to_string(),
+                usd_price: "2345.67".to_string(),
+                date: None,
+                user_override: false,
+            }],
+            usd_inr_rate: "83.45".to_string(),
+            use_44ada: false,
+            digital_receipts_95pct: true,
+            regime: TaxRegime::New,
+            deductions: Deductions::default(),
+            assessment_year: DEFAULT_ASSESSMENT_YEAR,
+            acquisition_lots: vec![],
+            corporate_regime: CorporateRegime::default(),
+            capital_assets: vec![],
+            manual_income: vec![],
+            tds_entries: vec![],
+            residential_status: ResidentialStatus::default(),
+            foreign_source_tx_hashes: vec![],
+            filing_date: None,
+            member_transferred_wallets: vec![],
+            gst: None,
+            brought_forward_losses: LossCarryForward::default(),
+            reference_rates: vec![],
+            nonce: [0; 32],
+        }
+    }
+
+    /// The `/tax` preview (core) and the proved amount (zkVM) must agree to the paisa
+    #[test]
+    fn test_core_matches_zkvm_execution() {
+        let prover = TaxProver::new().unwrap();
+
+        let mut with_44ada = input(UserType::Individual, vec![row("ETH", "9.87", Category::Income)]);
+        with_44ada.use_44ada = true;
+
+        let mut old_regime = input(UserType::Huf, vec![row("ETH", "7.5", Category::Income)]);
+        old_regime.regime = TaxRegime::Old;
+        old_regime.deductions = Deductions {
+            section_80c: "160000".to_string(),
+            section_80d: "12345.67".to_string(),
+            section_80g: "50000".to_string(),
+            ..Deductions::default()
+        };
+
+        let cases = vec![
+            input(
+                UserType::Individual,
+                vec![row("ETH", "1.5", Category::Income), row("ETH", "0.5", Category::Gains)],
+            ),
+            with_44ada,
+            old_regime,
+            // Above ₹50L: surcharge with marginal relief
+            input(UserType::Individual, vec![row("ETH", "26.01", Category::Income)]),
+            input(UserType::Corporate, vec![row("ETH", "12.34", Category::Income)]),
+        ];
+
+        for case in cases {
+            let breakdown = calculate_tax(&case).unwrap();
+            let expected = breakdown.total_tax_paisa;
+            let public_values = prover.execute(&case).unwrap().public_values;
+            let decoded = TaxProofPublicValues::abi_decode(&public_values).unwrap();
+
+            assert_eq!(decoded.totalTaxPaisa, alloy_sol_types::private::U256::from(expected));
+            assert_eq!(decoded.ledgerCommitment.0, compute_ledger_commitment(&case));
+            assert_eq!(decoded.vdaTaxPaisa, alloy_sol_types::private::U256::from(breakdown.vda_tax_paisa));
+            assert_eq!(decoded.cessPaisa, alloy_sol_types::private::U256::from(breakdown.cess_paisa));
+        }
+    }
+    /// The flat USD/INR rate, used before the reference rates start, is committed with them
+    #[test]
+    fn test_rate_table_commitment_covers_flat_rate() {
+        let prover = TaxProver::new().unwrap();
+        let commitment = |usd_inr_rate: &str| {
+            let mut case = input(UserType::Individual, vec![row("ETH", "1.5", Category::Income)]);
+            case.usd_inr_rate = usd_inr_rate.to_string();
+            let public_values = prover.execute(&case).unwrap().public_values;
+            TaxProofPublicValues::abi_decode(&public_values).unwrap().rateTableCommitment
+        };
+
+        assert_ne!(commitment("83.45"), commitment("95"));
+    }
+
+    /// A row valued at other than the committed prices and rates must not prove
+    #[test]
+    fn test_zkvm_rejects_tampered_row_value() {
+        let prover = TaxProver::new().unwrap();
+
+        let mut case = input(UserType::Individual, vec![row("ETH", "1.5", Category::Income)]);
+        value_ledger(&mut case.ledger, &case.prices, &case.usd_inr_rate, &case.reference_rates);
+        assert!(prover.execute(&case).is_ok());
+
+        case.ledger[0].inr_value = case.ledger[0].inr_value.map(|value| value / 2);
+        assert!(prover.execute(&case).is_err());
+    }
 }
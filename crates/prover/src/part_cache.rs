@@ -0,0 +1,92 @@
+//! On-disk cache of part proofs
+//!
+//! An aggregate proves every part of a ledger (compressed) before aggregating them. Editing
+//! a row only changes the part it falls in, so part proofs are kept under a hash of what
+//! was proved, the tax program's verification key and the part's input, and proving the
+//! ledger again only proves the parts that changed.
+//!
+//! Parts are wallet groups or financial years, whose tax stands alone. Arbitrary chunks of
+//! rows wouldn't do: their tax depends on the rows around them (slab rates, loss set-off,
+//! the lots sales are matched to).
+
+use std::path::PathBuf;
+
+use alloy_primitives::keccak256;
+use anyhow::Result;
+use financoor_core::TaxInput;
+use sp1_sdk::SP1ProofWithPublicValues;
+
+/// Where part proofs are kept unless `PART_PROOF_CACHE_DIR` says otherwise
+pub const DEFAULT_PART_PROOF_CACHE_DIR: &str = "part_proofs";
+
+pub struct PartCache {
+    dir: PathBuf,
+}
+
+/// Key of a part's proof: keccak256 of the tax program's vkey digest and the part's input
+pub fn part_key(vk_digest: &[u8; 32], part: &TaxInput) -> Result<String> {
+    let mut bytes = vk_digest.to_vec();
+    bytes.extend(bincode::serialize(part)?);
+    Ok(hex::encode(keccak256(bytes)))
+}
+
+impl PartCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", key))
+    }
+
+    /// Proof of a part proved before; None if it never was, or its file can't be read
+    pub fn get(&self, key: &str) -> Option<SP1ProofWithPublicValues> {
+        let path = self.path(key);
+        let contents = std::fs::read(&path).ok()?;
+        match bincode::deserialize(&contents) {
+            Ok(proof) => Some(proof),
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable part proof {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Keep a part's proof
+    pub fn put(&self, key: &str, proof: &SP1ProofWithPublicValues) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        // Write then rename, so a crash mid-write doesn't leave a truncated proof behind
+        let path = self.path(key);
+        let partial = path.with_extension("bin.partial");
+        std::fs::write(&partial, bincode::serialize(proof)?)?;
+        std::fs::rename(&partial, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_key_follows_input_and_program() {
+        let part: TaxInput = serde_json::from_value(serde_json::json!({
+            "user_type": "individual",
+            "wallets": [],
+            "ledger": [],
+            "prices": [],
+            "usd_inr_rate": "83",
+            "use_44ada": false,
+        }))
+        .unwrap();
+        let key = part_key(&[1; 32], &part).unwrap();
+        assert_eq!(part_key(&[1; 32], &part).unwrap(), key);
+        assert_ne!(part_key(&[2; 32], &part).unwrap(), key);
+
+        let changed = TaxInput {
+            use_44ada: true,
+            ..part
+        };
+        assert_ne!(part_key(&[1; 32], &changed).unwrap(), key);
+    }
+}
@@ -0,0 +1,137 @@
+//! Versions of the tax program
+//!
+//! Every change to what the tax program proves bumps `TAX_PROGRAM_VERSION`, which its
+//! proofs commit through their domain separator; an aggregate commits the verification key
+//! digest of the tax program its parts were proved with. The registry maps each version to
+//! its keys and ELF digest, so that a verifier can tell which version made a proof, and
+//! whether proofs of that version are still accepted.
+//!
+//! Versions released before the current one are bundled (`vk_registry.json`, recorded when
+//! the version after them is released); the current one is the program this prover loaded.
+
+use serde::{Deserialize, Serialize};
+
+use crate::DecodedPublicValues;
+
+const BUNDLED_VERSIONS: &str = include_str!("../vk_registry.json");
+
+/// A released version of the tax program, hashes hex encoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramVersion {
+    pub version: u32,
+    /// Verification key hash (`programVKey`)
+    pub vk_hash: String,
+    /// Verification key digest, as aggregates commit it (`taxProgramVkey`)
+    pub vk_digest: String,
+    /// Keccak256 hash of the program's ELF
+    pub elf_digest: String,
+    /// `domainSeparator` its proofs commit
+    pub domain_separator: String,
+    /// Whether its proofs are still accepted
+    pub accepted: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VkRegistry {
+    /// Oldest first
+    versions: Vec<ProgramVersion>,
+}
+
+/// Whether two hex strings are the same bytes, with or without 0x
+fn same_hex(a: &str, b: &str) -> bool {
+    a.trim_start_matches("0x").eq_ignore_ascii_case(b.trim_start_matches("0x"))
+}
+
+impl VkRegistry {
+    /// Versions released before the current one
+    pub fn bundled() -> Self {
+        serde_json::from_str(BUNDLED_VERSIONS).expect("bundled program versions are valid")
+    }
+
+    /// Add the version this prover proves with, in place of any entry for it
+    pub fn with_current(mut self, current: ProgramVersion) -> Self {
+        let released = self.versions.iter().find(|version| version.version == current.version);
+        if let Some(released) = released.filter(|released| !same_hex(&released.vk_hash, &current.vk_hash)) {
+            tracing::warn!(
+                "Tax program v{} changed since its release (verification key {}, was {}): bump its version",
+                current.version,
+                current.vk_hash,
+                released.vk_hash
+            );
+        }
+        self.versions.retain(|version| version.version != current.version);
+        self.versions.push(current);
+        self.versions.sort_by_key(|version| version.version);
+        self
+    }
+
+    /// Every version, oldest first
+    pub fn versions(&self) -> &[ProgramVersion] {
+        &self.versions
+    }
+
+    /// Version of the tax program with this verification key hash
+    pub fn by_vk_hash(&self, vk_hash: &str) -> Option<&ProgramVersion> {
+        self.versions.iter().find(|version| same_hex(&version.vk_hash, vk_hash))
+    }
+
+    /// Version of the tax program a proof's public values are from: named by the domain
+    /// separator, or for an aggregate, by the verification key its parts were proved with
+    pub fn of(&self, decoded: &DecodedPublicValues) -> Option<&ProgramVersion> {
+        match decoded {
+            DecodedPublicValues::Tax { domain_separator, .. }
+            | DecodedPublicValues::Disclosure { domain_separator, .. } => self
+                .versions
+                .iter()
+                .find(|version| same_hex(&version.domain_separator, domain_separator)),
+            DecodedPublicValues::Aggregate { tax_program_vkey, .. } => {
+                self.versions.iter().find(|version| same_hex(&version.vk_digest, tax_program_vkey))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: u32, vk_hash: &str, accepted: bool) -> ProgramVersion {
+        ProgramVersion {
+            version,
+            vk_hash: vk_hash.to_string(),
+            vk_digest: format!("0x{}", vk_hash.trim_start_matches("0x").repeat(2)),
+            elf_digest: "0x00".to_string(),
+            domain_separator: format!("0x{:02x}", version),
+            accepted,
+        }
+    }
+
+    #[test]
+    fn test_registry_finds_versions() {
+        let registry = VkRegistry {
+            versions: vec![version(1, "0x11", false), version(2, "0x22", true)],
+        };
+        let registry = registry.with_current(version(3, "0x33", true));
+        assert_eq!(registry.versions().iter().map(|version| version.version).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(!registry.by_vk_hash("11").unwrap().accepted);
+
+        let aggregate = DecodedPublicValues::Aggregate {
+            tax_program_vkey: "2222".to_string(),
+            parts_digest: String::new(),
+            part_count: 1,
+            total_tax_paisa: 0,
+            professional_income_paisa: 0,
+            vda_gains_paisa: 0,
+            vda_tax_paisa: 0,
+            cess_paisa: 0,
+            all_wallets_owned: true,
+        };
+        assert_eq!(registry.of(&aggregate).unwrap().version, 2);
+
+        // The current version replaces a released entry for it
+        let registry = registry.with_current(version(3, "0x44", true));
+        assert_eq!(registry.versions().len(), 3);
+        assert_eq!(registry.by_vk_hash("0x44").unwrap().version, 3);
+        assert!(VkRegistry::bundled().versions().iter().all(|version| version.version > 0));
+    }
+}
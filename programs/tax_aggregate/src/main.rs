@@ -0,0 +1,35 @@
+//! Financoor Tax Aggregation Program
+//!
+//! This SP1 program verifies compressed proofs of the tax program, one per part of a
+//! ledger (a wallet group or a financial year), and commits their combined figures, so
+//! that a large ledger is still a single proof on-chain. The parts' public values are
+//! read as they were committed: each is checked against its proof, then summed.
+
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use alloy_sol_types::SolType;
+use financoor_core::{aggregate_public_values, AggregatedTaxPublicValues};
+use sha2::{Digest, Sha256};
+
+pub fn main() {
+    // Verification key digest of the tax program, and each part's public values
+    let tax_program_vkey: [u32; 8] = sp1_zkvm::io::read();
+    let parts: Vec<Vec<u8>> = sp1_zkvm::io::read();
+
+    // Panics (and so can't be proved) unless the next proof is of the tax program with
+    // exactly these public values
+    for part in &parts {
+        let digest: [u8; 32] = Sha256::digest(part).into();
+        sp1_zkvm::lib::verify::verify_sp1_proof(&tax_program_vkey, &digest);
+    }
+
+    let mut vkey = [0u8; 32];
+    for (bytes, word) in vkey.chunks_mut(4).zip(tax_program_vkey) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    let public_values = aggregate_public_values(vkey, &parts).expect("Invalid public values of a part");
+
+    let encoded = AggregatedTaxPublicValues::abi_encode(&public_values);
+    sp1_zkvm::io::commit_slice(&encoded);
+}
@@ -0,0 +1,114 @@
+//! secp256k1 public key recovery on SP1's precompiles
+//!
+//! Point decompression, addition and doubling run as secp256k1 syscalls, and the scalar
+//! arithmetic mod the group order as uint256 multiplications. Numbers are eight
+//! little-endian u32 words, as the syscalls take them.
+
+use sp1_zkvm::lib::secp256k1::Secp256k1Point;
+use sp1_zkvm::lib::utils::AffinePoint;
+use sp1_zkvm::syscalls::{sys_bigint, syscall_secp256k1_decompress};
+
+type Words = [u32; 8];
+
+/// Point bytes, four-byte aligned as the decompression syscall needs them
+#[repr(align(4))]
+struct PointBytes([u8; 64]);
+
+/// Order of the secp256k1 group
+const N: Words = [
+    0xD036_4141, 0xBFD2_5E8C, 0xAF48_A03B, 0xBAAE_DCE6, 0xFFFF_FFFE, 0xFFFF_FFFF, 0xFFFF_FFFF, 0xFFFF_FFFF,
+];
+
+fn words(be_bytes: &[u8]) -> Words {
+    let mut words = [0u32; 8];
+    for (word, bytes) in words.iter_mut().zip(be_bytes.rchunks(4)) {
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+    words
+}
+
+fn is_zero(x: &Words) -> bool {
+    x.iter().all(|word| *word == 0)
+}
+
+fn less_than(x: &Words, y: &Words) -> bool {
+    x.iter().rev().cmp(y.iter().rev()).is_lt()
+}
+
+fn mul_mod_n(x: &Words, y: &Words) -> Words {
+    let mut result = [0u32; 8];
+    sys_bigint(&mut result, 0, x, y, &N);
+    result
+}
+
+/// `x⁻¹ mod n`, as `x^(n-2)` (n is prime)
+fn invert_mod_n(x: &Words) -> Words {
+    let mut exponent = N;
+    exponent[0] -= 2;
+    let mut result = [1, 0, 0, 0, 0, 0, 0, 0];
+    for bit in (0..256).rev() {
+        result = mul_mod_n(&result, &result);
+        if (exponent[bit / 32] >> (bit % 32)) & 1 == 1 {
+            result = mul_mod_n(&result, x);
+        }
+    }
+    result
+}
+
+/// `n - x` for `0 < x < n`
+fn negate_mod_n(x: &Words) -> Words {
+    let mut result = [0u32; 8];
+    let mut borrow = 0u64;
+    for ((result, n), x) in result.iter_mut().zip(N).zip(x) {
+        let difference = (n as u64).wrapping_sub(*x as u64).wrapping_sub(borrow);
+        *result = difference as u32;
+        borrow = (difference >> 63) & 1;
+    }
+    result
+}
+
+fn bits(x: &Words) -> [bool; 256] {
+    core::array::from_fn(|bit| (x[bit / 32] >> (bit % 32)) & 1 == 1)
+}
+
+/// Public key (big-endian x ‖ y) that signed a message hash with an `r ‖ s ‖ v`
+/// signature; None if the signature is malformed
+pub fn recover_public_key(hash: &[u8; 32], signature: &[u8; 65]) -> Option<[u8; 64]> {
+    let (r_bytes, s_bytes) = (&signature[..32], &signature[32..64]);
+    let recovery_id = match signature[64] {
+        v @ (0 | 1) => v,
+        v @ (27 | 28) => v - 27,
+        _ => return None,
+    };
+    let (r, s) = (words(r_bytes), words(s_bytes));
+    if is_zero(&r) || is_zero(&s) || !less_than(&r, &N) || !less_than(&s, &N) {
+        return None;
+    }
+
+    // R, the point whose x coordinate is r, on the side the recovery id picks
+    let mut point = PointBytes([0u8; 64]);
+    point.0[..32].copy_from_slice(r_bytes);
+    syscall_secp256k1_decompress(&mut point.0, recovery_id == 1);
+    let (mut x, mut y) = (point.0[..32].to_vec(), point.0[32..].to_vec());
+    x.reverse();
+    y.reverse();
+    let big_r = <Secp256k1Point as AffinePoint<16>>::from(&x, &y);
+
+    // Q = r⁻¹ (s R - z G) = (-z r⁻¹) G + (s r⁻¹) R
+    let r_inverse = invert_mod_n(&r);
+    let z_r = mul_mod_n(&words(hash), &r_inverse);
+    let u1 = if is_zero(&z_r) { z_r } else { negate_mod_n(&z_r) };
+    let u2 = mul_mod_n(&s, &r_inverse);
+    let key = Secp256k1Point::multi_scalar_multiplication(&bits(&u1), Secp256k1Point::GENERATOR_T, &bits(&u2), big_r);
+    if key.is_identity() {
+        return None;
+    }
+
+    let le_bytes = key.to_le_bytes();
+    let mut public_key = [0u8; 64];
+    for (half, coordinate) in public_key.chunks_mut(32).zip(le_bytes.chunks(32)) {
+        half.copy_from_slice(coordinate);
+        half.reverse();
+    }
+    Some(public_key)
+}
@@ -1,238 +1,59 @@
 //! Financoor Tax ZK Program
 //!
 //! This SP1 program computes tax over a committed ledger and outputs
-//! public values that can be verified on-chain.
+//! public values that can be verified on-chain. The types and tax math are
+//! `financoor_core`'s own, built without `std`, so the proved tax is exactly
+//! what the API previews.
+//!
+//! Given a `Disclosure`, it commits only that statement about the figures (and
+//! can't be proved unless it holds) instead of the figures themselves.
+//!
+//! Wallets with an ownership signature must have signed it themselves (checked with
+//! the secp256k1 precompile); the wallets proven owned are committed as a hash.
+//!
+//! Rows arrive valued in INR; each value must be the one the committed prices and rates
+//! give it, so those commitments bind the taxable amounts.
+//!
+//! The input's nonce and a domain separator naming the program version are committed
+//! with the figures, so a verifier can tell filings and program versions apart.
 
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use alloy_sol_types::{sol, SolType};
-use serde::{Deserialize, Serialize};
-use sp1_zkvm::syscalls;
-
-// Re-define types here since we can't easily share with core in zkVM
-// (In production, we'd use a no_std compatible shared crate)
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum UserType {
-    Individual,
-    Huf,
-    Corporate,
-}
+mod ecrecover;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum Category {
-    Income,
-    Gains,
-    Losses,
-    Fees,
-    Internal,
-    Unknown,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum Direction {
-    In,
-    Out,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LedgerRow {
-    pub chain_id: u64,
-    pub owner_wallet: String,
-    pub tx_hash: String,
-    pub block_time: u64,
-    pub asset: String,
-    pub amount: String,
-    pub decimals: u8,
-    pub direction: Direction,
-    pub counterparty: Option<String>,
-    pub category: Category,
-    pub confidence: f32,
-    pub user_override: bool,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PriceEntry {
-    pub asset: String,
-    pub usd_price: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Wallet {
-    pub id: String,
-    pub address: String,
-    pub label: Option<String>,
-    pub group_id: Option<String>,
-    pub source: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TaxInput {
-    pub user_type: UserType,
-    pub wallets: Vec<Wallet>,
-    pub ledger: Vec<LedgerRow>,
-    pub prices: Vec<PriceEntry>,
-    pub usd_inr_rate: String,
-    pub use_44ada: bool,
-}
-
-// ABI-encodable output struct
-sol! {
-    struct TaxProofPublicValues {
-        bytes32 ledgerCommitment;
-        uint256 totalTaxPaisa;
-        uint8 userType;
-        bool used44ada;
-    }
-}
-
-// ============================================================================
-// TAX CALCULATION (duplicated from core for zkVM compatibility)
-// ============================================================================
-
-/// New regime tax slabs for AY 2026-27 (Individual/HUF)
-const NEW_REGIME_SLABS: [(u64, u64, u64); 7] = [
-    (0, 400_000, 0),           // Up to 4L: 0%
-    (400_001, 800_000, 5),     // 4L-8L: 5%
-    (800_001, 1_200_000, 10),  // 8L-12L: 10%
-    (1_200_001, 1_600_000, 15), // 12L-16L: 15%
-    (1_600_001, 2_000_000, 20), // 16L-20L: 20%
-    (2_000_001, 2_400_000, 25), // 20L-24L: 25%
-    (2_400_001, u64::MAX, 30),  // Above 24L: 30%
-];
+use alloy_sol_types::SolType;
+use financoor_core::{
+    all_wallets_owned, calculate_tax_with_rules, canonical_price_table, canonical_rate_table,
+    disclosure_public_values, domain_separator_bytes, evm_address_bytes, financial_year_bounds, ledger_commitment_bytes,
+    misvalued_row, owned_wallets_bytes, ownership_message, personal_message, signature_bytes, Disclosure,
+    DisclosurePublicValues, TaxInput, TaxProofPublicValues, TaxRules, UserType,
+};
+use sp1_zkvm::syscalls;
 
-/// Section 87A rebate limit (for Individual/HUF under new regime)
-/// For FY 2025-26 (AY 2026-27): Rebate up to ₹60,000 if taxable income ≤ ₹12 lakh
-const SECTION_87A_INCOME_LIMIT: u64 = 1_200_000; // ₹12 lakh (in INR, not paisa)
-const SECTION_87A_REBATE_MAX: u64 = 60_000; // ₹60,000 (in INR, not paisa)
+/// Keccak256 hash using the SP1 keccak-f[1600] precompile
+fn keccak256_hash(data: &[u8]) -> [u8; 32] {
+    const RATE: usize = 136;
+    let mut state = [0u64; 25];
 
-fn calculate_slab_tax(taxable_income: u64) -> u64 {
-    let mut tax: u64 = 0;
+    // Keccak (not SHA-3) padding: 0x01, zeros, then 0x80 on the last byte of the block
+    let mut padded = data.to_vec();
+    padded.push(0x01);
+    padded.resize(padded.len().div_ceil(RATE) * RATE, 0);
+    *padded.last_mut().unwrap() |= 0x80;
 
-    for (lower, upper, rate) in NEW_REGIME_SLABS.iter() {
-        if taxable_income > *lower {
-            let amount_in_slab = if taxable_income >= *upper {
-                upper - lower
-            } else {
-                taxable_income.saturating_sub(*lower)
-            };
-            tax += (amount_in_slab * rate) / 100;
-        }
-
-        if taxable_income <= *upper {
-            break;
+    for block in padded.chunks(RATE) {
+        for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+            *lane ^= u64::from_le_bytes(bytes.try_into().unwrap());
         }
+        syscalls::syscall_keccak_permute(&mut state);
     }
 
-    tax
-}
-
-fn parse_amount(s: &str) -> u64 {
-    // Parse as float then convert to paisa (x100)
-    let f: f64 = s.parse().unwrap_or(0.0);
-    (f * 100.0) as u64
-}
-
-fn amount_to_inr_paisa(
-    amount: &str,
-    asset: &str,
-    prices: &[PriceEntry],
-    usd_inr_rate: u64, // in paisa per USD
-) -> u64 {
-    let amount_val = parse_amount(amount);
-
-    // Find USD price for this asset (in cents)
-    let usd_price_cents: u64 = prices
-        .iter()
-        .find(|p| p.asset == asset)
-        .map(|p| parse_amount(&p.usd_price))
-        .unwrap_or(100); // Default $1.00
-
-    // amount * usd_price * usd_inr / (100 * 100) to normalize
-    // amount_val is scaled by 100, usd_price_cents by 100, usd_inr_rate by 100
-    // Result should be in paisa, so divide by 100^2 (not 100^3)
-    (amount_val * usd_price_cents * usd_inr_rate) / (100 * 100)
-}
-
-fn calculate_tax(input: &TaxInput) -> u64 {
-    let usd_inr_rate = parse_amount(&input.usd_inr_rate);
-
-    // Sum up amounts by category (all in paisa)
-    let mut professional_income: u64 = 0;
-    let mut vda_gains: u64 = 0;
-
-    for row in &input.ledger {
-        let inr_value = amount_to_inr_paisa(&row.amount, &row.asset, &input.prices, usd_inr_rate);
-
-        match row.category {
-            Category::Income => {
-                if matches!(row.direction, Direction::In) {
-                    professional_income += inr_value;
-                }
-            }
-            Category::Gains => {
-                if matches!(row.direction, Direction::In) {
-                    vda_gains += inr_value;
-                }
-            }
-            // Losses, fees, internal, unknown don't add to taxable in MVP
-            _ => {}
-        }
+    let mut result = [0u8; 32];
+    for (bytes, lane) in result.chunks_mut(8).zip(state.iter()) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
     }
-
-    // Apply 44ADA if enabled (Individual only)
-    let taxable_professional_income = if input.use_44ada && matches!(input.user_type, UserType::Individual) {
-        professional_income / 2 // 50% presumptive
-    } else {
-        professional_income
-    };
-
-    // Calculate professional income tax
-    let (professional_tax_before_rebate, section_87a_rebate) = match input.user_type {
-        UserType::Individual | UserType::Huf => {
-            // taxable_professional_income is in paisa, convert to INR for slab calculation
-            let taxable_inr = taxable_professional_income / 100;
-            let slab_tax_inr = calculate_slab_tax(taxable_inr);
-            let slab_tax_paisa = slab_tax_inr * 100;
-
-            // Apply Section 87A rebate if taxable income ≤ ₹12 lakh
-            let rebate_paisa = if taxable_inr <= SECTION_87A_INCOME_LIMIT {
-                // Rebate is min(tax, ₹60,000) - convert to paisa
-                let max_rebate_paisa = SECTION_87A_REBATE_MAX * 100;
-                if slab_tax_paisa < max_rebate_paisa {
-                    slab_tax_paisa
-                } else {
-                    max_rebate_paisa
-                }
-            } else {
-                0
-            };
-            (slab_tax_paisa, rebate_paisa)
-        }
-        UserType::Corporate => {
-            // 22% + 10% surcharge = 24.2%, no rebate for corporates
-            ((taxable_professional_income * 242) / 1000, 0)
-        }
-    };
-
-    // Professional tax after rebate
-    let professional_tax = professional_tax_before_rebate - section_87a_rebate;
-
-    // VDA tax at 30% (no rebate for VDA income)
-    let vda_tax = (vda_gains * 30) / 100;
-
-    // Total before cess
-    let total_before_cess = professional_tax + vda_tax;
-
-    // Health & Education Cess at 4%
-    let cess = (total_before_cess * 4) / 100;
-
-    // Total tax payable (in paisa)
-    total_before_cess + cess
+    result
 }
 
 /// Simple SHA256 hash using SP1 syscalls
@@ -315,13 +136,39 @@ fn sha256_hash(data: &[u8]) -> [u8; 32] {
 pub fn main() {
     // Read input from the prover
     let input: TaxInput = sp1_zkvm::io::read();
+    let disclosure: Option<Disclosure> = sp1_zkvm::io::read();
+
+    // Commit to the ledger: keccak256 of its canonical encoding, as core's `compute_ledger_commitment`
+    let ledger_commitment = keccak256_hash(&ledger_commitment_bytes(&input));
+    // The USD/INR rates every row was converted at: the reference rates and the flat rate
+    let rate_table_commitment =
+        sha256_hash(canonical_rate_table(&input.usd_inr_rate, &input.reference_rates).as_bytes());
+    // The USD prices rows were valued at
+    let prices_commitment = sha256_hash(canonical_price_table(&input.prices).as_bytes());
+    // Values the host passed in must follow from those prices and rates
+    if let Some(row) = misvalued_row(&input) {
+        panic!("ledger row {} isn't valued at the committed prices and rates", row);
+    }
 
-    // Compute commitment to the ledger (SHA256 hash)
-    let ledger_json = serde_json::to_string(&input.ledger).unwrap();
-    let ledger_commitment = sha256_hash(ledger_json.as_bytes());
+    // Wallets that signed to show they're the user's; a signature from anyone else can't be proved
+    let mut owned = Vec::new();
+    for wallet in &input.wallets {
+        let Some(signature) = &wallet.ownership_signature else {
+            continue;
+        };
+        let address = evm_address_bytes(&wallet.address).expect("only EVM wallets can sign for ownership");
+        let hash = keccak256_hash(&personal_message(&ownership_message(&wallet.address)));
+        let signature = signature_bytes(signature).expect("malformed ownership signature");
+        let public_key = ecrecover::recover_public_key(&hash, &signature).expect("invalid ownership signature");
+        assert!(keccak256_hash(&public_key)[12..] == address, "ownership signature from another wallet");
+        owned.push(address);
+    }
+    let owned_wallets_commitment = keccak256_hash(&owned_wallets_bytes(&owned));
 
-    // Calculate tax using the same logic as the core crate
-    let total_tax_paisa = calculate_tax(&input);
+    // Calculate tax with core's logic and rule tables
+    let rules = TaxRules::for_assessment_year(input.assessment_year).expect("unsupported assessment year");
+    let breakdown = calculate_tax_with_rules(&input, &rules).expect("amounts too large to tax");
+    let (fy_start, fy_end) = financial_year_bounds(rules.assessment_year);
 
     let user_type_code = match input.user_type {
         UserType::Individual => 0u8,
@@ -332,11 +179,32 @@ pub fn main() {
     // Encode public values for on-chain verification
     let public_values = TaxProofPublicValues {
         ledgerCommitment: alloy_sol_types::private::FixedBytes(ledger_commitment),
-        totalTaxPaisa: alloy_sol_types::private::U256::from(total_tax_paisa),
+        totalTaxPaisa: alloy_sol_types::private::U256::from(breakdown.total_tax_paisa),
         userType: user_type_code,
-        used44ada: input.use_44ada,
+        used44ada: breakdown.presumptive_44ada_applied,
+        assessmentYear: rules.assessment_year,
+        fyStart: fy_start,
+        fyEnd: fy_end,
+        rateTableCommitment: alloy_sol_types::private::FixedBytes(rate_table_commitment),
+        pricesCommitment: alloy_sol_types::private::FixedBytes(prices_commitment),
+        professionalIncomePaisa: alloy_sol_types::private::U256::from(breakdown.professional_income_paisa),
+        vdaGainsPaisa: alloy_sol_types::private::U256::from(breakdown.vda_gains_paisa),
+        vdaTaxPaisa: alloy_sol_types::private::U256::from(breakdown.vda_tax_paisa),
+        cessPaisa: alloy_sol_types::private::U256::from(breakdown.cess_paisa),
+        ownedWalletsCommitment: alloy_sol_types::private::FixedBytes(owned_wallets_commitment),
+        allWalletsOwned: all_wallets_owned(&input.ledger, &owned),
+        nonce: alloy_sol_types::private::FixedBytes(input.nonce),
+        domainSeparator: alloy_sol_types::private::FixedBytes(keccak256_hash(&domain_separator_bytes())),
     };
 
-    let encoded = TaxProofPublicValues::abi_encode(&public_values);
+    let encoded = match disclosure {
+        None => TaxProofPublicValues::abi_encode(&public_values),
+        Some(disclosure) => {
+            let range = disclosure
+                .range(breakdown.total_tax_paisa, breakdown.total_income_paisa)
+                .expect("disclosed statement doesn't hold");
+            DisclosurePublicValues::abi_encode(&disclosure_public_values(&public_values, &range))
+        }
+    };
     sp1_zkvm::io::commit_slice(&encoded);
 }